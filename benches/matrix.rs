@@ -0,0 +1,28 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use raytracer::objects::transform::Transformable;
+use raytracer::prelude::*;
+use std::hint::black_box;
+
+// the matrix work done for every ray: composing transforms, inverting them,
+// and moving points through them
+fn matrix(c: &mut Criterion) {
+    let transform = Transform::from(vec![
+        TransformKind::Scale(0.5, 2.0, 1.5),
+        TransformKind::Rotate(Axis::Y, Angle::from_radians(0.7)),
+        TransformKind::Translate(1.0, -2.0, 3.0),
+    ]);
+    let point = Point::new(0.3, -1.7, 2.9);
+
+    let mut group = c.benchmark_group("matrix");
+    group.bench_function("compose", |b| {
+        b.iter(|| black_box(&transform).compose(black_box(&transform)))
+    });
+    group.bench_function("invert", |b| b.iter(|| black_box(&transform).invert()));
+    group.bench_function("transform_point", |b| {
+        b.iter(|| black_box(point).transform(black_box(&transform)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, matrix);
+criterion_main!(benches);
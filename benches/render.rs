@@ -0,0 +1,31 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use raytracer::prelude::*;
+
+// small enough that a run of every scene takes a few minutes
+const SIZE: (usize, usize) = (64, 48);
+
+// building each scene's world, mostly its kd-trees
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+    for scene in BenchmarkScene::ALL {
+        group.bench_function(scene.name(), |b| b.iter(|| scene.world()));
+    }
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let (hsize, vsize) = SIZE;
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    for scene in BenchmarkScene::ALL {
+        let world = scene.world();
+        group.bench_function(scene.name(), |b| {
+            b.iter(|| scene.camera(hsize, vsize).render(&world).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build, render);
+criterion_main!(benches);
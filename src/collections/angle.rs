@@ -1,27 +1,28 @@
-use std::f64::consts::PI as MATH_PI;
+use crate::utils::consts::PI as MATH_PI;
+use crate::utils::Float;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Angle {
-    degrees: Option<f64>,
-    radians: Option<f64>,
+    degrees: Option<Float>,
+    radians: Option<Float>,
 }
 
 impl Angle {
-    pub fn from_degrees(deg: f64) -> Angle {
+    pub fn from_degrees(deg: Float) -> Angle {
         Angle {
             degrees: Some(deg),
             radians: None,
         }
     }
 
-    pub fn from_radians(rad: f64) -> Angle {
+    pub fn from_radians(rad: Float) -> Angle {
         Angle {
             degrees: None,
             radians: Some(rad),
         }
     }
 
-    pub fn degrees(&mut self) -> f64 {
+    pub fn degrees(&mut self) -> Float {
         match self.degrees {
             Some(deg) => deg,
             None => {
@@ -31,7 +32,7 @@ impl Angle {
         }
     }
 
-    pub fn radians(&mut self) -> f64 {
+    pub fn radians(&mut self) -> Float {
         match self.radians {
             Some(rad) => rad,
             None => {
@@ -1,16 +1,162 @@
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::ops::{Add, Mul, Sub};
 
+use crate::utils::Float;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Colour {
-    pub red: f64,
-    pub green: f64,
-    pub blue: f64,
+    pub red: Float,
+    pub green: Float,
+    pub blue: Float,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexColourError {
+    // neither #rgb nor #rrggbb
+    Length(usize),
+    Digit(char),
+}
+
+impl Display for HexColourError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexColourError::Length(length) => {
+                write!(f, "expected 3 or 6 hex digits, found {length}")
+            }
+            HexColourError::Digit(digit) => write!(f, "{digit:?} is not a hex digit"),
+        }
+    }
 }
 
+impl Error for HexColourError {}
+
+// Colours are in linear light, but the familiar notations (hex codes, 8-bit
+// values, and hue wheels in colour pickers) describe sRGB encoded colours, so
+// they are decoded on the way in and encoded on the way out. Hues are in
+// degrees, and saturation, value and lightness from 0 to 1.
 impl Colour {
-    pub fn new(red: f64, green: f64, blue: f64) -> Colour {
+    pub fn new(red: Float, green: Float, blue: Float) -> Colour {
         Colour { red, green, blue }
     }
+
+    pub fn from_rgb8(red: u8, green: u8, blue: u8) -> Colour {
+        let [red, green, blue] = [red, green, blue].map(|value| value as Float / 255.0);
+        Colour::from_srgb(red, green, blue)
+    }
+
+    // "#ffcc00" or "#fc0", with or without the #
+    pub fn from_hex(hex: &str) -> Result<Colour, HexColourError> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some(digit) = hex.chars().find(|digit| !digit.is_ascii_hexdigit()) {
+            return Err(HexColourError::Digit(digit));
+        }
+        let byte = |digits: &str| u8::from_str_radix(digits, 16).unwrap();
+        match hex.len() {
+            3 => {
+                let [red, green, blue] = [0, 1, 2].map(|idx| byte(&hex[idx..idx + 1]) * 17);
+                Ok(Colour::from_rgb8(red, green, blue))
+            }
+            6 => {
+                let [red, green, blue] = [0, 2, 4].map(|idx| byte(&hex[idx..idx + 2]));
+                Ok(Colour::from_rgb8(red, green, blue))
+            }
+            length => Err(HexColourError::Length(length)),
+        }
+    }
+
+    pub fn from_hsv(hue: Float, saturation: Float, value: Float) -> Colour {
+        let chroma = value * saturation;
+        let [red, green, blue] = Colour::hue_to_rgb(hue, chroma);
+        let offset = value - chroma;
+        Colour::from_srgb(red + offset, green + offset, blue + offset)
+    }
+
+    pub fn from_hsl(hue: Float, saturation: Float, lightness: Float) -> Colour {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let [red, green, blue] = Colour::hue_to_rgb(hue, chroma);
+        let offset = lightness - chroma / 2.0;
+        Colour::from_srgb(red + offset, green + offset, blue + offset)
+    }
+
+    // (hue, saturation, value), with a hue of 0 for greys
+    pub fn to_hsv(self) -> (Float, Float, Float) {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, saturation, max)
+    }
+
+    // (hue, saturation, lightness), with a hue of 0 for greys
+    pub fn to_hsl(self) -> (Float, Float, Float) {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if lightness > 0.0 && lightness < 1.0 {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        (hue, saturation, lightness)
+    }
+
+    fn from_srgb(red: Float, green: Float, blue: Float) -> Colour {
+        Colour::new(
+            srgb_to_linear(red),
+            srgb_to_linear(green),
+            srgb_to_linear(blue),
+        )
+    }
+
+    // the red, green and blue of a fully saturated hue with the given chroma,
+    // before the offset that sets value or lightness
+    fn hue_to_rgb(hue: Float, chroma: Float) -> [Float; 3] {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        match sector as u32 {
+            0 => [chroma, second, 0.0],
+            1 => [second, chroma, 0.0],
+            2 => [0.0, chroma, second],
+            3 => [0.0, second, chroma],
+            4 => [second, 0.0, chroma],
+            _ => [chroma, 0.0, second],
+        }
+    }
+
+    // the hue, and largest and smallest components, of the sRGB encoded colour
+    fn hue_max_min(self) -> (Float, Float, Float) {
+        let [red, green, blue] = [self.red, self.green, self.blue].map(linear_to_srgb);
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let chroma = max - min;
+        let sector = if chroma == 0.0 {
+            0.0
+        } else if max == red {
+            ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            (blue - red) / chroma + 2.0
+        } else {
+            (red - green) / chroma + 4.0
+        };
+        (sector * 60.0, max, min)
+    }
+}
+
+// the sRGB transfer functions, for components clamped to 0..=1
+pub fn linear_to_srgb(value: Float) -> Float {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(value: Float) -> Float {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
 }
 
 impl Add<Colour> for Colour {
@@ -37,7 +183,7 @@ impl Sub<Colour> for Colour {
     }
 }
 
-impl Mul<Colour> for f64 {
+impl Mul<Colour> for Float {
     type Output = Colour;
 
     fn mul(self, other: Colour) -> Self::Output {
@@ -49,10 +195,10 @@ impl Mul<Colour> for f64 {
     }
 }
 
-impl Mul<f64> for Colour {
+impl Mul<Float> for Colour {
     type Output = Colour;
 
-    fn mul(self, other: f64) -> Self::Output {
+    fn mul(self, other: Float) -> Self::Output {
         other * self
     }
 }
@@ -72,13 +218,65 @@ impl Mul<Colour> for Colour {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn colours_from_hex_and_8_bit_values() {
+        assert_eq!(Colour::from_hex("#ff0000"), Ok(Colour::new(1.0, 0.0, 0.0)));
+        assert_eq!(Colour::from_hex("FFF"), Ok(Colour::new(1.0, 1.0, 1.0)));
+        assert_eq!(Colour::from_hex("#ffcc00"), Colour::from_hex("#fc0"));
+        assert_eq!(
+            Colour::from_hex("#ffcc00"),
+            Ok(Colour::from_rgb8(255, 204, 0))
+        );
+        // sRGB 50% grey is about 21% in linear light
+        let grey = Colour::from_rgb8(128, 128, 128);
+        assert!((grey.red - 0.2158).abs() < 1e-4);
+
+        assert_eq!(Colour::from_hex("#ffcc0"), Err(HexColourError::Length(5)));
+        assert_eq!(Colour::from_hex("#ffcg00"), Err(HexColourError::Digit('g')));
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip() {
+        let orange = Colour::from_rgb8(255, 128, 0);
+        let (hue, saturation, value) = orange.to_hsv();
+        assert!((hue - 30.1).abs() < 0.1);
+        approx_eq!(saturation, 1.0);
+        approx_eq!(value, 1.0);
+        let (hue, saturation, lightness) = orange.to_hsl();
+        assert!((hue - 30.1).abs() < 0.1);
+        approx_eq!(saturation, 1.0);
+        approx_eq!(lightness, 0.5);
+
+        for (hue, saturation, level) in [(0.0, 0.5, 0.5), (150.0, 0.25, 0.8), (300.0, 1.0, 0.3)] {
+            let from_hsv = Colour::from_hsv(hue, saturation, level).to_hsv();
+            approx_eq!(from_hsv.0, hue);
+            approx_eq!(from_hsv.1, saturation);
+            approx_eq!(from_hsv.2, level);
+            let from_hsl = Colour::from_hsl(hue, saturation, level).to_hsl();
+            approx_eq!(from_hsl.0, hue);
+            approx_eq!(from_hsl.1, saturation);
+            approx_eq!(from_hsl.2, level);
+        }
+
+        assert_eq!(Colour::from_hsv(0.0, 0.0, 1.0), Colour::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            Colour::from_hsl(120.0, 1.0, 0.5),
+            Colour::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(Colour::new(0.5, 0.5, 0.5).to_hsv().0, 0.0);
+    }
 
     #[test]
     fn add_two_colours() {
         let colour1 = Colour::new(0.9, 0.6, 0.7);
         let colour2 = Colour::new(0.7, 0.1, 1.0);
         let resulting_colour = Colour::new(1.6, 0.7, 1.7);
-        assert_eq!(colour1 + colour2, resulting_colour);
+        let sum = colour1 + colour2;
+        approx_eq!(sum.red, resulting_colour.red);
+        approx_eq!(sum.green, resulting_colour.green);
+        approx_eq!(sum.blue, resulting_colour.blue);
     }
 
     #[test]
@@ -86,20 +284,23 @@ mod tests {
         let colour1 = Colour::new(1.0, 0.6, 1.7);
         let colour2 = Colour::new(0.0, 0.1, 1.0);
         let resulting_colour = Colour::new(1.0, 0.5, 0.7);
-        assert_eq!(colour1 - colour2, resulting_colour);
+        let difference = colour1 - colour2;
+        approx_eq!(difference.red, resulting_colour.red);
+        approx_eq!(difference.green, resulting_colour.green);
+        approx_eq!(difference.blue, resulting_colour.blue);
     }
 
     #[test]
     fn mul_colour_by_scalar() {
         let colour = Colour::new(0.2, 0.3, 0.4);
-        let scalar = 2.0_f64;
+        let scalar: Float = 2.0;
         let resulting_colour = Colour::new(0.4, 0.6, 0.8);
         assert_eq!(colour * scalar, resulting_colour);
     }
 
     #[test]
     fn mul_scalar_by_colour() {
-        let scalar = 2.0_f64;
+        let scalar: Float = 2.0;
         let colour = Colour::new(0.2, 0.3, 0.4);
         let resulting_colour = Colour::new(0.4, 0.6, 0.8);
         assert_eq!(scalar * colour, resulting_colour);
@@ -0,0 +1,131 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+#[cfg(feature = "f32")]
+use wide::f32x4 as Simd;
+#[cfg(not(feature = "f32"))]
+use wide::f64x4 as Simd;
+
+use crate::utils::Float;
+
+pub(crate) const LANES: usize = 4;
+
+// Four Floats operated on together. Arithmetic goes through wide's f64x4 (or
+// f32x4), which uses SSE2 or AVX registers where the target has them and
+// plain loops where it does not; std::simd would do the same but is not yet
+// stable. Each lane is computed exactly as the scalar operation would be, so
+// comparisons and min and max, whose SIMD forms treat NaN differently, stay
+// lane by lane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Lanes(pub(crate) [Float; LANES]);
+
+impl Lanes {
+    pub(crate) fn splat(value: Float) -> Lanes {
+        Lanes([value; LANES])
+    }
+
+    pub(crate) fn from_fn(f: impl FnMut(usize) -> Float) -> Lanes {
+        Lanes(std::array::from_fn(f))
+    }
+
+    fn simd(self) -> Simd {
+        Simd::from(self.0)
+    }
+
+    fn from_simd(simd: Simd) -> Lanes {
+        Lanes(simd.to_array())
+    }
+
+    fn zip(self, other: Lanes, f: impl Fn(Float, Float) -> Float) -> Lanes {
+        let [a, b] = [self.0, other.0];
+        Lanes([f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])])
+    }
+
+    // as Float::min and Float::max, lane by lane
+    pub(crate) fn min(self, other: Lanes) -> Lanes {
+        self.zip(other, Float::min)
+    }
+
+    pub(crate) fn max(self, other: Lanes) -> Lanes {
+        self.zip(other, Float::max)
+    }
+
+    pub(crate) fn abs(self) -> Lanes {
+        Lanes::from_simd(self.simd().abs())
+    }
+
+    pub(crate) fn gt(self, other: Lanes) -> [bool; LANES] {
+        std::array::from_fn(|idx| self.0[idx] > other.0[idx])
+    }
+
+    pub(crate) fn ge(self, other: Lanes) -> [bool; LANES] {
+        std::array::from_fn(|idx| self.0[idx] >= other.0[idx])
+    }
+
+    // this lane where mask is set, other's where it is not
+    pub(crate) fn select(mask: [bool; LANES], this: Lanes, other: Lanes) -> Lanes {
+        Lanes(std::array::from_fn(|idx| {
+            if mask[idx] {
+                this.0[idx]
+            } else {
+                other.0[idx]
+            }
+        }))
+    }
+}
+
+impl Add for Lanes {
+    type Output = Lanes;
+
+    fn add(self, other: Lanes) -> Lanes {
+        Lanes::from_simd(self.simd() + other.simd())
+    }
+}
+
+impl Sub for Lanes {
+    type Output = Lanes;
+
+    fn sub(self, other: Lanes) -> Lanes {
+        Lanes::from_simd(self.simd() - other.simd())
+    }
+}
+
+impl Mul for Lanes {
+    type Output = Lanes;
+
+    fn mul(self, other: Lanes) -> Lanes {
+        Lanes::from_simd(self.simd() * other.simd())
+    }
+}
+
+impl Div for Lanes {
+    type Output = Lanes;
+
+    fn div(self, other: Lanes) -> Lanes {
+        Lanes::from_simd(self.simd() / other.simd())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_match_scalar_operations() {
+        let a = Lanes([1.0, -2.0, Float::NAN, 0.5]);
+        let b = Lanes([3.0, 4.0, 1.0, Float::INFINITY]);
+        for idx in 0..LANES {
+            let (x, y) = (a.0[idx], b.0[idx]);
+            assert_eq!((a + b).0[idx].to_bits(), (x + y).to_bits());
+            assert_eq!((a - b).0[idx].to_bits(), (x - y).to_bits());
+            assert_eq!((a * b).0[idx].to_bits(), (x * y).to_bits());
+            assert_eq!((a / b).0[idx].to_bits(), (x / y).to_bits());
+            assert_eq!(a.min(b).0[idx], x.min(y));
+            assert_eq!(a.max(b).0[idx], x.max(y));
+            assert_eq!(a.abs().0[idx].to_bits(), x.abs().to_bits());
+            assert_eq!(a.gt(b)[idx], x > y);
+        }
+        let mask = [true, false, true, false];
+        assert_eq!(Lanes::select(mask, a, b).0[1], 4.0);
+        assert_eq!(Lanes::select(mask, a, b).0[3], Float::INFINITY);
+    }
+}
@@ -1,25 +1,35 @@
 use std::ops::{Index, IndexMut, Mul};
 
+use super::Lanes;
+use crate::utils::Float;
+
+// Matrices are at most 4x4, the most a ray tracer needs (transforms, and
+// points and vectors as 4x1 columns), so the entries are stored inline and no
+// matrix operation allocates. Entries outside rows x cols are kept at zero, so
+// two matrices are equal exactly when their sizes and entries are.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Matrix {
     rows: usize,
     cols: usize,
-    matrix: Vec<Vec<f64>>,
+    matrix: [[Float; Matrix::MAX_SIZE]; Matrix::MAX_SIZE],
 }
 
 type Idx = [usize; 2];
 
 impl Matrix {
+    pub const MAX_SIZE: usize = 4;
+
     pub fn new(rows: usize, cols: usize) -> Matrix {
-        let mut matrix = Vec::with_capacity(rows);
-        for _i_row in 0..rows {
-            let mut row = Vec::with_capacity(cols);
-            for _i_col in 0..cols {
-                row.push(0.0);
-            }
-            matrix.push(row);
+        assert!(
+            rows <= Matrix::MAX_SIZE && cols <= Matrix::MAX_SIZE,
+            "a matrix is at most {0}x{0}",
+            Matrix::MAX_SIZE
+        );
+        Matrix {
+            rows,
+            cols,
+            matrix: [[0.0; Matrix::MAX_SIZE]; Matrix::MAX_SIZE],
         }
-        Matrix { rows, cols, matrix }
     }
 
     pub fn rows(&self) -> usize {
@@ -31,38 +41,86 @@ impl Matrix {
     }
 }
 
-impl From<&Vec<Vec<f64>>> for Matrix {
-    // does not consume the vector and requires cloning
-    fn from(vec2d: &Vec<Vec<f64>>) -> Self {
+impl From<[[Float; 4]; 4]> for Matrix {
+    fn from(matrix: [[Float; 4]; 4]) -> Self {
+        Matrix {
+            rows: 4,
+            cols: 4,
+            matrix,
+        }
+    }
+}
+
+impl From<&Vec<Vec<Float>>> for Matrix {
+    fn from(vec2d: &Vec<Vec<Float>>) -> Self {
         let rows = vec2d.len();
         assert_ne!(rows, 0);
-
-        let mut matrix = Vec::with_capacity(rows);
-
         let cols = vec2d[0].len();
-        assert_ne!(rows, 0);
 
-        // as we need to check row lengths anyway, we can clone while we're are it
-        for row in vec2d {
+        let mut matrix = Matrix::new(rows, cols);
+        for (idx_row, row) in vec2d.iter().enumerate() {
             assert_eq!(row.len(), cols);
-            matrix.push(row.clone());
+            matrix.matrix[idx_row][..cols].copy_from_slice(row);
         }
-
-        Matrix { rows, cols, matrix }
+        matrix
     }
 }
 
 impl Index<Idx> for Matrix {
-    type Output = f64;
+    type Output = Float;
 
-    fn index(&self, index: Idx) -> &Self::Output {
-        &self.matrix[index[0]][index[1]]
+    fn index(&self, [row, col]: Idx) -> &Self::Output {
+        assert!(row < self.rows && col < self.cols);
+        &self.matrix[row][col]
     }
 }
 
 impl IndexMut<Idx> for Matrix {
-    fn index_mut(&mut self, index: Idx) -> &mut Self::Output {
-        &mut self.matrix[index[0]][index[1]]
+    fn index_mut(&mut self, [row, col]: Idx) -> &mut Self::Output {
+        assert!(row < self.rows && col < self.cols);
+        &mut self.matrix[row][col]
+    }
+}
+
+impl Matrix {
+    fn is_4x4(&self) -> bool {
+        self.rows == 4 && self.cols == 4
+    }
+
+    // Each row of the product is a sum of the other's rows, scaled by this
+    // row's entries, so whole rows are multiplied and added at once. The
+    // sums are in the same order as the general product, so the results are
+    // identical.
+    fn mul_4x4(&self, other: &Matrix) -> Matrix {
+        let matrix = self.matrix.map(|row| {
+            let mut sum = Lanes::splat(0.0);
+            for (&entry, &other_row) in row.iter().zip(&other.matrix) {
+                sum = sum + Lanes::splat(entry) * Lanes(other_row);
+            }
+            sum.0
+        });
+        Matrix::from(matrix)
+    }
+
+    // the 4x4 matrix times the tuple as a column, without building a matrix
+    // for either; this is how every point and vector is transformed
+    pub(crate) fn mul_tuple4(&self, tuple: [Float; 4]) -> [Float; 4] {
+        assert!(self.is_4x4());
+        let mut sum = Lanes::splat(0.0);
+        for (idx_col, &value) in tuple.iter().enumerate() {
+            let column = Lanes::from_fn(|idx_row| self.matrix[idx_row][idx_col]);
+            sum = sum + column * Lanes::splat(value);
+        }
+        sum.0
+    }
+
+    // the tuple as a 4x1 column
+    fn column(tuple: [Float; 4]) -> Matrix {
+        let mut column = Matrix::new(4, 1);
+        for (idx_row, value) in tuple.into_iter().enumerate() {
+            column.matrix[idx_row][0] = value;
+        }
+        column
     }
 }
 
@@ -71,6 +129,9 @@ impl Mul<&Matrix> for Matrix {
 
     fn mul(self, other: &Matrix) -> Self::Output {
         assert_eq!(self.cols, other.rows);
+        if self.is_4x4() && other.is_4x4() {
+            return self.mul_4x4(other);
+        }
         let mut resulting_matrix = Matrix::new(self.rows, other.cols);
         for i in 0..self.rows {
             for j in 0..other.cols {
@@ -84,13 +145,16 @@ impl Mul<&Matrix> for Matrix {
 }
 
 pub trait Tuple4: Copy + From<Matrix> {
-    fn to_tuple4(self) -> [f64; 4];
+    fn to_tuple4(self) -> [Float; 4];
+
+    fn from_tuple4(tuple: [Float; 4]) -> Self {
+        Self::from(Matrix::column(tuple))
+    }
 }
 
 impl<T: Tuple4> From<T> for Matrix {
     fn from(value: T) -> Self {
-        let tuple: Vec<Vec<f64>> = value.to_tuple4().into_iter().map(|x| vec![x]).collect();
-        Matrix::from(&tuple)
+        Matrix::column(value.to_tuple4())
     }
 }
 
@@ -106,7 +170,7 @@ impl Matrix {
         transposed_matrix
     }
 
-    pub fn det(&self) -> f64 {
+    pub fn det(&self) -> Float {
         assert_eq!(self.rows, self.cols);
         assert!(self.rows >= 2);
 
@@ -122,26 +186,22 @@ impl Matrix {
     }
 
     pub fn submatrix(&self, [sm_row, sm_col]: Idx) -> Matrix {
-        let mut submatrix = self.matrix.clone();
-
-        submatrix.remove(sm_row);
-        submatrix.iter_mut().for_each(|row| {
-            row.remove(sm_col);
-            row.shrink_to_fit();
-        });
-
-        Matrix {
-            rows: self.rows - 1,
-            cols: self.cols - 1,
-            matrix: submatrix,
+        let mut submatrix = Matrix::new(self.rows - 1, self.cols - 1);
+        let rows = (0..self.rows).filter(|&row| row != sm_row);
+        for (idx_row, row) in rows.enumerate() {
+            let cols = (0..self.cols).filter(|&col| col != sm_col);
+            for (idx_col, col) in cols.enumerate() {
+                submatrix.matrix[idx_row][idx_col] = self.matrix[row][col];
+            }
         }
+        submatrix
     }
 
-    pub fn minor(&self, index: Idx) -> f64 {
+    pub fn minor(&self, index: Idx) -> Float {
         self.submatrix(index).det()
     }
 
-    pub fn cofactor(&self, index @ [row, col]: Idx) -> f64 {
+    pub fn cofactor(&self, index @ [row, col]: Idx) -> Float {
         if (row + col) % 2 == 0 {
             self.minor(index)
         } else {
@@ -149,6 +209,24 @@ impl Matrix {
         }
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.matrix.iter().flatten().all(|value| value.is_finite())
+    }
+
+    pub fn is_invertible(&self) -> bool {
+        let det = self.det();
+        det != 0.0 && det.is_finite()
+    }
+
+    // returns None instead of panicking for singular matrices
+    pub fn try_invert(&self) -> Option<Matrix> {
+        if self.is_invertible() {
+            Some(self.invert())
+        } else {
+            None
+        }
+    }
+
     pub fn invert(&self) -> Matrix {
         let (rows, cols) = (self.rows, self.cols);
         // panics if determinant is uncomputable (non-square matrix), checked by .det() method
@@ -175,20 +253,21 @@ mod tests {
 
     #[test]
     fn create_new_matrix() {
-        let matrix = Matrix::new(3, 5);
-        let stored_matrix = vec![
-            vec![0.0, 0.0, 0.0, 0.0, 0.0],
-            vec![0.0, 0.0, 0.0, 0.0, 0.0],
-            vec![0.0, 0.0, 0.0, 0.0, 0.0],
-        ];
+        let matrix = Matrix::new(3, 4);
         let resulting_matrix = Matrix {
             rows: 3,
-            cols: 5,
-            matrix: stored_matrix,
+            cols: 4,
+            matrix: [[0.0; 4]; 4],
         };
         assert_eq!(matrix, resulting_matrix);
     }
 
+    #[test]
+    #[should_panic(expected = "a matrix is at most 4x4")]
+    fn create_matrix_larger_than_4x4() {
+        Matrix::new(3, 5);
+    }
+
     #[test]
     fn matrix_accessors() {
         let matrix = Matrix::new(4, 1);
@@ -198,19 +277,31 @@ mod tests {
 
     #[test]
     fn index_and_modify_matrix() {
-        let mut matrix = Matrix::new(3, 5);
+        let mut matrix = Matrix::new(3, 4);
         assert_eq!(matrix[[2, 1]], 0.0);
         matrix[[2, 3]] = 64.0;
         assert_eq!(matrix[[2, 3]], 64.0);
     }
 
+    #[test]
+    #[should_panic]
+    fn index_outside_matrix() {
+        let matrix = Matrix::new(3, 2);
+        let _entry = matrix[[0, 3]];
+    }
+
     #[test]
     fn create_matrix_from_2d_vec() {
         let array = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]];
         let resulting_matrix = Matrix {
             rows: 3,
             cols: 2,
-            matrix: array.clone(),
+            matrix: [
+                [1.0, 2.0, 0.0, 0.0],
+                [3.0, 4.0, 0.0, 0.0],
+                [5.0, 6.0, 0.0, 0.0],
+                [0.0; 4],
+            ],
         };
         assert_eq!(Matrix::from(&array), resulting_matrix);
     }
@@ -245,13 +336,45 @@ mod tests {
         assert_eq!(matrix1 * &matrix2, resulting_matrix);
     }
 
+    #[test]
+    fn mul_4x4_matches_general_product() {
+        let matrix1 = Matrix::from(&vec![
+            vec![0.1, -2.0, 3.5, 4.0],
+            vec![5.0, 0.3, -7.0, 8.0],
+            vec![9.0, 8.0, 0.7, 6.0],
+            vec![-0.0, 4.0, 3.0, 2.0],
+        ]);
+        let matrix2 = matrix1.invert();
+        let mut general = Matrix::new(4, 4);
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    general[[i, j]] += matrix1[[i, k]] * matrix2[[k, j]];
+                }
+            }
+        }
+        assert_eq!(matrix1.clone() * &matrix2, general);
+
+        let point = Point::new(0.3, -1.7, 2.9);
+        let column = Matrix::from(point);
+        assert_eq!(
+            Point::from_tuple4(matrix1.mul_tuple4(point.to_tuple4())),
+            Point::from(matrix1 * &column)
+        );
+    }
+
     #[test]
     fn point_to_matrix() {
         let point = Point::new(6.0, 4.0, 2.0);
         let matrix = Matrix {
             rows: 4,
             cols: 1,
-            matrix: vec![vec![6.0], vec![4.0], vec![2.0], vec![1.0]],
+            matrix: [
+                [6.0, 0.0, 0.0, 0.0],
+                [4.0, 0.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0, 0.0],
+            ],
         };
         assert_eq!(Matrix::from(point), matrix);
     }
@@ -262,7 +385,12 @@ mod tests {
         let matrix = Matrix {
             rows: 4,
             cols: 1,
-            matrix: vec![vec![6.0], vec![4.0], vec![2.0], vec![0.0]],
+            matrix: [
+                [6.0, 0.0, 0.0, 0.0],
+                [4.0, 0.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0, 0.0],
+                [0.0; 4],
+            ],
         };
         assert_eq!(Matrix::from(vector), matrix);
     }
@@ -352,4 +480,24 @@ mod tests {
         ]);
         assert_eq!(matrix.invert(), matrix);
     }
+
+    #[test]
+    fn try_invert_singular_matrix() {
+        let matrix = Matrix::from(&vec![
+            vec![-4.0, 2.0, -2.0, -3.0],
+            vec![9.0, 6.0, 2.0, 6.0],
+            vec![0.0, -5.0, 1.0, -5.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+        ]);
+        assert!(!matrix.is_invertible());
+        assert_eq!(matrix.try_invert(), None);
+    }
+
+    #[test]
+    fn matrix_with_nan_is_not_finite() {
+        let mut matrix = Matrix::new(2, 2);
+        assert!(matrix.is_finite());
+        matrix[[1, 0]] = Float::NAN;
+        assert!(!matrix.is_finite());
+    }
 }
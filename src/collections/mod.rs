@@ -1,21 +1,26 @@
 pub mod angle;
 pub mod colour;
+pub(crate) mod lanes;
 pub mod matrix;
 pub mod point;
+pub mod spectrum;
 pub mod vector;
 
 // crate-level re-exports
 pub(crate) use angle::*;
 pub(crate) use colour::*;
+pub(crate) use lanes::*;
 pub(crate) use matrix::*;
 pub(crate) use point::*;
+pub(crate) use spectrum::*;
 pub(crate) use vector::*;
 
 // public re-exports (through crate::prelude)
 pub(super) mod prelude {
     pub use super::angle::Angle;
-    pub use super::colour::Colour;
+    pub use super::colour::{Colour, HexColourError};
     pub use super::matrix::{Matrix, Tuple4};
     pub use super::point::Point;
+    pub use super::spectrum::{cie_xyz, spectral_samples, VISIBLE_WAVELENGTHS};
     pub use super::vector::Vector;
 }
@@ -1,16 +1,17 @@
 use std::ops::{Add, Neg, Sub};
 
 use super::{Matrix, Tuple4, Vector};
+use crate::utils::Float;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Point {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
 }
 
 impl Point {
-    pub fn new(x: f64, y: f64, z: f64) -> Point {
+    pub fn new(x: Float, y: Float, z: Float) -> Point {
         Point { x, y, z }
     }
 
@@ -23,9 +24,9 @@ impl Point {
     }
 
     pub fn at_infinity(&self) -> bool {
-        self.x.abs() == f64::INFINITY
-            || self.y.abs() == f64::INFINITY
-            || self.z.abs() == f64::INFINITY
+        self.x.abs() == Float::INFINITY
+            || self.y.abs() == Float::INFINITY
+            || self.z.abs() == Float::INFINITY
     }
 }
 
@@ -78,9 +79,13 @@ impl Neg for Point {
 }
 
 impl Tuple4 for Point {
-    fn to_tuple4(self) -> [f64; 4] {
+    fn to_tuple4(self) -> [Float; 4] {
         [self.x, self.y, self.z, 1.0]
     }
+
+    fn from_tuple4([x, y, z, _]: [Float; 4]) -> Self {
+        Point::new(x, y, z)
+    }
 }
 
 impl From<Matrix> for Point {
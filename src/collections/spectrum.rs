@@ -0,0 +1,113 @@
+use crate::collections::Colour;
+use crate::utils::Float;
+
+// the visible range sampled in spectral rendering, in nanometres
+pub const VISIBLE_WAVELENGTHS: [Float; 2] = [380.0, 720.0];
+
+// The CIE 1931 2° colour matching functions at a wavelength in nanometres,
+// from the multi-lobe Gaussian fit of Wyman, Sloan and Shirley (2013), which
+// is within the variation between observers.
+pub fn cie_xyz(wavelength: Float) -> (Float, Float, Float) {
+    // a Gaussian with different widths either side of its peak
+    let lobe = |peak: Float, below: Float, above: Float| {
+        let width = if wavelength < peak { below } else { above };
+        (-0.5 * ((wavelength - peak) / width).powi(2)).exp()
+    };
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+        - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+impl Colour {
+    // linear sRGB (the colour space of the renderer) from CIE XYZ
+    pub fn from_xyz(x: Float, y: Float, z: Float) -> Colour {
+        Colour::new(
+            3.2406 * x - 1.5372 * y - 0.4986 * z,
+            -0.9689 * x + 1.8758 * y + 0.0415 * z,
+            0.0557 * x - 0.2040 * y + 1.0570 * z,
+        )
+    }
+
+    // the colour of light of a single wavelength, with the components that
+    // are out of gamut (negative) clipped
+    pub fn from_wavelength(wavelength: Float) -> Colour {
+        let (x, y, z) = cie_xyz(wavelength);
+        let colour = Colour::from_xyz(x, y, z);
+        Colour::new(
+            colour.red.max(0.0),
+            colour.green.max(0.0),
+            colour.blue.max(0.0),
+        )
+    }
+}
+
+// Evenly spaced wavelengths across the visible range, each with the weight
+// its radiance contributes to a pixel. The weights of each channel sum to one,
+// so light that is the same at every wavelength keeps its colour.
+pub fn spectral_samples(count: usize) -> Vec<(Float, Colour)> {
+    let [shortest, longest] = VISIBLE_WAVELENGTHS;
+    let wavelengths: Vec<Float> = (0..count)
+        .map(|idx| shortest + (idx as Float + 0.5) * (longest - shortest) / count as Float)
+        .collect();
+    let colours: Vec<Colour> = wavelengths
+        .iter()
+        .map(|&wavelength| Colour::from_wavelength(wavelength))
+        .collect();
+
+    let total = colours
+        .iter()
+        .fold(Colour::new(0.0, 0.0, 0.0), |sum, &colour| sum + colour);
+    let normalise = |component: Float, total: Float| {
+        if total > 0.0 {
+            component / total
+        } else {
+            0.0
+        }
+    };
+    wavelengths
+        .into_iter()
+        .zip(colours)
+        .map(|(wavelength, colour)| {
+            let weight = Colour::new(
+                normalise(colour.red, total.red),
+                normalise(colour.green, total.green),
+                normalise(colour.blue, total.blue),
+            );
+            (wavelength, weight)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn colour_matching_functions() {
+        // luminance peaks in the green, near 555 nm
+        let (_, y_green, _) = cie_xyz(555.0);
+        let (_, y_blue, _) = cie_xyz(450.0);
+        assert!(y_green > 0.99 && y_green > 5.0 * y_blue);
+
+        let red = Colour::from_wavelength(650.0);
+        assert!(red.red > 0.0 && red.green == 0.0);
+        let blue = Colour::from_wavelength(450.0);
+        assert!(blue.blue > blue.red && blue.blue > blue.green);
+    }
+
+    #[test]
+    fn sample_weights_sum_to_white() {
+        let samples = spectral_samples(16);
+        assert_eq!(samples.len(), 16);
+        assert!(samples.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        let total = samples
+            .iter()
+            .fold(Colour::new(0.0, 0.0, 0.0), |sum, &(_, weight)| sum + weight);
+        approx_eq!(total.red, 1.0);
+        approx_eq!(total.green, 1.0);
+        approx_eq!(total.blue, 1.0);
+    }
+}
@@ -1,16 +1,17 @@
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use super::{Matrix, Point, Tuple4};
+use crate::utils::Float;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vector {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
 }
 
 impl Vector {
-    pub fn new(x: f64, y: f64, z: f64) -> Vector {
+    pub const fn new(x: Float, y: Float, z: Float) -> Vector {
         Vector { x, y, z }
     }
 
@@ -67,7 +68,7 @@ impl Neg for Vector {
     }
 }
 
-impl Mul<Vector> for f64 {
+impl Mul<Vector> for Float {
     type Output = Vector;
 
     fn mul(self, other: Vector) -> Self::Output {
@@ -79,18 +80,18 @@ impl Mul<Vector> for f64 {
     }
 }
 
-impl Mul<f64> for Vector {
+impl Mul<Float> for Vector {
     type Output = Vector;
 
-    fn mul(self, other: f64) -> Self::Output {
+    fn mul(self, other: Float) -> Self::Output {
         other * self
     }
 }
 
-impl Div<f64> for Vector {
+impl Div<Float> for Vector {
     type Output = Vector;
 
-    fn div(self, other: f64) -> Self::Output {
+    fn div(self, other: Float) -> Self::Output {
         Vector {
             x: self.x / other,
             y: self.y / other,
@@ -100,8 +101,8 @@ impl Div<f64> for Vector {
 }
 
 impl Vector {
-    pub fn magnitude(self) -> f64 {
-        (self.x.powf(2.0_f64) + self.y.powf(2.0_f64) + self.z.powf(2.0_f64)).sqrt()
+    pub fn magnitude(self) -> Float {
+        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).sqrt()
     }
 
     pub fn normalise(self) -> Vector {
@@ -113,7 +114,7 @@ impl Vector {
         }
     }
 
-    pub fn dot(self, other: Vector) -> f64 {
+    pub fn dot(self, other: Vector) -> Float {
         (self.x * other.x) + (self.y * other.y) + (self.z * other.z)
     }
 
@@ -131,9 +132,13 @@ impl Vector {
 }
 
 impl Tuple4 for Vector {
-    fn to_tuple4(self) -> [f64; 4] {
+    fn to_tuple4(self) -> [Float; 4] {
         [self.x, self.y, self.z, 0.0]
     }
+
+    fn from_tuple4([x, y, z, _]: [Float; 4]) -> Self {
+        Vector::new(x, y, z)
+    }
 }
 
 impl From<Matrix> for Vector {
@@ -194,7 +199,7 @@ mod tests {
 
     #[test]
     fn mul_scalar_by_vector() {
-        let scalar = 2.0_f64;
+        let scalar: Float = 2.0;
         let vector = Vector::new(5.0, 5.0, 7.0);
         let resulting_vector = Vector::new(10.0, 10.0, 14.0);
         assert_eq!(scalar * vector, resulting_vector);
@@ -203,7 +208,7 @@ mod tests {
     #[test]
     fn mul_vector_by_scalar() {
         let vector = Vector::new(3.0, 1.0, 7.0);
-        let scalar = 3.0_f64;
+        let scalar: Float = 3.0;
         let resulting_vector = Vector::new(9.0, 3.0, 21.0);
         assert_eq!(vector * scalar, resulting_vector);
     }
@@ -211,7 +216,7 @@ mod tests {
     #[test]
     fn div_vector_by_scalar() {
         let vector = Vector::new(3.0, 6.0, 9.0);
-        let scalar = 3.0_f64;
+        let scalar: Float = 3.0;
         let resulting_vector = Vector::new(1.0, 2.0, 3.0);
         assert_eq!(vector / scalar, resulting_vector);
     }
@@ -219,7 +224,7 @@ mod tests {
     #[test]
     fn magnitude_of_vector() {
         let vector = Vector::new(1.0, 2.0, 2.0);
-        let resulting_magnitude = 3.0_f64;
+        let resulting_magnitude: Float = 3.0;
         assert_eq!(vector.magnitude(), resulting_magnitude);
     }
 
@@ -234,7 +239,7 @@ mod tests {
     fn dot_product_two_vectors() {
         let vector1 = Vector::new(1.0, 2.0, 3.0);
         let vector2 = Vector::new(2.0, 3.0, 4.0);
-        let resulting_value = 20.0_f64;
+        let resulting_value: Float = 20.0;
         assert_eq!(vector1.dot(vector2), resulting_value);
     }
 
@@ -265,7 +270,7 @@ mod tests {
         let vector1 = Vector::new(1.0, -1.0, 0.0);
         let vector2 = Vector::new(0.0, -1.0, 0.0);
         let normal1 = Vector::new(0.0, 1.0, 0.0);
-        let normal2 = Vector::new(2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0, 0.0);
+        let normal2 = Vector::new(Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0, 0.0);
         let resulting_vector1 = Vector::new(1.0, 1.0, 0.0);
         let resulting_vector2 = Vector::new(1.0, 0.0, 0.0);
         assert_eq!(vector1.reflect(normal1), resulting_vector1);
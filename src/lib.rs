@@ -59,6 +59,8 @@ exported as crate::module::Name in src/module/shapes.
 
 In src/module/shapes, bring all nested names into scope using
     `pub(crate) use submodule::*`.
+Submodules none of whose names are used elsewhere in the crate are left out
+(rustc warns of the unused import) until something needs them.
 
 Submodules within the same parent module should bring names into scope using
     `use super::Name`.
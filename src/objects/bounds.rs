@@ -1,21 +1,21 @@
 use std::ops::Add;
 
-use crate::collections::Point;
-use crate::objects::{Ray, Transform, Transformable};
-use crate::utils::EPSILON;
+use crate::collections::{Lanes, Point, LANES};
+use crate::objects::{Ray, RayPacket, Transform, Transformable};
+use crate::utils::{Float, EPSILON};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BoundingBox {
-    x_range: [f64; 2],
-    y_range: [f64; 2],
-    z_range: [f64; 2],
+    x_range: [Float; 2],
+    y_range: [Float; 2],
+    z_range: [Float; 2],
 }
 
 impl BoundingBox {
     const UNBOUNDED: BoundingBox = BoundingBox {
-        x_range: [f64::NEG_INFINITY, f64::INFINITY],
-        y_range: [f64::NEG_INFINITY, f64::INFINITY],
-        z_range: [f64::NEG_INFINITY, f64::INFINITY],
+        x_range: [Float::NEG_INFINITY, Float::INFINITY],
+        y_range: [Float::NEG_INFINITY, Float::INFINITY],
+        z_range: [Float::NEG_INFINITY, Float::INFINITY],
     };
 
     pub const fn new_unbounded() -> BoundingBox {
@@ -33,21 +33,21 @@ impl BoundingBox {
         let mut z_range = [z, z];
 
         for anchor in anchors.iter().skip(1) {
-            x_range[0] = f64::min(x_range[0], anchor.x);
-            x_range[1] = f64::max(x_range[1], anchor.x);
-            y_range[0] = f64::min(y_range[0], anchor.y);
-            y_range[1] = f64::max(y_range[1], anchor.y);
-            z_range[0] = f64::min(z_range[0], anchor.z);
-            z_range[1] = f64::max(z_range[1], anchor.z);
+            x_range[0] = Float::min(x_range[0], anchor.x);
+            x_range[1] = Float::max(x_range[1], anchor.x);
+            y_range[0] = Float::min(y_range[0], anchor.y);
+            y_range[1] = Float::max(y_range[1], anchor.y);
+            z_range[0] = Float::min(z_range[0], anchor.z);
+            z_range[1] = Float::max(z_range[1], anchor.z);
         }
 
         BoundingBox::from_axial_bounds(x_range, y_range, z_range)
     }
 
     pub const fn from_axial_bounds(
-        x_range: [f64; 2],
-        y_range: [f64; 2],
-        z_range: [f64; 2],
+        x_range: [Float; 2],
+        y_range: [Float; 2],
+        z_range: [Float; 2],
     ) -> BoundingBox {
         BoundingBox {
             x_range,
@@ -56,7 +56,7 @@ impl BoundingBox {
         }
     }
 
-    pub fn bound_in_x_axis(mut self, axial_bounds: [f64; 2]) -> BoundingBox {
+    pub fn bound_in_x_axis(mut self, axial_bounds: [Float; 2]) -> BoundingBox {
         let axial_bounds = if axial_bounds[0] > axial_bounds[1] {
             [axial_bounds[1], axial_bounds[0]]
         } else {
@@ -66,7 +66,7 @@ impl BoundingBox {
         self
     }
 
-    pub fn bound_in_y_axis(mut self, axial_bounds: [f64; 2]) -> BoundingBox {
+    pub fn bound_in_y_axis(mut self, axial_bounds: [Float; 2]) -> BoundingBox {
         let axial_bounds = if axial_bounds[0] > axial_bounds[1] {
             [axial_bounds[1], axial_bounds[0]]
         } else {
@@ -76,7 +76,7 @@ impl BoundingBox {
         self
     }
 
-    pub fn bound_in_z_axis(mut self, axial_bounds: [f64; 2]) -> BoundingBox {
+    pub fn bound_in_z_axis(mut self, axial_bounds: [Float; 2]) -> BoundingBox {
         let axial_bounds = if axial_bounds[0] > axial_bounds[1] {
             [axial_bounds[1], axial_bounds[0]]
         } else {
@@ -98,24 +98,24 @@ impl BoundingBox {
         anchors
     }
 
-    pub fn axial_bounds(&self) -> ([f64; 2], [f64; 2], [f64; 2]) {
+    pub fn axial_bounds(&self) -> ([Float; 2], [Float; 2], [Float; 2]) {
         (self.x_range, self.y_range, self.z_range)
     }
 
     pub fn is_bounded(&self) -> bool {
         // a bounding box is bounded if it does not include all representable points
         // in other words, at least one of the above f64 values must be non-infinite
-        !(self.x_range == [f64::NEG_INFINITY, f64::INFINITY]
-            && self.y_range == [f64::NEG_INFINITY, f64::INFINITY]
-            && self.z_range == [f64::NEG_INFINITY, f64::INFINITY])
+        !(self.x_range == [Float::NEG_INFINITY, Float::INFINITY]
+            && self.y_range == [Float::NEG_INFINITY, Float::INFINITY]
+            && self.z_range == [Float::NEG_INFINITY, Float::INFINITY])
     }
 
     pub fn intersect_bounds<'world: 'ray, 'ray>(
         &'world self,
         ray: &'ray Ray,
-        transform_stack: &Vec<&'ray Transform>,
+        transform_stack: &[&'ray Transform],
     ) -> bool {
-        fn check_axis(range: [f64; 2], origin: f64, direction: f64) -> (f64, f64) {
+        fn check_axis(range: [Float; 2], origin: Float, direction: Float) -> (Float, Float) {
             assert!(range[0] <= range[1]);
 
             let [min, max] = range;
@@ -128,8 +128,8 @@ impl BoundingBox {
                 tmin = tmin_numerator / direction;
                 tmax = tmax_numerator / direction;
             } else {
-                tmin = tmin_numerator * f64::INFINITY;
-                tmax = tmax_numerator * f64::INFINITY;
+                tmin = tmin_numerator * Float::INFINITY;
+                tmax = tmax_numerator * Float::INFINITY;
             }
 
             if tmin > tmax {
@@ -145,10 +145,50 @@ impl BoundingBox {
         let (ytmin, ytmax) = check_axis(self.y_range, ray.origin.y, ray.direction.y);
         let (ztmin, ztmax) = check_axis(self.z_range, ray.origin.z, ray.direction.z);
 
-        let tmin = [xtmin, ytmin, ztmin].into_iter().reduce(f64::max).unwrap();
-        let tmax = [xtmax, ytmax, ztmax].into_iter().reduce(f64::min).unwrap();
+        let tmin = [xtmin, ytmin, ztmin]
+            .into_iter()
+            .reduce(Float::max)
+            .unwrap();
+        let tmax = [xtmax, ytmax, ztmax]
+            .into_iter()
+            .reduce(Float::min)
+            .unwrap();
+
+        // and the box is not wholly outside the ray's range
+        tmax >= tmin && tmax >= ray.t_min && tmin < ray.t_max
+    }
+
+    // intersect_bounds for each ray of a world-space packet at once, lane by
+    // lane exactly as for one ray; lanes past the packet's rays are unused
+    pub(crate) fn intersect_packet(&self, packet: &RayPacket) -> [bool; LANES] {
+        fn check_axis(range: [Float; 2], origin: Lanes, direction: Lanes) -> (Lanes, Lanes) {
+            let [min, max] = range;
+            let tmin_numerator = Lanes::splat(min) - origin;
+            let tmax_numerator = Lanes::splat(max) - origin;
+
+            let steep = direction.abs().ge(Lanes::splat(EPSILON));
+            let infinity = Lanes::splat(Float::INFINITY);
+            let tmin = Lanes::select(steep, tmin_numerator / direction, tmin_numerator * infinity);
+            let tmax = Lanes::select(steep, tmax_numerator / direction, tmax_numerator * infinity);
+
+            let swapped = tmin.gt(tmax);
+            (
+                Lanes::select(swapped, tmax, tmin),
+                Lanes::select(swapped, tmin, tmax),
+            )
+        }
 
-        tmax >= tmin
+        let [x_origin, y_origin, z_origin] = *packet.origins();
+        let [x_direction, y_direction, z_direction] = *packet.directions();
+        let (xtmin, xtmax) = check_axis(self.x_range, x_origin, x_direction);
+        let (ytmin, ytmax) = check_axis(self.y_range, y_origin, y_direction);
+        let (ztmin, ztmax) = check_axis(self.z_range, z_origin, z_direction);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        let [t_min, t_max] = *packet.ranges();
+        let [crossed, not_behind, not_beyond] = [tmax.ge(tmin), tmax.ge(t_min), t_max.gt(tmin)];
+        std::array::from_fn(|idx| crossed[idx] && not_behind[idx] && not_beyond[idx])
     }
 }
 
@@ -157,16 +197,16 @@ impl Add for BoundingBox {
 
     fn add(self, rhs: Self) -> Self::Output {
         let x_range = [
-            f64::min(self.x_range[0], rhs.x_range[0]),
-            f64::max(self.x_range[1], rhs.x_range[1]),
+            Float::min(self.x_range[0], rhs.x_range[0]),
+            Float::max(self.x_range[1], rhs.x_range[1]),
         ];
         let y_range = [
-            f64::min(self.y_range[0], rhs.y_range[0]),
-            f64::max(self.y_range[1], rhs.y_range[1]),
+            Float::min(self.y_range[0], rhs.y_range[0]),
+            Float::max(self.y_range[1], rhs.y_range[1]),
         ];
         let z_range = [
-            f64::min(self.z_range[0], rhs.z_range[0]),
-            f64::max(self.z_range[1], rhs.z_range[1]),
+            Float::min(self.z_range[0], rhs.z_range[0]),
+            Float::max(self.z_range[1], rhs.z_range[1]),
         ];
 
         BoundingBox::from_axial_bounds(x_range, y_range, z_range)
@@ -230,13 +270,21 @@ impl Bounds {
     pub fn intersect_bounds<'world: 'ray, 'ray>(
         &'world self,
         ray: &'ray Ray,
-        transform_stack: &Vec<&'ray Transform>,
+        transform_stack: &[&'ray Transform],
     ) -> bool {
         match self {
             Bounds::Checked(bbox) => bbox.intersect_bounds(ray, transform_stack),
             Bounds::Unchecked(_) => true,
         }
     }
+
+    // see BoundingBox::intersect_packet
+    pub(crate) fn intersect_packet(&self, packet: &RayPacket) -> [bool; LANES] {
+        match self {
+            Bounds::Checked(bbox) => bbox.intersect_packet(packet),
+            Bounds::Unchecked(_) => [true; LANES],
+        }
+    }
 }
 
 pub trait Bounded {
@@ -276,8 +324,8 @@ mod tests {
 
     use crate::collections::Angle;
     use crate::objects::{Axis, Transform, TransformKind};
+    use crate::utils::consts::FRAC_PI_4;
     use crate::utils::floats::approx_eq;
-    use std::f64::consts::FRAC_PI_4;
 
     #[test]
     fn transform_bounding_box() {
@@ -297,8 +345,8 @@ mod tests {
             z_range,
             ..
         } = bounding_box;
-        approx_eq!(x_range[0], -1.414214);
-        approx_eq!(x_range[1], 1.414214);
+        approx_eq!(x_range[0], -crate::utils::consts::SQRT_2);
+        approx_eq!(x_range[1], crate::utils::consts::SQRT_2);
         approx_eq!(y_range[0], -1.707107);
         approx_eq!(y_range[1], 1.707107);
         approx_eq!(z_range[0], -1.707107);
@@ -341,7 +389,7 @@ mod tests {
         ];
         let rays: Vec<Ray> = origins
             .into_iter()
-            .zip(directions.into_iter())
+            .zip(directions)
             .map(|(origin, direction)| Ray::new(origin, direction))
             .collect();
         let results = vec![
@@ -352,9 +400,41 @@ mod tests {
             Point::new(1.0, 1.0, 1.0),
         ]);
 
-        for (ray, result) in rays.into_iter().zip(results.into_iter()) {
+        for (ray, &result) in rays.iter().zip(&results) {
             println!("{:?}, {:?}", ray, result);
-            assert_eq!(bounding_box.intersect_bounds(&ray, &vec![]), result);
+            assert_eq!(bounding_box.intersect_bounds(ray, &[]), result);
+        }
+
+        let packet_results: Vec<bool> = RayPacket::packets(&rays)
+            .flat_map(|packet| {
+                let hits = bounding_box.intersect_packet(&packet);
+                hits.into_iter().take(packet.rays().len())
+            })
+            .collect();
+        assert_eq!(packet_results, results);
+    }
+
+    #[test]
+    fn boxes_outside_the_ray_range_are_missed() {
+        let bounding_box = BoundingBox::from_anchors(vec![
+            Point::new(-1.0, -1.0, -1.0),
+            Point::new(1.0, 1.0, 1.0),
+        ]);
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let rays = [
+            ray,
+            ray.with_range(0.0, 3.0),
+            ray.with_range(0.0, 4.5),
+            ray.with_range(6.5, Float::INFINITY),
+        ];
+        let results = [true, false, true, false];
+
+        for (ray, result) in rays.iter().zip(results) {
+            assert_eq!(bounding_box.intersect_bounds(ray, &[]), result);
         }
+        assert_eq!(
+            bounding_box.intersect_packet(&RayPacket::new(&rays)),
+            results
+        );
     }
 }
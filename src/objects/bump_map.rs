@@ -0,0 +1,101 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::collections::{Point, Vector};
+use crate::utils::{Float, Perlin};
+
+// step for estimating the gradient by central differences
+const GRADIENT_STEP: Float = 1e-4;
+
+// A height field over a material's object space. Its gradient tilts shading
+// normals as though the surface were displaced by strength * height along
+// them, without changing the geometry, so silhouettes and shadows stay smooth.
+#[derive(Clone)]
+pub struct BumpMap {
+    height: Arc<dyn Fn(Point) -> Float + Send + Sync>,
+    pub strength: Float,
+}
+
+impl Debug for BumpMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BumpMap")
+            .field("strength", &self.strength)
+            .finish_non_exhaustive()
+    }
+}
+
+// height functions cannot be compared, so only shared ones are equal
+impl PartialEq for BumpMap {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.height, &other.height) && self.strength == other.strength
+    }
+}
+
+impl BumpMap {
+    pub fn new<F>(height: F) -> BumpMap
+    where
+        F: Fn(Point) -> Float + Send + Sync + 'static,
+    {
+        BumpMap {
+            height: Arc::new(height),
+            strength: 1.0,
+        }
+    }
+
+    // fractal noise bumps roughly 1 / frequency across, e.g. high frequency
+    // and low strength for orange peel, or low frequency for hammered metal
+    pub fn noise(noise: Perlin, frequency: Float, octaves: usize) -> BumpMap {
+        BumpMap::new(move |Point { x, y, z }| {
+            let sample = Point::new(x * frequency, y * frequency, z * frequency);
+            noise.fractal(sample, octaves) / frequency
+        })
+    }
+
+    pub fn height_at(&self, object_point: Point) -> Float {
+        self.strength * (self.height)(object_point)
+    }
+
+    pub(crate) fn gradient_at(&self, object_point: Point) -> Vector {
+        let Point { x, y, z } = object_point;
+        let difference = |ahead: Point, behind: Point| {
+            (self.height_at(ahead) - self.height_at(behind)) / (2.0 * GRADIENT_STEP)
+        };
+        let step = GRADIENT_STEP;
+        Vector::new(
+            difference(Point::new(x + step, y, z), Point::new(x - step, y, z)),
+            difference(Point::new(x, y + step, z), Point::new(x, y - step, z)),
+            difference(Point::new(x, y, z + step), Point::new(x, y, z - step)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn gradient_of_height_field() {
+        let bump_map = BumpMap {
+            strength: 2.0,
+            ..BumpMap::new(|point| 0.25 * point.x + point.y * point.y)
+        };
+        let gradient = bump_map.gradient_at(Point::new(3.0, 0.5, -1.0));
+        approx_eq!(gradient.x, 0.5);
+        approx_eq!(gradient.y, 2.0);
+        approx_eq!(gradient.z, 0.0);
+        approx_eq!(bump_map.height_at(Point::new(2.0, 1.0, 0.0)), 3.0);
+    }
+
+    #[test]
+    fn noise_bumps_vary_but_compare_only_when_shared() {
+        let bump_map = BumpMap::noise(Perlin::new(5), 8.0, 2);
+        let gradients: Vec<Vector> = (0..10)
+            .map(|step| bump_map.gradient_at(Point::new(step as Float * 0.07, 0.3, 0.1)))
+            .collect();
+        assert!(gradients.windows(2).any(|pair| pair[0] != pair[1]));
+
+        assert_eq!(bump_map, bump_map.clone());
+        assert_ne!(bump_map, BumpMap::noise(Perlin::new(5), 8.0, 2));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::objects::*;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Csg {
     csg_operation: CsgOperation,
     lshape: Box<Shape>,
@@ -88,20 +88,20 @@ impl Csg {
 }
 
 impl Intersectable<dyn PrimitiveShape> for Csg {
-    fn intersect_ray<'world: 'ray, 'ray>(
+    fn intersect_ray_into<'world: 'ray, 'ray>(
         &'world self,
         world_ray: &'ray Ray,
-        transform_stack: Vec<&'ray Transform>,
-    ) -> HitRegister<'ray, dyn PrimitiveShape> {
-        let mut lshape_hit_register = self
-            .lshape()
-            .intersect_ray(world_ray, transform_stack.clone());
-        let rshape_hit_register = self
-            .rshape()
-            .intersect_ray(world_ray, transform_stack.clone());
-        lshape_hit_register.combine_registers(rshape_hit_register);
-
-        self.evaluate_intersections(lshape_hit_register)
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
+        // both sides' intersections are needed together to decide which count
+        let mut shapes_hit_register = HitRegister::empty();
+        self.lshape()
+            .intersect_ray_into(world_ray, transform_stack, &mut shapes_hit_register);
+        self.rshape()
+            .intersect_ray_into(world_ray, transform_stack, &mut shapes_hit_register);
+
+        hit_register.combine_registers(self.evaluate_intersections(shapes_hit_register));
     }
 }
 
@@ -111,11 +111,17 @@ impl Bounded for Csg {
     }
 }
 
+impl From<Csg> for Shape {
+    fn from(csg: Csg) -> Shape {
+        Shape::Csg(csg)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::collections::{Point, Vector};
-    use crate::utils::{BuildInto, Buildable};
+    use crate::utils::{BuildInto, Buildable, Float};
 
     #[test]
     fn evaluate_intersections_for_union() {
@@ -206,14 +212,14 @@ mod tests {
             let rshape = rshape.as_ref();
 
             let hit_register = HitRegister::from(vec![
-                Intersect::new(0.0, lshape, &placeholder_ray, None, vec![]),
-                Intersect::new(1.0, rshape, &placeholder_ray, None, vec![]),
-                Intersect::new(2.0, lshape, &placeholder_ray, None, vec![]),
-                Intersect::new(3.0, rshape, &placeholder_ray, None, vec![]),
+                Intersect::new(0.0, lshape, &placeholder_ray, None, &[]),
+                Intersect::new(1.0, rshape, &placeholder_ray, None, &[]),
+                Intersect::new(2.0, lshape, &placeholder_ray, None, &[]),
+                Intersect::new(3.0, rshape, &placeholder_ray, None, &[]),
             ]);
 
             let filtered_intersections = csg.evaluate_intersections(hit_register).expose();
-            let t_list: Vec<f64> = filtered_intersections.iter().map(|itx| itx.t()).collect();
+            let t_list: Vec<Float> = filtered_intersections.iter().map(|itx| itx.t()).collect();
             assert_eq!(x0, t_list[0]);
             assert_eq!(x1, t_list[1]);
         }
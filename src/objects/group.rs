@@ -1,7 +1,8 @@
+use crate::collections::LANES;
 use crate::objects::*;
 use crate::utils::{Buildable, ConsumingBuilder};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Group {
     frame_transformation: Transform,
     objects: Vec<Shape>,
@@ -18,21 +19,45 @@ impl Group {
     }
 }
 
+impl Group {
+    // see Shape::intersect_packet_into
+    pub(crate) fn intersect_packet_into<'world: 'ray, 'ray>(
+        &'world self,
+        packet: &'ray RayPacket,
+        local_packet: &RayPacket,
+        lanes: [bool; LANES],
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_registers: &mut [HitRegister<'ray, dyn PrimitiveShape>],
+    ) {
+        transform_stack.push(self.frame_transformation());
+        let local_packet = local_packet
+            .clone()
+            .transform(&self.frame_transformation().invert());
+        for shape in &self.objects {
+            shape.intersect_packet_into(
+                packet,
+                &local_packet,
+                lanes,
+                transform_stack,
+                hit_registers,
+            );
+        }
+        transform_stack.pop();
+    }
+}
+
 impl Intersectable<dyn PrimitiveShape> for Group {
-    fn intersect_ray<'world: 'ray, 'ray>(
+    fn intersect_ray_into<'world: 'ray, 'ray>(
         &'world self,
         world_ray: &'ray Ray,
-        mut transform_stack: Vec<&'ray Transform>,
-    ) -> HitRegister<'ray, dyn PrimitiveShape> {
-        let mut ray_hit_register = HitRegister::empty();
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
         transform_stack.push(self.frame_transformation());
-
         for shape in &self.objects {
-            let shape_hit_register = shape.intersect_ray(world_ray, transform_stack.clone());
-            ray_hit_register.combine_registers(shape_hit_register);
+            shape.intersect_ray_into(world_ray, transform_stack, hit_register);
         }
-
-        ray_hit_register
+        transform_stack.pop();
     }
 }
 
@@ -74,21 +99,9 @@ impl GroupBuilder {
         }
         self
     }
-}
 
-impl Buildable for Group {
-    type Builder = GroupBuilder;
-
-    fn builder() -> Self::Builder {
-        GroupBuilder::default()
-    }
-}
-
-impl ConsumingBuilder for GroupBuilder {
-    type Built = Group;
-
-    fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
+    pub fn try_build(self) -> Result<Group, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
         let objects = self.objects.unwrap_or_default();
         let bounds = match objects
             .iter()
@@ -99,18 +112,33 @@ impl ConsumingBuilder for GroupBuilder {
             None => Bounds::Unchecked(BoundingBox::new_unbounded()),
         };
 
-        let group = Group {
+        Ok(Group {
             frame_transformation,
             objects,
             bounds,
-        };
-        group
+        })
+    }
+}
+
+impl Buildable for Group {
+    type Builder = GroupBuilder;
+
+    fn builder() -> Self::Builder {
+        GroupBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for GroupBuilder {
+    type Built = Group;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Group {
-    fn into(self) -> Shape {
-        Shape::Group(self)
+impl From<Group> for Shape {
+    fn from(group: Group) -> Shape {
+        Shape::Group(group)
     }
 }
 
@@ -145,6 +173,28 @@ mod tests {
         assert_eq!(shape, &resulting_shape as &dyn PrimitiveShape);
     }
 
+    #[test]
+    fn intersections_accumulate_in_one_register() {
+        let far: Shape = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.0, 3.0)))
+            .build_into();
+        let near = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.0, -3.0)))
+            .build_into();
+        let group: Shape = Group::builder().set_objects(vec![near]).build_into();
+        let ray = Ray::new(Point::new(0.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+
+        let outer = Transform::new(TransformKind::Identity);
+        let mut transform_stack = vec![&outer];
+        let mut hit_register = HitRegister::empty();
+        far.intersect_ray_into(&ray, &mut transform_stack, &mut hit_register);
+        group.intersect_ray_into(&ray, &mut transform_stack, &mut hit_register);
+        assert_eq!(transform_stack, vec![&outer]);
+
+        let hit = hit_register.finalise_hit().unwrap();
+        assert_eq!(hit.t(), 6.0);
+    }
+
     #[test]
     fn intersect_transformed_group() {
         let s1 = Sphere::builder()
@@ -182,7 +232,7 @@ mod tests {
         let g1 = Group::builder()
             .set_frame_transformation(Transform::new(TransformKind::Rotate(
                 Axis::Y,
-                Angle::from_radians(std::f64::consts::FRAC_PI_2),
+                Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             )))
             .set_objects(vec![g2])
             .build();
@@ -192,7 +242,7 @@ mod tests {
         let transform_stack = computed_intersect.transform_stack();
         let t1 = Transform::new(TransformKind::Rotate(
             Axis::Y,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
         ));
         let t2 = Transform::new(TransformKind::Scale(2.0, 2.0, 2.0));
         let t3 = Transform::new(TransformKind::Translate(5.0, 0.0, 0.0));
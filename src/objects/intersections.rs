@@ -1,27 +1,41 @@
 use std::marker::PhantomData;
 
+use smallvec::SmallVec;
+
+use super::Ray;
+use super::{LightSample, Material};
 use crate::collections::{Colour, Point, Vector};
-use crate::objects::{PrimitiveShape, Transform};
+use crate::objects::{
+    transform_through_stack_backwards, transform_through_stack_forwards, PrimitiveShape, Sidedness,
+    Transform,
+};
 use crate::utils::floats::EPSILON;
+use crate::utils::Float;
 
-use super::Light;
-use super::Ray;
+// A primitive's hits along one ray. No primitive is hit more than four times
+// (a capped cylinder or cone at most), so these never reach the heap.
+pub type LocalHits = SmallVec<[Coordinates; 4]>;
+
+// The transforms from the world down to a hit primitive, outermost first,
+// copied into each intersect. Shapes are seldom nested more than four deep, so
+// the copy is usually inline rather than an allocation per hit.
+pub type TransformStack<'ray> = SmallVec<[&'ray Transform; 4]>;
 
 pub struct Coordinates {
-    t: f64,
-    uv_coordinates: Option<(f64, f64)>,
+    t: Float,
+    uv_coordinates: Option<(Float, Float)>,
 }
 
 impl Coordinates {
-    pub fn new(t: f64, uv_coordinates: Option<(f64, f64)>) -> Coordinates {
+    pub fn new(t: Float, uv_coordinates: Option<(Float, Float)>) -> Coordinates {
         Coordinates { t, uv_coordinates }
     }
 
-    pub fn t(&self) -> f64 {
+    pub fn t(&self) -> Float {
         self.t
     }
 
-    pub fn uv_coordinates(&self) -> Option<(f64, f64)> {
+    pub fn uv_coordinates(&self) -> Option<(Float, Float)> {
         self.uv_coordinates
     }
 
@@ -29,7 +43,7 @@ impl Coordinates {
         self,
         object: &'ray S,
         ray: &'ray Ray,
-        transform_stack: Vec<&'ray Transform>,
+        transform_stack: &[&'ray Transform],
     ) -> Intersect<'ray, S, Raw>
     where
         S: PrimitiveShape + ?Sized,
@@ -61,11 +75,11 @@ where
     ItxState: IntersectState,
 {
     state: PhantomData<ItxState>,
-    t: f64,
+    t: Float,
     object: &'ray S,
     ray: &'ray Ray,
-    uv_coordinates: Option<(f64, f64)>,
-    transform_stack: Vec<&'ray Transform>,
+    uv_coordinates: Option<(Float, Float)>,
+    transform_stack: TransformStack<'ray>,
     computations: Option<Box<Computations>>,
 }
 
@@ -74,7 +88,7 @@ where
     S: PrimitiveShape + ?Sized,
     ItxState: IntersectState,
 {
-    pub fn t(&self) -> f64 {
+    pub fn t(&self) -> Float {
         self.t
     }
 
@@ -86,13 +100,27 @@ where
         self.ray
     }
 
-    pub fn uv_coordinates(&self) -> Option<(f64, f64)> {
+    pub fn uv_coordinates(&self) -> Option<(Float, Float)> {
         self.uv_coordinates
     }
 
-    pub fn transform_stack(&self) -> &Vec<&'ray Transform> {
+    pub fn transform_stack(&self) -> &[&'ray Transform] {
         &self.transform_stack
     }
+
+    // hits on the back of single sided surfaces do not count
+    fn is_culled(&self) -> bool {
+        self.object.material().sidedness == Sidedness::SingleSided
+            && self
+                .object
+                .normal_at(
+                    self.ray.position(self.t),
+                    self.uv_coordinates,
+                    &self.transform_stack,
+                )
+                .dot(-self.ray.direction)
+                < 0.0
+    }
 }
 
 impl<'ray, S> Intersect<'ray, S, Raw>
@@ -100,11 +128,11 @@ where
     S: PrimitiveShape + ?Sized,
 {
     pub fn new(
-        t: f64,
+        t: Float,
         object: &'ray S,
         ray: &'ray Ray,
-        uv_coordinates: Option<(f64, f64)>,
-        transform_stack: Vec<&'ray Transform>,
+        uv_coordinates: Option<(Float, Float)>,
+        transform_stack: &[&'ray Transform],
     ) -> Intersect<'ray, S, Raw> {
         Intersect {
             state: PhantomData,
@@ -112,12 +140,12 @@ where
             object,
             ray,
             uv_coordinates,
-            transform_stack,
+            transform_stack: SmallVec::from_slice(transform_stack),
             computations: None,
         }
     }
 
-    fn compute(self, refraction_boundary: (f64, f64)) -> Intersect<'ray, S, Computed> {
+    fn compute(self, refraction_boundary: (Float, Float)) -> Intersect<'ray, S, Computed> {
         let Intersect {
             t,
             object,
@@ -138,11 +166,27 @@ where
             _ => panic!(),
         };
         let over_point = target + normal * EPSILON;
+        let object_point = transform_through_stack_forwards(over_point, &transform_stack);
         let under_point = target - normal * EPSILON;
-        let reflected_ray = Ray::new(over_point, ray.direction.reflect(normal));
+        // offset points stay on the true surface, only shading sees the bumps
+        if let Some(bump_map) = &object.material().bump_map {
+            let gradient = bump_map.gradient_at(object_point);
+            let gradient = transform_through_stack_backwards(gradient, &transform_stack);
+            let tangential = gradient - normal * gradient.dot(normal);
+            normal = if inside {
+                normal + tangential
+            } else {
+                normal - tangential
+            }
+            .normalise();
+        }
+        let reflected_ray = Ray::new(over_point, ray.direction.reflect(normal))
+            .with_wavelength(ray.wavelength)
+            .with_time(ray.time);
 
         let computations = Some(Box::new(Computations {
             target,
+            object_point,
             eyev,
             normal,
             inside,
@@ -150,6 +194,8 @@ where
             under_point,
             reflected_ray,
             refraction_boundary,
+            wavelength: ray.wavelength,
+            time: ray.time,
         }));
         Intersect {
             state: PhantomData,
@@ -166,13 +212,16 @@ where
 #[derive(Clone, Copy, Debug)]
 pub struct Computations {
     target: Point,
+    object_point: Point,
     eyev: Vector,
     normal: Vector,
     inside: bool,
     over_point: Point,
     under_point: Point,
     reflected_ray: Ray,
-    refraction_boundary: (f64, f64),
+    refraction_boundary: (Float, Float),
+    wavelength: Option<Float>,
+    time: Float,
 }
 
 impl Computations {
@@ -180,6 +229,12 @@ impl Computations {
         self.target
     }
 
+    // the over point in the hit object's own space, where its material's
+    // pattern is evaluated
+    pub fn object_point(&self) -> Point {
+        self.object_point
+    }
+
     pub fn eyev(&self) -> Vector {
         self.eyev
     }
@@ -204,16 +259,99 @@ impl Computations {
         self.reflected_ray
     }
 
-    pub fn refraction_boundary(&self) -> (f64, f64) {
+    pub fn refraction_boundary(&self) -> (Float, Float) {
         self.refraction_boundary
     }
+
+    // the ray carrying on through the surface, bent as it crosses the
+    // refraction boundary; None under total internal reflection
+    pub fn refracted_ray(&self) -> Option<Ray> {
+        let (n1, n2) = self.refraction_boundary;
+        let n_ratio = n1 / n2;
+        let cos_i = self.eyev.dot(self.normal);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let refracted_direction = self.normal * (n_ratio * cos_i - cos_t) - self.eyev * n_ratio;
+        Some(
+            Ray::new(self.under_point, refracted_direction)
+                .with_wavelength(self.wavelength)
+                .with_time(self.time),
+        )
+    }
+
+    // of the ray that hit, in nanometres, if rendering spectrally
+    pub fn wavelength(&self) -> Option<Float> {
+        self.wavelength
+    }
+
+    // of the ray that hit
+    pub fn time(&self) -> Float {
+        self.time
+    }
+
+    pub(crate) fn shade(&self, material: &Material, light: &LightSample, shadowed: bool) -> Colour {
+        if material.metallic_roughness.is_some() {
+            return light.shade_metallic_roughness(
+                material,
+                self.object_point,
+                self.eyev,
+                self.normal,
+                shadowed,
+            );
+        }
+        light.shade_phong(
+            material,
+            self.object_point,
+            self.eyev,
+            self.normal,
+            shadowed,
+        )
+    }
+
+    pub(crate) fn shade_toon(
+        &self,
+        material: &Material,
+        light: &LightSample,
+        shadowed: bool,
+        bands: usize,
+    ) -> Colour {
+        light.shade_toon(material, self.object_point, self.normal, shadowed, bands)
+    }
+
+    pub(crate) fn shade_indirect(&self, material: &Material, irradiance: Colour) -> Colour {
+        material.pattern.colour_at(self.object_point) * irradiance * material.diffuse
+    }
+
+    pub(crate) fn schlick_reflectance(&self) -> Float {
+        let (n1, n2) = self.refraction_boundary;
+        let mut cos = self.eyev.dot(self.normal);
+
+        if n1 > n2 {
+            let n = n1 / n2;
+            let sin2_t = n.powi(2) * (1.0 - cos.powi(2));
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+
+            let cos_t = (1.0 - sin2_t).sqrt();
+
+            cos = cos_t
+        }
+
+        let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
 }
 
 impl<'ray, S> Intersect<'ray, S, Computed>
 where
     S: PrimitiveShape + ?Sized,
 {
-    fn computations(&self) -> &Computations {
+    pub fn computations(&self) -> &Computations {
         self.computations.as_ref().unwrap()
     }
 
@@ -245,39 +383,9 @@ where
         self.computations().reflected_ray()
     }
 
-    pub fn refraction_boundary(&self) -> (f64, f64) {
+    pub fn refraction_boundary(&self) -> (Float, Float) {
         self.computations().refraction_boundary()
     }
-
-    pub(crate) fn shade(&self, light: &Light, shadowed: bool) -> Colour {
-        light.shade_phong(
-            self.object().material(),
-            self.over_point(),
-            self.eyev(),
-            self.normal(),
-            shadowed,
-        )
-    }
-
-    pub(crate) fn schlick_reflectance(&self) -> f64 {
-        let (n1, n2) = self.refraction_boundary();
-        let mut cos = self.eyev().dot(self.normal());
-
-        if n1 > n2 {
-            let n = n1 / n2;
-            let sin2_t = n.powi(2) * (1.0 - cos.powi(2));
-            if sin2_t > 1.0 {
-                return 1.0;
-            }
-
-            let cos_t = (1.0 - sin2_t).sqrt();
-
-            cos = cos_t
-        }
-
-        let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
-        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
-    }
 }
 
 #[derive(Clone, Debug)]
@@ -303,13 +411,46 @@ where
 
     pub fn finalise_hit(mut self) -> Option<Intersect<'ray, S, Computed>> {
         self.sort_intersections_by_t();
-        match self.0.iter().position(|itx| itx.t >= 0.0) {
-            Some(idx_hit) => {
-                let refraction_boundary = self.compute_refraction_boundary(idx_hit);
-                Some(self.0.swap_remove(idx_hit).compute(refraction_boundary))
+        let media = self.walk_media();
+        let idx_hit = self.0.iter().zip(&media).position(|(itx, &(is_true, _))| {
+            itx.ray().in_range(itx.t) && is_true && !itx.is_culled()
+        })?;
+        let (_, refraction_boundary) = media[idx_hit];
+        Some(self.0.swap_remove(idx_hit).compute(refraction_boundary))
+    }
+
+    // drops the intersections with objects that cast no shadows, for
+    // shadow rays
+    pub(crate) fn shadow_casters(mut self) -> HitRegister<'ray, S> {
+        self.0
+            .retain(|intersect| intersect.object().material().casts_shadow);
+        self
+    }
+
+    // the fraction of light let through by the surfaces in the ray's range,
+    // for shadow rays: none if any of them is opaque, otherwise the product
+    // of their objects' transparencies, each object counted once however many
+    // of its surfaces are crossed
+    pub(crate) fn transmission(mut self) -> Float {
+        self.sort_intersections_by_t();
+        let media = self.walk_media();
+        let mut crossed: Vec<*const ()> = vec![];
+        let mut transmission = 1.0;
+        for (itx, &(is_true, _)) in self.0.iter().zip(&media) {
+            if !itx.ray().in_range(itx.t) || !is_true || itx.is_culled() {
+                continue;
+            }
+            let transparency = itx.object().material().transparency;
+            if transparency <= 0.0 {
+                return 0.0;
+            }
+            let object = itx.object() as *const S as *const ();
+            if !crossed.contains(&object) {
+                crossed.push(object);
+                transmission *= transparency;
             }
-            None => None,
         }
+        transmission
     }
 
     pub fn expose(mut self) -> Vec<Intersect<'ray, S, Raw>> {
@@ -321,32 +462,55 @@ where
         self.0.sort_by(|a, b| a.t().partial_cmp(&b.t()).unwrap());
     }
 
-    fn compute_refraction_boundary(&self, idx_hit: usize) -> (f64, f64) {
+    #[cfg(test)]
+    fn compute_refraction_boundary(&self, idx_hit: usize) -> (Float, Float) {
         assert!(idx_hit < self.0.len());
+        self.walk_media()[idx_hit].1
+    }
+
+    // Follows the ray through nested media, returning for each (sorted)
+    // intersection whether it is a true boundary, and the refractive indices
+    // on either side. Where objects overlap, the one with the highest medium
+    // priority fills the overlap (the most recently entered on ties), so
+    // surfaces of lower priority objects inside it are false boundaries and
+    // are skipped, e.g. the water surface inside a partly submerged ice cube.
+    // Surfaces that are not solid enclose nothing, so never change the medium.
+    fn walk_media(&self) -> Vec<(bool, (Float, Float))> {
+        fn refractive_index<S: PrimitiveShape + ?Sized>(
+            in_objects: &[&S],
+            wavelength: Option<Float>,
+        ) -> Float {
+            in_objects
+                .iter()
+                .max_by_key(|object| object.material().medium_priority)
+                .map_or(1.0, |object| {
+                    object.material().refractive_index_at(wavelength)
+                })
+        }
 
         let mut in_objects: Vec<&S> = vec![];
+        let mut media = Vec::with_capacity(self.0.len());
+
+        for current_intersect in &self.0 {
+            let object = current_intersect.object();
+            let wavelength = current_intersect.ray().wavelength;
+            let n1 = refractive_index(&in_objects, wavelength);
+            if object.material().sidedness != Sidedness::Solid {
+                media.push((true, (n1, n1)));
+                continue;
+            }
 
-        for (idx_current_intersect, current_intersect) in self.0.iter().enumerate() {
-            if idx_current_intersect == idx_hit {
-                let n1 = match in_objects.last() {
-                    Some(last_object) => last_object.material().refractive_index,
-                    None => 1.0,
-                };
-
-                HitRegister::update_containers(&mut in_objects, current_intersect);
-
-                let n2 = match in_objects.last() {
-                    Some(last_object) => last_object.material().refractive_index,
-                    None => 1.0,
-                };
+            let is_true = in_objects
+                .iter()
+                .filter(|&&other| other != object)
+                .all(|other| object.material().medium_priority >= other.material().medium_priority);
 
-                return (n1, n2);
-            } else {
-                HitRegister::update_containers(&mut in_objects, current_intersect);
-            }
+            HitRegister::update_containers(&mut in_objects, current_intersect);
+            let n2 = refractive_index(&in_objects, wavelength);
+            media.push((is_true, (n1, n2)));
         }
 
-        panic!();
+        media
     }
 
     fn update_containers<'tmp>(
@@ -381,15 +545,18 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::objects::{Material, Plane, Sphere, Transform, TransformKind};
+    use crate::objects::{
+        BumpMap, Group, Intersectable, Material, Plane, Shape, Sidedness, Sphere, Transform,
+        TransformKind,
+    };
     use crate::scenes::World;
-    use crate::utils::{BuildInto, Buildable, ConsumingBuilder};
+    use crate::utils::{approx_eq, BuildInto, Buildable, ConsumingBuilder};
 
     #[test]
     fn compute_intersect_ray_outside() {
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::builder().build();
-        let raw_intersect = Intersect::new(4.0, &shape, &ray, None, vec![]);
+        let raw_intersect = Intersect::new(4.0, &shape, &ray, None, &[]);
         let computed_intersect = raw_intersect.compute((0.0, 0.0));
         assert_eq!(computed_intersect.target(), Point::new(0.0, 0.0, -1.0));
         assert_eq!(computed_intersect.eyev(), Vector::new(0.0, 0.0, -1.0));
@@ -408,7 +575,7 @@ mod tests {
             .set_material(Material::preset())
             .build();
         let transform = Transform::new(TransformKind::Translate(0.0, 0.0, 1.0));
-        let raw_intersect = Intersect::new(5.0, &shape, &ray, None, vec![&transform]);
+        let raw_intersect = Intersect::new(5.0, &shape, &ray, None, &[&transform]);
         let computed_intersect = raw_intersect.compute((0.0, 0.0));
         assert!(computed_intersect.over_point().z < -EPSILON / 2.0);
         assert!(computed_intersect.target().z > computed_intersect.over_point().z);
@@ -420,7 +587,7 @@ mod tests {
     fn compute_intersect_ray_inside() {
         let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::builder().build();
-        let raw_intersect = Intersect::new(1.0, &shape, &ray, None, vec![]);
+        let raw_intersect = Intersect::new(1.0, &shape, &ray, None, &[]);
         let computed_intersect = raw_intersect.compute((0.0, 0.0));
         assert_eq!(computed_intersect.target(), Point::new(0.0, 0.0, 1.0));
         assert_eq!(computed_intersect.eyev(), Vector::new(0.0, 0.0, -1.0));
@@ -433,13 +600,13 @@ mod tests {
         let plane = Plane::builder().set_material(Material::preset()).build();
         let ray = Ray::new(
             Point::new(0.0, 1.0, -1.0),
-            Vector::new(0.0, -(2.0_f64.sqrt()) / 2.0, 2.0_f64.sqrt() / 2.0),
+            Vector::new(0.0, -(Float::sqrt(2.0)) / 2.0, Float::sqrt(2.0) / 2.0),
         );
-        let raw_intersect = Intersect::new(2.0_f64.sqrt() / 2.0, &plane, &ray, None, vec![]);
+        let raw_intersect = Intersect::new(Float::sqrt(2.0) / 2.0, &plane, &ray, None, &[]);
         let computed_intersect = raw_intersect.compute((0.0, 0.0));
         assert_eq!(
             computed_intersect.reflected_ray().direction,
-            Vector::new(0.0, 2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0)
+            Vector::new(0.0, Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0)
         );
     }
 
@@ -447,9 +614,9 @@ mod tests {
     fn hit_register_finalises_hit() {
         let sphere = Sphere::builder().build();
         let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
-        let intersect1 = Intersect::new(-1.0, &sphere, &ray, None, vec![]);
-        let intersect2 = Intersect::new(2.0, &sphere, &ray, None, vec![]);
-        let intersect3 = Intersect::new(3.0, &sphere, &ray, None, vec![]);
+        let intersect1 = Intersect::new(-1.0, &sphere, &ray, None, &[]);
+        let intersect2 = Intersect::new(2.0, &sphere, &ray, None, &[]);
+        let intersect3 = Intersect::new(3.0, &sphere, &ray, None, &[]);
         let hit_register = HitRegister::from(vec![intersect1, intersect2, intersect3]);
         let hit = hit_register.finalise_hit().unwrap();
         assert_eq!(hit.t(), 2.0);
@@ -487,7 +654,7 @@ mod tests {
         let mut hit_register = world.intersect_ray(&ray);
         hit_register.sort_intersections_by_t();
 
-        let test_cases: [(usize, f64, f64); 6] = [
+        let test_cases: [(usize, Float, Float); 6] = [
             (0, 1.0, 1.5),
             (1, 1.5, 2.0),
             (2, 2.0, 2.5),
@@ -500,4 +667,136 @@ mod tests {
             assert_eq!(refraction_boundary, (n1, n2), "{}", idx);
         }
     }
+
+    #[test]
+    fn thin_surfaces_do_not_change_the_medium() {
+        let glass = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)))
+            .set_material(Material::glass())
+            .build_into();
+        let pane = Sphere::builder()
+            .set_material(Material {
+                sidedness: Sidedness::Thin,
+                ..Material::glass()
+            })
+            .build_into();
+        let world = World::new(vec![glass, pane], vec![]);
+
+        let ray = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+        let mut hit_register = world.intersect_ray(&ray);
+        hit_register.sort_intersections_by_t();
+        assert_eq!(
+            hit_register.walk_media(),
+            vec![
+                (true, (1.0, 1.5)),
+                (true, (1.5, 1.5)),
+                (true, (1.5, 1.5)),
+                (true, (1.5, 1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_sided_surfaces_are_culled_from_behind() {
+        let material = Material {
+            sidedness: Sidedness::SingleSided,
+            ..Material::preset()
+        };
+        let floor = Plane::builder().set_material(material.clone()).build_into();
+        let ball = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 3.0, 0.0)))
+            .set_material(material)
+            .build_into();
+        let world = World::new(vec![floor, ball], vec![]);
+        let hit_along = |origin, direction| {
+            let ray = Ray::new(origin, direction);
+            world.intersect_ray(&ray).finalise_hit().map(|hit| hit.t())
+        };
+
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        assert_eq!(hit_along(Point::new(5.0, 1.0, 0.0), down), Some(1.0));
+        assert_eq!(hit_along(Point::new(5.0, -1.0, 0.0), up), None);
+        // from inside the ball its far side faces away, so the ray carries on
+        assert_eq!(hit_along(Point::new(0.0, 3.0, 0.0), down), Some(3.0));
+        assert_eq!(hit_along(Point::new(0.0, 6.0, 0.0), down), Some(2.0));
+    }
+
+    #[test]
+    fn lower_priority_media_inside_higher_are_skipped() {
+        let s1 = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)))
+            .set_material(Material {
+                transparency: 1.0,
+                refractive_index: 1.5,
+                medium_priority: 1,
+                ..Material::preset()
+            })
+            .build_into();
+        let s2 = Sphere::builder()
+            .set_material(Material {
+                transparency: 1.0,
+                refractive_index: 2.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let world = World::new(vec![s1, s2], vec![]);
+
+        let ray = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+        let mut hit_register = world.intersect_ray(&ray);
+        hit_register.sort_intersections_by_t();
+        let media = hit_register.walk_media();
+        assert_eq!(
+            media,
+            vec![
+                (true, (1.0, 1.5)),
+                (false, (1.5, 1.5)),
+                (false, (1.5, 1.5)),
+                (true, (1.5, 1.0)),
+            ]
+        );
+
+        // from inside both, the inner sphere's surface is not a hit
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = world.intersect_ray(&ray).finalise_hit().unwrap();
+        assert_eq!(hit.t(), 2.0);
+        assert_eq!(hit.refraction_boundary(), (1.5, 1.0));
+    }
+
+    #[test]
+    fn bump_map_tilts_shading_normal() {
+        let plane = Plane::builder()
+            .set_material(Material {
+                bump_map: Some(BumpMap::new(|point| 0.5 * point.x)),
+                ..Material::preset()
+            })
+            .build();
+        let ray = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let hit = Intersect::new(1.0, &plane, &ray, None, &[plane.frame_transformation()])
+            .compute((1.0, 1.0));
+        let computations = hit.computations();
+
+        // rising towards +x, so the normal leans towards -x
+        let normal = computations.normal();
+        approx_eq!(normal.x, -1.0 / Float::sqrt(5.0));
+        approx_eq!(normal.y, 2.0 / Float::sqrt(5.0));
+        approx_eq!(normal.z, 0.0);
+        // but the offset points stay along the true normal
+        assert_eq!(computations.over_point(), Point::new(0.0, EPSILON, 0.0));
+        approx_eq!(computations.reflected_ray().direction.x, -0.8);
+    }
+
+    #[test]
+    fn nested_transform_stacks_are_kept_inline() {
+        let sphere: Shape = Sphere::builder().build_into();
+        let inner: Shape = Group::builder().set_objects(vec![sphere]).build_into();
+        let outer: Shape = Group::builder().set_objects(vec![inner]).build_into();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersects = outer.intersect_ray(&ray, vec![]).expose();
+        assert_eq!(intersects.len(), 2);
+        for intersect in intersects {
+            assert_eq!(intersect.transform_stack().len(), 3);
+            assert!(!intersect.transform_stack.spilled());
+        }
+    }
 }
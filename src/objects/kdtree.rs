@@ -0,0 +1,535 @@
+use crate::collections::LANES;
+use crate::objects::*;
+use crate::scenes::{count, Counter};
+use crate::utils::{Buildable, ConsumingBuilder, Float, EPSILON};
+
+#[derive(Clone, Debug)]
+enum KdNode {
+    Leaf(Vec<usize>),
+    Split {
+        axis: usize,
+        position: Float,
+        below: Box<KdNode>,
+        above: Box<KdNode>,
+    },
+}
+
+// A container that partitions its objects with a kd-tree, as an alternative
+// to nesting Groups by hand. Split planes are chosen by the surface area
+// heuristic over the objects' bounding boxes; objects straddling a plane are
+// kept on both sides, and are only ever intersected once per ray.
+//
+// Objects without a finite bounding box (e.g. planes) cannot be partitioned,
+// and are tested against every ray.
+//
+// Every intersection along the ray's line is still reported (not just the
+// nearest), as refraction needs the surfaces behind the hit.
+#[derive(Clone, Debug)]
+pub struct KdTree {
+    objects: Vec<Shape>,
+    unbounded: Vec<usize>,
+    root: KdNode,
+    root_box: Option<BoundingBox>,
+    bounds: Bounds,
+}
+
+impl KdTree {
+    // relative costs for the surface area heuristic
+    const TRAVERSAL_COST: Float = 1.0;
+    const INTERSECTION_COST: Float = 2.0;
+
+    pub fn objects(&self) -> &Vec<Shape> {
+        &self.objects
+    }
+
+    pub fn depth(&self) -> usize {
+        fn depth(node: &KdNode) -> usize {
+            match node {
+                KdNode::Leaf(_) => 0,
+                KdNode::Split { below, above, .. } => 1 + depth(below).max(depth(above)),
+            }
+        }
+        depth(&self.root)
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        fn leaf_count(node: &KdNode) -> usize {
+            match node {
+                KdNode::Leaf(_) => 1,
+                KdNode::Split { below, above, .. } => leaf_count(below) + leaf_count(above),
+            }
+        }
+        leaf_count(&self.root)
+    }
+
+    fn surface_area(bbox: &BoundingBox) -> Float {
+        let (x_range, y_range, z_range) = bbox.axial_bounds();
+        let [dx, dy, dz] = [x_range, y_range, z_range].map(|range| range[1] - range[0]);
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    fn ranges(bbox: &BoundingBox) -> [[Float; 2]; 3] {
+        let (x_range, y_range, z_range) = bbox.axial_bounds();
+        [x_range, y_range, z_range]
+    }
+
+    fn split_box(bbox: &BoundingBox, axis: usize, position: Float) -> [BoundingBox; 2] {
+        let mut below = KdTree::ranges(bbox);
+        let mut above = below;
+        below[axis][1] = position;
+        above[axis][0] = position;
+        [below, above].map(|[x_range, y_range, z_range]| {
+            BoundingBox::from_axial_bounds(x_range, y_range, z_range)
+        })
+    }
+
+    fn build_node(
+        boxes: &[[[Float; 2]; 3]],
+        indices: Vec<usize>,
+        node_box: BoundingBox,
+        depth_remaining: usize,
+    ) -> KdNode {
+        let node_area = KdTree::surface_area(&node_box);
+        if indices.len() <= 1 || depth_remaining == 0 || node_area <= 0.0 {
+            return KdNode::Leaf(indices);
+        }
+
+        let node_ranges = KdTree::ranges(&node_box);
+        let leaf_cost = KdTree::INTERSECTION_COST * indices.len() as Float;
+        let mut best: Option<(Float, usize, Float)> = None;
+
+        for (axis, node_range) in node_ranges.iter().enumerate() {
+            // sweep over the box edges along this axis; starts sort before ends
+            // at the same position, so touching boxes are counted on both sides
+            let mut edges: Vec<(Float, bool)> = indices
+                .iter()
+                .flat_map(|&idx| [(boxes[idx][axis][0], true), (boxes[idx][axis][1], false)])
+                .collect();
+            edges.sort_by(|(pos_a, start_a), (pos_b, start_b)| {
+                pos_a.total_cmp(pos_b).then(start_b.cmp(start_a))
+            });
+
+            let mut count_below = 0;
+            let mut count_above = indices.len();
+            for (position, is_start) in edges {
+                if !is_start {
+                    count_above -= 1;
+                }
+                if position > node_range[0] && position < node_range[1] {
+                    let [below_box, above_box] = KdTree::split_box(&node_box, axis, position);
+                    let cost = KdTree::TRAVERSAL_COST
+                        + KdTree::INTERSECTION_COST
+                            * (KdTree::surface_area(&below_box) * count_below as Float
+                                + KdTree::surface_area(&above_box) * count_above as Float)
+                            / node_area;
+                    if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                        best = Some((cost, axis, position));
+                    }
+                }
+                if is_start {
+                    count_below += 1;
+                }
+            }
+        }
+
+        let (axis, position) = match best {
+            Some((cost, axis, position)) if cost < leaf_cost => (axis, position),
+            _ => return KdNode::Leaf(indices),
+        };
+
+        let (below, above): (Vec<usize>, Vec<usize>) = (
+            indices
+                .iter()
+                .copied()
+                .filter(|&idx| boxes[idx][axis][0] < position || boxes[idx][axis][1] <= position)
+                .collect(),
+            indices
+                .iter()
+                .copied()
+                .filter(|&idx| boxes[idx][axis][1] > position)
+                .collect(),
+        );
+        if below.len() == indices.len() && above.len() == indices.len() {
+            return KdNode::Leaf(indices);
+        }
+
+        let [below_box, above_box] = KdTree::split_box(&node_box, axis, position);
+        KdNode::Split {
+            axis,
+            position,
+            below: Box::new(KdTree::build_node(
+                boxes,
+                below,
+                below_box,
+                depth_remaining - 1,
+            )),
+            above: Box::new(KdTree::build_node(
+                boxes,
+                above,
+                above_box,
+                depth_remaining - 1,
+            )),
+        }
+    }
+
+    // the parameter range over which the (whole) line of the ray is in the box
+    fn line_interval(bbox: &BoundingBox, ray: &Ray) -> Option<[Float; 2]> {
+        let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+        let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+        let mut interval = [Float::NEG_INFINITY, Float::INFINITY];
+
+        for (axis, range) in KdTree::ranges(bbox).into_iter().enumerate() {
+            if direction[axis] == 0.0 {
+                if origin[axis] < range[0] || origin[axis] > range[1] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (range[0] - origin[axis]) / direction[axis];
+            let t1 = (range[1] - origin[axis]) / direction[axis];
+            interval[0] = interval[0].max(t0.min(t1));
+            interval[1] = interval[1].min(t0.max(t1));
+        }
+
+        (interval[0] <= interval[1]).then_some(interval)
+    }
+
+    // whether each object may be hit by the ray, in the space of the
+    // containing frame
+    fn candidates(&self, ray: &Ray) -> Vec<bool> {
+        let mut seen = vec![false; self.objects.len()];
+        for &idx in &self.unbounded {
+            seen[idx] = true;
+        }
+
+        if let Some(root_box) = &self.root_box {
+            if let Some([t0, t1]) = KdTree::line_interval(root_box, ray) {
+                // only objects where the line is in the ray's range can be hit
+                let interval = [t0.max(ray.t_min), t1.min(ray.t_max)];
+                if interval[0] <= interval[1] {
+                    self.collect_candidates(&self.root, ray, interval, &mut seen);
+                }
+            }
+        }
+        seen
+    }
+
+    // see Shape::intersect_packet_into; each ray finds its own way down the
+    // tree, then every candidate is intersected with the rays that found it
+    // together
+    pub(crate) fn intersect_packet_into<'world: 'ray, 'ray>(
+        &'world self,
+        packet: &'ray RayPacket,
+        local_packet: &RayPacket,
+        lanes: [bool; LANES],
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_registers: &mut [HitRegister<'ray, dyn PrimitiveShape>],
+    ) {
+        let candidates: [Option<Vec<bool>>; LANES] = std::array::from_fn(|idx| {
+            lanes[idx].then(|| self.candidates(&local_packet.rays()[idx]))
+        });
+        for (idx, shape) in self.objects.iter().enumerate() {
+            let shape_lanes: [bool; LANES] =
+                std::array::from_fn(|lane| candidates[lane].as_ref().is_some_and(|seen| seen[idx]));
+            if shape_lanes.contains(&true) {
+                shape.intersect_packet_into(
+                    packet,
+                    local_packet,
+                    shape_lanes,
+                    transform_stack,
+                    hit_registers,
+                );
+            }
+        }
+    }
+
+    fn collect_candidates(
+        &self,
+        node: &KdNode,
+        ray: &Ray,
+        interval: [Float; 2],
+        seen: &mut [bool],
+    ) {
+        count(Counter::KdTreeVisit);
+        match node {
+            KdNode::Leaf(indices) => {
+                for &idx in indices {
+                    seen[idx] = true;
+                }
+            }
+            KdNode::Split {
+                axis,
+                position,
+                below,
+                above,
+            } => {
+                let origin = [ray.origin.x, ray.origin.y, ray.origin.z][*axis];
+                let direction = [ray.direction.x, ray.direction.y, ray.direction.z][*axis];
+
+                if direction == 0.0 {
+                    if origin <= *position {
+                        self.collect_candidates(below, ray, interval, seen);
+                    }
+                    if origin >= *position {
+                        self.collect_candidates(above, ray, interval, seen);
+                    }
+                    return;
+                }
+
+                let t_split = (position - origin) / direction;
+                // the parts of the interval before and after crossing the plane
+                let before = [interval[0], interval[1].min(t_split)];
+                let after = [interval[0].max(t_split), interval[1]];
+                let (below_interval, above_interval) = if direction > 0.0 {
+                    (before, after)
+                } else {
+                    (after, before)
+                };
+
+                if below_interval[0] <= below_interval[1] {
+                    self.collect_candidates(below, ray, below_interval, seen);
+                }
+                if above_interval[0] <= above_interval[1] {
+                    self.collect_candidates(above, ray, above_interval, seen);
+                }
+            }
+        }
+    }
+}
+
+impl Intersectable<dyn PrimitiveShape> for KdTree {
+    fn intersect_ray_into<'world: 'ray, 'ray>(
+        &'world self,
+        world_ray: &'ray Ray,
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
+        // object bounds are in the space of the containing frame
+        let ray = transform_through_stack_forwards(*world_ray, transform_stack);
+        let seen = self.candidates(&ray);
+        for (shape, _) in self.objects.iter().zip(seen).filter(|(_, seen)| *seen) {
+            shape.intersect_ray_into(world_ray, transform_stack, hit_register);
+        }
+    }
+}
+
+impl Bounded for KdTree {
+    fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KdTreeBuilder {
+    objects: Option<Vec<Shape>>,
+    max_depth: Option<usize>,
+}
+
+impl KdTreeBuilder {
+    pub fn set_objects(mut self, objects: Vec<Shape>) -> KdTreeBuilder {
+        self.objects = Some(objects);
+        self
+    }
+
+    pub fn add_object(mut self, object: Shape) -> KdTreeBuilder {
+        match self.objects {
+            Some(ref mut objects) => {
+                objects.push(object);
+            }
+            None => self.objects = Some(vec![object]),
+        }
+        self
+    }
+
+    // defaults to 8 + 1.3 log2(n) for n objects
+    pub fn set_max_depth(mut self, max_depth: usize) -> KdTreeBuilder {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+impl Buildable for KdTree {
+    type Builder = KdTreeBuilder;
+
+    fn builder() -> Self::Builder {
+        KdTreeBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for KdTreeBuilder {
+    type Built = KdTree;
+
+    fn build(self) -> Self::Built {
+        let objects = self.objects.unwrap_or_default();
+        let bounds = match objects
+            .iter()
+            .map(|object| object.bounds().bounding_box())
+            .reduce(|bbox_a, bbox_b| bbox_a + bbox_b)
+        {
+            Some(bbox) => Bounds::Checked(bbox),
+            None => Bounds::Unchecked(BoundingBox::new_unbounded()),
+        };
+
+        let boxes: Vec<[[Float; 2]; 3]> = objects
+            .iter()
+            .map(|object| KdTree::ranges(&object.bounds().bounding_box()))
+            .collect();
+        let (bounded, unbounded): (Vec<usize>, Vec<usize>) = (0..objects.len())
+            .partition(|&idx| boxes[idx].iter().flatten().all(|bound| bound.is_finite()));
+
+        // padded slightly, so rays grazing the outermost faces are not lost
+        let root_box = bounded
+            .iter()
+            .map(|&idx| {
+                let [x_range, y_range, z_range] = boxes[idx];
+                BoundingBox::from_axial_bounds(x_range, y_range, z_range)
+            })
+            .reduce(|bbox_a, bbox_b| bbox_a + bbox_b)
+            .map(|bbox| {
+                let [x_range, y_range, z_range] =
+                    KdTree::ranges(&bbox).map(|[min, max]| [min - EPSILON, max + EPSILON]);
+                BoundingBox::from_axial_bounds(x_range, y_range, z_range)
+            });
+
+        let max_depth = self.max_depth.unwrap_or_else(|| {
+            (8.0 + 1.3 * (bounded.len().max(1) as Float).log2()).round() as usize
+        });
+        let root = match &root_box {
+            Some(root_box) => KdTree::build_node(&boxes, bounded, *root_box, max_depth),
+            None => KdNode::Leaf(vec![]),
+        };
+
+        KdTree {
+            objects,
+            unbounded,
+            root,
+            root_box,
+            bounds,
+        }
+    }
+}
+
+impl From<KdTree> for Shape {
+    fn from(kd_tree: KdTree) -> Shape {
+        Shape::KdTree(kd_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::{Point, Vector};
+    use crate::utils::BuildInto;
+
+    fn sphere_grid() -> Vec<Shape> {
+        let mut spheres = vec![];
+        for idx_x in 0..6 {
+            for idx_y in 0..6 {
+                spheres.push(
+                    Sphere::builder()
+                        .set_frame_transformation(Transform::new(TransformKind::Translate(
+                            idx_x as Float * 3.0,
+                            idx_y as Float * 3.0,
+                            (idx_x + idx_y) as Float,
+                        )))
+                        .build_into(),
+                );
+            }
+        }
+        spheres
+    }
+
+    fn hit_ts(shape: &Shape, ray: &Ray) -> Vec<Float> {
+        let mut ts: Vec<Float> = shape
+            .intersect_ray(ray, vec![])
+            .expose()
+            .iter()
+            .map(|hit| hit.t())
+            .collect();
+        ts.sort_by(Float::total_cmp);
+        ts
+    }
+
+    #[test]
+    fn sah_splits_spread_objects() {
+        let kd_tree = KdTree::builder().set_objects(sphere_grid()).build();
+        assert!(kd_tree.depth() > 0);
+        assert!(kd_tree.leaf_count() > 1);
+    }
+
+    #[test]
+    fn single_object_is_a_leaf() {
+        let kd_tree = KdTree::builder()
+            .add_object(Sphere::builder().build_into())
+            .build();
+        assert_eq!(kd_tree.depth(), 0);
+        assert_eq!(kd_tree.leaf_count(), 1);
+    }
+
+    #[test]
+    fn intersections_match_group() {
+        let kd_tree: Shape = KdTree::builder().set_objects(sphere_grid()).build_into();
+        let group: Shape = Group::builder().set_objects(sphere_grid()).build_into();
+
+        let rays = [
+            Ray::new(Point::new(0.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-5.0, -5.0, -5.0), Vector::new(1.0, 1.0, 0.5)),
+            Ray::new(Point::new(-5.0, 3.0, 3.0), Vector::new(1.0, 0.0, 0.0)),
+            Ray::new(Point::new(7.5, 7.5, 50.0), Vector::new(0.0, 0.0, -1.0)),
+            Ray::new(Point::new(6.0, 6.0, 6.0), Vector::new(0.3, -0.2, 0.1)),
+            Ray::new(Point::new(100.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        ];
+        for ray in rays {
+            assert_eq!(hit_ts(&kd_tree, &ray), hit_ts(&group, &ray));
+        }
+    }
+
+    #[test]
+    fn unbounded_objects_always_tested() {
+        let kd_tree: Shape = KdTree::builder()
+            .set_objects(sphere_grid())
+            .add_object(
+                Plane::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Translate(
+                        0.0, -10.0, 0.0,
+                    )))
+                    .build_into(),
+            )
+            .build_into();
+        let ray = Ray::new(Point::new(100.0, 0.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(hit_ts(&kd_tree, &ray), vec![10.0]);
+    }
+
+    #[test]
+    fn packets_match_single_rays() {
+        let kd_tree: Shape = KdTree::builder().set_objects(sphere_grid()).build_into();
+        let group: Shape = Group::builder()
+            .set_frame_transformation(Transform::from(vec![
+                TransformKind::Scale(0.5, 0.5, 0.5),
+                TransformKind::Translate(1.0, -2.0, 0.0),
+            ]))
+            .set_objects(vec![kd_tree])
+            .build_into();
+
+        let rays = [
+            Ray::new(Point::new(1.0, -2.0, -10.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-2.0, -5.0, -5.0), Vector::new(1.0, 1.0, 0.5)),
+            Ray::new(Point::new(-5.0, -0.5, 1.5), Vector::new(1.0, 0.0, 0.0)),
+            Ray::new(Point::new(4.0, 1.0, 50.0), Vector::new(0.0, 0.0, -1.0)),
+            Ray::new(Point::new(4.0, 1.0, 2.0), Vector::new(0.3, -0.2, 0.1)),
+            Ray::new(Point::new(100.0, 2.5, 2.0), Vector::new(-1.0, 0.0, 0.0)),
+        ];
+        for packet in RayPacket::packets(&rays) {
+            let mut hit_registers: Vec<_> =
+                packet.rays().iter().map(|_| HitRegister::empty()).collect();
+            let lanes = std::array::from_fn(|idx| idx < packet.rays().len());
+            group.intersect_packet_into(&packet, &packet, lanes, &mut vec![], &mut hit_registers);
+
+            for (hit_register, ray) in hit_registers.into_iter().zip(packet.rays()) {
+                let mut ts: Vec<Float> = hit_register.expose().iter().map(|hit| hit.t()).collect();
+                ts.sort_by(Float::total_cmp);
+                assert_eq!(ts, hit_ts(&group, ray));
+            }
+        }
+    }
+}
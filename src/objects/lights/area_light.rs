@@ -0,0 +1,86 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Light, LightSample};
+use crate::utils::Float;
+
+// A rectangular light with a corner at corner and sides along u and v, seen
+// as a grid of point lights at the centres of its cells, so that shadows are
+// soft where only some of them are blocked. Each cell carries an equal share
+// of the intensity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AreaLight {
+    pub corner: Point,
+    pub u: Vector,
+    pub v: Vector,
+    pub intensity: Colour,
+    // cells along u and v
+    pub resolution: [usize; 2],
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Point,
+        u: Vector,
+        v: Vector,
+        intensity: Colour,
+        resolution: [usize; 2],
+    ) -> AreaLight {
+        AreaLight {
+            corner,
+            u,
+            v,
+            intensity,
+            resolution,
+        }
+    }
+
+    pub fn centre(&self) -> Point {
+        self.corner + self.u * 0.5 + self.v * 0.5
+    }
+}
+
+impl Light for AreaLight {
+    fn samples(&self, target: Point) -> Vec<LightSample> {
+        let [u_steps, v_steps] = self.resolution.map(|steps| steps.max(1));
+        let intensity = self.intensity * (1.0 / (u_steps * v_steps) as Float);
+        let mut samples = Vec::with_capacity(u_steps * v_steps);
+        for idx_u in 0..u_steps {
+            for idx_v in 0..v_steps {
+                let position = self.corner
+                    + self.u * ((idx_u as Float + 0.5) / u_steps as Float)
+                    + self.v * ((idx_v as Float + 0.5) / v_steps as Float);
+                samples.push(LightSample::from_position(target, position, intensity));
+            }
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn samples_cover_the_area() {
+        let light = AreaLight::new(
+            Point::new(-1.0, 5.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            Colour::new(1.0, 1.0, 1.0),
+            [2, 2],
+        );
+        assert_eq!(light.centre(), Point::new(0.0, 5.0, 0.0));
+        let samples = light.samples(Point::zero());
+        assert_eq!(samples.len(), 4);
+        let total = samples
+            .iter()
+            .fold(Colour::new(0.0, 0.0, 0.0), |sum, sample| {
+                sum + sample.intensity
+            });
+        approx_eq!(total.red, 1.0);
+        // cell centres are half a cell in from the corners
+        let sample = samples[0];
+        approx_eq!(sample.distance, Float::sqrt(0.25 + 25.0 + 0.25));
+        assert!(sample.direction.x < 0.0 && sample.direction.z < 0.0);
+    }
+}
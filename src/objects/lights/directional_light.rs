@@ -0,0 +1,48 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Light, LightSample};
+use crate::utils::Float;
+
+// Light from infinitely far away, e.g. the sun, arriving along the same
+// direction everywhere and shadowed by anything in its way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    // that the light travels in
+    pub direction: Vector,
+    pub intensity: Colour,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Colour) -> DirectionalLight {
+        DirectionalLight {
+            direction,
+            intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn samples(&self, _target: Point) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction.normalise(),
+            distance: Float::INFINITY,
+            intensity: self.intensity,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_direction_everywhere() {
+        let light = DirectionalLight::new(Vector::new(0.0, -2.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        for target in [Point::zero(), Point::new(100.0, -5.0, 3.0)] {
+            let [sample] = light.samples(target)[..] else {
+                panic!("expected one sample");
+            };
+            assert_eq!(sample.direction, Vector::new(0.0, 1.0, 0.0));
+            assert_eq!(sample.distance, Float::INFINITY);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Light, LightSample, Shape};
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// A glowing object seen as a light: a disc the size of the object, facing
+// whatever it lights, sampled at evenly spread points that share the
+// object's emission. Shadow rays stop where they enter the object's bounding
+// sphere, so the object does not shadow its own light, and points within
+// that sphere are not lit by it at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub centre: Point,
+    pub radius: Float,
+    // encloses the object
+    pub bounding_radius: Float,
+    pub emission: Colour,
+    pub resolution: usize,
+}
+
+impl Emitter {
+    pub fn new(
+        centre: Point,
+        radius: Float,
+        bounding_radius: Float,
+        emission: Colour,
+        resolution: usize,
+    ) -> Emitter {
+        Emitter {
+            centre,
+            radius,
+            bounding_radius,
+            emission,
+            resolution,
+        }
+    }
+
+    // for a top-level primitive whose material glows, from its bounds; other
+    // shapes (groups, meshes, unbounded primitives) are not emitters
+    pub fn from_shape(shape: &Shape, resolution: usize) -> Option<Emitter> {
+        let Shape::Primitive(primitive) = shape else {
+            return None;
+        };
+        let emission = primitive.material().emission;
+        let bounding_box = primitive.bounds().bounding_box();
+        if emission == Colour::new(0.0, 0.0, 0.0) || !bounding_box.is_bounded() {
+            return None;
+        }
+
+        let (x_range, y_range, z_range) = bounding_box.axial_bounds();
+        let [extent_x, extent_y, extent_z] =
+            [x_range, y_range, z_range].map(|[min, max]| max - min);
+        let centre = Point::new(
+            (x_range[0] + x_range[1]) / 2.0,
+            (y_range[0] + y_range[1]) / 2.0,
+            (z_range[0] + z_range[1]) / 2.0,
+        );
+        let radius = (extent_x + extent_y + extent_z) / 6.0;
+        let bounding_radius = Vector::new(extent_x, extent_y, extent_z).magnitude() / 2.0;
+        Some(Emitter::new(
+            centre,
+            radius,
+            bounding_radius,
+            emission,
+            resolution,
+        ))
+    }
+}
+
+impl Light for Emitter {
+    fn samples(&self, target: Point) -> Vec<LightSample> {
+        let resolution = self.resolution.max(1);
+        let to_centre = self.centre - target;
+        let distance = to_centre.magnitude();
+        let axis = to_centre.normalise();
+        let intensity = if distance > self.bounding_radius {
+            self.emission * (1.0 / resolution as Float)
+        } else {
+            Colour::new(0.0, 0.0, 0.0)
+        };
+
+        // any two directions across the axis
+        let helper = if axis.x.abs() < 0.9 {
+            Vector::new(1.0, 0.0, 0.0)
+        } else {
+            Vector::new(0.0, 1.0, 0.0)
+        };
+        let across = axis.cross(helper).normalise();
+        let up = axis.cross(across);
+
+        // a sunflower spiral, which covers the disc evenly for any count
+        let golden_angle = PI * (3.0 - Float::sqrt(5.0));
+        (0..resolution)
+            .map(|idx| {
+                let radius = self.radius * ((idx as Float + 0.5) / resolution as Float).sqrt();
+                let angle = idx as Float * golden_angle;
+                let position =
+                    self.centre + across * (radius * angle.cos()) + up * (radius * angle.sin());
+                let direction = (position - target).normalise();
+
+                // where the ray enters the bounding sphere
+                let from_centre = target - self.centre;
+                let b = direction.dot(from_centre);
+                let c = from_centre.dot(from_centre) - self.bounding_radius.powi(2);
+                let entry = -b - (b * b - c).max(0.0).sqrt();
+                LightSample {
+                    direction,
+                    distance: entry.max(0.0),
+                    intensity,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{Material, Plane, Sphere, Transform, TransformKind};
+    use crate::utils::{approx_eq, BuildInto, Buildable};
+
+    #[test]
+    fn glowing_primitives_are_emitters() {
+        let glowing = Material {
+            emission: Colour::new(1.0, 0.5, 0.0),
+            ..Material::default()
+        };
+        let sphere: Shape = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)))
+            .set_material(glowing.clone())
+            .build_into();
+        let emitter = Emitter::from_shape(&sphere, 4).unwrap();
+        assert_eq!(emitter.centre, Point::zero());
+        approx_eq!(emitter.radius, 2.0);
+        approx_eq!(emitter.bounding_radius, Float::sqrt(12.0));
+
+        let dull: Shape = Sphere::builder().build_into();
+        assert_eq!(Emitter::from_shape(&dull, 4), None);
+        let plane: Shape = Plane::builder().set_material(glowing).build_into();
+        assert_eq!(Emitter::from_shape(&plane, 4), None);
+    }
+
+    #[test]
+    fn samples_share_emission_across_disc() {
+        let emitter = Emitter::new(
+            Point::new(0.0, 10.0, 0.0),
+            1.0,
+            1.5,
+            Colour::new(1.0, 1.0, 1.0),
+            8,
+        );
+        let samples = emitter.samples(Point::zero());
+        assert_eq!(samples.len(), 8);
+        let total = samples
+            .iter()
+            .fold(0.0, |sum, sample| sum + sample.intensity.red);
+        approx_eq!(total, 1.0);
+        for sample in &samples {
+            assert!(sample.direction.y > 0.99);
+            assert!(sample.distance > 8.4 && sample.distance < 9.0);
+        }
+
+        // from inside the bounding sphere, the emitter gives no light
+        let inside = emitter.samples(Point::new(0.0, 9.0, 0.0));
+        assert_eq!(inside.len(), 8);
+        assert!(inside
+            .iter()
+            .all(|sample| sample.intensity == Colour::new(0.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::Debug;
+
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::Material;
+use crate::utils::Float;
+
+// A source of light, seen from the points it lights as one or more samples,
+// e.g. spread over the area of an area light. Shading and shadows only see
+// samples, so any kind of light can be added by implementing this.
+pub trait Light: Debug + LightClone + Send + Sync {
+    // the light arriving at target, with the samples' intensities summing to
+    // the light's total; the number of samples must not depend on target
+    fn samples(&self, target: Point) -> Vec<LightSample>;
+}
+
+pub trait LightClone {
+    fn clone_box(&self) -> Box<dyn Light>;
+}
+
+impl<T> LightClone for T
+where
+    T: Light + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn Light> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Light> {
+    fn clone(&self) -> Box<dyn Light> {
+        self.clone_box()
+    }
+}
+
+// Light arriving at a point from a single direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    // normalised, from the point towards the light
+    pub direction: Vector,
+    // how far along direction the light is, so that only objects nearer than
+    // that shadow it; infinite for lights infinitely far away
+    pub distance: Float,
+    pub intensity: Colour,
+}
+
+impl LightSample {
+    // from a light at position
+    pub fn from_position(target: Point, position: Point, intensity: Colour) -> LightSample {
+        let vector = position - target;
+        LightSample {
+            direction: vector.normalise(),
+            distance: vector.magnitude(),
+            intensity,
+        }
+    }
+
+    pub(crate) fn shade_phong(
+        &self,
+        material: &Material,
+        object_point: Point,
+        eyev: Vector,
+        normal: Vector,
+        shadowed: bool,
+    ) -> Colour {
+        let effective_colour = material.pattern.colour_at(object_point) * self.intensity;
+        let lightv = self.direction;
+        let ambient = effective_colour * material.ambient;
+        if shadowed {
+            return ambient;
+        }
+
+        let light_dot_normal = lightv.dot(normal);
+        let diffuse;
+        let specular;
+        if light_dot_normal < 0.0 {
+            diffuse = Colour::new(0.0, 0.0, 0.0);
+            specular = Colour::new(0.0, 0.0, 0.0);
+        } else {
+            diffuse = effective_colour * material.diffuse * light_dot_normal;
+            let reflectv = (-lightv).reflect(normal);
+            let reflect_dot_eye = reflectv.dot(eyev);
+            if reflect_dot_eye <= 0.0 {
+                specular = Colour::new(0.0, 0.0, 0.0);
+            } else {
+                let factor = reflect_dot_eye.powf(material.shininess);
+                specular = self.intensity * material.specular * factor;
+            }
+        }
+        ambient + diffuse + specular
+    }
+
+    pub(crate) fn shade_metallic_roughness(
+        &self,
+        material: &Material,
+        object_point: Point,
+        eyev: Vector,
+        normal: Vector,
+        shadowed: bool,
+    ) -> Colour {
+        let base_colour = material.pattern.colour_at(object_point);
+        let ambient = base_colour * self.intensity * material.ambient;
+        if shadowed {
+            return ambient;
+        }
+
+        let model = material.metallic_roughness.unwrap_or_default();
+        ambient + model.reflectance(base_colour, normal, eyev, self.direction) * self.intensity
+    }
+
+    pub(crate) fn shade_toon(
+        &self,
+        material: &Material,
+        object_point: Point,
+        normal: Vector,
+        shadowed: bool,
+        bands: usize,
+    ) -> Colour {
+        let effective_colour = material.pattern.colour_at(object_point) * self.intensity;
+        let ambient = effective_colour * material.ambient;
+        let light_dot_normal = self.direction.dot(normal);
+        if shadowed || light_dot_normal <= 0.0 {
+            return ambient;
+        }
+
+        // lit surfaces always get at least the first band
+        let bands = bands.max(1) as Float;
+        let band = (light_dot_normal * bands).ceil() / bands;
+        ambient + effective_colour * material.diffuse * band
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{MetallicRoughness, PointLight};
+    use crate::utils::floats::approx_eq;
+
+    // as seen from the origin
+    fn sample(light: &PointLight) -> LightSample {
+        light.samples(Point::zero())[0]
+    }
+
+    #[test]
+    fn eye_directly_between_light_and_surface() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let resulting_colour = Colour::new(1.9, 1.9, 1.9);
+        assert_eq!(
+            sample(&light).shade_phong(&material, position, eyev, normal, false),
+            resulting_colour
+        );
+    }
+
+    #[test]
+    fn eye_between_light_and_surface_eye_offset_45_degrees() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, Float::sqrt(2.0) / 2.0, -Float::sqrt(2.0) / 2.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let resulting_colour = Colour::new(1.0, 1.0, 1.0);
+        assert_eq!(
+            sample(&light).shade_phong(&material, position, eyev, normal, false),
+            resulting_colour
+        );
+    }
+
+    #[test]
+    fn eye_between_light_and_surface_light_offset_45_degrees() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let colour = sample(&light).shade_phong(&material, position, eyev, normal, false);
+        let resulting_colour = Colour::new(0.736396, 0.736396, 0.736396);
+        approx_eq!(colour.red, resulting_colour.red);
+        approx_eq!(colour.green, resulting_colour.green);
+        approx_eq!(colour.blue, resulting_colour.blue);
+    }
+
+    #[test]
+    fn eye_in_path_of_reflection_vector() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, -Float::sqrt(2.0) / 2.0, -Float::sqrt(2.0) / 2.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let colour = sample(&light).shade_phong(&material, position, eyev, normal, false);
+        let resulting_colour = Colour::new(1.636396, 1.636396, 1.636396);
+        approx_eq!(colour.red, resulting_colour.red);
+        approx_eq!(colour.green, resulting_colour.green);
+        approx_eq!(colour.blue, resulting_colour.blue);
+    }
+
+    #[test]
+    fn light_behind_surface() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Colour::new(1.0, 1.0, 1.0));
+        let resulting_colour = Colour::new(0.1, 0.1, 0.1);
+        assert_eq!(
+            sample(&light).shade_phong(&material, position, eyev, normal, false),
+            resulting_colour
+        );
+    }
+
+    #[test]
+    fn light_in_shadow() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let resulting_colour = Colour::new(0.1, 0.1, 0.1);
+        assert_eq!(
+            sample(&light).shade_phong(&material, position, eyev, normal, true),
+            resulting_colour
+        );
+    }
+
+    #[test]
+    fn metallic_roughness_replaces_phong() {
+        let material = Material {
+            metallic_roughness: Some(MetallicRoughness::new(0.0, 1.0)),
+            ..Material::preset()
+        };
+        let position = Point::zero();
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let colour =
+            sample(&light).shade_metallic_roughness(&material, position, normal, normal, false);
+        // no Phong highlight, so far dimmer than the 1.9 of the same material
+        assert!(colour.red > 1.0 && colour.red < 1.1);
+        assert_eq!(
+            material.pattern.colour_at(position) * 0.1,
+            sample(&light).shade_metallic_roughness(&material, position, normal, normal, true)
+        );
+    }
+
+    #[test]
+    fn toon_shading_quantises_diffuse() {
+        let material = Material::preset();
+        let position = Point::zero();
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        // light_dot_normal is sqrt(2) / 2, which falls in the third of four bands
+        let colour = sample(&light).shade_toon(&material, position, normal, false, 4);
+        approx_eq!(colour.red, 0.1 + 0.9 * 0.75);
+
+        let colour = sample(&light).shade_toon(&material, position, normal, true, 4);
+        approx_eq!(colour.red, 0.1);
+    }
+}
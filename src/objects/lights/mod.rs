@@ -0,0 +1,25 @@
+pub mod area_light;
+pub mod directional_light;
+pub mod emitter;
+pub mod light;
+pub mod point_light;
+pub mod spot_light;
+pub mod tube_light;
+
+// crate-level re-exports
+pub(crate) use area_light::*;
+pub(crate) use directional_light::*;
+pub(crate) use emitter::*;
+pub(crate) use light::*;
+pub(crate) use point_light::*;
+
+// public re-exports (through crate::prelude)
+pub(super) mod prelude {
+    pub use super::area_light::AreaLight;
+    pub use super::directional_light::DirectionalLight;
+    pub use super::emitter::Emitter;
+    pub use super::light::{Light, LightSample};
+    pub use super::point_light::PointLight;
+    pub use super::spot_light::SpotLight;
+    pub use super::tube_light::TubeLight;
+}
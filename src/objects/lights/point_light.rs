@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Light, LightSample};
+use crate::utils::IesProfile;
+
+// Light from a single point, the same in every direction unless given the
+// photometric profile of a real luminaire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Point,
+    // the peak intensity, if there is a profile
+    pub intensity: Colour,
+    // aimed straight down, as luminaires are measured
+    pub profile: Option<Arc<IesProfile>>,
+}
+
+impl PointLight {
+    pub fn new(position: Point, intensity: Colour) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(self, profile: IesProfile) -> PointLight {
+        PointLight {
+            profile: Some(Arc::new(profile)),
+            ..self
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn samples(&self, target: Point) -> Vec<LightSample> {
+        let intensity = match &self.profile {
+            Some(profile) => {
+                self.intensity
+                    * profile
+                        .relative_intensity(target - self.position, Vector::new(0.0, -1.0, 0.0))
+            }
+            None => self.intensity,
+        };
+        vec![LightSample::from_position(target, self.position, intensity)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::approx_eq;
+    use crate::utils::ies::tests::DOWNLIGHT;
+
+    use super::*;
+
+    #[test]
+    fn profile_shapes_the_light() {
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Colour::new(1.0, 1.0, 1.0))
+            .with_profile(IesProfile::parse(DOWNLIGHT).unwrap());
+        let intensity = |target| light.samples(target)[0].intensity.red;
+        approx_eq!(intensity(Point::zero()), 1.0);
+        approx_eq!(intensity(Point::new(10.0, 0.0, 0.0)), 0.5);
+        approx_eq!(intensity(Point::new(0.0, 20.0, 0.0)), 0.0);
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use crate::collections::{Angle, Colour, Point, Vector};
+use crate::objects::{Light, LightSample};
+use crate::utils::{Float, IesProfile};
+
+// A point light shining in a cone, at full intensity within the inner angle
+// of its axis and fading smoothly to nothing at the outer angle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Point,
+    // the axis of the cone
+    pub direction: Vector,
+    pub intensity: Colour,
+    pub inner: Angle,
+    pub outer: Angle,
+    // aimed along direction, and within the cone as well
+    pub profile: Option<Arc<IesProfile>>,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        intensity: Colour,
+        inner: Angle,
+        outer: Angle,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction,
+            intensity,
+            inner,
+            outer,
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(self, profile: IesProfile) -> SpotLight {
+        SpotLight {
+            profile: Some(Arc::new(profile)),
+            ..self
+        }
+    }
+
+    // the fraction of the intensity shining towards target
+    fn falloff(&self, target: Point) -> Float {
+        let (mut inner, mut outer) = (self.inner, self.outer);
+        let cos_inner = inner.radians().cos();
+        let cos_outer = outer.radians().cos();
+        let cos_angle = (target - self.position)
+            .normalise()
+            .dot(self.direction.normalise());
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        let fraction = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        fraction * fraction * (3.0 - 2.0 * fraction)
+    }
+}
+
+impl Light for SpotLight {
+    fn samples(&self, target: Point) -> Vec<LightSample> {
+        let mut intensity = self.intensity * self.falloff(target);
+        if let Some(profile) = &self.profile {
+            intensity =
+                intensity * profile.relative_intensity(target - self.position, self.direction);
+        }
+        vec![LightSample::from_position(target, self.position, intensity)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn cone_falloff() {
+        let light = SpotLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Colour::new(1.0, 1.0, 1.0),
+            Angle::from_degrees(20.0),
+            Angle::from_degrees(40.0),
+        );
+        let intensity = |x: Float| light.samples(Point::new(x, 0.0, 0.0))[0].intensity.red;
+        // tan 20° is 0.36 and tan 40° is 0.84
+        assert_eq!(intensity(0.0), 1.0);
+        assert_eq!(intensity(3.5), 1.0);
+        let halfway = intensity(6.0);
+        assert!(halfway > 0.0 && halfway < 1.0);
+        assert_eq!(intensity(8.5), 0.0);
+
+        let sample = light.samples(Point::zero())[0];
+        approx_eq!(sample.distance, 10.0);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Light, LightSample};
+use crate::utils::Float;
+
+// A glowing line segment with some thickness, e.g. a fluorescent tube, seen
+// from each point it lights as a grid of samples along its length and across
+// its width (as it appears from that point), so that its shadows are soft
+// in both directions. Each sample carries an equal share of the intensity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TubeLight {
+    pub start: Point,
+    pub end: Point,
+    pub radius: Float,
+    pub intensity: Colour,
+    // samples along and across the tube
+    pub resolution: [usize; 2],
+}
+
+impl TubeLight {
+    pub fn new(
+        start: Point,
+        end: Point,
+        radius: Float,
+        intensity: Colour,
+        resolution: [usize; 2],
+    ) -> TubeLight {
+        TubeLight {
+            start,
+            end,
+            radius,
+            intensity,
+            resolution,
+        }
+    }
+
+    pub fn length(&self) -> Float {
+        (self.end - self.start).magnitude()
+    }
+}
+
+impl Light for TubeLight {
+    fn samples(&self, target: Point) -> Vec<LightSample> {
+        let [along_steps, across_steps] = self.resolution.map(|steps| steps.max(1));
+        let intensity = self.intensity * (1.0 / (along_steps * across_steps) as Float);
+        let axis = self.end - self.start;
+        let midpoint = self.start + axis * 0.5;
+
+        // across both the tube and the line of sight to it; any direction
+        // across the tube if the target is in line with it
+        let mut across = axis.cross(midpoint - target);
+        if across.magnitude() < 1e-9 {
+            let helper = if axis.normalise().x.abs() < 0.9 {
+                Vector::new(1.0, 0.0, 0.0)
+            } else {
+                Vector::new(0.0, 1.0, 0.0)
+            };
+            across = axis.cross(helper);
+        }
+        let across = across.normalise();
+
+        let mut samples = Vec::with_capacity(along_steps * across_steps);
+        for idx_along in 0..along_steps {
+            for idx_across in 0..across_steps {
+                let along = (idx_along as Float + 0.5) / along_steps as Float;
+                let offset = ((idx_across as Float + 0.5) / across_steps as Float) * 2.0 - 1.0;
+                let position = self.start + axis * along + across * (offset * self.radius);
+                samples.push(LightSample::from_position(target, position, intensity));
+            }
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn samples_spread_along_and_across() {
+        let light = TubeLight::new(
+            Point::new(-2.0, 5.0, 0.0),
+            Point::new(2.0, 5.0, 0.0),
+            0.5,
+            Colour::new(1.0, 1.0, 1.0),
+            [4, 2],
+        );
+        approx_eq!(light.length(), 4.0);
+        let samples = light.samples(Point::zero());
+        assert_eq!(samples.len(), 8);
+        let total = samples
+            .iter()
+            .fold(Colour::new(0.0, 0.0, 0.0), |sum, sample| {
+                sum + sample.intensity
+            });
+        approx_eq!(total.red, 1.0);
+
+        // seen from below, the tube's width is along z
+        let position = |sample: &LightSample| Point::zero() + sample.direction * sample.distance;
+        let first = position(&samples[0]);
+        approx_eq!(first.x, -1.5);
+        approx_eq!(first.y, 5.0);
+        approx_eq!(first.z.abs(), 0.25);
+        approx_eq!(position(&samples[1]).z, -first.z);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::collections::Point;
+use crate::objects::*;
+use crate::utils::{Buildable, ConsumingBuilder, Float};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LodMetric {
+    // distance from the ray origin to the centre of the bounding box
+    Distance,
+    // bounding box diagonal divided by that distance (roughly, the angular size)
+    ProjectedSize,
+}
+
+// A level-of-detail container: each ray only intersects one of the levels,
+// chosen by measuring the container from the ray's origin. The level used is
+// the one with the largest threshold not exceeding the measurement, falling
+// back to the level with the smallest threshold.
+//
+// e.g. with LodMetric::Distance, levels at 0.0 (detailed) and 50.0 (coarse)
+// switch to the coarse level 50 units away; with LodMetric::ProjectedSize,
+// levels at 0.0 (coarse) and 0.5 (detailed) switch to the detailed level once
+// the object appears large enough.
+//
+// Secondary rays start elsewhere and so may see a different level to the
+// camera ray.
+#[derive(Clone, Debug)]
+pub struct Lod {
+    metric: LodMetric,
+    levels: Vec<(Float, Shape)>,
+    bounds: Bounds,
+}
+
+impl Lod {
+    pub fn metric(&self) -> LodMetric {
+        self.metric
+    }
+
+    // sorted by ascending threshold
+    pub fn levels(&self) -> &Vec<(Float, Shape)> {
+        &self.levels
+    }
+
+    pub fn select_level(&self, origin: Point) -> Option<&Shape> {
+        let measurement = self.measure(origin);
+        self.levels
+            .iter()
+            .rev()
+            .find(|(threshold, _)| *threshold <= measurement)
+            .or(self.levels.first())
+            .map(|(_, shape)| shape)
+    }
+
+    fn measure(&self, origin: Point) -> Float {
+        let bounding_box = self.bounds.bounding_box();
+        let (x_range, y_range, z_range) = bounding_box.axial_bounds();
+        if [x_range, y_range, z_range]
+            .iter()
+            .flatten()
+            .any(|bound| !bound.is_finite())
+        {
+            // without a finite extent there is nothing sensible to measure
+            return match self.metric {
+                LodMetric::Distance => 0.0,
+                LodMetric::ProjectedSize => Float::INFINITY,
+            };
+        }
+
+        let [min, max] = [
+            Point::new(x_range[0], y_range[0], z_range[0]),
+            Point::new(x_range[1], y_range[1], z_range[1]),
+        ];
+        let diagonal = max - min;
+        let centre = min + diagonal * 0.5;
+        let distance = (centre - origin).magnitude();
+
+        match self.metric {
+            LodMetric::Distance => distance,
+            LodMetric::ProjectedSize => diagonal.magnitude() / distance,
+        }
+    }
+}
+
+impl Intersectable<dyn PrimitiveShape> for Lod {
+    fn intersect_ray_into<'world: 'ray, 'ray>(
+        &'world self,
+        world_ray: &'ray Ray,
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
+        // bounds are in the space of the containing frame, so measure from there
+        let origin = transform_through_stack_forwards(world_ray.origin, transform_stack);
+
+        if let Some(shape) = self.select_level(origin) {
+            shape.intersect_ray_into(world_ray, transform_stack, hit_register);
+        }
+    }
+}
+
+impl Bounded for Lod {
+    fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LodBuilder {
+    metric: Option<LodMetric>,
+    levels: Option<Vec<(Float, Shape)>>,
+}
+
+impl LodBuilder {
+    pub fn set_metric(mut self, metric: LodMetric) -> LodBuilder {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn add_level(mut self, threshold: Float, shape: Shape) -> LodBuilder {
+        match self.levels {
+            Some(ref mut levels) => {
+                levels.push((threshold, shape));
+            }
+            None => self.levels = Some(vec![(threshold, shape)]),
+        }
+        self
+    }
+}
+
+impl Buildable for Lod {
+    type Builder = LodBuilder;
+
+    fn builder() -> Self::Builder {
+        LodBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for LodBuilder {
+    type Built = Lod;
+
+    fn build(self) -> Self::Built {
+        let metric = self.metric.unwrap_or(LodMetric::Distance);
+        let mut levels = self.levels.unwrap_or_default();
+        levels.sort_by(|(threshold_a, _), (threshold_b, _)| threshold_a.total_cmp(threshold_b));
+        let bounds = match levels
+            .iter()
+            .map(|(_, shape)| shape.bounds().bounding_box())
+            .reduce(|bbox_a, bbox_b| bbox_a + bbox_b)
+        {
+            Some(bbox) => Bounds::Checked(bbox),
+            None => Bounds::Unchecked(BoundingBox::new_unbounded()),
+        };
+
+        Lod {
+            metric,
+            levels,
+            bounds,
+        }
+    }
+}
+
+impl From<Lod> for Shape {
+    fn from(lod: Lod) -> Shape {
+        Shape::Lod(lod)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Vector;
+    use crate::utils::BuildInto;
+
+    fn sphere_with_ambient(ambient: Float) -> Shape {
+        Sphere::builder()
+            .set_material(Material {
+                ambient,
+                ..Material::default()
+            })
+            .build_into()
+    }
+
+    #[test]
+    fn select_level_by_distance() {
+        let lod = Lod::builder()
+            .set_metric(LodMetric::Distance)
+            .add_level(10.0, sphere_with_ambient(0.5))
+            .add_level(0.0, sphere_with_ambient(1.0))
+            .build();
+        let near = lod.select_level(Point::new(0.0, 0.0, -5.0)).unwrap();
+        let far = lod.select_level(Point::new(0.0, 0.0, -20.0)).unwrap();
+        assert_eq!(near.primitives()[0].material().ambient, 1.0);
+        assert_eq!(far.primitives()[0].material().ambient, 0.5);
+    }
+
+    #[test]
+    fn select_level_by_projected_size() {
+        let lod = Lod::builder()
+            .set_metric(LodMetric::ProjectedSize)
+            .add_level(0.0, sphere_with_ambient(0.5))
+            .add_level(0.5, sphere_with_ambient(1.0))
+            .build();
+        // the diagonal of the unit sphere's bounding box is 2 * sqrt(3)
+        let near = lod.select_level(Point::new(0.0, 0.0, -5.0)).unwrap();
+        let far = lod.select_level(Point::new(0.0, 0.0, -20.0)).unwrap();
+        assert_eq!(near.primitives()[0].material().ambient, 1.0);
+        assert_eq!(far.primitives()[0].material().ambient, 0.5);
+    }
+
+    #[test]
+    fn intersect_only_selected_level() {
+        let lod: Shape = Lod::builder()
+            .add_level(0.0, sphere_with_ambient(1.0))
+            .add_level(
+                10.0,
+                Sphere::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)))
+                    .build_into(),
+            )
+            .build_into();
+
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let hit_register = lod.intersect_ray(&ray, vec![]);
+        assert_eq!(hit_register.finalise_hit().unwrap().t(), 4.0);
+
+        let ray = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0));
+        let hit_register = lod.intersect_ray(&ray, vec![]);
+        assert_eq!(hit_register.finalise_hit().unwrap().t(), 18.0);
+    }
+
+    #[test]
+    fn intersect_lod_through_transformed_group() {
+        // the group moves the lod 30 units away from the origin
+        let lod: Shape = Lod::builder()
+            .add_level(0.0, sphere_with_ambient(1.0))
+            .add_level(
+                20.0,
+                Sphere::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Translate(
+                        0.0, 3.0, 0.0,
+                    )))
+                    .build_into(),
+            )
+            .build_into();
+        let group: Shape = Group::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.0, 30.0)))
+            .add_object(lod)
+            .build_into();
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 25.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            group
+                .intersect_ray(&ray, vec![])
+                .finalise_hit()
+                .unwrap()
+                .t(),
+            4.0
+        );
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(group.intersect_ray(&ray, vec![]).finalise_hit().is_none());
+    }
+}
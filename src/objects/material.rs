@@ -1,15 +1,57 @@
-use crate::objects::{Pattern, Solid};
+use crate::collections::Colour;
+use crate::objects::{
+    BumpMap, MetallicRoughness, Pattern, Shader, Solid, Transform, TransformKind, Varied,
+};
+use crate::scenes::ShadingMode;
+use crate::utils::{Float, Random};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Material {
     pub pattern: Box<dyn Pattern>,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
-    pub reflectance: f64,
-    pub transparency: f64,
-    pub refractive_index: f64,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflectance: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
+    // how much the refractive index rises towards the blue end of the
+    // spectrum, as the B coefficient of Cauchy's equation n = A + B / λ²
+    // (λ in micrometres, so e.g. 0.0042 for crown glass), refractive_index
+    // being the index at 587.6 nm; only seen in spectral rendering
+    pub dispersion: Float,
+    // where transparent objects overlap, the higher priority medium wins
+    pub medium_priority: u32,
+    // light given off by the surface itself, whatever the lighting; a glowing
+    // top-level primitive also lights the objects around it, see Emitter
+    pub emission: Colour,
+    pub bump_map: Option<BumpMap>,
+    // if set, lights are reflected by this model instead of Phong's, and
+    // diffuse, specular and shininess are unused
+    pub metallic_roughness: Option<MetallicRoughness>,
+    pub sidedness: Sidedness,
+    // e.g. decorations that should not darken what is behind them, or a
+    // ground plane that shows shadows without casting its own
+    pub casts_shadow: bool,
+    pub receives_shadow: bool,
+    // if set, overrides the world's shading mode for this material
+    pub shading_mode: Option<ShadingMode>,
+    // if set, replaces all of the above in shading this material
+    pub shader: Option<Shader>,
+}
+
+// How hits on an object's surface are told apart. Solid objects enclose a
+// medium that rays enter and leave, and are shaded from either side. Thin
+// surfaces are shaded from either side too but enclose nothing, so that e.g. a
+// transparent plane does not bend light passing through it. Single sided
+// surfaces can only be hit from the front, the side their normals point to,
+// and are invisible (and cast no shadows) from behind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sidedness {
+    #[default]
+    Solid,
+    Thin,
+    SingleSided,
 }
 
 impl PartialEq for Material {
@@ -22,6 +64,16 @@ impl PartialEq for Material {
             && self.reflectance == other.reflectance
             && self.transparency == other.transparency
             && self.refractive_index == other.refractive_index
+            && self.dispersion == other.dispersion
+            && self.medium_priority == other.medium_priority
+            && self.emission == other.emission
+            && self.bump_map == other.bump_map
+            && self.metallic_roughness == other.metallic_roughness
+            && self.sidedness == other.sidedness
+            && self.casts_shadow == other.casts_shadow
+            && self.receives_shadow == other.receives_shadow
+            && self.shading_mode == other.shading_mode
+            && self.shader == other.shader
     }
 }
 
@@ -36,11 +88,65 @@ impl Default for Material {
             reflectance: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            dispersion: 0.0,
+            medium_priority: 0,
+            emission: Colour::new(0.0, 0.0, 0.0),
+            bump_map: None,
+            metallic_roughness: None,
+            sidedness: Sidedness::Solid,
+            casts_shadow: true,
+            receives_shadow: true,
+            shading_mode: None,
+            shader: None,
         }
     }
 }
 
+// How far each instance's material may stray from the original; all zero (no
+// variation) by default. Hue is in turns, and the rest are relative, e.g. a
+// brightness of 0.2 scales colours by between 0.8 and 1.2.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Variation {
+    pub seed: u64,
+    pub hue: Float,
+    pub brightness: Float,
+    pub shininess: Float,
+    pub scale: Float,
+}
+
 impl Material {
+    // a copy varied by a stable random amount for this instance, so the same
+    // instance id always gets the same material
+    pub fn vary(&self, variation: &Variation, instance_id: u64) -> Material {
+        let mut random = Random::keyed(variation.seed, instance_id);
+        let hue_shift = variation.hue * random.next_signed();
+        let brightness = 1.0 + variation.brightness * random.next_signed();
+        let shininess = 1.0 + variation.shininess * random.next_signed();
+        let scale = 1.0 + variation.scale * random.next_signed();
+
+        Material {
+            pattern: Box::new(Varied {
+                transform: Transform::new(TransformKind::Scale(scale, scale, scale)),
+                ..Varied::new(self.pattern.clone(), hue_shift, brightness)
+            }),
+            shininess: self.shininess * shininess,
+            ..self.clone()
+        }
+    }
+
+    // the refractive index for light of a wavelength in nanometres, or for
+    // all wavelengths at once if None
+    pub fn refractive_index_at(&self, wavelength: Option<Float>) -> Float {
+        match wavelength {
+            Some(wavelength) => {
+                let micrometres = wavelength / 1000.0;
+                self.refractive_index
+                    + self.dispersion * (1.0 / micrometres.powi(2) - 1.0 / Float::powi(0.5876, 2))
+            }
+            None => self.refractive_index,
+        }
+    }
+
     pub fn preset() -> Material {
         Material {
             pattern: Box::new(Solid::preset()),
@@ -51,6 +157,95 @@ impl Material {
             reflectance: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            dispersion: 0.0,
+            medium_priority: 0,
+            emission: Colour::new(0.0, 0.0, 0.0),
+            bump_map: None,
+            metallic_roughness: None,
+            sidedness: Sidedness::Solid,
+            casts_shadow: true,
+            receives_shadow: true,
+            shading_mode: None,
+            shader: None,
         }
     }
+
+    // plain matte white, with no highlights, reflection or refraction, so
+    // that only the lighting shows
+    pub fn clay() -> Material {
+        Material {
+            pattern: Box::new(Solid::preset()),
+            ambient: 0.1,
+            diffuse: 0.9,
+            ..Material::default()
+        }
+    }
+
+    // clear glass: nearly all light passes through, with Fresnel (Schlick)
+    // reflections growing towards grazing angles; combine with a coloured
+    // pattern for tinted glass
+    pub fn glass() -> Material {
+        Material {
+            pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+            ambient: 0.0,
+            diffuse: 0.1,
+            specular: 1.0,
+            shininess: 300.0,
+            reflectance: 1.0,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Material::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Point;
+
+    #[test]
+    fn dispersion_raises_index_towards_blue() {
+        let glass = Material {
+            dispersion: 0.0042,
+            ..Material::glass()
+        };
+        assert_eq!(glass.refractive_index_at(None), 1.5);
+        crate::utils::approx_eq!(glass.refractive_index_at(Some(587.6)), 1.5);
+        assert!(glass.refractive_index_at(Some(450.0)) > 1.505);
+        assert!(glass.refractive_index_at(Some(650.0)) < 1.5);
+        assert_eq!(Material::glass().refractive_index_at(Some(450.0)), 1.5);
+    }
+
+    #[test]
+    fn vary_is_stable_per_instance() {
+        let material = Material {
+            pattern: Box::new(Solid::new(Colour::new(0.8, 0.4, 0.2))),
+            ..Material::preset()
+        };
+        let variation = Variation {
+            seed: 3,
+            hue: 0.1,
+            brightness: 0.2,
+            shininess: 0.5,
+            scale: 0.0,
+        };
+
+        let first = material.vary(&variation, 0);
+        assert_eq!(first, material.vary(&variation, 0));
+        assert_ne!(first, material.vary(&variation, 1));
+        assert!((100.0..=300.0).contains(&first.shininess));
+        assert_eq!(first.diffuse, material.diffuse);
+    }
+
+    #[test]
+    fn vary_without_variation_keeps_colours() {
+        let material = Material::preset();
+        let varied = material.vary(&Variation::default(), 12);
+        assert_eq!(
+            varied.pattern.colour_at(Point::zero()),
+            material.pattern.colour_at(Point::zero())
+        );
+        assert_eq!(varied.shininess, material.shininess);
+    }
 }
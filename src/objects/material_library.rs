@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use crate::collections::Colour;
+use crate::objects::{Material, MetallicRoughness, Solid};
+
+// Materials by name, so that a scene can ask for "gold" instead of repeating
+// its constants. The default library has presets for common materials, and
+// registering a material under an existing name replaces it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialLibrary {
+    materials: HashMap<String, Material>,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> MaterialLibrary {
+        let metal = |red, green, blue, roughness| Material {
+            pattern: Box::new(Solid::new(Colour::new(red, green, blue))),
+            ambient: 0.05,
+            reflectance: 0.3,
+            metallic_roughness: Some(MetallicRoughness::new(1.0, roughness)),
+            ..Material::preset()
+        };
+        let plain = |colour: Colour, specular, shininess| Material {
+            pattern: Box::new(Solid::new(colour)),
+            specular,
+            shininess,
+            ..Material::preset()
+        };
+
+        let mut library = MaterialLibrary::empty();
+        library.register("clay", Material::clay());
+        library.register("glass", Material::glass());
+        library.register(
+            "water",
+            Material {
+                refractive_index: 1.333,
+                ..Material::glass()
+            },
+        );
+        library.register(
+            "diamond",
+            Material {
+                refractive_index: 2.417,
+                ..Material::glass()
+            },
+        );
+        library.register(
+            "mirror",
+            Material {
+                pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 1.0,
+                shininess: 300.0,
+                reflectance: 1.0,
+                ..Material::preset()
+            },
+        );
+        // base colours are the reflectances of the metals head on
+        library.register("gold", metal(1.0, 0.766, 0.336, 0.3));
+        library.register("silver", metal(0.972, 0.960, 0.915, 0.2));
+        library.register("copper", metal(0.955, 0.638, 0.538, 0.35));
+        library.register("aluminium", metal(0.913, 0.922, 0.924, 0.4));
+        library.register("chrome", metal(0.550, 0.556, 0.554, 0.1));
+        library.register("rubber", plain(Colour::new(0.1, 0.1, 0.1), 0.05, 10.0));
+        library.register("plastic", plain(Colour::new(0.8, 0.8, 0.8), 0.5, 100.0));
+        library.register("matte", plain(Colour::new(0.8, 0.8, 0.8), 0.0, 1.0));
+        library
+    }
+}
+
+impl MaterialLibrary {
+    pub fn empty() -> MaterialLibrary {
+        MaterialLibrary {
+            materials: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, material: Material) {
+        self.materials.insert(name.to_string(), material);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.materials.get(name)
+    }
+
+    // a copy to give to a shape, or None if there is no such material
+    pub fn material(&self, name: &str) -> Option<Material> {
+        self.get(name).cloned()
+    }
+
+    // in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.materials.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collections::Point;
+
+    use super::*;
+
+    #[test]
+    fn default_presets() {
+        let library = MaterialLibrary::default();
+        assert_eq!(library.material("glass"), Some(Material::glass()));
+        assert_eq!(library.get("water").unwrap().refractive_index, 1.333);
+        assert!(library.get("gold").unwrap().metallic_roughness.is_some());
+        assert!(library.get("unobtainium").is_none());
+        assert!(library
+            .names()
+            .starts_with(&["aluminium", "chrome", "clay"]));
+    }
+
+    #[test]
+    fn register_and_replace() {
+        let mut library = MaterialLibrary::empty();
+        assert!(library.names().is_empty());
+
+        let brick = Material {
+            pattern: Box::new(Solid::new(Colour::new(0.6, 0.2, 0.1))),
+            ..Material::preset()
+        };
+        library.register("brick", brick.clone());
+        assert_eq!(library.material("brick"), Some(brick));
+
+        library.register("brick", Material::clay());
+        let replaced = library.material("brick").unwrap();
+        assert_eq!(
+            replaced.pattern.colour_at(Point::zero()),
+            Colour::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(library.names(), vec!["brick"]);
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use crate::collections::{Point, Vector};
+use crate::objects::*;
+use crate::utils::{BuildInto, Buildable, ConsumingBuilder, Float};
+
+// An indexed triangle mesh. Faces are wound the same way as Triangle, so the
+// face normal is (v3 - v1) x (v2 - v1). Meshes are turned into a Group of
+// SmoothTriangles (with area-weighted vertex normals) when converted into a
+// Shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    frame_transformation: Transform,
+    material: Material,
+    vertices: Vec<Point>,
+    faces: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn frame_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn vertices(&self) -> &Vec<Point> {
+        &self.vertices
+    }
+
+    pub fn faces(&self) -> &Vec<[usize; 3]> {
+        &self.faces
+    }
+
+    pub fn vertex_normals(&self) -> Vec<Vector> {
+        let mut normals = vec![Vector::zero(); self.vertices.len()];
+        for &[i1, i2, i3] in &self.faces {
+            let [v1, v2, v3] = [self.vertices[i1], self.vertices[i2], self.vertices[i3]];
+            // not normalised, so larger faces contribute more
+            let face_normal = (v3 - v1).cross(v2 - v1);
+            for idx in [i1, i2, i3] {
+                normals[idx] = normals[idx] + face_normal;
+            }
+        }
+
+        normals
+            .into_iter()
+            .map(|normal| {
+                if normal.magnitude() == 0.0 {
+                    normal
+                } else {
+                    normal.normalise()
+                }
+            })
+            .collect()
+    }
+
+    // applies one level of Loop subdivision; every face is split into four, and
+    // both the old and the new vertices are smoothed towards their neighbours.
+    // Edges used by only one face are treated as creases along the boundary.
+    pub fn subdivide(&self) -> Mesh {
+        let edge_key = |a: usize, b: usize| (a.min(b), a.max(b));
+
+        // for every edge, the vertices opposite it in its adjacent faces
+        let mut edge_opposites: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for &[i1, i2, i3] in &self.faces {
+            for (a, b, opposite) in [(i1, i2, i3), (i2, i3, i1), (i3, i1, i2)] {
+                edge_opposites
+                    .entry(edge_key(a, b))
+                    .or_default()
+                    .push(opposite);
+            }
+        }
+
+        let mut neighbours: Vec<Vec<usize>> = vec![vec![]; self.vertices.len()];
+        let mut boundary_neighbours: Vec<Vec<usize>> = vec![vec![]; self.vertices.len()];
+        for (&(a, b), opposites) in &edge_opposites {
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+            if opposites.len() == 1 {
+                boundary_neighbours[a].push(b);
+                boundary_neighbours[b].push(a);
+            }
+        }
+
+        let position = |idx: usize| self.vertices[idx] - Point::zero();
+
+        let mut vertices: Vec<Point> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(idx, &vertex)| {
+                let valence = neighbours[idx].len();
+                if valence == 0 {
+                    vertex
+                } else if !boundary_neighbours[idx].is_empty() {
+                    if boundary_neighbours[idx].len() != 2 {
+                        // corners of non-manifold boundaries are left in place
+                        return vertex;
+                    }
+                    let [b1, b2] = [boundary_neighbours[idx][0], boundary_neighbours[idx][1]];
+                    Point::zero() + position(idx) * 0.75 + (position(b1) + position(b2)) * 0.125
+                } else {
+                    let beta = if valence == 3 {
+                        3.0 / 16.0
+                    } else {
+                        3.0 / (8.0 * valence as Float)
+                    };
+                    let neighbour_sum = neighbours[idx]
+                        .iter()
+                        .fold(Vector::zero(), |sum, &neighbour| sum + position(neighbour));
+                    Point::zero()
+                        + position(idx) * (1.0 - valence as Float * beta)
+                        + neighbour_sum * beta
+                }
+            })
+            .collect();
+
+        let mut edge_vertices: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edge_vertex = |a: usize, b: usize| -> usize {
+            *edge_vertices.entry(edge_key(a, b)).or_insert_with(|| {
+                let edge_point = match edge_opposites[&edge_key(a, b)].as_slice() {
+                    &[c, d] => {
+                        (position(a) + position(b)) * 0.375 + (position(c) + position(d)) * 0.125
+                    }
+                    _ => (position(a) + position(b)) * 0.5,
+                };
+                vertices.push(Point::zero() + edge_point);
+                vertices.len() - 1
+            })
+        };
+
+        let mut faces = Vec::with_capacity(self.faces.len() * 4);
+        for &[i1, i2, i3] in &self.faces {
+            let e12 = edge_vertex(i1, i2);
+            let e23 = edge_vertex(i2, i3);
+            let e31 = edge_vertex(i3, i1);
+            faces.push([i1, e12, e31]);
+            faces.push([i2, e23, e12]);
+            faces.push([i3, e31, e23]);
+            faces.push([e12, e23, e31]);
+        }
+
+        Mesh {
+            frame_transformation: self.frame_transformation.clone(),
+            material: self.material.clone(),
+            vertices,
+            faces,
+        }
+    }
+
+    pub fn subdivide_by(&self, levels: usize) -> Mesh {
+        (0..levels).fold(self.clone(), |mesh, _| mesh.subdivide())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MeshBuilder {
+    frame_transformation: Option<Transform>,
+    material: Option<Material>,
+    vertices: Option<Vec<Point>>,
+    faces: Option<Vec<[usize; 3]>>,
+    subdivision_level: Option<usize>,
+}
+
+impl MeshBuilder {
+    pub fn set_frame_transformation(mut self, frame_transformation: Transform) -> MeshBuilder {
+        self.frame_transformation = Some(frame_transformation);
+        self
+    }
+
+    pub fn set_material(mut self, material: Material) -> MeshBuilder {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn set_vertices(mut self, vertices: Vec<Point>) -> MeshBuilder {
+        self.vertices = Some(vertices);
+        self
+    }
+
+    pub fn set_faces(mut self, faces: Vec<[usize; 3]>) -> MeshBuilder {
+        self.faces = Some(faces);
+        self
+    }
+
+    // polygons (e.g. quads from a Catmull-Clark style cage) are fan-triangulated
+    pub fn set_polygons(mut self, polygons: Vec<Vec<usize>>) -> MeshBuilder {
+        let faces = polygons
+            .iter()
+            .filter(|polygon| polygon.len() >= 3)
+            .flat_map(|polygon| {
+                polygon[1..]
+                    .windows(2)
+                    .map(|pair| [polygon[0], pair[0], pair[1]])
+            })
+            .collect();
+        self.faces = Some(faces);
+        self
+    }
+
+    pub fn set_subdivision_level(mut self, subdivision_level: usize) -> MeshBuilder {
+        self.subdivision_level = Some(subdivision_level);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Mesh, TransformError> {
+        let vertices = self.vertices.unwrap_or_default();
+        let faces = self.faces.unwrap_or_default();
+        if let Some(face) = faces
+            .iter()
+            .find(|face| face.iter().any(|&idx| idx >= vertices.len()))
+        {
+            panic!(
+                "mesh face {:?} refers to a vertex outside of the {} given vertices",
+                face,
+                vertices.len()
+            );
+        }
+
+        let mesh = Mesh {
+            frame_transformation: Transform::checked_frame(self.frame_transformation)?,
+            material: self.material.unwrap_or_default(),
+            vertices,
+            faces,
+        };
+        Ok(mesh.subdivide_by(self.subdivision_level.unwrap_or(0)))
+    }
+}
+
+impl Buildable for Mesh {
+    type Builder = MeshBuilder;
+
+    fn builder() -> Self::Builder {
+        MeshBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for MeshBuilder {
+    type Built = Mesh;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl From<Mesh> for Shape {
+    fn from(mesh: Mesh) -> Shape {
+        let normals = mesh.vertex_normals();
+        let triangles = mesh
+            .faces
+            .iter()
+            .map(|&[i1, i2, i3]| {
+                SmoothTriangle::builder()
+                    .set_material(mesh.material.clone())
+                    .set_vertices([mesh.vertices[i1], mesh.vertices[i2], mesh.vertices[i3]])
+                    .set_normals([normals[i1], normals[i2], normals[i3]])
+                    .build_into()
+            })
+            .collect();
+
+        Group::builder()
+            .set_frame_transformation(mesh.frame_transformation)
+            .set_objects(triangles)
+            .build_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    fn tetrahedron() -> Mesh {
+        Mesh::builder()
+            .set_vertices(vec![
+                Point::new(1.0, 1.0, 1.0),
+                Point::new(-1.0, -1.0, 1.0),
+                Point::new(-1.0, 1.0, -1.0),
+                Point::new(1.0, -1.0, -1.0),
+            ])
+            .set_faces(vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]])
+            .build()
+    }
+
+    #[test]
+    fn triangulate_polygons() {
+        let mesh = Mesh::builder()
+            .set_vertices(vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ])
+            .set_polygons(vec![vec![0, 1, 2, 3]])
+            .build();
+        assert_eq!(mesh.faces(), &vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn subdivide_closed_mesh() {
+        let mesh = tetrahedron().subdivide();
+        // 4 original vertices and one per edge
+        assert_eq!(mesh.vertices().len(), 10);
+        assert_eq!(mesh.faces().len(), 16);
+
+        // valence 3 vertices move towards the centroid: (1 - 3 * 3/16) v + 3/16 * sum
+        let vertex = mesh.vertices()[0];
+        approx_eq!(vertex.x, 0.4375 - 0.1875);
+        approx_eq!(vertex.y, 0.4375 - 0.1875);
+        approx_eq!(vertex.z, 0.4375 - 0.1875);
+    }
+
+    #[test]
+    fn subdivide_open_mesh_along_boundary() {
+        let mesh = Mesh::builder()
+            .set_vertices(vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(4.0, 0.0, 0.0),
+                Point::new(0.0, 4.0, 0.0),
+            ])
+            .set_faces(vec![[0, 1, 2]])
+            .set_subdivision_level(1)
+            .build();
+        assert_eq!(mesh.vertices().len(), 6);
+        assert_eq!(mesh.faces().len(), 4);
+        assert_eq!(mesh.vertices()[0], Point::new(0.5, 0.5, 0.0));
+        assert_eq!(mesh.vertices()[3], Point::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn subdivide_by_several_levels() {
+        let mesh = tetrahedron().subdivide_by(2);
+        assert_eq!(mesh.faces().len(), 64);
+        assert_eq!(mesh.vertices().len(), 34);
+    }
+
+    #[test]
+    fn vertex_normals_of_planar_mesh() {
+        let mesh = Mesh::builder()
+            .set_vertices(vec![
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 1.0),
+                Point::new(0.0, 0.0, 1.0),
+            ])
+            .set_polygons(vec![vec![0, 1, 2, 3]])
+            .build();
+        for normal in mesh.vertex_normals() {
+            assert_eq!(normal, Vector::new(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn intersect_subdivided_mesh() {
+        let shape: Shape = tetrahedron().subdivide_by(1).into();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = shape.intersect_ray(&ray, vec![]).finalise_hit().unwrap();
+        assert!(hit.t() > 4.0 && hit.t() < 5.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_mesh_with_invalid_face() {
+        Mesh::builder()
+            .set_vertices(vec![Point::new(0.0, 0.0, 0.0)])
+            .set_faces(vec![[0, 1, 2]])
+            .build();
+    }
+}
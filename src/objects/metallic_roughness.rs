@@ -0,0 +1,131 @@
+use crate::collections::{Colour, Vector};
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// roughness below this makes highlights vanishingly small (and the
+// distribution singular)
+const MIN_ROUGHNESS: Float = 0.02;
+
+// The metallic-roughness model of glTF: the material's pattern is the base
+// colour, metals tint their reflections with it and have no diffuse part,
+// and dielectrics reflect a little of all colours (depending on the index of
+// refraction) and diffuse the rest. Highlights use the Cook-Torrance model
+// with the GGX distribution, widening with roughness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MetallicRoughness {
+    pub metallic: Float,
+    pub roughness: Float,
+    pub ior: Float,
+}
+
+impl Default for MetallicRoughness {
+    fn default() -> MetallicRoughness {
+        MetallicRoughness {
+            metallic: 0.0,
+            roughness: 0.5,
+            ior: 1.5,
+        }
+    }
+}
+
+impl MetallicRoughness {
+    pub fn new(metallic: Float, roughness: Float) -> MetallicRoughness {
+        MetallicRoughness {
+            metallic,
+            roughness,
+            ..MetallicRoughness::default()
+        }
+    }
+
+    // the fraction of light arriving along lightv that leaves along eyev,
+    // including the cosine falloff, scaled by pi so that a white Lambertian
+    // surface lit head on reflects everything (as with Phong's diffuse term)
+    pub(crate) fn reflectance(
+        &self,
+        base_colour: Colour,
+        normal: Vector,
+        eyev: Vector,
+        lightv: Vector,
+    ) -> Colour {
+        let n_dot_l = normal.dot(lightv);
+        let n_dot_v = normal.dot(eyev);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let halfway = (lightv + eyev).normalise();
+        let n_dot_h = normal.dot(halfway).max(0.0);
+        let v_dot_h = eyev.dot(halfway).max(0.0);
+
+        let dielectric = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        let f0 = Colour::new(dielectric, dielectric, dielectric) * (1.0 - metallic)
+            + base_colour * metallic;
+        let fresnel = f0 + (Colour::new(1.0, 1.0, 1.0) - f0) * (1.0 - v_dot_h).powi(5);
+
+        let roughness = self.roughness.clamp(MIN_ROUGHNESS, 1.0);
+        let alpha2 = roughness.powi(4);
+        let distribution = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+        let k = (roughness + 1.0).powi(2) / 8.0;
+        let geometry = n_dot_l / (n_dot_l * (1.0 - k) + k) * n_dot_v / (n_dot_v * (1.0 - k) + k);
+        let specular = fresnel * (distribution * geometry / (4.0 * n_dot_l * n_dot_v));
+
+        let diffuse =
+            (Colour::new(1.0, 1.0, 1.0) - fresnel) * base_colour * ((1.0 - metallic) / PI);
+
+        (diffuse + specular) * (PI * n_dot_l)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    fn head_on(model: MetallicRoughness, base_colour: Colour) -> Colour {
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        model.reflectance(base_colour, normal, normal, normal)
+    }
+
+    #[test]
+    fn rough_dielectric_is_mostly_diffuse() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let colour = head_on(MetallicRoughness::new(0.0, 1.0), white);
+        // 4% is reflected specularly, and a lobe that wide adds little
+        assert!(colour.red > 0.96 && colour.red < 1.0);
+
+        let eyev = Vector::new(0.0, 0.6, -0.8);
+        let lightv = Vector::new(0.0, -0.6, -0.8);
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let away = MetallicRoughness::new(0.0, 1.0).reflectance(white, normal, eyev, lightv);
+        approx_eq!(away.red, away.green);
+        assert!(away.red < colour.red);
+    }
+
+    #[test]
+    fn metals_tint_highlights_and_have_no_diffuse() {
+        let gold = Colour::new(1.0, 0.8, 0.3);
+        let polished = head_on(MetallicRoughness::new(1.0, 0.2), gold);
+        assert!(polished.red > polished.green && polished.green > polished.blue);
+
+        // away from the mirror direction, a polished metal is dark
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let eyev = Vector::new(0.0, 0.6, -0.8);
+        let off_mirror = MetallicRoughness::new(1.0, 0.2).reflectance(gold, normal, eyev, normal);
+        assert!(off_mirror.red < 0.05 * polished.red);
+        let rough = MetallicRoughness::new(1.0, 0.8).reflectance(gold, normal, eyev, normal);
+        assert!(rough.red > off_mirror.red);
+    }
+
+    #[test]
+    fn nothing_reflected_from_behind() {
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let colour = MetallicRoughness::default().reflectance(
+            Colour::new(1.0, 1.0, 1.0),
+            normal,
+            normal,
+            -normal,
+        );
+        assert_eq!(colour, Colour::new(0.0, 0.0, 0.0));
+    }
+}
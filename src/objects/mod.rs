@@ -1,35 +1,70 @@
 pub mod bounds;
+pub mod bump_map;
 pub mod csg;
 pub mod group;
 pub mod intersections;
-pub mod light;
+pub mod kdtree;
+pub mod lights;
+pub mod lod;
 pub mod material;
+pub mod material_library;
+pub mod mesh;
+pub mod metallic_roughness;
+pub mod moving;
+pub mod packet;
+pub mod parametric;
 pub mod patterns;
 pub mod ray;
+pub mod repeat;
+pub mod shader;
 pub mod shapes;
+pub mod text;
 pub mod transform;
 
 // crate-level re-exports
 pub(crate) use bounds::*;
+pub(crate) use bump_map::*;
 pub(crate) use csg::*;
 pub(crate) use group::*;
 pub(crate) use intersections::*;
-pub(crate) use light::*;
+pub(crate) use kdtree::*;
+pub(crate) use lights::*;
+pub(crate) use lod::*;
 pub(crate) use material::*;
+pub(crate) use mesh::*;
+pub(crate) use metallic_roughness::*;
+pub(crate) use moving::*;
+pub(crate) use packet::*;
+pub(crate) use parametric::*;
 pub(crate) use patterns::*;
 pub(crate) use ray::*;
+pub(crate) use shader::*;
 pub(crate) use shapes::*;
 pub(crate) use transform::*;
 
 // public re-exports (through crate::prelude)
 pub(super) mod prelude {
+    pub use super::lights::prelude::*;
     pub use super::patterns::prelude::*;
     pub use super::shapes::prelude::*;
 
+    pub use super::bump_map::BumpMap;
     pub use super::group::Group;
-    pub use super::intersections::{Coordinates, HitRegister, Intersect};
-    pub use super::light::Light;
-    pub use super::material::Material;
+    pub use super::intersections::{
+        Computations, Coordinates, HitRegister, Intersect, LocalHits, TransformStack,
+    };
+    pub use super::kdtree::KdTree;
+    pub use super::lod::{Lod, LodMetric};
+    pub use super::material::{Material, Sidedness, Variation};
+    pub use super::material_library::MaterialLibrary;
+    pub use super::mesh::Mesh;
+    pub use super::metallic_roughness::MetallicRoughness;
+    pub use super::moving::Moving;
+    pub use super::packet::{RayPacket, PACKET_RAYS};
+    pub use super::parametric::ParametricSurface;
     pub use super::ray::Ray;
-    pub use super::transform::{Axis, Transform, TransformKind};
+    pub use super::repeat::Repeat;
+    pub use super::shader::Shader;
+    pub use super::text::{Text, TextError};
+    pub use super::transform::{Axis, Transform, TransformError, TransformKind};
 }
@@ -0,0 +1,196 @@
+use crate::objects::*;
+use crate::utils::{Buildable, ConsumingBuilder, Float};
+
+// A shape that moves while the camera's shutter is open: each ray sees the
+// object under the keyframe transform nearest the ray's time. Motion given as
+// a function of time is sampled into keyframes when set, so smooth motion
+// needs enough steps to not look stepped once blurred.
+//
+// The bounds cover the object at every keyframe.
+#[derive(Clone, Debug)]
+pub struct Moving {
+    object: Box<Shape>,
+    // sorted by ascending time, never empty
+    keyframes: Vec<(Float, Transform)>,
+    bounds: Bounds,
+}
+
+impl Moving {
+    pub fn object(&self) -> &Shape {
+        &self.object
+    }
+
+    pub fn keyframes(&self) -> &Vec<(Float, Transform)> {
+        &self.keyframes
+    }
+
+    pub fn transform_at(&self, time: Float) -> &Transform {
+        let idx_after = self
+            .keyframes
+            .partition_point(|(keyframe_time, _)| *keyframe_time <= time);
+        let idx = match idx_after {
+            0 => 0,
+            idx if idx == self.keyframes.len() => idx - 1,
+            idx if time - self.keyframes[idx - 1].0 <= self.keyframes[idx].0 - time => idx - 1,
+            idx => idx,
+        };
+        &self.keyframes[idx].1
+    }
+}
+
+impl Intersectable<dyn PrimitiveShape> for Moving {
+    fn intersect_ray_into<'world: 'ray, 'ray>(
+        &'world self,
+        world_ray: &'ray Ray,
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
+        transform_stack.push(self.transform_at(world_ray.time));
+        self.object
+            .intersect_ray_into(world_ray, transform_stack, hit_register);
+        transform_stack.pop();
+    }
+}
+
+impl Bounded for Moving {
+    fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MovingBuilder {
+    object: Option<Shape>,
+    keyframes: Option<Vec<(Float, Transform)>>,
+}
+
+impl MovingBuilder {
+    pub fn set_object(mut self, object: Shape) -> MovingBuilder {
+        self.object = Some(object);
+        self
+    }
+
+    pub fn add_keyframe(mut self, time: Float, transform: Transform) -> MovingBuilder {
+        match self.keyframes {
+            Some(ref mut keyframes) => {
+                keyframes.push((time, transform));
+            }
+            None => self.keyframes = Some(vec![(time, transform)]),
+        }
+        self
+    }
+
+    // replaces any keyframes with steps + 1 evenly spaced from start to end
+    pub fn set_motion<F: Fn(Float) -> Transform>(
+        mut self,
+        start: Float,
+        end: Float,
+        steps: usize,
+        motion: F,
+    ) -> MovingBuilder {
+        let steps = steps.max(1);
+        self.keyframes = Some(
+            (0..=steps)
+                .map(|idx| {
+                    let time = start + (end - start) * idx as Float / steps as Float;
+                    (time, motion(time))
+                })
+                .collect(),
+        );
+        self
+    }
+}
+
+impl Buildable for Moving {
+    type Builder = MovingBuilder;
+
+    fn builder() -> Self::Builder {
+        MovingBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for MovingBuilder {
+    type Built = Moving;
+
+    fn build(self) -> Self::Built {
+        let object = self
+            .object
+            .unwrap_or_else(|| Group::builder().build().into());
+        let mut keyframes = self.keyframes.unwrap_or_default();
+        if keyframes.is_empty() {
+            keyframes.push((0.0, Transform::default()));
+        }
+        keyframes.sort_by(|(time_a, _), (time_b, _)| time_a.total_cmp(time_b));
+
+        let object_bounding_box = object.bounds().bounding_box();
+        let bounds = if object_bounding_box.is_bounded() {
+            let bounding_box = keyframes
+                .iter()
+                .map(|(_, transform)| object_bounding_box.transform(transform))
+                .reduce(|bbox_a, bbox_b| bbox_a + bbox_b)
+                .unwrap();
+            Bounds::Checked(bounding_box)
+        } else {
+            Bounds::Unchecked(BoundingBox::new_unbounded())
+        };
+
+        Moving {
+            object: Box::new(object),
+            keyframes,
+            bounds,
+        }
+    }
+}
+
+impl From<Moving> for Shape {
+    fn from(moving: Moving) -> Shape {
+        Shape::Moving(moving)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::{Point, Vector};
+    use crate::utils::BuildInto;
+
+    fn sliding_sphere() -> Shape {
+        Moving::builder()
+            .set_object(Sphere::builder().build_into())
+            .set_motion(0.0, 1.0, 4, |time| {
+                Transform::new(TransformKind::Translate(4.0 * time, 0.0, 0.0))
+            })
+            .build_into()
+    }
+
+    #[test]
+    fn nearest_keyframe_is_used() {
+        let moving = Moving::builder()
+            .add_keyframe(1.0, Transform::new(TransformKind::Translate(1.0, 0.0, 0.0)))
+            .add_keyframe(0.0, Transform::default())
+            .build();
+        assert_eq!(moving.transform_at(-1.0), &Transform::default());
+        assert_eq!(moving.transform_at(0.4), &Transform::default());
+        assert_eq!(
+            moving.transform_at(0.6),
+            &Transform::new(TransformKind::Translate(1.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            moving.transform_at(2.0),
+            &Transform::new(TransformKind::Translate(1.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn rays_see_the_object_at_their_time() {
+        let sphere = sliding_sphere();
+        let ray = Ray::new(Point::new(4.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(sphere.intersect_ray(&ray, vec![]).finalise_hit().is_none());
+        let later = ray.with_time(1.0);
+        let hit_register = sphere.intersect_ray(&later, vec![]);
+        assert_eq!(hit_register.finalise_hit().unwrap().t(), 4.0);
+
+        let (x_range, _, _) = sphere.bounds().bounding_box().axial_bounds();
+        assert_eq!(x_range, [-1.0, 5.0]);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::collections::{Lanes, LANES};
+use crate::objects::{Ray, Transform, Transformable};
+
+pub const PACKET_RAYS: usize = LANES;
+
+// A few rays that start near each other and head the same way, e.g. camera
+// rays through neighbouring pixels or shadow rays from one point, that are
+// intersected together: each object's bounds are tested against all of them
+// at once (see BoundingBox::intersect_packet), and an object none of them hit
+// is skipped for all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RayPacket {
+    rays: Vec<Ray>,
+    // by axis; lanes past the last ray repeat the first
+    origins: [Lanes; 3],
+    directions: [Lanes; 3],
+    // t_min and t_max
+    ranges: [Lanes; 2],
+}
+
+impl RayPacket {
+    // between 1 and PACKET_RAYS rays
+    pub fn new(rays: &[Ray]) -> RayPacket {
+        assert!(
+            (1..=PACKET_RAYS).contains(&rays.len()),
+            "a packet holds 1 to {PACKET_RAYS} rays"
+        );
+        let lane = |idx: usize| rays.get(idx).unwrap_or(&rays[0]);
+        RayPacket {
+            rays: rays.to_vec(),
+            origins: [
+                Lanes::from_fn(|idx| lane(idx).origin.x),
+                Lanes::from_fn(|idx| lane(idx).origin.y),
+                Lanes::from_fn(|idx| lane(idx).origin.z),
+            ],
+            directions: [
+                Lanes::from_fn(|idx| lane(idx).direction.x),
+                Lanes::from_fn(|idx| lane(idx).direction.y),
+                Lanes::from_fn(|idx| lane(idx).direction.z),
+            ],
+            ranges: [
+                Lanes::from_fn(|idx| lane(idx).t_min),
+                Lanes::from_fn(|idx| lane(idx).t_max),
+            ],
+        }
+    }
+
+    // the rays in packets, in order
+    pub fn packets(rays: &[Ray]) -> impl Iterator<Item = RayPacket> + '_ {
+        rays.chunks(PACKET_RAYS).map(RayPacket::new)
+    }
+
+    pub fn rays(&self) -> &[Ray] {
+        &self.rays
+    }
+
+    pub(crate) fn origins(&self) -> &[Lanes; 3] {
+        &self.origins
+    }
+
+    pub(crate) fn directions(&self) -> &[Lanes; 3] {
+        &self.directions
+    }
+
+    pub(crate) fn ranges(&self) -> &[Lanes; 2] {
+        &self.ranges
+    }
+}
+
+// each ray transformed, e.g. into the space of a group's children
+impl Transformable for RayPacket {
+    fn transform(self, transform: &Transform) -> RayPacket {
+        let rays: Vec<Ray> = self
+            .rays
+            .into_iter()
+            .map(|ray| ray.transform(transform))
+            .collect();
+        RayPacket::new(&rays)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collections::{Point, Vector};
+    use crate::utils::Float;
+
+    use super::*;
+
+    #[test]
+    fn short_packets_repeat_their_first_ray() {
+        let rays: Vec<Ray> = (0..6)
+            .map(|idx| {
+                Ray::new(
+                    Point::new(idx as Float, 0.0, 0.0),
+                    Vector::new(0.0, 0.0, 1.0),
+                )
+            })
+            .collect();
+        let packets: Vec<RayPacket> = RayPacket::packets(&rays).collect();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].rays(), &rays[..4]);
+        assert_eq!(packets[1].rays(), &rays[4..]);
+        assert_eq!(packets[1].origins()[0].0, [4.0, 5.0, 4.0, 4.0]);
+        assert_eq!(packets[1].directions()[2].0, [1.0; 4]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fmt::{self, Debug};
+
+use crate::collections::{Point, Vector};
+use crate::objects::*;
+use crate::utils::{BuildInto, Buildable, ConsumingBuilder, Float, EPSILON};
+
+type SurfaceFunction = Box<dyn Fn(Float, Float) -> Point>;
+
+// A surface (u, v) -> Point sampled over a rectangular domain. The closure is
+// only evaluated while building; the surface keeps a grid of vertices and
+// normals, and becomes a Group of SmoothTriangles when converted into a Shape.
+//
+// Normals follow the same winding as Triangle, i.e. dP/dv x dP/du.
+#[derive(Debug)]
+pub struct ParametricSurface {
+    frame_transformation: Transform,
+    material: Material,
+    resolution: [usize; 2],
+    vertices: Vec<Point>,
+    normals: Vec<Vector>,
+}
+
+impl ParametricSurface {
+    pub fn frame_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn resolution(&self) -> [usize; 2] {
+        self.resolution
+    }
+
+    // vertices (and normals) are stored u-major, with resolution + 1 samples
+    // along each parameter
+    pub fn vertices(&self) -> &Vec<Point> {
+        &self.vertices
+    }
+
+    pub fn normals(&self) -> &Vec<Vector> {
+        &self.normals
+    }
+
+    fn index(&self, idx_u: usize, idx_v: usize) -> usize {
+        idx_u * (self.resolution[1] + 1) + idx_v
+    }
+}
+
+pub struct ParametricSurfaceBuilder {
+    frame_transformation: Option<Transform>,
+    material: Option<Material>,
+    function: Option<SurfaceFunction>,
+    u_domain: Option<[Float; 2]>,
+    v_domain: Option<[Float; 2]>,
+    resolution: Option<[usize; 2]>,
+}
+
+impl ParametricSurfaceBuilder {
+    const DEFAULT_RESOLUTION: [usize; 2] = [16, 16];
+
+    pub fn set_frame_transformation(
+        mut self,
+        frame_transformation: Transform,
+    ) -> ParametricSurfaceBuilder {
+        self.frame_transformation = Some(frame_transformation);
+        self
+    }
+
+    pub fn set_material(mut self, material: Material) -> ParametricSurfaceBuilder {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn set_function<F>(mut self, function: F) -> ParametricSurfaceBuilder
+    where
+        F: Fn(Float, Float) -> Point + 'static,
+    {
+        self.function = Some(Box::new(function));
+        self
+    }
+
+    pub fn set_u_domain(mut self, u_domain: [Float; 2]) -> ParametricSurfaceBuilder {
+        self.u_domain = Some(u_domain);
+        self
+    }
+
+    pub fn set_v_domain(mut self, v_domain: [Float; 2]) -> ParametricSurfaceBuilder {
+        self.v_domain = Some(v_domain);
+        self
+    }
+
+    // number of cells along u and v; each cell is split into two triangles
+    pub fn set_resolution(mut self, resolution: [usize; 2]) -> ParametricSurfaceBuilder {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn try_build(self) -> Result<ParametricSurface, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let function = self
+            .function
+            .expect("parametric surfaces require a function to be set");
+        let u_domain = self.u_domain.unwrap_or([0.0, 1.0]);
+        let v_domain = self.v_domain.unwrap_or([0.0, 1.0]);
+        let resolution = self
+            .resolution
+            .unwrap_or(ParametricSurfaceBuilder::DEFAULT_RESOLUTION);
+        let [res_u, res_v] = resolution;
+        assert!(
+            res_u > 0 && res_v > 0,
+            "parametric surfaces require a resolution of at least one cell along u and v"
+        );
+
+        let mut vertices = Vec::with_capacity((res_u + 1) * (res_v + 1));
+        let mut normals = Vec::with_capacity((res_u + 1) * (res_v + 1));
+        for idx_u in 0..=res_u {
+            let u = u_domain[0] + (u_domain[1] - u_domain[0]) * idx_u as Float / res_u as Float;
+            for idx_v in 0..=res_v {
+                let v = v_domain[0] + (v_domain[1] - v_domain[0]) * idx_v as Float / res_v as Float;
+                vertices.push(function(u, v));
+                normals.push(ParametricSurfaceBuilder::normal_at(
+                    &function,
+                    u,
+                    v,
+                    [u_domain, v_domain],
+                ));
+            }
+        }
+
+        Ok(ParametricSurface {
+            frame_transformation,
+            material,
+            resolution,
+            vertices,
+            normals,
+        })
+    }
+
+    // central differences, falling back to one-sided differences at the edges
+    // of the domain; at degenerate points (e.g. the poles of a sphere) the
+    // normal is taken from slightly inside the domain instead
+    fn normal_at(
+        function: &SurfaceFunction,
+        u: Float,
+        v: Float,
+        domain: [[Float; 2]; 2],
+    ) -> Vector {
+        let [u_domain, v_domain] = domain;
+        let h_u = (u_domain[1] - u_domain[0]) * 1e-4;
+        let h_v = (v_domain[1] - v_domain[0]) * 1e-4;
+
+        let derivative = |u: Float, v: Float| {
+            let (u0, u1) = ((u - h_u).max(u_domain[0]), (u + h_u).min(u_domain[1]));
+            let (v0, v1) = ((v - h_v).max(v_domain[0]), (v + h_v).min(v_domain[1]));
+            let d_u = (function(u1, v) - function(u0, v)) / (u1 - u0);
+            let d_v = (function(u, v1) - function(u, v0)) / (v1 - v0);
+            d_v.cross(d_u)
+        };
+
+        let normal = derivative(u, v);
+        if normal.magnitude() > EPSILON {
+            return normal.normalise();
+        }
+
+        let centre_u = (u_domain[0] + u_domain[1]) / 2.0;
+        let centre_v = (v_domain[0] + v_domain[1]) / 2.0;
+        let normal = derivative(u + (centre_u - u) * 1e-3, v + (centre_v - v) * 1e-3);
+        if normal.magnitude() > EPSILON {
+            normal.normalise()
+        } else {
+            Vector::zero()
+        }
+    }
+}
+
+impl Debug for ParametricSurfaceBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParametricSurfaceBuilder")
+            .field("frame_transformation", &self.frame_transformation)
+            .field("material", &self.material)
+            .field(
+                "function",
+                &self.function.as_ref().map(|_| "Fn(Float, Float) -> Point"),
+            )
+            .field("u_domain", &self.u_domain)
+            .field("v_domain", &self.v_domain)
+            .field("resolution", &self.resolution)
+            .finish()
+    }
+}
+
+impl Buildable for ParametricSurface {
+    type Builder = ParametricSurfaceBuilder;
+
+    fn builder() -> Self::Builder {
+        ParametricSurfaceBuilder {
+            frame_transformation: None,
+            material: None,
+            function: None,
+            u_domain: None,
+            v_domain: None,
+            resolution: None,
+        }
+    }
+}
+
+impl ConsumingBuilder for ParametricSurfaceBuilder {
+    type Built = ParametricSurface;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl From<ParametricSurface> for Shape {
+    fn from(parametric_surface: ParametricSurface) -> Shape {
+        let [res_u, res_v] = parametric_surface.resolution;
+        let mut triangles = Vec::with_capacity(res_u * res_v * 2);
+
+        for idx_u in 0..res_u {
+            for idx_v in 0..res_v {
+                let corners = [
+                    parametric_surface.index(idx_u, idx_v),
+                    parametric_surface.index(idx_u + 1, idx_v),
+                    parametric_surface.index(idx_u + 1, idx_v + 1),
+                    parametric_surface.index(idx_u, idx_v + 1),
+                ];
+                for [i1, i2, i3] in [
+                    [corners[0], corners[1], corners[2]],
+                    [corners[0], corners[2], corners[3]],
+                ] {
+                    let vertices = [
+                        parametric_surface.vertices[i1],
+                        parametric_surface.vertices[i2],
+                        parametric_surface.vertices[i3],
+                    ];
+                    // cells collapsing to a point or line (e.g. at poles) are skipped
+                    if (vertices[2] - vertices[0])
+                        .cross(vertices[1] - vertices[0])
+                        .magnitude()
+                        < EPSILON * EPSILON
+                    {
+                        continue;
+                    }
+
+                    triangles.push(
+                        SmoothTriangle::builder()
+                            .set_material(parametric_surface.material.clone())
+                            .set_vertices(vertices)
+                            .set_normals([
+                                parametric_surface.normals[i1],
+                                parametric_surface.normals[i2],
+                                parametric_surface.normals[i3],
+                            ])
+                            .build_into(),
+                    );
+                }
+            }
+        }
+
+        Group::builder()
+            .set_frame_transformation(parametric_surface.frame_transformation)
+            .set_objects(triangles)
+            .build_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+    use crate::utils::consts::PI;
+
+    #[test]
+    fn tessellate_flat_surface() {
+        let surface = ParametricSurface::builder()
+            .set_function(|u, v| Point::new(u, 0.0, v))
+            .set_resolution([4, 2])
+            .build();
+        assert_eq!(surface.vertices().len(), 15);
+        assert_eq!(surface.vertices()[14], Point::new(1.0, 0.0, 1.0));
+        for &normal in surface.normals() {
+            approx_eq!(normal.x, 0.0);
+            approx_eq!(normal.y, 1.0);
+            approx_eq!(normal.z, 0.0);
+        }
+
+        let shape: Shape = surface.into();
+        if let Shape::Group(group) = &shape {
+            assert_eq!(group.objects().len(), 16);
+        } else {
+            panic!("parametric surfaces should convert into groups");
+        }
+    }
+
+    #[test]
+    fn intersect_surface_of_revolution() {
+        // a unit sphere, with u sweeping around the y axis
+        let surface: Shape = ParametricSurface::builder()
+            .set_function(|u, v| Point::new(v.sin() * u.cos(), v.cos(), v.sin() * u.sin()))
+            .set_u_domain([0.0, 2.0 * PI])
+            .set_v_domain([0.0, PI])
+            .set_resolution([48, 24])
+            .build_into();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = surface.intersect_ray(&ray, vec![]).finalise_hit().unwrap();
+        assert!((hit.t() - 4.0).abs() < 0.01);
+        let normal = hit.normal();
+        assert!((normal - Vector::new(0.0, 0.0, -1.0)).magnitude() < 0.01);
+    }
+
+    #[test]
+    fn normal_at_degenerate_pole() {
+        let surface = ParametricSurface::builder()
+            .set_function(|u, v| Point::new(v.sin() * u.cos(), v.cos(), v.sin() * u.sin()))
+            .set_u_domain([0.0, 2.0 * PI])
+            .set_v_domain([0.0, PI])
+            .set_resolution([8, 8])
+            .build();
+        // the pole at v = 0 should still have a usable normal
+        let normal = surface.normals()[0];
+        approx_eq!(normal.magnitude(), 1.0);
+    }
+}
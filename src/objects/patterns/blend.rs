@@ -0,0 +1,84 @@
+use crate::collections::{Colour, Point};
+use crate::objects::{Pattern, Transform};
+use crate::utils::Float;
+
+// Mixes two patterns everywhere, with factor 0 giving only pattern1 and 1 only
+// pattern2. The transform applies on top of each pattern's own.
+#[derive(Clone, Debug)]
+pub struct Blend {
+    pub pattern1: Box<dyn Pattern>,
+    pub pattern2: Box<dyn Pattern>,
+    pub factor: Float,
+    pub transform: Transform,
+}
+
+impl PartialEq for Blend {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern1.as_ref() == other.pattern1.as_ref()
+            && self.pattern2.as_ref() == other.pattern2.as_ref()
+            && self.factor == other.factor
+            && self.transform == other.transform
+    }
+}
+
+impl Blend {
+    pub fn new(pattern1: Box<dyn Pattern>, pattern2: Box<dyn Pattern>, factor: Float) -> Blend {
+        Blend {
+            pattern1,
+            pattern2,
+            factor,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for Blend {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        self.pattern1.colour_at(pattern_point) * (1.0 - self.factor)
+            + self.pattern2.colour_at(pattern_point) * self.factor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Angle;
+    use crate::objects::{Axis, Solid, Stripe, TransformKind};
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn blend_two_solids() {
+        let blend = Blend::new(
+            Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+            Box::new(Solid::new(Colour::new(0.0, 0.0, 1.0))),
+            0.25,
+        );
+        let colour = blend.colour_at(Point::zero());
+        approx_eq!(colour.red, 0.75);
+        approx_eq!(colour.green, 0.0);
+        approx_eq!(colour.blue, 0.25);
+    }
+
+    #[test]
+    fn blend_crossed_stripes() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let stripes = Stripe::new(white, black, Transform::default());
+        let crossed_stripes = Stripe::new(
+            white,
+            black,
+            Transform::new(TransformKind::Rotate(Axis::Y, Angle::from_degrees(90.0))),
+        );
+        let blend = Blend::new(Box::new(stripes), Box::new(crossed_stripes), 0.5);
+        assert_eq!(blend.colour_at(Point::new(0.5, 0.0, -0.5)), white);
+        assert_eq!(
+            blend.colour_at(Point::new(1.5, 0.0, -0.5)),
+            Colour::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(blend.colour_at(Point::new(1.5, 0.0, -1.5)), black);
+    }
+}
@@ -27,8 +27,8 @@ impl Pattern for Checker {
         let floored_sum_of_lengths =
             (pattern_point.x.floor() + pattern_point.y.floor() + pattern_point.z.floor()) as i32;
         match floored_sum_of_lengths.rem_euclid(2) {
-            x if x == 0 => self.colour1,
-            x if x == 1 => self.colour2,
+            0 => self.colour1,
+            1 => self.colour2,
             _ => panic!(),
         }
     }
@@ -99,11 +99,11 @@ mod tests {
             colour1
         );
         assert_eq!(
-            checker_pattern.colour_at(Point::new(0.0, 0.99, 0.0)),
+            checker_pattern.colour_at(Point::new(0.0, 0.0, 0.99)),
             colour1
         );
         assert_eq!(
-            checker_pattern.colour_at(Point::new(0.0, 1.01, 0.0)),
+            checker_pattern.colour_at(Point::new(0.0, 0.0, 1.01)),
             colour2
         );
     }
@@ -0,0 +1,311 @@
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+
+use crate::collections::Colour;
+use crate::objects::UvTexture;
+use crate::scenes::{Canvas, Encoding};
+use crate::utils::{hdr, Float, HdrImage, ImageError, RawImage};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+}
+
+// A raster image in UV space, with v = 0 along the bottom row. Textures wrap
+// around in u (e.g. the seam of a spherical mapping) and are clamped in v.
+// Alpha is ignored. Texels are shared between clones, so one decoded image
+// can texture any number of shapes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTexture {
+    // the image itself, then any mipmaps, each half the size of the last
+    levels: Arc<Vec<TextureLevel>>,
+    pub filter: TextureFilter,
+    // the mipmap level sampled, blending between levels when fractional,
+    // e.g. to blur a distant or glossy-reflected texture; 0 is the image
+    pub lod: Float,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TextureLevel {
+    width: usize,
+    height: usize,
+    // rows top to bottom
+    texels: Vec<Colour>,
+}
+
+impl ImageTexture {
+    // PNG, baseline JPEG, PPM or Radiance HDR (whose colours are not clamped);
+    // 8-bit images are taken to be sRGB encoded, and decoded to linear light
+    pub fn load(path_string: &str) -> Result<ImageTexture, Box<dyn Error>> {
+        Ok(ImageTexture::from_bytes(&fs::read(path_string)?)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<ImageTexture, ImageError> {
+        if hdr::is_hdr(data) {
+            return Ok(ImageTexture::from_hdr(&hdr::decode(data)?));
+        }
+        Ok(ImageTexture::from_image(&RawImage::decode(data)?))
+    }
+
+    pub fn from_hdr(image: &HdrImage) -> ImageTexture {
+        let texels = image
+            .rgb
+            .chunks(3)
+            .map(|pixel| Colour::new(pixel[0] as Float, pixel[1] as Float, pixel[2] as Float))
+            .collect();
+        ImageTexture::from_texels(image.width, image.height, texels)
+    }
+
+    pub fn from_canvas(canvas: &Canvas) -> ImageTexture {
+        let (width, height) = (canvas.width(), canvas.height());
+        let texels = (0..height)
+            .flat_map(|row| (0..width).map(move |column| canvas[[column, row]].colour()))
+            .collect();
+        ImageTexture::from_texels(width, height, texels)
+    }
+
+    fn from_image(image: &RawImage) -> ImageTexture {
+        let texels = image
+            .rgba
+            .chunks(4)
+            .map(|pixel| {
+                let [red, green, blue] = [pixel[0], pixel[1], pixel[2]]
+                    .map(|value| Encoding::Srgb.decode(value as Float / 255.0));
+                Colour::new(red, green, blue)
+            })
+            .collect();
+        ImageTexture::from_texels(image.width, image.height, texels)
+    }
+
+    fn from_texels(width: usize, height: usize, texels: Vec<Colour>) -> ImageTexture {
+        ImageTexture {
+            levels: Arc::new(vec![TextureLevel {
+                width,
+                height,
+                texels,
+            }]),
+            filter: TextureFilter::Bilinear,
+            lod: 0.0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    // including the image itself
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    // adds mipmaps down to a single texel, each averaging 2x2 texels of the
+    // level above (repeating the last row or column of an odd-sized level)
+    pub fn with_mipmaps(mut self) -> ImageTexture {
+        if self.levels.len() > 1 {
+            return self;
+        }
+        let mut levels = vec![self.levels[0].clone()];
+        while let Some(level) = levels.last().and_then(TextureLevel::halve) {
+            levels.push(level);
+        }
+        self.levels = Arc::new(levels);
+        self
+    }
+
+    // whether two textures share their texels, rather than holding copies
+    pub fn shares_texels(&self, other: &ImageTexture) -> bool {
+        Arc::ptr_eq(&self.levels, &other.levels)
+    }
+
+    fn sample(&self, level: &TextureLevel, u: Float, v: Float) -> Colour {
+        if level.texels.is_empty() {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+        let x = u * level.width as Float;
+        let y = (1.0 - v) * level.height as Float;
+        match self.filter {
+            TextureFilter::Nearest => level.texel(x.floor() as i64, y.floor() as i64),
+            TextureFilter::Bilinear => {
+                // texel centres lie at half-integer coordinates
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (column, row) = (x.floor() as i64, y.floor() as i64);
+                let (fx, fy) = (x - x.floor(), y - y.floor());
+                let top = level.texel(column, row) * (1.0 - fx) + level.texel(column + 1, row) * fx;
+                let bottom = level.texel(column, row + 1) * (1.0 - fx)
+                    + level.texel(column + 1, row + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+impl TextureLevel {
+    fn texel(&self, column: i64, row: i64) -> Colour {
+        let column = column.rem_euclid(self.width as i64) as usize;
+        let row = row.clamp(0, self.height as i64 - 1) as usize;
+        self.texels[row * self.width + column]
+    }
+
+    // None once there is nothing left to halve
+    fn halve(&self) -> Option<TextureLevel> {
+        if self.width <= 1 && self.height <= 1 {
+            return None;
+        }
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut texels = Vec::with_capacity(width * height);
+        for row in 0..height {
+            let rows = [2 * row, (2 * row + 1).min(self.height - 1)];
+            for column in 0..width {
+                let columns = [2 * column, (2 * column + 1).min(self.width - 1)];
+                let sum = rows
+                    .iter()
+                    .flat_map(|&row| columns.map(|column| self.texels[row * self.width + column]))
+                    .fold(Colour::new(0.0, 0.0, 0.0), |sum, texel| sum + texel);
+                texels.push(sum * 0.25);
+            }
+        }
+        Some(TextureLevel {
+            width,
+            height,
+            texels,
+        })
+    }
+}
+
+impl UvTexture for ImageTexture {
+    fn uv_colour_at(&self, u: Float, v: Float) -> Colour {
+        // clamp passes NaN through, which would index no level at all
+        let lod = if self.lod.is_nan() {
+            0.0
+        } else {
+            self.lod.clamp(0.0, (self.levels.len() - 1) as Float)
+        };
+        let idx_level = lod.floor() as usize;
+        let fraction = lod - lod.floor();
+        let colour = self.sample(&self.levels[idx_level], u, v);
+        if fraction == 0.0 {
+            return colour;
+        }
+        colour * (1.0 - fraction) + self.sample(&self.levels[idx_level + 1], u, v) * fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::{Height, Width};
+    use crate::utils::approx_eq;
+
+    // 2x2: red, green along the top, blue, white along the bottom
+    fn quad() -> ImageTexture {
+        ImageTexture::from_bytes(b"P3 2 2 255 255 0 0 0 255 0 0 0 255 255 255 255").unwrap()
+    }
+
+    #[test]
+    fn nearest_filtering() {
+        let texture = ImageTexture {
+            filter: TextureFilter::Nearest,
+            ..quad()
+        };
+        let cases = [
+            ((0.25, 0.75), Colour::new(1.0, 0.0, 0.0)),
+            ((0.75, 0.75), Colour::new(0.0, 1.0, 0.0)),
+            ((0.25, 0.25), Colour::new(0.0, 0.0, 1.0)),
+            ((0.75, 0.0), Colour::new(1.0, 1.0, 1.0)),
+            // wraps in u, clamps in v
+            ((1.25, 1.0), Colour::new(1.0, 0.0, 0.0)),
+            ((-0.25, -0.5), Colour::new(1.0, 1.0, 1.0)),
+        ];
+        for ((u, v), colour) in cases {
+            assert_eq!(texture.uv_colour_at(u, v), colour);
+        }
+    }
+
+    #[test]
+    fn hdr_texels_are_not_clamped() {
+        let data = crate::utils::hdr::encode(1, 1, &[16.0, 0.5, 0.0]);
+        let texture = ImageTexture::from_bytes(&data).unwrap();
+        assert_eq!(texture.uv_colour_at(0.5, 0.5), Colour::new(16.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn bilinear_filtering() {
+        let texture = quad();
+        assert_eq!(texture.uv_colour_at(0.25, 0.75), Colour::new(1.0, 0.0, 0.0));
+
+        let centre = texture.uv_colour_at(0.5, 0.5);
+        approx_eq!(centre.red, 0.5);
+        approx_eq!(centre.green, 0.5);
+        approx_eq!(centre.blue, 0.5);
+
+        // halfway between the top texels, and across the seam in u
+        let top = texture.uv_colour_at(0.5, 0.75);
+        assert_eq!(top, Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(texture.uv_colour_at(0.0, 0.75), top);
+    }
+
+    #[test]
+    fn mipmaps_average_texels() {
+        let texture = quad().with_mipmaps();
+        assert_eq!(texture.mip_levels(), 2);
+        assert!(texture.shares_texels(&texture.clone()));
+
+        let blurred = ImageTexture {
+            lod: 1.0,
+            ..texture
+        };
+        let average = blurred.uv_colour_at(0.1, 0.9);
+        approx_eq!(average.red, 0.5);
+        approx_eq!(average.green, 0.5);
+        approx_eq!(average.blue, 0.5);
+
+        // halfway between the image and its average
+        let halfway = ImageTexture {
+            lod: 0.5,
+            ..blurred
+        };
+        let colour = halfway.uv_colour_at(0.25, 0.75);
+        approx_eq!(colour.red, 0.75);
+        approx_eq!(colour.green, 0.25);
+
+        // an undefined level samples the image itself
+        let undefined = ImageTexture {
+            lod: Float::NAN,
+            ..halfway
+        };
+        assert_eq!(
+            undefined.uv_colour_at(0.25, 0.75),
+            quad().uv_colour_at(0.25, 0.75)
+        );
+    }
+
+    #[test]
+    fn texture_from_canvas() {
+        let mut canvas = Canvas::new(Width(3), Height(1));
+        canvas
+            .paint_colour_replace(2, 0, Colour::new(0.2, 0.4, 0.6))
+            .unwrap();
+        let texture = ImageTexture {
+            filter: TextureFilter::Nearest,
+            ..ImageTexture::from_canvas(&canvas)
+        };
+        assert_eq!((texture.width(), texture.height()), (3, 1));
+        assert_eq!(texture.uv_colour_at(0.9, 0.5), Colour::new(0.2, 0.4, 0.6));
+        assert_eq!(texture.uv_colour_at(0.1, 0.5), Colour::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn load_textures_from_files() {
+        let png = ImageTexture::load("./resources/test_inputs/python.png").unwrap();
+        let jpeg = ImageTexture::load("./resources/test_inputs/python.jpg").unwrap();
+        assert_eq!((png.width(), png.height()), (16, 16));
+        assert_eq!((jpeg.width(), jpeg.height()), (16, 16));
+        assert!(ImageTexture::load("./resources/test_inputs/missing.png").is_err());
+    }
+}
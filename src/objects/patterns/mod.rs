@@ -1,24 +1,57 @@
+pub mod blend;
 pub mod checker;
 pub mod gradient;
+pub mod image_texture;
+pub mod nested_checker;
 pub mod pattern;
+pub mod perturbed;
+pub mod radial_gradient;
 pub mod ring;
 pub mod solid;
 pub mod stripe;
+pub mod texture_cache;
+pub mod texture_map;
+pub mod uv_checker;
+pub mod uv_mapping;
+pub mod uv_texture;
+pub mod varied;
 
 // crate-level re-exports
+pub use blend::*;
 pub use checker::*;
 pub use gradient::*;
+pub use image_texture::*;
+pub use nested_checker::*;
 pub use pattern::*;
+pub use perturbed::*;
+pub use radial_gradient::*;
 pub use ring::*;
 pub use solid::*;
 pub use stripe::*;
+pub use texture_cache::*;
+pub use texture_map::*;
+pub use uv_checker::*;
+pub use uv_mapping::*;
+pub use uv_texture::*;
+pub use varied::*;
 
 // public re-exports (through crate::prelude)
 pub mod prelude {
+    pub use super::blend::Blend;
     pub use super::checker::Checker;
     pub use super::gradient::Gradient;
+    pub use super::image_texture::{ImageTexture, TextureFilter};
+    pub use super::nested_checker::NestedChecker;
     pub use super::pattern::Pattern;
+    pub use super::perturbed::Perturbed;
+    pub use super::radial_gradient::RadialGradient;
     pub use super::ring::Ring;
     pub use super::solid::Solid;
     pub use super::stripe::Stripe;
+    pub use super::texture_cache::TextureCache;
+    pub use super::texture_map::TextureMap;
+    pub use super::uv_checker::UvChecker;
+    pub use super::uv_mapping::{CubeFace, UvMapping};
+    pub use super::uv_texture::UvTexture;
+    pub use super::varied::Varied;
 }
@@ -0,0 +1,77 @@
+use crate::collections::{Colour, Point};
+use crate::objects::{Pattern, Transform};
+
+// A 3D checker whose alternating cubes are filled with patterns instead of
+// flat colours. The transform applies on top of each pattern's own.
+#[derive(Clone, Debug)]
+pub struct NestedChecker {
+    pub pattern1: Box<dyn Pattern>,
+    pub pattern2: Box<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl PartialEq for NestedChecker {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern1.as_ref() == other.pattern1.as_ref()
+            && self.pattern2.as_ref() == other.pattern2.as_ref()
+            && self.transform == other.transform
+    }
+}
+
+impl NestedChecker {
+    pub fn new(
+        pattern1: Box<dyn Pattern>,
+        pattern2: Box<dyn Pattern>,
+        transform: Transform,
+    ) -> NestedChecker {
+        NestedChecker {
+            pattern1,
+            pattern2,
+            transform,
+        }
+    }
+}
+
+impl Pattern for NestedChecker {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        let floored_sum_of_lengths =
+            (pattern_point.x.floor() + pattern_point.y.floor() + pattern_point.z.floor()) as i32;
+        match floored_sum_of_lengths.rem_euclid(2) {
+            0 => self.pattern1.colour_at(pattern_point),
+            1 => self.pattern2.colour_at(pattern_point),
+            _ => panic!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::{Solid, Stripe, TransformKind};
+
+    use super::*;
+
+    #[test]
+    fn nested_checker_fills_cubes_with_patterns() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let red = Colour::new(1.0, 0.0, 0.0);
+        let fine_stripes = Stripe::new(
+            white,
+            black,
+            Transform::new(TransformKind::Scale(0.25, 1.0, 1.0)),
+        );
+        let checker = NestedChecker::new(
+            Box::new(fine_stripes),
+            Box::new(Solid::new(red)),
+            Transform::default(),
+        );
+        assert_eq!(checker.colour_at(Point::new(0.1, 0.0, 0.0)), white);
+        assert_eq!(checker.colour_at(Point::new(0.3, 0.0, 0.0)), black);
+        assert_eq!(checker.colour_at(Point::new(1.1, 0.0, 0.0)), red);
+        assert_eq!(checker.colour_at(Point::new(0.1, 0.0, 1.1)), red);
+    }
+}
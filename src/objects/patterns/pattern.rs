@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use crate::collections::{Colour, Point};
 use crate::objects::{Transform, Transformable};
 
-pub trait Pattern: Debug {
+pub trait Pattern: Debug + PatternClone + Send + Sync {
     fn colour_at(&self, shape_point: Point) -> Colour {
         let pattern_point = shape_point.transform(&self.frame_transformation().invert());
         self.local_colour_at(pattern_point)
@@ -13,6 +13,27 @@ pub trait Pattern: Debug {
     fn local_colour_at(&self, pattern_point: Point) -> Colour;
 }
 
+// allows materials (and their boxed patterns) to be cloned, e.g. when one
+// material is shared across every face of a mesh
+pub trait PatternClone {
+    fn clone_box(&self) -> Box<dyn Pattern>;
+}
+
+impl<T> PatternClone for T
+where
+    T: Pattern + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn Pattern> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Pattern> {
+    fn clone(&self) -> Box<dyn Pattern> {
+        self.clone_box()
+    }
+}
+
 impl PartialEq for dyn Pattern {
     fn eq(&self, other: &Self) -> bool {
         format!("{:?}", self) == format!("{:?}", other)
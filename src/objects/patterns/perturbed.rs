@@ -0,0 +1,105 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{Pattern, Transform};
+use crate::utils::{Float, Perlin};
+
+// Wraps another pattern, jittering the points it is looked up at by fractal
+// Perlin noise, e.g. turning stripes into marble or rings into wood grain.
+// Each axis is displaced by up to about amplitude, with features roughly
+// 1 / frequency across. The transform applies on top of the wrapped
+// pattern's own.
+#[derive(Clone, Debug)]
+pub struct Perturbed {
+    pub pattern: Box<dyn Pattern>,
+    pub noise: Perlin,
+    pub amplitude: Float,
+    pub frequency: Float,
+    pub octaves: usize,
+    pub transform: Transform,
+}
+
+impl PartialEq for Perturbed {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_ref() == other.pattern.as_ref()
+            && self.noise == other.noise
+            && self.amplitude == other.amplitude
+            && self.frequency == other.frequency
+            && self.octaves == other.octaves
+            && self.transform == other.transform
+    }
+}
+
+impl Perturbed {
+    pub fn new(pattern: Box<dyn Pattern>, amplitude: Float) -> Perturbed {
+        Perturbed {
+            pattern,
+            noise: Perlin::default(),
+            amplitude,
+            frequency: 1.0,
+            octaves: 3,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for Perturbed {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        let Point { x, y, z } = pattern_point;
+        let frequency = self.frequency;
+        // sample well apart for each axis, so the displacements are unrelated
+        let displacement_along = |offset: Float| {
+            let sample = Point::new(
+                x * frequency + offset,
+                y * frequency + offset,
+                z * frequency + offset,
+            );
+            self.noise.fractal(sample, self.octaves) * self.amplitude
+        };
+        let displacement = Vector::new(
+            displacement_along(0.0),
+            displacement_along(31.416),
+            displacement_along(71.828),
+        );
+        self.pattern.colour_at(pattern_point + displacement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Stripe;
+
+    fn stripes() -> Box<dyn Pattern> {
+        Box::new(Stripe::new(
+            Colour::new(1.0, 1.0, 1.0),
+            Colour::new(0.0, 0.0, 0.0),
+            Transform::default(),
+        ))
+    }
+
+    #[test]
+    fn zero_amplitude_leaves_pattern_unchanged() {
+        let perturbed = Perturbed::new(stripes(), 0.0);
+        for step in 0..20 {
+            let point = Point::new(step as Float * 0.37, 0.2, -0.9);
+            assert_eq!(perturbed.colour_at(point), stripes().colour_at(point));
+        }
+    }
+
+    #[test]
+    fn perturbation_moves_stripe_edges() {
+        let perturbed = Perturbed {
+            noise: Perlin::new(11),
+            ..Perturbed::new(stripes(), 0.5)
+        };
+        let differing = (0..200)
+            .map(|step| Point::new(step as Float * 0.05, 0.3, 0.7))
+            .filter(|&point| perturbed.colour_at(point) != stripes().colour_at(point))
+            .count();
+        assert!(differing > 0 && differing < 200);
+        assert_eq!(perturbed, perturbed.clone());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::collections::{Colour, Point};
+use crate::objects::{Pattern, Transform};
+
+// Blends from pattern1 on the y axis to pattern2 at a distance of 1 from it,
+// repeating outwards like Ring. The transform applies on top of each
+// pattern's own.
+#[derive(Clone, Debug)]
+pub struct RadialGradient {
+    pub pattern1: Box<dyn Pattern>,
+    pub pattern2: Box<dyn Pattern>,
+    pub transform: Transform,
+}
+
+impl PartialEq for RadialGradient {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern1.as_ref() == other.pattern1.as_ref()
+            && self.pattern2.as_ref() == other.pattern2.as_ref()
+            && self.transform == other.transform
+    }
+}
+
+impl RadialGradient {
+    pub fn new(
+        pattern1: Box<dyn Pattern>,
+        pattern2: Box<dyn Pattern>,
+        transform: Transform,
+    ) -> RadialGradient {
+        RadialGradient {
+            pattern1,
+            pattern2,
+            transform,
+        }
+    }
+}
+
+impl Pattern for RadialGradient {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        let distance = (pattern_point.x.powi(2) + pattern_point.z.powi(2)).sqrt();
+        let fraction = distance - distance.floor();
+        self.pattern1.colour_at(pattern_point) * (1.0 - fraction)
+            + self.pattern2.colour_at(pattern_point) * fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::Solid;
+    use crate::utils::approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn radial_gradient_between_solids() {
+        let gradient = RadialGradient::new(
+            Box::new(Solid::new(Colour::new(1.0, 1.0, 1.0))),
+            Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+            Transform::default(),
+        );
+        assert_eq!(
+            gradient.colour_at(Point::zero()),
+            Colour::new(1.0, 1.0, 1.0)
+        );
+        let colour = gradient.colour_at(Point::new(0.3, 5.0, 0.4));
+        approx_eq!(colour.red, 0.5);
+        // repeats every unit of distance
+        let colour = gradient.colour_at(Point::new(0.0, 0.0, 1.25));
+        approx_eq!(colour.green, 0.75);
+    }
+}
@@ -26,8 +26,8 @@ impl Pattern for Ring {
     fn local_colour_at(&self, pattern_point: Point) -> Colour {
         let squared_magnitude = pattern_point.x.powi(2) + pattern_point.z.powi(2);
         match (squared_magnitude.sqrt().floor() as i32).rem_euclid(2) {
-            x if x == 0 => self.colour1,
-            x if x == 1 => self.colour2,
+            0 => self.colour1,
+            1 => self.colour2,
             _ => panic!(),
         }
     }
@@ -26,8 +26,8 @@ impl Pattern for Stripe {
 
     fn local_colour_at(&self, pattern_point: Point) -> Colour {
         match (pattern_point.x.floor() as i32).rem_euclid(2) {
-            x if x == 0 => self.colour1,
-            x if x == 1 => self.colour2,
+            0 => self.colour1,
+            1 => self.colour2,
             _ => panic!(),
         }
     }
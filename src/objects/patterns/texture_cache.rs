@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::objects::ImageTexture;
+
+// Image textures by file, each decoded the first time it is asked for. Every
+// texture handed out shares the cached texels, so a scene that puts the same
+// image on hundreds of shapes holds it in memory once.
+#[derive(Clone, Debug, Default)]
+pub struct TextureCache {
+    textures: HashMap<PathBuf, ImageTexture>,
+    // whether textures get mipmaps when they are first loaded
+    pub mipmaps: bool,
+}
+
+impl TextureCache {
+    pub fn new() -> TextureCache {
+        TextureCache::default()
+    }
+
+    // paths naming the same file (e.g. relative and absolute) share an entry
+    pub fn load(&mut self, path_string: &str) -> Result<ImageTexture, Box<dyn Error>> {
+        let key = fs::canonicalize(path_string).unwrap_or_else(|_| PathBuf::from(path_string));
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let mut texture = ImageTexture::load(path_string)?;
+        if self.mipmaps {
+            texture = texture.with_mipmaps();
+        }
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    // textures already handed out keep their texels
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn load_each_file_once() {
+        let mut cache = TextureCache::new();
+        let first = cache.load("./resources/test_inputs/python.png").unwrap();
+        let absolute = env::current_dir()
+            .unwrap()
+            .join("resources/test_inputs/python.png");
+        let second = cache.load(absolute.to_str().unwrap()).unwrap();
+        assert!(first.shares_texels(&second));
+        assert_eq!(cache.len(), 1);
+
+        let other = cache.load("./resources/test_inputs/python.jpg").unwrap();
+        assert!(!other.shares_texels(&first));
+        assert_eq!(cache.len(), 2);
+
+        // failures are not cached
+        assert!(cache.load("./resources/test_inputs/missing.png").is_err());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn mipmapped_on_first_load() {
+        let mut cache = TextureCache {
+            mipmaps: true,
+            ..TextureCache::new()
+        };
+        let texture = cache.load("./resources/test_inputs/python.png").unwrap();
+        // 16x16 down to 1x1
+        assert_eq!(texture.mip_levels(), 5);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::collections::{Colour, Point};
+use crate::objects::{Pattern, Transform, UvMapping, UvTexture};
+
+// Wraps a texture in UV space around objects, projecting pattern space points
+// to UV coordinates with the mapping.
+#[derive(Clone, Debug)]
+pub struct TextureMap {
+    pub texture: Box<dyn UvTexture>,
+    pub mapping: UvMapping,
+    pub transform: Transform,
+}
+
+impl PartialEq for TextureMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.texture.as_ref() == other.texture.as_ref()
+            && self.mapping == other.mapping
+            && self.transform == other.transform
+    }
+}
+
+impl TextureMap {
+    pub fn new(texture: Box<dyn UvTexture>, mapping: UvMapping) -> TextureMap {
+        TextureMap {
+            texture,
+            mapping,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl Pattern for TextureMap {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        let (u, v) = self.mapping.uv_at(pattern_point);
+        self.texture.uv_colour_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::UvChecker;
+
+    use super::*;
+
+    #[test]
+    fn checkers_on_a_sphere() {
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let texture_map = TextureMap::new(
+            Box::new(UvChecker::new(16, 8, black, white)),
+            UvMapping::Spherical,
+        );
+        let cases = [
+            (Point::new(0.4315, 0.4670, 0.7719), white),
+            (Point::new(-0.9654, 0.2552, -0.0534), black),
+            (Point::new(0.1039, 0.7090, 0.6975), white),
+            (Point::new(-0.4986, -0.7856, -0.3663), black),
+            (Point::new(-0.0317, -0.9395, 0.3411), black),
+            (Point::new(0.4809, -0.7721, 0.4154), black),
+            (Point::new(0.0285, -0.9612, -0.2745), black),
+            (Point::new(-0.5734, -0.2162, -0.7903), white),
+            (Point::new(0.7688, -0.1470, 0.6223), black),
+            (Point::new(-0.7652, 0.2175, 0.6060), black),
+        ];
+        for (point, colour) in cases {
+            assert_eq!(texture_map.colour_at(point), colour);
+        }
+    }
+}
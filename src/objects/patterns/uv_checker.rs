@@ -0,0 +1,57 @@
+use crate::collections::Colour;
+use crate::objects::UvTexture;
+use crate::utils::Float;
+
+// width squares across and height squares up the texture
+#[derive(Clone, Debug, PartialEq)]
+pub struct UvChecker {
+    pub width: usize,
+    pub height: usize,
+    pub colour1: Colour,
+    pub colour2: Colour,
+}
+
+impl UvChecker {
+    pub fn new(width: usize, height: usize, colour1: Colour, colour2: Colour) -> UvChecker {
+        UvChecker {
+            width,
+            height,
+            colour1,
+            colour2,
+        }
+    }
+}
+
+impl UvTexture for UvChecker {
+    fn uv_colour_at(&self, u: Float, v: Float) -> Colour {
+        let square_u = (u * self.width as Float).floor() as i64;
+        let square_v = (v * self.height as Float).floor() as i64;
+        match (square_u + square_v).rem_euclid(2) {
+            0 => self.colour1,
+            1 => self.colour2,
+            _ => panic!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checker_in_uv_space() {
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let checker = UvChecker::new(2, 2, black, white);
+        let cases = [
+            ((0.0, 0.0), black),
+            ((0.5, 0.0), white),
+            ((0.0, 0.5), white),
+            ((0.5, 0.5), black),
+            ((1.0, 1.0), black),
+        ];
+        for ((u, v), colour) in cases {
+            assert_eq!(checker.uv_colour_at(u, v), colour);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::collections::Point;
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+// Projections from a point in object space to texture coordinates, with u
+// increasing to the right and v upwards, both within [0, 1).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvMapping {
+    // for unit spheres about the origin; u wraps around the y axis and v runs
+    // from pole to pole
+    Spherical,
+    // the xz plane, repeating every unit
+    Planar,
+    // u wraps around the y axis, and v repeats every unit along it
+    Cylindrical,
+    // for the cube from -1 to 1; every face is mapped onto the whole texture,
+    // seen from outside the cube
+    Cubic,
+}
+
+impl UvMapping {
+    pub fn uv_at(&self, point: Point) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => UvMapping::spherical(point),
+            UvMapping::Planar => UvMapping::planar(point),
+            UvMapping::Cylindrical => UvMapping::cylindrical(point),
+            UvMapping::Cubic => UvMapping::cubic(point).1,
+        }
+    }
+
+    pub fn spherical(point: Point) -> (Float, Float) {
+        let Point { x, y, z } = point;
+        let radius = (x * x + y * y + z * z).sqrt();
+        if radius == 0.0 {
+            return (0.0, 0.5);
+        }
+        let phi = (y / radius).clamp(-1.0, 1.0).acos();
+        (UvMapping::around_y_axis(x, z), 1.0 - phi / PI)
+    }
+
+    pub fn planar(point: Point) -> (Float, Float) {
+        (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+    }
+
+    pub fn cylindrical(point: Point) -> (Float, Float) {
+        (
+            UvMapping::around_y_axis(point.x, point.z),
+            point.y.rem_euclid(1.0),
+        )
+    }
+
+    pub fn cubic(point: Point) -> (CubeFace, (Float, Float)) {
+        let Point { x, y, z } = point;
+        let largest = x.abs().max(y.abs()).max(z.abs());
+        // maps [-1, 1] onto [0, 1)
+        let unit = |coordinate: Float| (coordinate + 1.0).rem_euclid(2.0) / 2.0;
+
+        if largest == x {
+            (CubeFace::Right, (unit(-z), unit(y)))
+        } else if largest == -x {
+            (CubeFace::Left, (unit(z), unit(y)))
+        } else if largest == y {
+            (CubeFace::Up, (unit(x), unit(-z)))
+        } else if largest == -y {
+            (CubeFace::Down, (unit(x), unit(z)))
+        } else if largest == z {
+            (CubeFace::Front, (unit(x), unit(y)))
+        } else {
+            (CubeFace::Back, (unit(-x), unit(y)))
+        }
+    }
+
+    // increases anticlockwise seen from above, starting (and ending) at -z
+    fn around_y_axis(x: Float, z: Float) -> Float {
+        let theta = x.atan2(z);
+        (1.0 - (theta / (2.0 * PI) + 0.5)).rem_euclid(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+    use crate::utils::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn spherical_mapping() {
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Point::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Point::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Point::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Point::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (Point::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), (0.25, 0.75)),
+        ];
+        for (point, (u, v)) in cases {
+            let uv = UvMapping::Spherical.uv_at(point);
+            approx_eq!(uv.0, u);
+            approx_eq!(uv.1, v);
+        }
+    }
+
+    #[test]
+    fn planar_mapping() {
+        assert_eq!(
+            UvMapping::Planar.uv_at(Point::new(0.25, 0.0, 0.5)),
+            (0.25, 0.5)
+        );
+        assert_eq!(
+            UvMapping::Planar.uv_at(Point::new(-0.25, 0.5, -0.25)),
+            (0.75, 0.75)
+        );
+        assert_eq!(
+            UvMapping::Planar.uv_at(Point::new(1.25, 0.0, 0.5)),
+            (0.25, 0.5)
+        );
+    }
+
+    #[test]
+    fn cylindrical_mapping() {
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (Point::new(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (Point::new(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (Point::new(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.125, 0.5)),
+            (Point::new(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (Point::new(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), (0.375, 0.5)),
+            (Point::new(0.0, -0.25, 1.0), (0.5, 0.75)),
+            (Point::new(-1.0, 1.25, 0.0), (0.75, 0.25)),
+        ];
+        for (point, (u, v)) in cases {
+            let uv = UvMapping::Cylindrical.uv_at(point);
+            approx_eq!(uv.0, u);
+            approx_eq!(uv.1, v);
+        }
+    }
+
+    #[test]
+    fn cubic_mapping() {
+        let cases = [
+            (Point::new(-1.0, 0.5, -0.25), CubeFace::Left, (0.375, 0.75)),
+            (Point::new(1.1, -0.75, 0.8), CubeFace::Right, (0.1, 0.125)),
+            (Point::new(0.1, 0.6, 0.9), CubeFace::Front, (0.55, 0.8)),
+            (Point::new(-0.7, 0.1, -2.0), CubeFace::Back, (0.85, 0.55)),
+            (Point::new(-0.8, 1.7, -0.3), CubeFace::Up, (0.1, 0.65)),
+            (Point::new(0.5, -1.0, 0.25), CubeFace::Down, (0.75, 0.625)),
+        ];
+        for (point, face, (u, v)) in cases {
+            let (mapped_face, uv) = UvMapping::cubic(point);
+            assert_eq!(mapped_face, face);
+            approx_eq!(uv.0, u);
+            approx_eq!(uv.1, v);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt::Debug;
+
+use crate::collections::Colour;
+use crate::utils::Float;
+
+// A texture in UV space, with u and v within [0, 1); see TextureMap.
+pub trait UvTexture: Debug + UvTextureClone + Send + Sync {
+    fn uv_colour_at(&self, u: Float, v: Float) -> Colour;
+}
+
+pub trait UvTextureClone {
+    fn clone_box(&self) -> Box<dyn UvTexture>;
+}
+
+impl<T> UvTextureClone for T
+where
+    T: UvTexture + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn UvTexture> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn UvTexture> {
+    fn clone(&self) -> Box<dyn UvTexture> {
+        self.clone_box()
+    }
+}
+
+impl PartialEq for dyn UvTexture {
+    fn eq(&self, other: &Self) -> bool {
+        format!("{:?}", self) == format!("{:?}", other)
+    }
+}
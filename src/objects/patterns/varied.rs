@@ -0,0 +1,109 @@
+use crate::collections::{Colour, Point};
+use crate::objects::{Pattern, Transform};
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// Wraps another pattern, rotating its hue (in turns) and scaling its
+// brightness. The transform applies on top of the wrapped pattern's own.
+#[derive(Clone, Debug)]
+pub struct Varied {
+    pub pattern: Box<dyn Pattern>,
+    pub hue_shift: Float,
+    pub brightness: Float,
+    pub transform: Transform,
+}
+
+impl PartialEq for Varied {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_ref() == other.pattern.as_ref()
+            && self.hue_shift == other.hue_shift
+            && self.brightness == other.brightness
+            && self.transform == other.transform
+    }
+}
+
+impl Varied {
+    pub fn new(pattern: Box<dyn Pattern>, hue_shift: Float, brightness: Float) -> Varied {
+        Varied {
+            pattern,
+            hue_shift,
+            brightness,
+            transform: Transform::default(),
+        }
+    }
+
+    // rotation about the grey axis, which keeps greys grey
+    fn rotate_hue(&self, colour: Colour) -> Colour {
+        let angle = self.hue_shift * 2.0 * PI;
+        let cos = angle.cos();
+        let c = (1.0 - cos) / 3.0;
+        let s = angle.sin() / Float::sqrt(3.0);
+        let Colour { red, green, blue } = colour;
+
+        Colour::new(
+            red * (cos + c) + green * (c - s) + blue * (c + s),
+            red * (c + s) + green * (cos + c) + blue * (c - s),
+            red * (c - s) + green * (c + s) + blue * (cos + c),
+        )
+    }
+}
+
+impl Pattern for Varied {
+    fn frame_transformation(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_colour_at(&self, pattern_point: Point) -> Colour {
+        self.rotate_hue(self.pattern.colour_at(pattern_point)) * self.brightness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{Solid, Stripe, TransformKind};
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn third_turn_maps_red_to_green() {
+        let varied = Varied::new(
+            Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+            1.0 / 3.0,
+            0.5,
+        );
+        let colour = varied.colour_at(Point::zero());
+        approx_eq!(colour.red, 0.0);
+        approx_eq!(colour.green, 0.5);
+        approx_eq!(colour.blue, 0.0);
+    }
+
+    #[test]
+    fn hue_shift_keeps_greys() {
+        let varied = Varied::new(Box::new(Solid::new(Colour::new(0.4, 0.4, 0.4))), 0.123, 1.0);
+        let colour = varied.colour_at(Point::zero());
+        approx_eq!(colour.red, 0.4);
+        approx_eq!(colour.green, 0.4);
+        approx_eq!(colour.blue, 0.4);
+    }
+
+    #[test]
+    fn transform_scales_wrapped_pattern() {
+        let stripes = Stripe::new(
+            Colour::new(1.0, 1.0, 1.0),
+            Colour::new(0.0, 0.0, 0.0),
+            Transform::default(),
+        );
+        let varied = Varied {
+            transform: Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)),
+            ..Varied::new(Box::new(stripes), 0.0, 1.0)
+        };
+        assert_eq!(
+            varied.colour_at(Point::new(1.5, 0.0, 0.0)),
+            Colour::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            varied.colour_at(Point::new(2.5, 0.0, 0.0)),
+            Colour::new(0.0, 0.0, 0.0)
+        );
+    }
+}
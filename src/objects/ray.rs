@@ -1,29 +1,78 @@
-use crate::collections::{Point, Vector};
-
 use super::{Transform, Transformable};
+use crate::collections::{Point, Vector};
+use crate::utils::{widen, Float};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Vector,
+    // in nanometres, for spectral rendering; None for all wavelengths at once
+    pub wavelength: Option<Float>,
+    // when the ray is cast, for motion blur (see Moving); rays cast from a
+    // surface carry on with the time of the ray that hit it
+    pub time: Float,
+    // the ray only finds intersections from t_min up to (but not including)
+    // t_max: by default everything in front of its origin, while shadow rays
+    // stop at their light; objects entirely outside the range are skipped
+    pub t_min: Float,
+    pub t_max: Float,
 }
 
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            wavelength: None,
+            time: 0.0,
+            t_min: 0.0,
+            t_max: Float::INFINITY,
+        }
+    }
+
+    pub fn with_wavelength(self, wavelength: Option<Float>) -> Ray {
+        Ray { wavelength, ..self }
     }
 
-    pub fn position(&self, t: f64) -> Point {
+    pub fn with_time(self, time: Float) -> Ray {
+        Ray { time, ..self }
+    }
+
+    pub fn with_range(self, t_min: Float, t_max: Float) -> Ray {
+        Ray {
+            t_min,
+            t_max,
+            ..self
+        }
+    }
+
+    pub fn in_range(&self, t: Float) -> bool {
+        self.t_min <= t && t < self.t_max
+    }
+
+    pub fn position(&self, t: Float) -> Point {
         self.origin + t * self.direction
     }
+
+    // the origin and direction in f64, for the coefficients of quadratic_roots
+    pub(crate) fn widened(&self) -> ([f64; 3], [f64; 3]) {
+        let Ray {
+            origin, direction, ..
+        } = self;
+        (
+            [origin.x, origin.y, origin.z].map(widen),
+            [direction.x, direction.y, direction.z].map(widen),
+        )
+    }
 }
 
 impl Transformable for Ray {
     fn transform(self, transform: &Transform) -> Self {
-        Ray::new(
-            self.origin.transform(transform),
-            self.direction.transform(transform),
-        )
+        Ray {
+            origin: self.origin.transform(transform),
+            direction: self.direction.transform(transform),
+            ..self
+        }
     }
 }
 
@@ -39,10 +88,25 @@ mod tests {
         let resulting_ray = Ray {
             origin: Point::new(1.0, 2.0, 3.0),
             direction: Vector::new(6.0, 5.0, 4.0),
+            wavelength: None,
+            time: 0.0,
+            t_min: 0.0,
+            t_max: Float::INFINITY,
         };
         assert_eq!(ray, resulting_ray);
     }
 
+    #[test]
+    fn ray_range() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(ray.in_range(0.0) && ray.in_range(1e9));
+        assert!(!ray.in_range(-0.1));
+
+        let ray = ray.with_range(1.0, 2.0);
+        assert!(ray.in_range(1.0) && ray.in_range(1.5));
+        assert!(!ray.in_range(0.5) && !ray.in_range(2.0));
+    }
+
     #[test]
     fn ray_position() {
         let ray = Ray::new(Point::new(2.0, 3.0, 4.0), Vector::new(1.0, 0.0, 0.0));
@@ -0,0 +1,113 @@
+use crate::collections::Angle;
+use crate::objects::*;
+use crate::utils::consts::TAU;
+use crate::utils::{Buildable, ConsumingBuilder, Float};
+
+// Repeats a shape count times, each copy moved by transform_step from the
+// last, the first copy staying where it is. The copies share nothing, and are
+// ordinary groups once expanded.
+//
+// Repeats nest, e.g. a row of 10 along x expanded again into 10 rows along z
+// is a 10x10 grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repeat {
+    pub count: usize,
+    pub transform_step: Transform,
+}
+
+impl Repeat {
+    pub fn new(count: usize, transform_step: Transform) -> Repeat {
+        Repeat {
+            count,
+            transform_step,
+        }
+    }
+
+    // count copies evenly spaced around a full turn about the axis
+    pub fn ring(count: usize, axis: Axis) -> Repeat {
+        let step = Angle::from_radians(TAU / count.max(1) as Float);
+        Repeat::new(count, Transform::new(TransformKind::Rotate(axis, step)))
+    }
+
+    pub fn expand(&self, shape: &Shape) -> Shape {
+        let mut instances = Vec::with_capacity(self.count);
+        let mut offset = Transform::default();
+        for _ in 0..self.count {
+            instances.push(
+                Group::builder()
+                    .set_frame_transformation(offset.clone())
+                    .add_object(shape.clone())
+                    .build()
+                    .into(),
+            );
+            offset = offset.compose(&self.transform_step);
+        }
+        Group::builder().set_objects(instances).build().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::{Point, Vector};
+    use crate::utils::BuildInto;
+
+    fn sphere() -> Shape {
+        Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.4, 0.4, 0.4)))
+            .build_into()
+    }
+
+    fn hit_along(shape: &Shape, origin: Point, direction: Vector) -> Option<Float> {
+        let ray = Ray::new(origin, direction);
+        shape
+            .intersect_ray(&ray, vec![])
+            .finalise_hit()
+            .map(|hit| hit.t())
+    }
+
+    #[test]
+    fn repeat_in_a_row() {
+        let row = Repeat::new(3, Transform::new(TransformKind::Translate(2.0, 0.0, 0.0)))
+            .expand(&sphere());
+        assert_eq!(row.primitives().len(), 3);
+
+        let down = Vector::new(0.0, 0.0, 1.0);
+        for x in [0.0, 2.0, 4.0] {
+            assert!(hit_along(&row, Point::new(x, 0.0, -5.0), down).is_some());
+        }
+        assert!(hit_along(&row, Point::new(6.0, 0.0, -5.0), down).is_none());
+        assert!(hit_along(&row, Point::new(1.0, 0.0, -5.0), down).is_none());
+    }
+
+    #[test]
+    fn nested_repeats_make_a_grid() {
+        let row = Repeat::new(4, Transform::new(TransformKind::Translate(1.0, 0.0, 0.0)))
+            .expand(&sphere());
+        let grid =
+            Repeat::new(5, Transform::new(TransformKind::Translate(0.0, 1.0, 0.0))).expand(&row);
+        assert_eq!(grid.primitives().len(), 20);
+
+        let down = Vector::new(0.0, 0.0, 1.0);
+        assert!(hit_along(&grid, Point::new(3.0, 4.0, -5.0), down).is_some());
+        assert!(hit_along(&grid, Point::new(4.0, 4.0, -5.0), down).is_none());
+    }
+
+    #[test]
+    fn repeat_in_a_ring() {
+        let offset = Group::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(3.0, 0.0, 0.0)))
+            .add_object(sphere())
+            .build_into();
+        let ring = Repeat::ring(4, Axis::Y).expand(&offset);
+        assert_eq!(ring.primitives().len(), 4);
+
+        // copies a quarter turn apart, at +x, -z, -x and +z
+        let down = Vector::new(0.0, -1.0, 0.0);
+        for (x, z) in [(3.0, 0.0), (0.0, -3.0), (-3.0, 0.0), (0.0, 3.0)] {
+            let t = hit_along(&ring, Point::new(x, 5.0, z), down);
+            assert!(t.is_some_and(|t| (t - 4.6).abs() < 1e-6));
+        }
+        assert!(hit_along(&ring, Point::new(2.1, 5.0, 2.1), down).is_none());
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::collections::Colour;
+use crate::objects::Computations;
+use crate::scenes::World;
+
+type ShadingFunction = Arc<dyn Fn(&Computations, &World) -> Colour + Send + Sync>;
+
+// Bespoke shading for a material (x-ray, iridescence, debug views, ...) in
+// place of the crate's own. The shader's colour is used as is: lights,
+// ambient light, reflection and refraction are all up to it, e.g. through
+// world.cast_ray(computations.reflected_ray()), though rays cast that way
+// start again with the world's full depth.
+#[derive(Clone)]
+pub struct Shader {
+    shade: ShadingFunction,
+}
+
+impl Debug for Shader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shader").finish_non_exhaustive()
+    }
+}
+
+// shading functions cannot be compared, so only shared ones are equal
+impl PartialEq for Shader {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shade, &other.shade)
+    }
+}
+
+impl Shader {
+    pub fn new<F>(shade: F) -> Shader
+    where
+        F: Fn(&Computations, &World) -> Colour + Send + Sync + 'static,
+    {
+        Shader {
+            shade: Arc::new(shade),
+        }
+    }
+
+    pub fn shade(&self, computations: &Computations, world: &World) -> Colour {
+        (self.shade)(computations, world)
+    }
+}
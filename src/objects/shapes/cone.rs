@@ -1,14 +1,16 @@
+use smallvec::{smallvec, SmallVec};
+
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{narrow, quadratic_roots, widen, Buildable, ConsumingBuilder, Float, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Cone {
     frame_transformation: Transform,
     material: Material,
-    y_minimum: f64,
+    y_minimum: Float,
     closed_bot: bool,
-    y_maximum: f64,
+    y_maximum: Float,
     closed_top: bool,
     bounds: Bounds,
 }
@@ -16,82 +18,56 @@ pub struct Cone {
 impl Cone {
     const PRIMITIVE_BOUNDING_BOX: BoundingBox = BoundingBox::new_unbounded();
 
-    pub fn y_minimum(&mut self) -> Option<f64> {
-        if self.closed_bot {
-            None
-        } else {
-            Some(self.y_minimum)
-        }
+    // None if the shape is unbounded in that direction
+    pub fn y_minimum(&self) -> Option<Float> {
+        self.closed_bot.then_some(self.y_minimum)
     }
 
-    pub fn y_maximum(&mut self) -> Option<f64> {
-        if self.closed_bot {
-            None
-        } else {
-            Some(self.y_maximum)
-        }
+    pub fn y_maximum(&self) -> Option<Float> {
+        self.closed_top.then_some(self.y_maximum)
     }
 
-    fn intersect_walls(&self, local_ray: &Ray) -> Vec<f64> {
-        let &Ray { origin, direction } = local_ray;
-        let Point {
-            x: origin_x,
-            y: origin_y,
-            z: origin_z,
-        } = origin;
-        let Vector {
-            x: dir_x,
-            y: dir_y,
-            z: dir_z,
-        } = direction;
+    fn intersect_walls(&self, local_ray: &Ray) -> SmallVec<[Float; 2]> {
+        let ([origin_x, origin_y, origin_z], [dir_x, dir_y, dir_z]) = local_ray.widened();
 
         let a = dir_x.powi(2) - dir_y.powi(2) + dir_z.powi(2);
         let b = 2.0 * origin_x * dir_x - 2.0 * origin_y * dir_y + 2.0 * origin_z * dir_z;
         let c = origin_x.powi(2) - origin_y.powi(2) + origin_z.powi(2);
 
-        if a.abs() < EPSILON {
-            return if b.abs() < EPSILON {
-                vec![]
+        if a.abs() < widen(EPSILON) {
+            return if b.abs() < widen(EPSILON) {
+                smallvec![]
             } else {
-                vec![-c / (2.0 * b)]
+                smallvec![narrow(-c / (2.0 * b))]
             };
         }
 
-        let disc = b.powi(2) - 4.0 * a * c;
-
-        if disc < 0.0 {
-            return vec![];
-        }
-
-        let mut t_values = vec![];
-
-        let t0 = (-b - disc.sqrt()) / (2.0 * a);
-        let y0 = local_ray.position(t0).y;
-        if (self.y_minimum < y0) && (y0 < self.y_maximum) {
-            t_values.push(t0);
-        }
-
-        let t1 = (-b + disc.sqrt()) / (2.0 * a);
-        let y1 = local_ray.position(t1).y;
-        if (self.y_minimum < y1) && (y1 < self.y_maximum) {
-            t_values.push(t1);
-        }
+        let Some(roots) = quadratic_roots(a, b, c) else {
+            return smallvec![];
+        };
 
-        t_values
+        roots
+            .into_iter()
+            .filter(|&t| {
+                let y = local_ray.position(t).y;
+                (self.y_minimum < y) && (y < self.y_maximum)
+            })
+            .collect()
     }
 
-    fn intersect_caps(&self, local_ray: &Ray) -> Vec<f64> {
-        fn check_cap(local_ray: &Ray, t: f64, y: f64) -> bool {
+    fn intersect_caps(&self, local_ray: &Ray) -> SmallVec<[Float; 2]> {
+        // the rim counts to within EPSILON, as for Cylinder
+        fn check_cap(local_ray: &Ray, t: Float, y: Float) -> bool {
             let position = local_ray.position(t);
 
-            (position.x.powi(2) + position.z.powi(2)) <= y.powi(2)
+            (position.x.powi(2) + position.z.powi(2)) <= y.powi(2) + EPSILON
         }
 
         if local_ray.direction.y.abs() < EPSILON {
-            return vec![];
+            return smallvec![];
         }
 
-        let mut t_values = vec![];
+        let mut t_values = smallvec![];
 
         if self.closed_bot {
             let t = (self.y_minimum - local_ray.origin.y) / local_ray.direction.y;
@@ -112,6 +88,8 @@ impl Cone {
 }
 
 impl PrimitiveShape for Cone {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -120,10 +98,10 @@ impl PrimitiveShape for Cone {
         &self.material
     }
 
-    fn local_normal_at(&self, local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, local_point: Point, _: Option<(Float, Float)>) -> Vector {
         let dist = local_point.x.powi(2) + local_point.z.powi(2);
 
-        if dist < f64::abs(local_point.y) {
+        if dist < Float::abs(local_point.y) {
             match local_point.y {
                 y if y >= self.y_maximum - EPSILON => return Vector::new(0.0, 1.0, 0.0),
                 y if y <= self.y_minimum + EPSILON => return Vector::new(0.0, -1.0, 0.0),
@@ -140,15 +118,11 @@ impl PrimitiveShape for Cone {
         Vector::new(local_point.x, y, local_point.z)
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
-        let mut t_values = vec![];
-
-        t_values.extend_from_slice(&self.intersect_walls(local_ray));
-        t_values.extend_from_slice(&self.intersect_caps(local_ray));
-
-        t_values
-            .iter()
-            .map(|&t| Coordinates::new(t, None))
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
+        self.intersect_walls(local_ray)
+            .into_iter()
+            .chain(self.intersect_caps(local_ray))
+            .map(|t| Coordinates::new(t, None))
             .collect()
     }
 }
@@ -163,8 +137,8 @@ impl Bounded for Cone {
 pub struct ConeBuilder {
     frame_transformation: Option<Transform>,
     material: Option<Material>,
-    y_minimum: Option<f64>,
-    y_maximum: Option<f64>,
+    y_minimum: Option<Float>,
+    y_maximum: Option<Float>,
 }
 
 impl ConeBuilder {
@@ -178,40 +152,35 @@ impl ConeBuilder {
         self
     }
 
-    pub fn set_y_minimum(mut self, y_minimum: f64) -> ConeBuilder {
+    // The cone is the double cone x^2 + z^2 = y^2 about the y axis, in its
+    // own space, so its radius at any height is |y| and it narrows to a
+    // point at the origin. Setting a minimum or maximum cuts it off at that
+    // height and closes that end with a cap of radius |y|; an end left unset
+    // runs on to infinity, open. Limits either side of 0 keep both nappes,
+    // e.g. -1 and 1 give two cones tip to tip. The cut is exclusive, and a
+    // minimum at or above the maximum leaves only the two caps.
+    pub fn set_y_minimum(mut self, y_minimum: Float) -> ConeBuilder {
         self.y_minimum = Some(y_minimum);
         self
     }
 
-    pub fn set_y_maximum(mut self, y_maximum: f64) -> ConeBuilder {
+    pub fn set_y_maximum(mut self, y_maximum: Float) -> ConeBuilder {
         self.y_maximum = Some(y_maximum);
         self
     }
-}
-
-impl Buildable for Cone {
-    type Builder = ConeBuilder;
-
-    fn builder() -> Self::Builder {
-        ConeBuilder::default()
-    }
-}
-
-impl ConsumingBuilder for ConeBuilder {
-    type Built = Cone;
 
-    fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
+    pub fn try_build(self) -> Result<Cone, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
         let material = self.material.unwrap_or_default();
         let (y_minimum, closed_bot) = match self.y_minimum {
             Some(y_minimum) => (y_minimum, true),
-            None => (f64::NEG_INFINITY, false),
+            None => (Float::NEG_INFINITY, false),
         };
         let (y_maximum, closed_top) = match self.y_maximum {
             Some(y_maximum) => (y_maximum, true),
-            None => (f64::INFINITY, false),
+            None => (Float::INFINITY, false),
         };
-        let limit = f64::max(y_minimum.abs(), y_maximum.abs());
+        let limit = Float::max(y_minimum.abs(), y_maximum.abs());
         let bounds = Bounds::new(
             Cone::PRIMITIVE_BOUNDING_BOX
                 .bound_in_x_axis([-limit, limit])
@@ -219,7 +188,7 @@ impl ConsumingBuilder for ConeBuilder {
                 .bound_in_z_axis([-limit, limit])
                 .transform(&frame_transformation),
         );
-        let cone = Cone {
+        Ok(Cone {
             frame_transformation,
             material,
             y_minimum,
@@ -227,14 +196,29 @@ impl ConsumingBuilder for ConeBuilder {
             y_maximum,
             closed_top,
             bounds,
-        };
-        cone
+        })
+    }
+}
+
+impl Buildable for Cone {
+    type Builder = ConeBuilder;
+
+    fn builder() -> Self::Builder {
+        ConeBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for ConeBuilder {
+    type Built = Cone;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Cone {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<Cone> for Shape {
+    fn from(cone: Cone) -> Shape {
+        Shape::Primitive(Box::new(cone))
     }
 }
 
@@ -246,7 +230,7 @@ mod tests {
     #[test]
     fn ray_intersects_cone() {
         let cone = Cone::builder().build();
-        let test_cases: [(Point, Vector, f64, f64); 3] = [
+        let test_cases: [(Point, Vector, Float, Float); 3] = [
             (
                 Point::new(0.0, 0.0, -5.0),
                 Vector::new(0.0, 0.0, 1.0),
@@ -314,7 +298,7 @@ mod tests {
             (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0)),
             (
                 Point::new(1.0, 1.0, 1.0),
-                Vector::new(1.0, -2.0_f64.sqrt(), 1.0),
+                Vector::new(1.0, -Float::sqrt(2.0), 1.0),
             ),
             (Point::new(-1.0, -1.0, 0.0), Vector::new(-1.0, 1.0, 0.0)),
         ];
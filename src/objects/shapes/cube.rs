@@ -1,9 +1,9 @@
 use crate::collections::{Point, Vector};
 use crate::objects::*;
 use crate::utils::floats::EPSILON;
-use crate::utils::{Buildable, ConsumingBuilder};
+use crate::utils::{Buildable, ConsumingBuilder, Float};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Cube {
     frame_transformation: Transform,
     material: Material,
@@ -14,7 +14,7 @@ impl Cube {
     const PRIMITIVE_BOUNDING_BOX: BoundingBox =
         BoundingBox::from_axial_bounds([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]);
 
-    fn check_axis(origin: f64, direction: f64) -> (f64, f64) {
+    fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
         let tmin_numerator = -1.0 - origin;
         let tmax_numerator = 1.0 - origin;
 
@@ -24,8 +24,8 @@ impl Cube {
             tmin = tmin_numerator / direction;
             tmax = tmax_numerator / direction;
         } else {
-            tmin = tmin_numerator * f64::INFINITY;
-            tmax = tmax_numerator * f64::INFINITY;
+            tmin = tmin_numerator * Float::INFINITY;
+            tmax = tmax_numerator * Float::INFINITY;
         }
 
         if tmin > tmax {
@@ -37,6 +37,8 @@ impl Cube {
 }
 
 impl PrimitiveShape for Cube {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -45,14 +47,14 @@ impl PrimitiveShape for Cube {
         &self.material
     }
 
-    fn local_normal_at(&self, local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, local_point: Point, _: Option<(Float, Float)>) -> Vector {
         let maxc = [
             local_point.x.abs(),
             local_point.y.abs(),
             local_point.z.abs(),
         ]
         .into_iter()
-        .reduce(f64::max)
+        .reduce(Float::max)
         .unwrap();
 
         match maxc {
@@ -63,18 +65,24 @@ impl PrimitiveShape for Cube {
         }
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
         let (xtmin, xtmax) = Cube::check_axis(local_ray.origin.x, local_ray.direction.x);
         let (ytmin, ytmax) = Cube::check_axis(local_ray.origin.y, local_ray.direction.y);
         let (ztmin, ztmax) = Cube::check_axis(local_ray.origin.z, local_ray.direction.z);
 
-        let tmin = [xtmin, ytmin, ztmin].into_iter().reduce(f64::max).unwrap();
-        let tmax = [xtmax, ytmax, ztmax].into_iter().reduce(f64::min).unwrap();
+        let tmin = [xtmin, ytmin, ztmin]
+            .into_iter()
+            .reduce(Float::max)
+            .unwrap();
+        let tmax = [xtmax, ytmax, ztmax]
+            .into_iter()
+            .reduce(Float::min)
+            .unwrap();
 
         if tmin > tmax {
-            vec![]
+            LocalHits::new()
         } else {
-            vec![tmin, tmax]
+            [tmin, tmax]
                 .iter()
                 .map(|&t| Coordinates::new(t, None))
                 .collect()
@@ -104,6 +112,18 @@ impl CubeBuilder {
         self.material = Some(material);
         self
     }
+
+    pub fn try_build(self) -> Result<Cube, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let bounds = Bounds::new(Cube::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
+
+        Ok(Cube {
+            frame_transformation,
+            material,
+            bounds,
+        })
+    }
 }
 
 impl Buildable for Cube {
@@ -118,22 +138,13 @@ impl ConsumingBuilder for CubeBuilder {
     type Built = Cube;
 
     fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
-        let material = self.material.unwrap_or_default();
-        let bounds = Bounds::new(Cube::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
-
-        let cube = Cube {
-            frame_transformation,
-            material,
-            bounds,
-        };
-        cube
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Cube {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<Cube> for Shape {
+    fn from(cube: Cube) -> Shape {
+        Shape::Primitive(Box::new(cube))
     }
 }
 
@@ -145,7 +156,7 @@ mod tests {
     #[test]
     fn ray_intersects_cube() {
         let cube = Cube::builder().build();
-        let test_cases: [(Point, Vector, f64, f64); 7] = [
+        let test_cases: [(Point, Vector, Float, Float); 7] = [
             (
                 Point::new(5.0, 0.5, 0.0),
                 Vector::new(-1.0, 0.0, 0.0),
@@ -1,14 +1,16 @@
+use smallvec::{smallvec, SmallVec};
+
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{quadratic_roots, widen, Buildable, ConsumingBuilder, Float, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Cylinder {
     frame_transformation: Transform,
     material: Material,
-    y_minimum: f64,
+    y_minimum: Float,
     closed_bot: bool,
-    y_maximum: f64,
+    y_maximum: Float,
     closed_top: bool,
     bounds: Bounds,
 }
@@ -16,83 +18,58 @@ pub struct Cylinder {
 impl Cylinder {
     const PRIMITIVE_BOUNDING_BOX: BoundingBox = BoundingBox::from_axial_bounds(
         [-1.0, 1.0],
-        [f64::NEG_INFINITY, f64::INFINITY],
+        [Float::NEG_INFINITY, Float::INFINITY],
         [-1.0, 1.0],
     );
 
-    pub fn y_minimum(&mut self) -> Option<f64> {
-        if self.closed_bot {
-            None
-        } else {
-            Some(self.y_minimum)
-        }
+    // None if the shape is unbounded in that direction
+    pub fn y_minimum(&self) -> Option<Float> {
+        self.closed_bot.then_some(self.y_minimum)
     }
 
-    pub fn y_maximum(&mut self) -> Option<f64> {
-        if self.closed_bot {
-            None
-        } else {
-            Some(self.y_maximum)
-        }
+    pub fn y_maximum(&self) -> Option<Float> {
+        self.closed_top.then_some(self.y_maximum)
     }
 
-    fn intersect_walls(&self, local_ray: &Ray) -> Vec<f64> {
-        let &Ray { origin, direction } = local_ray;
-        let Point {
-            x: origin_x,
-            y: _origin_y,
-            z: origin_z,
-        } = origin;
-        let Vector {
-            x: dir_x,
-            y: _dir_y,
-            z: dir_z,
-        } = direction;
+    fn intersect_walls(&self, local_ray: &Ray) -> SmallVec<[Float; 2]> {
+        let ([origin_x, _, origin_z], [dir_x, _, dir_z]) = local_ray.widened();
 
         let a = dir_x.powi(2) + dir_z.powi(2);
 
-        if a.abs() < EPSILON {
-            return vec![];
+        if a.abs() < widen(EPSILON) {
+            return smallvec![];
         }
 
         let b = (2.0 * origin_x * dir_x) + (2.0 * origin_z * dir_z);
         let c = origin_x.powi(2) + origin_z.powi(2) - 1.0;
 
-        let disc = b.powi(2) - 4.0 * a * c;
-
-        if disc < 0.0 {
-            return vec![];
-        }
-
-        let mut t_values = vec![];
-
-        let t0 = (-b - disc.sqrt()) / (2.0 * a);
-        let y0 = local_ray.position(t0).y;
-        if (self.y_minimum < y0) && (y0 < self.y_maximum) {
-            t_values.push(t0);
-        }
-
-        let t1 = (-b + disc.sqrt()) / (2.0 * a);
-        let y1 = local_ray.position(t1).y;
-        if (self.y_minimum < y1) && (y1 < self.y_maximum) {
-            t_values.push(t1);
-        }
+        let Some(roots) = quadratic_roots(a, b, c) else {
+            return smallvec![];
+        };
 
-        t_values
+        roots
+            .into_iter()
+            .filter(|&t| {
+                let y = local_ray.position(t).y;
+                (self.y_minimum < y) && (y < self.y_maximum)
+            })
+            .collect()
     }
 
-    fn check_cap(local_ray: &Ray, t: f64) -> bool {
+    // the rim counts, to within EPSILON, as it would be lost to rounding
+    // where a ray crosses the cap exactly at its edge
+    fn check_cap(local_ray: &Ray, t: Float) -> bool {
         let position = local_ray.position(t);
 
-        (position.x.powi(2) + position.z.powi(2)) <= 1.0
+        (position.x.powi(2) + position.z.powi(2)) <= 1.0 + EPSILON
     }
 
-    fn intersect_caps(&self, local_ray: &Ray) -> Vec<f64> {
+    fn intersect_caps(&self, local_ray: &Ray) -> SmallVec<[Float; 2]> {
         if local_ray.direction.y.abs() < EPSILON {
-            return vec![];
+            return smallvec![];
         }
 
-        let mut t_values = vec![];
+        let mut t_values = smallvec![];
 
         if self.closed_bot {
             let t = (self.y_minimum - local_ray.origin.y) / local_ray.direction.y;
@@ -113,6 +90,8 @@ impl Cylinder {
 }
 
 impl PrimitiveShape for Cylinder {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -121,7 +100,7 @@ impl PrimitiveShape for Cylinder {
         &self.material
     }
 
-    fn local_normal_at(&self, local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, local_point: Point, _: Option<(Float, Float)>) -> Vector {
         let dist = local_point.x.powi(2) + local_point.z.powi(2);
 
         if dist < 1.0 {
@@ -135,15 +114,11 @@ impl PrimitiveShape for Cylinder {
         Vector::new(local_point.x, 0.0, local_point.z)
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
-        let mut t_values = vec![];
-
-        t_values.extend_from_slice(&self.intersect_walls(local_ray));
-        t_values.extend_from_slice(&self.intersect_caps(local_ray));
-
-        t_values
-            .iter()
-            .map(|&t| Coordinates::new(t, None))
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
+        self.intersect_walls(local_ray)
+            .into_iter()
+            .chain(self.intersect_caps(local_ray))
+            .map(|t| Coordinates::new(t, None))
             .collect()
     }
 }
@@ -158,8 +133,8 @@ impl Bounded for Cylinder {
 pub struct CylinderBuilder {
     frame_transformation: Option<Transform>,
     material: Option<Material>,
-    y_minimum: Option<f64>,
-    y_maximum: Option<f64>,
+    y_minimum: Option<Float>,
+    y_maximum: Option<Float>,
 }
 
 impl CylinderBuilder {
@@ -173,42 +148,35 @@ impl CylinderBuilder {
         self
     }
 
-    pub fn set_y_minimum(mut self, y_minimum: f64) -> CylinderBuilder {
+    // The cylinder is the unit-radius tube around the y axis, in its own
+    // space. Setting a minimum or maximum cuts it off at that height and
+    // closes that end with a cap; an end left unset runs on to infinity,
+    // open. The cut is exclusive, so the walls stop just short of the caps,
+    // and a minimum at or above the maximum leaves only the two caps.
+    pub fn set_y_minimum(mut self, y_minimum: Float) -> CylinderBuilder {
         self.y_minimum = Some(y_minimum);
         self
     }
 
-    pub fn set_y_maximum(mut self, y_maximum: f64) -> CylinderBuilder {
+    pub fn set_y_maximum(mut self, y_maximum: Float) -> CylinderBuilder {
         self.y_maximum = Some(y_maximum);
         self
     }
-}
 
-impl Buildable for Cylinder {
-    type Builder = CylinderBuilder;
-
-    fn builder() -> Self::Builder {
-        CylinderBuilder::default()
-    }
-}
-
-impl ConsumingBuilder for CylinderBuilder {
-    type Built = Cylinder;
-
-    fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
+    pub fn try_build(self) -> Result<Cylinder, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
         let material = self.material.unwrap_or_default();
         let (y_minimum, closed_bot) = match self.y_minimum {
             Some(y_minimum) => (y_minimum, true),
-            None => (f64::NEG_INFINITY, false),
+            None => (Float::NEG_INFINITY, false),
         };
         let (y_maximum, closed_top) = match self.y_maximum {
             Some(y_maximum) => (y_maximum, true),
-            None => (f64::INFINITY, false),
+            None => (Float::INFINITY, false),
         };
         let bounds = Bounds::new(Cylinder::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
 
-        let cylinder = Cylinder {
+        Ok(Cylinder {
             frame_transformation,
             material,
             y_minimum,
@@ -216,21 +184,36 @@ impl ConsumingBuilder for CylinderBuilder {
             y_maximum,
             closed_top,
             bounds,
-        };
-        cylinder
+        })
     }
 }
 
-impl Into<Shape> for Cylinder {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl Buildable for Cylinder {
+    type Builder = CylinderBuilder;
+
+    fn builder() -> Self::Builder {
+        CylinderBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for CylinderBuilder {
+    type Built = Cylinder;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl From<Cylinder> for Shape {
+    fn from(cylinder: Cylinder) -> Shape {
+        Shape::Primitive(Box::new(cylinder))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::approx_eq;
+    use crate::utils::{approx_eq, BuildInto};
 
     #[test]
     fn ray_misses_cylinder() {
@@ -249,7 +232,7 @@ mod tests {
     #[test]
     fn ray_hits_cylinder() {
         let cylinder = Cylinder::builder().build();
-        let test_cases: [(Point, Vector, f64, f64); 3] = [
+        let test_cases: [(Point, Vector, Float, Float); 3] = [
             (
                 Point::new(1.0, 0.0, -5.0),
                 Vector::new(0.0, 0.0, 1.0),
@@ -348,4 +331,17 @@ mod tests {
             assert_eq!(cylinder.local_normal_at(point, None), normal);
         }
     }
+
+    #[test]
+    fn downcast_shape_to_cylinder() {
+        let shape: Shape = Cylinder::builder().set_y_minimum(1.0).build_into();
+        let cylinder = shape.downcast_ref::<Cylinder>().unwrap();
+        assert_eq!(cylinder.y_minimum(), Some(1.0));
+        assert_eq!(cylinder.y_maximum(), None);
+        assert!(shape.downcast_ref::<Sphere>().is_none());
+
+        let group: Shape = Group::builder().add_object(shape).build_into();
+        assert!(group.downcast_ref::<Cylinder>().is_none());
+        assert!(group.primitives()[0].downcast_ref::<Cylinder>().is_some());
+    }
 }
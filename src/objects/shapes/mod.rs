@@ -3,6 +3,7 @@ pub mod cube;
 pub mod cylinder;
 pub mod plane;
 pub mod shape;
+pub mod shell;
 pub mod smooth_triangle;
 pub mod sphere;
 pub mod triangle;
@@ -24,6 +25,7 @@ pub(super) mod prelude {
     pub use super::cylinder::Cylinder;
     pub use super::plane::Plane;
     pub use super::shape::Shape;
+    pub use super::shell::Shell;
     pub use super::smooth_triangle::SmoothTriangle;
     pub use super::sphere::Sphere;
     pub use super::triangle::Triangle;
@@ -1,8 +1,10 @@
+use smallvec::smallvec;
+
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{Buildable, ConsumingBuilder, Float, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Plane {
     frame_transformation: Transform,
     material: Material,
@@ -11,13 +13,15 @@ pub struct Plane {
 
 impl Plane {
     const PRIMITIVE_BOUNDING_BOX: BoundingBox = BoundingBox::from_axial_bounds(
-        [f64::NEG_INFINITY, f64::INFINITY],
+        [Float::NEG_INFINITY, Float::INFINITY],
         [0.0, 0.0],
-        [f64::NEG_INFINITY, f64::INFINITY],
+        [Float::NEG_INFINITY, Float::INFINITY],
     );
 }
 
 impl PrimitiveShape for Plane {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -26,17 +30,17 @@ impl PrimitiveShape for Plane {
         &self.material
     }
 
-    fn local_normal_at(&self, _local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, _local_point: Point, _: Option<(Float, Float)>) -> Vector {
         Vector::new(0.0, 1.0, 0.0)
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
         if local_ray.direction.y.abs() < EPSILON {
-            return vec![];
+            return smallvec![];
         }
 
         let t = -local_ray.origin.y / local_ray.direction.y;
-        vec![t].iter().map(|&t| Coordinates::new(t, None)).collect()
+        smallvec![Coordinates::new(t, None)]
     }
 }
 
@@ -62,6 +66,18 @@ impl PlaneBuilder {
         self.material = Some(material);
         self
     }
+
+    pub fn try_build(self) -> Result<Plane, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let bounds = Bounds::new(Plane::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
+
+        Ok(Plane {
+            frame_transformation,
+            material,
+            bounds,
+        })
+    }
 }
 
 impl Buildable for Plane {
@@ -76,38 +92,28 @@ impl ConsumingBuilder for PlaneBuilder {
     type Built = Plane;
 
     fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
-        let material = self.material.unwrap_or_default();
-        let bounds = Bounds::new(Plane::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
-
-        let plane = Plane {
-            frame_transformation,
-            material,
-            bounds,
-        };
-        plane
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Plane {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<Plane> for Shape {
+    fn from(plane: Plane) -> Shape {
+        Shape::Primitive(Box::new(plane))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::collections::{Point, Vector};
     use crate::utils::BuildInto;
 
-    use super::*;
-
     #[test]
     fn normal_of_plane() {
         let default_plane = Plane::builder().build();
-        let normal1 = default_plane.normal_at(Point::new(0.0, 0.0, 0.0), None, &vec![]);
-        let normal2 = default_plane.normal_at(Point::new(10.0, 0.0, -10.0), None, &vec![]);
-        let normal3 = default_plane.normal_at(Point::new(-5.0, 0.0, 150.0), None, &vec![]);
+        let normal1 = default_plane.normal_at(Point::new(0.0, 0.0, 0.0), None, &[]);
+        let normal2 = default_plane.normal_at(Point::new(10.0, 0.0, -10.0), None, &[]);
+        let normal3 = default_plane.normal_at(Point::new(-5.0, 0.0, 150.0), None, &[]);
         let resulting_vector = Vector::new(0.0, 1.0, 0.0);
         assert_eq!(normal1, resulting_vector);
         assert_eq!(normal2, resulting_vector);
@@ -1,13 +1,19 @@
+use std::any::Any;
 use std::fmt::Debug;
 
-use crate::collections::{Point, Vector};
+use crate::collections::{Point, Vector, LANES};
 use crate::objects::*;
+use crate::scenes::{count, Counter};
+use crate::utils::Float;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Shape {
     Primitive(Box<dyn PrimitiveShape>),
     Group(Group),
     Csg(Csg),
+    Lod(Lod),
+    KdTree(KdTree),
+    Moving(Moving),
 }
 
 impl Shape {
@@ -18,37 +24,145 @@ impl Shape {
                 // For some reason, PartialEq does not work here when comparing references directly IF we remove `+ '_` from impl PartialEq for dyn PrimitiveShape + 'a.
                 shape.as_ref() == primitive_shape
             }
-            Shape::Group(group) => {
-                match group
-                    .objects()
-                    .iter()
-                    .position(|object| object.contains(primitive_shape))
-                {
-                    Some(_) => true,
-                    None => false,
-                }
-            }
+            Shape::Group(group) => group
+                .objects()
+                .iter()
+                .any(|object| object.contains(primitive_shape)),
             Shape::Csg(csg) => {
                 csg.lshape().contains(primitive_shape) || csg.rshape().contains(primitive_shape)
             }
+            Shape::Lod(lod) => lod
+                .levels()
+                .iter()
+                .any(|(_, level)| level.contains(primitive_shape)),
+            Shape::KdTree(kd_tree) => kd_tree
+                .objects()
+                .iter()
+                .any(|object| object.contains(primitive_shape)),
+            Shape::Moving(moving) => moving.object().contains(primitive_shape),
+        }
+    }
+
+    // the concrete primitive, if this is a primitive of type T
+    pub fn downcast_ref<T: PrimitiveShape + 'static>(&self) -> Option<&T> {
+        match self {
+            Shape::Primitive(primitive) => primitive.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    // all primitives in this shape, depth-first (left before right for CSG)
+    pub fn primitives(&self) -> Vec<&dyn PrimitiveShape> {
+        match self {
+            Shape::Primitive(shape) => vec![shape.as_ref()],
+            Shape::Group(group) => group
+                .objects()
+                .iter()
+                .flat_map(|object| object.primitives())
+                .collect(),
+            Shape::Csg(csg) => {
+                let mut primitives = csg.lshape().primitives();
+                primitives.extend(csg.rshape().primitives());
+                primitives
+            }
+            Shape::Lod(lod) => lod
+                .levels()
+                .iter()
+                .flat_map(|(_, level)| level.primitives())
+                .collect(),
+            Shape::KdTree(kd_tree) => kd_tree
+                .objects()
+                .iter()
+                .flat_map(|object| object.primitives())
+                .collect(),
+            Shape::Moving(moving) => moving.object().primitives(),
         }
     }
 }
 
 impl Intersectable<dyn PrimitiveShape> for Shape {
-    fn intersect_ray<'world: 'ray, 'ray>(
+    fn intersect_ray_into<'world: 'ray, 'ray>(
         &'world self,
         world_ray: &'ray Ray,
-        transform_stack: Vec<&'ray Transform>,
-    ) -> HitRegister<'ray, dyn PrimitiveShape> {
-        if !self.bounds().intersect_bounds(world_ray, &transform_stack) {
-            return HitRegister::empty();
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
+        if !self.bounds().intersect_bounds(world_ray, transform_stack) {
+            return;
         }
+        self.intersect_contents_into(world_ray, transform_stack, hit_register);
+    }
+}
 
+impl Shape {
+    // intersect_ray_into for the rays of a packet in the given lanes, each
+    // into its own register. Bounds are tested for all of the rays at once,
+    // as the packet descends through groups and kd-trees; local_packet holds
+    // the same rays in the space of the shape's containing frame. Other
+    // shapes take the rays that reach them one by one: a primitive's own
+    // test is per ray, moving shapes move with each ray's time, levels of
+    // detail choose by each ray's origin, and CSG combines each ray's hits.
+    pub(crate) fn intersect_packet_into<'world: 'ray, 'ray>(
+        &'world self,
+        packet: &'ray RayPacket,
+        local_packet: &RayPacket,
+        lanes: [bool; LANES],
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_registers: &mut [HitRegister<'ray, dyn PrimitiveShape>],
+    ) {
+        let hits = self.bounds().intersect_packet(local_packet);
+        let lanes: [bool; LANES] = std::array::from_fn(|idx| lanes[idx] && hits[idx]);
+        if !lanes.contains(&true) {
+            return;
+        }
+
+        match self {
+            Shape::Group(group) => group.intersect_packet_into(
+                packet,
+                local_packet,
+                lanes,
+                transform_stack,
+                hit_registers,
+            ),
+            Shape::KdTree(kd_tree) => kd_tree.intersect_packet_into(
+                packet,
+                local_packet,
+                lanes,
+                transform_stack,
+                hit_registers,
+            ),
+            _ => {
+                for (idx, ray) in packet.rays().iter().enumerate() {
+                    if lanes[idx] {
+                        self.intersect_contents_into(ray, transform_stack, &mut hit_registers[idx]);
+                    }
+                }
+            }
+        }
+    }
+
+    // intersect_ray_into once the ray is known to cross the shape's bounds
+    fn intersect_contents_into<'world: 'ray, 'ray>(
+        &'world self,
+        world_ray: &'ray Ray,
+        transform_stack: &mut Vec<&'ray Transform>,
+        hit_register: &mut HitRegister<'ray, dyn PrimitiveShape>,
+    ) {
         match self {
-            Shape::Primitive(primitive) => primitive.intersect_ray(world_ray, transform_stack),
-            Shape::Group(group) => group.intersect_ray(world_ray, transform_stack),
-            Shape::Csg(csg) => csg.intersect_ray(world_ray, transform_stack),
+            Shape::Primitive(primitive) => {
+                primitive.intersect_ray_into(world_ray, transform_stack, hit_register)
+            }
+            Shape::Group(group) => {
+                group.intersect_ray_into(world_ray, transform_stack, hit_register)
+            }
+            Shape::Csg(csg) => csg.intersect_ray_into(world_ray, transform_stack, hit_register),
+            Shape::Lod(lod) => lod.intersect_ray_into(world_ray, transform_stack, hit_register),
+            Shape::KdTree(kd_tree) => {
+                kd_tree.intersect_ray_into(world_ray, transform_stack, hit_register)
+            }
+            Shape::Moving(moving) => {
+                moving.intersect_ray_into(world_ray, transform_stack, hit_register)
+            }
         }
     }
 }
@@ -59,27 +173,70 @@ impl Bounded for Shape {
             Shape::Primitive(s) => s.bounds(),
             Shape::Group(s) => s.bounds(),
             Shape::Csg(s) => s.bounds(),
+            Shape::Lod(s) => s.bounds(),
+            Shape::KdTree(s) => s.bounds(),
+            Shape::Moving(s) => s.bounds(),
         }
     }
 }
 
-pub trait PrimitiveShape: Debug + Bounded {
+// Send + Sync (as for Pattern), so a World can be shared between threads
+pub trait PrimitiveShape: Debug + Bounded + ShapeClone + Send + Sync {
     fn normal_at(
         &self,
         world_point: Point,
-        uv_coordinates: Option<(f64, f64)>,
-        transform_stack: &Vec<&Transform>,
+        uv_coordinates: Option<(Float, Float)>,
+        transform_stack: &[&Transform],
     ) -> Vector {
-        let local_point = transform_through_stack_forwards(world_point, &transform_stack);
+        let local_point = transform_through_stack_forwards(world_point, transform_stack);
         let local_normal = self.local_normal_at(local_point, uv_coordinates);
-        let world_normal = transform_through_stack_backwards(local_normal, &transform_stack);
+        let world_normal = transform_through_stack_backwards(local_normal, transform_stack);
         world_normal.normalise()
     }
 
     fn frame_transformation(&self) -> &Transform;
     fn material(&self) -> &Material;
-    fn local_normal_at(&self, local_point: Point, uv_coordinates: Option<(f64, f64)>) -> Vector;
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates>;
+    fn local_normal_at(&self, local_point: Point, uv_coordinates: Option<(Float, Float)>)
+        -> Vector;
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits;
+    // implement with impl_as_any!()
+    fn as_any(&self) -> &dyn Any;
+}
+
+// allows boxed primitives, and so shapes and worlds, to be cloned, e.g. to
+// give each rendering thread its own copy of a scene
+pub trait ShapeClone {
+    fn clone_box(&self) -> Box<dyn PrimitiveShape>;
+}
+
+impl<T> ShapeClone for T
+where
+    T: PrimitiveShape + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn PrimitiveShape> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PrimitiveShape> {
+    fn clone(&self) -> Box<dyn PrimitiveShape> {
+        self.clone_box()
+    }
+}
+
+macro_rules! impl_as_any {
+    () => {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    };
+}
+pub(crate) use impl_as_any;
+
+impl dyn PrimitiveShape + '_ {
+    pub fn downcast_ref<T: PrimitiveShape + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
 }
 
 impl PartialEq for dyn PrimitiveShape + '_ {
@@ -92,33 +249,47 @@ pub trait Intersectable<S: PrimitiveShape + PartialEq + ?Sized> {
     fn intersect_ray<'a: 'r, 'r>(
         &'a self,
         world_ray: &'r Ray,
-        transform_stack: Vec<&'r Transform>,
-    ) -> HitRegister<'r, S>;
+        mut transform_stack: Vec<&'r Transform>,
+    ) -> HitRegister<'r, S> {
+        let mut hit_register = HitRegister::empty();
+        self.intersect_ray_into(world_ray, &mut transform_stack, &mut hit_register);
+        hit_register
+    }
+
+    // Adds the ray's intersections to hit_register, so that one register (and
+    // one transform stack, left as it was found) serves a whole scene rather
+    // than each object allocating its own.
+    fn intersect_ray_into<'a: 'r, 'r>(
+        &'a self,
+        world_ray: &'r Ray,
+        transform_stack: &mut Vec<&'r Transform>,
+        hit_register: &mut HitRegister<'r, S>,
+    );
 }
 
 impl<S: PrimitiveShape + PartialEq + ?Sized> Intersectable<S> for S {
-    fn intersect_ray<'a: 'r, 'r>(
+    fn intersect_ray_into<'a: 'r, 'r>(
         &'a self,
         world_ray: &'r Ray,
-        mut transform_stack: Vec<&'r Transform>,
-    ) -> HitRegister<'r, Self> {
-        let mut hit_register = HitRegister::empty();
+        transform_stack: &mut Vec<&'r Transform>,
+        hit_register: &mut HitRegister<'r, Self>,
+    ) {
         transform_stack.push(self.frame_transformation());
-        let local_ray = transform_through_stack_forwards(*world_ray, &transform_stack);
+        let local_ray = transform_through_stack_forwards(*world_ray, transform_stack);
+        count(Counter::IntersectionTest);
         let coordinates = self.local_intersect(&local_ray);
 
         for coordinate in coordinates {
-            let raw_intersect = coordinate.attach(self, world_ray, transform_stack.clone());
+            let raw_intersect = coordinate.attach(self, world_ray, transform_stack);
             hit_register.add_raw_intersect(raw_intersect);
         }
-
-        hit_register
+        transform_stack.pop();
     }
 }
 
 pub(crate) fn transform_through_stack_forwards<T: Transformable>(
     mut object: T,
-    transform_stack: &Vec<&Transform>,
+    transform_stack: &[&Transform],
 ) -> T {
     for &transform in transform_stack {
         object = object.transform(&transform.invert());
@@ -129,7 +300,7 @@ pub(crate) fn transform_through_stack_forwards<T: Transformable>(
 
 pub(crate) fn transform_through_stack_backwards<T: Transformable>(
     mut object: T,
-    transform_stack: &Vec<&Transform>,
+    transform_stack: &[&Transform],
 ) -> T {
     for &transform in transform_stack.iter().rev() {
         object = object.transform(&transform.invert().transpose());
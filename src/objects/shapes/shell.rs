@@ -0,0 +1,203 @@
+use smallvec::SmallVec;
+
+use crate::collections::{Point, Vector};
+use crate::objects::*;
+use crate::utils::{quadratic_roots, widen, Buildable, ConsumingBuilder, Float};
+
+// A hollow sphere: the solid between two concentric spheres around the origin.
+// A ray passing through reports all four surfaces, and normals on the inner
+// surface point towards the centre (out of the solid), so refraction treats
+// the cavity as a separate medium, e.g. for glass globes and bubbles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shell {
+    frame_transformation: Transform,
+    material: Material,
+    outer_radius: Float,
+    inner_radius: Float,
+    bounds: Bounds,
+}
+
+impl Shell {
+    pub fn outer_radius(&self) -> Float {
+        self.outer_radius
+    }
+
+    pub fn inner_radius(&self) -> Float {
+        self.inner_radius
+    }
+
+    fn intersect_sphere(local_ray: &Ray, radius: Float) -> SmallVec<[Float; 2]> {
+        let ([ox, oy, oz], [dx, dy, dz]) = local_ray.widened();
+        let a = (dx * dx) + (dy * dy) + (dz * dz);
+        let b = 2.0 * ((dx * ox) + (dy * oy) + (dz * oz));
+        let c = (ox * ox) + (oy * oy) + (oz * oz) - widen(radius).powi(2);
+
+        quadratic_roots(a, b, c).into_iter().flatten().collect()
+    }
+}
+
+impl PrimitiveShape for Shell {
+    impl_as_any!();
+
+    fn frame_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn local_normal_at(&self, local_point: Point, _: Option<(Float, Float)>) -> Vector {
+        let radial = local_point - Point::zero();
+        let distance = radial.magnitude();
+        if (distance - self.inner_radius).abs() < (distance - self.outer_radius).abs() {
+            -radial
+        } else {
+            radial
+        }
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
+        let mut ts = Shell::intersect_sphere(local_ray, self.outer_radius);
+        // the inner sphere can only be hit if the outer one is
+        if !ts.is_empty() {
+            ts.extend(Shell::intersect_sphere(local_ray, self.inner_radius));
+        }
+        ts.into_iter().map(|t| Coordinates::new(t, None)).collect()
+    }
+}
+
+impl Bounded for Shell {
+    fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ShellBuilder {
+    frame_transformation: Option<Transform>,
+    material: Option<Material>,
+    outer_radius: Option<Float>,
+    inner_radius: Option<Float>,
+}
+
+impl ShellBuilder {
+    pub fn set_frame_transformation(mut self, frame_transformation: Transform) -> ShellBuilder {
+        self.frame_transformation = Some(frame_transformation);
+        self
+    }
+
+    pub fn set_material(mut self, material: Material) -> ShellBuilder {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn set_outer_radius(mut self, outer_radius: Float) -> ShellBuilder {
+        self.outer_radius = Some(outer_radius);
+        self
+    }
+
+    pub fn set_inner_radius(mut self, inner_radius: Float) -> ShellBuilder {
+        self.inner_radius = Some(inner_radius);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Shell, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let outer_radius = self.outer_radius.unwrap_or(1.0);
+        let inner_radius = self.inner_radius.unwrap_or(outer_radius * 0.9);
+        assert!(
+            0.0 < inner_radius && inner_radius < outer_radius,
+            "shells require 0 < inner radius < outer radius"
+        );
+
+        let bounds = Bounds::new(
+            BoundingBox::from_axial_bounds(
+                [-outer_radius, outer_radius],
+                [-outer_radius, outer_radius],
+                [-outer_radius, outer_radius],
+            )
+            .transform(&frame_transformation),
+        );
+
+        Ok(Shell {
+            frame_transformation,
+            material,
+            outer_radius,
+            inner_radius,
+            bounds,
+        })
+    }
+}
+
+impl Buildable for Shell {
+    type Builder = ShellBuilder;
+
+    fn builder() -> Self::Builder {
+        ShellBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for ShellBuilder {
+    type Built = Shell;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl From<Shell> for Shape {
+    fn from(shell: Shell) -> Shape {
+        Shape::Primitive(Box::new(shell))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_intersects_both_surfaces() {
+        let shell = Shell::builder().set_inner_radius(0.5).build();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let mut ts: Vec<Float> = shell
+            .local_intersect(&ray)
+            .iter()
+            .map(|coordinates| coordinates.t())
+            .collect();
+        ts.sort_by(Float::total_cmp);
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn ray_misses_cavity() {
+        let shell = Shell::builder().set_inner_radius(0.5).build();
+        let ray = Ray::new(Point::new(0.0, 0.75, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(shell.local_intersect(&ray).len(), 2);
+    }
+
+    #[test]
+    fn normals_point_out_of_the_solid() {
+        let shell = Shell::builder().set_inner_radius(0.5).build();
+        assert_eq!(
+            shell.normal_at(Point::new(0.0, 0.0, -1.0), None, &[]),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            shell.normal_at(Point::new(0.0, 0.0, -0.5), None, &[]),
+            Vector::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn hit_from_inside_cavity() {
+        let shell = Shell::builder().set_inner_radius(0.5).build();
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = shell.intersect_ray(&ray, vec![]).finalise_hit().unwrap();
+        assert_eq!(hit.t(), 0.5);
+        // the normal faces the cavity, towards the eye
+        assert!(!hit.computations().inside());
+        assert_eq!(hit.normal(), Vector::new(0.0, 0.0, -1.0));
+    }
+}
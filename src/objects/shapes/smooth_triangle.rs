@@ -1,8 +1,10 @@
+use smallvec::smallvec;
+
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{Buildable, ConsumingBuilder, Float, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SmoothTriangle {
     frame_transformation: Transform,
     material: Material,
@@ -13,9 +15,6 @@ pub struct SmoothTriangle {
 }
 
 impl SmoothTriangle {
-    // always unbounded
-    const PRIMITIVE_BOUNDING_BOX: BoundingBox = BoundingBox::new_unbounded();
-
     pub fn vertices(&self) -> [Point; 3] {
         self.vertices
     }
@@ -30,6 +29,8 @@ impl SmoothTriangle {
 }
 
 impl PrimitiveShape for SmoothTriangle {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -38,37 +39,38 @@ impl PrimitiveShape for SmoothTriangle {
         &self.material
     }
 
-    fn local_normal_at(&self, _local_point: Point, uv_coordinates: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(
+        &self,
+        _local_point: Point,
+        uv_coordinates: Option<(Float, Float)>,
+    ) -> Vector {
         let [n1, n2, n3] = self.normals;
         let (u, v) = uv_coordinates.unwrap();
         (n2 * u + n3 * v + n1 * (1.0 - u - v)).normalise()
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
         let dir_cross_e2 = local_ray.direction.cross(self.edges[1]);
         let det = self.edges[0].dot(dir_cross_e2);
         if det.abs() < EPSILON {
-            return vec![];
+            return smallvec![];
         }
 
         let f = 1.0 / det;
         let p1_to_origin = local_ray.origin - self.vertices[0];
         let u = f * p1_to_origin.dot(dir_cross_e2);
-        if u < 0.0 || u > 1.0 {
-            return vec![];
+        if !(0.0..=1.0).contains(&u) {
+            return smallvec![];
         }
 
         let origin_cross_e1 = p1_to_origin.cross(self.edges[0]);
         let v = f * local_ray.direction.dot(origin_cross_e1);
         if v < 0.0 || (u + v) > 1.0 {
-            return vec![];
+            return smallvec![];
         }
 
         let t = f * self.edges[1].dot(origin_cross_e1);
-        vec![(t, Some((u, v)))]
-            .iter()
-            .map(|&(t, uv_coordinates)| Coordinates::new(t, uv_coordinates))
-            .collect()
+        smallvec![Coordinates::new(t, Some((u, v)))]
     }
 }
 
@@ -109,6 +111,27 @@ impl SmoothTriangleBuilder {
         self.normals = Some(normals);
         self
     }
+
+    pub fn try_build(self) -> Result<SmoothTriangle, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let [v1, v2, v3] = self.vertices.unwrap();
+        let normals = self.normals.unwrap();
+        let e1 = v2 - v1;
+        let e2 = v3 - v1;
+        let bounds = Bounds::new(
+            BoundingBox::from_anchors(vec![v1, v2, v3]).transform(&frame_transformation),
+        );
+
+        Ok(SmoothTriangle {
+            frame_transformation,
+            material,
+            vertices: [v1, v2, v3],
+            edges: [e1, e2],
+            normals,
+            bounds,
+        })
+    }
 }
 
 impl Buildable for SmoothTriangle {
@@ -123,28 +146,13 @@ impl ConsumingBuilder for SmoothTriangleBuilder {
     type Built = SmoothTriangle;
 
     fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
-        let material = self.material.unwrap_or_default();
-        let [v1, v2, v3] = self.vertices.unwrap();
-        let normals = self.normals.unwrap();
-        let e1 = v2 - v1;
-        let e2 = v3 - v1;
-        let bounds = Bounds::new(SmoothTriangle::PRIMITIVE_BOUNDING_BOX);
-        let smooth_triangle = SmoothTriangle {
-            frame_transformation,
-            material,
-            vertices: [v1, v2, v3],
-            edges: [e1, e2],
-            normals,
-            bounds,
-        };
-        smooth_triangle
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for SmoothTriangle {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<SmoothTriangle> for Shape {
+    fn from(smooth_triangle: SmoothTriangle) -> Shape {
+        Shape::Primitive(Box::new(smooth_triangle))
     }
 }
 
@@ -1,8 +1,8 @@
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{quadratic_roots, Buildable, ConsumingBuilder, Float};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Sphere {
     frame_transformation: Transform,
     material: Material,
@@ -15,6 +15,8 @@ impl Sphere {
 }
 
 impl PrimitiveShape for Sphere {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -23,28 +25,21 @@ impl PrimitiveShape for Sphere {
         &self.material
     }
 
-    fn local_normal_at(&self, local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, local_point: Point, _: Option<(Float, Float)>) -> Vector {
         local_point - Point::new(0.0, 0.0, 0.0)
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
-        let sphere_to_ray = local_ray.origin - Point::zero();
-        let a = local_ray.direction.dot(local_ray.direction);
-        let b = 2.0 * local_ray.direction.dot(sphere_to_ray);
-        let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
-        let discriminant = b.powi(2) - 4.0 * a * c;
-
-        if discriminant < 0.0 {
-            vec![]
-        } else {
-            let sqrt_discriminant = discriminant.sqrt();
-            let t1 = (-b - sqrt_discriminant) / (2.0 * a);
-            let t2 = (-b + sqrt_discriminant) / (2.0 * a);
-            vec![t1, t2]
-                .iter()
-                .map(|&t| Coordinates::new(t, None))
-                .collect()
-        }
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
+        let ([ox, oy, oz], [dx, dy, dz]) = local_ray.widened();
+        let a = (dx * dx) + (dy * dy) + (dz * dz);
+        let b = 2.0 * ((dx * ox) + (dy * oy) + (dz * oz));
+        let c = (ox * ox) + (oy * oy) + (oz * oz) - 1.0;
+
+        quadratic_roots(a, b, c)
+            .into_iter()
+            .flatten()
+            .map(|t| Coordinates::new(t, None))
+            .collect()
     }
 }
 
@@ -70,6 +65,19 @@ impl SphereBuilder {
         self.material = Some(material);
         self
     }
+
+    pub fn try_build(self) -> Result<Sphere, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+
+        let material = self.material.unwrap_or_default();
+        let bounds = Bounds::new(Sphere::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
+
+        Ok(Sphere {
+            frame_transformation,
+            material,
+            bounds,
+        })
+    }
 }
 
 impl Buildable for Sphere {
@@ -84,23 +92,13 @@ impl ConsumingBuilder for SphereBuilder {
     type Built = Sphere;
 
     fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
-
-        let material = self.material.unwrap_or_default();
-        let bounds = Bounds::new(Sphere::PRIMITIVE_BOUNDING_BOX.transform(&frame_transformation));
-
-        let sphere = Sphere {
-            frame_transformation,
-            material,
-            bounds,
-        };
-        sphere
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Sphere {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<Sphere> for Shape {
+    fn from(sphere: Sphere) -> Shape {
+        Shape::Primitive(Box::new(sphere))
     }
 }
 
@@ -118,29 +116,32 @@ mod tests {
         let point2 = Point::new(0.0, 1.0, 0.0);
         let point3 = Point::new(0.0, 0.0, 1.0);
         let point4 = Point::new(
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         );
         let normal1 = Vector::new(1.0, 0.0, 0.0);
         let normal2 = Vector::new(0.0, 1.0, 0.0);
         let normal3 = Vector::new(0.0, 0.0, 1.0);
         let normal4 = Vector::new(
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         );
-        assert_eq!(sphere.normal_at(point1, None, &vec![]), normal1);
-        assert_eq!(sphere.normal_at(point2, None, &vec![]), normal2);
-        assert_eq!(sphere.normal_at(point3, None, &vec![]), normal3);
-        assert_eq!(sphere.normal_at(point4, None, &vec![]), normal4);
+        assert_eq!(sphere.normal_at(point1, None, &[]), normal1);
+        assert_eq!(sphere.normal_at(point2, None, &[]), normal2);
+        assert_eq!(sphere.normal_at(point3, None, &[]), normal3);
+        let computed_normal4 = sphere.normal_at(point4, None, &[]);
+        approx_eq!(computed_normal4.x, normal4.x);
+        approx_eq!(computed_normal4.y, normal4.y);
+        approx_eq!(computed_normal4.z, normal4.z);
     }
 
     #[test]
     fn normal_on_transformed_sphere() {
         let transform1 = Transform::new(TransformKind::Translate(0.0, 1.0, 0.0));
         let transform2 = Transform::from(vec![
-            TransformKind::Rotate(Axis::Z, Angle::from_radians(std::f64::consts::PI / 5.0)),
+            TransformKind::Rotate(Axis::Z, Angle::from_radians(crate::utils::consts::PI / 5.0)),
             TransformKind::Scale(1.0, 0.5, 1.0),
         ]);
         let sphere1 = Sphere::builder()
@@ -149,11 +150,11 @@ mod tests {
         let sphere2 = Sphere::builder()
             .set_frame_transformation(transform2)
             .build();
-        let point1 = Point::new(0.0, 1.0 + 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
-        let point2 = Point::new(0.0, 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
-        let normal1 = sphere1.normal_at(point1, None, &vec![sphere1.frame_transformation()]);
-        let normal2 = sphere1.normal_at(point2, None, &vec![sphere2.frame_transformation()]);
-        let resulting_normal1 = Vector::new(0.0, 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
+        let point1 = Point::new(0.0, 1.0 + Float::sqrt(2.0) / 2.0, -Float::sqrt(2.0) / 2.0);
+        let point2 = Point::new(0.0, Float::sqrt(2.0) / 2.0, -Float::sqrt(2.0) / 2.0);
+        let normal1 = sphere1.normal_at(point1, None, &[sphere1.frame_transformation()]);
+        let normal2 = sphere1.normal_at(point2, None, &[sphere2.frame_transformation()]);
+        let resulting_normal1 = Vector::new(0.0, Float::sqrt(2.0) / 2.0, -Float::sqrt(2.0) / 2.0);
         let resulting_normal2 = Vector::new(0.0, 0.970143, -0.242535);
         approx_eq!(normal1.x, resulting_normal1.x);
         approx_eq!(normal1.y, resulting_normal1.y);
@@ -224,4 +225,29 @@ mod tests {
         let hit_register = sphere.intersect_ray(&ray, vec![]);
         assert!(hit_register.finalise_hit().is_none());
     }
+
+    #[test]
+    fn try_build_sphere_with_singular_transform() {
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.0, 1.0, 1.0)))
+            .try_build();
+        assert_eq!(sphere.unwrap_err(), TransformError::Singular);
+
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 1.0, 1.0)))
+            .try_build();
+        assert!(sphere.is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "transform contains NaN or infinite entries")]
+    fn build_sphere_with_non_finite_transform() {
+        Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(
+                Float::NAN,
+                0.0,
+                0.0,
+            )))
+            .build();
+    }
 }
@@ -1,8 +1,10 @@
+use smallvec::smallvec;
+
 use crate::collections::{Point, Vector};
 use crate::objects::*;
-use crate::utils::{Buildable, ConsumingBuilder, EPSILON};
+use crate::utils::{Buildable, ConsumingBuilder, Float, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Triangle {
     frame_transformation: Transform,
     material: Material,
@@ -27,6 +29,8 @@ impl Triangle {
 }
 
 impl PrimitiveShape for Triangle {
+    impl_as_any!();
+
     fn frame_transformation(&self) -> &Transform {
         &self.frame_transformation
     }
@@ -35,32 +39,32 @@ impl PrimitiveShape for Triangle {
         &self.material
     }
 
-    fn local_normal_at(&self, _local_point: Point, _: Option<(f64, f64)>) -> Vector {
+    fn local_normal_at(&self, _local_point: Point, _: Option<(Float, Float)>) -> Vector {
         self.normal
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Coordinates> {
+    fn local_intersect(&self, local_ray: &Ray) -> LocalHits {
         let dir_cross_e2 = local_ray.direction.cross(self.edges[1]);
         let det = self.edges[0].dot(dir_cross_e2);
         if det.abs() < EPSILON {
-            return vec![];
+            return smallvec![];
         }
 
         let f = 1.0 / det;
         let p1_to_origin = local_ray.origin - self.vertices[0];
         let u = f * p1_to_origin.dot(dir_cross_e2);
-        if u < 0.0 || u > 1.0 {
-            return vec![];
+        if !(0.0..=1.0).contains(&u) {
+            return smallvec![];
         }
 
         let origin_cross_e1 = p1_to_origin.cross(self.edges[0]);
         let v = f * local_ray.direction.dot(origin_cross_e1);
         if v < 0.0 || (u + v) > 1.0 {
-            return vec![];
+            return smallvec![];
         }
 
         let t = f * self.edges[1].dot(origin_cross_e1);
-        vec![t].iter().map(|&t| Coordinates::new(t, None)).collect()
+        smallvec![Coordinates::new(t, None)]
     }
 }
 
@@ -92,21 +96,9 @@ impl TriangleBuilder {
         self.vertices = Some(vertices);
         self
     }
-}
-
-impl Buildable for Triangle {
-    type Builder = TriangleBuilder;
 
-    fn builder() -> Self::Builder {
-        TriangleBuilder::default()
-    }
-}
-
-impl ConsumingBuilder for TriangleBuilder {
-    type Built = Triangle;
-
-    fn build(self) -> Self::Built {
-        let frame_transformation = self.frame_transformation.unwrap_or_default();
+    pub fn try_build(self) -> Result<Triangle, TransformError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
         let material = self.material.unwrap_or_default();
         let [v1, v2, v3] = self.vertices.unwrap();
         let e1 = v2 - v1;
@@ -116,21 +108,36 @@ impl ConsumingBuilder for TriangleBuilder {
             BoundingBox::from_anchors(vec![v1, v2, v3]).transform(&frame_transformation),
         );
 
-        let triangle = Triangle {
+        Ok(Triangle {
             frame_transformation,
             material,
             vertices: [v1, v2, v3],
             edges: [e1, e2],
             normal,
             bounds,
-        };
-        triangle
+        })
+    }
+}
+
+impl Buildable for Triangle {
+    type Builder = TriangleBuilder;
+
+    fn builder() -> Self::Builder {
+        TriangleBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for TriangleBuilder {
+    type Built = Triangle;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
-impl Into<Shape> for Triangle {
-    fn into(self) -> Shape {
-        Shape::Primitive(Box::new(self))
+impl From<Triangle> for Shape {
+    fn from(triangle: Triangle) -> Shape {
+        Shape::Primitive(Box::new(triangle))
     }
 }
 
@@ -0,0 +1,516 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::collections::Point;
+use crate::objects::*;
+use crate::utils::{BuildInto, Buildable, ConsumingBuilder, Float, Font, FontError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextError {
+    Transform(TransformError),
+    Font(FontError),
+    // there are no default glyphs to fall back on
+    MissingFont,
+}
+
+impl Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::Transform(error) => write!(f, "{error}"),
+            TextError::Font(error) => write!(f, "{error}"),
+            TextError::MissingFont => write!(f, "text requires a font to be set"),
+        }
+    }
+}
+
+impl Error for TextError {}
+
+impl From<TransformError> for TextError {
+    fn from(error: TransformError) -> TextError {
+        TextError::Transform(error)
+    }
+}
+
+impl From<FontError> for TextError {
+    fn from(error: FontError) -> TextError {
+        TextError::Font(error)
+    }
+}
+
+type Polygon = Vec<[Float; 2]>;
+
+// Extruded text, built from the glyph outlines of a TrueType font. The text
+// is laid out from the origin along +x with the baseline on y = 0, scaled so
+// that one em is one unit, and extruded from z = 0 (the front, facing -z) to
+// z = depth. Lines are separated by '\n'.
+//
+// It becomes a Group of flat Triangles when converted into a Shape.
+#[derive(Debug)]
+pub struct Text {
+    frame_transformation: Transform,
+    material: Material,
+    triangles: Vec<[Point; 3]>,
+}
+
+impl Text {
+    pub fn frame_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn triangles(&self) -> &Vec<[Point; 3]> {
+        &self.triangles
+    }
+
+    fn signed_area(polygon: &[[Float; 2]]) -> Float {
+        let mut area = 0.0;
+        for (idx, point) in polygon.iter().enumerate() {
+            let next = polygon[(idx + 1) % polygon.len()];
+            area += point[0] * next[1] - next[0] * point[1];
+        }
+        area / 2.0
+    }
+
+    fn contains(polygon: &[[Float; 2]], [x, y]: [Float; 2]) -> bool {
+        let mut inside = false;
+        for (idx, &[x1, y1]) in polygon.iter().enumerate() {
+            let [x2, y2] = polygon[(idx + 1) % polygon.len()];
+            if (y1 > y) != (y2 > y) && x < x1 + (y - y1) * (x2 - x1) / (y2 - y1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    fn orientation(a: [Float; 2], b: [Float; 2], c: [Float; 2]) -> Float {
+        (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+    }
+
+    fn segments_cross(p1: [Float; 2], p2: [Float; 2], q1: [Float; 2], q2: [Float; 2]) -> bool {
+        if [q1, q2].contains(&p1) || [q1, q2].contains(&p2) {
+            return false;
+        }
+        let d1 = Text::orientation(q1, q2, p1);
+        let d2 = Text::orientation(q1, q2, p2);
+        let d3 = Text::orientation(p1, p2, q1);
+        let d4 = Text::orientation(p1, p2, q2);
+        d1 * d2 < 0.0 && d3 * d4 < 0.0
+    }
+
+    // Splits a glyph outline into outer contours (counter-clockwise) each with
+    // their holes (clockwise). Contours nested an odd number of times are
+    // holes, which makes this independent of the font's winding convention.
+    fn nest_contours(contours: Vec<Polygon>) -> Vec<(Polygon, Vec<Polygon>)> {
+        let contours: Vec<Polygon> = contours
+            .into_iter()
+            .filter(|contour| Text::signed_area(contour) != 0.0)
+            .collect();
+        let depths: Vec<usize> = contours
+            .iter()
+            .enumerate()
+            .map(|(idx, contour)| {
+                contours
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other_idx, other)| {
+                        other_idx != idx && Text::contains(other, contour[0])
+                    })
+                    .count()
+            })
+            .collect();
+
+        let mut shapes: Vec<(Polygon, Vec<Polygon>)> = vec![];
+        let mut outer_indices = vec![];
+        for (idx, contour) in contours.iter().enumerate() {
+            if depths[idx].is_multiple_of(2) {
+                let mut outer = contour.clone();
+                if Text::signed_area(&outer) < 0.0 {
+                    outer.reverse();
+                }
+                shapes.push((outer, vec![]));
+                outer_indices.push(idx);
+            }
+        }
+        for (idx, contour) in contours.iter().enumerate() {
+            if !depths[idx].is_multiple_of(2) {
+                let mut hole = contour.clone();
+                if Text::signed_area(&hole) > 0.0 {
+                    hole.reverse();
+                }
+                // the innermost outer contour containing it
+                let parent = outer_indices
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &outer_idx)| {
+                        depths[outer_idx] + 1 == depths[idx]
+                            && Text::contains(&contours[outer_idx], contour[0])
+                    })
+                    .map(|(shape_idx, _)| shape_idx)
+                    .next();
+                if let Some(parent) = parent {
+                    shapes[parent].1.push(hole);
+                }
+            }
+        }
+        shapes
+    }
+
+    // Joins each hole to the outer contour with a pair of coincident edges,
+    // giving a single (weakly) simple polygon. Holes are bridged from their
+    // rightmost point to the nearest outer point that can be seen from it.
+    fn bridge_holes(mut outer: Polygon, mut holes: Vec<Polygon>) -> Polygon {
+        holes.sort_by(|hole_a, hole_b| {
+            let max_x = |hole: &Polygon| {
+                hole.iter()
+                    .map(|point| point[0])
+                    .fold(Float::MIN, Float::max)
+            };
+            max_x(hole_b).total_cmp(&max_x(hole_a))
+        });
+
+        for hole_idx in 0..holes.len() {
+            let hole = &holes[hole_idx];
+            let hole_point_idx = (0..hole.len())
+                .max_by(|&a, &b| hole[a][0].total_cmp(&hole[b][0]))
+                .unwrap();
+            let hole_point = hole[hole_point_idx];
+
+            let edges = |polygon: &Polygon| {
+                (0..polygon.len())
+                    .map(|idx| (polygon[idx], polygon[(idx + 1) % polygon.len()]))
+                    .collect::<Vec<_>>()
+            };
+            let mut blocking = edges(&outer);
+            for other in &holes[hole_idx..] {
+                blocking.extend(edges(other));
+            }
+
+            let mut candidates: Vec<usize> = (0..outer.len()).collect();
+            candidates.sort_by(|&a, &b| {
+                let distance = |point: [Float; 2]| {
+                    (point[0] - hole_point[0]).powi(2) + (point[1] - hole_point[1]).powi(2)
+                };
+                distance(outer[a]).total_cmp(&distance(outer[b]))
+            });
+            let outer_point_idx = candidates
+                .into_iter()
+                .find(|&idx| {
+                    blocking.iter().all(|&(edge_start, edge_end)| {
+                        !Text::segments_cross(hole_point, outer[idx], edge_start, edge_end)
+                    })
+                })
+                .unwrap_or(0);
+
+            let mut bridged = outer[..=outer_point_idx].to_vec();
+            bridged.extend(&hole[hole_point_idx..]);
+            bridged.extend(&hole[..=hole_point_idx]);
+            bridged.extend(&outer[outer_point_idx..]);
+            outer = bridged;
+        }
+        outer
+    }
+
+    // ear clipping of a counter-clockwise polygon
+    fn triangulate(polygon: &Polygon) -> Vec<[[Float; 2]; 3]> {
+        let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+        let mut triangles = vec![];
+
+        while remaining.len() > 3 {
+            let count = remaining.len();
+            let corners = |idx: usize| {
+                [
+                    polygon[remaining[(idx + count - 1) % count]],
+                    polygon[remaining[idx]],
+                    polygon[remaining[(idx + 1) % count]],
+                ]
+            };
+
+            // collinear (or doubled back) points can simply be dropped
+            if let Some(idx) = (0..count).find(|&idx| {
+                let [a, b, c] = corners(idx);
+                Text::orientation(a, b, c) == 0.0
+            }) {
+                remaining.remove(idx);
+                continue;
+            }
+
+            let is_ear = |idx: usize| {
+                let [a, b, c] = corners(idx);
+                Text::orientation(a, b, c) > 0.0
+                    && remaining.iter().all(|&other| {
+                        let point = polygon[other];
+                        [a, b, c].contains(&point)
+                            || Text::orientation(a, b, point) < 0.0
+                            || Text::orientation(b, c, point) < 0.0
+                            || Text::orientation(c, a, point) < 0.0
+                    })
+            };
+            // a polygon always has an ear, but rounding can hide them all, in
+            // which case any convex corner will do
+            let ear = (0..count)
+                .find(|&idx| is_ear(idx))
+                .or_else(|| {
+                    (0..count).find(|&idx| {
+                        let [a, b, c] = corners(idx);
+                        Text::orientation(a, b, c) > 0.0
+                    })
+                })
+                .unwrap_or(0);
+
+            triangles.push(corners(ear));
+            remaining.remove(ear);
+        }
+
+        if remaining.len() == 3 {
+            let [a, b, c] = [0, 1, 2].map(|idx| polygon[remaining[idx]]);
+            if Text::orientation(a, b, c) > 0.0 {
+                triangles.push([a, b, c]);
+            }
+        }
+        triangles
+    }
+
+    // front and back caps and side walls for one outer contour and its holes
+    fn extrude(outer: Polygon, holes: Vec<Polygon>, depth: Float) -> Vec<[Point; 3]> {
+        let mut triangles = vec![];
+        let front = |[x, y]: [Float; 2]| Point::new(x, y, 0.0);
+        let back = |[x, y]: [Float; 2]| Point::new(x, y, depth);
+
+        // material is on the left of every contour edge, so walls face right
+        for contour in std::iter::once(&outer).chain(&holes) {
+            for idx in 0..contour.len() {
+                let (start, end) = (contour[idx], contour[(idx + 1) % contour.len()]);
+                triangles.push([front(start), back(end), front(end)]);
+                triangles.push([front(start), back(start), back(end)]);
+            }
+        }
+
+        for [a, b, c] in Text::triangulate(&Text::bridge_holes(outer, holes)) {
+            triangles.push([front(a), front(b), front(c)]);
+            triangles.push([back(a), back(c), back(b)]);
+        }
+        triangles
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TextBuilder {
+    frame_transformation: Option<Transform>,
+    material: Option<Material>,
+    font: Option<Font>,
+    text: Option<String>,
+    depth: Option<Float>,
+    curve_segments: Option<usize>,
+}
+
+impl TextBuilder {
+    const DEFAULT_DEPTH: Float = 0.2;
+    const DEFAULT_CURVE_SEGMENTS: usize = 6;
+
+    pub fn set_frame_transformation(mut self, frame_transformation: Transform) -> TextBuilder {
+        self.frame_transformation = Some(frame_transformation);
+        self
+    }
+
+    pub fn set_material(mut self, material: Material) -> TextBuilder {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn set_font(mut self, font: Font) -> TextBuilder {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn set_text(mut self, text: &str) -> TextBuilder {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    // in ems
+    pub fn set_depth(mut self, depth: Float) -> TextBuilder {
+        self.depth = Some(depth);
+        self
+    }
+
+    // straight segments used for each quadratic curve in the outlines
+    pub fn set_curve_segments(mut self, curve_segments: usize) -> TextBuilder {
+        self.curve_segments = Some(curve_segments);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Text, TextError> {
+        let frame_transformation = Transform::checked_frame(self.frame_transformation)?;
+        let material = self.material.unwrap_or_default();
+        let font = self.font.ok_or(TextError::MissingFont)?;
+        let text = self.text.unwrap_or_default();
+        let depth = self.depth.unwrap_or(TextBuilder::DEFAULT_DEPTH);
+        let curve_segments = self
+            .curve_segments
+            .unwrap_or(TextBuilder::DEFAULT_CURVE_SEGMENTS);
+
+        let scale = 1.0 / font.units_per_em() as Float;
+        let mut triangles = vec![];
+        for (line_idx, line) in text.lines().enumerate() {
+            let baseline = -(line_idx as Float) * font.line_height();
+            let mut pen = 0.0;
+            for character in line.chars() {
+                let glyph = font.glyph_index(character)?;
+                let contours = font
+                    .glyph_outline(glyph, curve_segments)?
+                    .into_iter()
+                    .map(|contour| {
+                        contour
+                            .into_iter()
+                            .map(|[x, y]| [(x + pen) * scale, (y + baseline) * scale])
+                            .collect()
+                    })
+                    .collect();
+                for (outer, holes) in Text::nest_contours(contours) {
+                    triangles.extend(Text::extrude(outer, holes, depth));
+                }
+                pen += font.advance_width(glyph)? as Float;
+            }
+        }
+
+        Ok(Text {
+            frame_transformation,
+            material,
+            triangles,
+        })
+    }
+}
+
+impl Buildable for Text {
+    type Builder = TextBuilder;
+
+    fn builder() -> Self::Builder {
+        TextBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for TextBuilder {
+    type Built = Text;
+
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl From<Text> for Shape {
+    fn from(text: Text) -> Shape {
+        let triangles = text
+            .triangles
+            .into_iter()
+            .map(|vertices| {
+                Triangle::builder()
+                    .set_material(text.material.clone())
+                    .set_vertices(vertices)
+                    .build_into()
+            })
+            .collect();
+
+        Group::builder()
+            .set_frame_transformation(text.frame_transformation)
+            .set_objects(triangles)
+            .build_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Vector;
+    use crate::utils::test_font;
+
+    fn area(triangles: &[[[Float; 2]; 3]]) -> Float {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| Text::orientation(a, b, c) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn triangulate_polygon_with_hole() {
+        let outer = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let hole = vec![[1.0, 1.0], [1.0, 3.0], [3.0, 3.0], [3.0, 1.0]];
+        let triangles = Text::triangulate(&Text::bridge_holes(outer, vec![hole]));
+        assert_eq!(triangles.len(), 8);
+        assert_eq!(area(&triangles), 12.0);
+    }
+
+    #[test]
+    fn triangulate_concave_polygon() {
+        let polygon = vec![
+            [0.0, 0.0],
+            [3.0, 0.0],
+            [3.0, 3.0],
+            [2.0, 3.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 3.0],
+            [0.0, 3.0],
+        ];
+        let triangles = Text::triangulate(&polygon);
+        assert_eq!(triangles.len(), 6);
+        assert_eq!(area(&triangles), 7.0);
+    }
+
+    #[test]
+    fn nest_contours_finds_holes() {
+        let shapes = Text::nest_contours(vec![
+            vec![[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 0.0]],
+            vec![[1.0, 1.0], [3.0, 1.0], [3.0, 3.0], [1.0, 3.0]],
+        ]);
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].1.len(), 1);
+        assert!(Text::signed_area(&shapes[0].0) > 0.0);
+        assert!(Text::signed_area(&shapes[0].1[0]) < 0.0);
+    }
+
+    #[test]
+    fn extrude_glyph_with_hole() {
+        let text = Text::builder()
+            .set_font(test_font())
+            .set_text("o")
+            .set_depth(0.5)
+            .build();
+        // 8 wall quads, and 8 triangles on each cap
+        assert_eq!(text.triangles().len(), 32);
+
+        let shape: Shape = text.into();
+        // through the hole
+        let ray = Ray::new(Point::new(0.5, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(shape.intersect_ray(&ray, vec![]).finalise_hit().is_none());
+        // through the solid part, hitting the front cap
+        let ray = Ray::new(Point::new(0.1, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = shape.intersect_ray(&ray, vec![]).finalise_hit().unwrap();
+        assert_eq!(hit.t(), 5.0);
+        assert_eq!(hit.normal(), Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn lay_out_multiple_characters() {
+        let shape: Shape = Text::builder()
+            .set_font(test_font())
+            .set_text("vo\no")
+            .build_into();
+        // the 'o' after the 'v' starts one advance (1.0 em) along
+        let ray = Ray::new(Point::new(1.05, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(shape.intersect_ray(&ray, vec![]).finalise_hit().is_some());
+        // and the second line sits one line height (1.0 em) down
+        let ray = Ray::new(Point::new(0.1, -0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(shape.intersect_ray(&ray, vec![]).finalise_hit().is_some());
+        let ray = Ray::new(Point::new(2.5, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(shape.intersect_ray(&ray, vec![]).finalise_hit().is_none());
+    }
+
+    #[test]
+    fn text_without_a_font() {
+        let result = Text::builder().set_text("o").try_build();
+        assert!(matches!(result, Err(TextError::MissingFont)));
+    }
+}
@@ -1,6 +1,9 @@
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::ops::Mul;
 
 use crate::collections::{Angle, Matrix, Tuple4};
+use crate::utils::Float;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Transform(pub Matrix);
@@ -8,11 +11,11 @@ pub struct Transform(pub Matrix);
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TransformKind {
     Identity,
-    Translate(f64, f64, f64),
-    Scale(f64, f64, f64),
+    Translate(Float, Float, Float),
+    Scale(Float, Float, Float),
     Reflect(Axis),
     Rotate(Axis, Angle),
-    Shear(f64, f64, f64, f64, f64, f64),
+    Shear(Float, Float, Float, Float, Float, Float),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -22,8 +25,30 @@ pub enum Axis {
     Z,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformError {
+    NonFinite,
+    Singular,
+}
+
+impl Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::NonFinite => {
+                write!(f, "transform contains NaN or infinite entries")
+            }
+            TransformError::Singular => write!(
+                f,
+                "transform is singular and cannot be inverted (is something scaled by zero?)"
+            ),
+        }
+    }
+}
+
+impl Error for TransformError {}
+
 impl Transform {
-    const IDENTITY: [[f64; 4]; 4] = [
+    const IDENTITY: [[Float; 4]; 4] = [
         [1.0, 0.0, 0.0, 0.0],
         [0.0, 1.0, 0.0, 0.0],
         [0.0, 0.0, 1.0, 0.0],
@@ -55,6 +80,34 @@ impl Transform {
         Transform(self.0.invert())
     }
 
+    pub fn try_invert(&self) -> Result<Transform, TransformError> {
+        self.validate()?;
+        Ok(self.invert())
+    }
+
+    // checks that the transform can be used as a frame transformation, which
+    // requires it to be finite and invertible
+    pub fn validate(&self) -> Result<(), TransformError> {
+        if !self.0.is_finite() {
+            Err(TransformError::NonFinite)
+        } else if !self.0.is_invertible() {
+            Err(TransformError::Singular)
+        } else {
+            Ok(())
+        }
+    }
+
+    // a builder's frame transformation (the identity if it was not given one),
+    // validated; every builder's try_build uses it, and build panics with its
+    // error
+    pub(crate) fn checked_frame(
+        frame_transformation: Option<Transform>,
+    ) -> Result<Transform, TransformError> {
+        let frame_transformation = frame_transformation.unwrap_or_default();
+        frame_transformation.validate()?;
+        Ok(frame_transformation)
+    }
+
     pub fn transpose(&self) -> Transform {
         Transform(self.0.transpose())
     }
@@ -91,7 +144,7 @@ impl From<Vec<TransformKind>> for Transform {
 
 impl Transform {
     fn base() -> Matrix {
-        Matrix::from(&Transform::IDENTITY.iter().map(|row| row.to_vec()).collect())
+        Matrix::from(Transform::IDENTITY)
     }
 
     fn identity() -> Transform {
@@ -99,7 +152,7 @@ impl Transform {
         Transform(base_matrix)
     }
 
-    fn translate(x: f64, y: f64, z: f64) -> Transform {
+    fn translate(x: Float, y: Float, z: Float) -> Transform {
         let mut translation_matrix = Transform::base();
         translation_matrix[[0, 3]] = x;
         translation_matrix[[1, 3]] = y;
@@ -107,7 +160,7 @@ impl Transform {
         Transform(translation_matrix)
     }
 
-    fn scale(x: f64, y: f64, z: f64) -> Transform {
+    fn scale(x: Float, y: Float, z: Float) -> Transform {
         let mut scaling_matrix = Transform::base();
         scaling_matrix[[0, 0]] = x;
         scaling_matrix[[1, 1]] = y;
@@ -160,7 +213,7 @@ impl Transform {
         Transform(rotation_matrix)
     }
 
-    fn shear(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Transform {
+    fn shear(x_y: Float, x_z: Float, y_x: Float, y_z: Float, z_x: Float, z_y: Float) -> Transform {
         let mut shearing_matrix = Transform::base();
         shearing_matrix[[0, 1]] = x_y;
         shearing_matrix[[0, 2]] = x_z;
@@ -189,18 +242,16 @@ pub trait Transformable {
 
 impl<T: Tuple4 + From<Matrix>> Transformable for T {
     fn transform(self, transform: &Transform) -> T {
-        T::from(transform.clone() * &Matrix::from(self))
+        T::from_tuple4(transform.0.mul_tuple4(self.to_tuple4()))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::FRAC_PI_2 as MATH_FRAC_PI_2;
-
+    use super::*;
     use crate::collections::{Point, Vector};
     use crate::utils::approx_eq;
-
-    use super::*;
+    use crate::utils::consts::FRAC_PI_2 as MATH_FRAC_PI_2;
 
     #[test]
     fn create_identity_transform() {
@@ -499,4 +550,18 @@ mod tests {
         ]);
         assert_eq!(chained_transform, resulting_transform);
     }
+
+    #[test]
+    fn validate_transforms() {
+        let transform = Transform::new(TransformKind::Scale(2.0, 0.5, 1.0));
+        assert_eq!(transform.validate(), Ok(()));
+        assert!(transform.try_invert().is_ok());
+
+        let transform = Transform::new(TransformKind::Scale(1.0, 0.0, 1.0));
+        assert_eq!(transform.validate(), Err(TransformError::Singular));
+        assert_eq!(transform.try_invert(), Err(TransformError::Singular));
+
+        let transform = Transform::new(TransformKind::Translate(Float::NAN, 0.0, 0.0));
+        assert_eq!(transform.validate(), Err(TransformError::NonFinite));
+    }
 }
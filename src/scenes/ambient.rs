@@ -0,0 +1,117 @@
+use crate::collections::{Colour, Vector};
+use crate::objects::UvTexture;
+use crate::scenes::{AmbientCube, SphericalHarmonics};
+
+// Scene-wide fill light, added on top of each material's own ambient term.
+// Like baked irradiance, it is scaled by the surface colour and the material's
+// diffuse coefficient, so material.ambient can be left at zero and the fill
+// adjusted for the whole scene from one place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmbientLight {
+    Flat(Colour),
+    // a uniform sky above the horizon and uniform ground below it, blended
+    // by how much of each a surface faces, as outdoors
+    Hemisphere { sky: Colour, ground: Colour },
+    // radiance from around each axis, looked up by the surface normal
+    Environment(AmbientCube),
+    // smoother than an ambient cube, e.g. projected from an environment map
+    Harmonics(SphericalHarmonics),
+}
+
+impl AmbientLight {
+    // a simple environment: sky above, ground below, and their average around
+    // the horizon
+    pub fn sky(sky: Colour, ground: Colour) -> AmbientLight {
+        AmbientLight::Hemisphere { sky, ground }
+    }
+
+    // fill light from an environment map laid out like a spherical texture map,
+    // e.g. an ImageTexture loaded from a Radiance .hdr
+    pub fn from_environment(environment: &dyn UvTexture, samples: usize) -> AmbientLight {
+        AmbientLight::Harmonics(SphericalHarmonics::project_environment(
+            environment,
+            samples,
+        ))
+    }
+
+    pub fn irradiance(&self, normal: Vector) -> Colour {
+        match self {
+            AmbientLight::Flat(colour) => *colour,
+            AmbientLight::Hemisphere { sky, ground } => {
+                let fraction = (normal.normalise().y + 1.0) / 2.0;
+                *ground * (1.0 - fraction) + *sky * fraction
+            }
+            AmbientLight::Environment(ambient_cube) => ambient_cube.evaluate(normal),
+            AmbientLight::Harmonics(harmonics) => harmonics.irradiance(normal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::UvChecker;
+    use crate::utils::{approx_eq, Float};
+
+    #[test]
+    fn flat_ambient_ignores_normal() {
+        let ambient_light = AmbientLight::Flat(Colour::new(0.2, 0.3, 0.4));
+        assert_eq!(
+            ambient_light.irradiance(Vector::new(0.0, -1.0, 0.0)),
+            Colour::new(0.2, 0.3, 0.4)
+        );
+    }
+
+    #[test]
+    fn sky_ambient_by_normal() {
+        let ambient_light =
+            AmbientLight::sky(Colour::new(0.0, 0.0, 1.0), Colour::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            ambient_light.irradiance(Vector::new(0.0, 1.0, 0.0)),
+            Colour::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            ambient_light.irradiance(Vector::new(0.0, -1.0, 0.0)),
+            Colour::new(1.0, 0.0, 0.0)
+        );
+        let sideways = ambient_light.irradiance(Vector::new(1.0, 0.0, 0.0));
+        approx_eq!(sideways.red, 0.5);
+        approx_eq!(sideways.blue, 0.5);
+    }
+
+    #[test]
+    fn hemisphere_blends_smoothly() {
+        let ambient_light =
+            AmbientLight::sky(Colour::new(0.0, 0.0, 1.0), Colour::new(1.0, 0.0, 0.0));
+        // a quarter of the way down from straight up
+        let tilted = ambient_light.irradiance(Vector::new(1.0, 1.0, 0.0));
+        approx_eq!(tilted.blue, (1.0 + Float::sqrt(0.5)) / 2.0);
+        approx_eq!(tilted.red + tilted.blue, 1.0);
+    }
+
+    #[test]
+    fn environment_ambient_from_texture() {
+        let blue = Colour::new(0.0, 0.0, 1.0);
+        let red = Colour::new(1.0, 0.0, 0.0);
+        // the top half of the map is sky
+        let environment = UvChecker::new(1, 2, red, blue);
+        let ambient_light = AmbientLight::from_environment(&environment, 2000);
+        let from_above = ambient_light.irradiance(Vector::new(0.0, 1.0, 0.0));
+        let from_below = ambient_light.irradiance(Vector::new(0.0, -1.0, 0.0));
+        assert!(from_above.blue > from_above.red);
+        assert!(from_below.red > from_below.blue);
+    }
+
+    #[test]
+    fn harmonics_ambient_matches_sky() {
+        let sky = AmbientLight::sky(Colour::new(0.0, 0.0, 1.0), Colour::new(1.0, 0.0, 0.0));
+        let harmonics = AmbientLight::Harmonics(SphericalHarmonics::project(
+            |direction| sky.irradiance(direction),
+            2000,
+        ));
+        let from_above = harmonics.irradiance(Vector::new(0.0, 1.0, 0.0));
+        let from_below = harmonics.irradiance(Vector::new(0.0, -1.0, 0.0));
+        assert!(from_above.blue > from_above.red);
+        assert!(from_below.red > from_below.blue);
+    }
+}
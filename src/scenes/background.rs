@@ -0,0 +1,156 @@
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{CubeFace, UvMapping, UvTexture};
+use crate::scenes::image_based_lighting::fill;
+use crate::utils::Float;
+
+// What rays that miss every object see, looked up by their direction alone,
+// as though infinitely far away. Reflections and refractions see it too.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    Solid(Colour),
+    // blends from bottom (straight down) to top (straight up)
+    Gradient {
+        bottom: Colour,
+        top: Colour,
+    },
+    // faces in the order +x, -x, +y, -y, +z, -z, each mapped as by
+    // UvMapping::Cubic, which reads the right way round from inside the cube
+    CubeMap([Box<dyn UvTexture>; 6]),
+    // a latitude-longitude image, wrapped as by UvMapping::Spherical
+    Equirectangular(Box<dyn UvTexture>),
+    // an environment wrapped as Equirectangular, whose parts brighter than
+    // the threshold (in luminance) also light the world as lights; see
+    // ImageBasedLighting
+    ImageBased {
+        environment: Box<dyn UvTexture>,
+        threshold: Float,
+    },
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::Solid(Colour::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Background {
+    pub fn colour_at(&self, direction: Vector) -> Colour {
+        let direction = direction.normalise();
+        match self {
+            Background::Solid(colour) => *colour,
+            Background::Gradient { bottom, top } => {
+                let fraction = (direction.y + 1.0) / 2.0;
+                *bottom * (1.0 - fraction) + *top * fraction
+            }
+            Background::CubeMap(faces) => {
+                let (face, (u, v)) = UvMapping::cubic(Point::zero() + direction);
+                let idx_face = match face {
+                    CubeFace::Right => 0,
+                    CubeFace::Left => 1,
+                    CubeFace::Up => 2,
+                    CubeFace::Down => 3,
+                    CubeFace::Front => 4,
+                    CubeFace::Back => 5,
+                };
+                faces[idx_face].uv_colour_at(u, v)
+            }
+            Background::Equirectangular(texture)
+            | Background::ImageBased {
+                environment: texture,
+                ..
+            } => {
+                let (u, v) = UvMapping::spherical(Point::zero() + direction);
+                texture.uv_colour_at(u, v)
+            }
+        }
+    }
+
+    // the colour, less any part of it that lights the world as lights; a
+    // path that bounces diffusely off a surface and then misses sees this,
+    // as the surface was lit by those lights already
+    pub fn fill_at(&self, direction: Vector) -> Colour {
+        match self {
+            Background::ImageBased { threshold, .. } => fill(self.colour_at(direction), *threshold),
+            _ => self.colour_at(direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Angle;
+    use crate::objects::UvChecker;
+    use crate::scenes::*;
+    use crate::utils::approx_eq;
+    use crate::utils::consts::FRAC_PI_2;
+
+    #[test]
+    fn gradient_background() {
+        let background = Background::Gradient {
+            bottom: Colour::new(1.0, 0.0, 0.0),
+            top: Colour::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(
+            background.colour_at(Vector::new(0.0, 3.0, 0.0)),
+            Colour::new(0.0, 0.0, 1.0)
+        );
+        let horizon = background.colour_at(Vector::new(1.0, 0.0, 1.0));
+        approx_eq!(horizon.red, 0.5);
+        approx_eq!(horizon.blue, 0.5);
+    }
+
+    #[test]
+    fn cube_map_faces() {
+        let solid = |red, green, blue| -> Box<dyn UvTexture> {
+            let colour = Colour::new(red, green, blue);
+            Box::new(UvChecker::new(1, 1, colour, colour))
+        };
+        let background = Background::CubeMap([
+            solid(1.0, 0.0, 0.0),
+            solid(0.0, 1.0, 0.0),
+            solid(0.0, 0.0, 1.0),
+            solid(1.0, 1.0, 0.0),
+            solid(0.0, 1.0, 1.0),
+            solid(1.0, 0.0, 1.0),
+        ]);
+        let cases = [
+            (Vector::new(1.0, 0.2, -0.3), Colour::new(1.0, 0.0, 0.0)),
+            (Vector::new(-1.0, 0.5, 0.9), Colour::new(0.0, 1.0, 0.0)),
+            (Vector::new(0.3, 1.0, 0.1), Colour::new(0.0, 0.0, 1.0)),
+            (Vector::new(0.3, -1.0, 0.1), Colour::new(1.0, 1.0, 0.0)),
+            (Vector::new(0.0, 0.0, 1.0), Colour::new(0.0, 1.0, 1.0)),
+            (Vector::new(0.5, -0.5, -1.0), Colour::new(1.0, 0.0, 1.0)),
+        ];
+        for (direction, colour) in cases {
+            assert_eq!(background.colour_at(direction), colour);
+        }
+    }
+
+    #[test]
+    fn cube_map_faces_are_seen_from_inside() {
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let white = Colour::new(1.0, 1.0, 1.0);
+        // the left half of every face is black
+        let face = || -> Box<dyn UvTexture> { Box::new(UvChecker::new(2, 1, black, white)) };
+        let mut world = World::default();
+        world.background = Background::CubeMap([face(), face(), face(), face(), face(), face()]);
+
+        for to in [
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 0.0, -1.0),
+        ] {
+            let camera = Camera::new(Native::new(
+                2,
+                1,
+                Angle::from_radians(FRAC_PI_2),
+                Orientation::new(Point::zero(), to, Vector::new(0.0, 1.0, 0.0)),
+            ));
+            let image = camera.render(&world).unwrap();
+            assert_eq!(image[[0, 0]].colour(), black, "looking at {to:?}");
+            assert_eq!(image[[1, 0]].colour(), white, "looking at {to:?}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::collections::*;
+use crate::objects::*;
+use crate::scenes::*;
+use crate::utils::consts::{FRAC_PI_3, FRAC_PI_6, TAU};
+use crate::utils::{BuildInto, Buildable, Float};
+
+// Scenes for measuring performance (see benches/render.rs), each stressing a
+// different part of the renderer, and kept fixed so timings compare across
+// releases:
+// - CornellBox: soft shadows from an area light in a closed room
+// - SphereGrid: many simple objects in a kd-tree, half of them mirrors
+// - Dragon: one dense triangle mesh in a kd-tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BenchmarkScene {
+    CornellBox,
+    SphereGrid,
+    Dragon,
+}
+
+impl BenchmarkScene {
+    pub const ALL: [BenchmarkScene; 3] = [
+        BenchmarkScene::CornellBox,
+        BenchmarkScene::SphereGrid,
+        BenchmarkScene::Dragon,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BenchmarkScene::CornellBox => "cornell_box",
+            BenchmarkScene::SphereGrid => "sphere_grid",
+            BenchmarkScene::Dragon => "dragon",
+        }
+    }
+
+    pub fn world(self) -> World {
+        match self {
+            BenchmarkScene::CornellBox => cornell_box(),
+            BenchmarkScene::SphereGrid => sphere_grid(),
+            BenchmarkScene::Dragon => dragon(),
+        }
+    }
+
+    pub fn camera(self, hsize: usize, vsize: usize) -> Camera<Native> {
+        let (from, to) = match self {
+            BenchmarkScene::CornellBox => (Point::new(0.0, 1.0, -3.4), Point::new(0.0, 1.0, 0.0)),
+            BenchmarkScene::SphereGrid => (Point::new(0.0, 5.0, -9.0), Point::new(0.0, 0.0, 0.0)),
+            BenchmarkScene::Dragon => (Point::new(0.0, 1.5, -6.0), Point::new(0.0, 0.0, 0.0)),
+        };
+        Camera::new(Native::new(
+            hsize,
+            vsize,
+            Angle::from_radians(FRAC_PI_3),
+            Orientation::new(from, to, Vector::new(0.0, 1.0, 0.0)),
+        ))
+    }
+
+    pub fn render(self, hsize: usize, vsize: usize) -> Result<Canvas, WriteError> {
+        self.camera(hsize, vsize).render(&self.world())
+    }
+}
+
+fn matte(red: Float, green: Float, blue: Float) -> Material {
+    Material {
+        pattern: Box::new(Solid::new(Colour::new(red, green, blue))),
+        ambient: 0.05,
+        diffuse: 0.8,
+        specular: 0.0,
+        ..Material::preset()
+    }
+}
+
+fn cube(transform: Vec<TransformKind>, material: Material) -> Shape {
+    Cube::builder()
+        .set_frame_transformation(Transform::from(transform))
+        .set_material(material)
+        .build_into()
+}
+
+// a room two units on each side, open towards the camera, with a red wall on
+// the left, a green one on the right, and two boxes on the floor
+fn cornell_box() -> World {
+    let wall = |[scale_x, scale_y, scale_z]: [Float; 3], [x, y, z]: [Float; 3], material| {
+        cube(
+            vec![
+                TransformKind::Scale(scale_x, scale_y, scale_z),
+                TransformKind::Translate(x, y, z),
+            ],
+            material,
+        )
+    };
+    let white = || matte(0.73, 0.73, 0.73);
+    let block = |[scale_x, scale_y, scale_z]: [Float; 3], turn: Float, [x, z]: [Float; 2]| {
+        cube(
+            vec![
+                TransformKind::Scale(scale_x, scale_y, scale_z),
+                TransformKind::Rotate(Axis::Y, Angle::from_radians(turn)),
+                TransformKind::Translate(x, scale_y, z),
+            ],
+            white(),
+        )
+    };
+    let objects = vec![
+        wall([1.0, 0.01, 1.0], [0.0, -0.01, 0.0], white()),
+        wall([1.0, 0.01, 1.0], [0.0, 2.01, 0.0], white()),
+        wall([1.0, 1.0, 0.01], [0.0, 1.0, 1.01], white()),
+        wall([0.01, 1.0, 1.0], [-1.01, 1.0, 0.0], matte(0.65, 0.05, 0.05)),
+        wall([0.01, 1.0, 1.0], [1.01, 1.0, 0.0], matte(0.12, 0.45, 0.15)),
+        block([0.3, 0.6, 0.3], FRAC_PI_6, [-0.35, 0.35]),
+        block([0.3, 0.3, 0.3], -FRAC_PI_6 / 2.0, [0.35, -0.3]),
+    ];
+    let light = AreaLight::new(
+        Point::new(-0.25, 1.99, -0.25),
+        Vector::new(0.5, 0.0, 0.0),
+        Vector::new(0.0, 0.0, 0.5),
+        Colour::new(1.0, 1.0, 1.0),
+        [4, 4],
+    );
+    World::new(objects, vec![Box::new(light)])
+}
+
+// ten by ten spheres on a floor, alternately matte and mirrored
+fn sphere_grid() -> World {
+    let spheres = (0..100)
+        .map(|idx| {
+            let [x, z] = [idx % 10, idx / 10].map(|pos| pos as Float - 4.5);
+            let material = if (idx % 10 + idx / 10) % 2 == 0 {
+                matte(0.2 + 0.06 * (idx % 10) as Float, 0.3, 0.8)
+            } else {
+                Material {
+                    reflectance: 0.8,
+                    specular: 0.5,
+                    ..matte(0.1, 0.1, 0.1)
+                }
+            };
+            Sphere::builder()
+                .set_frame_transformation(Transform::from(vec![
+                    TransformKind::Scale(0.4, 0.4, 0.4),
+                    TransformKind::Translate(x, 0.4, z),
+                ]))
+                .set_material(material)
+                .build_into()
+        })
+        .collect();
+    let objects = vec![
+        Plane::builder()
+            .set_material(matte(0.8, 0.8, 0.8))
+            .build_into(),
+        KdTree::builder().set_objects(spheres).build_into(),
+    ];
+    let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+    World::new(objects, vec![Box::new(light)])
+}
+
+// Stands in for the Stanford dragon, which is too large to keep in the
+// repository: a scaly tube coiled into a trefoil knot, of about 23 thousand
+// triangles, which like the dragon is long, thin and close to itself.
+fn dragon() -> World {
+    let spine = |u: Float| {
+        Point::new(
+            u.sin() + 2.0 * (2.0 * u).sin(),
+            u.cos() - 2.0 * (2.0 * u).cos(),
+            -(3.0 * u).sin(),
+        )
+    };
+    let body = move |u: Float, v: Float| {
+        let tangent = (spine(u + 1e-3) - spine(u - 1e-3)).normalise();
+        let normal = tangent.cross(Vector::new(0.0, 0.0, 1.0)).normalise();
+        let binormal = tangent.cross(normal);
+        let radius = 0.4 + 0.04 * (40.0 * u).sin() * (12.0 * v).sin();
+        spine(u) + (normal * v.cos() + binormal * v.sin()) * radius
+    };
+    let surface: Shape = ParametricSurface::builder()
+        .set_frame_transformation(Transform::new(TransformKind::Scale(0.6, 0.6, 0.6)))
+        .set_material(Material {
+            specular: 0.4,
+            shininess: 50.0,
+            ..matte(0.3, 0.6, 0.3)
+        })
+        .set_function(body)
+        .set_u_domain([0.0, TAU])
+        .set_v_domain([0.0, TAU])
+        .set_resolution([480, 24])
+        .build_into();
+    let Shape::Group(group) = surface else {
+        unreachable!("parametric surfaces become groups")
+    };
+    let dragon = Group::builder()
+        .set_frame_transformation(group.frame_transformation().clone())
+        .set_objects(vec![KdTree::builder()
+            .set_objects(group.objects().clone())
+            .build_into()])
+        .build_into();
+
+    let objects = vec![
+        Plane::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -2.0, 0.0)))
+            .set_material(matte(0.8, 0.8, 0.8))
+            .build_into(),
+        dragon,
+    ];
+    let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+    World::new(objects, vec![Box::new(light)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_differ_and_show_something() {
+        let worlds = BenchmarkScene::ALL.map(BenchmarkScene::world);
+        let renders: Vec<Canvas> = BenchmarkScene::ALL
+            .iter()
+            .zip(&worlds)
+            .map(|(scene, world)| scene.camera(16, 12).render(world).unwrap())
+            .collect();
+        for (idx, render) in renders.iter().enumerate() {
+            assert_ne!(
+                render[[8, 6]].colour(),
+                Colour::new(0.0, 0.0, 0.0),
+                "{}",
+                BenchmarkScene::ALL[idx].name()
+            );
+            for other in &renders[idx + 1..] {
+                assert_ne!(render, other);
+            }
+        }
+
+        let Shape::Group(dragon) = &worlds[2].objects()[1] else {
+            panic!("the dragon is a group");
+        };
+        let Shape::KdTree(dragon) = &dragon.objects()[0] else {
+            panic!("the dragon is in a kd-tree");
+        };
+        assert!(dragon.objects().len() > 20_000);
+    }
+}
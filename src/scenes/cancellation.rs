@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Stops a render from elsewhere, e.g. a GUI's stop button or a Ctrl-C
+// handler, holding a clone of the camera's. Renders check it between tiles of
+// rays, so they stop soon after, returning the canvas with whatever was done
+// by then; clones share the same flag, so any of them can tell afterwards
+// whether the canvas is whole.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    pub fn new() -> Cancellation {
+        Cancellation::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// the same flag, not just the same state
+impl PartialEq for Cancellation {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation() {
+        let cancellation = Cancellation::new();
+        let handle = cancellation.clone();
+        assert_eq!(cancellation, handle);
+        assert_ne!(cancellation, Cancellation::new());
+        assert!(!cancellation.is_cancelled());
+
+        thread::spawn(move || handle.cancel()).join().unwrap();
+        assert!(cancellation.is_cancelled());
+    }
+}
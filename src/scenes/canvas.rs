@@ -1,31 +1,96 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
 use std::io::Write;
-use std::ops::{Add, AddAssign, Index};
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul};
 
-use crate::collections::Colour;
-use crate::utils::filehandler;
+use crate::collections::{linear_to_srgb, srgb_to_linear, Colour};
+use crate::scenes::{expose, ToneMapping};
+use crate::utils::consts::PI;
+use crate::utils::{
+    bmp, exr, filehandler, hdr, jpeg, png, tga, widen, Float, ImageError, PpmImage, RawImage,
+};
 
-const PPM_HEADER: &str = "P3";
 const PIXEL_MAX: u64 = 255;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Width(pub usize);
 pub struct Height(pub usize);
 
+// The colour is premultiplied by alpha (coverage), so that partially covered
+// pixels can be accumulated by adding weighted colours.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Pixel {
     colour: Colour,
+    alpha: Float,
 }
 
 impl Pixel {
     pub fn new(colour: Colour) -> Pixel {
-        Pixel { colour }
+        Pixel { colour, alpha: 1.0 }
     }
 
+    pub fn with_alpha(colour: Colour, alpha: Float) -> Pixel {
+        Pixel { colour, alpha }
+    }
+
+    pub fn colour(&self) -> Colour {
+        self.colour
+    }
+
+    pub fn alpha(&self) -> Float {
+        self.alpha
+    }
+
+    // the colour before premultiplication, as stored by e.g. PNG
+    fn straight_colour(&self) -> Colour {
+        if self.alpha <= 0.0 || self.alpha >= 1.0 {
+            self.colour
+        } else {
+            self.colour * (1.0 / self.alpha)
+        }
+    }
+
+    // exposed and tone mapped before premultiplying, so partial coverage
+    // doesn't change how a colour is mapped
+    fn tone_mapped(self, tone_mapping: ToneMapping, exposure: Float) -> Pixel {
+        if tone_mapping == ToneMapping::Clamp && exposure == 0.0 {
+            return self;
+        }
+        let colour = tone_mapping.map(expose(self.straight_colour(), exposure));
+        let alpha = self.alpha.clamp(0.0, 1.0);
+        Pixel::with_alpha(
+            if alpha > 0.0 { colour * alpha } else { colour },
+            self.alpha,
+        )
+    }
+
+    // premultiplied "over": this pixel in front, covering the other by its
+    // alpha
+    pub fn over(self, below: Pixel) -> Pixel {
+        self + below * (1.0 - self.alpha.clamp(0.0, 1.0))
+    }
+
+    fn to_rgba_values(self, encoding: Encoding, bit_depth: BitDepth) -> [u64; 4] {
+        let [red, green, blue] =
+            Pixel::new(self.straight_colour()).to_rgb_values(encoding, bit_depth);
+        let alpha = (self.alpha.clamp(0.0, 1.0) * bit_depth.max() as Float).round();
+        [red, green, blue, alpha as u64]
+    }
+
+    fn to_rgb_values(self, encoding: Encoding, bit_depth: BitDepth) -> [u64; 3] {
+        let Colour { red, green, blue } = self.colour;
+        let max = bit_depth.max() as Float;
+        [red, green, blue].map(|value| (encoding.encode(value) * max).round() as u64)
+    }
+
+    // the linear colour clamped and scaled to 0..=255, without the encoding
+    // applied on output
     pub fn red(&self) -> u64 {
         match self.colour.red {
             x if x > 1.0 => PIXEL_MAX,
             x if x < 0.0 => 0,
-            x => (x * PIXEL_MAX as f64).round() as u64,
+            x => (x * PIXEL_MAX as Float).round() as u64,
         }
     }
 
@@ -33,7 +98,7 @@ impl Pixel {
         match self.colour.green {
             x if x > 1.0 => PIXEL_MAX,
             x if x < 0.0 => 0,
-            x => (x * PIXEL_MAX as f64).round() as u64,
+            x => (x * PIXEL_MAX as Float).round() as u64,
         }
     }
 
@@ -41,7 +106,7 @@ impl Pixel {
         match self.colour.blue {
             x if x > 1.0 => PIXEL_MAX,
             x if x < 0.0 => 0,
-            x => (x * PIXEL_MAX as f64).round() as u64,
+            x => (x * PIXEL_MAX as Float).round() as u64,
         }
     }
 }
@@ -52,6 +117,18 @@ impl Add for Pixel {
     fn add(self, rhs: Self) -> Self::Output {
         Pixel {
             colour: self.colour + rhs.colour,
+            alpha: self.alpha + rhs.alpha,
+        }
+    }
+}
+
+impl Mul<Float> for Pixel {
+    type Output = Pixel;
+
+    fn mul(self, rhs: Float) -> Self::Output {
+        Pixel {
+            colour: self.colour * rhs,
+            alpha: self.alpha * rhs,
         }
     }
 }
@@ -62,15 +139,251 @@ impl AddAssign for Pixel {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResizeFilter {
+    // the source pixel under each target pixel's centre; keeps hard edges,
+    // e.g. for enlarging previews rendered at a fraction of the size
+    Nearest,
+    // interpolates between the four nearest pixel centres; best for enlarging
+    Bilinear,
+    // averages every source pixel covered by the target pixel, weighted by
+    // overlap; best for shrinking
+    Box,
+    // windowed sinc over three pixels each way (widened when shrinking);
+    // sharpest, though edges can ring slightly
+    Lanczos,
+}
+
+const LANCZOS_RADIUS: Float = 3.0;
+
+fn lanczos(x: Float) -> Float {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= LANCZOS_RADIUS {
+        return 0.0;
+    }
+    let pi_x = PI * x;
+    LANCZOS_RADIUS * pi_x.sin() * (pi_x / LANCZOS_RADIUS).sin() / (pi_x * pi_x)
+}
+
+// the source pixels, and their normalised weights, contributing to each of
+// `size` target pixels along one axis of `source_size` pixels
+fn lanczos_weights(source_size: usize, size: usize) -> Vec<Vec<(usize, Float)>> {
+    let scale = source_size as Float / size as Float;
+    let filter_scale = scale.max(1.0);
+    let reach = LANCZOS_RADIUS * filter_scale;
+    (0..size)
+        .map(|target| {
+            let centre = (target as Float + 0.5) * scale - 0.5;
+            let first = (centre - reach).floor() as isize;
+            let last = (centre + reach).ceil() as isize;
+            let weights: Vec<(usize, Float)> = (first..=last)
+                .map(|source| {
+                    let weight = lanczos((source as Float - centre) / filter_scale);
+                    // beyond the edges repeats the edge pixels
+                    (source.clamp(0, source_size as isize - 1) as usize, weight)
+                })
+                .filter(|&(_, weight)| weight != 0.0)
+                .collect();
+            let total: Float = weights.iter().map(|(_, weight)| weight).sum();
+            weights
+                .into_iter()
+                .map(|(source, weight)| (source, weight / total))
+                .collect()
+        })
+        .collect()
+}
+
+// How linear colours are encoded in 8-bit output. Rendering happens in linear
+// light, but images are displayed as though encoded for a gamma of about 2.2,
+// so writing linear values unchanged makes midtones look too dark.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Srgb,
+    // a pure power law, e.g. 2.2
+    Gamma(Float),
+    Linear,
+}
+
+impl Encoding {
+    // from linear light, clamped to 0..=1
+    pub fn encode(self, value: Float) -> Float {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            Encoding::Srgb => linear_to_srgb(value),
+            Encoding::Gamma(gamma) => value.powf(1.0 / gamma),
+            Encoding::Linear => value,
+        }
+    }
+
+    // back to linear light, e.g. for 8-bit textures
+    pub fn decode(self, value: Float) -> Float {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            Encoding::Srgb => srgb_to_linear(value),
+            Encoding::Gamma(gamma) => value.powf(gamma),
+            Encoding::Linear => value,
+        }
+    }
+}
+
+// Bits per channel for PPM and PNG; the other 8-bit formats are always
+// written with 8. Sixteen bits keep smooth gradients, such as skies and soft
+// shadows, from banding, at twice the size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+impl BitDepth {
+    // the largest value of a channel, written as the PPM's maximum value
+    pub fn max(self) -> u64 {
+        match self {
+            BitDepth::Eight => PIXEL_MAX,
+            BitDepth::Sixteen => u16::MAX as u64,
+        }
+    }
+
+    // big-endian, as in both PPM and PNG
+    fn extend_bytes(self, bytes: &mut Vec<u8>, values: &[u64]) {
+        for &value in values {
+            match self {
+                BitDepth::Eight => bytes.push(value as u8),
+                BitDepth::Sixteen => bytes.extend((value as u16).to_be_bytes()),
+            }
+        }
+    }
+}
+
+// P3 stores each value as decimal text, which is easy to read and diff;
+// P6 stores them as bytes, a fraction of the size and far faster to write
+// and parse for large images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PpmFormat {
+    #[default]
+    Ascii,
+    Binary,
+}
+
+impl PpmFormat {
+    fn magic(self) -> &'static str {
+        match self {
+            PpmFormat::Ascii => "P3",
+            PpmFormat::Binary => "P6",
+        }
+    }
+
+    pub(crate) fn write_header<W: Write>(
+        self,
+        writer: &mut W,
+        width: usize,
+        height: usize,
+        bit_depth: BitDepth,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "{}", self.magic())?;
+        writeln!(writer, "{} {}", width, height)?;
+        writeln!(writer, "{}", bit_depth.max())
+    }
+}
+
 #[derive(Debug)]
 pub enum WriteError {
     OutOfBounds,
+    // the format can't record a canvas this size
+    TooLarge {
+        format: &'static str,
+        width: usize,
+        height: usize,
+    },
+    // canvases compared (see Canvas::diff) are not the same size; each is
+    // given as width and height
+    SizeMismatch {
+        size: (usize, usize),
+        other: (usize, usize),
+    },
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::OutOfBounds => write!(f, "pixel out of bounds"),
+            WriteError::TooLarge {
+                format,
+                width,
+                height,
+            } => write!(f, "a {width}x{height} canvas is too large for {format}"),
+            WriteError::SizeMismatch { size, other } => write!(
+                f,
+                "canvases differ in size: {}x{} and {}x{}",
+                size.0, size.1, other.0, other.1
+            ),
+        }
+    }
+}
+
+impl Error for WriteError {}
+
+// How far one canvas is from another as they'd be written out. Errors are
+// differences of encoded values (0 to 1), which are spaced roughly as the
+// eye sees them, so a difference in the shadows counts as much as an equally
+// visible one in the highlights; a pixel's error is its largest difference
+// in any channel, alpha included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanvasDiff {
+    // each channel's difference, written unencoded so it can be viewed as is
+    pub image: Canvas,
+    pub max_error: Float,
+    pub mean_error: Float,
+}
+
+impl CanvasDiff {
+    pub fn is_within(&self, max_error: Float, mean_error: Float) -> bool {
+        self.max_error <= max_error && self.mean_error <= mean_error
+    }
+}
+
+// Panics unless two canvases are the same size and differ by no more than a
+// maximum error in any pixel and, if given, a mean error overall (see
+// CanvasDiff), e.g. to check a render against a golden image. Errors are
+// from 0 to 1, so 2.0 / 255.0 allows for rounding to 8 bits.
+#[macro_export]
+macro_rules! assert_images_close {
+    ($left:expr, $right:expr, $max_error:expr) => {
+        $crate::assert_images_close!($left, $right, $max_error, 1.0)
+    };
+    ($left:expr, $right:expr, $max_error:expr, $mean_error:expr) => {
+        match $left.diff(&$right) {
+            Ok(diff) => {
+                if !diff.is_within($max_error, $mean_error) {
+                    panic!(
+                        "images differ by up to {} (mean {}), over the allowed {} (mean {})",
+                        diff.max_error, diff.mean_error, $max_error, $mean_error
+                    );
+                }
+            }
+            Err(error) => panic!("could not compare images: {}", error),
+        }
+    };
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Canvas {
     size: Size,
-    pixels: Vec<Vec<Pixel>>,
+    // rows top to bottom, so the pixel at (column, row) is at
+    // row * width + column
+    pixels: Vec<Pixel>,
+    // applied when writing the 8-bit formats, after exposure and tone
+    // mapping; EXR and HDR are always linear
+    encoding: Encoding,
+    tone_mapping: ToneMapping,
+    // in stops
+    exposure: Float,
+    // for PPM and PNG
+    bit_depth: BitDepth,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -81,82 +394,858 @@ struct Size {
 
 impl Canvas {
     pub fn new(Width(width): Width, Height(height): Height) -> Canvas {
-        let mut canvas: Vec<Vec<Pixel>> = Vec::with_capacity(height);
-        for _row in 0..height {
-            let mut row_pixels = Vec::with_capacity(width);
-            for _column in 0..width {
-                row_pixels.push(Pixel::new(Colour::new(0.0, 0.0, 0.0)));
-            }
-            canvas.push(row_pixels);
+        Canvas {
+            size: Size { width, height },
+            pixels: vec![Pixel::new(Colour::new(0.0, 0.0, 0.0)); width * height],
+            encoding: Encoding::default(),
+            tone_mapping: ToneMapping::default(),
+            exposure: 0.0,
+            bit_depth: BitDepth::default(),
         }
+    }
+
+    // starts fully transparent; see paint_coverage_additive
+    pub fn new_transparent(width: Width, height: Height) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for pixel in canvas.pixels.iter_mut() {
+            pixel.alpha = 0.0;
+        }
+        canvas
+    }
+
+    // 8-bit pixels decoded to linear light, e.g. to compare a render with a
+    // golden image; writing the canvas with the same encoding gives back the
+    // same bytes
+    pub fn from_image(image: &RawImage, encoding: Encoding) -> Canvas {
+        let mut canvas = Canvas::new(Width(image.width), Height(image.height));
+        canvas.encoding = encoding;
+        for (pixel, rgba) in canvas.pixels.iter_mut().zip(image.rgba.chunks(4)) {
+            let [red, green, blue, alpha] =
+                [0, 1, 2, 3].map(|idx| rgba[idx] as Float / PIXEL_MAX as Float);
+            let [red, green, blue] = [red, green, blue].map(|value| encoding.decode(value));
+            *pixel = Pixel::with_alpha(Colour::new(red, green, blue) * alpha, alpha);
+        }
+        canvas
+    }
+
+    // P3 or P6, with any maximum value up to 65535; samples keep their full
+    // precision, and past 255 the canvas is written back at 16 bits
+    pub fn from_ppm(data: &[u8], encoding: Encoding) -> Result<Canvas, ImageError> {
+        let ppm = PpmImage::decode(data)?;
+        let mut canvas = Canvas::new(Width(ppm.width), Height(ppm.height));
+        canvas.encoding = encoding;
+        if ppm.max_value > PIXEL_MAX as usize {
+            canvas.bit_depth = BitDepth::Sixteen;
+        }
+        for (pixel, rgb) in canvas.pixels.iter_mut().zip(ppm.samples.chunks(3)) {
+            let [red, green, blue] =
+                [0, 1, 2].map(|idx| encoding.decode(rgb[idx] as Float / ppm.max_value as Float));
+            *pixel = Pixel::new(Colour::new(red, green, blue));
+        }
+        Ok(canvas)
+    }
+
+    pub fn load_ppm(path_string: &str, encoding: Encoding) -> Result<Canvas, Box<dyn Error>> {
+        Ok(Canvas::from_ppm(&fs::read(path_string)?, encoding)?)
+    }
+
+    pub fn has_transparency(&self) -> bool {
+        self.pixels.iter().any(|pixel| pixel.alpha < 1.0)
+    }
+
+    pub fn width(&self) -> usize {
+        self.size.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.size.height
+    }
+
+    // None outside the canvas
+    pub fn get(&self, column: usize, row: usize) -> Option<&Pixel> {
+        if column >= self.size.width || row >= self.size.height {
+            return None;
+        }
+        self.pixels.get(row * self.size.width + column)
+    }
+
+    pub fn get_mut(&mut self, column: usize, row: usize) -> Option<&mut Pixel> {
+        if column >= self.size.width || row >= self.size.height {
+            return None;
+        }
+        self.pixels.get_mut(row * self.size.width + column)
+    }
+
+    pub fn try_get(&self, column: usize, row: usize) -> Option<Pixel> {
+        self.get(column, row).copied()
+    }
+
+    pub fn get_pixel(&self, column: usize, row: usize) -> Result<Pixel, WriteError> {
+        self.try_get(column, row).ok_or(WriteError::OutOfBounds)
+    }
+
+    pub fn set(&mut self, column: usize, row: usize, pixel: Pixel) -> Result<(), WriteError> {
+        *self.get_mut(column, row).ok_or(WriteError::OutOfBounds)? = pixel;
+        Ok(())
+    }
+
+    // the same as set, named to pair with get_pixel
+    pub fn set_pixel(&mut self, column: usize, row: usize, pixel: Pixel) -> Result<(), WriteError> {
+        self.set(column, row, pixel)
+    }
+
+    // top to bottom, each left to right
+    pub fn rows(&self) -> impl Iterator<Item = &[Pixel]> {
+        self.pixels.chunks(self.size.width.max(1))
+    }
+
+    // rows don't overlap, so can be written from several threads at once
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Pixel]> {
+        self.pixels.chunks_mut(self.size.width.max(1))
+    }
+
+    // row by row
+    pub fn pixels(&self) -> impl Iterator<Item = &Pixel> {
+        self.pixels.iter()
+    }
+
+    pub fn pixels_mut(&mut self) -> impl Iterator<Item = &mut Pixel> {
+        self.pixels.iter_mut()
+    }
+
+    // for reading pixels already known to be in bounds
+    fn at(&self, column: usize, row: usize) -> Pixel {
+        self.pixels[row * self.size.width + column]
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    pub fn exposure(&self) -> Float {
+        self.exposure
+    }
+
+    // brightens (or, if negative, darkens) the 8-bit formats by this many
+    // stops before tone mapping
+    pub fn set_exposure(&mut self, exposure: Float) {
+        self.exposure = exposure;
+    }
+
+    pub fn bit_depth(&self) -> BitDepth {
+        self.bit_depth
+    }
+
+    pub fn set_bit_depth(&mut self, bit_depth: BitDepth) {
+        self.bit_depth = bit_depth;
+    }
+
+    // what a pixel is written as to the 8-bit formats, before encoding
+    fn output_pixel(&self, pixel: &Pixel) -> Pixel {
+        pixel.tone_mapped(self.tone_mapping, self.exposure)
+    }
+
+    // a canvas of the given size with the same output settings as this one
+    fn derive<F>(&self, width: usize, height: usize, pixel_at: F) -> Canvas
+    where
+        F: Fn(usize, usize) -> Pixel,
+    {
         Canvas {
             size: Size { width, height },
-            pixels: canvas,
+            pixels: (0..height)
+                .flat_map(|row| (0..width).map(move |column| (column, row)))
+                .map(|(column, row)| pixel_at(column, row))
+                .collect(),
+            encoding: self.encoding,
+            tone_mapping: self.tone_mapping,
+            exposure: self.exposure,
+            bit_depth: self.bit_depth,
+        }
+    }
+
+    pub fn resize(
+        &self,
+        Width(width): Width,
+        Height(height): Height,
+        filter: ResizeFilter,
+    ) -> Canvas {
+        if self.size.width == 0 || self.size.height == 0 {
+            return self.derive(width, height, |_, _| Pixel::new(Colour::new(0.0, 0.0, 0.0)));
+        }
+        if filter == ResizeFilter::Lanczos {
+            return self.resize_lanczos(width, height);
         }
+        let scale_x = self.size.width as Float / width as Float;
+        let scale_y = self.size.height as Float / height as Float;
+
+        self.derive(width, height, |column, row| match filter {
+            ResizeFilter::Nearest => self.at(
+                (((column as Float + 0.5) * scale_x) as usize).min(self.size.width - 1),
+                (((row as Float + 0.5) * scale_y) as usize).min(self.size.height - 1),
+            ),
+            ResizeFilter::Bilinear => self.sample_bilinear(
+                (column as Float + 0.5) * scale_x - 0.5,
+                (row as Float + 0.5) * scale_y - 0.5,
+            ),
+            ResizeFilter::Box => self.sample_box(
+                [column as Float * scale_x, (column + 1) as Float * scale_x],
+                [row as Float * scale_y, (row + 1) as Float * scale_y],
+            ),
+            ResizeFilter::Lanczos => unreachable!("resized by resize_lanczos"),
+        })
+    }
+
+    // the filter is separable, so rows are resized and then columns
+    fn resize_lanczos(&self, width: usize, height: usize) -> Canvas {
+        let weighted_sum = |weights: &[(usize, Float)], pixel_at: &dyn Fn(usize) -> Pixel| {
+            weights.iter().fold(
+                Pixel::with_alpha(Colour::new(0.0, 0.0, 0.0), 0.0),
+                |sum, &(source, weight)| sum + pixel_at(source) * weight,
+            )
+        };
+        let weights_x = lanczos_weights(self.size.width, width);
+        let rows_resized = self.derive(width, self.size.height, |column, row| {
+            weighted_sum(&weights_x[column], &|source| self.at(source, row))
+        });
+        let weights_y = lanczos_weights(self.size.height, height);
+        rows_resized.derive(width, height, |column, row| {
+            weighted_sum(&weights_y[row], &|source| rows_resized.at(column, source))
+        })
+    }
+
+    // coordinates are in pixels, with pixel centres on integers; samples
+    // beyond the edges are clamped to the edge pixels
+    fn sample_bilinear(&self, x: Float, y: Float) -> Pixel {
+        let x = x.clamp(0.0, (self.size.width - 1) as Float);
+        let y = y.clamp(0.0, (self.size.height - 1) as Float);
+        let (column, row) = (x.floor() as usize, y.floor() as usize);
+        let next_column = (column + 1).min(self.size.width - 1);
+        let next_row = (row + 1).min(self.size.height - 1);
+        let (fraction_x, fraction_y) = (x - column as Float, y - row as Float);
+
+        let pixel_at = |column: usize, row: usize| self.at(column, row);
+        let top =
+            pixel_at(column, row) * (1.0 - fraction_x) + pixel_at(next_column, row) * fraction_x;
+        let bottom = pixel_at(column, next_row) * (1.0 - fraction_x)
+            + pixel_at(next_column, next_row) * fraction_x;
+        top * (1.0 - fraction_y) + bottom * fraction_y
+    }
+
+    // the area-weighted average over [x_start, x_end) x [y_start, y_end), in
+    // pixels with pixel edges on integers
+    fn sample_box(&self, [x_start, x_end]: [Float; 2], [y_start, y_end]: [Float; 2]) -> Pixel {
+        let mut sum = Pixel::with_alpha(Colour::new(0.0, 0.0, 0.0), 0.0);
+        let mut total_weight = 0.0;
+        let last_column = (x_end.ceil() as usize).min(self.size.width);
+        let last_row = (y_end.ceil() as usize).min(self.size.height);
+        for row in (y_start.floor() as usize)..last_row {
+            let overlap_y = y_end.min((row + 1) as Float) - y_start.max(row as Float);
+            for column in (x_start.floor() as usize)..last_column {
+                let overlap_x = x_end.min((column + 1) as Float) - x_start.max(column as Float);
+                let weight = overlap_x * overlap_y;
+                if weight > 0.0 {
+                    sum += self.at(column, row) * weight;
+                    total_weight += weight;
+                }
+            }
+        }
+        sum * (1.0 / total_weight)
+    }
+
+    pub fn crop(
+        &self,
+        column: usize,
+        row: usize,
+        Width(width): Width,
+        Height(height): Height,
+    ) -> Result<Canvas, WriteError> {
+        if !self.contains(column, row, width, height) {
+            return Err(WriteError::OutOfBounds);
+        }
+
+        Ok(self.derive(width, height, |x, y| self.at(column + x, row + y)))
+    }
+
+    pub fn flip_horizontal(&self) -> Canvas {
+        let Size { width, height } = self.size;
+        self.derive(width, height, |column, row| {
+            self.at(width - 1 - column, row)
+        })
+    }
+
+    pub fn flip_vertical(&self) -> Canvas {
+        let Size { width, height } = self.size;
+        self.derive(width, height, |column, row| {
+            self.at(column, height - 1 - row)
+        })
+    }
+
+    pub fn rotate_clockwise(&self) -> Canvas {
+        let Size { width, height } = self.size;
+        self.derive(height, width, |column, row| {
+            self.at(row, height - 1 - column)
+        })
+    }
+
+    pub fn rotate_anticlockwise(&self) -> Canvas {
+        let Size { width, height } = self.size;
+        self.derive(height, width, |column, row| {
+            self.at(width - 1 - row, column)
+        })
+    }
+
+    pub fn rotate_half(&self) -> Canvas {
+        let Size { width, height } = self.size;
+        self.derive(width, height, |column, row| {
+            self.at(width - 1 - column, height - 1 - row)
+        })
+    }
+
+    // copies another canvas, alpha and all, with its top left corner at
+    // (column, row); it must fit entirely, as with crop
+    pub fn blit(&mut self, other: &Canvas, column: usize, row: usize) -> Result<(), WriteError> {
+        self.combine(other, column, row, |_, pixel| pixel)
+    }
+
+    // draws another canvas in front of this one, e.g. a render with a
+    // transparent background over a backdrop
+    pub fn composite_over(
+        &mut self,
+        other: &Canvas,
+        column: usize,
+        row: usize,
+    ) -> Result<(), WriteError> {
+        self.combine(other, column, row, |below, pixel| pixel.over(below))
+    }
+
+    // adds another canvas's colours and coverage, e.g. to sum passes of
+    // samples weighted to add up to one
+    pub fn blend_additive(
+        &mut self,
+        other: &Canvas,
+        column: usize,
+        row: usize,
+    ) -> Result<(), WriteError> {
+        self.combine(other, column, row, |below, pixel| below + pixel)
+    }
+
+    // whether a width by height block with its top left corner at (column,
+    // row) lies entirely on the canvas, however far off it is
+    fn contains(&self, column: usize, row: usize, width: usize, height: usize) -> bool {
+        let fits = |start: usize, length: usize, limit: usize| {
+            start.checked_add(length).is_some_and(|end| end <= limit)
+        };
+        fits(column, width, self.size.width) && fits(row, height, self.size.height)
+    }
+
+    fn combine<F>(
+        &mut self,
+        other: &Canvas,
+        column: usize,
+        row: usize,
+        blend: F,
+    ) -> Result<(), WriteError>
+    where
+        F: Fn(Pixel, Pixel) -> Pixel,
+    {
+        let Size { width, height } = other.size;
+        if !self.contains(column, row, width, height) {
+            return Err(WriteError::OutOfBounds);
+        }
+        if width == 0 {
+            return Ok(());
+        }
+        let stride = self.size.width;
+        for (y, source) in other.rows().enumerate() {
+            let start = (row + y) * stride + column;
+            for (target, pixel) in self.pixels[start..start + width].iter_mut().zip(source) {
+                *target = blend(*target, *pixel);
+            }
+        }
+        Ok(())
+    }
+
+    // both canvases are written with this one's encoding, tone mapping and
+    // exposure, so only their pixels are compared; they must be the same size
+    pub fn diff(&self, other: &Canvas) -> Result<CanvasDiff, WriteError> {
+        if self.size != other.size {
+            return Err(WriteError::SizeMismatch {
+                size: (self.width(), self.height()),
+                other: (other.width(), other.height()),
+            });
+        }
+        let encoded = |pixel: &Pixel| {
+            let pixel = self.output_pixel(pixel);
+            let Colour { red, green, blue } = pixel.straight_colour();
+            [red, green, blue]
+                .map(|value| self.encoding.encode(value))
+                .into_iter()
+                .chain([pixel.alpha.clamp(0.0, 1.0)])
+        };
+
+        let mut max_error: Float = 0.0;
+        let mut total_error = 0.0;
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for (pixel, other_pixel) in self.pixels.iter().zip(&other.pixels) {
+            let differences: Vec<Float> = encoded(pixel)
+                .zip(encoded(other_pixel))
+                .map(|(value, other_value)| (value - other_value).abs())
+                .collect();
+            let error = differences.iter().copied().fold(0.0, Float::max);
+            max_error = max_error.max(error);
+            total_error += error;
+            pixels.push(Pixel::new(Colour::new(
+                differences[0],
+                differences[1],
+                differences[2],
+            )));
+        }
+
+        let image = Canvas {
+            size: self.size,
+            pixels,
+            encoding: Encoding::Linear,
+            tone_mapping: ToneMapping::default(),
+            exposure: 0.0,
+            bit_depth: self.bit_depth,
+        };
+        Ok(CanvasDiff {
+            image,
+            max_error,
+            mean_error: total_error / self.pixels.len().max(1) as Float,
+        })
+    }
+
+    pub fn paint_colour_replace(
+        &mut self,
+        column: usize,
+        row: usize,
+        colour: Colour,
+    ) -> Result<(), WriteError> {
+        self.set(column, row, Pixel::new(colour))
+    }
+
+    pub fn paint_colour_additive(
+        &mut self,
+        column: usize,
+        row: usize,
+        colour: Colour,
+    ) -> Result<(), WriteError> {
+        let pixel = self.get_mut(column, row).ok_or(WriteError::OutOfBounds)?;
+        pixel.colour = pixel.colour + colour;
+        Ok(())
+    }
+
+    // adds to a pixel's alpha, e.g. by the weight of each ray that hits
+    // something, when rendering onto a transparent canvas
+    pub fn paint_coverage_additive(
+        &mut self,
+        column: usize,
+        row: usize,
+        alpha: Float,
+    ) -> Result<(), WriteError> {
+        self.get_mut(column, row)
+            .ok_or(WriteError::OutOfBounds)?
+            .alpha += alpha;
+        Ok(())
+    }
+
+    // The drawing methods burn debug overlays (bounding boxes, sample
+    // positions, tile boundaries) into an image with opaque, one-pixel-wide
+    // strokes. Points are [column, row] and may lie off the canvas, e.g. for
+    // a box partly out of view; only the pixels on the canvas are painted.
+
+    // Bresenham's line, both ends included
+    pub fn draw_line(&mut self, from: [isize; 2], to: [isize; 2], colour: Colour) {
+        self.line(
+            from.map(|value| value as i128),
+            to.map(|value| value as i128),
+            colour,
+        );
+    }
+
+    // the outline, between opposite corners
+    pub fn draw_rectangle(&mut self, corner: [isize; 2], opposite: [isize; 2], colour: Colour) {
+        let [x0, y0] = corner;
+        let [x1, y1] = opposite;
+        self.draw_line([x0, y0], [x1, y0], colour);
+        self.draw_line([x1, y0], [x1, y1], colour);
+        self.draw_line([x1, y1], [x0, y1], colour);
+        self.draw_line([x0, y1], [x0, y0], colour);
+    }
+
+    // the pixels nearest the circle in each row it crosses, for its steep
+    // parts, and in each column, for its shallow ones; only the rows and
+    // columns of the canvas are visited, however large the circle
+    pub fn draw_circle(&mut self, [centre_x, centre_y]: [isize; 2], radius: usize, colour: Colour) {
+        let (centre_x, centre_y, radius) = (centre_x as i128, centre_y as i128, radius as i128);
+        // the offset along a row or column, to the nearest pixel, where the
+        // circle crosses it; exact in integers, since radius squared fits in
+        // a u128
+        let half_chord = |offset: i128| {
+            let squared = (radius as u128).pow(2) - offset.unsigned_abs().pow(2);
+            let root = squared.isqrt();
+            (root + u128::from(squared - root * root > root)) as i128
+        };
+        let spans = |centre: i128, size: usize| {
+            (centre - radius).max(0)..=(centre + radius).min(size as i128 - 1)
+        };
+        for row in spans(centre_y, self.size.height) {
+            let offset = half_chord(row - centre_y);
+            self.plot([centre_x - offset, row], colour);
+            self.plot([centre_x + offset, row], colour);
+        }
+        for column in spans(centre_x, self.size.width) {
+            let offset = half_chord(column - centre_x);
+            self.plot([column, centre_y - offset], colour);
+            self.plot([column, centre_y + offset], colour);
+        }
+    }
+
+    // a plus sign with arms reaching size pixels out from the centre
+    pub fn draw_crosshair(
+        &mut self,
+        [centre_x, centre_y]: [isize; 2],
+        size: usize,
+        colour: Colour,
+    ) {
+        let (centre_x, centre_y, size) = (centre_x as i128, centre_y as i128, size as i128);
+        self.line(
+            [centre_x - size, centre_y],
+            [centre_x + size, centre_y],
+            colour,
+        );
+        self.line(
+            [centre_x, centre_y - size],
+            [centre_x, centre_y + size],
+            colour,
+        );
+    }
+
+    // points are i128 so that offsets from any isize point can't overflow;
+    // the line is clipped to the canvas first, so only the steps on it are
+    // walked
+    fn line(&mut self, from: [i128; 2], to: [i128; 2], colour: Colour) {
+        let Some(([x0, y0], [x1, y1])) = self.clip_line(from, to) else {
+            return;
+        };
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            self.plot([x, y], colour);
+            if x == x1 && y == y1 {
+                return;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // Cohen-Sutherland: each end outside the canvas is moved along the line
+    // to the edge it is beyond, until both are on the canvas or both are
+    // beyond the same edge; the crossings are rounded to the nearest pixel
+    fn clip_line(&self, from: [i128; 2], to: [i128; 2]) -> Option<([i128; 2], [i128; 2])> {
+        const LEFT: u8 = 1;
+        const RIGHT: u8 = 2;
+        const TOP: u8 = 4;
+        const BOTTOM: u8 = 8;
+
+        if self.pixels.is_empty() {
+            return None;
+        }
+        let (last_x, last_y) = ((self.size.width - 1) as f64, (self.size.height - 1) as f64);
+        let outcode = |[x, y]: [f64; 2]| {
+            let mut code = 0;
+            if x < 0.0 {
+                code |= LEFT;
+            } else if x > last_x {
+                code |= RIGHT;
+            }
+            if y < 0.0 {
+                code |= TOP;
+            } else if y > last_y {
+                code |= BOTTOM;
+            }
+            code
+        };
+
+        let mut ends = [from, to].map(|end| end.map(|value| value as f64));
+        let mut codes = ends.map(outcode);
+        // each pass moves an end onto an edge, so a few passes suffice
+        for _ in 0..4 {
+            if codes[0] | codes[1] == 0 {
+                let [from, to] = ends.map(|end| end.map(|value| value.round() as i128));
+                return Some((from, to));
+            }
+            if codes[0] & codes[1] != 0 {
+                return None;
+            }
+            let outside = if codes[0] != 0 { 0 } else { 1 };
+            let [[x0, y0], [x1, y1]] = ends;
+            let code = codes[outside];
+            let crossing = if code & TOP != 0 {
+                [x0 + (x1 - x0) * (0.0 - y0) / (y1 - y0), 0.0]
+            } else if code & BOTTOM != 0 {
+                [x0 + (x1 - x0) * (last_y - y0) / (y1 - y0), last_y]
+            } else if code & LEFT != 0 {
+                [0.0, y0 + (y1 - y0) * (0.0 - x0) / (x1 - x0)]
+            } else {
+                [last_x, y0 + (y1 - y0) * (last_x - x0) / (x1 - x0)]
+            };
+            ends[outside] = crossing;
+            codes[outside] = outcode(crossing);
+        }
+        None
+    }
+
+    fn plot(&mut self, [column, row]: [i128; 2], colour: Colour) {
+        if let (Ok(column), Ok(row)) = (usize::try_from(column), usize::try_from(row)) {
+            if let Some(pixel) = self.get_mut(column, row) {
+                *pixel = Pixel::new(colour);
+            }
+        }
+    }
+
+    pub fn write_to_ppm(&self, format: PpmFormat) -> Result<Vec<u8>, std::io::Error> {
+        let mut buffer = Vec::new();
+        let Size { width, height } = self.size;
+        format.write_header(&mut buffer, width, height, self.bit_depth)?;
+        if format == PpmFormat::Binary {
+            buffer.extend(self.samples(false));
+            return Ok(buffer);
+        }
+        for row in self.rows() {
+            let mut row_buffer = String::new();
+            for pixel in row {
+                let colour_values: Vec<String> = self
+                    .output_pixel(pixel)
+                    .to_rgb_values(self.encoding, self.bit_depth)
+                    .iter()
+                    .map(|cval| cval.to_string())
+                    .collect();
+                for colour_value in colour_values {
+                    if row_buffer.len() + colour_value.len() + 1 > 70 {
+                        writeln!(buffer, "{}", row_buffer.trim())?;
+                        row_buffer = String::new();
+                    }
+                    row_buffer.push_str(&colour_value[..]);
+                    row_buffer.push(' ');
+                }
+            }
+            writeln!(buffer, "{}", row_buffer.trim())?;
+        }
+        Ok(buffer)
+    }
+
+    // always RGBA, so opaque canvases get a full alpha throughout
+    pub fn write_to_png(&self) -> Vec<u8> {
+        let Size { width, height } = self.size;
+        match self.bit_depth {
+            BitDepth::Eight => png::encode_rgba(width, height, &self.samples(true)),
+            BitDepth::Sixteen => png::encode_rgba16(width, height, &self.samples(true)),
+        }
+    }
+
+    fn rgba_bytes(&self) -> Vec<u8> {
+        self.bytes_as(
+            self.encoding,
+            self.tone_mapping,
+            self.exposure,
+            BitDepth::Eight,
+            true,
+        )
+    }
+
+    // for formats without alpha, so partly covered pixels are as if over black
+    pub(crate) fn rgb_bytes(&self) -> Vec<u8> {
+        self.bytes_as(
+            self.encoding,
+            self.tone_mapping,
+            self.exposure,
+            BitDepth::Eight,
+            false,
+        )
+    }
+
+    // at the canvas's bit depth, for PPM and PNG
+    fn samples(&self, with_alpha: bool) -> Vec<u8> {
+        self.bytes_as(
+            self.encoding,
+            self.tone_mapping,
+            self.exposure,
+            self.bit_depth,
+            with_alpha,
+        )
+    }
+
+    // the output with other settings than the canvas's own, e.g. for rows
+    // of a larger image (see ScanlineWriter)
+    pub(crate) fn bytes_as(
+        &self,
+        encoding: Encoding,
+        tone_mapping: ToneMapping,
+        exposure: Float,
+        bit_depth: BitDepth,
+        with_alpha: bool,
+    ) -> Vec<u8> {
+        let channels = if with_alpha { 4 } else { 3 };
+        let bytes_per_channel = match bit_depth {
+            BitDepth::Eight => 1,
+            BitDepth::Sixteen => 2,
+        };
+        let mut bytes = Vec::with_capacity(self.pixels.len() * channels * bytes_per_channel);
+        for pixel in &self.pixels {
+            let pixel = pixel.tone_mapped(tone_mapping, exposure);
+            if with_alpha {
+                bit_depth.extend_bytes(&mut bytes, &pixel.to_rgba_values(encoding, bit_depth));
+            } else {
+                bit_depth.extend_bytes(&mut bytes, &pixel.to_rgb_values(encoding, bit_depth));
+            }
+        }
+        bytes
+    }
+
+    // quality is from 1 (smallest) to 100 (best); around 90 is hard to tell
+    // from the original
+    pub fn write_to_jpeg(&self, quality: u8) -> Result<Vec<u8>, WriteError> {
+        self.check_fits("JPEG", jpeg::fits)?;
+        Ok(jpeg::encode_rgb(
+            self.size.width,
+            self.size.height,
+            &self.rgb_bytes(),
+            quality,
+        ))
+    }
+
+    // RGBA like PNG, but uncompressed
+    pub fn write_to_tga(&self) -> Result<Vec<u8>, WriteError> {
+        self.check_fits("TGA", tga::fits)?;
+        Ok(tga::encode_rgba(
+            self.size.width,
+            self.size.height,
+            &self.rgba_bytes(),
+        ))
+    }
+
+    pub fn write_to_bmp(&self) -> Result<Vec<u8>, WriteError> {
+        self.check_fits("BMP", bmp::fits)?;
+        Ok(bmp::encode_rgb(
+            self.size.width,
+            self.size.height,
+            &self.rgb_bytes(),
+        ))
+    }
+
+    fn check_fits(
+        &self,
+        format: &'static str,
+        fits: fn(usize, usize) -> bool,
+    ) -> Result<(), WriteError> {
+        let Size { width, height } = self.size;
+        if !fits(width, height) {
+            return Err(WriteError::TooLarge {
+                format,
+                width,
+                height,
+            });
+        }
+        Ok(())
+    }
+
+    // unclamped floats, premultiplied by alpha
+    pub fn write_to_exr(&self) -> Vec<u8> {
+        let rgba: Vec<f32> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| {
+                let Colour { red, green, blue } = pixel.colour;
+                [red, green, blue, pixel.alpha].map(|value| widen(value) as f32)
+            })
+            .collect();
+        exr::encode_rgba(self.size.width, self.size.height, &rgba)
+    }
+
+    // unclamped floats like EXR, but without alpha, so partly covered pixels
+    // are as if over black; each channel keeps about 8 bits of precision
+    pub fn write_to_hdr(&self) -> Vec<u8> {
+        let rgb: Vec<f32> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| {
+                let Colour { red, green, blue } = pixel.colour;
+                [red, green, blue].map(|value| widen(value) as f32)
+            })
+            .collect();
+        hdr::encode(self.size.width, self.size.height, &rgb)
+    }
+
+    pub fn output_to_hdr(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_hdr(), output_path)?;
+
+        Ok(())
+    }
+
+    pub fn output_to_exr(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_exr(), output_path)?;
+
+        Ok(())
+    }
+
+    pub fn output_to_jpeg(
+        &self,
+        output_path: &str,
+        quality: u8,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_jpeg(quality)?, output_path)?;
+
+        Ok(())
     }
 
-    pub fn paint_colour_replace(
-        &mut self,
-        column: usize,
-        row: usize,
-        colour: Colour,
-    ) -> Result<(), WriteError> {
-        match (column, row) {
-            (column, row) if column > self.size.width || row > self.size.height => {
-                return Err(WriteError::OutOfBounds)
-            }
-            _ => (),
-        };
+    pub fn output_to_tga(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_tga()?, output_path)?;
 
-        self.pixels[row][column] = Pixel::new(colour);
         Ok(())
     }
 
-    pub fn paint_colour_additive(
-        &mut self,
-        column: usize,
-        row: usize,
-        colour: Colour,
-    ) -> Result<(), WriteError> {
-        match (column, row) {
-            (column, row) if column > self.size.width || row > self.size.height => {
-                return Err(WriteError::OutOfBounds)
-            }
-            _ => (),
-        };
+    pub fn output_to_bmp(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_bmp()?, output_path)?;
 
-        self.pixels[row][column] += Pixel::new(colour);
         Ok(())
     }
 
-    pub fn write_to_ppm(&self) -> Result<Vec<u8>, std::io::Error> {
-        let mut buffer = Vec::new();
-        writeln!(&mut buffer, "{}", PPM_HEADER)?;
-        writeln!(&mut buffer, "{} {}", self.size.width, self.size.height)?;
-        writeln!(&mut buffer, "{}", PIXEL_MAX)?;
-        for row in &self.pixels {
-            let mut row_buffer = String::new();
-            for pixel in row {
-                let colour_values: Vec<String> = vec![pixel.red(), pixel.green(), pixel.blue()]
-                    .iter()
-                    .map(|cval| cval.to_string())
-                    .collect();
-                for colour_value in colour_values {
-                    if row_buffer.len() + colour_value.len() + 1 > 70 {
-                        writeln!(buffer, "{}", row_buffer.trim())?;
-                        row_buffer = String::new();
-                    }
-                    row_buffer.push_str(&colour_value[..]);
-                    row_buffer.push(' ');
-                }
-            }
-            writeln!(buffer, "{}", row_buffer.trim())?;
-        }
-        Ok(buffer)
+    pub fn output_to_png(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        filehandler::write_to_file(&self.write_to_png(), output_path)?;
+
+        Ok(())
     }
 
-    pub fn output_to_ppm(&self, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let buffer = self.write_to_ppm()?;
+    pub fn output_to_ppm(
+        &self,
+        output_path: &str,
+        format: PpmFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let buffer = self.write_to_ppm(format)?;
 
         filehandler::write_to_file(&buffer, output_path)?;
 
@@ -168,7 +1257,36 @@ impl Index<[usize; 2]> for Canvas {
     type Output = Pixel;
 
     fn index(&self, index: [usize; 2]) -> &Self::Output {
-        &self.pixels[index[1]][index[0]]
+        &self[(index[0], index[1])]
+    }
+}
+
+impl IndexMut<[usize; 2]> for Canvas {
+    fn index_mut(&mut self, index: [usize; 2]) -> &mut Self::Output {
+        &mut self[(index[0], index[1])]
+    }
+}
+
+// (column, row); panics outside the canvas, see get for a checked version
+impl Index<(usize, usize)> for Canvas {
+    type Output = Pixel;
+
+    fn index(&self, (column, row): (usize, usize)) -> &Self::Output {
+        self.get(column, row).unwrap_or_else(|| {
+            panic!(
+                "pixel ({column}, {row}) is outside the {} by {} canvas",
+                self.size.width, self.size.height
+            )
+        })
+    }
+}
+
+impl IndexMut<(usize, usize)> for Canvas {
+    fn index_mut(&mut self, (column, row): (usize, usize)) -> &mut Self::Output {
+        let Size { width, height } = self.size;
+        self.get_mut(column, row).unwrap_or_else(|| {
+            panic!("pixel ({column}, {row}) is outside the {width} by {height} canvas")
+        })
     }
 }
 
@@ -178,12 +1296,13 @@ mod tests {
     use std::io::prelude::*;
 
     use super::*;
+    use crate::utils::{RawImage, EPSILON};
 
     #[test]
     fn create_canvas() {
         let canvas = Canvas::new(Width(1), Height(2));
         let black_pixel = Pixel::new(Colour::new(0.0, 0.0, 0.0));
-        let resulting_canvas = vec![vec![black_pixel], vec![black_pixel]];
+        let resulting_canvas = vec![black_pixel, black_pixel];
         assert_eq!(
             canvas,
             Canvas {
@@ -192,6 +1311,10 @@ mod tests {
                     height: 2,
                 },
                 pixels: resulting_canvas,
+                encoding: Encoding::Srgb,
+                tone_mapping: ToneMapping::Clamp,
+                exposure: 0.0,
+                bit_depth: BitDepth::Eight,
             }
         );
     }
@@ -204,9 +1327,12 @@ mod tests {
         let grey_pixel = Pixel::new(Colour::new(0.5, 0.5, 0.5));
         canvas.paint_colour_additive(0, 1, grey_colour).unwrap();
         let resulting_canvas = vec![
-            vec![black_pixel, black_pixel],
-            vec![grey_pixel, black_pixel],
-            vec![black_pixel, black_pixel],
+            black_pixel,
+            black_pixel,
+            grey_pixel,
+            black_pixel,
+            black_pixel,
+            black_pixel,
         ];
         assert_eq!(
             canvas,
@@ -216,6 +1342,10 @@ mod tests {
                     height: 3,
                 },
                 pixels: resulting_canvas,
+                encoding: Encoding::Srgb,
+                tone_mapping: ToneMapping::Clamp,
+                exposure: 0.0,
+                bit_depth: BitDepth::Eight,
             }
         );
     }
@@ -229,11 +1359,474 @@ mod tests {
         canvas
             .paint_colour_additive(1, 1, Colour::new(0.5, 0.5, 0.5))
             .unwrap();
-        let output_buffer = b"P3\n2 2\n255\n255 255 255 0 0 0\n0 0 0 128 128 128\n".to_vec();
-        let written_buffer = canvas.write_to_ppm().unwrap();
+        // 0.5 in linear light is 188 in sRGB
+        let output_buffer = b"P3\n2 2\n255\n255 255 255 0 0 0\n0 0 0 188 188 188\n".to_vec();
+        let written_buffer = canvas.write_to_ppm(PpmFormat::Ascii).unwrap();
         assert_eq!(written_buffer, output_buffer);
     }
 
+    #[test]
+    fn write_ppm_with_other_encodings() {
+        let mut canvas = Canvas::new(Width(1), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(0.5, 0.25, 2.0))
+            .unwrap();
+        canvas.set_encoding(Encoding::Linear);
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            b"P3\n1 1\n255\n128 64 255\n"
+        );
+        canvas.set_encoding(Encoding::Gamma(2.0));
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            b"P3\n1 1\n255\n180 128 255\n"
+        );
+        // derived canvases keep the encoding
+        assert_eq!(canvas.flip_vertical().encoding(), Encoding::Gamma(2.0));
+    }
+
+    #[test]
+    fn pixels_are_stored_row_by_row() {
+        let mut canvas = numbered_canvas(3, 2);
+        let red = Pixel::new(Colour::new(1.0, 0.0, 0.0));
+        canvas.set(2, 1, red).unwrap();
+        assert_eq!(canvas.get(2, 1), Some(&red));
+        assert_eq!(canvas[(2, 1)], red);
+        assert_eq!(canvas[[2, 1]], red);
+        assert_eq!(canvas.get(3, 0), None);
+        assert_eq!(canvas.get(0, 2), None);
+        assert!(matches!(
+            canvas.set(3, 0, red),
+            Err(WriteError::OutOfBounds)
+        ));
+
+        assert_eq!(canvas.rows().count(), 2);
+        assert!(canvas.rows().all(|row| row.len() == 3));
+        assert_eq!(canvas.rows().nth(1).unwrap()[2], red);
+        assert_eq!(canvas.pixels().nth(5), Some(&red));
+
+        for (row, pixels) in canvas.rows_mut().enumerate() {
+            pixels[0] = Pixel::new(Colour::new(0.0, row as Float, 0.0));
+        }
+        assert_eq!(canvas[(0, 1)].colour(), Colour::new(0.0, 1.0, 0.0));
+        canvas.pixels_mut().for_each(|pixel| pixel.alpha = 0.5);
+        assert!(canvas.has_transparency());
+    }
+
+    #[test]
+    fn read_back_painted_pixels() {
+        let mut canvas = Canvas::new(Width(2), Height(3));
+        let red = Colour::new(1.0, 0.0, 0.0);
+        canvas.paint_colour_replace(1, 2, red).unwrap();
+        assert_eq!(canvas.get_pixel(1, 2).unwrap(), Pixel::new(red));
+        assert_eq!(canvas.try_get(1, 2), Some(Pixel::new(red)));
+        canvas.set_pixel(1, 1, Pixel::new(red)).unwrap();
+        assert_eq!(canvas.get_pixel(1, 1).unwrap(), Pixel::new(red));
+
+        canvas[(0, 1)] = Pixel::with_alpha(red, 0.5);
+        canvas[[0, 1]].alpha *= 0.5;
+        assert_eq!(canvas.get_pixel(0, 1).unwrap().alpha(), 0.25);
+
+        // the first index past each edge is already outside
+        for (column, row) in [(2, 0), (0, 3), (2, 3)] {
+            assert_eq!(canvas.try_get(column, row), None);
+            assert!(canvas.get_pixel(column, row).is_err());
+            assert!(canvas.set_pixel(column, row, Pixel::new(red)).is_err());
+            assert!(canvas.paint_colour_replace(column, row, red).is_err());
+            assert!(canvas.paint_colour_additive(column, row, red).is_err());
+            assert!(canvas.paint_coverage_additive(column, row, 1.0).is_err());
+        }
+    }
+
+    #[test]
+    fn assemble_tiles() {
+        let full = numbered_canvas(4, 3);
+        let mut assembled = Canvas::new(Width(4), Height(3));
+        for (column, row, width, height) in [(0, 0, 3, 2), (3, 0, 1, 2), (0, 2, 4, 1)] {
+            let tile = full
+                .crop(column, row, Width(width), Height(height))
+                .unwrap();
+            assembled.blit(&tile, column, row).unwrap();
+        }
+        assert_eq!(assembled, full);
+
+        let tile = Canvas::new(Width(2), Height(2));
+        assert!(assembled.blit(&tile, 3, 0).is_err());
+        assert!(assembled.blit(&tile, 0, 2).is_err());
+        assert!(assembled.blit(&tile, 2, 1).is_ok());
+        assert!(assembled.blit(&tile, usize::MAX, 0).is_err());
+        assert!(assembled.blit(&tile, 0, usize::MAX - 1).is_err());
+    }
+
+    #[test]
+    fn composite_and_add_canvases() {
+        let mut backdrop = Canvas::new(Width(2), Height(1));
+        backdrop
+            .paint_colour_replace(0, 0, Colour::new(0.0, 0.0, 1.0))
+            .unwrap();
+        backdrop
+            .paint_colour_replace(1, 0, Colour::new(0.0, 0.0, 1.0))
+            .unwrap();
+        // a red object covering half of the first pixel and none of the second
+        let mut render = Canvas::new_transparent(Width(1), Height(1));
+        render
+            .paint_colour_additive(0, 0, Colour::new(0.5, 0.0, 0.0))
+            .unwrap();
+        render.paint_coverage_additive(0, 0, 0.5).unwrap();
+
+        let mut over = backdrop.clone();
+        over.composite_over(&render, 0, 0).unwrap();
+        assert_eq!(over[(0, 0)].colour(), Colour::new(0.5, 0.0, 0.5));
+        assert_eq!(over[(0, 0)].alpha(), 1.0);
+        assert_eq!(over[(1, 0)], backdrop[(1, 0)]);
+
+        let mut sum = render.clone();
+        sum.blend_additive(&render, 0, 0).unwrap();
+        assert_eq!(sum[(0, 0)].colour(), Colour::new(1.0, 0.0, 0.0));
+        assert_eq!(sum[(0, 0)].alpha(), 1.0);
+        assert!(sum.composite_over(&backdrop, 0, 0).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn indexing_past_the_end_of_a_row_panics() {
+        let canvas = Canvas::new(Width(2), Height(2));
+        let _ = canvas[(2, 0)];
+    }
+
+    #[test]
+    fn read_ppm() {
+        let canvas = Canvas::from_ppm(
+            b"P3 # a comment\n2 1\n# another\n\t10 10 0 0\n\n0 5 10",
+            Encoding::Linear,
+        )
+        .unwrap();
+        assert_eq!((canvas.width(), canvas.height()), (2, 1));
+        assert_eq!(canvas[(0, 0)].colour(), Colour::new(1.0, 0.0, 0.0));
+        // scaled by the maximum value, not rounded to 8 bits on the way
+        assert_eq!(canvas[(1, 0)].colour(), Colour::new(0.0, 0.5, 1.0));
+        assert!(!canvas.has_transparency());
+
+        assert!(Canvas::from_ppm(b"P6 2 1 255\n\x00\x00", Encoding::Srgb).is_err());
+        assert!(Canvas::from_ppm(b"P5 1 1 255\n\x00", Encoding::Srgb).is_err());
+        assert!(Canvas::from_ppm(b"", Encoding::Srgb).is_err());
+    }
+
+    #[test]
+    fn read_ppm_with_impossible_dimensions() {
+        // fails rather than overflowing or allocating for the header's size
+        for data in [
+            &b"P3 4294967296 4294967296 255\n"[..],
+            b"P6 4294967296 4294967296 255\n",
+            b"P3 65536 65536 255\n0 0 0",
+            b"P6 65536 65536 65535\n\x00\x00",
+        ] {
+            assert!(Canvas::from_ppm(data, Encoding::Srgb).is_err());
+        }
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut canvas = numbered_canvas(3, 2);
+        canvas.set_encoding(Encoding::Gamma(2.2));
+        for format in [PpmFormat::Ascii, PpmFormat::Binary] {
+            let written = canvas.write_to_ppm(format).unwrap();
+            let read = Canvas::from_ppm(&written, Encoding::Gamma(2.2)).unwrap();
+            assert_eq!(read.write_to_ppm(format).unwrap(), written);
+            for (read, painted) in read.pixels().zip(canvas.pixels()) {
+                assert!((read.colour().red - painted.colour().red).abs() < 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn sixteen_bit_ppm_round_trip() {
+        let mut canvas = numbered_canvas(3, 2);
+        canvas.set_bit_depth(BitDepth::Sixteen);
+        for format in [PpmFormat::Ascii, PpmFormat::Binary] {
+            let written = canvas.write_to_ppm(format).unwrap();
+            let read = Canvas::from_ppm(&written, Encoding::Srgb).unwrap();
+            assert_eq!(read.bit_depth(), BitDepth::Sixteen);
+            assert_eq!(read.write_to_ppm(format).unwrap(), written);
+            // closer than 8 bits could hold
+            for (read, painted) in read.pixels().zip(canvas.pixels()) {
+                assert!((read.colour().red - painted.colour().red).abs() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn write_binary_ppm() {
+        let mut canvas = Canvas::new(Width(2), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(1.0, 0.5, 0.0))
+            .unwrap();
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Binary).unwrap(),
+            b"P6\n2 1\n255\n\xff\xbc\x00\x00\x00\x00"
+        );
+
+        let canvas = numbered_canvas(3, 2);
+        let ascii = RawImage::decode(&canvas.write_to_ppm(PpmFormat::Ascii).unwrap()).unwrap();
+        let binary = RawImage::decode(&canvas.write_to_ppm(PpmFormat::Binary).unwrap()).unwrap();
+        assert_eq!(ascii, binary);
+    }
+
+    #[test]
+    fn write_sixteen_bits_per_channel() {
+        let mut canvas = Canvas::new(Width(2), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(1.0, 0.5, 0.0))
+            .unwrap();
+        canvas.set_encoding(Encoding::Linear);
+        canvas.set_bit_depth(BitDepth::Sixteen);
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            b"P3\n2 1\n65535\n65535 32768 0 0 0 0\n"
+        );
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Binary).unwrap(),
+            b"P6\n2 1\n65535\n\xff\xff\x80\x00\x00\x00\x00\x00\x00\x00\x00\x00"
+        );
+        let png = canvas.write_to_png();
+        // the bit depth in the header
+        assert_eq!(png[24], 16);
+        let image = RawImage::decode(&png).unwrap();
+        assert_eq!(image.pixel(0, 0), [255, 128, 0, 255]);
+
+        // fine steps that 8 bits would round together
+        let mut gradient = Canvas::new(Width(2), Height(1));
+        gradient.set_encoding(Encoding::Linear);
+        gradient.set_bit_depth(BitDepth::Sixteen);
+        gradient[[0, 0]] = Pixel::new(Colour::new(0.5, 0.0, 0.0));
+        gradient[[1, 0]] = Pixel::new(Colour::new(0.501, 0.0, 0.0));
+        let ppm = gradient.write_to_ppm(PpmFormat::Binary).unwrap();
+        let samples = &ppm[ppm.len() - 12..];
+        assert_ne!(samples[..2], samples[6..8]);
+        // the other formats stay at 8 bits
+        assert_eq!(
+            gradient.write_to_tga().unwrap()[18..20],
+            gradient.write_to_tga().unwrap()[22..24]
+        );
+    }
+
+    #[test]
+    fn write_hdr_unclamped() {
+        let mut canvas = Canvas::new(Width(2), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(16.0, 0.5, 0.0))
+            .unwrap();
+        canvas
+            .paint_colour_additive(1, 0, Colour::new(-1.0, 0.25, 2.0))
+            .unwrap();
+        let image = hdr::decode(&canvas.write_to_hdr()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixel(0, 0), [16.0, 0.5, 0.0]);
+        assert_eq!(image.pixel(1, 0), [0.0, 0.25, 2.0]);
+    }
+
+    #[test]
+    fn write_jpeg_tga_and_bmp() {
+        // half covered by a ray seeing red
+        let mut canvas = Canvas::new_transparent(Width(2), Height(1));
+        canvas
+            .paint_colour_additive(1, 0, Colour::new(0.5, 0.0, 0.0))
+            .unwrap();
+        canvas.paint_coverage_additive(1, 0, 0.5).unwrap();
+
+        let jpeg = RawImage::decode(&canvas.write_to_jpeg(100).unwrap()).unwrap();
+        assert_eq!((jpeg.width, jpeg.height), (2, 1));
+        assert_eq!(jpeg.pixel(0, 0)[3], 255);
+        // only the tga keeps alpha; the others are over black
+        let tga = canvas.write_to_tga().unwrap();
+        assert_eq!(&tga[18..26], &[0, 0, 0, 0, 0, 0, 255, 128]);
+        let bmp = canvas.write_to_bmp().unwrap();
+        assert_eq!(&bmp[54..60], &[0, 0, 0, 0, 0, 188]);
+
+        // rather than truncating the 16-bit sizes
+        let wide = Canvas::new(Width(65536), Height(1));
+        assert_eq!(
+            wide.write_to_tga().unwrap_err().to_string(),
+            "a 65536x1 canvas is too large for TGA"
+        );
+        assert!(wide.write_to_jpeg(90).is_err());
+        assert!(wide.write_to_bmp().is_ok());
+    }
+
+    #[test]
+    fn tone_mapping_and_exposure_on_output() {
+        let mut canvas = Canvas::new(Width(1), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(4.0, 1.0, 0.25))
+            .unwrap();
+        canvas.set_encoding(Encoding::Linear);
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            b"P3\n1 1\n255\n255 255 64\n"
+        );
+        canvas.set_exposure(-2.0);
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            b"P3\n1 1\n255\n255 64 16\n"
+        );
+        canvas.set_exposure(0.0);
+        canvas.set_tone_mapping(ToneMapping::Reinhard);
+        let mapped = ToneMapping::Reinhard.map(Colour::new(4.0, 1.0, 0.25));
+        let [red, green, blue] =
+            Pixel::new(mapped).to_rgb_values(Encoding::Linear, BitDepth::Eight);
+        assert_eq!(
+            canvas.write_to_ppm(PpmFormat::Ascii).unwrap(),
+            format!("P3\n1 1\n255\n{red} {green} {blue}\n").into_bytes()
+        );
+        // floats are written as they are
+        assert_eq!(
+            hdr::decode(&canvas.write_to_hdr()).unwrap().pixel(0, 0),
+            [4.0, 1.0, 0.25]
+        );
+
+        // partial coverage scales the mapped colour
+        let mut canvas = Canvas::new_transparent(Width(1), Height(1));
+        canvas
+            .paint_colour_additive(0, 0, Colour::new(2.0, 0.0, 0.0))
+            .unwrap();
+        canvas.paint_coverage_additive(0, 0, 0.5).unwrap();
+        canvas.set_encoding(Encoding::Linear);
+        canvas.set_tone_mapping(ToneMapping::Aces);
+        let red = ToneMapping::Aces.map(Colour::new(4.0, 0.0, 0.0)).red;
+        assert_eq!(
+            canvas.write_to_tga().unwrap()[18..22],
+            [0, 0, (red * 255.0).round() as u8, 128]
+        );
+    }
+
+    #[test]
+    fn diff_canvases() {
+        let mut canvas = Canvas::new(Width(2), Height(2));
+        canvas.set_encoding(Encoding::Linear);
+        let mut other = canvas.clone();
+        other
+            .paint_colour_replace(1, 0, Colour::new(0.5, 0.0, 0.25))
+            .unwrap();
+        other
+            .paint_colour_replace(0, 1, Colour::new(0.0, 0.1, 0.0))
+            .unwrap();
+
+        let diff = canvas.diff(&other).unwrap();
+        assert!((diff.max_error - 0.5).abs() < EPSILON);
+        assert!((diff.mean_error - 0.15).abs() < EPSILON);
+        assert_eq!(diff.image[[1, 0]].colour(), Colour::new(0.5, 0.0, 0.25));
+        assert_eq!(diff.image[[1, 1]].colour(), Colour::new(0.0, 0.0, 0.0));
+        assert!(diff.is_within(0.5, 0.2));
+        assert!(!diff.is_within(0.4, 0.2));
+        assert_eq!(canvas.diff(&canvas).unwrap().max_error, 0.0);
+        assert!(matches!(
+            canvas.diff(&Canvas::new(Width(2), Height(1))),
+            Err(WriteError::SizeMismatch {
+                size: (2, 2),
+                other: (2, 1)
+            })
+        ));
+
+        // compared as encoded, where dark values are further apart
+        canvas.set_encoding(Encoding::Srgb);
+        assert!(canvas.diff(&other).unwrap().mean_error > 0.15);
+    }
+
+    #[test]
+    fn assert_images_close_within_tolerance() {
+        let canvas = numbered_canvas(3, 2);
+        let mut other = canvas.clone();
+        other[[2, 1]] += Pixel::new(Colour::new(0.001, 0.0, 0.0));
+        assert_images_close!(canvas, other, 2.0 / 255.0);
+        assert_images_close!(canvas, other, 2.0 / 255.0, 0.001);
+    }
+
+    #[test]
+    #[should_panic(expected = "images differ by up to")]
+    fn assert_images_close_past_tolerance() {
+        let canvas = Canvas::new(Width(1), Height(1));
+        let mut other = canvas.clone();
+        other[[0, 0]] = Pixel::new(Colour::new(1.0, 1.0, 1.0));
+        assert_images_close!(canvas, other, 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "canvases differ in size: 1x1 and 2x1")]
+    fn assert_images_close_of_different_sizes() {
+        let canvas = Canvas::new(Width(1), Height(1));
+        assert_images_close!(canvas, Canvas::new(Width(2), Height(1)), 0.5);
+    }
+
+    #[test]
+    fn draw_debug_overlays() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let painted = |canvas: &Canvas| -> Vec<[usize; 2]> {
+            let width = canvas.width();
+            (0..canvas.pixels.len())
+                .filter(|&idx| canvas.pixels[idx].colour() == white)
+                .map(|idx| [idx % width, idx / width])
+                .collect()
+        };
+
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_line([0, 0], [3, 1], white);
+        assert_eq!(painted(&canvas), [[0, 0], [1, 0], [2, 1], [3, 1]]);
+
+        // clipped to the canvas
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_rectangle([1, 1], [6, 3], white);
+        assert_eq!(
+            painted(&canvas),
+            [
+                [1, 1],
+                [2, 1],
+                [3, 1],
+                [4, 1],
+                [1, 2],
+                [1, 3],
+                [2, 3],
+                [3, 3],
+                [4, 3]
+            ]
+        );
+
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_circle([2, 2], 2, white);
+        let circle = painted(&canvas);
+        assert_eq!(circle.len(), 12);
+        assert!(circle.contains(&[2, 0]) && circle.contains(&[4, 2]));
+        assert!(!circle.contains(&[2, 2]) && !circle.contains(&[0, 0]));
+
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_crosshair([0, 4], 1, white);
+        assert_eq!(painted(&canvas), [[0, 3], [0, 4], [1, 4]]);
+
+        // far off the canvas, only the part on it is walked
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_line([isize::MIN, 2], [isize::MAX, 2], white);
+        assert_eq!(painted(&canvas), [[0, 2], [1, 2], [2, 2], [3, 2], [4, 2]]);
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_line([-1_000_000_000, -1_000_000_000], [2, 2], white);
+        assert_eq!(painted(&canvas), [[0, 0], [1, 1], [2, 2]]);
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_line([isize::MIN, isize::MIN], [isize::MIN, isize::MAX], white);
+        canvas.draw_crosshair([isize::MAX, isize::MIN], usize::MAX, white);
+        canvas.draw_circle([isize::MIN, 0], usize::MAX, white);
+        assert!(painted(&canvas).is_empty());
+        let mut canvas = Canvas::new(Width(5), Height(5));
+        canvas.draw_circle([2, 2], usize::MAX, white);
+        assert!(painted(&canvas).is_empty());
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            let encoded = Encoding::Srgb.encode(value);
+            assert!((Encoding::Srgb.decode(encoded) - value).abs() < EPSILON);
+        }
+        assert!(Encoding::Srgb.encode(0.2) > 0.45);
+    }
+
     #[test]
     fn write_ppm_large_canvas() {
         let mut canvas = Canvas::new(Width(10), Height(2));
@@ -243,10 +1836,172 @@ mod tests {
                 .unwrap();
         }
         let output_buffer = b"P3\n10 2\n255\n255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255\n255 255 255 255 255 255 255 255 255 255 255 255 255\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n".to_vec();
-        let written_buffer = canvas.write_to_ppm().unwrap();
+        let written_buffer = canvas.write_to_ppm(PpmFormat::Ascii).unwrap();
         assert_eq!(written_buffer, output_buffer);
     }
 
+    fn numbered_canvas(width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(Width(width), Height(height));
+        for row in 0..height {
+            for column in 0..width {
+                let value = (row * width + column) as Float / 10.0;
+                canvas
+                    .paint_colour_replace(column, row, Colour::new(value, 0.0, 0.0))
+                    .unwrap();
+            }
+        }
+        canvas
+    }
+
+    fn red_values(canvas: &Canvas) -> Vec<Vec<Float>> {
+        canvas
+            .rows()
+            .map(|row| row.iter().map(|pixel| pixel.colour.red).collect())
+            .collect()
+    }
+
+    #[test]
+    fn flip_and_rotate_canvas() {
+        // 0.0 0.1 0.2
+        // 0.3 0.4 0.5
+        let canvas = numbered_canvas(3, 2);
+        assert_eq!(
+            red_values(&canvas.flip_horizontal()),
+            vec![vec![0.2, 0.1, 0.0], vec![0.5, 0.4, 0.3]]
+        );
+        assert_eq!(
+            red_values(&canvas.flip_vertical()),
+            vec![vec![0.3, 0.4, 0.5], vec![0.0, 0.1, 0.2]]
+        );
+        assert_eq!(
+            red_values(&canvas.rotate_clockwise()),
+            vec![vec![0.3, 0.0], vec![0.4, 0.1], vec![0.5, 0.2]]
+        );
+        assert_eq!(
+            red_values(&canvas.rotate_anticlockwise()),
+            vec![vec![0.2, 0.5], vec![0.1, 0.4], vec![0.0, 0.3]]
+        );
+        assert_eq!(
+            canvas.rotate_half(),
+            canvas.flip_horizontal().flip_vertical()
+        );
+        assert_eq!(canvas.rotate_clockwise().rotate_anticlockwise(), canvas);
+    }
+
+    #[test]
+    fn crop_canvas() {
+        let canvas = numbered_canvas(3, 2);
+        let cropped = canvas.crop(1, 0, Width(2), Height(2)).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 2));
+        assert_eq!(red_values(&cropped), vec![vec![0.1, 0.2], vec![0.4, 0.5]]);
+        assert!(canvas.crop(2, 0, Width(2), Height(1)).is_err());
+    }
+
+    #[test]
+    fn box_resize_averages_pixels() {
+        let canvas = numbered_canvas(2, 2);
+        let resized = canvas.resize(Width(1), Height(1), ResizeFilter::Box);
+        assert!((resized[[0, 0]].colour.red - 0.15).abs() < EPSILON);
+
+        // enlarging by a whole factor repeats pixels
+        let enlarged = canvas.resize(Width(4), Height(4), ResizeFilter::Box);
+        assert_eq!(enlarged[[1, 1]], canvas[[0, 0]]);
+        assert_eq!(enlarged[[3, 2]], canvas[[1, 1]]);
+    }
+
+    #[test]
+    fn bilinear_resize_interpolates_pixels() {
+        let canvas = numbered_canvas(2, 1);
+        let enlarged = canvas.resize(Width(4), Height(1), ResizeFilter::Bilinear);
+        assert_eq!(
+            red_values(&enlarged)[0]
+                .iter()
+                .map(|red| (red * 1000.0).round() / 1000.0)
+                .collect::<Vec<_>>(),
+            vec![0.0, 0.025, 0.075, 0.1]
+        );
+        assert_eq!(
+            canvas.resize(Width(2), Height(1), ResizeFilter::Bilinear),
+            canvas
+        );
+    }
+
+    #[test]
+    fn nearest_resize_repeats_pixels() {
+        let canvas = numbered_canvas(3, 2);
+        let enlarged = canvas.resize(Width(6), Height(4), ResizeFilter::Nearest);
+        assert_eq!(red_values(&enlarged)[3], vec![0.3, 0.3, 0.4, 0.4, 0.5, 0.5]);
+        let shrunk = enlarged.resize(Width(3), Height(2), ResizeFilter::Nearest);
+        assert_eq!(shrunk, canvas);
+    }
+
+    #[test]
+    fn lanczos_resize_keeps_flat_areas_and_sharp_detail() {
+        let canvas = numbered_canvas(5, 4);
+        let same_size = canvas.resize(Width(5), Height(4), ResizeFilter::Lanczos);
+        for (resized, pixel) in same_size.pixels().zip(canvas.pixels()) {
+            assert!((resized.colour().red - pixel.colour().red).abs() < EPSILON);
+        }
+
+        let mut flat = Canvas::new(Width(7), Height(5));
+        flat.pixels_mut()
+            .for_each(|pixel| *pixel = Pixel::new(Colour::new(0.25, 0.5, 0.75)));
+        for (width, height) in [(3, 2), (16, 11)] {
+            let resized = flat.resize(Width(width), Height(height), ResizeFilter::Lanczos);
+            assert_eq!((resized.width(), resized.height()), (width, height));
+            for pixel in resized.pixels() {
+                assert!((pixel.colour().green - 0.5).abs() < EPSILON);
+                assert!((pixel.alpha() - 1.0).abs() < EPSILON);
+            }
+        }
+
+        // a step stays steeper than with bilinear filtering
+        let mut step = Canvas::new(Width(4), Height(1));
+        for column in 2..4 {
+            step.paint_colour_replace(column, 0, Colour::new(1.0, 1.0, 1.0))
+                .unwrap();
+        }
+        let [lanczos, bilinear] = [ResizeFilter::Lanczos, ResizeFilter::Bilinear]
+            .map(|filter| red_values(&step.resize(Width(16), Height(1), filter))[0].clone());
+        assert!(lanczos[9] - lanczos[6] > bilinear[9] - bilinear[6]);
+    }
+
+    #[test]
+    fn transparent_canvas_to_png_pixels() {
+        let mut canvas = Canvas::new_transparent(Width(2), Height(1));
+        assert!(canvas.has_transparency());
+        // half covered by a ray seeing red
+        canvas
+            .paint_colour_additive(1, 0, Colour::new(0.5, 0.0, 0.0))
+            .unwrap();
+        canvas.paint_coverage_additive(1, 0, 0.5).unwrap();
+        assert_eq!(
+            canvas[[0, 0]].to_rgba_values(Encoding::Srgb, BitDepth::Eight),
+            [0, 0, 0, 0]
+        );
+        assert_eq!(
+            canvas[[1, 0]].to_rgba_values(Encoding::Srgb, BitDepth::Eight),
+            [255, 0, 0, 128]
+        );
+        assert!(!Canvas::new(Width(2), Height(1)).has_transparency());
+
+        // resampling keeps coverage
+        let shrunk = canvas.resize(Width(1), Height(1), ResizeFilter::Box);
+        assert!((shrunk[[0, 0]].alpha() - 0.25).abs() < EPSILON);
+        assert_eq!(
+            shrunk[[0, 0]].to_rgba_values(Encoding::Srgb, BitDepth::Eight),
+            [255, 0, 0, 64]
+        );
+    }
+
+    #[test]
+    fn write_png_header() {
+        let canvas = Canvas::new(Width(3), Height(2));
+        let png = canvas.write_to_png();
+        assert_eq!(png[1..4], *b"PNG");
+        assert_eq!(png[16..24], [0, 0, 0, 3, 0, 0, 0, 2]);
+    }
+
     #[test]
     #[ignore]
     fn output_canvas_to_ppm() {
@@ -257,9 +2012,10 @@ mod tests {
         canvas
             .paint_colour_additive(1, 1, Colour::new(0.5, 0.5, 0.5))
             .unwrap();
-        let output_buffer = b"P3\n2 2\n255\n255 255 255 0 0 0\n0 0 0 128 128 128\n".to_vec();
+        // 0.5 in linear light is 188 in sRGB
+        let output_buffer = b"P3\n2 2\n255\n255 255 255 0 0 0\n0 0 0 188 188 188\n".to_vec();
 
-        canvas.output_to_ppm("test.ppm").unwrap();
+        canvas.output_to_ppm("test.ppm", PpmFormat::Ascii).unwrap();
 
         let mut read_buffer = Vec::new();
         File::open("test.ppm")
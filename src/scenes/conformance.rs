@@ -0,0 +1,601 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+
+use crate::collections::*;
+use crate::objects::*;
+use crate::scenes::*;
+use crate::utils::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, FRAC_PI_6};
+use crate::utils::{Buildable, ConsumingBuilder, Float, ImageError, RawImage};
+
+#[derive(Debug)]
+pub enum ConformanceError {
+    Write(WriteError),
+    Io(std::io::Error),
+    Image(ImageError),
+    Mismatch {
+        scene: ConformanceScene,
+        max_difference: u8,
+    },
+}
+
+impl Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::Write(error) => write!(f, "could not paint canvas: {error:?}"),
+            ConformanceError::Io(error) => write!(f, "could not access golden image: {error}"),
+            ConformanceError::Image(error) => write!(f, "could not read golden image: {error}"),
+            ConformanceError::Mismatch {
+                scene,
+                max_difference,
+            } => write!(
+                f,
+                "{} differs from its golden image by up to {max_difference}/255",
+                scene.name()
+            ),
+        }
+    }
+}
+
+impl Error for ConformanceError {}
+
+impl From<WriteError> for ConformanceError {
+    fn from(error: WriteError) -> ConformanceError {
+        ConformanceError::Write(error)
+    }
+}
+
+impl From<std::io::Error> for ConformanceError {
+    fn from(error: std::io::Error) -> ConformanceError {
+        ConformanceError::Io(error)
+    }
+}
+
+impl From<ImageError> for ConformanceError {
+    fn from(error: ImageError) -> ConformanceError {
+        ConformanceError::Image(error)
+    }
+}
+
+// The end-of-chapter scenes of The Ray Tracer Challenge (approximately; the
+// book leaves some details open), plus a set of nested glass spheres for the
+// refraction containers. Each renders small and is checked against a golden
+// image in resources/conformance, so a change that breaks one subsystem shows
+// up as the scene exercising it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConformanceScene {
+    Shadows,
+    Patterns,
+    Reflection,
+    Refraction,
+    Cubes,
+    Cylinders,
+    Groups,
+    Csg,
+    RussianDoll,
+}
+
+impl ConformanceScene {
+    pub const ALL: [ConformanceScene; 9] = [
+        ConformanceScene::Shadows,
+        ConformanceScene::Patterns,
+        ConformanceScene::Reflection,
+        ConformanceScene::Refraction,
+        ConformanceScene::Cubes,
+        ConformanceScene::Cylinders,
+        ConformanceScene::Groups,
+        ConformanceScene::Csg,
+        ConformanceScene::RussianDoll,
+    ];
+
+    // golden images are rendered at this size
+    pub const GOLDEN_SIZE: (usize, usize) = (40, 20);
+    // the largest difference in any channel of any pixel still passing, to
+    // allow for rounding differences between platforms
+    pub const TOLERANCE: u8 = 2;
+    // how many pixels may differ by more than that: none, but with the f32
+    // feature a few land on the other side of an edge (a checker square, a
+    // stripe, the rim of a glass sphere) from where they are in the f64
+    // golden images, and are a different colour altogether
+    #[cfg(not(feature = "f32"))]
+    pub const OUTLIERS: usize = 0;
+    #[cfg(feature = "f32")]
+    pub const OUTLIERS: usize = 8;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConformanceScene::Shadows => "shadows",
+            ConformanceScene::Patterns => "patterns",
+            ConformanceScene::Reflection => "reflection",
+            ConformanceScene::Refraction => "refraction",
+            ConformanceScene::Cubes => "cubes",
+            ConformanceScene::Cylinders => "cylinders",
+            ConformanceScene::Groups => "groups",
+            ConformanceScene::Csg => "csg",
+            ConformanceScene::RussianDoll => "russian_doll",
+        }
+    }
+
+    pub fn golden_path(self) -> String {
+        format!("./resources/conformance/{}.ppm", self.name())
+    }
+
+    pub fn world(self) -> World {
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let objects = match self {
+            ConformanceScene::Shadows => shadows_scene(),
+            ConformanceScene::Patterns => patterns_scene(),
+            ConformanceScene::Reflection => reflection_scene(),
+            ConformanceScene::Refraction => refraction_scene(),
+            ConformanceScene::Cubes => cubes_scene(),
+            ConformanceScene::Cylinders => cylinders_scene(),
+            ConformanceScene::Groups => groups_scene(),
+            ConformanceScene::Csg => csg_scene(),
+            ConformanceScene::RussianDoll => russian_doll_scene(),
+        };
+        World::new(objects, vec![Box::new(light)])
+    }
+
+    pub fn camera(self, hsize: usize, vsize: usize) -> Camera<Native> {
+        let (from, to) = match self {
+            ConformanceScene::Groups => (Point::new(0.0, 4.0, -4.0), Point::new(0.0, 0.5, 0.0)),
+            ConformanceScene::RussianDoll => {
+                (Point::new(0.0, 0.5, -5.0), Point::new(0.0, 1.0, 0.0))
+            }
+            _ => (Point::new(0.0, 1.5, -5.0), Point::new(0.0, 1.0, 0.0)),
+        };
+        Camera::new(Native::new(
+            hsize,
+            vsize,
+            Angle::from_radians(FRAC_PI_3),
+            Orientation::new(from, to, Vector::new(0.0, 1.0, 0.0)),
+        ))
+    }
+
+    pub fn render(self, hsize: usize, vsize: usize) -> Result<Canvas, WriteError> {
+        self.camera(hsize, vsize).render(&self.world())
+    }
+
+    // renders at the golden size and compares with the golden image
+    pub fn verify(self) -> Result<(), ConformanceError> {
+        let (hsize, vsize) = ConformanceScene::GOLDEN_SIZE;
+        let rendered =
+            RawImage::decode(&self.render(hsize, vsize)?.write_to_ppm(PpmFormat::Ascii)?)?;
+        let golden = RawImage::decode(&fs::read(self.golden_path())?)?;
+        if (rendered.width, rendered.height) != (golden.width, golden.height) {
+            return Err(ConformanceError::Mismatch {
+                scene: self,
+                max_difference: u8::MAX,
+            });
+        }
+
+        // the largest difference in any channel, for each pixel
+        let differences: Vec<u8> = rendered
+            .rgba
+            .chunks(4)
+            .zip(golden.rgba.chunks(4))
+            .map(|(rendered, golden)| {
+                rendered
+                    .iter()
+                    .zip(golden)
+                    .map(|(&rendered, &golden)| rendered.abs_diff(golden))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let max_difference = differences.iter().copied().max().unwrap_or(0);
+        let outliers = differences
+            .iter()
+            .filter(|&&difference| difference > ConformanceScene::TOLERANCE)
+            .count();
+        if outliers > ConformanceScene::OUTLIERS {
+            return Err(ConformanceError::Mismatch {
+                scene: self,
+                max_difference,
+            });
+        }
+        Ok(())
+    }
+
+    // replaces the golden image, after an intended change to the renderer
+    pub fn write_golden(self) -> Result<(), ConformanceError> {
+        let (hsize, vsize) = ConformanceScene::GOLDEN_SIZE;
+        let ppm = self.render(hsize, vsize)?.write_to_ppm(PpmFormat::Ascii)?;
+        fs::write(self.golden_path(), ppm)?;
+        Ok(())
+    }
+}
+
+fn solid(red: Float, green: Float, blue: Float) -> Material {
+    Material {
+        pattern: Box::new(Solid::new(Colour::new(red, green, blue))),
+        diffuse: 0.7,
+        specular: 0.3,
+        ..Material::preset()
+    }
+}
+
+fn floor(material: Material) -> Shape {
+    Plane::builder().set_material(material).build().into()
+}
+
+fn checkered_floor(reflectance: Float) -> Shape {
+    floor(Material {
+        pattern: Box::new(Checker::new(
+            Colour::new(0.9, 0.9, 0.9),
+            Colour::new(0.2, 0.2, 0.2),
+            Transform::default(),
+        )),
+        specular: 0.0,
+        reflectance,
+        ..Material::preset()
+    })
+}
+
+fn sphere(transform: Vec<TransformKind>, material: Material) -> Shape {
+    Sphere::builder()
+        .set_frame_transformation(Transform::from(transform))
+        .set_material(material)
+        .build()
+        .into()
+}
+
+fn glass(refractive_index: Float) -> Material {
+    Material {
+        pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+        ambient: 0.0,
+        diffuse: 0.1,
+        specular: 1.0,
+        shininess: 300.0,
+        reflectance: 0.9,
+        transparency: 0.9,
+        refractive_index,
+        ..Material::preset()
+    }
+}
+
+// chapter 7: three spheres casting shadows onto a floor and each other
+fn shadows_scene() -> Vec<Shape> {
+    vec![
+        floor(Material {
+            specular: 0.0,
+            ..solid(1.0, 0.9, 0.9)
+        }),
+        sphere(
+            vec![TransformKind::Translate(-0.5, 1.0, 0.5)],
+            solid(0.1, 1.0, 0.5),
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.5, 0.5, 0.5),
+                TransformKind::Translate(1.5, 0.5, -0.5),
+            ],
+            solid(0.5, 1.0, 0.1),
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.33, 0.33, 0.33),
+                TransformKind::Translate(-1.5, 0.33, -0.75),
+            ],
+            solid(1.0, 0.8, 0.1),
+        ),
+    ]
+}
+
+// chapter 10: the same spheres wearing stripes, gradients and rings over a
+// checkered floor
+fn patterns_scene() -> Vec<Shape> {
+    let patterned = |pattern: Box<dyn Pattern>| Material {
+        pattern,
+        ..solid(0.0, 0.0, 0.0)
+    };
+    let small = Transform::new(TransformKind::Scale(0.25, 0.25, 0.25));
+    vec![
+        checkered_floor(0.0),
+        sphere(
+            vec![TransformKind::Translate(-0.5, 1.0, 0.5)],
+            patterned(Box::new(Stripe::new(
+                Colour::new(0.1, 1.0, 0.5),
+                Colour::new(1.0, 1.0, 1.0),
+                Transform::from(vec![
+                    TransformKind::Scale(0.2, 0.2, 0.2),
+                    TransformKind::Rotate(Axis::Z, Angle::from_radians(FRAC_PI_4)),
+                ]),
+            ))),
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.5, 0.5, 0.5),
+                TransformKind::Translate(1.5, 0.5, -0.5),
+            ],
+            patterned(Box::new(Gradient::new(
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(0.0, 0.0, 1.0),
+                Transform::from(vec![
+                    TransformKind::Scale(2.0, 1.0, 1.0),
+                    TransformKind::Translate(-1.0, 0.0, 0.0),
+                ]),
+            ))),
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.33, 0.33, 0.33),
+                TransformKind::Translate(-1.5, 0.33, -0.75),
+            ],
+            patterned(Box::new(Ring::new(
+                Colour::new(1.0, 0.8, 0.1),
+                Colour::new(0.3, 0.2, 0.0),
+                small,
+            ))),
+        ),
+    ]
+}
+
+// chapter 11: a mirrored sphere and a reflective floor
+fn reflection_scene() -> Vec<Shape> {
+    vec![
+        checkered_floor(0.3),
+        sphere(
+            vec![TransformKind::Translate(-0.5, 1.0, 0.5)],
+            Material {
+                reflectance: 0.9,
+                ..solid(0.1, 0.1, 0.1)
+            },
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.5, 0.5, 0.5),
+                TransformKind::Translate(1.5, 0.5, -0.5),
+            ],
+            solid(0.9, 0.2, 0.2),
+        ),
+    ]
+}
+
+// chapter 11: a glass sphere with an air bubble, in front of a red sphere
+fn refraction_scene() -> Vec<Shape> {
+    vec![
+        checkered_floor(0.0),
+        sphere(vec![TransformKind::Translate(0.0, 1.0, 0.0)], glass(1.5)),
+        sphere(
+            vec![
+                TransformKind::Scale(0.5, 0.5, 0.5),
+                TransformKind::Translate(0.0, 1.0, 0.0),
+            ],
+            Material {
+                reflectance: 0.0,
+                ..glass(1.0)
+            },
+        ),
+        sphere(
+            vec![
+                TransformKind::Scale(0.7, 0.7, 0.7),
+                TransformKind::Translate(1.0, 0.7, 3.0),
+            ],
+            solid(0.9, 0.2, 0.2),
+        ),
+    ]
+}
+
+// chapter 12: a table of cubes with a cube on top
+fn cubes_scene() -> Vec<Shape> {
+    let cube = |transform: Vec<TransformKind>, material: Material| -> Shape {
+        Cube::builder()
+            .set_frame_transformation(Transform::from(transform))
+            .set_material(material)
+            .build()
+            .into()
+    };
+    let wood = solid(0.55, 0.35, 0.2);
+    let mut objects = vec![
+        floor(solid(0.9, 0.9, 0.9)),
+        cube(
+            vec![
+                TransformKind::Scale(1.5, 0.05, 1.0),
+                TransformKind::Translate(0.0, 1.0, 0.0),
+            ],
+            wood.clone(),
+        ),
+        cube(
+            vec![
+                TransformKind::Scale(0.2, 0.2, 0.2),
+                TransformKind::Rotate(Axis::Y, Angle::from_radians(FRAC_PI_6)),
+                TransformKind::Translate(0.3, 1.25, -0.2),
+            ],
+            solid(0.2, 0.4, 0.9),
+        ),
+    ];
+    for (x, z) in [(-1.3, -0.8), (1.3, -0.8), (-1.3, 0.8), (1.3, 0.8)] {
+        objects.push(cube(
+            vec![
+                TransformKind::Scale(0.05, 0.5, 0.05),
+                TransformKind::Translate(x, 0.5, z),
+            ],
+            wood.clone(),
+        ));
+    }
+    objects
+}
+
+// chapter 13: capped and open cylinders and a cone
+fn cylinders_scene() -> Vec<Shape> {
+    let cylinder = |transform: Vec<TransformKind>, maximum: Float, material: Material| -> Shape {
+        Cylinder::builder()
+            .set_frame_transformation(Transform::from(transform))
+            .set_y_minimum(0.0)
+            .set_y_maximum(maximum)
+            .set_material(material)
+            .build()
+            .into()
+    };
+    vec![
+        checkered_floor(0.0),
+        cylinder(
+            vec![
+                TransformKind::Scale(0.5, 1.0, 0.5),
+                TransformKind::Translate(-1.2, 0.0, 0.0),
+            ],
+            1.5,
+            solid(0.2, 0.6, 0.9),
+        ),
+        cylinder(
+            vec![
+                TransformKind::Scale(0.3, 1.0, 0.3),
+                TransformKind::Rotate(Axis::Z, Angle::from_radians(FRAC_PI_2)),
+                TransformKind::Translate(0.6, 0.3, -1.0),
+            ],
+            1.2,
+            solid(0.9, 0.6, 0.2),
+        ),
+        Cone::builder()
+            .set_frame_transformation(Transform::from(vec![
+                TransformKind::Scale(0.5, 1.0, 0.5),
+                TransformKind::Translate(0.9, 1.0, 0.8),
+            ]))
+            .set_y_minimum(-1.0)
+            .set_y_maximum(0.0)
+            .set_material(solid(0.3, 0.9, 0.3))
+            .build()
+            .into(),
+    ]
+}
+
+// chapter 14: a hexagon of spheres and cylinders, each side its own group
+fn groups_scene() -> Vec<Shape> {
+    let material = solid(0.8, 0.3, 0.3);
+    let side = |index: usize| -> Shape {
+        let corner = sphere(
+            vec![
+                TransformKind::Scale(0.25, 0.25, 0.25),
+                TransformKind::Translate(0.0, 0.0, -1.0),
+            ],
+            material.clone(),
+        );
+        let edge = Cylinder::builder()
+            .set_frame_transformation(Transform::from(vec![
+                TransformKind::Scale(0.25, 1.0, 0.25),
+                TransformKind::Rotate(Axis::Z, Angle::from_radians(-FRAC_PI_2)),
+                TransformKind::Rotate(Axis::Y, Angle::from_radians(-FRAC_PI_6)),
+                TransformKind::Translate(0.0, 0.0, -1.0),
+            ]))
+            .set_y_minimum(0.0)
+            .set_y_maximum(1.0)
+            .set_material(material.clone())
+            .build()
+            .into();
+        Group::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Rotate(
+                Axis::Y,
+                Angle::from_radians(index as Float * FRAC_PI_3),
+            )))
+            .set_objects(vec![corner, edge])
+            .build()
+            .into()
+    };
+    let hexagon = Group::builder()
+        .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.5, 0.0)))
+        .set_objects((0..6).map(side).collect())
+        .build()
+        .into();
+    vec![checkered_floor(0.0), hexagon]
+}
+
+// chapter 16: a die, a cube with a sphere carved out of each face
+fn csg_scene() -> Vec<Shape> {
+    let cube = Cube::builder().set_material(solid(0.9, 0.9, 0.8)).build();
+    let sphere = Sphere::builder()
+        .set_frame_transformation(Transform::new(TransformKind::Scale(1.35, 1.35, 1.35)))
+        .set_material(solid(0.9, 0.2, 0.2))
+        .build();
+    let die = Csg::new(CsgOperation::Intersect, cube.into(), sphere.into());
+    let hollow = Sphere::builder()
+        .set_frame_transformation(Transform::new(TransformKind::Scale(0.6, 0.6, 0.6)))
+        .set_material(solid(0.2, 0.2, 0.9))
+        .build();
+    let carved = Csg::new(CsgOperation::Difference, die.into(), hollow.into());
+    let placed = Group::builder()
+        .set_frame_transformation(Transform::from(vec![
+            TransformKind::Scale(0.7, 0.7, 0.7),
+            TransformKind::Rotate(Axis::Y, Angle::from_radians(FRAC_PI_6)),
+            TransformKind::Translate(0.0, 0.7, 0.0),
+        ]))
+        .add_object(carved.into())
+        .build()
+        .into();
+    vec![checkered_floor(0.0), placed]
+}
+
+// glass spheres nested like Russian dolls, alternating dense and light, with
+// an opaque core; without reflections, so rays do not branch at every shell
+fn russian_doll_scene() -> Vec<Shape> {
+    let mut objects = vec![floor(Material {
+        pattern: Box::new(Stripe::new(
+            Colour::new(0.9, 0.9, 0.9),
+            Colour::new(0.1, 0.3, 0.6),
+            Transform::new(TransformKind::Scale(0.3, 0.3, 0.3)),
+        )),
+        specular: 0.0,
+        ..Material::preset()
+    })];
+    for (radius, refractive_index) in [(1.0, 1.5), (0.75, 1.1), (0.5, 1.8)] {
+        objects.push(sphere(
+            vec![
+                TransformKind::Scale(radius, radius, radius),
+                TransformKind::Translate(0.0, 1.0, 0.0),
+            ],
+            Material {
+                reflectance: 0.0,
+                ..glass(refractive_index)
+            },
+        ));
+    }
+    objects.push(sphere(
+        vec![
+            TransformKind::Scale(0.25, 0.25, 0.25),
+            TransformKind::Translate(0.0, 1.0, 0.0),
+        ],
+        solid(0.9, 0.7, 0.1),
+    ));
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_match_golden_images() {
+        std::thread::scope(|scope| {
+            let handles = ConformanceScene::ALL.map(|scene| scope.spawn(move || scene.verify()));
+            for handle in handles {
+                if let Err(error) = handle.join().unwrap() {
+                    panic!("{error}");
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn scenes_differ_and_show_something() {
+        let renders: Vec<Canvas> = ConformanceScene::ALL
+            .iter()
+            .map(|scene| scene.render(16, 8).unwrap())
+            .collect();
+        for (idx, render) in renders.iter().enumerate() {
+            assert!(renders[idx + 1..].iter().all(|other| other != render));
+            assert!(render[[8, 4]].colour() != Colour::new(0.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn display_mismatch() {
+        let error = ConformanceError::Mismatch {
+            scene: ConformanceScene::RussianDoll,
+            max_difference: 40,
+        };
+        assert_eq!(
+            error.to_string(),
+            "russian_doll differs from its golden image by up to 40/255"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::collections::Colour;
+use crate::objects::Material;
+use crate::scenes::*;
+use crate::utils::{Float, Font, FontError};
+
+#[derive(Debug)]
+pub enum ContactSheetError {
+    Write(WriteError),
+    Font(FontError),
+}
+
+impl Display for ContactSheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContactSheetError::Write(error) => write!(f, "could not paint canvas: {error:?}"),
+            ContactSheetError::Font(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for ContactSheetError {}
+
+impl From<WriteError> for ContactSheetError {
+    fn from(error: WriteError) -> ContactSheetError {
+        ContactSheetError::Write(error)
+    }
+}
+
+impl From<FontError> for ContactSheetError {
+    fn from(error: FontError) -> ContactSheetError {
+        ContactSheetError::Font(error)
+    }
+}
+
+// Lays out labelled images in a grid, e.g. to compare the renders of a
+// parameter sweep side by side. Every cell is as large as the largest image,
+// cells are filled left to right then top to bottom, and each label is drawn
+// centred under its image (only if a font is given).
+#[derive(Clone, Debug)]
+pub struct ContactSheet {
+    pub columns: usize,
+    pub padding: usize,
+    pub background: Colour,
+    pub label_colour: Colour,
+    pub label_height: usize,
+    pub font: Option<Font>,
+}
+
+impl Default for ContactSheet {
+    fn default() -> ContactSheet {
+        ContactSheet {
+            columns: 4,
+            padding: 4,
+            background: Colour::new(0.0, 0.0, 0.0),
+            label_colour: Colour::new(1.0, 1.0, 1.0),
+            label_height: 16,
+            font: None,
+        }
+    }
+}
+
+impl ContactSheet {
+    pub fn assemble(&self, cells: &[(Canvas, String)]) -> Result<Canvas, ContactSheetError> {
+        let columns = self.columns.clamp(1, cells.len().max(1));
+        let rows = cells.len().div_ceil(columns);
+        let cell_width = cells
+            .iter()
+            .map(|(image, _)| image.width())
+            .max()
+            .unwrap_or(0);
+        let image_height = cells
+            .iter()
+            .map(|(image, _)| image.height())
+            .max()
+            .unwrap_or(0);
+        let label_height = if self.font.is_some() {
+            self.label_height
+        } else {
+            0
+        };
+        let cell_height = image_height + label_height;
+
+        let width = columns * cell_width + (columns + 1) * self.padding;
+        let height = rows * cell_height + (rows + 1) * self.padding;
+        let mut sheet = Canvas::new(Width(width), Height(height));
+        for row in 0..height {
+            for column in 0..width {
+                sheet.paint_colour_replace(column, row, self.background)?;
+            }
+        }
+
+        for (idx, (image, label)) in cells.iter().enumerate() {
+            let left = self.padding + (idx % columns) * (cell_width + self.padding);
+            let top = self.padding + (idx / columns) * (cell_height + self.padding);
+            for row in 0..image.height() {
+                for column in 0..image.width() {
+                    let colour = image[[column, row]].colour();
+                    sheet.paint_colour_replace(left + column, top + row, colour)?;
+                }
+            }
+            if let Some(font) = &self.font {
+                let label_area = [left, top + image_height, cell_width, label_height];
+                self.draw_label(&mut sheet, font, label, label_area)?;
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    // rasterises the label with the non-zero winding rule, sampling pixel
+    // centres; the text is shrunk to fit the area's width if needed
+    fn draw_label(
+        &self,
+        sheet: &mut Canvas,
+        font: &Font,
+        label: &str,
+        [left, top, width, height]: [usize; 4],
+    ) -> Result<(), ContactSheetError> {
+        let mut pen = 0.0;
+        let mut glyphs = vec![];
+        for character in label.chars() {
+            let glyph = font.glyph_index(character)?;
+            glyphs.push((pen, glyph));
+            pen += font.advance_width(glyph)? as Float;
+        }
+        if pen == 0.0 {
+            return Ok(());
+        }
+        let scale = (height as Float / font.line_height()).min(width as Float / pen);
+        let origin_x = left as Float + (width as Float - pen * scale) / 2.0;
+        let baseline = top as Float + height as Float * 0.8;
+
+        let mut edges = vec![];
+        for (offset, glyph) in glyphs {
+            for contour in font.glyph_outline(glyph, 4)? {
+                let points: Vec<[Float; 2]> = contour
+                    .iter()
+                    .map(|&[x, y]| [origin_x + (offset + x) * scale, baseline - y * scale])
+                    .collect();
+                for (idx, &start) in points.iter().enumerate() {
+                    edges.push([start, points[(idx + 1) % points.len()]]);
+                }
+            }
+        }
+
+        for row in top..top + height {
+            let centre_y = row as Float + 0.5;
+            let mut crossings: Vec<(Float, i32)> = edges
+                .iter()
+                .filter(|[[_, y0], [_, y1]]| (y0 <= &centre_y) != (y1 <= &centre_y))
+                .map(|&[[x0, y0], [x1, y1]]| {
+                    let x = x0 + (centre_y - y0) / (y1 - y0) * (x1 - x0);
+                    (x, if y1 > y0 { 1 } else { -1 })
+                })
+                .collect();
+            crossings.sort_by(|(x_a, _), (x_b, _)| x_a.total_cmp(x_b));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+                let first_column = (pair[0].0 - 0.5).ceil().max(left as Float) as usize;
+                let last_column = (pair[1].0 - 0.5).ceil().min((left + width) as Float) as usize;
+                for column in first_column..last_column {
+                    sheet.paint_colour_replace(column, row, self.label_colour)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // one cell per camera, e.g. different viewpoints or frames of a camera path
+    pub fn render_cameras<R: RayGenerator>(
+        &self,
+        world: &World,
+        cameras: Vec<(Camera<R>, String)>,
+    ) -> Result<Canvas, ContactSheetError> {
+        let mut cells = vec![];
+        for (camera, label) in cameras {
+            cells.push((camera.render(world)?, label));
+        }
+        self.assemble(&cells)
+    }
+
+    // one cell per version of the scene, all seen through the same camera
+    pub fn render_worlds<R: RayGenerator + Clone>(
+        &self,
+        camera: &Camera<R>,
+        worlds: &[(World, String)],
+    ) -> Result<Canvas, ContactSheetError> {
+        let mut cells = vec![];
+        for (world, label) in worlds {
+            cells.push((camera.clone().render(world)?, label.clone()));
+        }
+        self.assemble(&cells)
+    }
+
+    // one cell per material given to a single object (see World::primitives);
+    // visibility is only traced once, as for FirstHitBuffer::reshade
+    pub fn render_materials<R: RayGenerator>(
+        &self,
+        camera: Camera<R>,
+        world: &World,
+        object_id: usize,
+        materials: Vec<(Material, String)>,
+    ) -> Result<Canvas, ContactSheetError> {
+        let first_hits = camera.capture(world);
+        let mut cells = vec![];
+        for (material, label) in materials {
+            let overrides = MaterialOverrides::from([(object_id, material)]);
+            cells.push((first_hits.reshade(world, &overrides)?, label));
+        }
+        self.assemble(&cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::*;
+    use crate::objects::*;
+    use crate::utils::consts::FRAC_PI_2;
+    use crate::utils::{test_font, BuildInto, Buildable};
+
+    fn filled_canvas(width: usize, height: usize, colour: Colour) -> Canvas {
+        let mut canvas = Canvas::new(Width(width), Height(height));
+        for row in 0..height {
+            for column in 0..width {
+                canvas.paint_colour_replace(column, row, colour).unwrap();
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn assemble_grid_without_labels() {
+        let red = Colour::new(1.0, 0.0, 0.0);
+        let green = Colour::new(0.0, 1.0, 0.0);
+        let blue = Colour::new(0.0, 0.0, 1.0);
+        let grey = Colour::new(0.5, 0.5, 0.5);
+        let contact_sheet = ContactSheet {
+            columns: 2,
+            padding: 1,
+            background: grey,
+            ..ContactSheet::default()
+        };
+        let sheet = contact_sheet
+            .assemble(&[
+                (filled_canvas(3, 2, red), "red".to_string()),
+                (filled_canvas(2, 2, green), "green".to_string()),
+                (filled_canvas(3, 2, blue), "blue".to_string()),
+            ])
+            .unwrap();
+
+        assert_eq!((sheet.width(), sheet.height()), (9, 7));
+        assert_eq!(sheet[[0, 0]], Pixel::new(grey));
+        assert_eq!(sheet[[1, 1]], Pixel::new(red));
+        assert_eq!(sheet[[3, 2]], Pixel::new(red));
+        assert_eq!(sheet[[5, 1]], Pixel::new(green));
+        // cells are sized for the largest image
+        assert_eq!(sheet[[7, 1]], Pixel::new(grey));
+        assert_eq!(sheet[[1, 4]], Pixel::new(blue));
+        assert_eq!(sheet[[5, 4]], Pixel::new(grey));
+    }
+
+    #[test]
+    fn assemble_grid_with_labels() {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let black = Colour::new(0.0, 0.0, 0.0);
+        let contact_sheet = ContactSheet {
+            columns: 1,
+            padding: 0,
+            label_height: 20,
+            font: Some(test_font()),
+            ..ContactSheet::default()
+        };
+        let sheet = contact_sheet
+            .assemble(&[(filled_canvas(22, 2, black), "o".to_string())])
+            .unwrap();
+        assert_eq!((sheet.width(), sheet.height()), (22, 22));
+
+        // the 'o' is 1.1 em wide, so it is scaled to fit the 22 pixel wide
+        // label: its outer square spans pixels 0-20 across and its hole 5-15
+        // across and rows 3-13 (the baseline is at row 18)
+        let hole_row = 8;
+        assert_eq!(sheet[[0, hole_row]], Pixel::new(white));
+        assert_eq!(sheet[[4, hole_row]], Pixel::new(white));
+        assert_eq!(sheet[[10, hole_row]], Pixel::new(black));
+        assert_eq!(sheet[[16, hole_row]], Pixel::new(white));
+        assert_eq!(sheet[[21, hole_row]], Pixel::new(black));
+        assert_eq!(sheet[[10, 15]], Pixel::new(white));
+        assert_eq!(sheet[[10, 19]], Pixel::new(black));
+    }
+
+    #[test]
+    fn render_material_sweep() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            5,
+            5,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+        let materials = [0.0, 0.1, 0.2]
+            .into_iter()
+            .map(|ambient| {
+                let material = Material {
+                    ambient,
+                    ..Material::preset()
+                };
+                (material, format!("ambient {ambient}"))
+            })
+            .collect();
+
+        let contact_sheet = ContactSheet {
+            padding: 0,
+            ..ContactSheet::default()
+        };
+        let sheet = contact_sheet
+            .render_materials(camera.clone(), &world, 0, materials)
+            .unwrap();
+        assert_eq!((sheet.width(), sheet.height()), (15, 5));
+        let centre_red = |cell: usize| sheet[[cell * 5 + 2, 2]].red();
+        assert!(centre_red(0) < centre_red(1) && centre_red(1) < centre_red(2));
+
+        let world_sheet = contact_sheet
+            .render_worlds(&camera, &[(world.clone(), "preset".to_string())])
+            .unwrap();
+        assert_eq!(world_sheet, camera.render(&world).unwrap());
+    }
+}
@@ -0,0 +1,566 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::collections::Colour;
+use crate::scenes::*;
+use crate::utils::{widen, Float};
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Write(WriteError),
+    Io(io::Error),
+    // the other end sent something that is not part of the protocol
+    Protocol(&'static str),
+    // the worker's camera renders a canvas of a different size, so would
+    // render the wrong tiles
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributedError::Write(error) => write!(f, "could not paint canvas: {error:?}"),
+            DistributedError::Io(error) => write!(f, "connection failed: {error}"),
+            DistributedError::Protocol(problem) => write!(f, "unexpected message: {problem}"),
+            DistributedError::SizeMismatch { expected, found } => write!(
+                f,
+                "worker renders {}x{} pixels, but the frame is {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl Error for DistributedError {}
+
+impl From<WriteError> for DistributedError {
+    fn from(error: WriteError) -> DistributedError {
+        DistributedError::Write(error)
+    }
+}
+
+impl From<io::Error> for DistributedError {
+    fn from(error: io::Error) -> DistributedError {
+        DistributedError::Io(error)
+    }
+}
+
+// Messages are a 4-byte big-endian length, then that many bytes: a tag byte
+// followed by big-endian fields. Colours are sent as the exact bits of f64s, so
+// a distributed render is identical to one rendered in a single process.
+#[derive(Clone, Debug, PartialEq)]
+enum Message {
+    // worker to coordinator, on connecting: the size of its camera's canvas
+    Hello(usize, usize),
+    // coordinator to worker: render this region next
+    Tile(Region),
+    // worker to coordinator: a rendered region, its colours row by row
+    Pixels(Region, Vec<Colour>),
+    // coordinator to worker: every tile is done
+    Done,
+}
+
+impl Message {
+    // far more than any tile needs, but small enough that a corrupt length
+    // cannot exhaust memory
+    const MAX_LENGTH: usize = 1 << 28;
+
+    fn send(&self, stream: &mut TcpStream) -> Result<(), DistributedError> {
+        let mut body = vec![];
+        let mut put = |value: usize| body.extend_from_slice(&(value as u64).to_be_bytes());
+        match self {
+            Message::Hello(width, height) => {
+                put(*width);
+                put(*height);
+            }
+            Message::Tile(region) | Message::Pixels(region, _) => {
+                for value in [region.column, region.row, region.width, region.height] {
+                    put(value);
+                }
+            }
+            Message::Done => {}
+        }
+        if let Message::Pixels(_, colours) = self {
+            for colour in colours {
+                for value in [colour.red, colour.green, colour.blue] {
+                    body.extend_from_slice(&widen(value).to_bits().to_be_bytes());
+                }
+            }
+        }
+        let tag = match self {
+            Message::Hello(..) => b'H',
+            Message::Tile(_) => b'T',
+            Message::Pixels(..) => b'P',
+            Message::Done => b'D',
+        };
+
+        stream.write_all(&(body.len() as u32 + 1).to_be_bytes())?;
+        stream.write_all(&[tag])?;
+        stream.write_all(&body)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn receive(stream: &mut TcpStream) -> Result<Message, DistributedError> {
+        let mut length = [0; 4];
+        stream.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 || length > Message::MAX_LENGTH {
+            return Err(DistributedError::Protocol("message length out of range"));
+        }
+        let mut message = vec![0; length];
+        stream.read_exact(&mut message)?;
+
+        let (tag, body) = (message[0], &message[1..]);
+        let mut fields = body
+            .chunks_exact(8)
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()));
+        if body.len() % 8 != 0 {
+            return Err(DistributedError::Protocol("truncated field"));
+        }
+        let mut next = || {
+            fields
+                .next()
+                .ok_or(DistributedError::Protocol("missing field"))
+        };
+        let mut region = || -> Result<Region, DistributedError> {
+            let [column, row, width, height] = [next()?, next()?, next()?, next()?];
+            Ok(Region::new(
+                column as usize,
+                row as usize,
+                Width(width as usize),
+                Height(height as usize),
+            ))
+        };
+
+        match tag {
+            b'H' if body.len() == 16 => {
+                let size = (
+                    u64::from_be_bytes(body[..8].try_into().unwrap()),
+                    u64::from_be_bytes(body[8..].try_into().unwrap()),
+                );
+                Ok(Message::Hello(size.0 as usize, size.1 as usize))
+            }
+            b'T' if body.len() == 32 => Ok(Message::Tile(region()?)),
+            b'P' => {
+                let region = region()?;
+                let values: Vec<Float> = body[32..]
+                    .chunks_exact(8)
+                    .map(|chunk| {
+                        f64::from_bits(u64::from_be_bytes(chunk.try_into().unwrap())) as Float
+                    })
+                    .collect();
+                let expected = region
+                    .width
+                    .checked_mul(region.height)
+                    .and_then(|pixels| pixels.checked_mul(3));
+                if expected != Some(values.len()) {
+                    return Err(DistributedError::Protocol("wrong number of pixels"));
+                }
+                let colours = values
+                    .chunks_exact(3)
+                    .map(|rgb| Colour::new(rgb[0], rgb[1], rgb[2]))
+                    .collect();
+                Ok(Message::Pixels(region, colours))
+            }
+            b'D' if body.is_empty() => Ok(Message::Done),
+            _ => Err(DistributedError::Protocol("unknown message")),
+        }
+    }
+}
+
+// Spreads a frame across several machines: workers (see Worker) connect over
+// TCP, each with the same world and camera as the others, and are handed
+// tiles of the frame one at a time, so faster machines take more of them.
+// Tiles are keyed as in a single render (see Camera::with_seed), so the
+// result is the same as rendering the frame in one process. A tile whose
+// worker disconnects, stalls or sends back something else is handed to
+// another.
+pub struct Coordinator {
+    listener: TcpListener,
+    timeout: Duration,
+}
+
+impl Coordinator {
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+    pub const TIMEOUT: Duration = Duration::from_secs(60);
+
+    // e.g. "0.0.0.0:7878" to be reachable from other machines on the
+    // network, or port 0 for any free port (see address)
+    pub fn bind(address: &str) -> Result<Coordinator, DistributedError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Coordinator {
+            listener,
+            timeout: Coordinator::TIMEOUT,
+        })
+    }
+
+    // how long a worker may go without answering (including while it renders
+    // a tile) before it is dropped and its tile handed to another
+    pub fn with_timeout(self, timeout: Duration) -> Coordinator {
+        Coordinator { timeout, ..self }
+    }
+
+    pub fn address(&self) -> Result<SocketAddr, DistributedError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    // Waits for workers, for as long as it takes, and returns once they have
+    // rendered every tile of the frame; workers can join at any point.
+    // Workers that fail (even if their thread panics) only lose the tile they
+    // were working on.
+    pub fn render(
+        &self,
+        Width(width): Width,
+        Height(height): Height,
+        tile_size: usize,
+    ) -> Result<Canvas, DistributedError> {
+        let tiles = Region::tiles(Width(width), Height(height), tile_size);
+        let state = Mutex::new(Progress {
+            remaining: tiles.len(),
+            pending: tiles.into_iter().rev().collect(),
+            canvas: Canvas::new(Width(width), Height(height)),
+        });
+
+        thread::scope(|scope| {
+            let mut workers = vec![];
+            let result = loop {
+                if Progress::lock(&state).remaining == 0 {
+                    break Ok(());
+                }
+                match self.listener.accept() {
+                    Ok((stream, _)) => {
+                        let state = &state;
+                        let timeout = self.timeout;
+                        // a worker that fails only affects its own tile
+                        workers.push(scope.spawn(move || {
+                            Coordinator::direct(stream, (width, height), timeout, state)
+                        }));
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Coordinator::POLL_INTERVAL)
+                    }
+                    Err(error) => break Err(DistributedError::Io(error)),
+                }
+            };
+            // joined here so that a panic in one is not passed on; its tile
+            // has already been handed back
+            for worker in workers {
+                let _ = worker.join();
+            }
+            result
+        })?;
+        Ok(state
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .canvas)
+    }
+
+    // hands tiles to one worker until there are none left
+    fn direct(
+        mut stream: TcpStream,
+        size: (usize, usize),
+        timeout: Duration,
+        state: &Mutex<Progress>,
+    ) -> Result<(), DistributedError> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        match Message::receive(&mut stream)? {
+            Message::Hello(width, height) if (width, height) == size => {}
+            Message::Hello(width, height) => {
+                return Err(DistributedError::SizeMismatch {
+                    expected: size,
+                    found: (width, height),
+                })
+            }
+            _ => return Err(DistributedError::Protocol("expected a greeting")),
+        }
+
+        loop {
+            let next = {
+                let mut progress = Progress::lock(state);
+                if progress.remaining == 0 {
+                    break;
+                }
+                progress.pending.pop()
+            };
+            // the remaining tiles are all out with other workers, but may
+            // come back if they fail
+            let Some(region) = next else {
+                thread::sleep(Coordinator::POLL_INTERVAL);
+                continue;
+            };
+
+            let assignment = Assignment {
+                state,
+                region: Some(region),
+            };
+            let colours = Coordinator::render_tile(&mut stream, region)?;
+            assignment.finish(colours)?;
+        }
+        Message::Done.send(&mut stream)
+    }
+
+    fn render_tile(
+        stream: &mut TcpStream,
+        region: Region,
+    ) -> Result<Vec<Colour>, DistributedError> {
+        Message::Tile(region).send(stream)?;
+        match Message::receive(stream)? {
+            Message::Pixels(rendered, colours) if rendered == region => Ok(colours),
+            _ => Err(DistributedError::Protocol("expected the tile's pixels")),
+        }
+    }
+}
+
+struct Progress {
+    // tiles not yet handed out (or handed back), last first
+    pending: Vec<Region>,
+    // tiles not yet rendered, including those out with workers
+    remaining: usize,
+    canvas: Canvas,
+}
+
+impl Progress {
+    // a worker's thread that panicked leaves the progress as it was, so it
+    // carries on being used
+    fn lock(state: &Mutex<Progress>) -> MutexGuard<'_, Progress> {
+        state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// A tile out with a worker, handed back to be rendered again unless it is
+// finished, however the worker's thread stops.
+struct Assignment<'a> {
+    state: &'a Mutex<Progress>,
+    region: Option<Region>,
+}
+
+impl Assignment<'_> {
+    // the pixels have been checked to be the region's
+    fn finish(mut self, colours: Vec<Colour>) -> Result<(), DistributedError> {
+        let Some(region) = self.region else {
+            return Ok(());
+        };
+        let mut progress = Progress::lock(self.state);
+        for (idx, colour) in colours.into_iter().enumerate() {
+            let column = region.column + idx % region.width;
+            let row = region.row + idx / region.width;
+            progress.canvas.paint_colour_replace(column, row, colour)?;
+        }
+        progress.remaining -= 1;
+        self.region = None;
+        Ok(())
+    }
+}
+
+impl Drop for Assignment<'_> {
+    fn drop(&mut self) {
+        if let Some(region) = self.region.take() {
+            Progress::lock(self.state).pending.push(region);
+        }
+    }
+}
+
+// Renders tiles for a Coordinator; see Worker::serve.
+pub struct Worker {
+    stream: TcpStream,
+}
+
+impl Worker {
+    pub fn connect(address: &str) -> Result<Worker, DistributedError> {
+        Ok(Worker {
+            stream: TcpStream::connect(address)?,
+        })
+    }
+
+    // Renders whatever tiles the coordinator asks for with the camera, until
+    // it has all it needs, and returns how many this worker rendered. The
+    // world and camera (including its seed and frame) must be the same as
+    // every other worker's.
+    pub fn serve<R: RayGenerator>(
+        mut self,
+        camera: Camera<R>,
+        world: &World,
+    ) -> Result<usize, DistributedError> {
+        let (width, height) = camera.canvas_size();
+        Message::Hello(width, height).send(&mut self.stream)?;
+
+        let mut rendered = 0;
+        loop {
+            match Message::receive(&mut self.stream)? {
+                Message::Tile(region) => {
+                    // render_region checks that the region is within the
+                    // frame
+                    let canvas = camera.clone().render_region(world, region)?;
+                    let colours = (0..region.height)
+                        .flat_map(|row| (0..region.width).map(move |column| [column, row]))
+                        .map(|index| canvas[index].colour())
+                        .collect();
+                    Message::Pixels(region, colours).send(&mut self.stream)?;
+                    rendered += 1;
+                }
+                Message::Done => return Ok(rendered),
+                _ => return Err(DistributedError::Protocol("expected a tile")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::*;
+    use crate::utils::consts::FRAC_PI_3;
+
+    fn camera() -> Camera<Native> {
+        Camera::new(Native::new(
+            23,
+            17,
+            Angle::from_radians(FRAC_PI_3),
+            Orientation::new(
+                Point::new(0.0, 1.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ))
+        .with_seed(3)
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut receiver, _) = listener.accept().unwrap();
+        let region = Region::new(4, 8, Width(2), Height(1));
+        let messages = [
+            Message::Hello(640, 480),
+            Message::Tile(region),
+            Message::Pixels(
+                region,
+                vec![Colour::new(0.1, 2.5, -0.0), Colour::new(1e-9, 0.0, 7.0)],
+            ),
+            Message::Done,
+        ];
+        for message in &messages {
+            message.send(&mut sender).unwrap();
+        }
+        for message in messages {
+            assert_eq!(Message::receive(&mut receiver).unwrap(), message);
+        }
+
+        sender.write_all(&[0, 0, 0, 2, b'D', 0]).unwrap();
+        assert!(matches!(
+            Message::receive(&mut receiver),
+            Err(DistributedError::Protocol(_))
+        ));
+
+        // pixel counts that overflow are just wrong
+        let huge = Region::new(0, 0, Width(usize::MAX), Height(3));
+        Message::Pixels(huge, vec![]).send(&mut sender).unwrap();
+        assert!(matches!(
+            Message::receive(&mut receiver),
+            Err(DistributedError::Protocol("wrong number of pixels"))
+        ));
+    }
+
+    #[test]
+    fn workers_render_the_same_frame_as_one_process() {
+        let world = World::test_world();
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let address = coordinator.address().unwrap().to_string();
+
+        let (canvas, rendered) = thread::scope(|scope| {
+            let workers: Vec<_> = (0..2)
+                .map(|_| {
+                    let (address, world) = (&address, &world);
+                    scope.spawn(move || Worker::connect(address)?.serve(camera(), world))
+                })
+                .collect();
+            let canvas = coordinator.render(Width(23), Height(17), 5).unwrap();
+            let rendered: usize = workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap().unwrap())
+                .sum();
+            (canvas, rendered)
+        });
+        assert_eq!(rendered, Region::tiles(Width(23), Height(17), 5).len());
+        assert_eq!(canvas, camera().render(&world).unwrap());
+    }
+
+    #[test]
+    fn tiles_from_failed_workers_are_handed_to_others() {
+        let world = World::test_world();
+        let coordinator = Coordinator::bind("127.0.0.1:0")
+            .unwrap()
+            .with_timeout(Duration::from_millis(200));
+        let address = coordinator.address().unwrap().to_string();
+        let greet = || {
+            let mut stream = TcpStream::connect(&address).unwrap();
+            Message::Hello(23, 17).send(&mut stream).unwrap();
+            let Message::Tile(region) = Message::receive(&mut stream).unwrap() else {
+                panic!("expected a tile");
+            };
+            (stream, region)
+        };
+
+        let canvas = thread::scope(|scope| {
+            let canvas = scope.spawn(|| coordinator.render(Width(23), Height(17), 8));
+            // one sends back the pixels of another region
+            let (mut stream, region) = greet();
+            let elsewhere = Region { row: 1, ..region };
+            let colours = vec![Colour::new(1.0, 0.0, 0.0); region.width * region.height];
+            Message::Pixels(elsewhere, colours)
+                .send(&mut stream)
+                .unwrap();
+            // and one never answers; both lose their tile
+            let (_stalled, _) = greet();
+            Worker::connect(&address)
+                .unwrap()
+                .serve(camera(), &world)
+                .unwrap();
+            canvas.join().unwrap().unwrap()
+        });
+        assert_eq!(canvas, camera().render(&world).unwrap());
+    }
+
+    #[test]
+    fn workers_with_another_frame_size_are_turned_away() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let address = coordinator.address().unwrap().to_string();
+        thread::spawn(move || {
+            let small = Camera::new(Native::new(
+                4,
+                4,
+                Angle::from_radians(FRAC_PI_3),
+                Orientation::new(
+                    Point::new(0.0, 0.0, -5.0),
+                    Point::new(0.0, 0.0, 0.0),
+                    Vector::new(0.0, 1.0, 0.0),
+                ),
+            ));
+            let turned_away = Worker::connect(&address)
+                .unwrap()
+                .serve(small, &World::test_world());
+            tx.send(turned_away.is_err()).unwrap();
+            // then a worker that fits finishes the frame
+            Worker::connect(&address)
+                .unwrap()
+                .serve(camera(), &World::test_world())
+                .unwrap();
+        });
+        coordinator.render(Width(23), Height(17), 8).unwrap();
+        assert!(rx.recv().unwrap());
+    }
+}
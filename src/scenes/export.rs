@@ -0,0 +1,393 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::collections::Colour;
+use crate::scenes::*;
+use crate::utils::{Float, Random};
+
+#[derive(Debug)]
+pub enum ExportError {
+    Write(WriteError),
+    Io(io::Error),
+    UnknownFormat(String),
+    // an auxiliary pass was asked for without the first hits it comes from
+    MissingFirstHits,
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Write(error) => write!(f, "could not write canvas: {error}"),
+            ExportError::Io(error) => write!(f, "could not write export: {error}"),
+            ExportError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "cannot tell the image format of {path} from its extension"
+                )
+            }
+            ExportError::MissingFirstHits => {
+                write!(f, "auxiliary passes need a first-hit buffer")
+            }
+        }
+    }
+}
+
+impl Error for ExportError {}
+
+impl From<WriteError> for ExportError {
+    fn from(error: WriteError) -> ExportError {
+        ExportError::Write(error)
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> ExportError {
+        ExportError::Io(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Ppm,
+    Png,
+    // quality from 1 to 100 (see Canvas::write_to_jpeg); JPEG_QUALITY when
+    // going by the extension
+    Jpeg(u8),
+    Tga,
+    Bmp,
+    // floating point, so nothing is clamped
+    Exr,
+    // Radiance RGBE; unclamped like EXR, but without alpha, and each channel
+    // keeps only about 8 bits of precision
+    Hdr,
+}
+
+impl ExportFormat {
+    pub const JPEG_QUALITY: u8 = 90;
+
+    pub fn from_path(path: &str) -> Option<ExportFormat> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ExportFormat::Ppm),
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg(ExportFormat::JPEG_QUALITY)),
+            "tga" => Some(ExportFormat::Tga),
+            "bmp" => Some(ExportFormat::Bmp),
+            "exr" => Some(ExportFormat::Exr),
+            "hdr" => Some(ExportFormat::Hdr),
+            _ => None,
+        }
+    }
+
+    fn encode(self, canvas: &Canvas) -> Result<Vec<u8>, ExportError> {
+        Ok(match self {
+            ExportFormat::Ppm => canvas.write_to_ppm(PpmFormat::Ascii)?,
+            ExportFormat::Png => canvas.write_to_png(),
+            ExportFormat::Jpeg(quality) => canvas.write_to_jpeg(quality)?,
+            ExportFormat::Tga => canvas.write_to_tga()?,
+            ExportFormat::Bmp => canvas.write_to_bmp()?,
+            ExportFormat::Exr => canvas.write_to_exr(),
+            ExportFormat::Hdr => canvas.write_to_hdr(),
+        })
+    }
+}
+
+// What an export shows. Depth is the distance along each pixel's dominant
+// camera ray, written as is to EXR and HDR, but scaled so that the farthest
+// hit is white for the 8-bit formats. Normals are mapped from -1..1 to 0..1
+// per axis. Albedo is the unlit colour of the surface's material, as
+// denoisers expect. Object ids (see World::primitives) are written as is to EXR, but as a
+// distinct colour per object for the other formats, for picking out mattes by
+// eye. Pixels that see nothing are transparent in all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportPass {
+    Beauty,
+    Depth,
+    Normals,
+    Albedo,
+    ObjectId,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Export {
+    pub pass: ExportPass,
+    pub path: String,
+    // None to go by the path's extension
+    pub format: Option<ExportFormat>,
+}
+
+impl Export {
+    pub fn new(pass: ExportPass, path: &str) -> Export {
+        Export {
+            pass,
+            path: path.to_string(),
+            format: None,
+        }
+    }
+
+    pub fn format(&self) -> Result<ExportFormat, ExportError> {
+        self.format
+            .or_else(|| ExportFormat::from_path(&self.path))
+            .ok_or_else(|| ExportError::UnknownFormat(self.path.clone()))
+    }
+}
+
+// Several files written from a single render, e.g. an EXR of the beauty pass
+// for grading alongside a PNG of it for sharing and a PNG of its depth, so
+// that getting another file type never means rendering again.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportPlan {
+    pub exports: Vec<Export>,
+}
+
+impl ExportPlan {
+    pub fn new() -> ExportPlan {
+        ExportPlan::default()
+    }
+
+    pub fn add(mut self, pass: ExportPass, path: &str) -> ExportPlan {
+        self.exports.push(Export::new(pass, path));
+        self
+    }
+
+    // whether any export needs a first-hit buffer as well as the beauty pass
+    pub fn needs_first_hits(&self) -> bool {
+        self.exports
+            .iter()
+            .any(|export| export.pass != ExportPass::Beauty)
+    }
+
+    // every format is checked before anything is written, so a typo in one
+    // path does not leave the others half done
+    pub fn write(
+        &self,
+        world: &World,
+        beauty: &Canvas,
+        first_hits: Option<&FirstHitBuffer>,
+    ) -> Result<(), ExportError> {
+        let formats = self
+            .exports
+            .iter()
+            .map(Export::format)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (export, format) in self.exports.iter().zip(formats) {
+            let auxiliary;
+            let canvas = match export.pass {
+                ExportPass::Beauty => beauty,
+                pass => {
+                    let first_hits = first_hits.ok_or(ExportError::MissingFirstHits)?;
+                    auxiliary = ExportPlan::auxiliary(pass, format, world, first_hits)?;
+                    &auxiliary
+                }
+            };
+            fs::write(&export.path, format.encode(canvas)?)?;
+        }
+        Ok(())
+    }
+
+    fn auxiliary(
+        pass: ExportPass,
+        format: ExportFormat,
+        world: &World,
+        first_hits: &FirstHitBuffer,
+    ) -> Result<Canvas, WriteError> {
+        let (hsize, vsize) = first_hits.canvas_size();
+        let primitives = world.primitives();
+        let dominant_hits = first_hits.dominant_hits();
+        let depth_scale = match format {
+            ExportFormat::Exr | ExportFormat::Hdr => 1.0,
+            _ => {
+                let farthest = first_hits.hits().map(|hit| hit.t()).fold(0.0, Float::max);
+                if farthest > 0.0 {
+                    1.0 / farthest
+                } else {
+                    1.0
+                }
+            }
+        };
+
+        let mut canvas = Canvas::new_transparent(Width(hsize), Height(vsize));
+        for (idx, hit) in dominant_hits.into_iter().enumerate() {
+            let Some(hit) = hit else {
+                continue;
+            };
+            let colour = match pass {
+                ExportPass::Depth => {
+                    let depth = hit.t() * depth_scale;
+                    Colour::new(depth, depth, depth)
+                }
+                ExportPass::Albedo => primitives[hit.object_id()]
+                    .material()
+                    .pattern
+                    .colour_at(hit.computations().object_point()),
+                ExportPass::ObjectId => match format {
+                    ExportFormat::Exr => {
+                        let object_id = hit.object_id() as Float;
+                        Colour::new(object_id, object_id, object_id)
+                    }
+                    _ => {
+                        let hue = Random::keyed(0, hit.object_id() as u64).next_f64() * 360.0;
+                        Colour::from_hsv(hue, 0.8, 0.9)
+                    }
+                },
+                _ => {
+                    let normal = hit.computations().normal();
+                    Colour::new(normal.x, normal.y, normal.z) * 0.5 + Colour::new(0.5, 0.5, 0.5)
+                }
+            };
+            let (column, row) = (idx % hsize, idx / hsize);
+            canvas.paint_colour_additive(column, row, colour)?;
+            canvas.paint_coverage_additive(column, row, 1.0)?;
+        }
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::*;
+    use crate::objects::*;
+    use crate::utils::consts::FRAC_PI_6;
+    use crate::utils::{png, BuildInto, Buildable};
+
+    fn camera() -> Camera<Native> {
+        Camera::new(Native::new(
+            9,
+            9,
+            Angle::from_radians(FRAC_PI_6),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ))
+    }
+
+    fn world() -> World {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        World::new(vec![sphere], vec![Box::new(light)])
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("raytracer_export_test_{name}"));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(ExportFormat::from_path("out.PNG"), Some(ExportFormat::Png));
+        assert_eq!(ExportFormat::from_path("a/b.exr"), Some(ExportFormat::Exr));
+        assert_eq!(ExportFormat::from_path("a/b.HDR"), Some(ExportFormat::Hdr));
+        assert_eq!(
+            ExportFormat::from_path("a/b.jpg"),
+            Some(ExportFormat::Jpeg(ExportFormat::JPEG_QUALITY))
+        );
+        assert_eq!(ExportFormat::from_path("b.tga"), Some(ExportFormat::Tga));
+        assert_eq!(ExportFormat::from_path("render"), None);
+        let export = Export {
+            format: Some(ExportFormat::Ppm),
+            ..Export::new(ExportPass::Beauty, "render")
+        };
+        assert_eq!(export.format().unwrap(), ExportFormat::Ppm);
+    }
+
+    #[test]
+    fn write_several_formats_from_one_render() {
+        let [beauty_exr, beauty_png, depth_png, normals_ppm] =
+            ["beauty.exr", "beauty.png", "depth.png", "normals.ppm"].map(temp_path);
+        let plan = ExportPlan::new()
+            .add(ExportPass::Beauty, &beauty_exr)
+            .add(ExportPass::Beauty, &beauty_png)
+            .add(ExportPass::Depth, &depth_png)
+            .add(ExportPass::Normals, &normals_ppm);
+        let beauty = camera().render_with_exports(&world(), &plan).unwrap();
+        assert_eq!(beauty, camera().render(&world()).unwrap());
+
+        assert_eq!(fs::read(&beauty_png).unwrap(), beauty.write_to_png());
+        assert_eq!(fs::read(&beauty_exr).unwrap(), beauty.write_to_exr());
+
+        // the nearest point of the sphere is in the middle, and nothing is in
+        // the corners
+        let depth = png::decode(&fs::read(&depth_png).unwrap()).unwrap();
+        let [middle, ..] = depth.pixel(4, 4);
+        let [edge, ..] = depth.pixel(4, 2);
+        assert!(middle < edge);
+        assert_eq!(depth.pixel(0, 0)[3], 0);
+
+        assert!(fs::read_to_string(&normals_ppm)
+            .unwrap()
+            .starts_with("P3\n9 9"));
+
+        for path in [beauty_exr, beauty_png, depth_png, normals_ppm] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn albedo_and_object_id_passes() {
+        let [albedo_png, ids_png] = ["albedo.png", "ids.png"].map(temp_path);
+        let red = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(-1.0, 0.0, 0.0)))
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+                ..Material::preset()
+            })
+            .build_into();
+        let blue = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(1.0, 0.0, 0.0)))
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 1.0))),
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![red, blue], vec![Box::new(light)]);
+        let plan = ExportPlan::new()
+            .add(ExportPass::Albedo, &albedo_png)
+            .add(ExportPass::ObjectId, &ids_png);
+        camera().render_with_exports(&world, &plan).unwrap();
+
+        // unlit, so the same colour across each sphere
+        let albedo = png::decode(&fs::read(&albedo_png).unwrap()).unwrap();
+        assert_eq!(albedo.pixel(2, 4), [255, 0, 0, 255]);
+        assert_eq!(albedo.pixel(2, 3), [255, 0, 0, 255]);
+        assert_eq!(albedo.pixel(6, 4), [0, 0, 255, 255]);
+
+        let ids = png::decode(&fs::read(&ids_png).unwrap()).unwrap();
+        assert_eq!(ids.pixel(2, 4), ids.pixel(2, 3));
+        assert_ne!(ids.pixel(2, 4), ids.pixel(6, 4));
+        assert_eq!(ids.pixel(0, 0)[3], 0);
+
+        for path in [albedo_png, ids_png] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn unknown_format_writes_nothing() {
+        let path = temp_path("unwritten.png");
+        let plan = ExportPlan::new()
+            .add(ExportPass::Beauty, &path)
+            .add(ExportPass::Beauty, "render.tiff");
+        let beauty = camera().render(&world()).unwrap();
+        assert!(matches!(
+            plan.write(&world(), &beauty, None),
+            Err(ExportError::UnknownFormat(_))
+        ));
+        assert!(!Path::new(&path).exists());
+
+        let plan = ExportPlan::new().add(ExportPass::Depth, &path);
+        assert!(matches!(
+            plan.write(&world(), &beauty, None),
+            Err(ExportError::MissingFirstHits)
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, BufWriter, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+
+use crate::scenes::*;
+use crate::utils::Float;
+
+#[derive(Debug)]
+pub enum FfmpegError {
+    Io(io::Error),
+    // every frame must be the size the sink was spawned with
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    // ffmpeg exited unsuccessfully, with what it printed to stderr
+    Failed {
+        status: ExitStatus,
+        message: String,
+    },
+}
+
+impl Display for FfmpegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FfmpegError::Io(error) => write!(f, "could not stream to ffmpeg: {error}"),
+            FfmpegError::SizeMismatch { expected, found } => write!(
+                f,
+                "frame is {}x{}, but the video is {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            FfmpegError::Failed { status, message } => {
+                write!(f, "ffmpeg failed ({status}): {}", message.trim())
+            }
+        }
+    }
+}
+
+impl Error for FfmpegError {}
+
+impl From<io::Error> for FfmpegError {
+    fn from(error: io::Error) -> FfmpegError {
+        FfmpegError::Io(error)
+    }
+}
+
+// Streams the frames of an animation to an ffmpeg process as raw RGB over its
+// stdin, so that a video is encoded as the frames are rendered rather than
+// from thousands of intermediate images. ffmpeg picks the codec from the
+// output's extension (e.g. H.264 for .mp4, VP9 for .webm); frames are
+// converted to 4:2:0 chroma, which most players need, and padded to even
+// sizes for it.
+//
+// ffmpeg's stderr is read on a thread of its own as it runs, so it can't fill
+// its pipe and block while frames are still being written. A sink dropped
+// without finish closes ffmpeg's stdin and waits for it to exit.
+pub struct FfmpegSink {
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    stderr: Option<JoinHandle<io::Result<String>>>,
+    size: (usize, usize),
+    frames_written: usize,
+}
+
+impl FfmpegSink {
+    pub const PROGRAM: &'static str = "ffmpeg";
+
+    pub fn spawn(
+        output_path: &str,
+        width: Width,
+        height: Height,
+        frame_rate: Float,
+    ) -> Result<FfmpegSink, FfmpegError> {
+        FfmpegSink::spawn_with(
+            FfmpegSink::PROGRAM,
+            &[],
+            output_path,
+            width,
+            height,
+            frame_rate,
+        )
+    }
+
+    // with another ffmpeg binary, and extra options for the output, e.g.
+    // ["-crf", "18"] for higher quality H.264
+    pub fn spawn_with(
+        program: &str,
+        output_options: &[&str],
+        output_path: &str,
+        Width(width): Width,
+        Height(height): Height,
+        frame_rate: Float,
+    ) -> Result<FfmpegSink, FfmpegError> {
+        let mut child = Command::new(program)
+            .args(FfmpegSink::arguments(
+                output_options,
+                output_path,
+                (width, height),
+                frame_rate,
+            ))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+        let stderr = child.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut message = String::new();
+                stderr.read_to_string(&mut message).map(|_| message)
+            })
+        });
+        Ok(FfmpegSink {
+            child,
+            stdin,
+            stderr,
+            size: (width, height),
+            frames_written: 0,
+        })
+    }
+
+    fn arguments(
+        output_options: &[&str],
+        output_path: &str,
+        (width, height): (usize, usize),
+        frame_rate: Float,
+    ) -> Vec<String> {
+        let input = [
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pixel_format",
+            "rgb24",
+            "-video_size",
+            &format!("{width}x{height}"),
+            "-framerate",
+            &frame_rate.to_string(),
+            "-i",
+            "-",
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ]
+        .map(String::from);
+        input
+            .into_iter()
+            .chain(output_options.iter().map(|option| option.to_string()))
+            .chain([output_path.to_string()])
+            .collect()
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    pub fn write_frame(&mut self, canvas: &Canvas) -> Result<(), FfmpegError> {
+        let size = (canvas.width(), canvas.height());
+        if size != self.size {
+            return Err(FfmpegError::SizeMismatch {
+                expected: self.size,
+                found: size,
+            });
+        }
+        let Some(stdin) = &mut self.stdin else {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe).into());
+        };
+        if let Err(error) = stdin.write_all(&canvas.rgb_bytes()) {
+            // most likely ffmpeg has given up, and says why
+            self.stdin = None;
+            return Err(self.wait().err().unwrap_or(error.into()));
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    // closes ffmpeg's stdin, then waits for it to finish encoding
+    pub fn finish(mut self) -> Result<(), FfmpegError> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.flush()?;
+        }
+        self.wait()
+    }
+
+    // with stdin closed; the stderr thread ends once ffmpeg exits
+    fn wait(&mut self) -> Result<(), FfmpegError> {
+        let status = self.child.wait()?;
+        let message = match self.stderr.take().map(JoinHandle::join) {
+            Some(Ok(message)) => message?,
+            Some(Err(_)) | None => String::new(),
+        };
+        if !status.success() {
+            return Err(FfmpegError::Failed { status, message });
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FfmpegSink {
+    fn drop(&mut self) {
+        // a no-op after finish, since the child's status is kept once it has
+        // been waited on
+        self.stdin = None;
+        let _ = self.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_frames_in_and_options_before_the_output() {
+        let arguments = FfmpegSink::arguments(&["-crf", "18"], "out.mp4", (640, 360), 24.0);
+        let position = |argument: &str| arguments.iter().position(|other| other == argument);
+        assert_eq!(arguments[position("-video_size").unwrap() + 1], "640x360");
+        assert_eq!(arguments[position("-framerate").unwrap() + 1], "24");
+        assert_eq!(arguments[position("-i").unwrap() + 1], "-");
+        assert!(position("-crf") > position("-i"));
+        assert_eq!(arguments.last().unwrap(), "out.mp4");
+    }
+
+    #[test]
+    fn missing_program() {
+        let result = FfmpegSink::spawn_with(
+            "raytracer-no-such-ffmpeg",
+            &[],
+            "out.mp4",
+            Width(2),
+            Height(2),
+            24.0,
+        );
+        assert!(
+            matches!(result, Err(FfmpegError::Io(error)) if error.kind() == io::ErrorKind::NotFound)
+        );
+    }
+
+    // cat stands in for an ffmpeg that rejects its options
+    #[cfg(unix)]
+    #[test]
+    fn failure_reports_stderr() {
+        let sink =
+            FfmpegSink::spawn_with("cat", &[], "out.mp4", Width(2), Height(2), 24.0).unwrap();
+        match sink.finish() {
+            Err(FfmpegError::Failed { status, message }) => {
+                assert!(!status.success());
+                assert!(message.contains("cat"));
+            }
+            _ => panic!("expected cat to fail"),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn encode_video() {
+        let path = std::env::temp_dir().join("raytracer_ffmpeg_sink_test.mp4");
+        let mut sink =
+            FfmpegSink::spawn(path.to_str().unwrap(), Width(33), Height(17), 24.0).unwrap();
+        for _ in 0..12 {
+            sink.write_frame(&Canvas::new(Width(33), Height(17)))
+                .unwrap();
+        }
+        assert!(matches!(
+            sink.write_frame(&Canvas::new(Width(2), Height(2))),
+            Err(FfmpegError::SizeMismatch { .. })
+        ));
+        assert_eq!(sink.frames_written(), 12);
+        sink.finish().unwrap();
+        assert!(std::fs::metadata(path).unwrap().len() > 0);
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::objects::{Computations, Intersectable, Material, PrimitiveShape, Ray, Shape};
+use crate::scenes::*;
+use crate::utils::Float;
+
+// materials to use in place of the world's own, keyed by object id (see
+// World::primitives)
+pub type MaterialOverrides = HashMap<usize, Material>;
+
+// The visible surface seen by a single camera ray, with everything needed to
+// shade it again except the material.
+#[derive(Clone, Debug)]
+pub struct FirstHit {
+    object_id: usize,
+    t: Float,
+    uv_coordinates: Option<(Float, Float)>,
+    computations: Computations,
+    shadowed: Vec<Float>,
+}
+
+impl FirstHit {
+    pub fn object_id(&self) -> usize {
+        self.object_id
+    }
+
+    pub fn t(&self) -> Float {
+        self.t
+    }
+
+    pub fn uv_coordinates(&self) -> Option<(Float, Float)> {
+        self.uv_coordinates
+    }
+
+    pub fn computations(&self) -> &Computations {
+        &self.computations
+    }
+
+    // how shadowed each of the world's light samples is, in order, from 0
+    // (lit) to 1 (in full shadow)
+    pub fn shadowed(&self) -> &Vec<Float> {
+        &self.shadowed
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BufferedRay {
+    ray: Ray,
+    pixels: Vec<TaggedPixel>,
+    hit: Option<FirstHit>,
+}
+
+// Caches the first hit of every camera ray so that material-only edits can be
+// re-shaded without tracing visibility (or shadow rays) again. Reflections and
+// refractions are still traced from the cached hits, and only see the world's
+// own materials.
+//
+// The buffer is only valid while the camera and lights of the world it was
+// captured from are unchanged; after geometry changes, refresh it with the ids
+// of the objects that changed.
+#[derive(Clone, Debug)]
+pub struct FirstHitBuffer {
+    canvas_size: (usize, usize),
+    rays: Vec<BufferedRay>,
+}
+
+impl FirstHitBuffer {
+    pub fn capture<R: RayGenerator>(ray_generator: R, world: &World) -> FirstHitBuffer {
+        let canvas_size = ray_generator.canvas_size();
+        let object_ids = FirstHitBuffer::object_ids(world);
+
+        let rays = ray_generator
+            .into_iter()
+            .map(|tagged_ray| {
+                let ray = tagged_ray.ray();
+                BufferedRay {
+                    ray,
+                    pixels: tagged_ray.pixels().clone(),
+                    hit: FirstHitBuffer::trace(world, &ray, &object_ids),
+                }
+            })
+            .collect();
+
+        FirstHitBuffer { canvas_size, rays }
+    }
+
+    fn object_ids(world: &World) -> HashMap<*const (), usize> {
+        world
+            .primitives()
+            .into_iter()
+            .enumerate()
+            .map(|(object_id, primitive)| {
+                (
+                    primitive as *const dyn PrimitiveShape as *const (),
+                    object_id,
+                )
+            })
+            .collect()
+    }
+
+    fn trace(world: &World, ray: &Ray, object_ids: &HashMap<*const (), usize>) -> Option<FirstHit> {
+        world.intersect_ray(ray).finalise_hit().map(|hit| {
+            let object_id = object_ids[&(hit.object() as *const dyn PrimitiveShape as *const ())];
+            FirstHit {
+                object_id,
+                t: hit.t(),
+                uv_coordinates: hit.uv_coordinates(),
+                computations: *hit.computations(),
+                shadowed: world.shadowed_lights(hit.over_point(), hit.computations().time()),
+            }
+        })
+    }
+
+    // Brings the buffer up to date with a world in which only the given
+    // objects (see World::changed_objects) have changed, e.g. the next frame
+    // of an animation with a static camera. Rays are only traced again if they
+    // saw a changed object or a changed object now blocks them; the rest only
+    // re-test their shadows. Returns the number of rays traced again.
+    //
+    // Object ids must be the same in both worlds, i.e. no objects added or
+    // removed.
+    pub fn refresh(&mut self, world: &World, changed_objects: &HashSet<usize>) -> usize {
+        if changed_objects.is_empty() {
+            return 0;
+        }
+        let object_ids = FirstHitBuffer::object_ids(world);
+
+        // only whole top-level objects can be intersected on their own
+        let mut changed_shapes: Vec<&Shape> = vec![];
+        let mut first_id = 0;
+        for object in world.objects() {
+            let primitive_count = object.primitives().len();
+            if (first_id..first_id + primitive_count).any(|id| changed_objects.contains(&id)) {
+                changed_shapes.push(object);
+            }
+            first_id += primitive_count;
+        }
+        let blocked_within = |ray: &Ray, distance: Float| {
+            let ray = ray.with_range(ray.t_min, distance);
+            changed_shapes
+                .iter()
+                .any(|shape| shape.intersect_ray(&ray, vec![]).finalise_hit().is_some())
+        };
+        let shadowed_within = |ray: &Ray, distance: Float| {
+            let ray = ray.with_range(ray.t_min, distance);
+            changed_shapes.iter().any(|shape| {
+                shape
+                    .intersect_ray(&ray, vec![])
+                    .shadow_casters()
+                    .finalise_hit()
+                    .is_some()
+            })
+        };
+
+        let mut traced = 0;
+        for buffered_ray in &mut self.rays {
+            let needs_trace = match &buffered_ray.hit {
+                Some(hit) => {
+                    changed_objects.contains(&hit.object_id)
+                        || blocked_within(&buffered_ray.ray, hit.t)
+                }
+                None => blocked_within(&buffered_ray.ray, Float::INFINITY),
+            };
+            if needs_trace {
+                buffered_ray.hit = FirstHitBuffer::trace(world, &buffered_ray.ray, &object_ids);
+                traced += 1;
+                continue;
+            }
+
+            let Some(hit) = &mut buffered_ray.hit else {
+                continue;
+            };
+            let over_point = hit.computations.over_point();
+            let time = hit.computations.time();
+            let samples = world.light_samples(over_point);
+            if hit.shadowed.len() != samples.len() {
+                hit.shadowed = world.shadowed_lights(over_point, time);
+                continue;
+            }
+            for (sample, shadowed) in samples.iter().zip(hit.shadowed.iter_mut()) {
+                // the shadow caster is unknown, so it may have moved away
+                let ray = Ray::new(over_point, sample.direction).with_time(time);
+                if *shadowed > 0.0 || shadowed_within(&ray, sample.distance) {
+                    *shadowed = world.shadowing(sample, over_point, time);
+                }
+            }
+        }
+        traced
+    }
+
+    pub fn canvas_size(&self) -> (usize, usize) {
+        self.canvas_size
+    }
+
+    pub fn hits(&self) -> impl Iterator<Item = &FirstHit> {
+        self.rays
+            .iter()
+            .filter_map(|buffered_ray| buffered_ray.hit.as_ref())
+    }
+
+    // for every pixel (row-major), the hit of the ray contributing the most to it
+    pub(crate) fn dominant_hits(&self) -> Vec<Option<&FirstHit>> {
+        let (hsize, vsize) = self.canvas_size;
+        let mut dominant: Vec<(Float, Option<&FirstHit>)> = vec![(0.0, None); hsize * vsize];
+        for buffered_ray in &self.rays {
+            for tagged_pixel in &buffered_ray.pixels {
+                let [pos_x, pos_y] = tagged_pixel.index();
+                let entry = &mut dominant[pos_y * hsize + pos_x];
+                if tagged_pixel.blend_weight() > entry.0 {
+                    *entry = (tagged_pixel.blend_weight(), buffered_ray.hit.as_ref());
+                }
+            }
+        }
+        dominant.into_iter().map(|(_, hit)| hit).collect()
+    }
+
+    // the object contributing the most to a pixel, for picking what to edit
+    pub fn object_at(&self, pos_x: usize, pos_y: usize) -> Option<usize> {
+        self.rays
+            .iter()
+            .filter_map(|buffered_ray| {
+                let weight = buffered_ray
+                    .pixels
+                    .iter()
+                    .filter(|tagged_pixel| tagged_pixel.index() == [pos_x, pos_y])
+                    .map(|tagged_pixel| tagged_pixel.blend_weight())
+                    .sum::<Float>();
+                buffered_ray
+                    .hit
+                    .as_ref()
+                    .filter(|_| weight > 0.0)
+                    .map(|hit| (hit.object_id, weight))
+            })
+            .max_by(|(_, weight_a), (_, weight_b)| weight_a.total_cmp(weight_b))
+            .map(|(object_id, _)| object_id)
+    }
+
+    pub fn reshade(
+        &self,
+        world: &World,
+        overrides: &MaterialOverrides,
+    ) -> Result<Canvas, WriteError> {
+        let (hsize, vsize) = self.canvas_size;
+        let primitives = world.primitives();
+        let mut image = Canvas::new(Width(hsize), Height(vsize));
+
+        for buffered_ray in &self.rays {
+            let colour = match &buffered_ray.hit {
+                Some(hit) => {
+                    let material = overrides
+                        .get(&hit.object_id)
+                        .unwrap_or_else(|| primitives[hit.object_id].material());
+                    world.shade_hit(
+                        material,
+                        &hit.computations,
+                        &hit.shadowed,
+                        Some(hit.object_id),
+                        world.max_depth,
+                    )
+                }
+                None => world.background.colour_at(buffered_ray.ray.direction),
+            };
+
+            for tagged_pixel in &buffered_ray.pixels {
+                let [pos_x, pos_y] = tagged_pixel.index();
+                image.paint_colour_additive(pos_x, pos_y, colour * tagged_pixel.blend_weight())?;
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::*;
+    use crate::objects::*;
+    use crate::utils::consts::FRAC_PI_2;
+    use crate::utils::{BuildInto, Buildable};
+
+    fn two_sphere_world() -> World {
+        let s1 = Sphere::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.8, 1.0, 0.6))),
+                diffuse: 0.7,
+                specular: 0.2,
+                ..Material::preset()
+            })
+            .build_into();
+        let s2 = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        World::new(vec![s1, s2], vec![Box::new(light)])
+    }
+
+    fn camera() -> Camera<Native> {
+        Camera::new(Native::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ))
+    }
+
+    #[test]
+    fn reshade_without_overrides_matches_render() {
+        let world = two_sphere_world();
+        let first_hits = camera().capture(&world);
+        let image = camera().render(&world).unwrap();
+        assert_eq!(
+            first_hits
+                .reshade(&world, &MaterialOverrides::new())
+                .unwrap(),
+            image
+        );
+        assert_eq!(first_hits.object_at(5, 5), Some(0));
+        assert_eq!(first_hits.object_at(0, 0), None);
+    }
+
+    #[test]
+    fn reshade_with_material_override() {
+        let world = two_sphere_world();
+        let first_hits = camera().capture(&world);
+        let overrides = MaterialOverrides::from([(
+            0,
+            Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+                ambient: 1.0,
+                ..Material::default()
+            },
+        )]);
+        let image = first_hits.reshade(&world, &overrides).unwrap();
+        assert_eq!(image[[5, 5]], Pixel::new(Colour::new(1.0, 0.0, 0.0)));
+        assert_eq!(image[[0, 0]], Pixel::new(Colour::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn refresh_after_moving_an_object() {
+        let world = two_sphere_world();
+        let mut first_hits = camera().capture(&world);
+
+        let mut next_world = world.clone();
+        next_world.objects_mut()[1] = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(1.5, 0.0, -2.0)))
+            .set_material(Material::preset())
+            .build_into();
+        let changed_objects = next_world.changed_objects(&world);
+        assert_eq!(changed_objects, HashSet::from([1]));
+
+        let traced = first_hits.refresh(&next_world, &changed_objects);
+        assert!(traced > 0 && traced < 11 * 11);
+        assert_eq!(
+            first_hits
+                .reshade(&next_world, &MaterialOverrides::new())
+                .unwrap(),
+            camera().render(&next_world).unwrap()
+        );
+        assert_eq!(first_hits.refresh(&next_world, &HashSet::new()), 0);
+    }
+
+    #[test]
+    fn object_ids_are_depth_first() {
+        let world = two_sphere_world();
+        let primitives = world.primitives();
+        assert_eq!(primitives.len(), 2);
+        assert_eq!(world.object_id(primitives[1]), Some(1));
+    }
+}
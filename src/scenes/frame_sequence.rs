@@ -0,0 +1,201 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::time::Duration;
+
+use crate::scenes::*;
+use crate::utils::gif::GifWriter;
+
+#[derive(Debug)]
+pub enum FrameSequenceError {
+    Io(io::Error),
+    // every frame must be the size of the first
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    // GIF sizes are 16-bit
+    TooLarge {
+        width: usize,
+        height: usize,
+    },
+}
+
+impl Display for FrameSequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameSequenceError::Io(error) => write!(f, "could not write frame: {error}"),
+            FrameSequenceError::SizeMismatch { expected, found } => write!(
+                f,
+                "frame is {}x{}, but the sequence is {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            FrameSequenceError::TooLarge { width, height } => {
+                write!(f, "a {width}x{height} frame is too large for a GIF")
+            }
+        }
+    }
+}
+
+impl Error for FrameSequenceError {}
+
+impl From<io::Error> for FrameSequenceError {
+    fn from(error: io::Error) -> FrameSequenceError {
+        FrameSequenceError::Io(error)
+    }
+}
+
+// Writes the frames of an animation as they are rendered, as numbered PNGs
+// (the prefix followed by the frame number, four digits wide, e.g.
+// frames/walk_0007.png), as an animated GIF, or both. The GIF is written a
+// frame at a time, so no frame has to be kept once it has been written.
+#[derive(Default)]
+pub struct FrameSequence {
+    png_prefix: Option<String>,
+    gif_path: Option<String>,
+    gif: Option<GifWriter<BufWriter<File>>>,
+    // how long each frame of the GIF shows for, to the hundredth of a second
+    frame_delay: Duration,
+    size: Option<(usize, usize)>,
+    frames_written: usize,
+}
+
+impl FrameSequence {
+    pub fn new() -> FrameSequence {
+        FrameSequence::default()
+    }
+
+    pub fn with_png_frames(self, prefix: &str) -> FrameSequence {
+        FrameSequence {
+            png_prefix: Some(prefix.to_string()),
+            ..self
+        }
+    }
+
+    // the GIF's palette is a fixed 216 colours, so it suits previews more
+    // than finished animations
+    pub fn with_gif(self, path: &str, frame_delay: Duration) -> FrameSequence {
+        FrameSequence {
+            gif_path: Some(path.to_string()),
+            frame_delay,
+            ..self
+        }
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    pub fn png_path(&self, frame: usize) -> Option<String> {
+        self.png_prefix
+            .as_ref()
+            .map(|prefix| format!("{prefix}{frame:04}.png"))
+    }
+
+    pub fn write_frame(&mut self, canvas: &Canvas) -> Result<(), FrameSequenceError> {
+        let size = (canvas.width(), canvas.height());
+        match self.size {
+            Some(expected) if expected != size => {
+                return Err(FrameSequenceError::SizeMismatch {
+                    expected,
+                    found: size,
+                });
+            }
+            Some(_) => (),
+            None => {
+                // the GIF is started with the first frame, once its size is
+                // known
+                if let Some(path) = &self.gif_path {
+                    let (width, height) = size;
+                    if width > u16::MAX as usize || height > u16::MAX as usize {
+                        return Err(FrameSequenceError::TooLarge { width, height });
+                    }
+                    let file = BufWriter::new(File::create(path)?);
+                    self.gif = Some(GifWriter::new(file, width, height)?);
+                }
+                self.size = Some(size);
+            }
+        }
+
+        if let Some(path) = self.png_path(self.frames_written) {
+            fs::write(path, canvas.write_to_png())?;
+        }
+        if let Some(gif) = &mut self.gif {
+            let centiseconds = (self.frame_delay.as_millis() + 5) / 10;
+            gif.write_frame(
+                &canvas.rgb_bytes(),
+                centiseconds.min(u16::MAX as u128) as u16,
+            )?;
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    // ends the GIF; the PNGs are complete as each is written
+    pub fn finish(self) -> Result<(), FrameSequenceError> {
+        if let Some(gif) = self.gif {
+            gif.finish()?
+                .into_inner()
+                .map_err(|error| error.into_error())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Colour;
+    use crate::utils::{png, Float};
+
+    fn frame(brightness: Float) -> Canvas {
+        let mut canvas = Canvas::new(Width(3), Height(2));
+        canvas
+            .paint_colour_replace(1, 1, Colour::new(brightness, 0.0, 0.0))
+            .unwrap();
+        canvas
+    }
+
+    #[test]
+    fn write_png_frames_and_gif() {
+        let directory = std::env::temp_dir();
+        let prefix = directory.join("raytracer_frame_sequence_test_");
+        let gif_path = directory.join("raytracer_frame_sequence_test.gif");
+        let mut sequence = FrameSequence::new()
+            .with_png_frames(prefix.to_str().unwrap())
+            .with_gif(gif_path.to_str().unwrap(), Duration::from_millis(40));
+        for brightness in [0.25, 0.5, 1.0] {
+            sequence.write_frame(&frame(brightness)).unwrap();
+        }
+        assert_eq!(sequence.frames_written(), 3);
+        let last_png = sequence.png_path(2).unwrap();
+        assert!(last_png.ends_with("raytracer_frame_sequence_test_0002.png"));
+        sequence.finish().unwrap();
+
+        let image = png::decode(&fs::read(last_png).unwrap()).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixel(1, 1), [255, 0, 0, 255]);
+
+        let gif = fs::read(gif_path).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        // a graphic control extension per frame, each 4 hundredths long
+        let delays: Vec<&[u8]> = gif
+            .windows(6)
+            .filter(|window| window[..3] == [0x21, 0xf9, 4])
+            .map(|window| &window[4..6])
+            .collect();
+        assert_eq!(delays, [[4, 0]; 3]);
+    }
+
+    #[test]
+    fn frames_must_match_the_first() {
+        let mut sequence = FrameSequence::new();
+        sequence.write_frame(&frame(1.0)).unwrap();
+        let error = sequence
+            .write_frame(&Canvas::new(Width(2), Height(2)))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "frame is 2x2, but the sequence is 3x2");
+        assert_eq!(sequence.frames_written(), 1);
+    }
+}
@@ -0,0 +1,955 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::{mpsc, Arc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+use crate::collections::Point;
+use crate::objects::*;
+use crate::scenes::*;
+use crate::utils::{widen, Float};
+
+#[derive(Debug)]
+pub enum GpuError {
+    // no adapter, or none that can run compute shaders
+    NoAdapter(wgpu::RequestAdapterError),
+    Device(wgpu::RequestDeviceError),
+    // the world can't be intersected on the GPU without changing how it
+    // looks, e.g. as it has overlapping media with priorities
+    Unsupported(&'static str),
+    // a buffer needed is larger than the device allows
+    TooLarge { bytes: u64, limit: u64 },
+    Poll(wgpu::PollError),
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter(error) => write!(f, "no GPU adapter: {error}"),
+            GpuError::Device(error) => write!(f, "could not open the GPU: {error}"),
+            GpuError::Unsupported(reason) => {
+                write!(f, "the world can't be intersected on the GPU: {reason}")
+            }
+            GpuError::TooLarge { bytes, limit } => write!(
+                f,
+                "a GPU buffer of {bytes} bytes is larger than the {limit} allowed"
+            ),
+            GpuError::Poll(error) => write!(f, "could not wait for the GPU: {error}"),
+            GpuError::Readback(error) => write!(f, "could not read back from the GPU: {error}"),
+        }
+    }
+}
+
+impl Error for GpuError {}
+
+impl From<wgpu::RequestAdapterError> for GpuError {
+    fn from(error: wgpu::RequestAdapterError) -> GpuError {
+        GpuError::NoAdapter(error)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for GpuError {
+    fn from(error: wgpu::RequestDeviceError) -> GpuError {
+        GpuError::Device(error)
+    }
+}
+
+impl From<wgpu::PollError> for GpuError {
+    fn from(error: wgpu::PollError) -> GpuError {
+        GpuError::Poll(error)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for GpuError {
+    fn from(error: wgpu::BufferAsyncError) -> GpuError {
+        GpuError::Readback(error)
+    }
+}
+
+// A compute device that finds which primitive each camera ray hits first
+// (see Camera::with_gpu); shading, and every other ray, stays on the CPU.
+// Spheres and triangles, in groups and kd-trees, are flattened into world
+// space with a BVH over them and intersected in f32 by a compute shader. Any
+// top-level object with something else in it (planes, cubes, CSG, moving
+// objects and so on) is left for each ray to look at on the CPU, as are those
+// with single-sided surfaces, since whether they are seen depends on their
+// normals.
+//
+// The GPU only chooses the primitive; the CPU intersects it (and the
+// unchecked objects) again in full precision and shades the hit as usual, so
+// the image is the same as without the GPU except where f32 picks another
+// primitive than the CPU would, i.e. at the very edges of objects. Rays that
+// hit an object with anything transparent or custom shaded in it are cast as
+// usual, as their refraction depends on every surface they cross.
+//
+// The last world uploaded is kept, and a render of the same world (flattened
+// to the same primitives) uses it again rather than building and uploading
+// its BVH anew; the world is still flattened on each render to tell. Clones
+// share the device and the upload.
+#[derive(Clone, Debug)]
+pub struct Gpu {
+    context: Arc<GpuContext>,
+    uploaded: Arc<Mutex<Option<Arc<GpuScene>>>>,
+}
+
+#[derive(Debug)]
+struct GpuContext {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl PartialEq for Gpu {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.context, &other.context)
+    }
+}
+
+impl Gpu {
+    const SHADER: &'static str = include_str!("gpu.wgsl");
+    // as in the shader
+    const WORKGROUP_SIZE: u32 = 64;
+    // tiles whose rays are sent to the GPU together (see primary_hits_by_tile)
+    const BATCH_TILES: usize = 64;
+
+    // the system's default adapter, preferring a discrete GPU
+    pub fn new() -> Result<Gpu, GpuError> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                label: Some("raytracer"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            }))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("first hits"),
+            source: wgpu::ShaderSource::Wgsl(Gpu::SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("first hits"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Ok(Gpu {
+            context: Arc::new(GpuContext {
+                name: adapter.get_info().name,
+                device,
+                queue,
+                pipeline,
+            }),
+            uploaded: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    // the adapter's name, e.g. to tell a software renderer from a GPU
+    pub fn name(&self) -> &str {
+        &self.context.name
+    }
+
+    // For each ray, the index in world.objects of the object it hits first,
+    // or None if it hits nothing, among the objects the GPU can intersect;
+    // the others are ignored.
+    pub fn first_objects(
+        &self,
+        world: &World,
+        rays: &[Ray],
+    ) -> Result<Vec<Option<usize>>, GpuError> {
+        let scene = self.upload(world)?;
+        let hits = scene.hits(rays)?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| hit.map(|idx_path| scene.paths[idx_path][0]))
+            .collect())
+    }
+
+    // flattens the world for the render, uploading it unless it is the
+    // world last uploaded
+    pub(crate) fn upload(&self, world: &World) -> Result<Arc<GpuScene>, GpuError> {
+        let flattened = Flattened::new(world)?;
+        let mut uploaded = self
+            .uploaded
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        if let Some(scene) = uploaded.as_ref() {
+            if scene.flattened == flattened {
+                return Ok(Arc::clone(scene));
+            }
+        }
+
+        // the old world's buffers are let go before the new one's are made
+        *uploaded = None;
+        let (nodes, primitives) = flattened.bvh();
+        let device = &self.context.device;
+        let scene = Arc::new(GpuScene {
+            context: Arc::clone(&self.context),
+            nodes: storage_buffer(device, "nodes", &nodes)?,
+            primitives: storage_buffer(device, "primitives", &primitives)?,
+            paths: flattened
+                .primitives
+                .iter()
+                .map(|(_, path)| path.clone())
+                .collect(),
+            unchecked: flattened.unchecked.as_slice().into(),
+            flattened,
+        });
+        *uploaded = Some(Arc::clone(&scene));
+        Ok(scene)
+    }
+
+    // The tiles of a render, each with the primitives its rays hit first,
+    // found a batch of tiles at a time. The world is uploaded once, if at
+    // all (see upload); if it can't be, or a batch fails, those tiles are
+    // cast as usual.
+    pub(crate) fn primary_hits_by_tile<'a>(
+        &self,
+        world: &World,
+        tiles: impl Iterator<Item = Tile> + 'a,
+    ) -> impl Iterator<Item = (Tile, Option<PrimaryHits>)> + 'a {
+        let scene = world
+            .shades_first_hits()
+            .then(|| self.upload(world).ok())
+            .flatten();
+        let mut tiles = tiles.peekable();
+        std::iter::from_fn(move || {
+            tiles.peek()?;
+            let batch: Vec<Tile> = tiles.by_ref().take(Gpu::BATCH_TILES).collect();
+            let rays: Vec<Ray> = batch
+                .iter()
+                .flat_map(|tile| tile.iter().map(|&(_, ray, _)| ray))
+                .collect();
+            let Some(Ok(primary_hits)) = scene.as_ref().map(|scene| scene.primary_hits(&rays))
+            else {
+                return Some(batch.into_iter().map(|tile| (tile, None)).collect());
+            };
+
+            let mut hits = primary_hits.hits.into_iter();
+            let tiles: Vec<(Tile, Option<PrimaryHits>)> = batch
+                .into_iter()
+                .map(|tile| {
+                    let tile_hits = PrimaryHits {
+                        hits: hits.by_ref().take(tile.len()).collect(),
+                        paths: Arc::clone(&primary_hits.paths),
+                        unchecked: Arc::clone(&primary_hits.unchecked),
+                    };
+                    (tile, Some(tile_hits))
+                })
+                .collect();
+            Some(tiles)
+        })
+        .flatten()
+    }
+}
+
+// A world flattened and uploaded for finding first hits.
+#[derive(Debug)]
+pub(crate) struct GpuScene {
+    context: Arc<GpuContext>,
+    nodes: wgpu::Buffer,
+    primitives: wgpu::Buffer,
+    // by primitive, as the shader numbers them (see PrimaryHits)
+    paths: Arc<[Box<[usize]>]>,
+    unchecked: Arc<[usize]>,
+    // what was uploaded, to tell whether a world needs uploading again
+    flattened: Flattened,
+}
+
+impl GpuScene {
+    const MISSED: u32 = u32::MAX;
+
+    pub(crate) fn primary_hits(&self, rays: &[Ray]) -> Result<PrimaryHits, GpuError> {
+        let hits = self
+            .hits(rays)?
+            .into_iter()
+            .map(|hit| match hit {
+                None => PrimaryHit::Missed,
+                Some(idx_path) if self.flattened.cast_as_usual[self.paths[idx_path][0]] => {
+                    PrimaryHit::Any
+                }
+                Some(idx_path) => PrimaryHit::Primitive(idx_path),
+            })
+            .collect();
+        Ok(PrimaryHits {
+            hits,
+            paths: Arc::clone(&self.paths),
+            unchecked: Arc::clone(&self.unchecked),
+        })
+    }
+
+    // the primitive each ray hits first, if any
+    fn hits(&self, rays: &[Ray]) -> Result<Vec<Option<usize>>, GpuError> {
+        if self.paths.is_empty() {
+            return Ok(vec![None; rays.len()]);
+        }
+        // each dispatch is limited in how many workgroups it has
+        let max_rays = self
+            .context
+            .device
+            .limits()
+            .max_compute_workgroups_per_dimension as usize
+            * Gpu::WORKGROUP_SIZE as usize;
+        let mut hits = Vec::with_capacity(rays.len());
+        for chunk in rays.chunks(max_rays) {
+            hits.extend(
+                self.dispatch(chunk)?
+                    .into_iter()
+                    .map(|hit| (hit != GpuScene::MISSED).then_some(hit as usize)),
+            );
+        }
+        Ok(hits)
+    }
+
+    fn dispatch(&self, rays: &[Ray]) -> Result<Vec<u32>, GpuError> {
+        let GpuContext {
+            device,
+            queue,
+            pipeline,
+            ..
+        } = self.context.as_ref();
+        let ray_words: Vec<u32> = rays.iter().flat_map(ray_words).collect();
+        let ray_buffer = storage_buffer(device, "rays", &ray_words)?;
+        let size = (rays.len() * size_of::<u32>()) as u64;
+        let hit_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hits"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hits readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("first hits"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[&ray_buffer, &self.nodes, &self.primitives, &hit_buffer]
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((rays.len() as u32).div_ceil(Gpu::WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&hit_buffer, 0, &readback, 0, size);
+        queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+        let hits = readback
+            .get_mapped_range(..)
+            .map_err(|_| wgpu::BufferAsyncError)?
+            .chunks_exact(size_of::<u32>())
+            .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect();
+        Ok(hits)
+    }
+}
+
+// a buffer for the shader to read, if the device allows one that large
+fn storage_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    words: &[u32],
+) -> Result<wgpu::Buffer, GpuError> {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    let limit = device.limits().max_storage_buffer_binding_size;
+    if bytes.len() as u64 > limit {
+        return Err(GpuError::TooLarge {
+            bytes: bytes.len() as u64,
+            limit,
+        });
+    }
+    Ok(
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+        }),
+    )
+}
+
+fn to_f32(value: Float) -> f32 {
+    widen(value) as f32
+}
+
+// origin and t_min, direction and t_max, as in the shader; an unbounded
+// t_max is the largest f32, as shaders need not handle infinities
+fn ray_words(ray: &Ray) -> [u32; 8] {
+    let Ray {
+        origin,
+        direction,
+        t_min,
+        t_max,
+        ..
+    } = *ray;
+    [
+        origin.x,
+        origin.y,
+        origin.z,
+        t_min,
+        direction.x,
+        direction.y,
+        direction.z,
+        t_max,
+    ]
+    .map(|value| to_f32(value).min(f32::MAX).to_bits())
+}
+
+// a box's min and max, in f32 as the shader has them
+type Corners = ([f32; 3], [f32; 3]);
+
+#[derive(Clone, Debug, PartialEq)]
+enum GpuPrimitive {
+    // a unit sphere
+    Sphere { local_from_world: Transform },
+    // in world space
+    Triangle { vertices: [Point; 3] },
+}
+
+impl GpuPrimitive {
+    const SPHERE: u32 = 0;
+    const TRIANGLE: u32 = 1;
+
+    fn bounds(&self) -> Corners {
+        let (x_range, y_range, z_range) = match self {
+            GpuPrimitive::Sphere { local_from_world } => {
+                BoundingBox::from_axial_bounds([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0])
+                    .transform(&local_from_world.invert())
+            }
+            GpuPrimitive::Triangle { vertices } => BoundingBox::from_anchors(vertices.to_vec()),
+        }
+        .axial_bounds();
+        let ranges = [x_range, y_range, z_range];
+        (
+            ranges.map(|range| to_f32(range[0])),
+            ranges.map(|range| to_f32(range[1])),
+        )
+    }
+
+    // three vec4s, then the kind and the primitive's index in Flattened, as
+    // in the shader: a sphere's are the top rows of its transform, a
+    // triangle's its first vertex and edges
+    fn words(&self, idx_primitive: usize) -> [u32; 16] {
+        let rows = match self {
+            GpuPrimitive::Sphere { local_from_world } => {
+                let Transform(matrix) = local_from_world;
+                [0, 1, 2].map(|row| [0, 1, 2, 3].map(|col| matrix[[row, col]]))
+            }
+            GpuPrimitive::Triangle {
+                vertices: [p1, p2, p3],
+            } => {
+                let (e1, e2) = (*p2 - *p1, *p3 - *p1);
+                [
+                    [p1.x, p1.y, p1.z, 1.0],
+                    [e1.x, e1.y, e1.z, 0.0],
+                    [e2.x, e2.y, e2.z, 0.0],
+                ]
+            }
+        };
+        let kind = match self {
+            GpuPrimitive::Sphere { .. } => GpuPrimitive::SPHERE,
+            GpuPrimitive::Triangle { .. } => GpuPrimitive::TRIANGLE,
+        };
+        let mut words = [0; 16];
+        for (word, value) in words.iter_mut().zip(rows.into_iter().flatten()) {
+            *word = to_f32(value).to_bits();
+        }
+        words[12] = kind;
+        words[13] = idx_primitive as u32;
+        words
+    }
+}
+
+// The world's primitives in world space, each with its path (see
+// PrimaryHits), and the objects that are left to the CPU.
+#[derive(Debug, PartialEq)]
+struct Flattened {
+    primitives: Vec<(GpuPrimitive, Box<[usize]>)>,
+    cast_as_usual: Vec<bool>,
+    unchecked: Vec<usize>,
+}
+
+impl Flattened {
+    // objects in a BVH leaf, at most
+    const LEAF_SIZE: usize = 4;
+
+    fn new(world: &World) -> Result<Flattened, GpuError> {
+        // a hit may not count if it is inside a medium of higher priority,
+        // which depends on every surface crossed
+        let primitives = world.primitives();
+        if primitives
+            .iter()
+            .any(|primitive| primitive.material().medium_priority != 0)
+        {
+            return Err(GpuError::Unsupported("media have priorities"));
+        }
+
+        let mut flattened = Flattened {
+            primitives: vec![],
+            cast_as_usual: vec![],
+            unchecked: vec![],
+        };
+        for (idx_object, object) in world.objects().iter().enumerate() {
+            let flattened_len = flattened.primitives.len();
+            if !flattened.flatten(object, &Transform::default(), &mut vec![idx_object]) {
+                flattened.primitives.truncate(flattened_len);
+                flattened.unchecked.push(idx_object);
+            }
+            flattened
+                .cast_as_usual
+                .push(object.primitives().iter().any(|primitive| {
+                    let material = primitive.material();
+                    material.transparency > 0.0 || material.shader.is_some()
+                }));
+        }
+        Ok(flattened)
+    }
+
+    // false if the shape has anything the GPU can't intersect as the CPU
+    // would; world_from_frame takes the shape's containing frame to world
+    // space, and path leads to the shape
+    fn flatten(
+        &mut self,
+        shape: &Shape,
+        world_from_frame: &Transform,
+        path: &mut Vec<usize>,
+    ) -> bool {
+        match shape {
+            Shape::Primitive(primitive) => {
+                if primitive.material().sidedness == Sidedness::SingleSided {
+                    return false;
+                }
+                let world_from_local = primitive.frame_transformation().compose(world_from_frame);
+                let gpu_primitive = if primitive.downcast_ref::<Sphere>().is_some() {
+                    GpuPrimitive::Sphere {
+                        local_from_world: world_from_local.invert(),
+                    }
+                } else if let Some(triangle) = primitive.downcast_ref::<Triangle>() {
+                    GpuPrimitive::Triangle {
+                        vertices: triangle
+                            .vertices()
+                            .map(|vertex| vertex.transform(&world_from_local)),
+                    }
+                } else if let Some(triangle) = primitive.downcast_ref::<SmoothTriangle>() {
+                    GpuPrimitive::Triangle {
+                        vertices: triangle
+                            .vertices()
+                            .map(|vertex| vertex.transform(&world_from_local)),
+                    }
+                } else {
+                    return false;
+                };
+                self.primitives
+                    .push((gpu_primitive, path.as_slice().into()));
+                true
+            }
+            Shape::Group(group) => {
+                let world_from_group = group.frame_transformation().compose(world_from_frame);
+                self.flatten_all(group.objects(), &world_from_group, path)
+            }
+            Shape::KdTree(kd_tree) => self.flatten_all(kd_tree.objects(), world_from_frame, path),
+            Shape::Csg(_) | Shape::Lod(_) | Shape::Moving(_) => false,
+        }
+    }
+
+    fn flatten_all(
+        &mut self,
+        objects: &[Shape],
+        world_from_frame: &Transform,
+        path: &mut Vec<usize>,
+    ) -> bool {
+        objects.iter().enumerate().all(|(idx, object)| {
+            path.push(idx);
+            let flattened = self.flatten(object, world_from_frame, path);
+            path.pop();
+            flattened
+        })
+    }
+
+    // The BVH's nodes (depth first, each left child straight after its
+    // parent) and its primitives in leaf order, as words for the shader.
+    // Nodes are split at the median of their primitives' centres along
+    // their longest axis.
+    fn bvh(&self) -> (Vec<u32>, Vec<u32>) {
+        let mut leaves: Vec<(Corners, usize)> = self
+            .primitives
+            .iter()
+            .enumerate()
+            .map(|(idx, (primitive, _))| (primitive.bounds(), idx))
+            .collect();
+        let mut nodes = vec![];
+        if !leaves.is_empty() {
+            Flattened::build(&mut leaves, 0, &mut nodes);
+        }
+
+        // buffers can't be empty, so a world without primitives has
+        // placeholders, which the shader isn't run on (see GpuScene::hits)
+        let mut node_words: Vec<u32> = nodes.into_iter().flatten().collect();
+        node_words.resize(node_words.len().max(8), 0);
+        let mut primitive_words: Vec<u32> = leaves
+            .iter()
+            .flat_map(|&(_, idx)| self.primitives[idx].0.words(idx))
+            .collect();
+        primitive_words.resize(primitive_words.len().max(16), 0);
+        (node_words, primitive_words)
+    }
+
+    // the box's min, then the right child (or a leaf's first primitive), its
+    // max, then the number of primitives (0 for a branch)
+    fn build(leaves: &mut [(Corners, usize)], first: usize, nodes: &mut Vec<[u32; 8]>) {
+        let centre = |(min, max): &Corners, axis: usize| (min[axis] + max[axis]) / 2.0;
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        let mut centre_min = [f32::INFINITY; 3];
+        let mut centre_max = [f32::NEG_INFINITY; 3];
+        for (bounds, _) in leaves.iter() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(bounds.0[axis]);
+                max[axis] = max[axis].max(bounds.1[axis]);
+                centre_min[axis] = centre_min[axis].min(centre(bounds, axis));
+                centre_max[axis] = centre_max[axis].max(centre(bounds, axis));
+            }
+        }
+        let node = |index: usize, count: usize| {
+            [
+                min[0].to_bits(),
+                min[1].to_bits(),
+                min[2].to_bits(),
+                index as u32,
+                max[0].to_bits(),
+                max[1].to_bits(),
+                max[2].to_bits(),
+                count as u32,
+            ]
+        };
+
+        let axis = (0..3)
+            .max_by(|&a, &b| {
+                (centre_max[a] - centre_min[a]).total_cmp(&(centre_max[b] - centre_min[b]))
+            })
+            .unwrap();
+        // primitives with the same centre can't be split apart
+        if leaves.len() <= Flattened::LEAF_SIZE || centre_max[axis] <= centre_min[axis] {
+            nodes.push(node(first, leaves.len()));
+            return;
+        }
+
+        let idx_node = nodes.len();
+        nodes.push(node(0, 0));
+        let middle = leaves.len() / 2;
+        leaves.select_nth_unstable_by(middle, |(a, _), (b, _)| {
+            centre(a, axis).total_cmp(&centre(b, axis))
+        });
+        let (left, right) = leaves.split_at_mut(middle);
+        Flattened::build(left, first, nodes);
+        nodes[idx_node] = node(nodes.len(), 0);
+        Flattened::build(right, first + middle, nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::collections::{Angle, Colour, Vector};
+    use crate::scenes::conformance::ConformanceScene;
+    use crate::utils::objparser::ObjModel;
+    use crate::utils::{BuildInto, Buildable, RawImage};
+
+    fn sphere(transform: Transform, material: Material) -> Shape {
+        Sphere::builder()
+            .set_frame_transformation(transform)
+            .set_material(material)
+            .build_into()
+    }
+
+    fn triangle(material: Material) -> Shape {
+        Triangle::builder()
+            .set_vertices([
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ])
+            .set_material(material)
+            .build_into()
+    }
+
+    fn translate(x: Float, y: Float, z: Float) -> Transform {
+        Transform::new(TransformKind::Translate(x, y, z))
+    }
+
+    fn world(objects: Vec<Shape>) -> World {
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        World::new(objects, vec![Box::new(light)])
+    }
+
+    #[test]
+    fn flattens_what_the_gpu_can_intersect() {
+        let single_sided = Material {
+            sidedness: Sidedness::SingleSided,
+            ..Material::preset()
+        };
+        let world = world(vec![
+            sphere(Transform::default(), Material::preset()),
+            Group::builder()
+                .set_frame_transformation(translate(1.0, 0.0, 0.0))
+                .set_objects(vec![
+                    triangle(Material::preset()),
+                    KdTree::builder()
+                        .set_objects(vec![sphere(translate(0.0, 2.0, 0.0), Material::preset())])
+                        .build_into(),
+                ])
+                .build_into(),
+            Plane::builder().build_into(),
+            sphere(Transform::default(), Material::glass()),
+            Group::builder()
+                .set_objects(vec![
+                    sphere(Transform::default(), Material::preset()),
+                    triangle(single_sided),
+                ])
+                .build_into(),
+        ]);
+
+        let flattened = Flattened::new(&world).unwrap();
+        let paths: Vec<&[usize]> = flattened
+            .primitives
+            .iter()
+            .map(|(_, path)| path.as_ref())
+            .collect();
+        assert_eq!(paths, [&[0][..], &[1, 0], &[1, 1, 0], &[3]]);
+        assert_eq!(flattened.unchecked, [2, 4]);
+        assert_eq!(flattened.cast_as_usual, [false, false, false, true, false]);
+
+        // in world space, within the group
+        assert_eq!(
+            flattened.primitives[1].0,
+            GpuPrimitive::Triangle {
+                vertices: [
+                    Point::new(1.0, 1.0, 0.0),
+                    Point::new(0.0, 0.0, 0.0),
+                    Point::new(2.0, 0.0, 0.0),
+                ]
+            }
+        );
+        assert_eq!(
+            flattened.primitives[2].0,
+            GpuPrimitive::Sphere {
+                local_from_world: translate(-1.0, -2.0, 0.0)
+            }
+        );
+    }
+
+    #[test]
+    fn media_with_priorities_are_left_to_the_cpu() {
+        let world = world(vec![sphere(
+            Transform::default(),
+            Material {
+                medium_priority: 1,
+                ..Material::glass()
+            },
+        )]);
+        assert!(matches!(
+            Flattened::new(&world),
+            Err(GpuError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn bvh_holds_every_primitive_once_within_its_nodes() {
+        let spheres = (0..100)
+            .map(|idx| {
+                let [x, z] = [idx % 10, idx / 10].map(|pos| pos as Float * 1.5);
+                sphere(translate(x, (idx % 3) as Float, z), Material::preset())
+            })
+            .collect();
+        let flattened = Flattened::new(&world(spheres)).unwrap();
+        let (node_words, primitive_words) = flattened.bvh();
+        let nodes: Vec<&[u32]> = node_words.chunks(8).collect();
+        let primitives: Vec<&[u32]> = primitive_words.chunks(16).collect();
+        let bounds = |words: &[u32]| {
+            let floats = words.iter().map(|&word| f32::from_bits(word));
+            let floats: Vec<f32> = floats.collect();
+            (
+                [floats[0], floats[1], floats[2]],
+                [floats[4], floats[5], floats[6]],
+            )
+        };
+        let within = |(min, max): Corners, (outer_min, outer_max): Corners| {
+            (0..3).all(|axis| outer_min[axis] <= min[axis] && max[axis] <= outer_max[axis])
+        };
+
+        let mut ids = vec![];
+        let mut stack = vec![0];
+        while let Some(idx_node) = stack.pop() {
+            let node = nodes[idx_node];
+            let (index, count) = (node[3] as usize, node[7] as usize);
+            if count == 0 {
+                assert!(index > idx_node + 1);
+                for child in [idx_node + 1, index] {
+                    assert!(within(bounds(nodes[child]), bounds(node)));
+                    stack.push(child);
+                }
+                continue;
+            }
+            assert!(count <= Flattened::LEAF_SIZE);
+            for primitive in &primitives[index..index + count] {
+                let id = primitive[13] as usize;
+                assert!(within(flattened.primitives[id].0.bounds(), bounds(node)));
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+        assert_eq!(ids, (0..100).collect::<Vec<usize>>());
+    }
+
+    // the index in world.objects of the object each ray hits first
+    fn first_objects(world: &World, rays: &[Ray]) -> Vec<Option<usize>> {
+        let objects: HashMap<*const (), usize> = world
+            .objects()
+            .iter()
+            .enumerate()
+            .flat_map(|(idx_object, object)| {
+                let primitives = object.primitives().into_iter();
+                primitives.map(move |primitive| (primitive as *const _ as *const (), idx_object))
+            })
+            .collect();
+        rays.iter()
+            .map(|ray| {
+                let hit = world.intersect_ray(ray).finalise_hit()?;
+                Some(objects[&(hit.object() as *const _ as *const ())])
+            })
+            .collect()
+    }
+
+    // needs a GPU adapter, if only a software one
+    #[test]
+    #[ignore]
+    fn gpu_finds_first_objects_as_the_cpu_does() {
+        let gpu = Gpu::new().unwrap();
+        // a smooth mesh in a kd-tree, among a grid of spheres
+        let mesh = ObjModel::parse(
+            "v 1 1 1\nv -1 -1 1\nv -1 1 -1\nv 1 -1 -1\nf 1 2 3\nf 1 4 2\nf 1 3 4\nf 2 4 3\n",
+        )
+        .unwrap()
+        .mesh()
+        .set_subdivision_level(4)
+        .set_material(Material::preset())
+        .build_into();
+        let mut objects: Vec<Shape> = (0..100)
+            .map(|idx| {
+                let [x, z] = [idx % 10, idx / 10].map(|pos| pos as Float * 1.5 - 7.0);
+                let scale = Transform::new(TransformKind::Scale(0.5, 0.5, 0.5));
+                sphere(scale.compose(&translate(x, -2.0, z)), Material::preset())
+            })
+            .collect();
+        objects.push(KdTree::builder().set_objects(vec![mesh]).build_into());
+        objects.push(sphere(translate(0.0, 0.0, -3.0), Material::preset()));
+        let world = world(objects);
+        let orientation = Orientation::new(
+            Point::new(1.0, 3.0, -8.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let rays: Vec<Ray> = Native::new(160, 120, Angle::from_degrees(60.0), orientation)
+            .into_iter()
+            .map(|tagged_ray| tagged_ray.ray())
+            .collect();
+
+        let found = gpu.first_objects(&world, &rays).unwrap();
+        let expected = first_objects(&world, &rays);
+        assert!(expected.iter().flatten().count() > rays.len() / 4);
+        // only rays at the very edges of objects may differ, as the GPU
+        // intersects in f32
+        let differing = found.iter().zip(&expected).filter(|(a, b)| a != b).count();
+        assert!(differing * 200 <= rays.len(), "{differing} rays differ");
+    }
+
+    // needs a GPU adapter, if only a software one
+    #[test]
+    #[ignore]
+    fn renders_with_the_gpu_as_without() {
+        let gpu = Gpu::new().unwrap();
+        let (hsize, vsize) = ConformanceScene::GOLDEN_SIZE;
+        let decode = |canvas: Canvas| {
+            RawImage::decode(&canvas.write_to_ppm(PpmFormat::Ascii).unwrap()).unwrap()
+        };
+        for scene in ConformanceScene::ALL {
+            let world = scene.world();
+            let expected = decode(scene.camera(hsize, vsize).render(&world).unwrap());
+            let rendered = scene
+                .camera(hsize, vsize)
+                .with_gpu(gpu.clone())
+                .render(&world)
+                .unwrap();
+            let rendered = decode(rendered);
+            let differing = rendered
+                .rgba
+                .chunks(4)
+                .zip(expected.rgba.chunks(4))
+                .filter(|(rendered, expected)| {
+                    let channels = rendered.iter().zip(expected.iter());
+                    channels
+                        .map(|(&rendered, &expected)| rendered.abs_diff(expected))
+                        .any(|difference| difference > ConformanceScene::TOLERANCE)
+                })
+                .count();
+            assert!(
+                differing <= ConformanceScene::OUTLIERS + 4,
+                "{} pixels of {} differ",
+                differing,
+                scene.name()
+            );
+        }
+    }
+
+    // needs a GPU adapter, if only a software one
+    #[test]
+    #[ignore]
+    fn previews_render_with_the_gpu_and_reuse_its_upload() {
+        let gpu = Gpu::new().unwrap();
+        let world = ConformanceScene::ALL[0].world();
+        let first = gpu.upload(&world).unwrap();
+        assert!(Arc::ptr_eq(&first, &gpu.upload(&world).unwrap()));
+
+        let (hsize, vsize) = ConformanceScene::GOLDEN_SIZE;
+        let camera = ConformanceScene::ALL[0].camera(hsize, vsize);
+        let preview = Preview::default();
+        let previewed = camera
+            .clone()
+            .with_gpu(gpu.clone())
+            .render_with_preview(&world, &preview)
+            .unwrap();
+        let rendered = camera.with_gpu(gpu.clone()).render(&world).unwrap();
+        assert_eq!(previewed, rendered);
+        assert!(Arc::ptr_eq(&first, &gpu.upload(&world).unwrap()));
+
+        let mut moved = world.clone();
+        moved.objects_mut().swap(0, 1);
+        assert!(!Arc::ptr_eq(&first, &gpu.upload(&moved).unwrap()));
+    }
+}
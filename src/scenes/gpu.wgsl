@@ -0,0 +1,157 @@
+// Finds the primitive each ray hits first (see Gpu in gpu.rs), by walking a
+// BVH over spheres and triangles in world space.
+
+struct Ray {
+    origin: vec3<f32>,
+    t_min: f32,
+    direction: vec3<f32>,
+    t_max: f32,
+}
+
+// a branch's left child is the next node and its right child is at index; a
+// leaf's count primitives start at index
+struct Node {
+    min: vec3<f32>,
+    index: u32,
+    max: vec3<f32>,
+    count: u32,
+}
+
+// a sphere's rows take world space into the unit sphere's; a triangle's are
+// its first vertex and its two edges from it
+struct Primitive {
+    rows: array<vec4<f32>, 3>,
+    kind: u32,
+    id: u32,
+}
+
+const SPHERE: u32 = 0u;
+const MISSED: u32 = 0xffffffffu;
+// deep enough for a median split of any buffer a device allows
+const STACK_SIZE: u32 = 64u;
+
+@group(0) @binding(0) var<storage, read> rays: array<Ray>;
+@group(0) @binding(1) var<storage, read> nodes: array<Node>;
+@group(0) @binding(2) var<storage, read> primitives: array<Primitive>;
+@group(0) @binding(3) var<storage, read_write> hits: array<u32>;
+
+// whether the ray enters the box before t_max
+fn hits_box(node: Node, origin: vec3<f32>, inverse_direction: vec3<f32>, t_min: f32, t_max: f32) -> bool {
+    let t_lower = (node.min - origin) * inverse_direction;
+    let t_upper = (node.max - origin) * inverse_direction;
+    let t_near = min(t_lower, t_upper);
+    let t_far = max(t_lower, t_upper);
+    let t_enter = max(max(t_near.x, t_near.y), max(t_near.z, t_min));
+    let t_exit = min(min(t_far.x, t_far.y), min(t_far.z, t_max));
+    return t_enter <= t_exit;
+}
+
+// the nearest t in [t_min, t_max) at which the ray hits the primitive, or
+// t_max if there is none
+fn hit_primitive(primitive: Primitive, ray: Ray, t_max: f32) -> f32 {
+    if primitive.kind == SPHERE {
+        let origin = vec4<f32>(ray.origin, 1.0);
+        let direction = vec4<f32>(ray.direction, 0.0);
+        let local_origin = vec3<f32>(
+            dot(primitive.rows[0], origin),
+            dot(primitive.rows[1], origin),
+            dot(primitive.rows[2], origin),
+        );
+        let local_direction = vec3<f32>(
+            dot(primitive.rows[0], direction),
+            dot(primitive.rows[1], direction),
+            dot(primitive.rows[2], direction),
+        );
+        let a = dot(local_direction, local_direction);
+        let b = 2.0 * dot(local_origin, local_direction);
+        let c = dot(local_origin, local_origin) - 1.0;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return t_max;
+        }
+        let root = sqrt(discriminant);
+        let near = (-b - root) / (2.0 * a);
+        if near >= ray.t_min && near < t_max {
+            return near;
+        }
+        let far = (-b + root) / (2.0 * a);
+        if far >= ray.t_min && far < t_max {
+            return far;
+        }
+        return t_max;
+    }
+
+    // otherwise a triangle, by Möller–Trumbore
+    let p1 = primitive.rows[0].xyz;
+    let e1 = primitive.rows[1].xyz;
+    let e2 = primitive.rows[2].xyz;
+    let p = cross(ray.direction, e2);
+    let determinant = dot(e1, p);
+    if determinant == 0.0 {
+        return t_max;
+    }
+    let f = 1.0 / determinant;
+    let s = ray.origin - p1;
+    let u = f * dot(s, p);
+    if u < 0.0 || u > 1.0 {
+        return t_max;
+    }
+    let q = cross(s, e1);
+    let v = f * dot(ray.direction, q);
+    if v < 0.0 || u + v > 1.0 {
+        return t_max;
+    }
+    let t = f * dot(e2, q);
+    if t >= ray.t_min && t < t_max {
+        return t;
+    }
+    return t_max;
+}
+
+// 1 / x, kept finite so that a ray along an axis doesn't make NaNs
+fn safe_inverse(x: vec3<f32>) -> vec3<f32> {
+    let tiny = vec3<f32>(1e-30);
+    let magnitude = max(abs(x), tiny);
+    return select(vec3<f32>(1.0), vec3<f32>(-1.0), x < vec3<f32>(0.0)) / magnitude;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let idx_ray = id.x;
+    if idx_ray >= arrayLength(&rays) {
+        return;
+    }
+    let ray = rays[idx_ray];
+    let inverse_direction = safe_inverse(ray.direction);
+
+    var t_best = ray.t_max;
+    var hit = MISSED;
+    var stack: array<u32, STACK_SIZE>;
+    var stack_len = 1u;
+    stack[0] = 0u;
+    while stack_len > 0u {
+        stack_len -= 1u;
+        let idx_node = stack[stack_len];
+        let node = nodes[idx_node];
+        if !hits_box(node, ray.origin, inverse_direction, ray.t_min, t_best) {
+            continue;
+        }
+        if node.count == 0u {
+            if stack_len + 2u <= STACK_SIZE {
+                stack[stack_len] = node.index;
+                stack[stack_len + 1u] = idx_node + 1u;
+                stack_len += 2u;
+            }
+            continue;
+        }
+        for (var idx = node.index; idx < node.index + node.count; idx++) {
+            let primitive = primitives[idx];
+            let t = hit_primitive(primitive, ray, t_best);
+            if t < t_best {
+                t_best = t;
+                hit = primitive.id;
+            }
+        }
+    }
+    hits[idx_ray] = hit;
+}
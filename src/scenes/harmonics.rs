@@ -0,0 +1,227 @@
+use std::ops::{Add, Mul};
+
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{UvMapping, UvTexture};
+use crate::scenes::IrradianceVolume;
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// The first nine real spherical harmonics (bands 0 to 2), in the usual order
+// Y00, Y1-1, Y10, Y11, Y2-2, Y2-1, Y20, Y21, Y22, evaluated on world axes with
+// y up. Nine coefficients per channel are enough to reproduce diffuse
+// irradiance to within a few percent, which is what real-time engines expect
+// for light probes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SphericalHarmonics([Colour; 9]);
+
+impl SphericalHarmonics {
+    // per-band convolution with a clamped cosine lobe, already divided by pi
+    // so that irradiance reads like radiance (as AmbientCube::evaluate does)
+    const COSINE_LOBE: [Float; 3] = [1.0, 2.0 / 3.0, 1.0 / 4.0];
+
+    pub fn new(coefficients: [Colour; 9]) -> SphericalHarmonics {
+        SphericalHarmonics(coefficients)
+    }
+
+    pub fn black() -> SphericalHarmonics {
+        SphericalHarmonics([Colour::new(0.0, 0.0, 0.0); 9])
+    }
+
+    pub fn coefficients(&self) -> [Colour; 9] {
+        self.0
+    }
+
+    pub fn basis(direction: Vector) -> [Float; 9] {
+        let Vector { x, y, z } = direction.normalise();
+        [
+            0.282095,
+            0.488603 * y,
+            0.488603 * z,
+            0.488603 * x,
+            1.092548 * x * y,
+            1.092548 * y * z,
+            0.315392 * (3.0 * z * z - 1.0),
+            1.092548 * x * z,
+            0.546274 * (x * x - y * y),
+        ]
+    }
+
+    // projects radiance arriving from every direction, sampled at evenly
+    // distributed directions so that the result is deterministic
+    pub fn project<F>(radiance: F, samples: usize) -> SphericalHarmonics
+    where
+        F: Fn(Vector) -> Colour,
+    {
+        let directions = IrradianceVolume::sample_directions(samples);
+        let mut coefficients = [Colour::new(0.0, 0.0, 0.0); 9];
+        for &direction in &directions {
+            let colour = radiance(direction);
+            for (coefficient, basis) in coefficients
+                .iter_mut()
+                .zip(SphericalHarmonics::basis(direction))
+            {
+                *coefficient = *coefficient + colour * basis;
+            }
+        }
+
+        let solid_angle = 4.0 * PI / samples.max(1) as Float;
+        SphericalHarmonics(coefficients.map(|coefficient| coefficient * solid_angle))
+    }
+
+    // an environment map laid out like a spherical texture map, i.e. with u
+    // around the y axis and v from the bottom pole to the top
+    pub fn project_environment(environment: &dyn UvTexture, samples: usize) -> SphericalHarmonics {
+        SphericalHarmonics::project(
+            |direction| {
+                let (u, v) = UvMapping::spherical(Point::zero() + direction);
+                environment.uv_colour_at(u, v)
+            },
+            samples,
+        )
+    }
+
+    // the (band limited) radiance arriving from a direction
+    pub fn radiance(&self, direction: Vector) -> Colour {
+        self.0
+            .iter()
+            .zip(SphericalHarmonics::basis(direction))
+            .fold(Colour::new(0.0, 0.0, 0.0), |sum, (&coefficient, basis)| {
+                sum + coefficient * basis
+            })
+    }
+
+    // diffuse irradiance on a surface facing along the normal, divided by pi
+    pub fn irradiance(&self, normal: Vector) -> Colour {
+        self.0
+            .iter()
+            .zip(SphericalHarmonics::basis(normal))
+            .enumerate()
+            .fold(
+                Colour::new(0.0, 0.0, 0.0),
+                |sum, (idx, (&coefficient, basis))| {
+                    let band = match idx {
+                        0 => 0,
+                        1..=3 => 1,
+                        _ => 2,
+                    };
+                    sum + coefficient * (basis * SphericalHarmonics::COSINE_LOBE[band])
+                },
+            )
+    }
+}
+
+impl Add for SphericalHarmonics {
+    type Output = SphericalHarmonics;
+
+    fn add(self, rhs: SphericalHarmonics) -> SphericalHarmonics {
+        let mut coefficients = self.0;
+        for (coefficient, other) in coefficients.iter_mut().zip(rhs.0) {
+            *coefficient = *coefficient + other;
+        }
+        SphericalHarmonics(coefficients)
+    }
+}
+
+impl Mul<Float> for SphericalHarmonics {
+    type Output = SphericalHarmonics;
+
+    fn mul(self, rhs: Float) -> SphericalHarmonics {
+        SphericalHarmonics(self.0.map(|coefficient| coefficient * rhs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::ImageTexture;
+    use crate::scenes::{Canvas, Height, Width};
+
+    fn assert_close(colour: Colour, expected: Colour) {
+        for (channel, expected) in [
+            (colour.red, expected.red),
+            (colour.green, expected.green),
+            (colour.blue, expected.blue),
+        ] {
+            assert!(
+                (channel - expected).abs() < 1e-3,
+                "{colour:?} is not close to {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_environment() {
+        let grey = Colour::new(0.5, 0.25, 1.0);
+        let harmonics = SphericalHarmonics::project(|_| grey, 1000);
+        for direction in [
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(1.0, -2.0, 0.5),
+            Vector::new(0.0, 0.0, -1.0),
+        ] {
+            assert_close(harmonics.radiance(direction), grey);
+            assert_close(harmonics.irradiance(direction), grey);
+        }
+    }
+
+    #[test]
+    fn irradiance_under_a_bright_sky() {
+        // a hemisphere of light is exactly (1 + cos) / 2 after the cosine
+        // convolution, which the first two bands capture completely
+        let sky = |direction: Vector| {
+            if direction.y > 0.0 {
+                Colour::new(1.0, 1.0, 1.0)
+            } else {
+                Colour::new(0.0, 0.0, 0.0)
+            }
+        };
+        let harmonics = SphericalHarmonics::project(sky, 4000);
+        assert_close(
+            harmonics.irradiance(Vector::new(0.0, 1.0, 0.0)),
+            Colour::new(1.0, 1.0, 1.0),
+        );
+        assert_close(
+            harmonics.irradiance(Vector::new(0.0, -1.0, 0.0)),
+            Colour::new(0.0, 0.0, 0.0),
+        );
+        assert_close(
+            harmonics.irradiance(Vector::new(1.0, 0.0, 0.0)),
+            Colour::new(0.5, 0.5, 0.5),
+        );
+        let tilted = harmonics.irradiance(Vector::new(0.0, 1.0, 1.0));
+        assert!((tilted.red - (1.0 + Float::sqrt(0.5)) / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn project_environment_map() {
+        // top half of the map (v above 0.5) is red, bottom half blue
+        let mut canvas = Canvas::new(Width(8), Height(4));
+        for row in 0..4 {
+            let colour = if row < 2 {
+                Colour::new(1.0, 0.0, 0.0)
+            } else {
+                Colour::new(0.0, 0.0, 1.0)
+            };
+            for column in 0..8 {
+                canvas.paint_colour_replace(column, row, colour).unwrap();
+            }
+        }
+        let harmonics =
+            SphericalHarmonics::project_environment(&ImageTexture::from_canvas(&canvas), 4000);
+        let up = harmonics.irradiance(Vector::new(0.0, 1.0, 0.0));
+        let down = harmonics.irradiance(Vector::new(0.0, -1.0, 0.0));
+        assert!(up.red > 0.9 && up.blue < 0.1);
+        assert!(down.blue > 0.9 && down.red < 0.1);
+    }
+
+    #[test]
+    fn harmonics_combine_linearly() {
+        let red = SphericalHarmonics::project(|_| Colour::new(1.0, 0.0, 0.0), 100);
+        let blue = SphericalHarmonics::project(|_| Colour::new(0.0, 0.0, 1.0), 100);
+        let purple = (red + blue) * 0.5;
+        assert_close(
+            purple.irradiance(Vector::new(0.0, 0.0, 1.0)),
+            Colour::new(0.5, 0.0, 0.5),
+        );
+        assert_eq!(red + SphericalHarmonics::black(), red);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::collections::Colour;
+use crate::scenes::RenderStats;
+use crate::utils::Float;
+
+// What a heatmap (see Camera::render_heatmap) counts for each primary ray.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeatmapMetric {
+    // rays tested against primitives; high where many objects' bounds overlap
+    #[default]
+    IntersectionTests,
+    // nodes of kd-trees passed through; high where splits cut few objects away
+    KdTreeVisits,
+}
+
+impl HeatmapMetric {
+    pub(crate) fn of(self, stats: &RenderStats) -> u64 {
+        match self {
+            HeatmapMetric::IntersectionTests => stats.intersection_tests,
+            HeatmapMetric::KdTreeVisits => stats.kd_tree_visits,
+        }
+    }
+}
+
+// black through blue, red and yellow to white, as heat goes from 0 to 1
+pub(crate) fn heat_colour(heat: Float) -> Colour {
+    const RAMP: [(Float, Float, Float); 5] = [
+        (0.0, 0.0, 0.0),
+        (0.0, 0.0, 1.0),
+        (1.0, 0.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (1.0, 1.0, 1.0),
+    ];
+    let position = heat.clamp(0.0, 1.0) * (RAMP.len() - 1) as Float;
+    let idx = (position.floor() as usize).min(RAMP.len() - 2);
+    let fraction = position - idx as Float;
+    let [(r0, g0, b0), (r1, g1, b1)] = [RAMP[idx], RAMP[idx + 1]];
+    Colour::new(
+        r0 + (r1 - r0) * fraction,
+        g0 + (g1 - g0) * fraction,
+        b0 + (b1 - b0) * fraction,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_ramps_from_black_to_white() {
+        assert_eq!(heat_colour(0.0), Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(heat_colour(0.25), Colour::new(0.0, 0.0, 1.0));
+        assert_eq!(heat_colour(0.375), Colour::new(0.5, 0.0, 0.5));
+        assert_eq!(heat_colour(1.0), Colour::new(1.0, 1.0, 1.0));
+        assert_eq!(heat_colour(2.0), Colour::new(1.0, 1.0, 1.0));
+    }
+}
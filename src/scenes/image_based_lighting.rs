@@ -0,0 +1,315 @@
+use crate::collections::{Colour, Vector};
+use crate::objects::{DirectionalLight, Light, UvTexture};
+use crate::scenes::{AmbientLight, Background, SphericalHarmonics, World};
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// the latitude-longitude grid an environment is read at
+const COLUMNS: usize = 128;
+const ROWS: usize = 64;
+
+// radiance brighter than this multiple of the median is treated as a light
+// source (the sun, studio softboxes) rather than as fill; the median, unlike
+// the mean, is not raised by the light sources themselves
+const BRIGHT_THRESHOLD: Float = 4.0;
+
+// Lights a world with a high dynamic range environment map laid out like a
+// spherical texture map (e.g. an ImageTexture loaded from a Radiance .hdr).
+// Its bright parts are importance sampled into directional lights, so that
+// they cast shadows and highlights, and the rest becomes spherical harmonic
+// ambient light. The map itself becomes the background, which reflective and
+// refractive surfaces see as well; paths that bounce diffusely and then miss
+// see only the rest (see Background::fill_at), as the lights stand in for the
+// bright parts.
+#[derive(Clone, Debug)]
+pub struct ImageBasedLighting {
+    pub environment: Box<dyn UvTexture>,
+    pub lights: Vec<DirectionalLight>,
+    pub ambient: AmbientLight,
+    // the luminance above which the environment is given by the lights
+    pub threshold: Float,
+}
+
+impl ImageBasedLighting {
+    pub fn new(environment: Box<dyn UvTexture>, light_count: usize) -> ImageBasedLighting {
+        let cells = ImageBasedLighting::cells(environment.as_ref());
+        let threshold = ImageBasedLighting::median_luminance(&cells) * BRIGHT_THRESHOLD;
+
+        // splits each cell into the part bright enough for the lights and the
+        // part left for the ambient light
+        let (bright, fill): (Vec<Colour>, Vec<Colour>) = cells
+            .iter()
+            .map(|cell| {
+                let fill = fill(cell.radiance, threshold);
+                (cell.radiance - fill, fill)
+            })
+            .unzip();
+
+        let mut coefficients = [Colour::new(0.0, 0.0, 0.0); 9];
+        for (cell, &radiance) in cells.iter().zip(&fill) {
+            for (coefficient, basis) in coefficients
+                .iter_mut()
+                .zip(SphericalHarmonics::basis(cell.direction))
+            {
+                *coefficient = *coefficient + radiance * (basis * cell.solid_angle);
+            }
+        }
+
+        let weights: Vec<Float> = cells
+            .iter()
+            .zip(&bright)
+            .map(|(cell, &radiance)| luminance(radiance) * cell.solid_angle)
+            .collect();
+        let lights = ImageBasedLighting::sample_lights(&cells, &bright, &weights, light_count)
+            .into_iter()
+            .map(|(direction, intensity)| DirectionalLight::new(-direction, intensity))
+            .collect();
+
+        ImageBasedLighting {
+            environment,
+            lights,
+            ambient: AmbientLight::Harmonics(SphericalHarmonics::new(coefficients)),
+            threshold,
+        }
+    }
+
+    // adds the lights to any already in the world, and replaces its ambient
+    // light and background
+    pub fn apply(self, world: &mut World) {
+        world.lights.extend(
+            self.lights
+                .into_iter()
+                .map(|light| Box::new(light) as Box<dyn Light>),
+        );
+        world.ambient_light = Some(self.ambient);
+        world.background = Background::ImageBased {
+            environment: self.environment,
+            threshold: self.threshold,
+        };
+    }
+
+    fn cells(environment: &dyn UvTexture) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(COLUMNS * ROWS);
+        for row in 0..ROWS {
+            let v = (row as Float + 0.5) / ROWS as Float;
+            // v runs from the bottom pole to the top
+            let polar = (1.0 - v) * PI;
+            let solid_angle = (2.0 * PI / COLUMNS as Float) * (PI / ROWS as Float) * polar.sin();
+            for column in 0..COLUMNS {
+                let u = (column as Float + 0.5) / COLUMNS as Float;
+                // the inverse of UvMapping::spherical
+                let azimuth = 2.0 * PI * (0.5 - u);
+                let direction = Vector::new(
+                    polar.sin() * azimuth.sin(),
+                    polar.cos(),
+                    polar.sin() * azimuth.cos(),
+                );
+                cells.push(Cell {
+                    direction,
+                    solid_angle,
+                    radiance: environment.uv_colour_at(u, v),
+                });
+            }
+        }
+        cells
+    }
+
+    // over the sphere, i.e. weighted by solid angle
+    fn median_luminance(cells: &[Cell]) -> Float {
+        let mut luminances: Vec<(Float, Float)> = cells
+            .iter()
+            .map(|cell| (luminance(cell.radiance), cell.solid_angle))
+            .collect();
+        luminances.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        let half = luminances
+            .iter()
+            .map(|&(_, solid_angle)| solid_angle)
+            .sum::<Float>()
+            / 2.0;
+        let mut cumulative = 0.0;
+        for (luminance, solid_angle) in luminances {
+            cumulative += solid_angle;
+            if cumulative >= half {
+                return luminance;
+            }
+        }
+        0.0
+    }
+
+    // picks cells in proportion to their weights at evenly spaced quantiles,
+    // so that the result is deterministic, and weights each light to make the
+    // sum an unbiased estimate of the bright part's irradiance (divided by pi,
+    // as Phong's diffuse term expects); cells picked more than once give a
+    // single, brighter light
+    fn sample_lights(
+        cells: &[Cell],
+        radiance: &[Colour],
+        weights: &[Float],
+        light_count: usize,
+    ) -> Vec<(Vector, Colour)> {
+        let total: Float = weights.iter().sum();
+        if total <= 0.0 || light_count == 0 {
+            return vec![];
+        }
+
+        let mut lights: Vec<(usize, Colour)> = vec![];
+        let mut idx_cell = 0;
+        let mut cumulative = weights[0];
+        for sample in 0..light_count {
+            let quantile = (sample as Float + 0.5) / light_count as Float * total;
+            while cumulative < quantile && idx_cell + 1 < weights.len() {
+                idx_cell += 1;
+                cumulative += weights[idx_cell];
+            }
+            let probability = weights[idx_cell] / total;
+            let intensity = radiance[idx_cell]
+                * (cells[idx_cell].solid_angle / (probability * light_count as Float * PI));
+            match lights.last_mut() {
+                Some((idx_last, sum)) if *idx_last == idx_cell => *sum = *sum + intensity,
+                _ => lights.push((idx_cell, intensity)),
+            }
+        }
+
+        lights
+            .into_iter()
+            .map(|(idx_cell, intensity)| (cells[idx_cell].direction, intensity))
+            .collect()
+    }
+}
+
+struct Cell {
+    direction: Vector,
+    solid_angle: Float,
+    radiance: Colour,
+}
+
+// the part of the radiance left for the ambient light, dimmed (keeping its
+// hue) to the threshold if brighter; the rest is made into lights
+pub(crate) fn fill(radiance: Colour, threshold: Float) -> Colour {
+    let radiance_luminance = luminance(radiance);
+    if radiance_luminance > threshold && radiance_luminance > 0.0 {
+        radiance * (threshold / radiance_luminance)
+    } else {
+        radiance
+    }
+}
+
+fn luminance(colour: Colour) -> Float {
+    0.2126 * colour.red + 0.7152 * colour.green + 0.0722 * colour.blue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Point;
+    use crate::objects::{ImageTexture, Material, Plane, PointLight, Ray, UvChecker};
+    use crate::scenes::{Integrator, PathTracing};
+    use crate::utils::approx_eq;
+    use crate::utils::hdr::encode;
+    use crate::utils::{BuildInto, Buildable};
+
+    // a dim grey sky with a small, very bright patch straight up
+    fn sky_with_sun() -> Box<dyn UvTexture> {
+        let (width, height) = (16, 8);
+        let mut rgb = vec![];
+        for row in 0..height {
+            for _ in 0..width {
+                rgb.extend(if row == 0 {
+                    [500.0, 400.0, 300.0]
+                } else {
+                    [0.25, 0.25, 0.25]
+                });
+            }
+        }
+        Box::new(ImageTexture::from_bytes(&encode(width, height, &rgb)).unwrap())
+    }
+
+    #[test]
+    fn bright_patches_become_lights() {
+        let lighting = ImageBasedLighting::new(sky_with_sun(), 8);
+        assert!(!lighting.lights.is_empty() && lighting.lights.len() <= 8);
+        for light in &lighting.lights {
+            // shining down from the sun
+            assert!(
+                light.direction.y < -0.8,
+                "light along {:?}",
+                light.direction
+            );
+            approx_eq!(light.direction.magnitude(), 1.0);
+            assert!(light.intensity.red > light.intensity.blue);
+        }
+
+        // the fill is dim, and brighter from above than from below
+        let AmbientLight::Harmonics(harmonics) = lighting.ambient else {
+            panic!("expected spherical harmonics");
+        };
+        let up = harmonics.irradiance(Vector::new(0.0, 1.0, 0.0));
+        let down = harmonics.irradiance(Vector::new(0.0, -1.0, 0.0));
+        assert!(up.red > down.red && up.red < 1.0);
+    }
+
+    #[test]
+    fn uniform_environment_is_all_ambient() {
+        let grey = Colour::new(0.5, 0.5, 0.5);
+        let lighting = ImageBasedLighting::new(Box::new(UvChecker::new(1, 1, grey, grey)), 4);
+        assert!(lighting.lights.is_empty());
+        let irradiance = lighting.ambient.irradiance(Vector::new(0.3, 0.4, -0.5));
+        assert!((irradiance.green - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn apply_sets_background() {
+        let mut world = World::default();
+        world.lights.push(Box::new(PointLight::new(
+            Point::zero(),
+            Colour::new(1.0, 1.0, 1.0),
+        )));
+        let lighting = ImageBasedLighting::new(sky_with_sun(), 2);
+        let count = lighting.lights.len();
+        lighting.apply(&mut world);
+        assert_eq!(world.lights.len(), count + 1);
+        assert!(world.ambient_light.is_some());
+        assert_eq!(
+            world.background.colour_at(Vector::new(0.0, -1.0, 0.0)),
+            Colour::new(0.25, 0.25, 0.25)
+        );
+
+        // the sun is left out of the fill, which is all the rest
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let sun = world.background.colour_at(up);
+        let fill = world.background.fill_at(up);
+        assert!(fill.red < sun.red / 100.0);
+        approx_eq!(fill.red / fill.blue, sun.red / sun.blue);
+        assert_eq!(
+            world.background.fill_at(Vector::new(0.0, -1.0, 0.0)),
+            Colour::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn traced_paths_see_the_lights_once() {
+        let floor = Plane::builder()
+            .set_material(Material {
+                ambient: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let mut world = World::new(vec![floor], vec![]);
+        world.integrator = Integrator::PathTracing(PathTracing::new(64));
+        ImageBasedLighting::new(sky_with_sun(), 8).apply(&mut world);
+        let down = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let lit = world.cast_ray(down);
+
+        // without the lights, the floor is lit by the dim rest of the sky
+        // alone, as paths bouncing off it no longer see the sun
+        world.lights.clear();
+        let unlit = world.cast_ray(down);
+        assert!(unlit.red < 1.0, "{:?}", unlit);
+        assert!(lit.red > unlit.red + 1.0, "{:?}", lit);
+
+        // but the camera still does
+        let up = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert!(world.cast_ray(up).red > 100.0);
+    }
+}
@@ -0,0 +1,316 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::collections::{Colour, Point, Vector};
+use crate::objects::{BoundingBox, Ray};
+use crate::scenes::World;
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// An ambient cube stores the average incoming radiance arriving from around
+// each of the six axial directions, in the order +x, -x, +y, -y, +z, -z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AmbientCube([Colour; 6]);
+
+impl AmbientCube {
+    const AXES: [Vector; 6] = [
+        Vector::new(1.0, 0.0, 0.0),
+        Vector::new(-1.0, 0.0, 0.0),
+        Vector::new(0.0, 1.0, 0.0),
+        Vector::new(0.0, -1.0, 0.0),
+        Vector::new(0.0, 0.0, 1.0),
+        Vector::new(0.0, 0.0, -1.0),
+    ];
+
+    pub fn new(faces: [Colour; 6]) -> AmbientCube {
+        AmbientCube(faces)
+    }
+
+    pub fn black() -> AmbientCube {
+        AmbientCube([Colour::new(0.0, 0.0, 0.0); 6])
+    }
+
+    pub fn faces(&self) -> [Colour; 6] {
+        self.0
+    }
+
+    fn bake(world: &World, position: Point, directions: &[Vector]) -> AmbientCube {
+        let mut faces = [Colour::new(0.0, 0.0, 0.0); 6];
+        let mut weights = [0.0; 6];
+
+        for &direction in directions {
+            let radiance = world.cast_ray(Ray::new(position, direction));
+            for (idx, axis) in AmbientCube::AXES.iter().enumerate() {
+                let weight = direction.dot(*axis);
+                if weight > 0.0 {
+                    faces[idx] = faces[idx] + radiance * weight;
+                    weights[idx] += weight;
+                }
+            }
+        }
+
+        for (face, weight) in faces.iter_mut().zip(weights) {
+            if weight > 0.0 {
+                *face = *face * (1.0 / weight);
+            }
+        }
+
+        AmbientCube(faces)
+    }
+
+    // standard ambient cube lookup: each face contributes by the square of the
+    // matching normal component, picking the face on the side the normal faces
+    pub fn evaluate(&self, normal: Vector) -> Colour {
+        let [px, nx, py, ny, pz, nz] = self.0;
+        let x = if normal.x >= 0.0 { px } else { nx };
+        let y = if normal.y >= 0.0 { py } else { ny };
+        let z = if normal.z >= 0.0 { pz } else { nz };
+
+        x * normal.x.powi(2) + y * normal.y.powi(2) + z * normal.z.powi(2)
+    }
+
+    fn blend(&self, other: &AmbientCube, weight: Float) -> AmbientCube {
+        let mut faces = self.0;
+        for (face, other_face) in faces.iter_mut().zip(other.0) {
+            *face = *face * (1.0 - weight) + other_face * weight;
+        }
+        AmbientCube(faces)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IrradianceError {
+    // the box has an infinite (or NaN) bound on some axis, so its probes
+    // could not be placed
+    UnboundedBox,
+    // the resolution asks for no probes along some axis
+    NoProbes([usize; 3]),
+}
+
+impl Display for IrradianceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IrradianceError::UnboundedBox => write!(
+                f,
+                "irradiance volumes can only be baked over a finite bounding box"
+            ),
+            IrradianceError::NoProbes(resolution) => write!(
+                f,
+                "irradiance volumes need at least one probe along each axis, not {:?}",
+                resolution
+            ),
+        }
+    }
+}
+
+impl Error for IrradianceError {}
+
+// A coarse grid of ambient cube probes spanning a bounding box. It is baked
+// once from the direct (Whitted) shading of the world, and sampled with
+// trilinear interpolation as a cheap single-bounce indirect light estimate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IrradianceVolume {
+    bounding_box: BoundingBox,
+    resolution: [usize; 3],
+    probes: Vec<AmbientCube>,
+}
+
+impl IrradianceVolume {
+    pub fn bake(
+        world: &World,
+        bounding_box: BoundingBox,
+        resolution: [usize; 3],
+        samples: usize,
+    ) -> Result<IrradianceVolume, IrradianceError> {
+        let (x_range, y_range, z_range) = bounding_box.axial_bounds();
+        if [x_range, y_range, z_range]
+            .iter()
+            .flatten()
+            .any(|bound| !bound.is_finite())
+        {
+            return Err(IrradianceError::UnboundedBox);
+        }
+        if resolution.contains(&0) {
+            return Err(IrradianceError::NoProbes(resolution));
+        }
+
+        let directions = IrradianceVolume::sample_directions(samples);
+        let mut volume = IrradianceVolume {
+            bounding_box,
+            resolution,
+            probes: Vec::with_capacity(resolution.iter().product()),
+        };
+
+        // probes are stored x-major, then y, then z
+        for idx_x in 0..resolution[0] {
+            for idx_y in 0..resolution[1] {
+                for idx_z in 0..resolution[2] {
+                    let position = volume.probe_position([idx_x, idx_y, idx_z]);
+                    volume
+                        .probes
+                        .push(AmbientCube::bake(world, position, &directions));
+                }
+            }
+        }
+
+        Ok(volume)
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.bounding_box
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    pub fn probe(&self, index: [usize; 3]) -> &AmbientCube {
+        let [idx_x, idx_y, idx_z] = index;
+        let [_, res_y, res_z] = self.resolution;
+        &self.probes[(idx_x * res_y + idx_y) * res_z + idx_z]
+    }
+
+    pub fn irradiance_at(&self, point: Point, normal: Vector) -> Colour {
+        let (x_range, y_range, z_range) = self.bounding_box.axial_bounds();
+        let [(idx_x0, idx_x1, wx), (idx_y0, idx_y1, wy), (idx_z0, idx_z1, wz)] = [
+            IrradianceVolume::cell(point.x, x_range, self.resolution[0]),
+            IrradianceVolume::cell(point.y, y_range, self.resolution[1]),
+            IrradianceVolume::cell(point.z, z_range, self.resolution[2]),
+        ];
+
+        let lerp_z = |idx_x, idx_y| {
+            self.probe([idx_x, idx_y, idx_z0])
+                .blend(self.probe([idx_x, idx_y, idx_z1]), wz)
+        };
+        let lerp_yz = |idx_x| lerp_z(idx_x, idx_y0).blend(&lerp_z(idx_x, idx_y1), wy);
+
+        lerp_yz(idx_x0).blend(&lerp_yz(idx_x1), wx).evaluate(normal)
+    }
+
+    fn probe_position(&self, index: [usize; 3]) -> Point {
+        let (x_range, y_range, z_range) = self.bounding_box.axial_bounds();
+        let axial = |range: [Float; 2], idx: usize, res: usize| {
+            if res == 1 {
+                (range[0] + range[1]) / 2.0
+            } else {
+                range[0] + (range[1] - range[0]) * idx as Float / (res - 1) as Float
+            }
+        };
+
+        Point::new(
+            axial(x_range, index[0], self.resolution[0]),
+            axial(y_range, index[1], self.resolution[1]),
+            axial(z_range, index[2], self.resolution[2]),
+        )
+    }
+
+    // returns the two neighbouring probe indices along an axis and the
+    // interpolation weight towards the second; points outside are clamped
+    fn cell(value: Float, range: [Float; 2], res: usize) -> (usize, usize, Float) {
+        if res == 1 || range[1] <= range[0] {
+            return (0, 0, 0.0);
+        }
+
+        let position =
+            ((value - range[0]) / (range[1] - range[0])).clamp(0.0, 1.0) * (res - 1) as Float;
+        let idx_lower = (position.floor() as usize).min(res - 2);
+        (idx_lower, idx_lower + 1, position - idx_lower as Float)
+    }
+
+    // evenly distributed directions over the unit sphere (a Fibonacci lattice),
+    // so that baking is deterministic
+    pub(crate) fn sample_directions(samples: usize) -> Vec<Vector> {
+        let golden_angle = PI * (3.0 - Float::sqrt(5.0));
+        (0..samples)
+            .map(|idx| {
+                let y = 1.0 - 2.0 * (idx as Float + 0.5) / samples as Float;
+                let radius = (1.0 - y * y).sqrt();
+                let phi = golden_angle * idx as Float;
+                Vector::new(phi.cos() * radius, y, phi.sin() * radius)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::*;
+    use crate::utils::{approx_eq, BuildInto, Buildable};
+
+    fn glowing_floor_world() -> World {
+        let floor = Plane::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -1.0, 0.0)))
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 1.0, 1.0))),
+                ambient: 1.0,
+                ..Material::default()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        World::new(vec![floor], vec![Box::new(light)])
+    }
+
+    #[test]
+    fn uniform_ambient_cube_is_independent_of_normal() {
+        let cube = AmbientCube::new([Colour::new(0.5, 0.25, 1.0); 6]);
+        let normal = Vector::new(1.0, 2.0, -3.0).normalise();
+        let colour = cube.evaluate(normal);
+        approx_eq!(colour.red, 0.5);
+        approx_eq!(colour.green, 0.25);
+        approx_eq!(colour.blue, 1.0);
+    }
+
+    #[test]
+    fn bake_volume_in_empty_world() {
+        let world = World::default();
+        let bounding_box = BoundingBox::from_axial_bounds([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]);
+        let volume = IrradianceVolume::bake(&world, bounding_box, [2, 2, 2], 16).unwrap();
+        assert_eq!(*volume.probe([1, 0, 1]), AmbientCube::black());
+        assert_eq!(
+            volume.irradiance_at(Point::zero(), Vector::new(0.0, 1.0, 0.0)),
+            Colour::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn bake_volume_above_glowing_floor() {
+        let world = glowing_floor_world();
+        let bounding_box = BoundingBox::from_axial_bounds([-1.0, 1.0], [0.0, 1.0], [-1.0, 1.0]);
+        let volume = IrradianceVolume::bake(&world, bounding_box, [2, 2, 2], 64).unwrap();
+
+        let from_below =
+            volume.irradiance_at(Point::new(0.3, 0.5, -0.2), Vector::new(0.0, -1.0, 0.0));
+        let from_above =
+            volume.irradiance_at(Point::new(0.3, 0.5, -0.2), Vector::new(0.0, 1.0, 0.0));
+        approx_eq!(from_below.red, 1.0);
+        approx_eq!(from_above.red, 0.0);
+    }
+
+    #[test]
+    fn sample_outside_volume_is_clamped() {
+        let world = glowing_floor_world();
+        let bounding_box = BoundingBox::from_axial_bounds([-1.0, 1.0], [0.0, 1.0], [-1.0, 1.0]);
+        let volume = IrradianceVolume::bake(&world, bounding_box, [3, 1, 2], 16).unwrap();
+        let normal = Vector::new(0.0, -1.0, 0.0);
+        assert_eq!(
+            volume.irradiance_at(Point::new(50.0, 0.5, 50.0), normal),
+            volume.probe([2, 0, 1]).evaluate(normal)
+        );
+    }
+
+    #[test]
+    fn bake_volume_over_unbounded_box() {
+        let world = World::default();
+        let baked = IrradianceVolume::bake(&world, BoundingBox::new_unbounded(), [2, 2, 2], 16);
+        assert_eq!(baked, Err(IrradianceError::UnboundedBox));
+    }
+
+    #[test]
+    fn bake_volume_without_probes() {
+        let world = World::default();
+        let bounding_box = BoundingBox::from_axial_bounds([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]);
+        let baked = IrradianceVolume::bake(&world, bounding_box, [2, 0, 2], 16);
+        assert_eq!(baked, Err(IrradianceError::NoProbes([2, 0, 2])));
+    }
+}
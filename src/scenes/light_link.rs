@@ -0,0 +1,41 @@
+use std::collections::HashSet;
+
+// Which objects a light shines on, by object id (see World::object_id), e.g.
+// so that a rim light picks out the subject without washing out the
+// background. Unlinked objects get nothing from the light, not even its
+// ambient term, but are still shadowed by it as usual elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LightLink {
+    Only(HashSet<usize>),
+    Except(HashSet<usize>),
+}
+
+impl LightLink {
+    pub fn only<I: IntoIterator<Item = usize>>(object_ids: I) -> LightLink {
+        LightLink::Only(object_ids.into_iter().collect())
+    }
+
+    pub fn except<I: IntoIterator<Item = usize>>(object_ids: I) -> LightLink {
+        LightLink::Except(object_ids.into_iter().collect())
+    }
+
+    pub fn lights(&self, object_id: usize) -> bool {
+        match self {
+            LightLink::Only(object_ids) => object_ids.contains(&object_id),
+            LightLink::Except(object_ids) => !object_ids.contains(&object_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_and_exclude() {
+        let only = LightLink::only([1, 3]);
+        assert!(only.lights(1) && !only.lights(2));
+        let except = LightLink::except([1, 3]);
+        assert!(!except.lights(1) && except.lights(2));
+    }
+}
@@ -1,19 +1,97 @@
+pub mod ambient;
+pub mod background;
+pub mod benchmark;
+pub mod cancellation;
 pub mod canvas;
+pub mod conformance;
+pub mod contact_sheet;
+pub mod distributed;
+pub mod export;
+pub mod ffmpeg_sink;
+pub mod first_hit;
+pub mod frame_sequence;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod harmonics;
+pub mod heatmap;
+pub mod image_based_lighting;
+pub mod irradiance;
+pub mod light_link;
+pub mod outline;
+pub mod path_tracing;
+pub mod preview;
 pub mod raygen;
+pub mod region;
+pub mod scanline;
+pub mod scheduler;
+pub mod shading;
+pub mod stats;
+pub mod stereo;
+pub mod tone_mapping;
 pub mod view;
 pub mod world;
 
 // crate-level re-exports
+pub(crate) use ambient::*;
+pub(crate) use background::*;
+pub(crate) use cancellation::*;
 pub(crate) use canvas::*;
+pub(crate) use export::*;
+pub(crate) use first_hit::*;
+#[cfg(feature = "gpu")]
+pub(crate) use gpu::*;
+pub(crate) use harmonics::*;
+pub(crate) use heatmap::*;
+pub(crate) use irradiance::*;
+pub(crate) use light_link::*;
+pub(crate) use path_tracing::*;
+pub(crate) use preview::*;
 pub(crate) use raygen::*;
+pub(crate) use region::*;
+pub(crate) use scanline::*;
+pub(crate) use scheduler::*;
+pub(crate) use shading::*;
+pub(crate) use stats::*;
+pub(crate) use stereo::*;
+pub(crate) use tone_mapping::*;
 pub(crate) use view::*;
 pub(crate) use world::*;
 
 // public re-exports (through crate::prelude)
 pub(super) mod prelude {
+    pub use super::ambient::AmbientLight;
+    pub use super::background::Background;
+    pub use super::benchmark::BenchmarkScene;
+    pub use super::cancellation::Cancellation;
     pub use super::canvas;
-    pub use super::canvas::Canvas;
+    pub use super::canvas::{BitDepth, Canvas, CanvasDiff, Encoding, PpmFormat, ResizeFilter};
+    pub use super::conformance::{ConformanceError, ConformanceScene};
+    pub use super::contact_sheet::{ContactSheet, ContactSheetError};
+    pub use super::distributed::{Coordinator, DistributedError, Worker};
+    pub use super::export::{Export, ExportError, ExportFormat, ExportPass, ExportPlan};
+    pub use super::ffmpeg_sink::{FfmpegError, FfmpegSink};
+    pub use super::first_hit::{FirstHit, FirstHitBuffer, MaterialOverrides};
+    pub use super::frame_sequence::{FrameSequence, FrameSequenceError};
+    #[cfg(feature = "gpu")]
+    pub use super::gpu::{Gpu, GpuError};
+    pub use super::harmonics::SphericalHarmonics;
+    pub use super::heatmap::HeatmapMetric;
+    pub use super::image_based_lighting::ImageBasedLighting;
+    pub use super::irradiance::{AmbientCube, IrradianceError, IrradianceVolume};
+    pub use super::light_link::LightLink;
+    pub use super::outline::Outline;
+    pub use super::path_tracing::{Integrator, PathTracing};
+    pub use super::preview::{Preview, PreviewError, PreviewServer};
     pub use super::raygen::prelude::*;
-    pub use super::view::{Camera, Orientation};
+    pub use super::region::Region;
+    pub use super::scanline::{ScanlineError, ScanlineFormat, ScanlineWriter};
+    pub use super::shading::ShadingMode;
+    pub use super::stats::{RenderStats, StatsRecorder, StatsReport};
+    pub use super::stereo::{Stereo, StereoError, StereoLayout};
+    pub use super::tone_mapping::ToneMapping;
+    pub use super::view::{
+        AdaptiveSampling, Camera, CameraBuilder, FramingError, Orientation, OrientationError,
+        Shutter,
+    };
     pub use super::world::World;
 }
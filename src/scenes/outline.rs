@@ -0,0 +1,141 @@
+use crate::collections::{Angle, Colour};
+use crate::scenes::*;
+use crate::utils::Float;
+
+// Edge detection over a first-hit buffer. An edge is drawn between
+// neighbouring pixels that see different objects (silhouettes), whose depths
+// differ by more than depth_threshold relative to the nearer one, or whose
+// normals differ by more than crease_angle. The nearer of the two pixels is
+// painted, so lines sit on the inside of object boundaries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    pub colour: Colour,
+    pub depth_threshold: Float,
+    pub crease_angle: Angle,
+}
+
+impl Default for Outline {
+    fn default() -> Outline {
+        Outline {
+            colour: Colour::new(0.0, 0.0, 0.0),
+            depth_threshold: 0.1,
+            crease_angle: Angle::from_degrees(60.0),
+        }
+    }
+}
+
+impl Outline {
+    // is there an edge between two pixels, and if so, which one (first or
+    // second) should be painted
+    fn edge_between(&self, first: Option<&FirstHit>, second: Option<&FirstHit>) -> Option<bool> {
+        match (first, second) {
+            (None, None) => None,
+            (Some(_), None) => Some(true),
+            (None, Some(_)) => Some(false),
+            (Some(first), Some(second)) => {
+                let paint_first = first.t() <= second.t();
+                let nearer_t = first.t().min(second.t());
+                let mut crease_angle = self.crease_angle;
+                let cos_crease = crease_angle.radians().cos();
+
+                let silhouette = first.object_id() != second.object_id();
+                let depth_step = (first.t() - second.t()).abs() > self.depth_threshold * nearer_t;
+                let crease = first
+                    .computations()
+                    .normal()
+                    .dot(second.computations().normal())
+                    < cos_crease;
+
+                (silhouette || depth_step || crease).then_some(paint_first)
+            }
+        }
+    }
+}
+
+impl FirstHitBuffer {
+    pub fn outline_pixels(&self, outline: &Outline) -> Vec<[usize; 2]> {
+        let (hsize, vsize) = self.canvas_size();
+        let hits = self.dominant_hits();
+        let hit_at = |pos_x: usize, pos_y: usize| hits[pos_y * hsize + pos_x];
+
+        let mut is_edge = vec![false; hsize * vsize];
+        for pos_y in 0..vsize {
+            for pos_x in 0..hsize {
+                // only look right and down, so each pair is visited once
+                for [next_x, next_y] in [[pos_x + 1, pos_y], [pos_x, pos_y + 1]] {
+                    if next_x >= hsize || next_y >= vsize {
+                        continue;
+                    }
+                    match outline.edge_between(hit_at(pos_x, pos_y), hit_at(next_x, next_y)) {
+                        Some(true) => is_edge[pos_y * hsize + pos_x] = true,
+                        Some(false) => is_edge[next_y * hsize + next_x] = true,
+                        None => (),
+                    }
+                }
+            }
+        }
+
+        is_edge
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, edge)| edge)
+            .map(|(idx, _)| [idx % hsize, idx / hsize])
+            .collect()
+    }
+
+    pub fn draw_outlines(&self, image: &mut Canvas, outline: &Outline) -> Result<(), WriteError> {
+        for [pos_x, pos_y] in self.outline_pixels(outline) {
+            image.paint_colour_replace(pos_x, pos_y, outline.colour)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::*;
+    use crate::objects::*;
+    use crate::utils::consts::FRAC_PI_2;
+    use crate::utils::{BuildInto, Buildable};
+
+    #[test]
+    fn outline_sphere_silhouette() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![sphere], vec![Box::new(light)]);
+        world.shading_mode = ShadingMode::toon(3);
+        let camera = Camera::new(Native::new(
+            41,
+            41,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+
+        let first_hits = camera.capture(&world);
+        let mut image = first_hits
+            .reshade(&world, &MaterialOverrides::new())
+            .unwrap();
+        let outline = Outline {
+            colour: Colour::new(1.0, 0.0, 0.0),
+            ..Outline::default()
+        };
+        first_hits.draw_outlines(&mut image, &outline).unwrap();
+
+        let outline_pixels = first_hits.outline_pixels(&outline);
+        assert!(!outline_pixels.is_empty());
+        // every outline pixel lies on the sphere, next to the background
+        for [pos_x, pos_y] in outline_pixels {
+            assert_eq!(image[[pos_x, pos_y]], Pixel::new(outline.colour));
+            assert!(first_hits.object_at(pos_x, pos_y).is_some());
+        }
+        assert_ne!(image[[20, 20]], Pixel::new(outline.colour));
+        assert_eq!(image[[0, 0]], Pixel::new(Colour::new(0.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::collections::{Colour, Vector};
+use crate::objects::Ray;
+use crate::utils::consts::PI;
+use crate::utils::{widen, Float, Random, SampleKey};
+
+// How the colour seen along a camera ray is worked out.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    // direct lighting at each surface, with perfect reflections and
+    // refractions; light bouncing between surfaces is only approximated, by
+    // the world's ambient light and baked irradiance
+    #[default]
+    Whitted,
+    PathTracing(PathTracing),
+}
+
+// Unidirectional path tracing: from each surface a path reaches, it is lit
+// directly by every light (next-event estimation) and then carries on in one
+// random direction, bouncing diffusely (cosine-weighted), reflecting or
+// refracting in proportion to the material's diffuse, reflectance and
+// transparency. Light bouncing off other surfaces, e.g. the colour of a wall
+// bleeding onto the floor, is then picked up along the way, at the cost of
+// noise that only averaging many samples removes. The world's ambient light
+// and baked irradiance are ignored, as they stand in for exactly this.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathTracing {
+    // paths traced and averaged for each camera ray
+    pub samples: usize,
+    // bounces after the first hit before a path is cut off
+    pub max_depth: usize,
+    // bounces after which paths carrying little light are ended at random
+    // (Russian roulette), with the survivors weighted up to make up for them
+    pub roulette_depth: usize,
+    // if set, paths brighter than this in any channel are dimmed to it
+    // before averaging, so that the rare path that finds a small bright light
+    // does not leave a lone white pixel (a firefly) in an otherwise converged
+    // render; this loses a little energy, darkening highlights slightly
+    pub max_sample: Option<Float>,
+}
+
+impl Default for PathTracing {
+    fn default() -> PathTracing {
+        PathTracing::new(16)
+    }
+}
+
+impl PathTracing {
+    pub fn new(samples: usize) -> PathTracing {
+        PathTracing {
+            samples,
+            max_depth: 8,
+            roulette_depth: 3,
+            max_sample: None,
+        }
+    }
+
+    // a path's colour, scaled down evenly (keeping its hue) so that no
+    // channel is over max_sample
+    pub(crate) fn clamp(&self, sample: Colour) -> Colour {
+        let Some(max_sample) = self.max_sample else {
+            return sample;
+        };
+        let brightest = sample.red.max(sample.green).max(sample.blue);
+        if brightest > max_sample {
+            sample * (max_sample / brightest)
+        } else {
+            sample
+        }
+    }
+}
+
+// for a ray cast on its own rather than as a sample of a render, a key that is
+// the same every time the same ray is cast
+pub(crate) fn ray_key(ray: &Ray) -> SampleKey {
+    let key = [
+        ray.origin.x,
+        ray.origin.y,
+        ray.origin.z,
+        ray.direction.x,
+        ray.direction.y,
+        ray.direction.z,
+        ray.time,
+    ]
+    .iter()
+    .fold(0u64, |key, value| {
+        key.rotate_left(9) ^ widen(*value).to_bits().wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    });
+    SampleKey::new(0, 0, key, 0)
+}
+
+// a direction in the hemisphere around normal, more likely the nearer it is
+// to normal (with density cos θ / π), so that light arriving along it needs
+// no weighting by the angle it arrives at
+pub(crate) fn cosine_hemisphere(normal: Vector, random: &mut Random) -> Vector {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalise();
+    let bitangent = normal.cross(tangent);
+
+    let radius_squared = random.next_f64();
+    let radius = radius_squared.sqrt();
+    let phi = 2.0 * PI * random.next_f64();
+    (tangent * (radius * phi.cos())
+        + bitangent * (radius * phi.sin())
+        + normal * (1.0 - radius_squared).sqrt())
+    .normalise()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Point;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn hemisphere_directions_favour_the_normal() {
+        let normal = Vector::new(1.0, 2.0, -1.0).normalise();
+        let mut random = Random::new(3);
+        let count = 4000;
+        let mut total_cos = 0.0;
+        for _ in 0..count {
+            let direction = cosine_hemisphere(normal, &mut random);
+            approx_eq!(direction.magnitude(), 1.0);
+            assert!(direction.dot(normal) >= 0.0);
+            total_cos += direction.dot(normal);
+        }
+        // the mean cosine under a cos θ / π density is 2/3
+        assert!((total_cos / count as Float - 2.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn clamp_keeps_hue() {
+        let sample = Colour::new(8.0, 4.0, 0.5);
+        let path_tracing = PathTracing::new(1);
+        assert_eq!(path_tracing.clamp(sample), sample);
+
+        let clamped = PathTracing {
+            max_sample: Some(2.0),
+            ..path_tracing
+        };
+        assert_eq!(clamped.clamp(sample), Colour::new(2.0, 1.0, 0.125));
+        let dim = Colour::new(1.0, 0.5, 2.0);
+        assert_eq!(clamped.clamp(dim), dim);
+    }
+
+    #[test]
+    fn rays_on_their_own_are_keyed_by_ray() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let other = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.1, 1.0));
+        assert_eq!(ray_key(&ray), ray_key(&ray));
+        assert_ne!(ray_key(&ray), ray_key(&other));
+        assert_ne!(ray_key(&ray), ray_key(&ray.with_time(0.5)));
+    }
+}
@@ -0,0 +1,282 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::scenes::*;
+use crate::utils::Float;
+
+#[derive(Debug)]
+pub enum PreviewError {
+    Write(WriteError),
+    Io(io::Error),
+}
+
+impl Display for PreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreviewError::Write(error) => write!(f, "could not paint canvas: {error:?}"),
+            PreviewError::Io(error) => write!(f, "could not write preview: {error}"),
+        }
+    }
+}
+
+impl Error for PreviewError {}
+
+impl From<WriteError> for PreviewError {
+    fn from(error: WriteError) -> PreviewError {
+        PreviewError::Write(error)
+    }
+}
+
+impl From<io::Error> for PreviewError {
+    fn from(error: io::Error) -> PreviewError {
+        PreviewError::Io(error)
+    }
+}
+
+// Small PNG snapshots of a render in progress (see Camera::render_with_preview),
+// written to a file, served over HTTP, or both, so that a long render can be
+// checked on from another machine. Snapshots are shrunk to at most max_size
+// pixels along their longer side.
+#[derive(Debug)]
+pub struct Preview {
+    pub path: Option<String>,
+    pub server: Option<PreviewServer>,
+    pub interval: Duration,
+    pub max_size: usize,
+}
+
+impl Default for Preview {
+    fn default() -> Preview {
+        Preview {
+            path: None,
+            server: None,
+            interval: Duration::from_secs(10),
+            max_size: 256,
+        }
+    }
+}
+
+impl Preview {
+    pub fn to_file(path: &str) -> Preview {
+        Preview {
+            path: Some(path.to_string()),
+            ..Preview::default()
+        }
+    }
+
+    pub fn publish(&self, canvas: &Canvas) -> Result<(), PreviewError> {
+        let png = self.snapshot(canvas)?.write_to_png();
+        if let Some(path) = &self.path {
+            // renamed into place, so readers never see a partly written file
+            let partial_path = format!("{path}.partial");
+            fs::write(&partial_path, &png)?;
+            fs::rename(&partial_path, path)?;
+        }
+        if let Some(server) = &self.server {
+            *server.latest.lock().unwrap() = png;
+        }
+        Ok(())
+    }
+
+    // shrunk and tone mapped
+    fn snapshot(&self, canvas: &Canvas) -> Result<Canvas, WriteError> {
+        let (width, height) = (canvas.width(), canvas.height());
+        let longer_side = width.max(height);
+        let small = if longer_side > self.max_size && self.max_size > 0 {
+            let scale = self.max_size as Float / longer_side as Float;
+            let width = ((width as Float * scale).round() as usize).max(1);
+            let height = ((height as Float * scale).round() as usize).max(1);
+            canvas.resize(Width(width), Height(height), ResizeFilter::Box)
+        } else {
+            canvas.clone()
+        };
+
+        let mut snapshot = Canvas::new(Width(small.width()), Height(small.height()));
+        for row in 0..small.height() {
+            for column in 0..small.width() {
+                let colour = ToneMapping::Reinhard.map(small[[column, row]].colour());
+                snapshot.paint_colour_replace(column, row, colour)?;
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+// Serves the latest preview as a PNG to any HTTP request, from a background
+// thread that stops when the server is dropped.
+pub struct PreviewServer {
+    address: SocketAddr,
+    latest: Arc<Mutex<Vec<u8>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Debug for PreviewServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreviewServer")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for PreviewServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+impl PreviewServer {
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+    // clients are answered one at a time, so one that stops reading or
+    // writing is dropped after this long rather than stalling the rest
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+    // e.g. "0.0.0.0:8080" to be reachable from other machines, or port 0 for
+    // any free port (see address)
+    pub fn bind(address: &str) -> Result<PreviewServer, io::Error> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let server = PreviewServer {
+            address: listener.local_addr()?,
+            latest: Arc::new(Mutex::new(vec![])),
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let latest = Arc::clone(&server.latest);
+        let stopped = Arc::clone(&server.stopped);
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    // a failed response only affects that one client
+                    Ok((stream, _)) => {
+                        let _ = respond(stream, &latest);
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(PreviewServer::POLL_INTERVAL)
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+fn respond(mut stream: TcpStream, latest: &Mutex<Vec<u8>>) -> Result<(), io::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(PreviewServer::CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(PreviewServer::CLIENT_TIMEOUT))?;
+    // the request itself does not matter, but is read so the client sees a
+    // clean response rather than a reset connection
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+
+    let png = latest.lock().unwrap().clone();
+    let header = if png.is_empty() {
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string()
+    } else {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nRefresh: 5\r\nConnection: close\r\n\r\n",
+            png.len()
+        )
+    };
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(&png)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Colour;
+    use crate::utils::png;
+
+    fn bright_canvas() -> Canvas {
+        let mut canvas = Canvas::new(Width(40), Height(10));
+        for row in 0..10 {
+            for column in 0..40 {
+                canvas
+                    .paint_colour_replace(column, row, Colour::new(1.5, 0.75, 0.0))
+                    .unwrap();
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn publish_to_file() {
+        let path = std::env::temp_dir().join("raytracer_preview_test.png");
+        let path = path.to_str().unwrap();
+        let preview = Preview {
+            max_size: 8,
+            ..Preview::to_file(path)
+        };
+        preview.publish(&bright_canvas()).unwrap();
+
+        let image = png::decode(&fs::read(path).unwrap()).unwrap();
+        assert_eq!((image.width, image.height), (8, 2));
+        // red stays twice as bright as green, in linear light
+        let [red, green, blue, _] = image.pixel(3, 1);
+        assert!(red > green && green > 0 && blue == 0);
+        let linear = |value: u8| Encoding::Srgb.decode(value as Float / 255.0);
+        assert!((linear(red) / linear(green) - 2.0).abs() < 0.05);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn serve_over_http() {
+        let server = PreviewServer::bind("127.0.0.1:0").unwrap();
+        let address = server.address();
+        let get = || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+            let mut response = vec![];
+            stream.read_to_end(&mut response).unwrap();
+            response
+        };
+        assert!(get().starts_with(b"HTTP/1.1 503"));
+
+        let preview = Preview {
+            server: Some(server),
+            ..Preview::default()
+        };
+        preview.publish(&bright_canvas()).unwrap();
+        let response = get();
+        let body_start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&response[..body_start]);
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Type: image/png"));
+        let image = png::decode(&response[body_start..]).unwrap();
+        assert_eq!((image.width, image.height), (40, 10));
+    }
+
+    #[test]
+    fn stalled_clients_time_out() {
+        let server = PreviewServer::bind("127.0.0.1:0").unwrap();
+        // far more than the socket buffers hold, so writing it blocks until
+        // the client reads
+        *server.latest.lock().unwrap() = vec![0; 64 << 20];
+        let silent = TcpStream::connect(server.address()).unwrap();
+        let mut not_reading = TcpStream::connect(server.address()).unwrap();
+        not_reading.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut status = [0; 12];
+        stream.read_exact(&mut status).unwrap();
+        assert_eq!(&status, b"HTTP/1.1 200");
+        drop((silent, not_reading));
+    }
+}
@@ -1,13 +1,15 @@
 use super::Native;
 use crate::collections::{Angle, Point};
-use crate::objects::{Ray, Transform, Transformable};
+use crate::objects::Transform;
 use crate::scenes::raygen;
 use crate::scenes::raygen::{RayGenerator, TaggedPixel, TaggedRay};
-use crate::scenes::Orientation;
+use crate::scenes::{Height, Orientation, Region, Width};
 use crate::utils::floats::EPSILON;
+use crate::utils::Float;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Agss {
-    render_scale: f64,
+    render_scale: Float,
     native: Native,
 }
 
@@ -17,7 +19,7 @@ impl Agss {
         vsize: usize,
         fov: Angle,
         orientation: Orientation,
-        render_scale: f64,
+        render_scale: Float,
     ) -> Agss {
         let native = Native::new(hsize, vsize, fov, orientation);
         Agss {
@@ -42,19 +44,19 @@ impl Agss {
         self.native.frame_transformation()
     }
 
-    pub fn half_height(&self) -> f64 {
+    pub fn half_height(&self) -> Float {
         self.native.half_height()
     }
 
-    pub fn half_width(&self) -> f64 {
+    pub fn half_width(&self) -> Float {
         self.native.half_width()
     }
 
-    pub fn pixel_size(&self) -> f64 {
+    pub fn pixel_size(&self) -> Float {
         self.native.pixel_size()
     }
 
-    pub fn render_scale(&self) -> f64 {
+    pub fn render_scale(&self) -> Float {
         self.render_scale
     }
 }
@@ -65,11 +67,21 @@ impl IntoIterator for Agss {
 
     fn into_iter(self) -> Self::IntoIter {
         let render_scale = self.render_scale();
-        let hsize = f64::ceil(self.hsize() as f64 * render_scale) as usize;
-        let vsize = f64::ceil(self.vsize() as f64 * render_scale) as usize;
-        let pixel_iterator = Box::new(
-            (0..hsize).flat_map(move |pos_x| std::iter::repeat(pos_x).take(vsize).zip(0..vsize)),
-        );
+        let hsize = Float::ceil(self.hsize() as Float * render_scale) as usize;
+        let vsize = Float::ceil(self.vsize() as Float * render_scale) as usize;
+        // the subpixels overlapping the pixels it is restricted to
+        let region = self.native.region().map(|region| {
+            let start = |pixel: usize| (pixel as Float * render_scale).floor() as usize;
+            let end = |pixel: usize| (pixel as Float * render_scale).ceil() as usize;
+            let (column, row) = (start(region.column), start(region.row));
+            Region::new(
+                column,
+                row,
+                Width(end(region.column + region.width).saturating_sub(column)),
+                Height(end(region.row + region.height).saturating_sub(row)),
+            )
+        });
+        let pixel_iterator = raygen::pixels(hsize, vsize, region);
 
         AgssIterator {
             pixel_iterator,
@@ -83,11 +95,49 @@ impl RayGenerator for Agss {
     fn canvas_size(&self) -> (usize, usize) {
         (self.hsize(), self.vsize())
     }
+
+    fn view_transformation(&self) -> &Transform {
+        self.native.view_transformation()
+    }
+
+    fn half_extents(&self) -> (Float, Float) {
+        self.native.half_extents()
+    }
+
+    fn reorient(self, orientation: Orientation) -> Agss {
+        Agss {
+            native: self.native.reorient(orientation),
+            ..self
+        }
+    }
+
+    fn restrict(self, region: Region) -> Agss {
+        Agss {
+            native: self.native.restrict(region),
+            ..self
+        }
+    }
+
+    // a subpixel covering a pixel in the region may cover one outside it
+    // more, as far away as a subpixel is wide
+    fn keyed_region(&self, region: Region) -> Region {
+        let margin = (1.0 / self.render_scale).ceil().max(1.0) as usize;
+        let (column, row) = (
+            region.column.saturating_sub(margin),
+            region.row.saturating_sub(margin),
+        );
+        Region::new(
+            column,
+            row,
+            Width((region.column + region.width + margin).min(self.hsize()) - column),
+            Height((region.row + region.height + margin).min(self.vsize()) - row),
+        )
+    }
 }
 
 pub struct AgssIterator {
     pixel_iterator: Box<dyn Iterator<Item = (usize, usize)>>,
-    render_scale: f64,
+    render_scale: Float,
     native: Native,
 }
 
@@ -115,32 +165,32 @@ impl Iterator for AgssIterator {
                 // compute subpixel-pixel boundary intersections
                 // (x0, y0) and (x1, y1) denote the subpixel's boundaries in the pixel frame
                 let corner_0 = [
-                    pos_x as f64 / self.render_scale,
-                    pos_y as f64 / self.render_scale,
+                    pos_x as Float / self.render_scale,
+                    pos_y as Float / self.render_scale,
                 ];
                 // edge alignment strategy: truncation
                 let corner_1 = [
-                    f64::min(
-                        (pos_x as f64 + 1.0) / self.render_scale,
-                        self.native.hsize() as f64,
+                    Float::min(
+                        (pos_x as Float + 1.0) / self.render_scale,
+                        self.native.hsize() as Float,
                     ),
-                    f64::min(
-                        (pos_y as f64 + 1.0) / self.render_scale,
-                        self.native.vsize() as f64,
+                    Float::min(
+                        (pos_y as Float + 1.0) / self.render_scale,
+                        self.native.vsize() as Float,
                     ),
                 ];
 
                 let mut tagged_pixels: Vec<TaggedPixel> = vec![TaggedPixel::new(
                     [
-                        f64::floor(corner_0[0]) as usize,
-                        f64::floor(corner_0[1]) as usize,
+                        Float::floor(corner_0[0]) as usize,
+                        Float::floor(corner_0[1]) as usize,
                     ],
                     (corner_1[0] - corner_0[0]) * (corner_1[1] - corner_0[1]),
                 )];
 
                 for axis_index in 0_usize..=1_usize {
-                    if f64::ceil(corner_0[axis_index] + EPSILON)
-                        == f64::floor(corner_1[axis_index] - EPSILON)
+                    if Float::ceil(corner_0[axis_index] + EPSILON)
+                        == Float::floor(corner_1[axis_index] - EPSILON)
                     {
                         let mut new_tagged_pixels =
                             Vec::with_capacity(2_usize.pow(axis_index as u32 + 1));
@@ -167,21 +217,21 @@ impl Iterator for AgssIterator {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::collections::Vector;
+    use crate::objects::Ray;
     use crate::utils::approx_eq;
 
-    use super::*;
-
     #[test]
     fn integer_render_scale_centre_of_screen() {
         let canvas = Agss::new(
             7,
             7,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             Orientation::default(),
             3.0,
         );
-        let tagged_ray = canvas.into_iter().skip(21 * 10 + 10).next().unwrap();
+        let tagged_ray = canvas.into_iter().nth(21 * 10 + 10).unwrap();
         let casted_ray = tagged_ray.ray();
         let resulting_ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
         approx_eq!(casted_ray.origin.x, resulting_ray.origin.x);
@@ -202,11 +252,11 @@ mod tests {
         let canvas = Agss::new(
             14,
             14,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             Orientation::default(),
             1.5,
         );
-        let tagged_ray = canvas.into_iter().skip(21 * 10 + 10).next().unwrap();
+        let tagged_ray = canvas.into_iter().nth(21 * 10 + 10).unwrap();
         let casted_ray = tagged_ray.ray();
         let resulting_ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
         approx_eq!(casted_ray.origin.x, resulting_ray.origin.x);
@@ -228,12 +278,44 @@ mod tests {
         approx_eq!(pixels[3].blend_weight(), 0.111111);
     }
 
+    #[test]
+    fn restricted_to_a_region() {
+        let agss = Agss::new(
+            10,
+            10,
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
+            Orientation::default(),
+            1.0 + (1.0 / 3.0),
+        );
+        let region = Region::new(3, 2, Width(4), Height(5));
+        let covers_region = |tagged_ray: &TaggedRay| {
+            tagged_ray
+                .pixels()
+                .iter()
+                .any(|tagged_pixel| region.contains(tagged_pixel.index()))
+        };
+        let all: Vec<TaggedRay> = agss.clone().into_iter().filter(covers_region).collect();
+        let restricted: Vec<TaggedRay> = agss.clone().restrict(region).into_iter().collect();
+        assert!(restricted.len() < 14 * 14);
+        assert_eq!(
+            restricted
+                .into_iter()
+                .filter(covers_region)
+                .collect::<Vec<_>>(),
+            all
+        );
+        assert_eq!(
+            agss.keyed_region(region),
+            Region::new(2, 1, Width(6), Height(7))
+        );
+    }
+
     #[test]
     fn noninteger_render_scale_edge_misaligned() {
         let canvas = Agss::new(
             10,
             10,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             Orientation::default(),
             1.0 + (1.0 / 3.0),
         );
@@ -1,13 +1,18 @@
 pub mod agss;
 pub mod native;
+pub mod panoramic;
+#[allow(clippy::module_inception)]
 pub mod raygen;
+pub mod supersampled;
 
 // crate-level re-exports
-pub(crate) use agss::*;
 pub(crate) use native::*;
 pub(crate) use raygen::*;
+pub(crate) use supersampled::*;
 
 pub(super) mod prelude {
     pub use super::agss::Agss;
     pub use super::native::Native;
+    pub use super::panoramic::{Panoramic, Projection};
+    pub use super::supersampled::{Sampling, Supersampled};
 }
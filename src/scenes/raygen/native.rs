@@ -1,17 +1,20 @@
 use crate::collections::{Angle, Point};
-use crate::objects::{Ray, Transform, Transformable};
+use crate::objects::{Ray, Transform};
 use crate::scenes::raygen;
 use crate::scenes::raygen::{RayGenerator, TaggedPixel, TaggedRay};
-use crate::scenes::Orientation;
+use crate::scenes::{Orientation, Region};
+use crate::utils::Float;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Native {
     hsize: usize,
     vsize: usize,
     fov: Angle,
     frame_transformation: Transform,
-    half_height: f64,
-    half_width: f64,
-    pixel_size: f64,
+    half_height: Float,
+    half_width: Float,
+    pixel_size: Float,
+    region: Option<Region>,
 }
 
 impl Native {
@@ -25,7 +28,7 @@ impl Native {
 
         let half_width;
         let half_height;
-        match hsize as f64 / vsize as f64 {
+        match hsize as Float / vsize as Float {
             aspect_ratio if aspect_ratio >= 1.0 => {
                 half_width = half_view;
                 half_height = half_view / aspect_ratio;
@@ -37,7 +40,7 @@ impl Native {
             _ => panic!(),
         }
 
-        let pixel_size = (half_width * 2.0) / hsize as f64;
+        let pixel_size = (half_width * 2.0) / hsize as Float;
 
         Native {
             hsize,
@@ -47,6 +50,7 @@ impl Native {
             half_height,
             half_width,
             pixel_size,
+            region: None,
         }
     }
 
@@ -66,17 +70,51 @@ impl Native {
         &self.frame_transformation
     }
 
-    pub fn half_height(&self) -> f64 {
+    pub fn half_height(&self) -> Float {
         self.half_height
     }
 
-    pub fn half_width(&self) -> f64 {
+    pub fn half_width(&self) -> Float {
         self.half_width
     }
 
-    pub fn pixel_size(&self) -> f64 {
+    pub fn pixel_size(&self) -> Float {
         self.pixel_size
     }
+
+    // the pixels it is restricted to, if any
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
+    // through the centre of a pixel, counted from the top left
+    pub fn ray_for_pixel(&self, pos_x: usize, pos_y: usize) -> Ray {
+        let (offset_x, offset_y) = raygen::pixel_offset_from_centre_target(
+            pos_x,
+            pos_y,
+            self.pixel_size,
+            self.half_width,
+            self.half_height,
+        );
+        raygen::generate_normalised_ray(
+            Point::zero(),
+            Point::new(offset_x, offset_y, -1.0),
+            &self.frame_transformation.invert(),
+        )
+    }
+
+    // through any point on the canvas, in pixels from its top left corner
+    pub fn ray_through(&self, x: Float, y: Float) -> Ray {
+        raygen::generate_normalised_ray(
+            Point::zero(),
+            Point::new(
+                self.half_width - x * self.pixel_size,
+                self.half_height - y * self.pixel_size,
+                -1.0,
+            ),
+            &self.frame_transformation.invert(),
+        )
+    }
 }
 
 impl IntoIterator for Native {
@@ -84,11 +122,7 @@ impl IntoIterator for Native {
     type IntoIter = NativeIterator;
 
     fn into_iter(self) -> Self::IntoIter {
-        let hsize = self.hsize();
-        let vsize = self.vsize();
-        let pixel_iterator = Box::new(
-            (0..hsize).flat_map(move |pos_x| std::iter::repeat(pos_x).take(vsize).zip(0..vsize)),
-        );
+        let pixel_iterator = raygen::pixels(self.hsize, self.vsize, self.region);
 
         NativeIterator {
             pixel_iterator,
@@ -101,6 +135,28 @@ impl RayGenerator for Native {
     fn canvas_size(&self) -> (usize, usize) {
         (self.hsize, self.vsize)
     }
+
+    fn view_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    fn half_extents(&self) -> (Float, Float) {
+        (self.half_width, self.half_height)
+    }
+
+    fn reorient(self, orientation: Orientation) -> Native {
+        Native {
+            region: self.region,
+            ..Native::new(self.hsize, self.vsize, self.fov, orientation)
+        }
+    }
+
+    fn restrict(self, region: Region) -> Native {
+        Native {
+            region: Some(region),
+            ..self
+        }
+    }
 }
 
 pub struct NativeIterator {
@@ -114,18 +170,7 @@ impl Iterator for NativeIterator {
     fn next(&mut self) -> Option<Self::Item> {
         match self.pixel_iterator.next() {
             Some((pos_x, pos_y)) => {
-                let (offset_x, offset_y) = raygen::pixel_offset_from_centre_target(
-                    pos_x,
-                    pos_y,
-                    self.native.pixel_size(),
-                    self.native.half_width(),
-                    self.native.half_height(),
-                );
-                let ray = raygen::generate_normalised_ray(
-                    Point::zero(),
-                    Point::new(offset_x, offset_y, -1.0),
-                    &self.native.frame_transformation().invert(),
-                );
+                let ray = self.native.ray_for_pixel(pos_x, pos_y);
 
                 // tag pixel
                 let tagged_pixel = TaggedPixel::new([pos_x, pos_y], 1.0);
@@ -141,32 +186,31 @@ impl Iterator for NativeIterator {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::collections::*;
     use crate::objects::*;
     use crate::scenes::Orientation;
     use crate::utils::approx_eq;
 
-    use super::*;
-
     #[test]
     fn pixel_size() {
         let horizontal_canvas = Native::new(
             200,
             125,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             Orientation::default(),
         );
         let vertical_canvas = Native::new(
             125,
             200,
-            Angle::from_radians(std::f64::consts::FRAC_PI_2),
+            Angle::from_radians(crate::utils::consts::FRAC_PI_2),
             Orientation::default(),
         );
         approx_eq!(horizontal_canvas.pixel_size, 0.01);
         approx_eq!(vertical_canvas.pixel_size, 0.01);
     }
 
-    use std::f64::consts::FRAC_PI_2;
+    use crate::utils::consts::FRAC_PI_2;
 
     #[test]
     fn ray_through_centre_of_camera_view() {
@@ -176,7 +220,7 @@ mod tests {
             Angle::from_radians(FRAC_PI_2),
             Orientation::default(),
         );
-        let tagged_ray = native.into_iter().skip(101 * 100 + 50).next().unwrap(); // ray for pixel [100, 50]
+        let tagged_ray = native.into_iter().nth(101 * 100 + 50).unwrap(); // ray for pixel [100, 50]
         let casted_ray = tagged_ray.ray();
         let resulting_ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
         approx_eq!(casted_ray.origin.x, resulting_ray.origin.x);
@@ -187,6 +231,23 @@ mod tests {
         approx_eq!(casted_ray.direction.z, resulting_ray.direction.z);
     }
 
+    #[test]
+    fn ray_for_pixel_matches_iteration() {
+        let native = Native::new(
+            11,
+            5,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(1.0, 2.0, 3.0),
+                Point::zero(),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        let tagged_ray = native.clone().into_iter().nth(5 * 7 + 3).unwrap();
+        assert_eq!(tagged_ray.pixels()[0].index(), [7, 3]);
+        assert_eq!(native.ray_for_pixel(7, 3), tagged_ray.ray());
+    }
+
     #[test]
     fn ray_through_corner_of_camera_view() {
         let native = Native::new(
@@ -208,7 +269,7 @@ mod tests {
         approx_eq!(casted_ray.direction.z, resulting_ray.direction.z);
     }
 
-    use std::f64::consts::FRAC_PI_4;
+    use crate::utils::consts::FRAC_PI_4;
 
     #[test]
     fn ray_with_transformed_camera() {
@@ -222,15 +283,10 @@ mod tests {
             Angle::from_radians(FRAC_PI_2),
             Orientation::default().transform(&transform),
         );
-        let casted_ray = native
-            .into_iter()
-            .skip(101 * 100 + 50)
-            .next()
-            .unwrap()
-            .ray();
+        let casted_ray = native.into_iter().nth(101 * 100 + 50).unwrap().ray();
         let resulting_ray = Ray::new(
             Point::new(0.0, 2.0, -5.0),
-            Vector::new(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0),
+            Vector::new(Float::sqrt(2.0) / 2.0, 0.0, -Float::sqrt(2.0) / 2.0),
         );
         approx_eq!(casted_ray.origin.x, resulting_ray.origin.x);
         approx_eq!(casted_ray.origin.y, resulting_ray.origin.y);
@@ -0,0 +1,227 @@
+use crate::collections::{Angle, Point, Vector};
+use crate::objects::{Ray, Transform, Transformable};
+use crate::scenes::raygen::{self, RayGenerator, TaggedPixel, TaggedRay};
+use crate::scenes::{Orientation, Region};
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+// How a panoramic camera spreads directions over the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // equidistant: the angle from the view direction grows in step with the
+    // distance from the centre of the canvas, reaching half the field of view
+    // (which may be 180° or more) at the edge of the largest circle that fits;
+    // pixels outside the circle are not rendered
+    Fisheye(Angle),
+    // every direction, with longitude across and latitude up the canvas, the
+    // view direction in the middle; laid out as Background::Equirectangular
+    // reads it, so that a render from a camera looking along +z (with +y up)
+    // can be used as an environment map as it is
+    Equirectangular,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Panoramic {
+    hsize: usize,
+    vsize: usize,
+    projection: Projection,
+    frame_transformation: Transform,
+    region: Option<Region>,
+}
+
+impl Panoramic {
+    pub fn new(
+        hsize: usize,
+        vsize: usize,
+        projection: Projection,
+        Orientation(frame_transformation): Orientation,
+    ) -> Panoramic {
+        Panoramic {
+            hsize,
+            vsize,
+            projection,
+            frame_transformation,
+            region: None,
+        }
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn frame_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    // through the centre of a pixel, counted from the top left, or None if
+    // the projection leaves the pixel out
+    pub fn ray_for_pixel(&self, pos_x: usize, pos_y: usize) -> Option<Ray> {
+        let direction = match self.projection {
+            Projection::Fisheye(mut fov) => {
+                // from the centre, in pixels, with +x to the left as for Native
+                let offset_x = self.hsize as Float / 2.0 - (pos_x as Float + 0.5);
+                let offset_y = self.vsize as Float / 2.0 - (pos_y as Float + 0.5);
+                let radius = self.hsize.min(self.vsize) as Float / 2.0;
+                let distance = offset_x.hypot(offset_y);
+                if distance > radius {
+                    return None;
+                }
+                let polar = distance / radius * fov.radians() / 2.0;
+                let (across_x, across_y) = if distance > 0.0 {
+                    (offset_x / distance, offset_y / distance)
+                } else {
+                    (0.0, 0.0)
+                };
+                Vector::new(polar.sin() * across_x, polar.sin() * across_y, -polar.cos())
+            }
+            Projection::Equirectangular => {
+                let u = (pos_x as Float + 0.5) / self.hsize as Float;
+                let v = 1.0 - (pos_y as Float + 0.5) / self.vsize as Float;
+                // the inverse of UvMapping::spherical, turned to face -z
+                let azimuth = 2.0 * PI * (0.5 - u);
+                let polar = (1.0 - v) * PI;
+                Vector::new(
+                    -polar.sin() * azimuth.sin(),
+                    polar.cos(),
+                    -polar.sin() * azimuth.cos(),
+                )
+            }
+        };
+
+        let camera_to_world = self.frame_transformation.invert();
+        let origin = Point::zero().transform(&camera_to_world);
+        let target = (Point::zero() + direction).transform(&camera_to_world);
+        Some(Ray::new(origin, (target - origin).normalise()))
+    }
+}
+
+impl IntoIterator for Panoramic {
+    type Item = TaggedRay;
+    type IntoIter = PanoramicIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let pixel_iterator = raygen::pixels(self.hsize, self.vsize, self.region);
+
+        PanoramicIterator {
+            pixel_iterator,
+            panoramic: self,
+        }
+    }
+}
+
+impl RayGenerator for Panoramic {
+    fn canvas_size(&self) -> (usize, usize) {
+        (self.hsize, self.vsize)
+    }
+
+    fn view_transformation(&self) -> &Transform {
+        &self.frame_transformation
+    }
+
+    // of a narrower view within the panorama, for framing; anything beyond
+    // 89° either way is framed as though it were 89°
+    fn half_extents(&self) -> (Float, Float) {
+        let half_angle = match self.projection {
+            Projection::Fisheye(mut fov) => fov.radians() / 2.0,
+            Projection::Equirectangular => PI,
+        };
+        let half_extent = half_angle.min(Float::to_radians(89.0)).tan();
+        (half_extent, half_extent)
+    }
+
+    fn reorient(self, orientation: Orientation) -> Panoramic {
+        Panoramic {
+            region: self.region,
+            ..Panoramic::new(self.hsize, self.vsize, self.projection, orientation)
+        }
+    }
+
+    fn restrict(self, region: Region) -> Panoramic {
+        Panoramic {
+            region: Some(region),
+            ..self
+        }
+    }
+}
+
+pub struct PanoramicIterator {
+    pixel_iterator: Box<dyn Iterator<Item = (usize, usize)>>,
+    panoramic: Panoramic,
+}
+
+impl Iterator for PanoramicIterator {
+    type Item = TaggedRay;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (pos_x, pos_y) in self.pixel_iterator.by_ref() {
+            if let Some(ray) = self.panoramic.ray_for_pixel(pos_x, pos_y) {
+                let tagged_pixel = TaggedPixel::new([pos_x, pos_y], 1.0);
+                return Some(TaggedRay::new(ray, vec![tagged_pixel]));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::UvMapping;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn fisheye_spreads_angle_evenly() {
+        let fisheye = Panoramic::new(
+            101,
+            101,
+            Projection::Fisheye(Angle::from_degrees(180.0)),
+            Orientation::default(),
+        );
+        let centre = fisheye.ray_for_pixel(50, 50).unwrap();
+        approx_eq!(centre.direction.z, -1.0);
+        // the middle of the left edge looks 90° to the side, as Native's +x
+        let edge = fisheye.ray_for_pixel(0, 50).unwrap();
+        assert!(edge.direction.x > 0.99);
+        // halfway out is 45° from the view direction
+        let halfway = fisheye.ray_for_pixel(50, 25).unwrap();
+        assert!((halfway.direction.y - Float::sqrt(0.5)).abs() < 0.02);
+        assert_eq!(fisheye.ray_for_pixel(0, 0), None);
+
+        let rays = fisheye.into_iter().count();
+        assert!(rays < 101 * 101 && rays > 7000);
+    }
+
+    #[test]
+    fn equirectangular_matches_background_layout() {
+        let equirectangular = Panoramic::new(
+            64,
+            32,
+            Projection::Equirectangular,
+            Orientation::new(
+                Point::new(1.0, 2.0, 3.0),
+                Point::new(1.0, 2.0, 4.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        for (pos_x, pos_y) in [(0, 0), (17, 5), (32, 16), (63, 31)] {
+            let ray = equirectangular.ray_for_pixel(pos_x, pos_y).unwrap();
+            approx_eq!(ray.origin.z, 3.0);
+            let (u, v) = UvMapping::spherical(Point::zero() + ray.direction);
+            approx_eq!(u, (pos_x as Float + 0.5) / 64.0);
+            approx_eq!(v, 1.0 - (pos_y as Float + 0.5) / 32.0);
+        }
+
+        // the middle of the canvas is the view direction
+        let default = Panoramic::new(64, 32, Projection::Equirectangular, Orientation::default());
+        let middle = default.ray_for_pixel(32, 16).unwrap();
+        assert!(middle.direction.z < -0.99);
+    }
+}
@@ -1,14 +1,16 @@
 use crate::collections::Point;
 use crate::objects::{Ray, Transform, Transformable};
+use crate::scenes::{Height, Orientation, Region, Width};
+use crate::utils::Float;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TaggedPixel {
     pub index: [usize; 2],
-    pub blend_weight: f64,
+    pub blend_weight: Float,
 }
 
 impl TaggedPixel {
-    pub fn new(index: [usize; 2], blend_weight: f64) -> TaggedPixel {
+    pub fn new(index: [usize; 2], blend_weight: Float) -> TaggedPixel {
         TaggedPixel {
             index,
             blend_weight,
@@ -19,7 +21,7 @@ impl TaggedPixel {
         self.index
     }
 
-    pub fn blend_weight(&self) -> f64 {
+    pub fn blend_weight(&self) -> Float {
         self.blend_weight
     }
 }
@@ -44,24 +46,65 @@ impl TaggedRay {
     }
 }
 
-pub trait RayGenerator: IntoIterator<Item = TaggedRay> {
+pub trait RayGenerator: IntoIterator<Item = TaggedRay> + Clone {
     fn canvas_size(&self) -> (usize, usize);
+    // world to camera, as given by the generator's Orientation
+    fn view_transformation(&self) -> &Transform;
+    // half the width and height of the view plane, one unit from the camera
+    fn half_extents(&self) -> (Float, Float);
+    // the same generator looking from somewhere else
+    fn reorient(self, orientation: Orientation) -> Self;
+    // the same generator with its random choices (if any) keyed by a render's
+    // seed and frame; see Camera::with_seed
+    fn reseed(self, _seed: u64, _frame: u64) -> Self {
+        self
+    }
+    // the same generator, casting only the rays that cover some pixel in the
+    // region, in the same order as it casts them across the whole canvas
+    fn restrict(self, region: Region) -> Self;
+    // the pixels whose rays must all be cast to key the rays covering the
+    // region as a full render keys them, since a ray is keyed by the pixel
+    // it covers most (see Camera::with_seed)
+    fn keyed_region(&self, region: Region) -> Region {
+        region
+    }
+}
+
+// the pixels generators cast through, column by column; only those in the
+// region if there is one
+pub fn pixels(
+    hsize: usize,
+    vsize: usize,
+    region: Option<Region>,
+) -> Box<dyn Iterator<Item = (usize, usize)>> {
+    let Region {
+        column,
+        row,
+        width,
+        height,
+    } = region.unwrap_or(Region::new(0, 0, Width(hsize), Height(vsize)));
+    let columns = column.min(hsize)..(column + width).min(hsize);
+    let rows = row.min(vsize)..(row + height).min(vsize);
+    Box::new(columns.flat_map(move |pos_x| std::iter::repeat(pos_x).zip(rows.clone())))
 }
 
 pub fn pixel_offset_from_centre_target(
     pixel_pos_x: usize,
     pixel_pos_y: usize,
-    pixel_size: f64,
-    half_width: f64,
-    half_height: f64,
-) -> (f64, f64) {
+    pixel_size: Float,
+    half_width: Float,
+    half_height: Float,
+) -> (Float, Float) {
     (
-        half_width - ((pixel_pos_x as f64 + 0.5) * pixel_size),
-        half_height - ((pixel_pos_y as f64 + 0.5) * pixel_size),
+        half_width - ((pixel_pos_x as Float + 0.5) * pixel_size),
+        half_height - ((pixel_pos_y as Float + 0.5) * pixel_size),
     )
 }
 
-pub fn subpixel_to_pixel_frame([subpixel_x, subpixel_y]: [f64; 2], render_scale: f64) -> [f64; 2] {
+pub fn subpixel_to_pixel_frame(
+    [subpixel_x, subpixel_y]: [Float; 2],
+    render_scale: Float,
+) -> [Float; 2] {
     [(subpixel_x / render_scale), (subpixel_y / render_scale)]
 }
 
@@ -78,15 +121,15 @@ pub fn generate_normalised_ray(
 
 pub fn section_pixel(
     tagged_pixel: TaggedPixel,
-    coordinate_0: f64,
-    coordinate_1: f64,
+    coordinate_0: Float,
+    coordinate_1: Float,
     axis_index: usize,
 ) -> Vec<TaggedPixel> {
-    assert!(coordinate_0 < ((tagged_pixel.index()[axis_index] + 1) as f64));
-    assert!(((tagged_pixel.index()[axis_index] + 1) as f64) < coordinate_1);
+    assert!(coordinate_0 < ((tagged_pixel.index()[axis_index] + 1) as Float));
+    assert!(((tagged_pixel.index()[axis_index] + 1) as Float) < coordinate_1);
 
     let tagged_pixel_index_1 = tagged_pixel.index();
-    let boundary = f64::ceil(coordinate_0);
+    let boundary = Float::ceil(coordinate_0);
 
     let old_length = coordinate_1 - coordinate_0;
 
@@ -97,7 +140,7 @@ pub fn section_pixel(
         old_blend_weight * blend_weight_1_ratio,
     );
 
-    let mut tagged_pixel_index_2 = tagged_pixel_index_1.clone();
+    let mut tagged_pixel_index_2 = tagged_pixel_index_1;
     tagged_pixel_index_2[axis_index] += 1;
     let blend_weight_2_ratio = (coordinate_1 - boundary) / old_length;
     let tagged_pixel_2 = TaggedPixel::new(
@@ -109,9 +152,8 @@ pub fn section_pixel(
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::approx_eq;
-
     use super::*;
+    use crate::utils::approx_eq;
 
     #[test]
     fn centre_pixel_offset() {
@@ -149,6 +191,15 @@ mod tests {
         approx_eq!(pixel_offset.1, -0.095);
     }
 
+    #[test]
+    fn pixels_in_a_region() {
+        let all: Vec<(usize, usize)> = pixels(3, 2, None).collect();
+        assert_eq!(all, vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]);
+        let region = Region::new(1, 1, Width(4), Height(4));
+        let some: Vec<(usize, usize)> = pixels(3, 2, Some(region)).collect();
+        assert_eq!(some, vec![(1, 1), (2, 1)]);
+    }
+
     #[test]
     fn section_pixels() {
         let tagged_pixel = TaggedPixel::new([0, 1], 0.5);
@@ -0,0 +1,264 @@
+use super::Native;
+use crate::collections::{Angle, Point};
+use crate::objects::Transform;
+use crate::scenes::raygen;
+use crate::scenes::raygen::{RayGenerator, TaggedPixel, TaggedRay};
+use crate::scenes::{Orientation, Region};
+use crate::utils::{Float, Random, SampleKey, Stream};
+
+// Where the samples within each pixel go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    // the centres of a square grid of cells; the sample count is rounded to
+    // the nearest square
+    Grid,
+    // a random point in each cell of the same grid
+    Jittered,
+    // exactly the sample count, each in its own row and column of a grid that
+    // many cells across (so any count spreads evenly along both axes), at a
+    // random point within its cell
+    Stratified,
+}
+
+impl Sampling {
+    // points within a pixel, in [0, 1) along each axis
+    pub fn positions(&self, samples: usize, random: &mut Random) -> Vec<[Float; 2]> {
+        let samples = samples.max(1);
+        match self {
+            Sampling::Grid | Sampling::Jittered => {
+                let side = ((samples as Float).sqrt().round() as usize).max(1);
+                (0..side * side)
+                    .map(|idx| {
+                        let [offset_x, offset_y] = match self {
+                            Sampling::Grid => [0.5, 0.5],
+                            _ => [random.next_f64(), random.next_f64()],
+                        };
+                        [
+                            ((idx % side) as Float + offset_x) / side as Float,
+                            ((idx / side) as Float + offset_y) / side as Float,
+                        ]
+                    })
+                    .collect()
+            }
+            Sampling::Stratified => {
+                // a shuffled column for each row
+                let mut columns: Vec<usize> = (0..samples).collect();
+                for idx in (1..samples).rev() {
+                    let swap = (random.next_u64() % (idx as u64 + 1)) as usize;
+                    columns.swap(idx, swap);
+                }
+                columns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(row, column)| {
+                        [
+                            (column as Float + random.next_f64()) / samples as Float,
+                            (row as Float + random.next_f64()) / samples as Float,
+                        ]
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// Several rays through each pixel, each weighted equally, to smooth the edges
+// a single ray through the centre leaves jagged. The random placements are
+// stable from one render to the next with the same seed and frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Supersampled {
+    samples: usize,
+    sampling: Sampling,
+    native: Native,
+    seed: u64,
+    frame: u64,
+}
+
+impl Supersampled {
+    pub fn new(
+        hsize: usize,
+        vsize: usize,
+        fov: Angle,
+        orientation: Orientation,
+        samples: usize,
+        sampling: Sampling,
+    ) -> Supersampled {
+        Supersampled {
+            samples,
+            sampling,
+            native: Native::new(hsize, vsize, fov, orientation),
+            seed: 0,
+            frame: 0,
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    pub fn native(&self) -> &Native {
+        &self.native
+    }
+}
+
+impl IntoIterator for Supersampled {
+    type Item = TaggedRay;
+    type IntoIter = SupersampledIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let native = &self.native;
+        let pixel_iterator = raygen::pixels(native.hsize(), native.vsize(), native.region());
+
+        SupersampledIterator {
+            pixel_iterator,
+            pending: vec![],
+            camera_to_world: self.native.frame_transformation().invert(),
+            supersampled: self,
+        }
+    }
+}
+
+impl RayGenerator for Supersampled {
+    fn canvas_size(&self) -> (usize, usize) {
+        self.native.canvas_size()
+    }
+
+    fn view_transformation(&self) -> &Transform {
+        self.native.view_transformation()
+    }
+
+    fn half_extents(&self) -> (Float, Float) {
+        self.native.half_extents()
+    }
+
+    fn reorient(self, orientation: Orientation) -> Supersampled {
+        Supersampled {
+            native: self.native.reorient(orientation),
+            ..self
+        }
+    }
+
+    fn restrict(self, region: Region) -> Supersampled {
+        Supersampled {
+            native: self.native.restrict(region),
+            ..self
+        }
+    }
+
+    fn reseed(self, seed: u64, frame: u64) -> Supersampled {
+        Supersampled {
+            seed,
+            frame,
+            ..self
+        }
+    }
+}
+
+pub struct SupersampledIterator {
+    pixel_iterator: Box<dyn Iterator<Item = (usize, usize)>>,
+    // the rest of the current pixel's rays, last first
+    pending: Vec<TaggedRay>,
+    camera_to_world: Transform,
+    supersampled: Supersampled,
+}
+
+impl Iterator for SupersampledIterator {
+    type Item = TaggedRay;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(tagged_ray) = self.pending.pop() {
+            return Some(tagged_ray);
+        }
+
+        let (pos_x, pos_y) = self.pixel_iterator.next()?;
+        let native = &self.supersampled.native;
+        let pixel = (pos_y * native.hsize() + pos_x) as u64;
+        let key = SampleKey::new(self.supersampled.seed, self.supersampled.frame, pixel, 0);
+        let mut random = key.random(Stream::Placement);
+        let positions = self
+            .supersampled
+            .sampling
+            .positions(self.supersampled.samples, &mut random);
+        let blend_weight = 1.0 / positions.len() as Float;
+
+        self.pending = positions
+            .into_iter()
+            .rev()
+            .map(|[within_x, within_y]| {
+                let target = Point::new(
+                    native.half_width() - (pos_x as Float + within_x) * native.pixel_size(),
+                    native.half_height() - (pos_y as Float + within_y) * native.pixel_size(),
+                    -1.0,
+                );
+                let ray =
+                    raygen::generate_normalised_ray(Point::zero(), target, &self.camera_to_world);
+                TaggedRay::new(ray, vec![TaggedPixel::new([pos_x, pos_y], blend_weight)])
+            })
+            .collect();
+        self.pending.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::consts::FRAC_PI_2;
+
+    #[test]
+    fn sample_positions() {
+        let mut random = Random::new(1);
+        assert_eq!(
+            Sampling::Grid.positions(5, &mut random),
+            vec![[0.25, 0.25], [0.75, 0.25], [0.25, 0.75], [0.75, 0.75]]
+        );
+
+        let jittered = Sampling::Jittered.positions(9, &mut random);
+        assert_eq!(jittered.len(), 9);
+        for (idx, [x, y]) in jittered.into_iter().enumerate() {
+            assert_eq!((x * 3.0) as usize, idx % 3);
+            assert_eq!((y * 3.0) as usize, idx / 3);
+        }
+
+        let stratified = Sampling::Stratified.positions(5, &mut random);
+        assert_eq!(stratified.len(), 5);
+        for axis in 0..2 {
+            let mut cells: Vec<usize> = stratified
+                .iter()
+                .map(|position| (position[axis] * 5.0) as usize)
+                .collect();
+            cells.sort();
+            assert_eq!(cells, vec![0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn rays_share_their_pixel() {
+        let supersampled = Supersampled::new(
+            3,
+            3,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::default(),
+            4,
+            Sampling::Grid,
+        );
+        let tagged_rays: Vec<TaggedRay> = supersampled.clone().into_iter().collect();
+        assert_eq!(tagged_rays.len(), 3 * 3 * 4);
+        for tagged_ray in &tagged_rays[..4] {
+            assert_eq!(tagged_ray.pixels(), &vec![TaggedPixel::new([0, 0], 0.25)]);
+        }
+        assert_eq!(tagged_rays[4].pixels()[0].index(), [0, 1]);
+
+        // the grid's rays surround the ray through the pixel's centre
+        let centre = supersampled.native().ray_for_pixel(1, 1).direction;
+        let mean = tagged_rays[16..20]
+            .iter()
+            .fold(crate::collections::Vector::zero(), |sum, tagged_ray| {
+                sum + tagged_ray.ray().direction
+            });
+        assert!((mean.normalise() - centre).magnitude() < crate::utils::EPSILON);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::scenes::{Height, Width};
+
+// A rectangle of pixels, from the top-left pixel at (column, row).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub column: usize,
+    pub row: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn new(column: usize, row: usize, Width(width): Width, Height(height): Height) -> Region {
+        Region {
+            column,
+            row,
+            width,
+            height,
+        }
+    }
+
+    // regions can come from elsewhere (see Coordinator), so their ends may
+    // not fit in a usize
+    pub fn contains(&self, [pos_x, pos_y]: [usize; 2]) -> bool {
+        pos_x >= self.column
+            && pos_x - self.column < self.width
+            && pos_y >= self.row
+            && pos_y - self.row < self.height
+    }
+
+    // whether the region lies entirely within a canvas of this size
+    pub fn fits(&self, Width(width): Width, Height(height): Height) -> bool {
+        self.column
+            .checked_add(self.width)
+            .is_some_and(|end| end <= width)
+            && self
+                .row
+                .checked_add(self.height)
+                .is_some_and(|end| end <= height)
+    }
+
+    // square tiles covering a canvas row by row, those on the right and
+    // bottom edges cut down to fit
+    pub fn tiles(Width(width): Width, Height(height): Height, tile_size: usize) -> Vec<Region> {
+        let tile_size = tile_size.max(1);
+        (0..height)
+            .step_by(tile_size)
+            .flat_map(|row| {
+                (0..width).step_by(tile_size).map(move |column| {
+                    Region::new(
+                        column,
+                        row,
+                        Width(tile_size.min(width - column)),
+                        Height(tile_size.min(height - row)),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_cover_canvas() {
+        let tiles = Region::tiles(Width(5), Height(3), 2);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[2], Region::new(4, 0, Width(1), Height(2)));
+        assert_eq!(tiles[5], Region::new(4, 2, Width(1), Height(1)));
+        for pos_y in 0..3 {
+            for pos_x in 0..5 {
+                let covering = tiles
+                    .iter()
+                    .filter(|tile| tile.contains([pos_x, pos_y]))
+                    .count();
+                assert_eq!(covering, 1);
+            }
+        }
+        assert!(tiles.iter().all(|tile| tile.fits(Width(5), Height(3))));
+        assert!(!Region::new(4, 0, Width(2), Height(1)).fits(Width(5), Height(3)));
+
+        let huge = Region::new(usize::MAX, 1, Width(usize::MAX), Height(1));
+        assert!(!huge.fits(Width(5), Height(3)));
+        assert!(huge.contains([usize::MAX, 1]));
+        assert!(!huge.contains([0, 1]));
+    }
+}
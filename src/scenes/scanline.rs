@@ -0,0 +1,256 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::scenes::*;
+use crate::utils::png::PngWriter;
+use crate::utils::Float;
+
+#[derive(Debug)]
+pub enum ScanlineError {
+    Io(io::Error),
+    Write(WriteError),
+    // finished before every row was written
+    Incomplete { written: usize, height: usize },
+}
+
+impl Display for ScanlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanlineError::Io(error) => write!(f, "could not write image: {error}"),
+            ScanlineError::Write(error) => write!(f, "rows don't fit the image: {error:?}"),
+            ScanlineError::Incomplete { written, height } => {
+                write!(
+                    f,
+                    "only {written} of the image's {height} rows were written"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ScanlineError {}
+
+impl From<io::Error> for ScanlineError {
+    fn from(error: io::Error) -> ScanlineError {
+        ScanlineError::Io(error)
+    }
+}
+
+impl From<WriteError> for ScanlineError {
+    fn from(error: WriteError) -> ScanlineError {
+        ScanlineError::Write(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanlineFormat {
+    // binary, P6
+    Ppm,
+    Png,
+}
+
+enum Encoder<W: Write> {
+    Ppm(W),
+    Png(PngWriter<W>),
+}
+
+// Writes an image a few rows at a time as they are rendered (see
+// Camera::render_streaming), so neither the whole canvas nor the whole
+// encoded file has to be held in memory. Rows are written with the writer's
+// encoding, tone mapping, exposure and bit depth, not those of the canvases
+// holding them.
+pub struct ScanlineWriter<W: Write> {
+    encoder: Encoder<W>,
+    width: usize,
+    height: usize,
+    rows_written: usize,
+    encoding: Encoding,
+    tone_mapping: ToneMapping,
+    exposure: Float,
+    bit_depth: BitDepth,
+}
+
+impl ScanlineWriter<BufWriter<File>> {
+    pub fn create(
+        path_string: &str,
+        format: ScanlineFormat,
+        width: Width,
+        height: Height,
+        bit_depth: BitDepth,
+    ) -> Result<ScanlineWriter<BufWriter<File>>, ScanlineError> {
+        ScanlineWriter::new(
+            BufWriter::new(File::create(path_string)?),
+            format,
+            width,
+            height,
+            bit_depth,
+        )
+    }
+}
+
+impl<W: Write> ScanlineWriter<W> {
+    pub fn new(
+        mut writer: W,
+        format: ScanlineFormat,
+        Width(width): Width,
+        Height(height): Height,
+        bit_depth: BitDepth,
+    ) -> Result<ScanlineWriter<W>, ScanlineError> {
+        let encoder = match format {
+            ScanlineFormat::Ppm => {
+                PpmFormat::Binary.write_header(&mut writer, width, height, bit_depth)?;
+                Encoder::Ppm(writer)
+            }
+            ScanlineFormat::Png => {
+                let bits = match bit_depth {
+                    BitDepth::Eight => 8,
+                    BitDepth::Sixteen => 16,
+                };
+                Encoder::Png(PngWriter::new(writer, width, height, bits)?)
+            }
+        };
+        Ok(ScanlineWriter {
+            encoder,
+            width,
+            height,
+            rows_written: 0,
+            encoding: Encoding::default(),
+            tone_mapping: ToneMapping::default(),
+            exposure: 0.0,
+            bit_depth,
+        })
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    pub fn set_exposure(&mut self, exposure: Float) {
+        self.exposure = exposure;
+    }
+
+    pub fn rows_written(&self) -> usize {
+        self.rows_written
+    }
+
+    // the next rows of the image, top to bottom; the canvas must be as wide
+    // as the image
+    pub fn write_rows(&mut self, rows: &Canvas) -> Result<(), ScanlineError> {
+        if rows.width() != self.width || self.rows_written + rows.height() > self.height {
+            return Err(WriteError::OutOfBounds.into());
+        }
+        let bytes_as = |with_alpha| {
+            rows.bytes_as(
+                self.encoding,
+                self.tone_mapping,
+                self.exposure,
+                self.bit_depth,
+                with_alpha,
+            )
+        };
+        match &mut self.encoder {
+            Encoder::Ppm(writer) => writer.write_all(&bytes_as(false))?,
+            Encoder::Png(writer) => {
+                let rgba = bytes_as(true);
+                for row in rgba.chunks((rgba.len() / rows.height().max(1)).max(1)) {
+                    writer.write_row(row)?;
+                }
+            }
+        }
+        self.rows_written += rows.height();
+        Ok(())
+    }
+
+    // once every row has been written
+    pub fn finish(self) -> Result<W, ScanlineError> {
+        if self.rows_written != self.height {
+            return Err(ScanlineError::Incomplete {
+                written: self.rows_written,
+                height: self.height,
+            });
+        }
+        let mut writer = match self.encoder {
+            Encoder::Ppm(writer) => writer,
+            Encoder::Png(writer) => writer.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Colour;
+
+    #[test]
+    fn streamed_images_match_whole_canvases() {
+        let mut canvas = Canvas::new(Width(3), Height(5));
+        for row in 0..5 {
+            for column in 0..3 {
+                let colour = Colour::new(column as Float / 2.0, row as Float / 4.0, 0.5);
+                canvas.paint_colour_replace(column, row, colour).unwrap();
+            }
+        }
+        canvas.set_tone_mapping(ToneMapping::Reinhard);
+
+        for (format, bit_depth) in [
+            (ScanlineFormat::Ppm, BitDepth::Eight),
+            (ScanlineFormat::Ppm, BitDepth::Sixteen),
+            (ScanlineFormat::Png, BitDepth::Eight),
+            (ScanlineFormat::Png, BitDepth::Sixteen),
+        ] {
+            canvas.set_bit_depth(bit_depth);
+            let mut writer =
+                ScanlineWriter::new(Vec::new(), format, Width(3), Height(5), bit_depth).unwrap();
+            writer.set_tone_mapping(ToneMapping::Reinhard);
+            for (row, height) in [(0, 2), (2, 2), (4, 1)] {
+                let band = canvas.crop(0, row, Width(3), Height(height)).unwrap();
+                writer.write_rows(&band).unwrap();
+            }
+            let streamed = writer.finish().unwrap();
+            let whole = match format {
+                ScanlineFormat::Ppm => canvas.write_to_ppm(PpmFormat::Binary).unwrap(),
+                ScanlineFormat::Png => canvas.write_to_png(),
+            };
+            assert_eq!(streamed, whole);
+        }
+    }
+
+    #[test]
+    fn rows_must_fit_and_complete_the_image() {
+        let mut writer = ScanlineWriter::new(
+            Vec::new(),
+            ScanlineFormat::Png,
+            Width(2),
+            Height(2),
+            BitDepth::Eight,
+        )
+        .unwrap();
+        let wrong_width = Canvas::new(Width(3), Height(1));
+        assert!(matches!(
+            writer.write_rows(&wrong_width),
+            Err(ScanlineError::Write(WriteError::OutOfBounds))
+        ));
+        writer
+            .write_rows(&Canvas::new(Width(2), Height(1)))
+            .unwrap();
+        assert!(writer
+            .write_rows(&Canvas::new(Width(2), Height(2)))
+            .is_err());
+        assert_eq!(writer.rows_written(), 1);
+        assert!(matches!(
+            writer.finish(),
+            Err(ScanlineError::Incomplete {
+                written: 1,
+                height: 2
+            })
+        ));
+    }
+}
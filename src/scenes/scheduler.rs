@@ -0,0 +1,160 @@
+use crate::collections::Colour;
+use crate::objects::Ray;
+use crate::scenes::{count, counting, Counter, PrimaryHits, RenderStats, TaggedRay, World};
+use crate::utils::SampleKey;
+
+// How many rays are cast together: small enough that an expensive part of
+// the image is spread over many tiles, large enough that handing them out
+// costs little.
+pub(crate) const TILE_RAYS: usize = 256;
+
+pub(crate) type Tile = Vec<(TaggedRay, Ray, SampleKey)>;
+
+pub(crate) struct CastTile {
+    pub(crate) rays: Vec<(TaggedRay, Colour)>,
+    // the work done casting them, if it was counted
+    pub(crate) stats: RenderStats,
+}
+
+// Casts every ray in each tile, handing the tiles to finish in the order
+// they came. A tile may come with the primitives its rays first hit, found
+// before casting (see World::cast_samples_among). With the parallel feature,
+// tiles are taken a batch at a time (a few for each thread, so that a slow
+// tile is spread among quick ones) and cast with rayon, which keeps them in
+// order and passes on any panic; finish runs on the calling thread. The work
+// done is only counted if asked for.
+pub(crate) fn cast_tiles<I, F, E>(
+    world: &World,
+    tiles: I,
+    count_stats: bool,
+    mut finish: F,
+) -> Result<(), E>
+where
+    I: Iterator<Item = (Tile, Option<PrimaryHits>)>,
+    F: FnMut(CastTile) -> Result<(), E>,
+{
+    #[cfg(not(feature = "parallel"))]
+    {
+        for tile in tiles {
+            finish(cast_tile(world, tile, count_stats))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        let batch_size = rayon::current_num_threads() * 4;
+        let mut tiles = tiles.peekable();
+        while tiles.peek().is_some() {
+            let batch: Vec<(Tile, Option<PrimaryHits>)> = tiles.by_ref().take(batch_size).collect();
+            let cast: Vec<CastTile> = batch
+                .into_par_iter()
+                .map(|tile| cast_tile(world, tile, count_stats))
+                .collect();
+            for cast_tile in cast {
+                finish(cast_tile)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// neighbouring rays in a tile are cast together; see RayPacket
+fn cast_tile(
+    world: &World,
+    (tile, primary_hits): (Tile, Option<PrimaryHits>),
+    count_stats: bool,
+) -> CastTile {
+    let (tagged_rays, samples): (Vec<TaggedRay>, Vec<(Ray, SampleKey)>) = tile
+        .into_iter()
+        .map(|(tagged_ray, ray, key)| (tagged_ray, (ray, key)))
+        .unzip();
+    let (colours, stats) = counting(count_stats, || {
+        samples.iter().for_each(|_| count(Counter::CameraRay));
+        match &primary_hits {
+            Some(primary_hits) => world.cast_samples_among(&samples, primary_hits),
+            None => world.cast_samples(&samples),
+        }
+    });
+    CastTile {
+        rays: tagged_rays.into_iter().zip(colours).collect(),
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::{Point, Vector};
+    use crate::objects::*;
+    use crate::utils::{BuildInto, Buildable, Float};
+
+    #[test]
+    fn tiles_finish_in_order() {
+        let world = World::test_world();
+        let tiles = (0..20).map(|idx_tile| {
+            let tile = (0..idx_tile % 4 + 1)
+                .map(|idx_ray| {
+                    let ray = Ray::new(
+                        Point::new(idx_ray as Float * 0.1, 0.0, -5.0),
+                        Vector::new(0.0, 0.0, 1.0),
+                    );
+                    (TaggedRay::new(ray, vec![]), ray, SampleKey::default())
+                })
+                .collect();
+            (tile, None)
+        });
+
+        let mut finished = vec![];
+        cast_tiles(&world, tiles, true, |cast_tile| {
+            finished.push(cast_tile.rays.len());
+            assert_eq!(cast_tile.stats.camera_rays, cast_tile.rays.len() as u64);
+            for (tagged_ray, colour) in cast_tile.rays {
+                let key = SampleKey::default();
+                assert_eq!(colour, world.cast_sample(tagged_ray.ray(), key));
+            }
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        let expected: Vec<usize> = (0..20).map(|idx_tile| idx_tile % 4 + 1).collect();
+        assert_eq!(finished, expected);
+
+        let stopped = cast_tiles(&world, (0..5).map(|_| (vec![], None)), false, |_| {
+            Err("stop")
+        });
+        assert_eq!(stopped, Err("stop"));
+    }
+
+    // a pattern that fails, as a bug might, while its ray is being cast
+    #[derive(Clone, Debug)]
+    struct Broken(Transform);
+
+    impl Pattern for Broken {
+        fn frame_transformation(&self) -> &Transform {
+            &self.0
+        }
+
+        fn local_colour_at(&self, _pattern_point: Point) -> Colour {
+            panic!("broken pattern");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "broken pattern")]
+    fn panics_reach_the_caller() {
+        let sphere = Sphere::builder()
+            .set_material(Material {
+                pattern: Box::new(Broken(Transform::default())),
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let tile = vec![(TaggedRay::new(ray, vec![]), ray, SampleKey::default())];
+        let tiles = (0..100).map(|_| (tile.clone(), None));
+        cast_tiles(&world, tiles, false, |_| Ok::<(), ()>(())).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+use crate::utils::Float;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShadingMode {
+    #[default]
+    Phong,
+    // diffuse lighting quantised into the given number of flat bands, with no
+    // specular highlights, and inked silhouettes where the surface turns away
+    // from the eye, i.e. where the cosine between the normal and the eye
+    // vector is below the silhouette threshold (0 for no silhouettes)
+    Toon {
+        bands: usize,
+        silhouette: Float,
+    },
+}
+
+impl ShadingMode {
+    pub fn toon(bands: usize) -> ShadingMode {
+        ShadingMode::Toon {
+            bands,
+            silhouette: 0.0,
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::cell::Cell;
+use std::ops::{Add, Sub};
+use std::sync::{Arc, Mutex};
+
+// Counts of the work done in rendering, to see where the time goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub camera_rays: u64,
+    pub shadow_rays: u64,
+    pub reflection_rays: u64,
+    pub refraction_rays: u64,
+    // diffuse bounces of paths (see PathTracing)
+    pub path_rays: u64,
+    // rays tested against a primitive, whether or not they hit it
+    pub intersection_tests: u64,
+    // nodes of kd-trees (see KdTree) a ray passes through
+    pub kd_tree_visits: u64,
+    // hits shaded by the lighting model
+    pub shading_evaluations: u64,
+}
+
+impl RenderStats {
+    fn fields(self) -> [u64; 8] {
+        [
+            self.camera_rays,
+            self.shadow_rays,
+            self.reflection_rays,
+            self.refraction_rays,
+            self.path_rays,
+            self.intersection_tests,
+            self.kd_tree_visits,
+            self.shading_evaluations,
+        ]
+    }
+
+    fn from_fields(fields: [u64; 8]) -> RenderStats {
+        let [camera_rays, shadow_rays, reflection_rays, refraction_rays, path_rays, intersection_tests, kd_tree_visits, shading_evaluations] =
+            fields;
+        RenderStats {
+            camera_rays,
+            shadow_rays,
+            reflection_rays,
+            refraction_rays,
+            path_rays,
+            intersection_tests,
+            kd_tree_visits,
+            shading_evaluations,
+        }
+    }
+
+    pub fn rays(&self) -> u64 {
+        self.camera_rays
+            + self.shadow_rays
+            + self.reflection_rays
+            + self.refraction_rays
+            + self.path_rays
+    }
+}
+
+impl Add for RenderStats {
+    type Output = RenderStats;
+
+    fn add(self, other: RenderStats) -> RenderStats {
+        let [a, b] = [self.fields(), other.fields()];
+        RenderStats::from_fields(std::array::from_fn(|idx| a[idx] + b[idx]))
+    }
+}
+
+impl Sub for RenderStats {
+    type Output = RenderStats;
+
+    fn sub(self, other: RenderStats) -> RenderStats {
+        let [a, b] = [self.fields(), other.fields()];
+        RenderStats::from_fields(std::array::from_fn(|idx| a[idx] - b[idx]))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Counter {
+    CameraRay,
+    ShadowRay,
+    ReflectionRay,
+    RefractionRay,
+    PathRay,
+    IntersectionTest,
+    KdTreeVisit,
+    ShadingEvaluation,
+}
+
+// Each thread counts its own work, so tiles (which each run on one thread)
+// can tell exactly what they did. Nothing is counted outside of counting, so
+// renders without stats pay only for a check that it is off.
+thread_local! {
+    static COUNTS: Cell<Option<RenderStats>> = const { Cell::new(None) };
+}
+
+pub(crate) fn count(counter: Counter) {
+    COUNTS.with(|counts| {
+        let Some(mut stats) = counts.get() else {
+            return;
+        };
+        let field = match counter {
+            Counter::CameraRay => &mut stats.camera_rays,
+            Counter::ShadowRay => &mut stats.shadow_rays,
+            Counter::ReflectionRay => &mut stats.reflection_rays,
+            Counter::RefractionRay => &mut stats.refraction_rays,
+            Counter::PathRay => &mut stats.path_rays,
+            Counter::IntersectionTest => &mut stats.intersection_tests,
+            Counter::KdTreeVisit => &mut stats.kd_tree_visits,
+            Counter::ShadingEvaluation => &mut stats.shading_evaluations,
+        };
+        *field += 1;
+        counts.set(Some(stats));
+    });
+}
+
+// Runs work, counting what it does on this thread if enabled (otherwise the
+// stats are all zero). Counts nest: one running around another includes the
+// work counted by the inner one.
+pub(crate) fn counting<T>(enabled: bool, work: impl FnOnce() -> T) -> (T, RenderStats) {
+    if !enabled {
+        return (work(), RenderStats::default());
+    }
+    let counting = Counting {
+        outer: COUNTS.with(|counts| counts.replace(Some(RenderStats::default()))),
+    };
+    let result = work();
+    let stats = COUNTS.with(Cell::get).unwrap_or_default();
+    drop(counting);
+    (result, stats)
+}
+
+// puts back the count that was running, even if the work panics
+struct Counting {
+    outer: Option<RenderStats>,
+}
+
+impl Drop for Counting {
+    fn drop(&mut self) {
+        COUNTS.with(|counts| {
+            let inner = counts.get().unwrap_or_default();
+            counts.set(self.outer.map(|outer| outer + inner));
+        });
+    }
+}
+
+// of one render
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsReport {
+    pub total: RenderStats,
+    // one for each tile of rays (see Camera::with_stats), in order
+    pub tiles: Vec<RenderStats>,
+}
+
+// Collects the stats of renders from a camera holding a clone of it (see
+// Camera::with_stats); clones share the same report, which each render
+// starts afresh.
+#[derive(Clone, Debug, Default)]
+pub struct StatsRecorder {
+    report: Arc<Mutex<StatsReport>>,
+}
+
+impl StatsRecorder {
+    pub fn new() -> StatsRecorder {
+        StatsRecorder::default()
+    }
+
+    // what has been recorded so far, of the latest render
+    pub fn report(&self) -> StatsReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    pub(crate) fn start_render(&self) {
+        *self.report.lock().unwrap() = StatsReport::default();
+    }
+
+    pub(crate) fn record_tile(&self, stats: RenderStats) {
+        let mut report = self.report.lock().unwrap();
+        report.total = report.total + stats;
+        report.tiles.push(stats);
+    }
+}
+
+// the same report, not just the same stats
+impl PartialEq for StatsRecorder {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.report, &other.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn threads_count_separately() {
+        let ((), stats) = counting(true, || {
+            count(Counter::ShadowRay);
+            let ((), inner) = counting(true, || count(Counter::ShadowRay));
+            assert_eq!(inner.shadow_rays, 1);
+            count(Counter::IntersectionTest);
+            thread::spawn(|| count(Counter::ShadowRay)).join().unwrap();
+        });
+        assert_eq!(stats.shadow_rays, 2);
+        assert_eq!(stats.intersection_tests, 1);
+        assert_eq!(stats.rays(), 2);
+    }
+
+    #[test]
+    fn counting_is_opt_in() {
+        count(Counter::CameraRay);
+        let ((), stats) = counting(false, || count(Counter::CameraRay));
+        assert_eq!(stats, RenderStats::default());
+        assert_eq!(COUNTS.with(Cell::get), None);
+    }
+
+    #[test]
+    fn recorders_total_their_tiles() {
+        let recorder = StatsRecorder::new();
+        let tile = RenderStats {
+            camera_rays: 3,
+            shading_evaluations: 2,
+            ..RenderStats::default()
+        };
+        recorder.clone().record_tile(tile);
+        recorder.record_tile(tile);
+
+        let report = recorder.report();
+        assert_eq!(report.tiles, vec![tile, tile]);
+        assert_eq!(report.total.camera_rays, 6);
+        assert_eq!(report.total.shading_evaluations, 4);
+
+        recorder.start_render();
+        assert_eq!(recorder.report(), StatsReport::default());
+    }
+}
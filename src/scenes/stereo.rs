@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::collections::{Colour, Point};
+use crate::objects::{Transform, Transformable};
+use crate::scenes::{Canvas, Height, Orientation, OrientationError, Width, WriteError};
+use crate::utils::Float;
+
+#[derive(Debug)]
+pub enum StereoError {
+    // the eyes must converge ahead of the camera, or at infinity
+    Convergence(Float),
+    Orientation(OrientationError),
+    Write(WriteError),
+}
+
+impl Display for StereoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StereoError::Convergence(distance) => {
+                write!(f, "stereo convergence must be positive, not {distance}")
+            }
+            StereoError::Orientation(error) => write!(f, "invalid stereo eye: {error}"),
+            StereoError::Write(error) => write!(f, "could not composite eyes: {error}"),
+        }
+    }
+}
+
+impl Error for StereoError {}
+
+impl From<OrientationError> for StereoError {
+    fn from(error: OrientationError) -> StereoError {
+        StereoError::Orientation(error)
+    }
+}
+
+impl From<WriteError> for StereoError {
+    fn from(error: WriteError) -> StereoError {
+        StereoError::Write(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    // the left eye's image on the left, twice as wide as either
+    SideBySide,
+    // red from the left eye, green and blue from the right, for red-cyan
+    // glasses
+    Anaglyph,
+}
+
+// Two eyes either side of the camera, interocular apart, both turned in
+// towards a point convergence along the camera's view direction; objects at
+// that distance appear at the depth of the screen. An infinite convergence
+// keeps the eyes parallel, so everything appears in front of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stereo {
+    pub interocular: Float,
+    pub convergence: Float,
+    pub layout: StereoLayout,
+}
+
+impl Stereo {
+    pub fn new(interocular: Float, convergence: Float, layout: StereoLayout) -> Stereo {
+        Stereo {
+            interocular,
+            convergence,
+            layout,
+        }
+    }
+
+    // left then right, for a camera with this view transformation
+    pub fn eyes(&self, view_transformation: &Transform) -> Result<[Orientation; 2], StereoError> {
+        if self.convergence.is_nan() || self.convergence <= 0.0 {
+            return Err(StereoError::Convergence(self.convergence));
+        }
+        let camera_to_world = view_transformation.invert();
+        let from = Point::zero().transform(&camera_to_world);
+        let forward = (Point::new(0.0, 0.0, -1.0).transform(&camera_to_world) - from).normalise();
+        let up = Point::new(0.0, 1.0, 0.0).transform(&camera_to_world) - from;
+        // camera space +x is towards the left of the image
+        let left = (Point::new(1.0, 0.0, 0.0).transform(&camera_to_world) - from).normalise();
+
+        let offset = left * (self.interocular / 2.0);
+        let eye = |position: Point| {
+            let to = if self.convergence == Float::INFINITY {
+                position + forward
+            } else {
+                from + forward * self.convergence
+            };
+            Orientation::try_new(position, to, up)
+        };
+        Ok([eye(from + offset)?, eye(from - offset)?])
+    }
+
+    pub fn composite(&self, left: &Canvas, right: &Canvas) -> Result<Canvas, WriteError> {
+        let (width, height) = (left.width(), left.height());
+        if right.width() != width || right.height() != height {
+            return Err(WriteError::OutOfBounds);
+        }
+
+        let mut canvas = match self.layout {
+            StereoLayout::SideBySide => Canvas::new(Width(width * 2), Height(height)),
+            StereoLayout::Anaglyph => Canvas::new(Width(width), Height(height)),
+        };
+        for row in 0..height {
+            for column in 0..width {
+                let left_colour = left[[column, row]].colour();
+                let right_colour = right[[column, row]].colour();
+                match self.layout {
+                    StereoLayout::SideBySide => {
+                        canvas.paint_colour_replace(column, row, left_colour)?;
+                        canvas.paint_colour_replace(column + width, row, right_colour)?;
+                    }
+                    StereoLayout::Anaglyph => {
+                        let colour =
+                            Colour::new(left_colour.red, right_colour.green, right_colour.blue);
+                        canvas.paint_colour_replace(column, row, colour)?;
+                    }
+                }
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Vector;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn eyes_converge_ahead() {
+        let stereo = Stereo::new(0.2, 5.0, StereoLayout::SideBySide);
+        let orientation = Orientation::new(
+            Point::new(0.0, 0.0, -5.0),
+            Point::zero(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let [left, right] = stereo.eyes(&orientation.0).unwrap();
+
+        let eye_position = |eye: &Orientation| Point::zero().transform(&eye.0.invert());
+        // looking along +z, the viewer's left is -x
+        approx_eq!(eye_position(&left).x, -0.1);
+        approx_eq!(eye_position(&right).x, 0.1);
+        // both look at the convergence point
+        for eye in [&left, &right] {
+            let ahead = Point::zero().transform(&eye.0);
+            approx_eq!(ahead.x, 0.0);
+            approx_eq!(ahead.y, 0.0);
+        }
+    }
+
+    #[test]
+    fn parallel_or_degenerate_eyes() {
+        let view = Orientation::new(
+            Point::new(0.0, 0.0, -5.0),
+            Point::zero(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let parallel = Stereo::new(0.2, Float::INFINITY, StereoLayout::Anaglyph);
+        let [left, right] = parallel.eyes(&view.0).unwrap();
+        // each eye looks straight ahead of itself, so the point the camera
+        // looks at stays off to one side
+        let target = |eye: &Orientation| Point::zero().transform(&eye.0);
+        approx_eq!(target(&left).x.abs(), 0.1);
+        approx_eq!(target(&left).x, -target(&right).x);
+        approx_eq!(target(&left).y, 0.0);
+        approx_eq!(target(&left).z, -5.0);
+
+        for convergence in [0.0, -1.0, Float::NAN] {
+            let stereo = Stereo::new(0.2, convergence, StereoLayout::Anaglyph);
+            assert!(matches!(
+                stereo.eyes(&view.0),
+                Err(StereoError::Convergence(_))
+            ));
+        }
+        // the camera's own orientation is checked too, not unwrapped
+        let stereo = Stereo::new(0.0, 1e-9, StereoLayout::Anaglyph);
+        assert!(matches!(
+            stereo.eyes(&view.0),
+            Err(StereoError::Orientation(_))
+        ));
+    }
+
+    #[test]
+    fn composite_layouts() {
+        let mut left = Canvas::new(Width(2), Height(1));
+        let mut right = Canvas::new(Width(2), Height(1));
+        left.paint_colour_replace(0, 0, Colour::new(1.0, 1.0, 1.0))
+            .unwrap();
+        right
+            .paint_colour_replace(0, 0, Colour::new(0.5, 0.5, 0.5))
+            .unwrap();
+
+        let side_by_side = Stereo::new(0.1, 1.0, StereoLayout::SideBySide)
+            .composite(&left, &right)
+            .unwrap();
+        assert_eq!(side_by_side.width(), 4);
+        assert_eq!(side_by_side[[0, 0]].colour(), Colour::new(1.0, 1.0, 1.0));
+        assert_eq!(side_by_side[[2, 0]].colour(), Colour::new(0.5, 0.5, 0.5));
+
+        let anaglyph = Stereo::new(0.1, 1.0, StereoLayout::Anaglyph)
+            .composite(&left, &right)
+            .unwrap();
+        assert_eq!(anaglyph.width(), 2);
+        assert_eq!(anaglyph[[0, 0]].colour(), Colour::new(1.0, 0.5, 0.5));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::collections::Colour;
+use crate::utils::Float;
+
+// How linear colours brighter than white are brought into range when a
+// canvas is written to an 8-bit format (see Canvas::set_tone_mapping), after
+// scaling by the exposure. Float formats (EXR and HDR) are written as they
+// are, to be tone mapped elsewhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapping {
+    // each channel clipped to 1, so bright colours wash out to white
+    #[default]
+    Clamp,
+    // Reinhard's operator on luminance, which keeps hues (unlike clipping
+    // each channel) while compressing highlights into range
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, per channel: a toe that
+    // deepens shadows and a shoulder that rolls highlights off to white
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn map(self, colour: Colour) -> Colour {
+        match self {
+            ToneMapping::Clamp => colour,
+            ToneMapping::Reinhard => {
+                let luminance = 0.2126 * colour.red + 0.7152 * colour.green + 0.0722 * colour.blue;
+                if luminance <= 0.0 {
+                    return Colour::new(0.0, 0.0, 0.0);
+                }
+                colour * (1.0 / (1.0 + luminance))
+            }
+            ToneMapping::Aces => {
+                let curve = |value: Float| {
+                    let value = value.max(0.0);
+                    (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14)
+                };
+                Colour::new(curve(colour.red), curve(colour.green), curve(colour.blue))
+            }
+        }
+    }
+}
+
+// scales colours by 2 to the power of exposure, i.e. exposure is in stops
+pub(crate) fn expose(colour: Colour, exposure: Float) -> Colour {
+    if exposure == 0.0 {
+        return colour;
+    }
+    colour * Float::powf(2.0, exposure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn reinhard_keeps_hue() {
+        let mapped = ToneMapping::Reinhard.map(Colour::new(1.5, 0.75, 0.0));
+        approx_eq!(mapped.red / mapped.green, 2.0);
+        assert!(mapped.red < 1.0);
+        assert_eq!(
+            ToneMapping::Reinhard.map(Colour::new(0.0, 0.0, 0.0)),
+            Colour::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn aces_rolls_off_highlights() {
+        let curve = |value| ToneMapping::Aces.map(Colour::new(value, 0.0, -1.0));
+        assert_eq!(curve(0.0), Colour::new(0.0, 0.0, 0.0));
+        assert!(curve(0.01).red < 0.01);
+        assert!(curve(1.0).red < curve(4.0).red);
+        assert!(curve(100.0).red > 0.99 && curve(100.0).red < 1.05);
+    }
+
+    #[test]
+    fn exposure_is_in_stops() {
+        let colour = Colour::new(0.25, 0.5, 1.0);
+        assert_eq!(expose(colour, 1.0), Colour::new(0.5, 1.0, 2.0));
+        assert_eq!(expose(colour, -2.0), Colour::new(0.0625, 0.125, 0.25));
+        assert_eq!(expose(colour, 0.0), colour);
+    }
+}
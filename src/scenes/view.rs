@@ -1,13 +1,93 @@
-use crate::collections::{Matrix, Point, Vector};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::time::Instant;
+
+use crate::collections::{Angle, Colour, Matrix, Point, Vector};
 use crate::objects::*;
 use crate::scenes::*;
+use crate::utils::{Buildable, ConsumingBuilder, Float, SampleKey, Stream, EPSILON};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Orientation(pub Transform);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrientationError {
+    NonFinite,
+    ZeroLengthDirection,
+    ZeroLengthUp,
+    CollinearUp,
+}
+
+impl Display for OrientationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrientationError::NonFinite => {
+                write!(
+                    f,
+                    "camera from, to and up must not contain NaN or infinite values"
+                )
+            }
+            OrientationError::ZeroLengthDirection => {
+                write!(
+                    f,
+                    "camera from and to points are the same, so there is no view direction"
+                )
+            }
+            OrientationError::ZeroLengthUp => write!(f, "camera up vector has zero length"),
+            OrientationError::CollinearUp => write!(
+                f,
+                "camera up vector is parallel to the view direction, so the roll is undefined"
+            ),
+        }
+    }
+}
+
+impl Error for OrientationError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FramingError {
+    NothingToFrame,
+}
+
+impl Display for FramingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FramingError::NothingToFrame => {
+                write!(f, "world has no objects with finite bounds to frame")
+            }
+        }
+    }
+}
+
+impl Error for FramingError {}
+
 impl Orientation {
+    // panics with a descriptive message on degenerate configurations; use
+    // try_new to handle these instead
     pub fn new(from: Point, to: Point, up: Vector) -> Orientation {
-        Orientation(Orientation::view_transform(from, to, up))
+        Orientation::try_new(from, to, up).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(from: Point, to: Point, up: Vector) -> Result<Orientation, OrientationError> {
+        let components = [from.x, from.y, from.z, to.x, to.y, to.z, up.x, up.y, up.z];
+        if components.iter().any(|component| !component.is_finite()) {
+            return Err(OrientationError::NonFinite);
+        }
+
+        let direction = to - from;
+        if direction.magnitude() < EPSILON {
+            return Err(OrientationError::ZeroLengthDirection);
+        }
+        if up.magnitude() < EPSILON {
+            return Err(OrientationError::ZeroLengthUp);
+        }
+        if direction.normalise().cross(up.normalise()).magnitude() < EPSILON {
+            return Err(OrientationError::CollinearUp);
+        }
+
+        Ok(Orientation(Orientation::view_transform(from, to, up)))
     }
 
     pub fn frame_transformation(&self) -> &Transform {
@@ -48,27 +128,737 @@ impl Default for Orientation {
     }
 }
 
+// How long the camera's shutter is open, in the same time as Moving
+// keyframes. Each ray is cast at a random time in the interval, over samples
+// passes that each cover an equal part of it, so objects moving while the
+// shutter is open blur. A path of orientations spread evenly across the
+// interval moves the camera too; each pass looks from the one nearest its
+// part of the interval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shutter {
+    pub open: Float,
+    pub close: Float,
+    pub samples: usize,
+    pub path: Vec<Orientation>,
+}
+
+impl Shutter {
+    pub fn new(open: Float, close: Float, samples: usize) -> Shutter {
+        Shutter {
+            open,
+            close,
+            samples,
+            path: vec![],
+        }
+    }
+
+    pub fn with_path(self, path: Vec<Orientation>) -> Shutter {
+        Shutter { path, ..self }
+    }
+
+    // the time for a ray in a pass, stable for each sample
+    fn time(&self, idx_pass: usize, key: SampleKey) -> Float {
+        let jitter = key.random(Stream::Shutter).next_f64();
+        let fraction = (idx_pass as Float + jitter) / self.samples.max(1) as Float;
+        self.open + (self.close - self.open) * fraction
+    }
+
+    fn orientation(&self, idx_pass: usize) -> Option<&Orientation> {
+        let last = self.path.len().checked_sub(1)?;
+        let fraction = (idx_pass as Float + 0.5) / self.samples.max(1) as Float;
+        self.path.get((fraction * last as Float).round() as usize)
+    }
+}
+
+// Extra rays only where they are needed: after one ray through each pixel,
+// pixels differing from a neighbour by more than threshold in any channel
+// are rendered again with samples rays, placed as sampling places them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    pub threshold: Float,
+    pub samples: usize,
+    pub sampling: Sampling,
+}
+
+impl AdaptiveSampling {
+    pub fn new(threshold: Float, samples: usize, sampling: Sampling) -> AdaptiveSampling {
+        AdaptiveSampling {
+            threshold,
+            samples,
+            sampling,
+        }
+    }
+
+    // the pixels that differ too much from a neighbour
+    fn refine(&self, image: &Canvas) -> Vec<[usize; 2]> {
+        let (width, height) = (image.width(), image.height());
+        let differs = |[pos_x, pos_y]: [usize; 2], [other_x, other_y]: [usize; 2]| {
+            let difference = image[[pos_x, pos_y]].colour() - image[[other_x, other_y]].colour();
+            [difference.red, difference.green, difference.blue]
+                .iter()
+                .any(|channel| channel.abs() > self.threshold)
+        };
+        (0..height)
+            .flat_map(|pos_y| (0..width).map(move |pos_x| [pos_x, pos_y]))
+            .filter(|&[pos_x, pos_y]| {
+                let neighbours = [
+                    (pos_x > 0).then(|| [pos_x - 1, pos_y]),
+                    (pos_x + 1 < width).then_some([pos_x + 1, pos_y]),
+                    (pos_y > 0).then(|| [pos_x, pos_y - 1]),
+                    (pos_y + 1 < height).then_some([pos_x, pos_y + 1]),
+                ];
+                neighbours
+                    .into_iter()
+                    .flatten()
+                    .any(|neighbour| differs([pos_x, pos_y], neighbour))
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Camera<R: RayGenerator> {
     ray_generator: R,
+    shutter: Option<Shutter>,
+    seed: u64,
+    frame: u64,
+    cancellation: Option<Cancellation>,
+    stats: Option<StatsRecorder>,
+    #[cfg(feature = "gpu")]
+    gpu: Option<Gpu>,
+}
+
+// Keys each camera ray of a render as a sample of the pixel it contributes
+// most to, numbering each pixel's samples in the order its rays come. Only
+// the pixels in its region are counted, so every ray keyed to them must come
+// to it.
+struct SampleCounter {
+    seed: u64,
+    frame: u64,
+    hsize: usize,
+    region: Region,
+    counts: Vec<u64>,
+}
+
+impl SampleCounter {
+    fn new(seed: u64, frame: u64, hsize: usize, region: Region) -> SampleCounter {
+        SampleCounter {
+            seed,
+            frame,
+            hsize,
+            region,
+            counts: vec![0; region.width * region.height],
+        }
+    }
+
+    fn key(&mut self, tagged_ray: &TaggedRay) -> SampleKey {
+        let [pos_x, pos_y] = tagged_ray
+            .pixels()
+            .iter()
+            .max_by(|a, b| a.blend_weight().total_cmp(&b.blend_weight()))
+            .map_or([0, 0], TaggedPixel::index);
+        let sample = if self.region.contains([pos_x, pos_y]) {
+            let count = &mut self.counts
+                [(pos_y - self.region.row) * self.region.width + pos_x - self.region.column];
+            *count += 1;
+            *count - 1
+        } else {
+            0
+        };
+        let pixel = pos_y * self.hsize + pos_x;
+        SampleKey::new(self.seed, self.frame, pixel as u64, sample)
+    }
 }
 
 impl<R: RayGenerator> Camera<R> {
     pub fn new(ray_generator: R) -> Camera<R> {
-        Camera { ray_generator }
+        Camera {
+            ray_generator,
+            shutter: None,
+            seed: 0,
+            frame: 0,
+            cancellation: None,
+            stats: None,
+            #[cfg(feature = "gpu")]
+            gpu: None,
+        }
+    }
+
+    // Every random choice in rendering (where samples go within pixels, when
+    // the shutter is open for them, which way paths bounce) is keyed by the
+    // seed, the frame, and the pixel and sample it is made for, so the same
+    // seed renders the same image. Frames of an animation should be numbered
+    // so that each gets its own noise rather than the same pattern again.
+    pub fn with_seed(self, seed: u64) -> Camera<R> {
+        Camera { seed, ..self }
+    }
+
+    pub fn with_frame(self, frame: u64) -> Camera<R> {
+        Camera { frame, ..self }
+    }
+
+    // lets render (and render_tiles, render_region and render_lighting),
+    // render_transparent, render_with_preview and render_adaptive be stopped
+    // early, keeping what was rendered so far; the others run to the end
+    pub fn with_cancellation(self, cancellation: Cancellation) -> Camera<R> {
+        Camera {
+            cancellation: Some(cancellation),
+            ..self
+        }
+    }
+
+    // counts the work done by render (and render_tiles, render_region,
+    // render_lighting and render_with_preview) into the recorder, tile by
+    // tile; each render replaces the report of the last. Without it, nothing
+    // is counted.
+    pub fn with_stats(self, stats: StatsRecorder) -> Camera<R> {
+        Camera {
+            stats: Some(stats),
+            ..self
+        }
+    }
+
+    // finds where render's (and render_tiles', render_region's,
+    // render_lighting's and render_with_preview's) camera rays first hit on
+    // the GPU, then shades them as usual; see Gpu for what is left to the CPU.
+    // The other renders cast every ray on the CPU: render_transparent and
+    // render_adaptive look at each ray as it is cast, and capture keeps every
+    // first hit's geometry rather than shading it.
+    #[cfg(feature = "gpu")]
+    pub fn with_gpu(self, gpu: Gpu) -> Camera<R> {
+        Camera {
+            gpu: Some(gpu),
+            ..self
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    // the ray generator, with its random choices keyed by the camera's
+    fn seeded_generator(&self) -> R {
+        self.ray_generator.clone().reseed(self.seed, self.frame)
+    }
+
+    // for the rays keyed to the region's pixels
+    fn sample_counter(&self, region: Region) -> SampleCounter {
+        let (hsize, _) = self.ray_generator.canvas_size();
+        SampleCounter::new(self.seed, self.frame, hsize, region)
+    }
+
+    // motion blur, for render (and render_lighting, render_tiles,
+    // render_region and render_with_preview); the other renders see the world
+    // at time 0 from the camera's own orientation
+    pub fn with_shutter(self, shutter: Shutter) -> Camera<R> {
+        Camera {
+            shutter: Some(shutter),
+            ..self
+        }
+    }
+
+    // the width and height of the canvases it renders
+    pub fn canvas_size(&self) -> (usize, usize) {
+        self.ray_generator.canvas_size()
     }
 
     pub fn render(self, world: &World) -> Result<Canvas, WriteError> {
+        let region = self.full_region();
+        self.render_pixels(world, region, |_| true)
+    }
+
+    // only the pixels in the tiles are rendered; the rest are left black
+    pub fn render_tiles(self, world: &World, tiles: &[Region]) -> Result<Canvas, WriteError> {
+        let region = self.full_region();
+        self.render_pixels(world, region, |index| {
+            tiles.iter().any(|tile| tile.contains(index))
+        })
+    }
+
+    // only the pixels in the region, onto a canvas the size of the region;
+    // they are the same as the region's pixels in a full render
+    pub fn render_region(self, world: &World, region: Region) -> Result<Canvas, WriteError> {
         let (hsize, vsize) = self.ray_generator.canvas_size();
-        let mut image = Canvas::new(Width(hsize), Height(vsize));
-        for tagged_ray in self.ray_generator {
-            let cast_ray = tagged_ray.ray();
-            let colour = world.cast_ray(cast_ray);
-            let tagged_pixels = tagged_ray.pixels();
-            for tagged_pixel in tagged_pixels {
+        if !region.fits(Width(hsize), Height(vsize)) {
+            return Err(WriteError::OutOfBounds);
+        }
+        self.render_pixels(world, region, |_| true)
+    }
+
+    // renders the rows band_height at a time, each written out before the
+    // next is begun, so only a band's canvas is held in memory and only its
+    // rays are cast; the same as writing out a full render
+    pub fn render_streaming<W: Write>(
+        self,
+        world: &World,
+        writer: &mut ScanlineWriter<W>,
+        band_height: usize,
+    ) -> Result<(), ScanlineError> {
+        let (hsize, vsize) = self.ray_generator.canvas_size();
+        for row in (0..vsize).step_by(band_height.max(1)) {
+            let band = Region::new(
+                0,
+                row,
+                Width(hsize),
+                Height(band_height.max(1).min(vsize - row)),
+            );
+            writer.write_rows(&self.clone().render_region(world, band)?)?;
+        }
+        Ok(())
+    }
+
+    fn full_region(&self) -> Region {
+        let (hsize, vsize) = self.ray_generator.canvas_size();
+        Region::new(0, 0, Width(hsize), Height(vsize))
+    }
+
+    // onto a canvas covering the region; rays are only cast for pixels in it
+    // that are kept
+    fn render_pixels<F: Fn([usize; 2]) -> bool>(
+        self,
+        world: &World,
+        region: Region,
+        keep: F,
+    ) -> Result<Canvas, WriteError> {
+        self.render_pixels_with(world, region, keep, |_| Ok(()))
+    }
+
+    // render_pixels, handing the image so far to painted after each tile
+    fn render_pixels_with<F, P, E>(
+        self,
+        world: &World,
+        region: Region,
+        keep: F,
+        mut painted: P,
+    ) -> Result<Canvas, E>
+    where
+        F: Fn([usize; 2]) -> bool,
+        P: FnMut(&Canvas) -> Result<(), E>,
+        E: From<WriteError>,
+    {
+        let mut image = Canvas::new(Width(region.width), Height(region.height));
+        let keep = |index: [usize; 2]| region.contains(index) && keep(index);
+        let paint = |image: &mut Canvas, tagged_ray: &TaggedRay, colour: Colour| {
+            for tagged_pixel in tagged_ray.pixels() {
+                if keep(tagged_pixel.index()) {
+                    let [pos_x, pos_y] = tagged_pixel.index();
+                    let blend_weight = tagged_pixel.blend_weight();
+                    image.paint_colour_additive(
+                        pos_x - region.column,
+                        pos_y - region.row,
+                        colour * blend_weight,
+                    )?;
+                }
+            }
+            Ok(())
+        };
+        let is_kept = |tagged_ray: &TaggedRay| {
+            tagged_ray
+                .pixels()
+                .iter()
+                .any(|tagged_pixel| keep(tagged_pixel.index()))
+        };
+
+        let samples = self
+            .shutter
+            .as_ref()
+            .map_or(1, |shutter| shutter.samples.max(1));
+        let weight = 1.0 / samples as Float;
+        // every ray keyed to the same pixels as the region's rays is counted
+        // before those outside it are left out, so each sample is keyed as it
+        // would be in a full render
+        let keyed_region = self.ray_generator.keyed_region(region);
+        let sample_counter = RefCell::new(self.sample_counter(keyed_region));
+        let sample_counter = &sample_counter;
+        let rays = (0..samples).flat_map(|idx_pass| {
+            let orientation = self
+                .shutter
+                .as_ref()
+                .and_then(|shutter| shutter.orientation(idx_pass));
+            let ray_generator = match orientation {
+                Some(orientation) => self.seeded_generator().reorient(orientation.clone()),
+                None => self.seeded_generator(),
+            };
+            let shutter = self.shutter.as_ref();
+            ray_generator
+                .restrict(keyed_region)
+                .into_iter()
+                .map(move |tagged_ray| {
+                    let key = sample_counter.borrow_mut().key(&tagged_ray);
+                    (tagged_ray, key)
+                })
+                .filter(|(tagged_ray, _)| is_kept(tagged_ray))
+                .map(move |(tagged_ray, key)| {
+                    let ray = match shutter {
+                        Some(shutter) => tagged_ray.ray().with_time(shutter.time(idx_pass, key)),
+                        None => tagged_ray.ray(),
+                    };
+                    (tagged_ray, ray, key)
+                })
+        });
+        let mut rays = rays.peekable();
+        let tiles = std::iter::from_fn(|| {
+            if self.is_cancelled() {
+                return None;
+            }
+            rays.peek()?;
+            Some(rays.by_ref().take(TILE_RAYS).collect())
+        });
+        #[cfg(feature = "gpu")]
+        let tiles: Box<dyn Iterator<Item = (Tile, Option<PrimaryHits>)>> = match &self.gpu {
+            Some(gpu) => Box::new(gpu.primary_hits_by_tile(world, tiles)),
+            None => Box::new(tiles.map(|tile| (tile, None))),
+        };
+        #[cfg(not(feature = "gpu"))]
+        let tiles = tiles.map(|tile| (tile, None));
+
+        if let Some(stats) = &self.stats {
+            stats.start_render();
+        }
+        cast_tiles(world, tiles, self.stats.is_some(), |cast_tile| {
+            if let Some(stats) = &self.stats {
+                stats.record_tile(cast_tile.stats);
+            }
+            for (tagged_ray, colour) in cast_tile.rays {
+                paint(&mut image, &tagged_ray, colour * weight)?;
+            }
+            painted(&image)
+        })?;
+        Ok(image)
+    }
+
+    // like render, but the background is transparent instead of black; each
+    // pixel's alpha is the fraction of it covered by objects
+    pub fn render_transparent(self, world: &World) -> Result<Canvas, WriteError> {
+        let (hsize, vsize) = self.ray_generator.canvas_size();
+        let mut image = Canvas::new_transparent(Width(hsize), Height(vsize));
+        let mut sample_counter = self.sample_counter(self.full_region());
+        for tagged_ray in self.seeded_generator() {
+            if self.is_cancelled() {
+                break;
+            }
+            let key = sample_counter.key(&tagged_ray);
+            let Some(colour) = world.cast_sample_hit(tagged_ray.ray(), key) else {
+                continue;
+            };
+            for tagged_pixel in tagged_ray.pixels() {
                 let [pos_x, pos_y] = tagged_pixel.index();
                 let blend_weight = tagged_pixel.blend_weight();
                 image.paint_colour_additive(pos_x, pos_y, colour * blend_weight)?;
+                image.paint_coverage_additive(pos_x, pos_y, blend_weight)?;
+            }
+        }
+        Ok(image)
+    }
+
+    // like render, also publishing the image so far every preview.interval,
+    // and once more when finished
+    pub fn render_with_preview(
+        self,
+        world: &World,
+        preview: &Preview,
+    ) -> Result<Canvas, PreviewError> {
+        let region = self.full_region();
+        let mut last_published = Instant::now();
+        let image = self.render_pixels_with(
+            world,
+            region,
+            |_| true,
+            |image| {
+                if last_published.elapsed() >= preview.interval {
+                    preview.publish(image)?;
+                    last_published = Instant::now();
+                }
+                Ok::<(), PreviewError>(())
+            },
+        )?;
+        preview.publish(&image)?;
+        Ok(image)
+    }
+
+    // renders once, then writes every export in the plan; the first hits are
+    // only captured (and the beauty pass shaded from them) if some export
+    // needs them
+    pub fn render_with_exports(
+        self,
+        world: &World,
+        plan: &ExportPlan,
+    ) -> Result<Canvas, ExportError> {
+        if !plan.needs_first_hits() {
+            let beauty = self.render(world)?;
+            plan.write(world, &beauty, None)?;
+            return Ok(beauty);
+        }
+
+        let first_hits = self.capture(world);
+        let beauty = first_hits.reshade(world, &MaterialOverrides::new())?;
+        plan.write(world, &beauty, Some(&first_hits))?;
+        Ok(beauty)
+    }
+
+    // Colours each pixel by how much work its primary rays took to intersect
+    // with the world (not shaded, and without any rays they would spawn),
+    // from black for none up through blue, red and yellow to white for the
+    // costliest pixel, so that slow parts of the scene stand out.
+    pub fn render_heatmap(
+        self,
+        world: &World,
+        metric: HeatmapMetric,
+    ) -> Result<Canvas, WriteError> {
+        let (hsize, vsize) = self.ray_generator.canvas_size();
+        let mut costs = vec![0.0; hsize * vsize];
+        for tagged_ray in self.seeded_generator() {
+            let ((), stats) = counting(true, || {
+                world.intersect_ray(&tagged_ray.ray());
+            });
+            let cost = metric.of(&stats) as Float;
+            for tagged_pixel in tagged_ray.pixels() {
+                let [pos_x, pos_y] = tagged_pixel.index();
+                if let Some(pixel) = costs.get_mut(pos_y * hsize + pos_x) {
+                    *pixel += cost * tagged_pixel.blend_weight();
+                }
+            }
+        }
+
+        let max_cost = costs.iter().copied().fold(0.0, Float::max);
+        let mut image = Canvas::new(Width(hsize), Height(vsize));
+        for (idx, cost) in costs.into_iter().enumerate() {
+            let heat = if max_cost > 0.0 { cost / max_cost } else { 0.0 };
+            image.paint_colour_replace(idx % hsize, idx / hsize, heat_colour(heat))?;
+        }
+        Ok(image)
+    }
+
+    // a render of the world's illumination alone; see World::lighting_only
+    pub fn render_lighting(self, world: &World) -> Result<Canvas, WriteError> {
+        let mut lighting_world = world.clone();
+        lighting_world.lighting_only = true;
+        self.render(&lighting_world)
+    }
+
+    // Moves the camera back along its current view direction (keeping its
+    // roll) until the bounding sphere of everything with finite bounds in the
+    // world fits in view, aimed at its centre. padding adds to the sphere's
+    // radius as a fraction of it, e.g. 0.1 for a 10% margin.
+    pub fn frame_world(self, world: &World, padding: Float) -> Result<Camera<R>, FramingError> {
+        let bounding_box = world.bounding_box().ok_or(FramingError::NothingToFrame)?;
+        Ok(self.frame_bounding_box(&bounding_box, padding))
+    }
+
+    fn frame_bounding_box(self, bounding_box: &BoundingBox, padding: Float) -> Camera<R> {
+        let ([x_min, x_max], [y_min, y_max], [z_min, z_max]) = bounding_box.axial_bounds();
+        let centre = Point::new(
+            (x_min + x_max) / 2.0,
+            (y_min + y_max) / 2.0,
+            (z_min + z_max) / 2.0,
+        );
+        let radius =
+            (Point::new(x_max, y_max, z_max) - centre).magnitude() * (1.0 + padding.max(0.0));
+
+        let camera_to_world = self.ray_generator.view_transformation().invert();
+        let from = Point::zero().transform(&camera_to_world);
+        let forward = (Point::new(0.0, 0.0, -1.0).transform(&camera_to_world) - from).normalise();
+        let up = Point::new(0.0, 1.0, 0.0).transform(&camera_to_world) - from;
+
+        let (half_width, half_height) = self.ray_generator.half_extents();
+        let half_angle = half_width.min(half_height).atan();
+        // a point sphere is framed from an arbitrary small distance
+        let distance = (radius / half_angle.sin()).max(EPSILON);
+
+        let orientation = Orientation::new(centre - forward * distance, centre, up);
+        Camera {
+            ray_generator: self.ray_generator.reorient(orientation),
+            ..self
+        }
+    }
+
+    // renders each eye as render would, then composites them
+    pub fn render_stereo(self, world: &World, stereo: &Stereo) -> Result<Canvas, StereoError> {
+        let [left, right] = stereo
+            .eyes(self.ray_generator.view_transformation())?
+            .map(|eye| Camera {
+                ray_generator: self.ray_generator.clone().reorient(eye),
+                shutter: self.shutter.clone(),
+                seed: self.seed,
+                frame: self.frame,
+                cancellation: self.cancellation.clone(),
+                stats: self.stats.clone(),
+                #[cfg(feature = "gpu")]
+                gpu: self.gpu.clone(),
+            });
+        Ok(stereo.composite(&left.render(world)?, &right.render(world)?)?)
+    }
+
+    // traces only the first hit of every ray, for re-shading after material edits
+    pub fn capture(self, world: &World) -> FirstHitBuffer {
+        FirstHitBuffer::capture(self.seeded_generator(), world)
+    }
+}
+
+// Builds a Native camera, 100 pixels square with a 90° field of view by
+// default, at the origin looking along -z with +y up.
+#[derive(Debug, Default)]
+pub struct CameraBuilder {
+    size: Option<(usize, usize)>,
+    fov: Option<Angle>,
+    position: Option<Point>,
+    target: Option<Point>,
+    up: Option<Vector>,
+    framing: Option<(BoundingBox, Float)>,
+    shutter: Option<Shutter>,
+    seed: u64,
+    frame: u64,
+    cancellation: Option<Cancellation>,
+    stats: Option<StatsRecorder>,
+    #[cfg(feature = "gpu")]
+    gpu: Option<Gpu>,
+}
+
+impl CameraBuilder {
+    pub fn set_size(mut self, hsize: usize, vsize: usize) -> CameraBuilder {
+        self.size = Some((hsize, vsize));
+        self
+    }
+
+    pub fn set_fov(mut self, fov: Angle) -> CameraBuilder {
+        self.fov = Some(fov);
+        self
+    }
+
+    pub fn set_position(mut self, position: Point) -> CameraBuilder {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn look_at(mut self, target: Point) -> CameraBuilder {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn set_up(mut self, up: Vector) -> CameraBuilder {
+        self.up = Some(up);
+        self
+    }
+
+    // once built, the camera is moved back as Camera::frame_world moves it,
+    // keeping the direction from the position to the target
+    pub fn frame(mut self, world: &World, padding: Float) -> Result<CameraBuilder, FramingError> {
+        let bounding_box = world.bounding_box().ok_or(FramingError::NothingToFrame)?;
+        self.framing = Some((bounding_box, padding));
+        Ok(self)
+    }
+
+    pub fn set_shutter(mut self, shutter: Shutter) -> CameraBuilder {
+        self.shutter = Some(shutter);
+        self
+    }
+
+    pub fn set_seed(mut self, seed: u64) -> CameraBuilder {
+        self.seed = seed;
+        self
+    }
+
+    pub fn set_frame(mut self, frame: u64) -> CameraBuilder {
+        self.frame = frame;
+        self
+    }
+
+    pub fn set_cancellation(mut self, cancellation: Cancellation) -> CameraBuilder {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn set_stats(mut self, stats: StatsRecorder) -> CameraBuilder {
+        self.stats = Some(stats);
+        self
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn set_gpu(mut self, gpu: Gpu) -> CameraBuilder {
+        self.gpu = Some(gpu);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Camera<Native>, OrientationError> {
+        let (hsize, vsize) = self.size.unwrap_or((100, 100));
+        let fov = self.fov.unwrap_or(Angle::from_degrees(90.0));
+        let position = self.position.unwrap_or(Point::zero());
+        let target = self
+            .target
+            .unwrap_or(position + Vector::new(0.0, 0.0, -1.0));
+        let up = self.up.unwrap_or(Vector::new(0.0, 1.0, 0.0));
+        let orientation = Orientation::try_new(position, target, up)?;
+
+        let mut camera = Camera::new(Native::new(hsize, vsize, fov, orientation));
+        camera.shutter = self.shutter;
+        camera.seed = self.seed;
+        camera.frame = self.frame;
+        camera.cancellation = self.cancellation;
+        camera.stats = self.stats;
+        #[cfg(feature = "gpu")]
+        {
+            camera.gpu = self.gpu;
+        }
+        Ok(match self.framing {
+            Some((bounding_box, padding)) => camera.frame_bounding_box(&bounding_box, padding),
+            None => camera,
+        })
+    }
+}
+
+impl Buildable for Camera<Native> {
+    type Builder = CameraBuilder;
+
+    fn builder() -> Self::Builder {
+        CameraBuilder::default()
+    }
+}
+
+impl ConsumingBuilder for CameraBuilder {
+    type Built = Camera<Native>;
+
+    // panics with a descriptive message on a degenerate orientation; use
+    // try_build to handle these instead
+    fn build(self) -> Self::Built {
+        self.try_build().unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl Camera<Native> {
+    // one ray per pixel, then more where the image has edges; refined pixels
+    // come out as they would from Supersampled with the same samples and
+    // sampling. Like the other renders besides render, it ignores the shutter.
+    pub fn render_adaptive(
+        self,
+        world: &World,
+        adaptive: &AdaptiveSampling,
+    ) -> Result<Canvas, WriteError> {
+        let (seed, frame) = (self.seed, self.frame);
+        let native = self.ray_generator.clone();
+        let mut image = Camera {
+            shutter: None,
+            ..self.clone()
+        }
+        .render(world)?;
+        for [pos_x, pos_y] in adaptive.refine(&image) {
+            if self.is_cancelled() {
+                break;
+            }
+            let pixel = (pos_y * native.hsize() + pos_x) as u64;
+            let mut random = SampleKey::new(seed, frame, pixel, 0).random(Stream::Placement);
+            let positions = adaptive.sampling.positions(adaptive.samples, &mut random);
+            let blend_weight = 1.0 / positions.len() as Float;
+            image.paint_colour_replace(pos_x, pos_y, Colour::new(0.0, 0.0, 0.0))?;
+            for (idx_sample, [within_x, within_y]) in positions.into_iter().enumerate() {
+                let ray = native.ray_through(pos_x as Float + within_x, pos_y as Float + within_y);
+                let key = SampleKey::new(seed, frame, pixel, idx_sample as u64);
+                image.paint_colour_additive(
+                    pos_x,
+                    pos_y,
+                    world.cast_sample(ray, key) * blend_weight,
+                )?;
             }
         }
         Ok(image)
@@ -77,12 +867,47 @@ impl<R: RayGenerator> Camera<R> {
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::FRAC_PI_2;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
+    use super::*;
     use crate::collections::*;
+    use crate::scenes::raygen::agss::Agss;
+    use crate::utils::consts::FRAC_PI_2;
     use crate::utils::{approx_eq, BuildInto, Buildable};
 
-    use super::*;
+    #[test]
+    fn orientation_with_degenerate_configurations() {
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        assert_eq!(
+            Orientation::try_new(origin, origin, up),
+            Err(OrientationError::ZeroLengthDirection)
+        );
+        assert_eq!(
+            Orientation::try_new(origin, Point::new(0.0, 0.0, -1.0), Vector::zero()),
+            Err(OrientationError::ZeroLengthUp)
+        );
+        assert_eq!(
+            Orientation::try_new(origin, Point::new(0.0, 5.0, 0.0), up),
+            Err(OrientationError::CollinearUp)
+        );
+        assert_eq!(
+            Orientation::try_new(origin, Point::new(Float::NAN, 0.0, 0.0), up),
+            Err(OrientationError::NonFinite)
+        );
+        assert!(Orientation::try_new(origin, Point::new(0.0, 0.0, -1.0), up).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "parallel to the view direction")]
+    fn orientation_with_collinear_up_panics() {
+        Orientation::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, -2.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+    }
 
     #[test]
     fn view_transform_default() {
@@ -154,11 +979,8 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let native_ray_generator = Native::new(
             11,
             11,
@@ -177,4 +999,755 @@ mod tests {
         assert_eq!(painted_pixel.green(), resulting_pixel.green());
         assert_eq!(painted_pixel.blue(), resulting_pixel.blue());
     }
+
+    #[test]
+    fn render_across_tiles_matches_each_ray() {
+        let world = World::test_world();
+        let native = Native::new(
+            80,
+            60,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        // many more rays than fit in one tile
+        let image = Camera::new(native.clone()).render(&world).unwrap();
+        for (pos_x, pos_y) in [(0, 0), (40, 30), (79, 59), (52, 45)] {
+            let colour = world.cast_ray(native.ray_for_pixel(pos_x, pos_y));
+            assert_eq!(image[[pos_x, pos_y]].colour(), colour);
+        }
+    }
+
+    #[test]
+    fn shutter_blurs_moving_objects() {
+        let sphere = Moving::builder()
+            .set_object(
+                Sphere::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
+                    .set_material(Material {
+                        ambient: 1.0,
+                        diffuse: 0.0,
+                        specular: 0.0,
+                        ..Material::preset()
+                    })
+                    .build_into(),
+            )
+            .set_motion(0.0, 1.0, 64, |time| {
+                Transform::new(TransformKind::Translate(2.0 * time - 1.0, 0.0, 0.0))
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+
+        // the sphere only crosses the centre for the middle half of the interval
+        let still = camera.clone().render(&world).unwrap();
+        assert_eq!(still[[5, 5]].colour().red, 0.0);
+        let blurred = camera
+            .with_shutter(Shutter::new(0.0, 1.0, 32))
+            .render(&world)
+            .unwrap();
+        let centre = blurred[[5, 5]].colour().red;
+        assert!(centre > 0.3 && centre < 0.7, "{centre}");
+    }
+
+    #[test]
+    fn shutter_path_moves_the_camera() {
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
+            .set_material(Material {
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let looking_from = |x| {
+            Orientation::new(
+                Point::new(x, 0.0, -5.0),
+                Point::new(x, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+        };
+        let camera = Camera::new(Native::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            looking_from(0.0),
+        ))
+        .with_shutter(
+            Shutter::new(0.0, 1.0, 4).with_path(vec![looking_from(0.0), looking_from(4.0)]),
+        );
+        let image = camera.render(&world).unwrap();
+        // two passes look straight at the sphere, and two look away from it
+        approx_eq!(image[[5, 5]].colour().red, 0.5);
+    }
+
+    #[test]
+    fn frame_world_fits_distant_objects() {
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::from(vec![
+                TransformKind::Scale(50.0, 50.0, 50.0),
+                TransformKind::Translate(100.0, 20.0, 300.0),
+            ]))
+            .set_material(Material {
+                ambient: 1.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let floor = Plane::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere, floor], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            21,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 30.0, -5.0),
+                Point::new(0.0, 30.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+
+        let framed = camera.frame_world(&world, 0.1).unwrap();
+        let first_hits = framed.capture(&world);
+        // the sphere fills the centre, and the frame's edges see past it
+        assert_eq!(first_hits.object_at(10, 5), Some(0));
+        for pos_x in 0..21 {
+            assert_eq!(first_hits.object_at(pos_x, 0), None);
+        }
+        for pos_y in 0..11 {
+            assert_ne!(first_hits.object_at(0, pos_y), Some(0));
+            assert_ne!(first_hits.object_at(20, pos_y), Some(0));
+        }
+    }
+
+    #[test]
+    fn build_camera_looking_at_target() {
+        let camera = Camera::builder()
+            .set_size(21, 11)
+            .set_fov(Angle::from_radians(FRAC_PI_2))
+            .set_position(Point::new(0.0, 30.0, -5.0))
+            .look_at(Point::new(0.0, 30.0, 0.0))
+            .build();
+        let native = Native::new(
+            21,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 30.0, -5.0),
+                Point::new(0.0, 30.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        assert_eq!(camera, Camera::new(native.clone()));
+
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(100.0, 20.0, 300.0)))
+            .build_into();
+        let world = World::new(vec![sphere], vec![]);
+        let framed = Camera::builder()
+            .set_size(21, 11)
+            .set_fov(Angle::from_radians(FRAC_PI_2))
+            .set_position(Point::new(0.0, 30.0, -5.0))
+            .look_at(Point::new(0.0, 30.0, 0.0))
+            .frame(&world, 0.1)
+            .unwrap()
+            .build();
+        assert_eq!(
+            framed,
+            Camera::new(native).frame_world(&world, 0.1).unwrap()
+        );
+
+        assert_eq!(
+            Camera::builder()
+                .look_at(Point::new(0.0, 5.0, 0.0))
+                .try_build()
+                .unwrap_err(),
+            OrientationError::CollinearUp
+        );
+        let empty = World::new(vec![], vec![]);
+        assert_eq!(
+            Camera::builder().frame(&empty, 0.0).unwrap_err(),
+            FramingError::NothingToFrame
+        );
+    }
+
+    #[test]
+    fn frame_world_without_bounded_objects() {
+        let floor = Plane::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let world = World::new(vec![floor], vec![]);
+        let camera = Camera::new(Native::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::default(),
+        ));
+        assert_eq!(
+            camera.frame_world(&world, 0.0).unwrap_err(),
+            FramingError::NothingToFrame
+        );
+    }
+
+    #[test]
+    fn render_with_transparent_background() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let ray_generator = Agss::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            2.0,
+        );
+        let opaque = Camera::new(ray_generator.clone()).render(&world).unwrap();
+        let transparent = Camera::new(ray_generator)
+            .render_transparent(&world)
+            .unwrap();
+
+        assert_eq!(transparent[[0, 0]].alpha(), 0.0);
+        assert_eq!(transparent[[5, 5]], opaque[[5, 5]]);
+        // the sphere's edge only partly covers some pixels
+        let partly_covered = (0..11)
+            .flat_map(|pos_y| (0..11).map(move |pos_x| [pos_x, pos_y]))
+            .filter(|&index| {
+                let alpha = transparent[index].alpha();
+                alpha > 0.0 && alpha < 1.0
+            })
+            .count();
+        assert!(partly_covered > 0);
+        for pos_y in 0..11 {
+            for pos_x in 0..11 {
+                assert_eq!(
+                    transparent[[pos_x, pos_y]].colour(),
+                    opaque[[pos_x, pos_y]].colour()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn supersampling_smooths_edges() {
+        let sphere = Sphere::builder()
+            .set_material(Material {
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let orientation = Orientation::new(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let is_partial = |image: &Canvas| {
+            (0..11)
+                .flat_map(|pos_y| (0..11).map(move |pos_x| [pos_x, pos_y]))
+                .any(|index| {
+                    let red = image[index].colour().red;
+                    red > 0.01 && red < 0.99
+                })
+        };
+
+        let native = Native::new(11, 11, Angle::from_radians(FRAC_PI_2), orientation.clone());
+        assert!(!is_partial(&Camera::new(native).render(&world).unwrap()));
+        for sampling in [Sampling::Grid, Sampling::Jittered, Sampling::Stratified] {
+            let supersampled = Supersampled::new(
+                11,
+                11,
+                Angle::from_radians(FRAC_PI_2),
+                orientation.clone(),
+                16,
+                sampling,
+            );
+            let image = Camera::new(supersampled).render(&world).unwrap();
+            assert!(is_partial(&image));
+            approx_eq!(image[[5, 5]].colour().red, 1.0);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling_refines_edges() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let orientation = Orientation::new(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let fov = Angle::from_radians(FRAC_PI_2);
+        let native = Native::new(11, 11, fov, orientation.clone());
+        let plain = Camera::new(native.clone()).render(&world).unwrap();
+        let supersampled = Camera::new(Supersampled::new(
+            11,
+            11,
+            fov,
+            orientation,
+            9,
+            Sampling::Jittered,
+        ))
+        .render(&world)
+        .unwrap();
+
+        let adaptive = AdaptiveSampling::new(0.1, 9, Sampling::Jittered);
+        let refined = adaptive.refine(&plain);
+        assert!(!refined.is_empty() && refined.len() < 11 * 11 / 2);
+        let image = Camera::new(native)
+            .render_adaptive(&world, &adaptive)
+            .unwrap();
+        for pos_y in 0..11 {
+            for pos_x in 0..11 {
+                let expected = if refined.contains(&[pos_x, pos_y]) {
+                    &supersampled
+                } else {
+                    &plain
+                };
+                assert_eq!(image[[pos_x, pos_y]], expected[[pos_x, pos_y]]);
+            }
+        }
+    }
+
+    #[test]
+    fn render_stereo_side_by_side() {
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+
+        // converging beyond the sphere, so each eye sees it shifted its own way
+        let stereo = Stereo::new(2.0, 20.0, StereoLayout::SideBySide);
+        let image = camera.render_stereo(&world, &stereo).unwrap();
+        assert_eq!(image.width(), 22);
+        let lit_columns = |offset: usize| {
+            (0..11)
+                .filter(|&column| image[[column + offset, 5]].colour().red > 0.0)
+                .collect::<Vec<usize>>()
+        };
+        let (left, right) = (lit_columns(0), lit_columns(11));
+        assert!(!left.is_empty() && !right.is_empty());
+        assert_ne!(left, right);
+    }
+
+    #[test]
+    fn seeded_renders_are_reproducible() {
+        let mut world = World::test_world();
+        world.integrator = Integrator::PathTracing(PathTracing::new(1));
+        let supersampled = Supersampled::new(
+            10,
+            8,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -3.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            2,
+            Sampling::Stratified,
+        );
+        let camera = |seed, frame| {
+            Camera::new(supersampled.clone())
+                .with_seed(seed)
+                .with_frame(frame)
+        };
+        let image = camera(7, 0).render(&world).unwrap();
+        assert_eq!(image, camera(7, 0).render(&world).unwrap());
+        assert_ne!(image, camera(8, 0).render(&world).unwrap());
+        assert_ne!(image, camera(7, 1).render(&world).unwrap());
+
+        // every sample is keyed by its pixel, not by when it is rendered
+        let region = Region::new(2, 3, Width(4), Height(2));
+        let cropped = camera(7, 0).render_region(&world, region).unwrap();
+        for pos_y in 0..2 {
+            for pos_x in 0..4 {
+                assert_eq!(cropped[[pos_x, pos_y]], image[[pos_x + 2, pos_y + 3]]);
+            }
+        }
+    }
+
+    #[test]
+    fn cancelled_renders_keep_what_was_rendered() {
+        // cancels the render once it has shaded a few hundred points
+        #[derive(Clone, Debug)]
+        struct CancellingLight {
+            light: PointLight,
+            shaded: Arc<AtomicUsize>,
+            cancellation: Cancellation,
+        }
+        impl Light for CancellingLight {
+            fn samples(&self, target: Point) -> Vec<LightSample> {
+                if self.shaded.fetch_add(1, Ordering::Relaxed) == 300 {
+                    self.cancellation.cancel();
+                }
+                self.light.samples(target)
+            }
+        }
+
+        let native = Native::new(
+            40,
+            40,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -1.5),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        let cancellation = Cancellation::new();
+        let mut world = World::test_world();
+        world.lights = vec![Box::new(CancellingLight {
+            light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0)),
+            shaded: Arc::new(AtomicUsize::new(0)),
+            cancellation: cancellation.clone(),
+        })];
+        world.background = Background::Solid(Colour::new(0.2, 0.2, 0.2));
+        let image = Camera::new(native.clone())
+            .with_cancellation(cancellation.clone())
+            .render(&world)
+            .unwrap();
+        assert!(cancellation.is_cancelled());
+        assert_eq!((image.width(), image.height()), (40, 40));
+        assert_eq!(image[[0, 0]].colour(), Colour::new(0.2, 0.2, 0.2));
+        #[cfg(not(feature = "parallel"))]
+        assert_eq!(image[[39, 39]].colour(), Colour::new(0.0, 0.0, 0.0));
+
+        // already cancelled, so nothing is rendered
+        let image = Camera::new(native)
+            .with_cancellation(cancellation)
+            .render_transparent(&World::test_world())
+            .unwrap();
+        assert_eq!(image[[20, 20]].alpha(), 0.0);
+    }
+
+    #[test]
+    fn renders_record_stats_by_tile() {
+        let native = Native::new(
+            20,
+            20,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        let stats = StatsRecorder::new();
+        let camera = Camera::new(native).with_stats(stats.clone());
+        // each render is reported on its own
+        for _ in 0..2 {
+            camera.clone().render(&World::test_world()).unwrap();
+        }
+
+        let report = stats.report();
+        assert_eq!(report.tiles.len(), 400_usize.div_ceil(TILE_RAYS));
+        assert_eq!(report.total.camera_rays, 400);
+        let total = report
+            .tiles
+            .iter()
+            .fold(RenderStats::default(), |total, &tile| total + tile);
+        assert_eq!(total, report.total);
+        // the test world's two spheres are tested against every camera ray
+        assert!(report.total.intersection_tests >= 800);
+        assert!(report.total.shading_evaluations > 0);
+        assert_eq!(report.total.shadow_rays, report.total.shading_evaluations);
+    }
+
+    #[test]
+    fn heatmaps_are_hottest_where_objects_overlap() {
+        let native = Native::new(
+            21,
+            21,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        let unit_sphere = || -> Shape { Sphere::builder().build_into() };
+        let world = World::new(
+            vec![
+                Group::builder()
+                    .set_objects(vec![unit_sphere(), unit_sphere()])
+                    .build_into(),
+                Group::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Translate(
+                        3.0, 3.0, 0.0,
+                    )))
+                    .set_objects(vec![unit_sphere()])
+                    .build_into(),
+            ],
+            vec![],
+        );
+        let image = Camera::new(native.clone())
+            .render_heatmap(&world, HeatmapMetric::IntersectionTests)
+            .unwrap();
+        // both spheres at the centre are tested, neither in the corner
+        assert_eq!(image[[10, 10]].colour(), Colour::new(1.0, 1.0, 1.0));
+        assert_eq!(image[[0, 20]].colour(), Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(image[[20, 0]].colour(), heat_colour(0.5));
+
+        // without kd-trees there is nothing to visit
+        let image = Camera::new(native.clone())
+            .render_heatmap(&world, HeatmapMetric::KdTreeVisits)
+            .unwrap();
+        assert_eq!(image[[10, 10]].colour(), Colour::new(0.0, 0.0, 0.0));
+        let spheres = (-2..=2)
+            .map(|idx| -> Shape {
+                Sphere::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Translate(
+                        idx as Float * 2.5,
+                        0.0,
+                        0.0,
+                    )))
+                    .build_into()
+            })
+            .collect();
+        let world = World::new(
+            vec![KdTree::builder().set_objects(spheres).build_into()],
+            vec![],
+        );
+        let image = Camera::new(native)
+            .render_heatmap(&world, HeatmapMetric::KdTreeVisits)
+            .unwrap();
+        assert_ne!(image[[10, 10]].colour(), Colour::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn render_region_matches_full_render() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Agss::new(
+            11,
+            11,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            2.0,
+        ));
+        let full = camera.clone().render(&world).unwrap();
+
+        let region = Region::new(3, 4, Width(5), Height(2));
+        let cropped = camera.clone().render_region(&world, region).unwrap();
+        assert_eq!(cropped.width(), 5);
+        assert_eq!(cropped.height(), 2);
+        for pos_y in 0..2 {
+            for pos_x in 0..5 {
+                assert_eq!(cropped[[pos_x, pos_y]], full[[pos_x + 3, pos_y + 4]]);
+            }
+        }
+
+        let tiles = [region, Region::new(0, 0, Width(2), Height(2))];
+        let tiled = camera.clone().render_tiles(&world, &tiles).unwrap();
+        assert_eq!(tiled[[4, 5]], full[[4, 5]]);
+        assert_eq!(tiled[[1, 1]], full[[1, 1]]);
+        assert_eq!(tiled[[5, 7]].colour(), Colour::new(0.0, 0.0, 0.0));
+
+        let outside = Region::new(8, 0, Width(4), Height(1));
+        assert!(matches!(
+            camera.render_region(&world, outside),
+            Err(WriteError::OutOfBounds)
+        ));
+    }
+
+    // with a moving sphere and the shutter open, each ray's time comes from
+    // its key, so a region only matches if its samples are keyed alike
+    #[test]
+    fn render_region_keys_samples_as_a_full_render() {
+        let sphere = Moving::builder()
+            .set_object(
+                Sphere::builder()
+                    .set_material(Material::preset())
+                    .build_into(),
+            )
+            .set_motion(0.0, 1.0, 16, |time| {
+                Transform::new(TransformKind::Translate(time - 0.5, 0.0, 0.0))
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let orientation = Orientation::new(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let fov = Angle::from_radians(FRAC_PI_2);
+        fn matches_full_render<R: RayGenerator>(world: &World, camera: Camera<R>) {
+            let region = Region::new(2, 3, Width(5), Height(4));
+            let camera = camera.with_seed(7).with_shutter(Shutter::new(0.0, 1.0, 3));
+            let full = camera.clone().render(world).unwrap();
+            let cropped = camera.render_region(world, region).unwrap();
+            for pos_y in 0..region.height {
+                for pos_x in 0..region.width {
+                    assert_eq!(
+                        cropped[[pos_x, pos_y]],
+                        full[[pos_x + region.column, pos_y + region.row]]
+                    );
+                }
+            }
+        }
+        for render_scale in [1.5, 0.75] {
+            matches_full_render(
+                &world,
+                Camera::new(Agss::new(11, 9, fov, orientation.clone(), render_scale)),
+            );
+        }
+        matches_full_render(
+            &world,
+            Camera::new(Supersampled::new(
+                11,
+                9,
+                fov,
+                orientation,
+                4,
+                Sampling::Jittered,
+            )),
+        );
+    }
+
+    #[test]
+    fn render_streaming_matches_full_render() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Agss::new(
+            9,
+            7,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+            2.0,
+        ));
+        let full = camera.clone().render(&world).unwrap();
+
+        // bands of 3 leave a last band of 1
+        let mut writer = ScanlineWriter::new(
+            Vec::new(),
+            ScanlineFormat::Png,
+            Width(9),
+            Height(7),
+            BitDepth::Eight,
+        )
+        .unwrap();
+        camera.render_streaming(&world, &mut writer, 3).unwrap();
+        assert_eq!(writer.finish().unwrap(), full.write_to_png());
+    }
+
+    #[test]
+    fn render_lighting_pass() {
+        let red = Material {
+            pattern: Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+            ..Material::preset()
+        };
+        let sphere = Sphere::builder().set_material(red).build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light.clone())]);
+        let clay_sphere = Sphere::builder()
+            .set_material(Material::clay())
+            .build_into();
+        let clay_world = World::new(vec![clay_sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            5,
+            5,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+
+        let lighting = camera.clone().render_lighting(&world).unwrap();
+        assert_eq!(lighting, camera.clone().render(&clay_world).unwrap());
+        assert_ne!(lighting, camera.render(&world).unwrap());
+        assert!(!world.lighting_only);
+    }
+
+    #[test]
+    fn render_with_preview_publishes_final_image() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let camera = Camera::new(Native::new(
+            6,
+            4,
+            Angle::from_radians(FRAC_PI_2),
+            Orientation::new(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        ));
+        let path = std::env::temp_dir().join("raytracer_render_preview_test.png");
+        let path = path.to_str().unwrap();
+        let preview = Preview {
+            interval: std::time::Duration::ZERO,
+            max_size: 3,
+            ..Preview::to_file(path)
+        };
+
+        let image = camera
+            .clone()
+            .render_with_preview(&world, &preview)
+            .unwrap();
+        assert_eq!(image, camera.render(&world).unwrap());
+        let published = std::fs::read(path).unwrap();
+        assert_eq!(&published[1..4], b"PNG");
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -1,45 +1,483 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
 use crate::collections::*;
 use crate::objects::*;
-use crate::utils::*;
+use crate::scenes::{
+    cosine_hemisphere, count, ray_key, AmbientLight, Background, Counter, Integrator,
+    IrradianceError, IrradianceVolume, LightLink, PathTracing, ShadingMode,
+};
+use crate::utils::{BuildInto, Buildable, Float, Random, SampleKey, Stream};
 
-#[derive(Default, Debug)]
+#[derive(Clone, Debug)]
 pub struct World {
-    pub objects: Vec<Shape>,
-    pub lights: Vec<Light>,
+    // see objects_mut
+    objects: Vec<Shape>,
+    pub lights: Vec<Box<dyn Light>>,
+    // the objects each light shines on, by its index in lights; lights with
+    // no link shine on everything
+    pub light_links: HashMap<usize, LightLink>,
+    pub irradiance: Option<IrradianceVolume>,
+    pub ambient_light: Option<AmbientLight>,
+    pub background: Background,
+    pub shading_mode: ShadingMode,
+    // shades every surface with Material::clay, to judge the lighting apart
+    // from surface colours and textures
+    pub lighting_only: bool,
+    // how many surfaces a ray is followed through, counting the first one it
+    // hits; reflections and refractions beyond that come out black
+    pub max_depth: i32,
+    // if set, each camera ray is traced at this many wavelengths and the
+    // results are combined, so that dispersive materials split white light
+    // into colours (at this many times the cost)
+    pub spectral_samples: Option<usize>,
+    // how many points each glowing object (see Emitter) is sampled at in
+    // lighting the objects around it; 0 leaves them glowing but lighting
+    // nothing
+    pub emitter_resolution: usize,
+    // if set, light passes through transparent objects to the surfaces in
+    // their shadow, dimmed by their transparency; otherwise every object that
+    // casts a shadow blocks light entirely
+    pub transparent_shadows: bool,
+    pub integrator: Integrator,
+    pub(crate) emitters: Emitters,
+}
+
+// The glowing objects of a world as lights (see Emitter), found the first
+// time they light anything rather than for every point lit. They are kept
+// with the resolution they were found at, and found again on every use (as
+// if not kept) once emitter_resolution changes; World::objects_mut lets them
+// go, as the objects may change. Clones of a world find their own.
+#[derive(Debug, Default)]
+pub(crate) struct Emitters(OnceLock<FoundEmitters>);
+
+#[derive(Debug)]
+struct FoundEmitters {
+    resolution: usize,
+    emitters: Vec<Emitter>,
+}
+
+impl Emitters {
+    fn get(&self, objects: &[Shape], resolution: usize) -> Cow<'_, [Emitter]> {
+        let find = || {
+            objects
+                .iter()
+                .filter_map(|object| Emitter::from_shape(object, resolution))
+                .collect::<Vec<_>>()
+        };
+        let found = self.0.get_or_init(|| FoundEmitters {
+            resolution,
+            emitters: find(),
+        });
+        if found.resolution == resolution {
+            Cow::Borrowed(&found.emitters)
+        } else {
+            Cow::Owned(find())
+        }
+    }
+}
+
+impl Clone for Emitters {
+    fn clone(&self) -> Emitters {
+        Emitters::default()
+    }
+}
+
+// The primitive each of a run of camera rays hits first, found before they
+// are cast, e.g. on the GPU (see Gpu); see World::cast_samples_among. Each
+// primitive is given by its path: the index of its top-level object in
+// World::objects, then its index in each group or kd-tree down to it. Objects
+// that could not be looked at are unchecked, and every ray looks at them for
+// itself. Without the gpu feature, only tests find them.
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PrimaryHits {
+    pub(crate) hits: Vec<PrimaryHit>,
+    pub(crate) paths: Arc<[Box<[usize]>]>,
+    pub(crate) unchecked: Arc<[usize]>,
+}
+
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PrimaryHit {
+    // the ray misses every object that was looked at
+    Missed,
+    // an index into PrimaryHits::paths
+    Primitive(usize),
+    // the ray is cast as usual, e.g. as it hits glass, which refracts
+    // according to every surface the ray crosses (see HitRegister)
+    Any,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new(vec![], vec![])
+    }
 }
 
 impl<'world: 'ray, 'ray> World {
-    const MAX_RAYCAST_DEPTH: i32 = 10;
+    pub(crate) const MAX_RAYCAST_DEPTH: i32 = 10;
+    pub(crate) const EMITTER_RESOLUTION: usize = 16;
+
+    pub fn new(objects: Vec<Shape>, lights: Vec<Box<dyn Light>>) -> World {
+        World {
+            objects,
+            lights,
+            light_links: HashMap::new(),
+            irradiance: None,
+            ambient_light: None,
+            background: Background::default(),
+            shading_mode: ShadingMode::default(),
+            lighting_only: false,
+            max_depth: Self::MAX_RAYCAST_DEPTH,
+            spectral_samples: None,
+            emitter_resolution: Self::EMITTER_RESOLUTION,
+            transparent_shadows: false,
+            integrator: Integrator::default(),
+            emitters: Emitters::default(),
+        }
+    }
+
+    pub fn objects(&self) -> &Vec<Shape> {
+        &self.objects
+    }
+
+    // the objects, to be changed; the glowing ones are found again the next
+    // time they light anything (see Emitters)
+    pub fn objects_mut(&mut self) -> &mut Vec<Shape> {
+        self.emitters = Emitters::default();
+        &mut self.objects
+    }
 
-    pub fn new(objects: Vec<Shape>, lights: Vec<Light>) -> World {
-        World { objects, lights }
+    // a unit sphere around a half-size one, lit from above left and in front;
+    // a known scene for trying out rendering settings
+    pub fn test_world() -> World {
+        let outer = Sphere::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.8, 1.0, 0.6))),
+                diffuse: 0.7,
+                specular: 0.2,
+                ..Material::preset()
+            })
+            .build_into();
+        let inner = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        World::new(vec![outer, inner], vec![Box::new(light)])
     }
 
+    // bakes an irradiance volume over the given region from the current direct
+    // shading of the world, replacing any previously baked volume; on an error
+    // the world is left without one
+    pub fn bake_irradiance(
+        &mut self,
+        bounding_box: BoundingBox,
+        resolution: [usize; 3],
+        samples: usize,
+    ) -> Result<(), IrradianceError> {
+        self.irradiance = None;
+        self.irradiance = Some(IrradianceVolume::bake(
+            self,
+            bounding_box,
+            resolution,
+            samples,
+        )?);
+        Ok(())
+    }
+
+    // the combined bounds of every object with finite bounds, i.e. ignoring
+    // planes and the like; None if there are no such objects
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.objects
+            .iter()
+            .map(|object| object.bounds().bounding_box())
+            .filter(|bounding_box| {
+                let (x_range, y_range, z_range) = bounding_box.axial_bounds();
+                [x_range, y_range, z_range]
+                    .iter()
+                    .flatten()
+                    .all(|bound| bound.is_finite())
+            })
+            .reduce(|combined, bounding_box| combined + bounding_box)
+    }
+
+    // object ids index into this list, which visits every object depth-first
+    pub fn primitives(&self) -> Vec<&dyn PrimitiveShape> {
+        self.objects
+            .iter()
+            .flat_map(|object| object.primitives())
+            .collect()
+    }
+
+    pub fn object_id(&self, primitive_shape: &dyn PrimitiveShape) -> Option<usize> {
+        self.primitives()
+            .iter()
+            .position(|&primitive| std::ptr::addr_eq(primitive, primitive_shape))
+    }
+
+    // ids of the objects that differ from those in another version of this
+    // world (every id, if the number of objects differs)
+    pub fn changed_objects(&self, previous: &World) -> HashSet<usize> {
+        let primitives = self.primitives();
+        let previous_primitives = previous.primitives();
+        if primitives.len() != previous_primitives.len() {
+            return (0..primitives.len()).collect();
+        }
+
+        primitives
+            .into_iter()
+            .zip(previous_primitives)
+            .enumerate()
+            .filter(|(_, (primitive, previous_primitive))| primitive != previous_primitive)
+            .map(|(object_id, _)| object_id)
+            .collect()
+    }
+
+    // random choices in shading the ray (see PathTracing) are keyed by the
+    // ray itself, so casting it again gives the same colour
     pub fn cast_ray(&self, ray: Ray) -> Colour {
-        self.shade_ray(&ray, Self::MAX_RAYCAST_DEPTH)
+        self.cast_sample(ray, ray_key(&ray))
+    }
+
+    // None if the ray misses everything and so sees the background
+    pub fn cast_ray_hit(&self, ray: Ray) -> Option<Colour> {
+        self.cast_sample_hit(ray, ray_key(&ray))
+    }
+
+    // as cast_ray, for a camera ray cast as a sample of a render
+    pub fn cast_sample(&self, ray: Ray, key: SampleKey) -> Colour {
+        self.cast_sample_hit(ray, key)
+            .unwrap_or_else(|| self.background.colour_at(ray.direction))
+    }
+
+    pub fn cast_sample_hit(&self, ray: Ray, key: SampleKey) -> Option<Colour> {
+        match self.spectral_samples {
+            Some(count) if ray.wavelength.is_none() => {
+                // every wavelength sees the same first hit, so all of them
+                // miss if one does
+                let mut colour = Colour::new(0.0, 0.0, 0.0);
+                for (wavelength, weight) in spectral_samples(count) {
+                    let ray = ray.with_wavelength(Some(wavelength));
+                    colour = colour + self.integrate(&ray, key)? * weight;
+                }
+                Some(colour)
+            }
+            _ => self.integrate(&ray, key),
+        }
+    }
+
+    // cast_ray for each ray of the packet, in order, intersecting them
+    // together; see RayPacket
+    pub fn cast_packet(&self, packet: &RayPacket) -> Vec<Colour> {
+        let keys: Vec<SampleKey> = packet.rays().iter().map(ray_key).collect();
+        self.cast_packet_samples(packet, &keys)
+    }
+
+    // cast_sample for each ray in order, cast in packets of neighbours
+    pub(crate) fn cast_samples(&self, samples: &[(Ray, SampleKey)]) -> Vec<Colour> {
+        let mut colours = Vec::with_capacity(samples.len());
+        for chunk in samples.chunks(PACKET_RAYS) {
+            let rays: Vec<Ray> = chunk.iter().map(|&(ray, _)| ray).collect();
+            let keys: Vec<SampleKey> = chunk.iter().map(|&(_, key)| key).collect();
+            colours.extend(self.cast_packet_samples(&RayPacket::new(&rays), &keys));
+        }
+        colours
+    }
+
+    // whether a camera ray is shaded from its first hit alone, by Whitted
+    // shading; the rays of a path or of each wavelength go their own ways
+    pub(crate) fn shades_first_hits(&self) -> bool {
+        matches!(self.integrator, Integrator::Whitted)
+            && self.spectral_samples.is_none()
+            && self.max_depth != 0
+    }
+
+    // cast_samples, with each ray's first hit only looked for on the
+    // primitive it is known to hit first and the unchecked objects; the rays
+    // are cast as usual if they are not shaded from their first hits
+    pub(crate) fn cast_samples_among(
+        &self,
+        samples: &[(Ray, SampleKey)],
+        primary_hits: &PrimaryHits,
+    ) -> Vec<Colour> {
+        if !self.shades_first_hits() {
+            return self.cast_samples(samples);
+        }
+
+        samples
+            .iter()
+            .zip(&primary_hits.hits)
+            .map(|(&(ray, key), &primary_hit)| {
+                let mut paths: Vec<&[usize]> = primary_hits
+                    .unchecked
+                    .iter()
+                    .map(std::slice::from_ref)
+                    .collect();
+                match primary_hit {
+                    PrimaryHit::Missed => (),
+                    PrimaryHit::Primitive(idx_path) => paths.push(&primary_hits.paths[idx_path]),
+                    PrimaryHit::Any => return self.cast_sample(ray, key),
+                }
+                // in the order the whole world is intersected in
+                paths.sort_unstable_by_key(|path| path[0]);
+
+                let mut hit_register = HitRegister::empty();
+                let mut transform_stack = vec![];
+                for path in paths {
+                    self.intersect_path_into(path, &ray, &mut transform_stack, &mut hit_register);
+                }
+                self.shade_register(hit_register, self.max_depth)
+                    .unwrap_or_else(|| self.background.colour_at(ray.direction))
+            })
+            .collect()
+    }
+
+    // intersect_ray_into for the shape at the end of the path (see
+    // PrimaryHits), within the groups along it
+    fn intersect_path_into<'a: 'r, 'r>(
+        &'a self,
+        path: &[usize],
+        world_ray: &'r Ray,
+        transform_stack: &mut Vec<&'r Transform>,
+        hit_register: &mut HitRegister<'r, dyn PrimitiveShape>,
+    ) {
+        let depth = transform_stack.len();
+        let mut shape = &self.objects[path[0]];
+        for &idx in &path[1..] {
+            shape = match shape {
+                Shape::Group(group) => {
+                    transform_stack.push(group.frame_transformation());
+                    &group.objects()[idx]
+                }
+                Shape::KdTree(kd_tree) => &kd_tree.objects()[idx],
+                _ => unreachable!("paths only go through groups and kd-trees"),
+            };
+        }
+        shape.intersect_ray_into(world_ray, transform_stack, hit_register);
+        transform_stack.truncate(depth);
+    }
+
+    // Only the first hits of Whitted shading are found together; the rays
+    // of a path or of each wavelength go their own ways, so are cast one by
+    // one. Either way, each ray's colour is as if it were cast on its own.
+    fn cast_packet_samples(&self, packet: &RayPacket, keys: &[SampleKey]) -> Vec<Colour> {
+        if !self.shades_first_hits() {
+            return packet
+                .rays()
+                .iter()
+                .zip(keys)
+                .map(|(&ray, &key)| self.cast_sample(ray, key))
+                .collect();
+        }
+
+        self.intersect_packet(packet)
+            .into_iter()
+            .zip(packet.rays())
+            .map(|(hit_register, ray)| {
+                self.shade_register(hit_register, self.max_depth)
+                    .unwrap_or_else(|| self.background.colour_at(ray.direction))
+            })
+            .collect()
+    }
+
+    // a camera ray's colour by the world's integrator, None if it misses
+    fn integrate(&self, ray: &Ray, key: SampleKey) -> Option<Colour> {
+        match &self.integrator {
+            Integrator::Whitted => self.shade_ray_hit(ray, self.max_depth),
+            Integrator::PathTracing(path_tracing) => {
+                self.trace_paths(ray, path_tracing, &mut key.random(Stream::Paths))
+            }
+        }
     }
 
     fn shade_ray(&self, ray: &Ray, depth_remaining: i32) -> Colour {
+        self.shade_ray_hit(ray, depth_remaining)
+            .unwrap_or_else(|| self.background.colour_at(ray.direction))
+    }
+
+    fn shade_ray_hit(&self, ray: &Ray, depth_remaining: i32) -> Option<Colour> {
         if depth_remaining == 0 {
-            return Colour::new(0.0, 0.0, 0.0);
+            return Some(Colour::new(0.0, 0.0, 0.0));
         }
 
-        let hit_register = self.intersect_ray(ray);
-
-        if let Some(computed_intersect) = hit_register.finalise_hit() {
-            let surface = self.shade_surface(&computed_intersect);
-            let reflected = self.shade_reflection(&computed_intersect, depth_remaining);
-            let refracted = self.shade_refraction(&computed_intersect, depth_remaining);
+        self.shade_register(self.intersect_ray(ray), depth_remaining)
+    }
 
-            let material = computed_intersect.object().material();
-            if material.reflectance > 0.0 && material.transparency > 0.0 {
-                let reflectance = computed_intersect.schlick_reflectance();
-                surface + reflected * reflectance + refracted * (1.0 - reflectance)
+    // shades the first hit of a ray's intersections, None if there is none
+    fn shade_register(
+        &self,
+        hit_register: HitRegister<'_, dyn PrimitiveShape>,
+        depth_remaining: i32,
+    ) -> Option<Colour> {
+        hit_register.finalise_hit().map(|computed_intersect| {
+            let shadowed = self.shadowed_lights(
+                computed_intersect.over_point(),
+                computed_intersect.computations().time(),
+            );
+            // only looked up if needed, as it means listing every object
+            let object_id = if self.light_links.is_empty() {
+                None
             } else {
-                surface + reflected + refracted
+                self.object_id(computed_intersect.object())
+            };
+            self.shade_hit(
+                computed_intersect.object().material(),
+                computed_intersect.computations(),
+                &shadowed,
+                object_id,
+                depth_remaining,
+            )
+        })
+    }
+
+    // shades a hit from its precomputed geometry, given how shadowed each
+    // light sample is (see shadowing); the material is passed separately so it can be
+    // overridden, and the hit object's id is only needed for light links
+    pub(crate) fn shade_hit(
+        &self,
+        material: &Material,
+        computations: &Computations,
+        shadowed: &[Float],
+        object_id: Option<usize>,
+        depth_remaining: i32,
+    ) -> Colour {
+        count(Counter::ShadingEvaluation);
+        let unshadowed;
+        let shadowed = if material.receives_shadow {
+            shadowed
+        } else {
+            unshadowed = vec![0.0; shadowed.len()];
+            &unshadowed
+        };
+        let clay;
+        let material = if self.lighting_only {
+            clay = Material::clay();
+            &clay
+        } else {
+            material
+        };
+        if let Some(shader) = &material.shader {
+            return shader.shade(computations, self);
+        }
+        let shading_mode = material.shading_mode.unwrap_or(self.shading_mode);
+        if let ShadingMode::Toon { silhouette, .. } = shading_mode {
+            if computations.eyev().dot(computations.normal()) < silhouette {
+                return Colour::new(0.0, 0.0, 0.0);
             }
+        }
+
+        let surface = self.shade_surface(material, computations, shadowed, object_id);
+        let reflected = self.shade_reflection(material, computations, depth_remaining);
+        let refracted = self.shade_refraction(material, computations, depth_remaining);
+
+        if material.reflectance > 0.0 && material.transparency > 0.0 {
+            let reflectance = computations.schlick_reflectance();
+            surface + reflected * reflectance + refracted * (1.0 - reflectance)
         } else {
-            return Colour::new(0.0, 0.0, 0.0);
+            surface + reflected + refracted
         }
     }
 
@@ -48,10 +486,10 @@ impl<'world: 'ray, 'ray> World {
         ray: &'ray Ray,
     ) -> HitRegister<'ray, dyn PrimitiveShape> {
         let mut ray_hit_register = HitRegister::empty();
+        let mut transform_stack = vec![];
 
         for shape in &self.objects {
-            let shape_hit_register = shape.intersect_ray(ray, vec![]);
-            ray_hit_register.combine_registers(shape_hit_register);
+            shape.intersect_ray_into(ray, &mut transform_stack, &mut ray_hit_register);
 
             // match shape {
             //     Shape::Primitive(primitive_shape) => {
@@ -68,89 +506,401 @@ impl<'world: 'ray, 'ray> World {
         ray_hit_register
     }
 
-    fn is_shadowed_point(&self, light: &Light, point: Point) -> bool {
-        let vector = light.position - point;
-        let distance = vector.magnitude();
-        let direction = vector.normalise();
+    // intersect_ray for each ray of the packet, in order
+    pub(crate) fn intersect_packet(
+        &'world self,
+        packet: &'ray RayPacket,
+    ) -> Vec<HitRegister<'ray, dyn PrimitiveShape>> {
+        let mut hit_registers: Vec<_> =
+            packet.rays().iter().map(|_| HitRegister::empty()).collect();
 
-        let ray = Ray::new(point, direction);
-        let hit_register = self.intersect_ray(&ray);
+        // lanes past the packet's rays are unused
+        let lanes = std::array::from_fn(|idx| idx < packet.rays().len());
+        let mut transform_stack = vec![];
+        for shape in &self.objects {
+            shape.intersect_packet_into(
+                packet,
+                packet,
+                lanes,
+                &mut transform_stack,
+                &mut hit_registers,
+            );
+        }
 
-        matches!(hit_register.finalise_hit(), Some(hit) if hit.t() < distance)
+        hit_registers
+    }
+
+    // every light's samples as seen from a point, in order, followed by those
+    // of glowing objects
+    pub(crate) fn light_samples(&self, point: Point) -> Vec<LightSample> {
+        self.linked_light_samples(point, None)
+            .into_iter()
+            .map(|(sample, _)| sample)
+            .collect()
+    }
+
+    // as light_samples, with whether each one shines on the object (any
+    // object, if None)
+    fn linked_light_samples(
+        &self,
+        point: Point,
+        object_id: Option<usize>,
+    ) -> Vec<(LightSample, bool)> {
+        let mut samples = vec![];
+        for (idx_light, light) in self.lights.iter().enumerate() {
+            let linked = match (self.light_links.get(&idx_light), object_id) {
+                (Some(light_link), Some(object_id)) => light_link.lights(object_id),
+                _ => true,
+            };
+            samples.extend(
+                light
+                    .samples(point)
+                    .into_iter()
+                    .map(|sample| (sample, linked)),
+            );
+        }
+        if self.emitter_resolution > 0 {
+            let emitters = self.emitters.get(&self.objects, self.emitter_resolution);
+            for emitter in emitters.iter() {
+                samples.extend(
+                    emitter
+                        .samples(point)
+                        .into_iter()
+                        .map(|sample| (sample, true)),
+                );
+            }
+        }
+        samples
+    }
+
+    // how much of a light sample is blocked from point, from 0 (none) to 1
+    // (all of it), ignoring objects that cast no shadow; only ever 0 or 1
+    // without transparent_shadows. At time, as the shadow ray is cast when
+    // the ray that hit point was.
+    pub(crate) fn shadowing(&self, sample: &LightSample, point: Point, time: Float) -> Float {
+        self.shadowing_by(self.intersect_ray(&shadow_ray(sample, point, time)))
+    }
+
+    // shadowing, given what the shadow ray towards the sample hits
+    fn shadowing_by(&self, hit_register: HitRegister<'_, dyn PrimitiveShape>) -> Float {
+        let hit_register = hit_register.shadow_casters();
+        if self.transparent_shadows {
+            return 1.0 - hit_register.transmission();
+        }
+
+        match hit_register.finalise_hit() {
+            Some(_) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    // whether a light sample is blocked entirely from point
+    pub(crate) fn is_shadowed_point(
+        &self,
+        sample: &LightSample,
+        point: Point,
+        time: Float,
+    ) -> bool {
+        self.shadowing(sample, point, time) >= 1.0
+    }
+
+    // whether every sample of a light is blocked entirely from a point; the
+    // light need not be one of the world's
+    pub fn is_shadowed(&self, point: Point, light: &dyn Light) -> bool {
+        light
+            .samples(point)
+            .iter()
+            .all(|sample| self.is_shadowed_point(sample, point, 0.0))
+    }
+
+    // how shadowed each of the light samples at a point is; the shadow rays
+    // all start at the point, so are cast in packets
+    pub(crate) fn shadowed_lights(&self, point: Point, time: Float) -> Vec<Float> {
+        let samples = self.light_samples(point);
+        let rays: Vec<Ray> = samples
+            .iter()
+            .map(|sample| shadow_ray(sample, point, time))
+            .collect();
+
+        let mut shadowed = Vec::with_capacity(samples.len());
+        for packet in RayPacket::packets(&rays) {
+            for hit_register in self.intersect_packet(&packet) {
+                shadowed.push(self.shadowing_by(hit_register));
+            }
+        }
+        shadowed
     }
 
     fn shade_surface(
         &self,
-        computed_intersect: &Intersect<dyn PrimitiveShape, Computed>,
+        material: &Material,
+        computations: &Computations,
+        shadowed: &[Float],
+        object_id: Option<usize>,
     ) -> Colour {
-        let mut surface_colour = Colour::new(0.0, 0.0, 0.0);
-        for light in &self.lights {
+        let mut surface_colour = material.emission;
+        let samples = self.linked_light_samples(computations.over_point(), object_id);
+        for ((sample, linked), &shadowed) in samples.iter().zip(shadowed) {
+            if !linked {
+                continue;
+            }
+            let shade = |shadowed| match material.shading_mode.unwrap_or(self.shading_mode) {
+                ShadingMode::Phong => computations.shade(material, sample, shadowed),
+                ShadingMode::Toon { bands, .. } => {
+                    computations.shade_toon(material, sample, shadowed, bands)
+                }
+            };
+            // partly shadowed (by transparent objects) is between the two
             surface_colour = surface_colour
-                + computed_intersect.shade(
-                    light,
-                    self.is_shadowed_point(light, computed_intersect.over_point()),
+                + match shadowed {
+                    shadowed if shadowed <= 0.0 => shade(false),
+                    shadowed if shadowed >= 1.0 => shade(true),
+                    shadowed => shade(false) * (1.0 - shadowed) + shade(true) * shadowed,
+                };
+        }
+        if let Some(irradiance) = &self.irradiance {
+            surface_colour = surface_colour
+                + computations.shade_indirect(
+                    material,
+                    irradiance.irradiance_at(computations.over_point(), computations.normal()),
                 );
         }
+        if let Some(ambient_light) = &self.ambient_light {
+            surface_colour = surface_colour
+                + computations
+                    .shade_indirect(material, ambient_light.irradiance(computations.normal()));
+        }
         surface_colour
     }
 
     fn shade_reflection(
         &self,
-        computed_intersect: &Intersect<dyn PrimitiveShape, Computed>,
+        material: &Material,
+        computations: &Computations,
         depth_remaining: i32,
     ) -> Colour {
         if depth_remaining == 0 {
             return Colour::new(0.0, 0.0, 0.0);
         }
 
-        let reflected_ray = computed_intersect.reflected_ray();
-        let reflectance = computed_intersect.object().material().reflectance;
+        let reflected_ray = computations.reflected_ray();
+        let reflectance = material.reflectance;
 
         if reflectance == 0.0 {
             return Colour::new(0.0, 0.0, 0.0);
         };
 
+        count(Counter::ReflectionRay);
         reflectance * self.shade_ray(&reflected_ray, depth_remaining - 1)
     }
 
     fn shade_refraction(
         &self,
-        computed_intersect: &Intersect<dyn PrimitiveShape, Computed>,
+        material: &Material,
+        computations: &Computations,
         depth_remaining: i32,
     ) -> Colour {
         if depth_remaining == 0 {
             return Colour::new(0.0, 0.0, 0.0);
         }
 
-        let transparency = computed_intersect.object().material().transparency;
+        let transparency = material.transparency;
 
         if transparency == 0.0 {
             return Colour::new(0.0, 0.0, 0.0);
         }
 
-        let (n1, n2) = computed_intersect.refraction_boundary();
+        let Some(refracted_ray) = computations.refracted_ray() else {
+            return Colour::new(0.0, 0.0, 0.0);
+        };
 
-        let n_ratio = n1 / n2;
-        let cos_i = computed_intersect.eyev().dot(computed_intersect.normal());
-        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        count(Counter::RefractionRay);
+        transparency * self.shade_ray(&refracted_ray, depth_remaining - 1)
+    }
+    // the average of a camera ray's paths (see PathTracing), None if it misses
+    fn trace_paths(
+        &self,
+        ray: &Ray,
+        path_tracing: &PathTracing,
+        random: &mut Random,
+    ) -> Option<Colour> {
+        let samples = path_tracing.samples.max(1);
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            // every path starts with the same first hit, so all of them miss
+            // if one does
+            let sample = path_tracing.clamp(self.trace_path(*ray, path_tracing, random)?);
+            colour = colour + sample * (1.0 / samples as Float);
+        }
+        Some(colour)
+    }
 
-        if sin2_t > 1.0 {
-            return Colour::new(0.0, 0.0, 0.0);
+    fn trace_path(
+        &self,
+        mut ray: Ray,
+        path_tracing: &PathTracing,
+        random: &mut Random,
+    ) -> Option<Colour> {
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        // the fraction of the light found from here on that reaches the camera
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        // a glowing surface reached by a diffuse bounce has already lit the
+        // surface before it as an emitter, so is only seen directly if
+        // emitters light nothing
+        let mut sees_emission = true;
+        // likewise, the background is seen without the parts of it that are
+        // lights after a diffuse bounce (see Background::fill_at)
+        let mut sees_background_lights = true;
+        for bounce in 0..=path_tracing.max_depth {
+            let Some(hit) = self.intersect_ray(&ray).finalise_hit() else {
+                if bounce == 0 {
+                    return None;
+                }
+                let background = if sees_background_lights {
+                    self.background.colour_at(ray.direction)
+                } else {
+                    self.background.fill_at(ray.direction)
+                };
+                return Some(colour + throughput * background);
+            };
+            let computations = hit.computations();
+            let clay;
+            let material = if self.lighting_only {
+                clay = Material::clay();
+                &clay
+            } else {
+                hit.object().material()
+            };
+            let object_id = if self.light_links.is_empty() {
+                None
+            } else {
+                self.object_id(hit.object())
+            };
+
+            if sees_emission {
+                colour = colour + throughput * material.emission;
+            }
+            colour = colour + throughput * self.direct_lighting(material, computations, object_id);
+
+            let Some((weight, next_ray, diffuse)) = scatter(material, computations, random) else {
+                break;
+            };
+            throughput = throughput * weight;
+            sees_emission = !diffuse || self.emitter_resolution == 0;
+            sees_background_lights = !diffuse;
+            // the hit borrows the ray it was found along
+            drop(hit);
+            ray = next_ray;
+            count(Counter::PathRay);
+
+            if bounce >= path_tracing.roulette_depth {
+                let survival = throughput
+                    .red
+                    .max(throughput.green)
+                    .max(throughput.blue)
+                    .min(1.0);
+                if random.next_f64() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
         }
+        Some(colour)
+    }
 
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let refracted_direction = computed_intersect.normal() * (n_ratio * cos_i - cos_t)
-            - computed_intersect.eyev() * n_ratio;
-        let refracted_ray = Ray::new(computed_intersect.under_point(), refracted_direction);
+    // the light reaching a surface straight from each light sample, less what
+    // is shadowed, and without the ambient term
+    fn direct_lighting(
+        &self,
+        material: &Material,
+        computations: &Computations,
+        object_id: Option<usize>,
+    ) -> Colour {
+        count(Counter::ShadingEvaluation);
+        let mut colour = Colour::new(0.0, 0.0, 0.0);
+        let point = computations.over_point();
+        for (sample, linked) in self.linked_light_samples(point, object_id) {
+            if !linked {
+                continue;
+            }
+            let shadowing = if material.receives_shadow {
+                self.shadowing(&sample, point, computations.time())
+            } else {
+                0.0
+            };
+            if shadowing >= 1.0 {
+                continue;
+            }
+            // shading in shadow leaves only the ambient term
+            let lit = computations.shade(material, &sample, false)
+                - computations.shade(material, &sample, true);
+            colour = colour + lit * (1.0 - shadowing);
+        }
+        colour
+    }
+}
 
-        transparency * self.shade_ray(&refracted_ray, depth_remaining - 1)
+// from point towards a light sample, stopping at the light
+fn shadow_ray(sample: &LightSample, point: Point, time: Float) -> Ray {
+    count(Counter::ShadowRay);
+    Ray::new(point, sample.direction)
+        .with_time(time)
+        .with_range(0.0, sample.distance)
+}
+
+// How a path carries on from a surface: reflected, refracted, or bounced
+// diffusely in a random direction, each chosen in proportion to how much light
+// the material sends that way. Returns the new ray with the weight of the
+// light it brings back and whether it was a diffuse bounce, or None if the
+// material sends no light on.
+fn scatter(
+    material: &Material,
+    computations: &Computations,
+    random: &mut Random,
+) -> Option<(Colour, Ray, bool)> {
+    let (reflected, refracted) = if material.reflectance > 0.0 && material.transparency > 0.0 {
+        let reflectance = computations.schlick_reflectance();
+        (reflectance, 1.0 - reflectance)
+    } else {
+        (material.reflectance, material.transparency)
+    };
+    // metals have no diffuse part
+    let diffuse = match material.metallic_roughness {
+        Some(model) => 1.0 - model.metallic,
+        None => material.diffuse,
+    };
+    let total = reflected + refracted + diffuse;
+    if total <= 0.0 {
+        return None;
+    }
+
+    let choice = random.next_f64() * total;
+    let weight = Colour::new(total, total, total);
+    if choice < reflected {
+        Some((weight, computations.reflected_ray(), false))
+    } else if choice < reflected + refracted {
+        // under total internal reflection, all of it is reflected instead
+        let ray = computations
+            .refracted_ray()
+            .unwrap_or_else(|| computations.reflected_ray());
+        Some((weight, ray, false))
+    } else {
+        let direction = cosine_hemisphere(computations.normal(), random);
+        let ray = Ray::new(computations.over_point(), direction)
+            .with_wavelength(computations.wavelength())
+            .with_time(computations.time());
+        let albedo = material.pattern.colour_at(computations.object_point());
+        Some((albedo * weight, ray, true))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::approx_eq;
+    use crate::objects::lights::spot_light::SpotLight;
+    use crate::scenes::{Integrator, PathTracing};
+    use crate::utils::consts::PI;
+    use crate::utils::{approx_eq, EPSILON};
 
     #[test]
     fn cast_ray() {
@@ -166,11 +916,8 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let colour = world.cast_ray(ray);
         let resulting_colour = Colour::new(0.380661, 0.475826, 0.285496);
@@ -179,6 +926,57 @@ mod tests {
         approx_eq!(colour.blue, resulting_colour.blue);
     }
 
+    #[test]
+    fn test_world_shading_and_shadows() {
+        let world = World::test_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let colour = world.cast_ray(ray);
+        approx_eq!(colour.red, 0.380661);
+        approx_eq!(colour.green, 0.475826);
+        approx_eq!(colour.blue, 0.285496);
+
+        let light = world.lights[0].clone();
+        assert!(!world.is_shadowed(Point::new(0.0, 10.0, 0.0), light.as_ref()));
+        assert!(world.is_shadowed(Point::new(10.0, -10.0, 10.0), light.as_ref()));
+        assert!(!world.is_shadowed(Point::new(-20.0, 20.0, -20.0), light.as_ref()));
+    }
+
+    #[test]
+    fn transparent_objects_cast_lighter_shadows() {
+        let ball = |transparency, casts_shadow| -> Shape {
+            Sphere::builder()
+                .set_material(Material {
+                    transparency,
+                    casts_shadow,
+                    ..Material::preset()
+                })
+                .build_into()
+        };
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        let point = Point::new(0.0, -5.0, 0.0);
+        let shadowing = |objects: Vec<Shape>| {
+            let world = World {
+                transparent_shadows: true,
+                ..World::new(objects, vec![Box::new(light.clone())])
+            };
+            world.shadowed_lights(point, 0.0)[0]
+        };
+
+        approx_eq!(shadowing(vec![ball(0.0, true)]), 1.0);
+        // both of the sphere's surfaces are crossed, but it only counts once
+        approx_eq!(shadowing(vec![ball(0.5, true)]), 0.5);
+        approx_eq!(shadowing(vec![ball(0.5, false)]), 0.0);
+
+        let world = World {
+            transparent_shadows: true,
+            ..World::new(vec![ball(0.5, true)], vec![Box::new(light.clone())])
+        };
+        assert!(!world.is_shadowed(point, &light));
+        // by default, transparent objects shadow as opaque ones do
+        let world = World::new(vec![ball(0.5, true)], vec![Box::new(light.clone())]);
+        assert!(world.is_shadowed(point, &light));
+    }
+
     #[test]
     fn cast_ray_inside() {
         let s1 = Sphere::builder()
@@ -193,11 +991,8 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(0.0, 0.25, 0.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(0.0, 0.25, 0.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let colour = world.cast_ray(ray);
         let resulting_colour = Colour::new(0.904984, 0.904984, 0.904984);
@@ -220,11 +1015,8 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
         let resulting_colour = Colour::new(0.0, 0.0, 0.0);
         assert_eq!(world.cast_ray(ray), resulting_colour);
@@ -244,11 +1036,8 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let colour = world.cast_ray(ray);
         let resulting_colour = Colour::new(0.380661, 0.475826, 0.285496);
@@ -257,6 +1046,95 @@ mod tests {
         approx_eq!(colour.blue, resulting_colour.blue);
     }
 
+    #[test]
+    fn packets_cast_as_rays_would() {
+        let mut world = World::test_world();
+        world.objects_mut().push(
+            Plane::builder()
+                .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -1.0, 0.0)))
+                .set_material(Material::preset())
+                .build_into(),
+        );
+        let rays: Vec<Ray> = (0..11)
+            .map(|idx| {
+                let x = idx as Float * 0.3 - 1.5;
+                Ray::new(
+                    Point::new(x, 0.5, -5.0),
+                    Vector::new(0.0, -0.1, 1.0).normalise(),
+                )
+            })
+            .collect();
+
+        let colours: Vec<Colour> = RayPacket::packets(&rays)
+            .flat_map(|packet| world.cast_packet(&packet))
+            .collect();
+        let expected: Vec<Colour> = rays.iter().map(|&ray| world.cast_ray(ray)).collect();
+        assert_eq!(colours, expected);
+
+        let point = Point::new(0.0, -0.99, 0.0);
+        let shadowed: Vec<Float> = world
+            .light_samples(point)
+            .iter()
+            .map(|sample| world.shadowing(sample, point, 0.0))
+            .collect();
+        assert_eq!(world.shadowed_lights(point, 0.0), shadowed);
+    }
+
+    #[test]
+    fn samples_cast_among_their_primary_hits() {
+        let sphere_at = |x: Float| {
+            Sphere::builder()
+                .set_frame_transformation(Transform::new(TransformKind::Translate(x, 0.0, 0.0)))
+                .set_material(Material::preset())
+                .build_into()
+        };
+        let plane = Plane::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -1.0, 0.0)))
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        // the second sphere is at x = 2 too, in its group's frame
+        let group = Group::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(1.0, 0.0, 0.0)))
+            .set_objects(vec![sphere_at(-10.0), sphere_at(1.0)])
+            .build_into();
+        let world = World::new(vec![sphere_at(-2.0), group, plane], vec![Box::new(light)]);
+        let samples: Vec<(Ray, SampleKey)> = [-2.0, 0.0, 2.0, 0.0]
+            .into_iter()
+            .map(|x| {
+                let direction = Vector::new(0.0, -0.1, 1.0).normalise();
+                (
+                    Ray::new(Point::new(x, 0.5, -5.0), direction),
+                    SampleKey::default(),
+                )
+            })
+            .collect();
+
+        // the plane is left for each ray to look at
+        let mut primary_hits = PrimaryHits {
+            hits: vec![
+                PrimaryHit::Primitive(0),
+                PrimaryHit::Missed,
+                PrimaryHit::Primitive(1),
+                PrimaryHit::Any,
+            ],
+            paths: Arc::from([[0].into(), [1, 1].into()]),
+            unchecked: Arc::from([2]),
+        };
+        let expected: Vec<Colour> = samples
+            .iter()
+            .map(|&(ray, key)| world.cast_sample(ray, key))
+            .collect();
+        assert_eq!(world.cast_samples_among(&samples, &primary_hits), expected);
+
+        // the sphere isn't looked at, so the ray goes on to the plane
+        primary_hits.hits[0] = PrimaryHit::Missed;
+        let colours = world.cast_samples_among(&samples, &primary_hits);
+        let (ray, _) = samples[0];
+        assert_ne!(colours[0], world.cast_ray(ray));
+        assert_ne!(colours[0], world.background.colour_at(ray.direction));
+    }
+
     #[test]
     fn cast_ray_intersects_behind() {
         let s1 = Sphere::builder()
@@ -275,9 +1153,9 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World::new(vec![s1, s2], vec![light]);
-        let inner = &world.objects[1];
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
+        let inner = &world.objects()[1];
         let ray = Ray::new(Point::new(0.0, 0.0, 0.75), Vector::new(0.0, 0.0, -1.0));
         if let Shape::Primitive(shape) = inner {
             let resulting_colour = shape
@@ -304,12 +1182,10 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
-        assert!(!world.is_shadowed_point(&world.lights[0], Point::new(0.0, 10.0, 0.0)));
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
+        let point = Point::new(0.0, 10.0, 0.0);
+        assert!(!world.is_shadowed_point(&world.light_samples(point)[0], point, 0.0));
     }
 
     #[test]
@@ -326,13 +1202,10 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let point = Point::new(0.0, 10.0, 0.0);
-        assert!(!world.is_shadowed_point(&world.lights[0], point));
+        assert!(!world.is_shadowed_point(&world.light_samples(point)[0], point, 0.0));
     }
 
     #[test]
@@ -349,13 +1222,50 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let point = Point::new(10.0, -10.0, 10.0);
-        assert!(world.is_shadowed_point(&world.lights[0], point));
+        assert!(world.is_shadowed_point(&world.light_samples(point)[0], point, 0.0));
+    }
+
+    #[test]
+    fn shadow_casting_and_receiving_flags() {
+        let floor = |receives_shadow| {
+            Plane::builder()
+                .set_material(Material {
+                    receives_shadow,
+                    ..Material::preset()
+                })
+                .build_into()
+        };
+        let ball = |casts_shadow| {
+            Sphere::builder()
+                .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 2.0, 0.0)))
+                .set_material(Material {
+                    casts_shadow,
+                    ..Material::preset()
+                })
+                .build_into()
+        };
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        // from the side, under the ball, onto the floor right below it
+        let ray = Ray::new(
+            Point::new(5.0, 1.0, 0.0),
+            Vector::new(-5.0, -1.0, 0.0).normalise(),
+        );
+        let colour_under_ball = |receives_shadow, casts_shadow| {
+            World::new(
+                vec![floor(receives_shadow), ball(casts_shadow)],
+                vec![Box::new(light.clone())],
+            )
+            .cast_ray(ray)
+        };
+
+        let shadowed = colour_under_ball(true, true);
+        approx_eq!(shadowed.red, 0.1);
+        let lit = colour_under_ball(true, false);
+        assert!(lit.red > 0.9);
+        assert_eq!(colour_under_ball(false, true), lit);
     }
 
     #[test]
@@ -372,13 +1282,10 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let point = Point::new(-20.0, 20.0, -20.0);
-        assert!(!world.is_shadowed_point(&world.lights[0], point));
+        assert!(!world.is_shadowed_point(&world.light_samples(point)[0], point, 0.0));
     }
 
     #[test]
@@ -395,13 +1302,10 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let point = Point::new(-2.0, 2.0, -2.0);
-        assert!(!world.is_shadowed_point(&world.lights[0], point));
+        assert!(!world.is_shadowed_point(&world.light_samples(point)[0], point, 0.0));
     }
 
     #[test]
@@ -413,15 +1317,17 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.0, 10.0)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World::new(vec![s1, s2], vec![light]);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
+        let sample = world.light_samples(computed_intersect.target())[0];
         let resulting_colour = Colour::new(0.1, 0.1, 0.1);
         assert_eq!(
-            computed_intersect.shade(
-                &world.lights[0],
-                world.is_shadowed_point(&world.lights[0], computed_intersect.target()),
+            computed_intersect.computations().shade(
+                computed_intersect.object().material(),
+                &sample,
+                world.is_shadowed_point(&sample, computed_intersect.target(), 0.0),
             ),
             resulting_colour
         );
@@ -444,16 +1350,17 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
         let resulting_colour = Colour::new(0.0, 0.0, 0.0);
         assert_eq!(
-            world.shade_reflection(&computed_intersect, 10),
+            world.shade_reflection(
+                computed_intersect.object().material(),
+                computed_intersect.computations(),
+                10,
+            ),
             resulting_colour
         );
     }
@@ -479,17 +1386,18 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2, s3],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2, s3], vec![Box::new(light)]);
         let ray = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+            Vector::new(0.0, -Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0),
         );
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
-        let colour = world.shade_reflection(&computed_intersect, 10);
+        let colour = world.shade_reflection(
+            computed_intersect.object().material(),
+            computed_intersect.computations(),
+            10,
+        );
         let resulting_colour = Colour::new(0.190331, 0.237913, 0.142748);
         approx_eq!(colour.red, resulting_colour.red);
         approx_eq!(colour.green, resulting_colour.green);
@@ -517,14 +1425,11 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2, s3],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2, s3], vec![Box::new(light)]);
         let ray = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+            Vector::new(0.0, -Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0),
         );
         let colour = world.cast_ray(ray);
         let resulting_colour = Colour::new(0.876756, 0.924339, 0.829173);
@@ -549,16 +1454,111 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(0.0, 0.0, 0.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
         // the following method call should terminate in finite time
         world.cast_ray(ray);
     }
 
+    #[test]
+    fn see_through_glass() {
+        let floor = Plane::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -2.0, 0.0)))
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+                ..Material::preset()
+            })
+            .build_into();
+        let ball = Sphere::builder()
+            .set_material(Material::glass())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![floor, ball], vec![Box::new(light)]);
+        // head on, hardly anything is reflected, so the floor shows through
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let colour = world.cast_ray(ray);
+        assert!(colour.red > 0.05);
+        assert!(colour.red > 5.0 * colour.green.max(colour.blue));
+    }
+
+    #[test]
+    fn spectral_rendering_disperses_light() {
+        let prism = |dispersion| {
+            let ball = Sphere::builder()
+                .set_material(Material {
+                    dispersion,
+                    ..Material::glass()
+                })
+                .build_into();
+            let mut world = World::new(vec![ball], vec![]);
+            world.background = Background::Gradient {
+                bottom: Colour::new(0.0, 0.0, 0.0),
+                top: Colour::new(1.0, 1.0, 1.0),
+            };
+            world.spectral_samples = Some(16);
+            world
+        };
+        // off centre, so that refraction bends the ray towards the gradient
+        let ray = Ray::new(Point::new(0.0, 0.6, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let colour = prism(0.0).cast_ray(ray);
+        approx_eq!(colour.red, colour.blue);
+        let colour = prism(0.05).cast_ray(ray);
+        assert!((colour.red - colour.blue).abs() > 0.005, "{colour:?}");
+    }
+
+    #[test]
+    fn misses_and_reflections_see_the_background() {
+        let mirror = Plane::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                reflectance: 1.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let mut world = World::new(vec![mirror], vec![]);
+        world.background = Background::Gradient {
+            bottom: Colour::new(1.0, 0.0, 0.0),
+            top: Colour::new(0.0, 0.0, 1.0),
+        };
+        let up = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(world.cast_ray(up), Colour::new(0.0, 0.0, 1.0));
+        assert_eq!(world.cast_ray_hit(up), None);
+        let down = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(world.cast_ray(down), Colour::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn max_depth_limits_reflections() {
+        let floor = Plane::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.0, 0.0, 0.0))),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                reflectance: 1.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let ball = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 2.0, 0.0)))
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![floor, ball], vec![Box::new(light)]);
+        // straight down at the mirror, which reflects the underside of the ball
+        let ray = Ray::new(Point::new(0.0, 0.5, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_ne!(world.cast_ray(ray), Colour::new(0.0, 0.0, 0.0));
+
+        world.max_depth = 1;
+        assert_eq!(world.cast_ray(ray), Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(World::default().max_depth, World::MAX_RAYCAST_DEPTH);
+    }
+
     #[test]
     fn refracted_colour_of_opaque_object() {
         let s1 = Sphere::builder()
@@ -573,16 +1573,17 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
         let resulting_colour = Colour::new(0.0, 0.0, 0.0);
         assert_eq!(
-            world.shade_refraction(&computed_intersect, 10),
+            world.shade_refraction(
+                computed_intersect.object().material(),
+                computed_intersect.computations(),
+                10,
+            ),
             resulting_colour
         );
     }
@@ -603,24 +1604,25 @@ mod tests {
             .set_frame_transformation(Transform::new(TransformKind::Scale(0.5, 0.5, 0.5)))
             .set_material(Material::preset())
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(
-            Point::new(0.0, 0.0, 2.0_f64.sqrt() / 2.0),
+            Point::new(0.0, 0.0, Float::sqrt(2.0) / 2.0),
             Vector::new(0.0, 1.0, 0.0),
         );
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
         let resulting_colour = Colour::new(0.0, 0.0, 0.0);
         assert_eq!(
-            world.shade_refraction(&computed_intersect, 10),
+            world.shade_refraction(
+                computed_intersect.object().material(),
+                computed_intersect.computations(),
+                10,
+            ),
             resulting_colour
         );
     }
 
-    #[derive(Debug)]
+    #[derive(Clone, Debug)]
     struct TestPattern {
         frame_transformation: Transform,
     }
@@ -644,6 +1646,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pattern_in_object_and_pattern_space() {
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Scale(2.0, 2.0, 2.0)))
+            .set_material(Material {
+                pattern: Box::new(TestPattern::new(Transform::new(TransformKind::Translate(
+                    0.5, 1.0, 1.5,
+                )))),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let group = Group::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 0.0, 2.0)))
+            .set_objects(vec![sphere])
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![group], vec![Box::new(light)]);
+
+        // hits the sphere at (0, 0, 0) in world space, (0, 0, -1) in object
+        // space, and (-0.5, -1, -2.5) in pattern space
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let colour = world.cast_ray(ray);
+        approx_eq!(colour.red, -0.5);
+        approx_eq!(colour.green, -1.0);
+        approx_eq!(colour.blue, -2.5);
+    }
+
     #[test]
     fn refracted_colour_from_refracted_ray() {
         let s1 = Sphere::builder()
@@ -663,18 +1695,21 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2], vec![Box::new(light)]);
         let ray = Ray::new(Point::new(0.0, 0.0, 0.1), Vector::new(0.0, 1.0, 0.0));
         let computed_intersect = world.intersect_ray(&ray).finalise_hit().unwrap();
-        let colour = world.shade_refraction(&computed_intersect, 10);
+        let colour = world.shade_refraction(
+            computed_intersect.object().material(),
+            computed_intersect.computations(),
+            10,
+        );
+        // the colour is the point the refracted ray hits, which is found from
+        // EPSILON below the surface, so it can be out by about as much
         let resulting_colour = Colour::new(0.0, 0.998884, 0.047216);
-        approx_eq!(colour.red, resulting_colour.red);
-        approx_eq!(colour.green, resulting_colour.green);
-        approx_eq!(colour.blue, resulting_colour.blue);
+        approx_eq!(colour.red, resulting_colour.red, 2.0 * EPSILON);
+        approx_eq!(colour.green, resulting_colour.green, 2.0 * EPSILON);
+        approx_eq!(colour.blue, resulting_colour.blue, 2.0 * EPSILON);
     }
 
     #[test]
@@ -708,15 +1743,12 @@ mod tests {
                 ..Material::preset()
             })
             .build_into();
-        let light = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
-        let world = World {
-            objects: vec![s1, s2, s3, s4],
-            lights: vec![light],
-        };
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![s1, s2, s3, s4], vec![Box::new(light)]);
 
         let ray = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+            Vector::new(0.0, -Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0),
         );
         let colour = world.cast_ray(ray);
         let resulting_colour = Colour::new(0.933915, 0.696434, 0.692431);
@@ -747,4 +1779,454 @@ mod tests {
         approx_eq!(normal.y, resulting_normal.y);
         approx_eq!(normal.z, resulting_normal.z);
     }
+
+    #[test]
+    fn shade_hit_with_baked_irradiance() {
+        let floor = Plane::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, -3.0, 0.0)))
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 1.0, 1.0))),
+                ambient: 1.0,
+                ..Material::default()
+            })
+            .build_into();
+        let sphere = Sphere::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.5, 0.25))),
+                diffuse: 0.5,
+                ..Material::default()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![floor, sphere], vec![Box::new(light)]);
+        let ray = Ray::new(Point::new(0.0, -2.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(world.cast_ray(ray), Colour::new(0.0, 0.0, 0.0));
+
+        world
+            .bake_irradiance(
+                BoundingBox::from_axial_bounds([-1.0, 1.0], [-2.0, -1.5], [-1.0, 1.0]),
+                [2, 2, 2],
+                32,
+            )
+            .unwrap();
+        let colour = world.cast_ray(ray);
+        approx_eq!(colour.red, 0.5);
+        approx_eq!(colour.green, 0.25);
+        approx_eq!(colour.blue, 0.125);
+    }
+
+    #[test]
+    fn area_light_casts_soft_shadows() {
+        let ball = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 5.0, 0.0)))
+            .build_into();
+        let light = AreaLight::new(
+            Point::new(-1.0, 10.0, -0.5),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Colour::new(1.0, 1.0, 1.0),
+            [4, 1],
+        );
+        let world = World::new(vec![ball], vec![Box::new(light)]);
+        let blocked = |x| {
+            world
+                .shadowed_lights(Point::new(x, 0.0, 0.0), 0.0)
+                .into_iter()
+                .filter(|&shadowed| shadowed >= 1.0)
+                .count()
+        };
+        // umbra, penumbra and full light
+        assert_eq!(blocked(0.0), 4);
+        assert_eq!(blocked(1.5), 3);
+        assert_eq!(blocked(3.0), 0);
+    }
+
+    #[test]
+    fn glowing_objects_light_their_surroundings() {
+        let floor = Plane::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let lamp = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 3.0, 0.0)))
+            .set_material(Material {
+                emission: Colour::new(1.0, 0.5, 0.25),
+                ..Material::default()
+            })
+            .build_into();
+        let blocker = Cube::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(6.0, 1.0, 0.0)))
+            .set_material(Material::preset())
+            .build_into();
+        let mut world = World::new(vec![floor, lamp, blocker], vec![]);
+        let colour_at = |world: &World, x| {
+            world.cast_ray(Ray::new(
+                Point::new(x, 1.0, 0.0),
+                Vector::new(0.0, -1.0, 0.0),
+            ))
+        };
+
+        // the lamp glows, and lights the floor under it in its own colour
+        let lamp_colour = world.cast_ray(Ray::new(
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+        ));
+        assert_eq!(lamp_colour, Colour::new(1.0, 0.5, 0.25));
+        let under_lamp = colour_at(&world, 0.0);
+        assert!(under_lamp.red > 0.5 && under_lamp.red > under_lamp.blue);
+        // but not behind the blocker
+        assert!(colour_at(&world, 8.0).red < 0.2);
+
+        world.emitter_resolution = 0;
+        assert_eq!(colour_at(&world, 0.0), Colour::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn emitters_are_found_once() {
+        let lamp = |emission| -> Shape {
+            Sphere::builder()
+                .set_material(Material {
+                    emission,
+                    ..Material::default()
+                })
+                .build_into()
+        };
+        let glowing = Colour::new(1.0, 1.0, 1.0);
+        let mut world = World::new(vec![lamp(glowing)], vec![]);
+        world.emitter_resolution = 2;
+        let point = Point::new(0.0, 5.0, 0.0);
+        assert_eq!(world.light_samples(point).len(), 2);
+        let found = world.emitters.get(&world.objects, 2);
+        assert!(matches!(found, Cow::Borrowed(_)));
+
+        // more objects, or another resolution, are noticed
+        world.objects_mut().push(lamp(glowing));
+        assert_eq!(world.light_samples(point).len(), 4);
+        world.emitter_resolution = 3;
+        assert_eq!(world.light_samples(point).len(), 6);
+
+        // as are objects replaced in place, even where the list of them is
+        // neither moved nor resized
+        let mut world = World::new(vec![lamp(glowing)], vec![]);
+        assert_eq!(world.light_samples(point).len(), World::EMITTER_RESOLUTION);
+        world.objects_mut()[0] = lamp(Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(world.light_samples(point).len(), 0);
+    }
+
+    #[test]
+    fn path_tracing_without_bounces_is_direct_lighting() {
+        let matte = || Material {
+            ambient: 0.0,
+            specular: 0.0,
+            ..Material::preset()
+        };
+        let floor = Plane::builder().set_material(matte()).build_into();
+        let sphere = Sphere::builder()
+            .set_frame_transformation(Transform::new(TransformKind::Translate(0.0, 1.0, 0.0)))
+            .set_material(matte())
+            .build_into();
+        let light = PointLight::new(Point::new(-4.0, 6.0, -4.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![floor, sphere], vec![Box::new(light)]);
+        let rays = [
+            Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(3.0, 1.0, -3.0), Vector::new(0.0, -1.0, 0.5)),
+            Ray::new(Point::new(1.0, 1.0, 3.0), Vector::new(0.0, -1.0, -0.2)),
+        ];
+        let whitted = rays.map(|ray| world.cast_ray(ray));
+
+        world.integrator = Integrator::PathTracing(PathTracing {
+            max_depth: 0,
+            ..PathTracing::new(4)
+        });
+        for (ray, whitted) in rays.into_iter().zip(whitted) {
+            let traced = world.cast_ray(ray);
+            approx_eq!(traced.red, whitted.red);
+            approx_eq!(traced.green, whitted.green);
+            approx_eq!(traced.blue, whitted.blue);
+        }
+        let miss = Ray::new(Point::new(5.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(world.cast_ray_hit(miss), None);
+    }
+
+    #[test]
+    fn path_tracing_bleeds_colour_between_surfaces() {
+        let floor = Plane::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(0.8, 0.8, 0.8))),
+                ambient: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let wall = Plane::builder()
+            .set_frame_transformation(
+                Transform::new(TransformKind::Rotate(
+                    Axis::Z,
+                    Angle::from_radians(PI / 2.0),
+                ))
+                .compose(&Transform::new(TransformKind::Translate(1.0, 0.0, 0.0))),
+            )
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.0, 0.0))),
+                ambient: 0.0,
+                specular: 0.0,
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(-2.0, 4.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![floor, wall], vec![Box::new(light)]);
+        let near_wall = Ray::new(Point::new(0.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        let whitted = world.cast_ray(near_wall);
+        approx_eq!(whitted.red, whitted.green);
+
+        world.integrator = Integrator::PathTracing(PathTracing::new(64));
+        let traced = world.cast_ray(near_wall);
+        assert!(traced.red > traced.green + 0.05);
+        assert!(traced.green >= whitted.green - EPSILON);
+        // the same paths every time
+        assert_eq!(traced, world.cast_ray(near_wall));
+    }
+
+    #[test]
+    fn path_tracing_clamps_bright_samples() {
+        let mut world = World::test_world();
+        world.lights = vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Colour::new(50.0, 50.0, 50.0),
+        ))];
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        world.integrator = Integrator::PathTracing(PathTracing::new(8));
+        assert!(world.cast_ray(ray).green > 1.0);
+
+        world.integrator = Integrator::PathTracing(PathTracing {
+            max_sample: Some(1.0),
+            ..PathTracing::new(8)
+        });
+        let clamped = world.cast_ray(ray);
+        assert!(clamped.red.max(clamped.green).max(clamped.blue) <= 1.0 + EPSILON);
+    }
+
+    #[test]
+    fn linked_lights_only_shine_on_their_objects() {
+        let sphere_at = |x| {
+            Sphere::builder()
+                .set_frame_transformation(Transform::new(TransformKind::Translate(x, 0.0, 0.0)))
+                .set_material(Material::preset())
+                .build_into()
+        };
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![sphere_at(-2.0), sphere_at(2.0)], vec![Box::new(light)]);
+        let colour_at = |world: &World, x| {
+            world.cast_ray(Ray::new(
+                Point::new(x, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+            ))
+        };
+        let lit = colour_at(&world, 2.0);
+        assert!(lit.red > 0.9);
+
+        world.light_links.insert(0, LightLink::only([0]));
+        assert_eq!(colour_at(&world, -2.0), lit);
+        assert_eq!(colour_at(&world, 2.0), Colour::new(0.0, 0.0, 0.0));
+
+        world.light_links.insert(0, LightLink::except([0]));
+        assert_eq!(colour_at(&world, -2.0), Colour::new(0.0, 0.0, 0.0));
+        assert_eq!(colour_at(&world, 2.0), lit);
+    }
+
+    #[test]
+    fn spot_light_lights_a_pool() {
+        let floor = Plane::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = SpotLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Colour::new(1.0, 1.0, 1.0),
+            Angle::from_degrees(20.0),
+            Angle::from_degrees(40.0),
+        );
+        let world = World::new(vec![floor], vec![Box::new(light)]);
+        let colour_at = |x| {
+            world
+                .cast_ray(Ray::new(
+                    Point::new(x, 1.0, 0.0),
+                    Vector::new(0.0, -1.0, 0.0),
+                ))
+                .red
+        };
+        // full light in the middle, fading to darkness outside the cone
+        assert!(colour_at(0.0) > 0.9);
+        assert!(colour_at(6.0) > 0.1 && colour_at(6.0) < colour_at(3.0));
+        assert_eq!(colour_at(9.0), 0.0);
+    }
+
+    #[test]
+    fn shade_hit_with_ambient_light() {
+        let sphere = Sphere::builder()
+            .set_material(Material {
+                pattern: Box::new(Solid::new(Colour::new(1.0, 0.5, 0.25))),
+                diffuse: 0.5,
+                ..Material::default()
+            })
+            .build_into();
+        // the only light is behind the sphere, so the near side is unlit
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![sphere], vec![Box::new(light)]);
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(world.cast_ray(ray), Colour::new(0.0, 0.0, 0.0));
+
+        world.ambient_light = Some(AmbientLight::Flat(Colour::new(0.4, 0.4, 0.4)));
+        let colour = world.cast_ray(ray);
+        approx_eq!(colour.red, 0.2);
+        approx_eq!(colour.green, 0.1);
+        approx_eq!(colour.blue, 0.05);
+    }
+
+    #[test]
+    fn lighting_only_ignores_surface_materials() {
+        let textured = Material {
+            pattern: Box::new(Checker::new(
+                Colour::new(1.0, 0.0, 0.0),
+                Colour::new(0.0, 0.0, 1.0),
+                Transform::new(TransformKind::Scale(0.1, 0.1, 0.1)),
+            )),
+            reflectance: 0.5,
+            ..Material::preset()
+        };
+        let sphere = Sphere::builder().set_material(textured).build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![sphere], vec![Box::new(light.clone())]);
+        world.lighting_only = true;
+
+        let clay_sphere = Sphere::builder()
+            .set_material(Material::clay())
+            .build_into();
+        let clay_world = World::new(vec![clay_sphere], vec![Box::new(light)]);
+        for direction in [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.1, 0.05, 1.0).normalise(),
+            Vector::new(-0.15, -0.1, 1.0).normalise(),
+        ] {
+            let ray = Ray::new(Point::new(0.0, 0.0, -5.0), direction);
+            let colour = world.cast_ray(ray);
+            assert_eq!(colour, clay_world.cast_ray(ray));
+            assert_eq!(colour.red, colour.blue);
+        }
+    }
+
+    #[test]
+    fn toon_shading_per_material_with_silhouettes() {
+        let toon = |shading_mode| {
+            Sphere::builder()
+                .set_material(Material {
+                    shading_mode,
+                    ..Material::preset()
+                })
+                .build_into()
+        };
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let centre = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let edge = Ray::new(Point::new(0.0, 0.99, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        // head on, toon shading has no highlight, unlike the world's Phong
+        let phong = World::new(vec![toon(None)], vec![Box::new(light.clone())]);
+        let banded = World::new(
+            vec![toon(Some(ShadingMode::toon(4)))],
+            vec![Box::new(light.clone())],
+        );
+        approx_eq!(banded.cast_ray(centre).red, 1.0);
+        assert!(phong.cast_ray(centre).red > 1.5);
+        assert!(banded.cast_ray(edge).red > 0.0);
+
+        let inked = World::new(
+            vec![toon(Some(ShadingMode::Toon {
+                bands: 4,
+                silhouette: 0.3,
+            }))],
+            vec![Box::new(light)],
+        );
+        assert_eq!(inked.cast_ray(edge), Colour::new(0.0, 0.0, 0.0));
+        approx_eq!(inked.cast_ray(centre).red, 1.0);
+    }
+
+    #[test]
+    fn material_shader_replaces_shading() {
+        // x-ray: brighter where the surface is seen edge on
+        let x_ray = Shader::new(|computations, _| {
+            let edge = 1.0 - computations.eyev().dot(computations.normal()).abs();
+            Colour::new(edge, edge, edge)
+        });
+        let sphere = Sphere::builder()
+            .set_material(Material {
+                shader: Some(x_ray),
+                ..Material::preset()
+            })
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let mut world = World::new(vec![sphere], vec![Box::new(light)]);
+
+        let centre = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let edge = Ray::new(Point::new(0.0, 0.99, -5.0), Vector::new(0.0, 0.0, 1.0));
+        approx_eq!(world.cast_ray(centre).red, 0.0);
+        assert!(world.cast_ray(edge).red > 0.8);
+
+        // the shader belongs to the material, so lighting only replaces it too
+        world.lighting_only = true;
+        assert!(world.cast_ray(centre).red > 0.5);
+    }
+
+    #[test]
+    fn cloned_world_renders_identically() {
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let group = Group::builder()
+            .add_object(
+                Cube::builder()
+                    .set_frame_transformation(Transform::new(TransformKind::Translate(
+                        0.0, 0.0, 3.0,
+                    )))
+                    .build_into(),
+            )
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere, group], vec![Box::new(light)]);
+        let cloned_world = world.clone();
+
+        assert_eq!(cloned_world.primitives().len(), 2);
+        // the clone owns separate copies of every primitive
+        assert!(!std::ptr::addr_eq(
+            world.primitives()[0],
+            cloned_world.primitives()[0]
+        ));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(world.cast_ray(ray), cloned_world.cast_ray(ray));
+    }
+
+    #[test]
+    fn share_world_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+
+        let sphere = Sphere::builder()
+            .set_material(Material::preset())
+            .build_into();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+        let world = World::new(vec![sphere], vec![Box::new(light)]);
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let colours: Vec<Colour> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| world.cast_ray(ray)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        for colour in colours {
+            assert_eq!(colour, world.cast_ray(ray));
+        }
+    }
 }
@@ -0,0 +1,72 @@
+// A minimal encoder for uncompressed 24-bit Windows bitmaps. Rows are stored
+// bottom to top, each padded to a multiple of four bytes.
+
+const HEADER_SIZE: usize = 14 + 40;
+
+// sizes are signed 32-bit, and the file's size unsigned 32-bit
+pub fn fits(width: usize, height: usize) -> bool {
+    let file_size = (width.checked_mul(3).map(|row| row.div_ceil(4) * 4))
+        .and_then(|stride| stride.checked_mul(height))
+        .and_then(|pixels| pixels.checked_add(HEADER_SIZE));
+    width <= i32::MAX as usize
+        && height <= i32::MAX as usize
+        && file_size.is_some_and(|size| size <= u32::MAX as usize)
+}
+
+// rgb holds the rows top to bottom, three bytes per pixel
+pub fn encode_rgb(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert!(fits(width, height));
+    assert_eq!(rgb.len(), width * height * 3);
+    let stride = (width * 3).div_ceil(4) * 4;
+    let file_size = HEADER_SIZE + stride * height;
+
+    let mut bmp = b"BM".to_vec();
+    bmp.extend((file_size as u32).to_le_bytes());
+    bmp.extend([0; 4]);
+    bmp.extend((HEADER_SIZE as u32).to_le_bytes());
+    // BITMAPINFOHEADER: one plane, 24 bits per pixel, uncompressed, at 72
+    // dots per inch, with no palette
+    bmp.extend(40_u32.to_le_bytes());
+    bmp.extend((width as i32).to_le_bytes());
+    bmp.extend((height as i32).to_le_bytes());
+    bmp.extend(1_u16.to_le_bytes());
+    bmp.extend(24_u16.to_le_bytes());
+    bmp.extend(0_u32.to_le_bytes());
+    bmp.extend(((stride * height) as u32).to_le_bytes());
+    bmp.extend([2835_u32.to_le_bytes(), 2835_u32.to_le_bytes()].concat());
+    bmp.extend([0; 8]);
+
+    for row in rgb.chunks(width.max(1) * 3).take(height).rev() {
+        for pixel in row.chunks(3) {
+            bmp.extend([pixel[2], pixel[1], pixel[0]]);
+        }
+        bmp.extend(vec![0; stride - width * 3]);
+    }
+    bmp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_bottom_up_padded_rows() {
+        // 1 by 2: red over blue
+        let bmp = encode_rgb(1, 2, &[255, 0, 0, 0, 0, 255]);
+        assert_eq!(bmp.len(), HEADER_SIZE + 8);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(&bmp[2..6], &(bmp.len() as u32).to_le_bytes());
+        assert_eq!(&bmp[18..26], &[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&bmp[HEADER_SIZE..], &[255, 0, 0, 0, 0, 0, 255, 0]);
+    }
+
+    #[test]
+    fn sizes_past_the_header_fields_do_not_fit() {
+        assert!(fits(20_000, 20_000));
+        // over 4 GiB of pixels
+        assert!(!fits(30_000, 50_000));
+        assert!(!fits(1 << 31, 1));
+        assert!(!fits(1, 1 << 31));
+        assert!(!fits(usize::MAX, 1));
+    }
+}
@@ -0,0 +1,122 @@
+// A minimal OpenEXR encoder for RGBA images of 32-bit floats, stored as
+// uncompressed scanlines, so values outside 0 to 1 (and depths) survive
+// intact. Like the PNG encoder, it trades file size for simplicity.
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+// rgba holds the rows top to bottom, four floats per pixel, with colours
+// premultiplied by alpha as EXR expects
+pub fn encode_rgba(width: usize, height: usize, rgba: &[f32]) -> Vec<u8> {
+    assert_eq!(rgba.len(), width * height * 4);
+
+    let mut channels = vec![];
+    // channels must be listed (and stored) in alphabetical order
+    for name in [b'A', b'B', b'G', b'R'] {
+        channels.extend([name, 0]);
+        channels.extend(PIXEL_TYPE_FLOAT.to_le_bytes());
+        // linear flag and reserved bytes, then x and y sampling
+        channels.extend([0, 0, 0, 0]);
+        channels.extend(1_i32.to_le_bytes());
+        channels.extend(1_i32.to_le_bytes());
+    }
+    channels.push(0);
+
+    let mut window = vec![];
+    for bound in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend(bound.to_le_bytes());
+    }
+
+    let mut exr = MAGIC.to_vec();
+    exr.extend(VERSION);
+    write_attribute(&mut exr, "channels", "chlist", &channels);
+    write_attribute(&mut exr, "compression", "compression", &[0]);
+    write_attribute(&mut exr, "dataWindow", "box2i", &window);
+    write_attribute(&mut exr, "displayWindow", "box2i", &window);
+    write_attribute(&mut exr, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut exr,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    write_attribute(&mut exr, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut exr,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    exr.push(0);
+
+    // one scanline per block, each located through the offset table
+    let block_size = 8 + width * 4 * 4;
+    let first_block = exr.len() + height * 8;
+    for row in 0..height {
+        exr.extend(((first_block + row * block_size) as u64).to_le_bytes());
+    }
+    for (row, pixels) in rgba.chunks(width * 4).take(height).enumerate() {
+        exr.extend((row as i32).to_le_bytes());
+        exr.extend(((width * 4 * 4) as i32).to_le_bytes());
+        for channel in [3, 2, 1, 0] {
+            for pixel in pixels.chunks(4) {
+                exr.extend(pixel[channel].to_le_bytes());
+            }
+        }
+    }
+    exr
+}
+
+fn write_attribute(exr: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    exr.extend(name.as_bytes());
+    exr.push(0);
+    exr.extend(kind.as_bytes());
+    exr.push(0);
+    exr.extend((value.len() as i32).to_le_bytes());
+    exr.extend(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_i32(data: &[u8], position: usize) -> i32 {
+        i32::from_le_bytes(data[position..position + 4].try_into().unwrap())
+    }
+
+    fn read_f32(data: &[u8], position: usize) -> f32 {
+        f32::from_le_bytes(data[position..position + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn encode_layout() {
+        // 2x2: one bright red pixel, the rest grey and half transparent
+        let mut rgba = vec![0.25, 0.25, 0.25, 0.5];
+        rgba.extend([4.0, 0.0, 0.0, 1.0]);
+        rgba.extend([0.25, 0.25, 0.25, 0.5].repeat(2));
+        let exr = encode_rgba(2, 2, &rgba);
+        assert_eq!(exr[..8], [0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        assert!(exr[8..].starts_with(b"channels\0chlist\0"));
+
+        // the last offset table entry is followed directly by the first block
+        let blocks = exr.len() - 2 * (8 + 2 * 16);
+        let offset = |row: usize| {
+            u64::from_le_bytes(
+                exr[blocks - 16 + row * 8..blocks - 8 + row * 8]
+                    .try_into()
+                    .unwrap(),
+            ) as usize
+        };
+        assert_eq!(offset(0), blocks);
+        assert_eq!(read_i32(&exr, offset(1)), 1);
+        assert_eq!(read_i32(&exr, offset(0) + 4), 32);
+
+        // per scanline, all of A, then B, G and R
+        let first_row = offset(0) + 8;
+        assert_eq!(read_f32(&exr, first_row), 0.5);
+        assert_eq!(read_f32(&exr, first_row + 4), 1.0);
+        assert_eq!(read_f32(&exr, first_row + 24), 0.25);
+        assert_eq!(read_f32(&exr, first_row + 28), 4.0);
+    }
+}
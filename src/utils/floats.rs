@@ -1,9 +1,61 @@
-pub const EPSILON: f64 = 1e-6;
+// The scalar every coordinate, distance and colour channel is stored as: f64
+// by default, or f32 with the f32 feature, which halves the memory of large
+// meshes and canvases. The tolerance below scales with it.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
 
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 1e-6;
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 1e-3;
+
+// the mathematical constants for Float
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+// a Float as an f64, e.g. to hash or send its exact bits the same way in
+// either precision
+#[allow(clippy::useless_conversion)]
+pub(crate) fn widen(value: Float) -> f64 {
+    f64::from(value)
+}
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+// an f64 worked out from Floats, back as a Float
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn narrow(value: f64) -> Float {
+    value as Float
+}
+
+// The roots of a*t^2 + b*t + c, as (-b - sqrt(d)) / 2a then (-b + sqrt(d)) /
+// 2a, or None if the discriminant d is negative. This is solved in f64 in
+// either precision, from coefficients summed from widened Floats: a product
+// of two f32s is exact in f64, so a ray grazing a surface gets a discriminant
+// of zero rather than one rounded just below it, and its hit isn't lost.
+pub(crate) fn quadratic_roots(a: f64, b: f64, c: f64) -> Option<[Float; 2]> {
+    let discriminant = b.powi(2) - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrt_discriminant = discriminant.sqrt();
+    Some(
+        [-b - sqrt_discriminant, -b + sqrt_discriminant]
+            .map(|numerator| narrow(numerator / (2.0 * a))),
+    )
+}
+
+// to within EPSILON, or a tolerance of its own
+#[cfg(test)]
 macro_rules! approx_eq {
     ($left:expr, $right:expr) => {
+        crate::utils::approx_eq!($left, $right, crate::utils::floats::EPSILON)
+    };
+    ($left:expr, $right:expr, $tolerance:expr) => {
         let (left, right) = ($left, $right);
-        if (left - right).abs() > crate::utils::floats::EPSILON {
+        if (left - right).abs() > $tolerance {
             panic!(
                 r#"approx_eq failed: left: `{:?}`, right: `{:?}`"#,
                 left, right
@@ -12,4 +64,5 @@ macro_rules! approx_eq {
     };
 }
 
+#[cfg(test)]
 pub(crate) use approx_eq;
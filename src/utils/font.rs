@@ -0,0 +1,669 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+
+use crate::utils::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontError {
+    Truncated,
+    MissingTable(&'static str),
+    Unsupported(&'static str),
+}
+
+impl Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Truncated => write!(f, "font data ends unexpectedly"),
+            FontError::MissingTable(tag) => write!(f, "font has no '{tag}' table"),
+            FontError::Unsupported(feature) => write!(f, "unsupported font feature: {feature}"),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+// a point on a glyph outline, in font units, and whether it lies on the curve
+// (as opposed to being a quadratic control point)
+type OutlinePoint = ([Float; 2], bool);
+
+// A minimal TrueType reader: just enough of cmap (format 4), loca/glyf
+// (simple and compound glyphs), hhea and hmtx to lay out and outline text.
+// CFF-flavoured OpenType fonts, kerning and hinting are not supported.
+#[derive(Clone, Debug)]
+pub struct Font {
+    data: Vec<u8>,
+    glyf: usize,
+    loca: usize,
+    hmtx: usize,
+    cmap_subtable: usize,
+    units_per_em: u16,
+    long_loca: bool,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    ascender: i16,
+    descender: i16,
+    line_gap: i16,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, FontError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or(FontError::Truncated)
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16, FontError> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, FontError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(FontError::Truncated)
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, FontError> {
+    data.get(offset).copied().ok_or(FontError::Truncated)
+}
+
+impl Font {
+    const MAX_COMPOUND_DEPTH: usize = 8;
+
+    pub fn load(path_string: &str) -> Result<Font, Box<dyn Error>> {
+        Ok(Font::from_bytes(fs::read(path_string)?)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, FontError> {
+        match read_u32(&data, 0)? {
+            0x0001_0000 | 0x7472_7565 => (),
+            0x4f54_544f => return Err(FontError::Unsupported("CFF outlines")),
+            _ => return Err(FontError::Unsupported("not a TrueType font")),
+        }
+
+        let num_tables = read_u16(&data, 4)? as usize;
+        let table = |tag: &'static str| -> Result<usize, FontError> {
+            for idx in 0..num_tables {
+                let record = 12 + idx * 16;
+                if data.get(record..record + 4) == Some(tag.as_bytes()) {
+                    return Ok(read_u32(&data, record + 8)? as usize);
+                }
+            }
+            Err(FontError::MissingTable(tag))
+        };
+
+        let head = table("head")?;
+        let maxp = table("maxp")?;
+        let hhea = table("hhea")?;
+        let hmtx = table("hmtx")?;
+        let loca = table("loca")?;
+        let glyf = table("glyf")?;
+        let cmap = table("cmap")?;
+
+        // prefer a Windows Unicode BMP subtable, then any Unicode platform one
+        let num_subtables = read_u16(&data, cmap + 2)? as usize;
+        let mut cmap_subtable = None;
+        for idx in 0..num_subtables {
+            let record = cmap + 4 + idx * 8;
+            let platform = read_u16(&data, record)?;
+            let encoding = read_u16(&data, record + 2)?;
+            let offset = cmap + read_u32(&data, record + 4)? as usize;
+            if read_u16(&data, offset)? != 4 {
+                continue;
+            }
+            match (platform, encoding) {
+                (3, 1) => {
+                    cmap_subtable = Some(offset);
+                    break;
+                }
+                (0, _) => cmap_subtable = cmap_subtable.or(Some(offset)),
+                _ => (),
+            }
+        }
+
+        let font = Font {
+            glyf,
+            loca,
+            hmtx,
+            cmap_subtable: cmap_subtable
+                .ok_or(FontError::Unsupported("no format 4 Unicode cmap"))?,
+            units_per_em: read_u16(&data, head + 18)?,
+            long_loca: read_i16(&data, head + 50)? == 1,
+            num_glyphs: read_u16(&data, maxp + 4)?,
+            num_h_metrics: read_u16(&data, hhea + 34)?,
+            ascender: read_i16(&data, hhea + 4)?,
+            descender: read_i16(&data, hhea + 6)?,
+            line_gap: read_i16(&data, hhea + 8)?,
+            data,
+        };
+        if font.units_per_em == 0 || font.num_h_metrics == 0 {
+            return Err(FontError::Truncated);
+        }
+        Ok(font)
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.num_glyphs
+    }
+
+    // distance between baselines, in font units
+    pub fn line_height(&self) -> Float {
+        self.ascender as Float - self.descender as Float + self.line_gap as Float
+    }
+
+    // characters without a glyph map to glyph 0, the missing glyph box
+    pub fn glyph_index(&self, character: char) -> Result<u16, FontError> {
+        let code = character as u32;
+        if code > 0xffff {
+            return Ok(0);
+        }
+        let code = code as u16;
+
+        let data = &self.data;
+        let subtable = self.cmap_subtable;
+        let seg_count_x2 = read_u16(data, subtable + 6)? as usize;
+        let end_codes = subtable + 14;
+        let start_codes = end_codes + seg_count_x2 + 2;
+        let id_deltas = start_codes + seg_count_x2;
+        let id_range_offsets = id_deltas + seg_count_x2;
+
+        for segment in (0..seg_count_x2).step_by(2) {
+            if read_u16(data, end_codes + segment)? < code {
+                continue;
+            }
+            let start_code = read_u16(data, start_codes + segment)?;
+            if start_code > code {
+                return Ok(0);
+            }
+            let id_delta = read_u16(data, id_deltas + segment)?;
+            let id_range_offset = read_u16(data, id_range_offsets + segment)? as usize;
+            if id_range_offset == 0 {
+                return Ok(code.wrapping_add(id_delta));
+            }
+            let glyph_address =
+                id_range_offsets + segment + id_range_offset + 2 * (code - start_code) as usize;
+            let glyph = read_u16(data, glyph_address)?;
+            return Ok(if glyph == 0 {
+                0
+            } else {
+                glyph.wrapping_add(id_delta)
+            });
+        }
+        Ok(0)
+    }
+
+    pub fn advance_width(&self, glyph: u16) -> Result<u16, FontError> {
+        let metric = glyph.min(self.num_h_metrics - 1) as usize;
+        read_u16(&self.data, self.hmtx + metric * 4)
+    }
+
+    fn glyph_range(&self, glyph: u16) -> Result<(usize, usize), FontError> {
+        if glyph >= self.num_glyphs {
+            return Err(FontError::Truncated);
+        }
+        let glyph = glyph as usize;
+        let (start, end) = if self.long_loca {
+            (
+                read_u32(&self.data, self.loca + glyph * 4)? as usize,
+                read_u32(&self.data, self.loca + glyph * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                read_u16(&self.data, self.loca + glyph * 2)? as usize * 2,
+                read_u16(&self.data, self.loca + glyph * 2 + 2)? as usize * 2,
+            )
+        };
+        Ok((self.glyf + start, self.glyf + end))
+    }
+
+    fn raw_contours(&self, glyph: u16, depth: usize) -> Result<Vec<Vec<OutlinePoint>>, FontError> {
+        let (start, end) = self.glyph_range(glyph)?;
+        if start >= end {
+            return Ok(vec![]);
+        }
+
+        let num_contours = read_i16(&self.data, start)?;
+        if num_contours >= 0 {
+            self.simple_contours(start, num_contours as usize)
+        } else if depth < Font::MAX_COMPOUND_DEPTH {
+            self.compound_contours(start, depth)
+        } else {
+            Err(FontError::Unsupported("deeply nested compound glyphs"))
+        }
+    }
+
+    fn simple_contours(
+        &self,
+        start: usize,
+        num_contours: usize,
+    ) -> Result<Vec<Vec<OutlinePoint>>, FontError> {
+        const ON_CURVE: u8 = 0x01;
+        const X_SHORT: u8 = 0x02;
+        const Y_SHORT: u8 = 0x04;
+        const REPEAT: u8 = 0x08;
+        const X_SAME_OR_POSITIVE: u8 = 0x10;
+        const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+        let data = &self.data;
+        let mut end_points = Vec::with_capacity(num_contours);
+        for idx in 0..num_contours {
+            end_points.push(read_u16(data, start + 10 + idx * 2)? as usize);
+        }
+        let num_points = end_points.last().map_or(0, |&last| last + 1);
+
+        let instructions_length = start + 10 + num_contours * 2;
+        let mut offset = instructions_length + 2 + read_u16(data, instructions_length)? as usize;
+
+        let mut flags = Vec::with_capacity(num_points);
+        while flags.len() < num_points {
+            let flag = read_u8(data, offset)?;
+            offset += 1;
+            flags.push(flag);
+            if flag & REPEAT != 0 {
+                let repeats = read_u8(data, offset)?;
+                offset += 1;
+                for _ in 0..repeats {
+                    flags.push(flag);
+                }
+            }
+        }
+        flags.truncate(num_points);
+
+        let mut read_coordinates = |short: u8, same_or_positive: u8| {
+            let mut value = 0i32;
+            let mut coordinates = Vec::with_capacity(num_points);
+            for &flag in &flags {
+                if flag & short != 0 {
+                    let delta = read_u8(data, offset)? as i32;
+                    offset += 1;
+                    value += if flag & same_or_positive != 0 {
+                        delta
+                    } else {
+                        -delta
+                    };
+                } else if flag & same_or_positive == 0 {
+                    value += read_i16(data, offset)? as i32;
+                    offset += 2;
+                }
+                coordinates.push(value as Float);
+            }
+            Ok::<Vec<Float>, FontError>(coordinates)
+        };
+        let xs = read_coordinates(X_SHORT, X_SAME_OR_POSITIVE)?;
+        let ys = read_coordinates(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+        let mut contours = Vec::with_capacity(num_contours);
+        let mut first = 0;
+        for end_point in end_points {
+            if end_point < first || end_point >= num_points {
+                return Err(FontError::Truncated);
+            }
+            contours.push(
+                (first..=end_point)
+                    .map(|idx| ([xs[idx], ys[idx]], flags[idx] & ON_CURVE != 0))
+                    .collect(),
+            );
+            first = end_point + 1;
+        }
+        Ok(contours)
+    }
+
+    fn compound_contours(
+        &self,
+        start: usize,
+        depth: usize,
+    ) -> Result<Vec<Vec<OutlinePoint>>, FontError> {
+        const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+        const WE_HAVE_A_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+        let data = &self.data;
+        let f2dot14 = |offset: usize| read_i16(data, offset).map(|value| value as Float / 16384.0);
+        let mut contours = vec![];
+        let mut offset = start + 10;
+
+        loop {
+            let flags = read_u16(data, offset)?;
+            let component = read_u16(data, offset + 2)?;
+            offset += 4;
+
+            let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                offset += 4;
+                (
+                    read_i16(data, offset - 4)? as Float,
+                    read_i16(data, offset - 2)? as Float,
+                )
+            } else {
+                offset += 2;
+                (
+                    read_u8(data, offset - 2)? as i8 as Float,
+                    read_u8(data, offset - 1)? as i8 as Float,
+                )
+            };
+            if flags & ARGS_ARE_XY_VALUES == 0 {
+                return Err(FontError::Unsupported(
+                    "compound glyphs positioned by points",
+                ));
+            }
+
+            let [a, b, c, d] = if flags & WE_HAVE_A_SCALE != 0 {
+                offset += 2;
+                let scale = f2dot14(offset - 2)?;
+                [scale, 0.0, 0.0, scale]
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                offset += 4;
+                [f2dot14(offset - 4)?, 0.0, 0.0, f2dot14(offset - 2)?]
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                offset += 8;
+                [
+                    f2dot14(offset - 8)?,
+                    f2dot14(offset - 6)?,
+                    f2dot14(offset - 4)?,
+                    f2dot14(offset - 2)?,
+                ]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+
+            for contour in self.raw_contours(component, depth + 1)? {
+                contours.push(
+                    contour
+                        .into_iter()
+                        .map(|([x, y], on_curve)| {
+                            ([a * x + c * y + arg1, b * x + d * y + arg2], on_curve)
+                        })
+                        .collect(),
+                );
+            }
+
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+        Ok(contours)
+    }
+
+    // closed polylines in font units (y up), with each quadratic curve split
+    // into curve_segments straight segments; the closing point is not repeated
+    pub fn glyph_outline(
+        &self,
+        glyph: u16,
+        curve_segments: usize,
+    ) -> Result<Vec<Vec<[Float; 2]>>, FontError> {
+        Ok(self
+            .raw_contours(glyph, 0)?
+            .iter()
+            .map(|contour| Font::flatten(contour, curve_segments.max(1)))
+            .filter(|polyline| polyline.len() >= 3)
+            .collect())
+    }
+
+    fn flatten(contour: &[OutlinePoint], curve_segments: usize) -> Vec<[Float; 2]> {
+        let midpoint =
+            |[x1, y1]: [Float; 2], [x2, y2]: [Float; 2]| [(x1 + x2) / 2.0, (y1 + y2) / 2.0];
+        if contour.len() < 2 {
+            return vec![];
+        }
+
+        // start from a point on the curve, making one up between two control
+        // points if there are none
+        let points: Vec<OutlinePoint> = match contour.iter().position(|&(_, on_curve)| on_curve) {
+            Some(idx) => contour[idx..]
+                .iter()
+                .chain(&contour[..idx])
+                .copied()
+                .collect(),
+            None => std::iter::once((midpoint(contour[0].0, contour[1].0), true))
+                .chain(contour[1..].iter().copied())
+                .chain(std::iter::once(contour[0]))
+                .collect(),
+        };
+
+        fn add_curve(
+            polyline: &mut Vec<[Float; 2]>,
+            [from, control, to]: [[Float; 2]; 3],
+            curve_segments: usize,
+        ) {
+            for step in 1..=curve_segments {
+                let t = step as Float / curve_segments as Float;
+                let s = 1.0 - t;
+                polyline.push([
+                    s * s * from[0] + 2.0 * s * t * control[0] + t * t * to[0],
+                    s * s * from[1] + 2.0 * s * t * control[1] + t * t * to[1],
+                ]);
+            }
+        }
+
+        let mut polyline = vec![points[0].0];
+        let mut current = points[0].0;
+        let mut control: Option<[Float; 2]> = None;
+        for &(point, on_curve) in points[1..].iter().chain(std::iter::once(&points[0])) {
+            match (on_curve, control) {
+                (true, None) => {
+                    polyline.push(point);
+                    current = point;
+                }
+                (true, Some(control_point)) => {
+                    add_curve(
+                        &mut polyline,
+                        [current, control_point, point],
+                        curve_segments,
+                    );
+                    current = point;
+                    control = None;
+                }
+                (false, None) => control = Some(point),
+                (false, Some(control_point)) => {
+                    let implied = midpoint(control_point, point);
+                    add_curve(
+                        &mut polyline,
+                        [current, control_point, implied],
+                        curve_segments,
+                    );
+                    current = implied;
+                    control = Some(point);
+                }
+            }
+        }
+
+        polyline.dedup();
+        if polyline.len() > 1 && polyline.first() == polyline.last() {
+            polyline.pop();
+        }
+        polyline
+    }
+}
+
+// A tiny TrueType font for tests: glyph 1 ('o') is a square with a square
+// hole, and glyph 2 ('v') is a triangle with one curved side.
+#[cfg(test)]
+pub(crate) fn test_font() -> Font {
+    fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+        bytes.extend(value.to_be_bytes());
+    }
+    fn push_i16(bytes: &mut Vec<u8>, value: i16) {
+        bytes.extend(value.to_be_bytes());
+    }
+    fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+        bytes.extend(value.to_be_bytes());
+    }
+    fn simple_glyph(contours: &[&[(i16, i16, bool)]]) -> Vec<u8> {
+        let mut bytes = vec![];
+        push_i16(&mut bytes, contours.len() as i16);
+        bytes.extend([0; 8]);
+        let mut end_point = 0;
+        for contour in contours {
+            end_point += contour.len() as u16;
+            push_u16(&mut bytes, end_point - 1);
+        }
+        push_u16(&mut bytes, 0);
+        let points: Vec<(i16, i16, bool)> =
+            contours.iter().flat_map(|c| c.iter()).copied().collect();
+        bytes.extend(points.iter().map(|&(_, _, on_curve)| on_curve as u8));
+        let (mut last_x, mut last_y) = (0, 0);
+        for &(x, _, _) in &points {
+            push_i16(&mut bytes, x - last_x);
+            last_x = x;
+        }
+        for &(_, y, _) in &points {
+            push_i16(&mut bytes, y - last_y);
+            last_y = y;
+        }
+        bytes
+    }
+
+    let glyphs = [
+        vec![],
+        simple_glyph(&[
+            &[
+                (0, 0, true),
+                (0, 1000, true),
+                (1000, 1000, true),
+                (1000, 0, true),
+            ],
+            &[
+                (250, 250, true),
+                (750, 250, true),
+                (750, 750, true),
+                (250, 750, true),
+            ],
+        ]),
+        simple_glyph(&[&[(0, 0, true), (500, 1000, false), (1000, 0, true)]]),
+    ];
+
+    let mut glyf = vec![];
+    let mut loca = vec![];
+    for glyph in &glyphs {
+        push_u32(&mut loca, glyf.len() as u32);
+        glyf.extend(glyph);
+    }
+    push_u32(&mut loca, glyf.len() as u32);
+
+    let mut head = vec![0; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    head[50..52].copy_from_slice(&1i16.to_be_bytes());
+
+    let mut maxp = vec![0, 0, 0x50, 0];
+    push_u16(&mut maxp, glyphs.len() as u16);
+
+    let mut hhea = vec![0; 36];
+    hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+    hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+    hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+
+    let mut hmtx = vec![];
+    for advance in [500u16, 1100, 1000] {
+        push_u16(&mut hmtx, advance);
+        push_i16(&mut hmtx, 0);
+    }
+
+    // format 4 segments for 'o' -> 1, 'v' -> 2, and the final 0xffff
+    let segments: [(u16, u16); 3] = [('o' as u16, 1), ('v' as u16, 2), (0xffff, 0)];
+    let mut cmap = vec![];
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, 1);
+    push_u16(&mut cmap, 3);
+    push_u16(&mut cmap, 1);
+    push_u32(&mut cmap, 12);
+    push_u16(&mut cmap, 4);
+    push_u16(&mut cmap, 16 + 8 * segments.len() as u16);
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, 2 * segments.len() as u16);
+    cmap.extend([0; 6]);
+    for &(code, _) in &segments {
+        push_u16(&mut cmap, code);
+    }
+    push_u16(&mut cmap, 0);
+    for &(code, _) in &segments {
+        push_u16(&mut cmap, code);
+    }
+    for &(code, glyph) in &segments {
+        push_u16(&mut cmap, glyph.wrapping_sub(code));
+    }
+    for _ in &segments {
+        push_u16(&mut cmap, 0);
+    }
+
+    let tables: [(&[u8; 4], Vec<u8>); 7] = [
+        (b"cmap", cmap),
+        (b"glyf", glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+    ];
+    let mut font = vec![];
+    push_u32(&mut font, 0x0001_0000);
+    push_u16(&mut font, tables.len() as u16);
+    font.extend([0; 6]);
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        font.extend(*tag);
+        push_u32(&mut font, 0);
+        push_u32(&mut font, offset as u32);
+        push_u32(&mut font, table.len() as u32);
+        offset += table.len();
+    }
+    for (_, table) in &tables {
+        font.extend(table);
+    }
+
+    Font::from_bytes(font).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_characters_to_glyphs() {
+        let font = test_font();
+        assert_eq!(font.units_per_em(), 1000);
+        assert_eq!(font.glyph_index('o'), Ok(1));
+        assert_eq!(font.glyph_index('v'), Ok(2));
+        assert_eq!(font.glyph_index('x'), Ok(0));
+        assert_eq!(font.advance_width(1), Ok(1100));
+        assert_eq!(font.line_height(), 1000.0);
+    }
+
+    #[test]
+    fn outline_simple_glyph_with_hole() {
+        let font = test_font();
+        let outline = font.glyph_outline(1, 8).unwrap();
+        assert_eq!(outline.len(), 2);
+        assert_eq!(
+            outline[0],
+            vec![[0.0, 0.0], [0.0, 1000.0], [1000.0, 1000.0], [1000.0, 0.0]]
+        );
+        assert_eq!(outline[1].len(), 4);
+        assert!(font.glyph_outline(0, 8).unwrap().is_empty());
+    }
+
+    #[test]
+    fn flatten_quadratic_curve() {
+        let font = test_font();
+        let outline = font.glyph_outline(2, 4).unwrap();
+        // four segments along the curve, then the straight closing edge
+        assert_eq!(outline[0].len(), 5);
+        assert_eq!(outline[0][2], [500.0, 500.0]);
+    }
+
+    #[test]
+    fn reject_truncated_data() {
+        assert_eq!(
+            Font::from_bytes(vec![0, 1, 0]).unwrap_err(),
+            FontError::Truncated
+        );
+        assert_eq!(
+            Font::from_bytes(b"OTTO\0\0".to_vec()).unwrap_err(),
+            FontError::Unsupported("CFF outlines")
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+// A minimal encoder for animated GIFs, written a frame at a time. Every frame
+// uses one fixed palette, a 6x6x6 colour cube, so nothing has to be known
+// about the colours ahead of time; smooth gradients band, as they would with
+// any 256-colour palette. Frames are opaque and loop forever.
+
+const MAX_CODE: u16 = 4095;
+// colour cube levels per channel
+const LEVELS: usize = 6;
+// with a 256-entry palette, codes start at 9 bits
+const MIN_CODE_SIZE: u8 = 8;
+const CLEAR_CODE: u16 = 256;
+const END_CODE: u16 = 257;
+
+pub(crate) struct GifWriter<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub(crate) fn new(mut writer: W, width: usize, height: usize) -> io::Result<GifWriter<W>> {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend((width as u16).to_le_bytes());
+        gif.extend((height as u16).to_le_bytes());
+        // a global colour table of 256 entries, with 8 bits per primary;
+        // background colour 0 and square pixels
+        gif.extend([0xf7, 0, 0]);
+        for idx in 0..256 {
+            let colour = if idx < LEVELS.pow(3) {
+                [idx / (LEVELS * LEVELS), idx / LEVELS % LEVELS, idx % LEVELS]
+                    .map(|level| (level * 255 / (LEVELS - 1)) as u8)
+            } else {
+                [0; 3]
+            };
+            gif.extend(colour);
+        }
+        // the NETSCAPE2.0 extension, looping forever (a count of 0)
+        gif.extend([0x21, 0xff, 11]);
+        gif.extend(b"NETSCAPE2.0");
+        gif.extend([3, 1, 0, 0, 0]);
+        writer.write_all(&gif)?;
+        Ok(GifWriter {
+            writer,
+            width,
+            height,
+        })
+    }
+
+    // three bytes per pixel, rows top to bottom; the delay is in hundredths
+    // of a second
+    pub(crate) fn write_frame(&mut self, rgb: &[u8], delay: u16) -> io::Result<()> {
+        assert_eq!(rgb.len(), self.width * self.height * 3);
+
+        // graphic control: each frame replaces the last, with no transparency
+        let mut frame = vec![0x21, 0xf9, 4, 0x04];
+        frame.extend(delay.to_le_bytes());
+        frame.extend([0, 0]);
+        // the image descriptor, covering the whole screen, using the global
+        // colour table and not interlaced
+        frame.push(0x2c);
+        frame.extend([0; 4]);
+        frame.extend((self.width as u16).to_le_bytes());
+        frame.extend((self.height as u16).to_le_bytes());
+        frame.push(0);
+
+        let indices: Vec<u8> = rgb.chunks(3).map(palette_index).collect();
+        frame.push(MIN_CODE_SIZE);
+        for block in lzw_encode(&indices).chunks(255) {
+            frame.push(block.len() as u8);
+            frame.extend(block);
+        }
+        frame.push(0);
+        self.writer.write_all(&frame)
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        Ok(self.writer)
+    }
+}
+
+// the nearest colour in the cube
+fn palette_index(rgb: &[u8]) -> u8 {
+    let level = |value: u8| (value as usize * (LEVELS - 1) + 127) / 255;
+    (level(rgb[0]) * LEVELS * LEVELS + level(rgb[1]) * LEVELS + level(rgb[2])) as u8
+}
+
+// variable-length codes from 9 to 12 bits, packed least significant bit
+// first; the table is cleared whenever it fills
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let mut packer = BitPacker::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = MIN_CODE_SIZE as u32 + 1;
+    let mut next_code = END_CODE + 1;
+    packer.push(CLEAR_CODE, code_size);
+
+    let mut indices = indices.iter();
+    let Some(&first) = indices.next() else {
+        packer.push(END_CODE, code_size);
+        return packer.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in indices {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        packer.push(prefix, code_size);
+        if next_code > MAX_CODE {
+            packer.push(CLEAR_CODE, code_size);
+            table.clear();
+            code_size = MIN_CODE_SIZE as u32 + 1;
+            next_code = END_CODE + 1;
+        } else {
+            table.insert((prefix, index), next_code);
+            // the decoder widens its codes as soon as the code just added
+            // no longer fits
+            if next_code == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            next_code += 1;
+        }
+        prefix = index as u16;
+    }
+    packer.push(prefix, code_size);
+    packer.push(END_CODE, code_size);
+    packer.finish()
+}
+
+#[derive(Default)]
+struct BitPacker {
+    bytes: Vec<u8>,
+    bits: u32,
+    bit_count: u32,
+}
+
+impl BitPacker {
+    fn push(&mut self, code: u16, size: u32) {
+        self.bits |= (code as u32) << self.bit_count;
+        self.bit_count += size;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a plain decoder, to check that the codes read back
+    fn lzw_decode(data: &[u8]) -> Vec<u8> {
+        let mut table: Vec<Vec<u8>> = vec![];
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..=255).map(|index| vec![index]).collect();
+            table.extend([vec![], vec![]]);
+        };
+        reset(&mut table);
+        let (mut bits, mut bit_count, mut position) = (0u32, 0u32, 0);
+        let mut code_size = MIN_CODE_SIZE as u32 + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = vec![];
+        loop {
+            while bit_count < code_size {
+                bits |= (data[position] as u32) << bit_count;
+                position += 1;
+                bit_count += 8;
+            }
+            let code = (bits & ((1 << code_size) - 1)) as u16;
+            bits >>= code_size;
+            bit_count -= code_size;
+
+            match code {
+                CLEAR_CODE => {
+                    reset(&mut table);
+                    code_size = MIN_CODE_SIZE as u32 + 1;
+                    previous = None;
+                    continue;
+                }
+                END_CODE => return output,
+                _ => (),
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("unknown first code"),
+            };
+            output.extend(&entry);
+            if let Some(previous) = previous {
+                table.push([previous, vec![entry[0]]].concat());
+                if table.len() == 1 << code_size && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        assert_eq!(lzw_decode(&lzw_encode(&[])), Vec::<u8>::new());
+        let repeated = vec![7; 1000];
+        assert_eq!(lzw_decode(&lzw_encode(&repeated)), repeated);
+        // enough variety to fill the table and clear it
+        let noisy: Vec<u8> = (0..40_000u32)
+            .map(|idx| (idx.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        assert_eq!(lzw_decode(&lzw_encode(&noisy)), noisy);
+    }
+
+    #[test]
+    fn palette_is_a_colour_cube() {
+        assert_eq!(palette_index(&[0, 0, 0]), 0);
+        assert_eq!(palette_index(&[255, 255, 255]), 215);
+        assert_eq!(palette_index(&[255, 0, 0]), 180);
+        assert_eq!(palette_index(&[0, 60, 0]), 6);
+    }
+
+    #[test]
+    fn encode_frames() {
+        let mut writer = GifWriter::new(Vec::new(), 2, 1).unwrap();
+        writer.write_frame(&[255, 0, 0, 0, 0, 255], 5).unwrap();
+        writer.write_frame(&[0; 6], 5).unwrap();
+        let gif = writer.finish().unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(&gif[6..10], &[2, 0, 1, 0]);
+        // the red entry of the palette
+        assert_eq!(&gif[13 + 180 * 3..13 + 181 * 3], &[255, 0, 0]);
+        let frames = gif
+            .windows(4)
+            .filter(|window| window[..3] == [0x21, 0xf9, 4]);
+        assert_eq!(frames.count(), 2);
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+}
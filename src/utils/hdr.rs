@@ -0,0 +1,289 @@
+use std::error::Error;
+use std::fs;
+
+use crate::utils::{image_size, ImageError};
+
+// A decoder and encoder for Radiance RGBE (.hdr) images, the usual format of
+// high dynamic range environment maps, with flat or run-length encoded
+// scanlines. Only the standard orientation (-Y height +X width) is read.
+
+// Decoded linear radiance, three floats per pixel, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgb: Vec<f32>,
+}
+
+impl HdrImage {
+    pub fn load(path_string: &str) -> Result<HdrImage, Box<dyn Error>> {
+        Ok(decode(&fs::read(path_string)?)?)
+    }
+
+    pub fn pixel(&self, column: usize, row: usize) -> [f32; 3] {
+        let offset = (row * self.width + column) * 3;
+        [self.rgb[offset], self.rgb[offset + 1], self.rgb[offset + 2]]
+    }
+}
+
+pub fn is_hdr(data: &[u8]) -> bool {
+    data.starts_with(b"#?")
+}
+
+pub fn decode(data: &[u8]) -> Result<HdrImage, ImageError> {
+    if !is_hdr(data) {
+        return Err(ImageError::Malformed("missing Radiance signature"));
+    }
+
+    let mut position = 0;
+    let mut next_line = || -> Result<&[u8], ImageError> {
+        let length = data[position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(ImageError::Truncated)?;
+        let line = &data[position..position + length];
+        position += length + 1;
+        Ok(line)
+    };
+
+    // header lines up to a blank one, then the resolution
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix(b"FORMAT=") {
+            if format != b"32-bit_rle_rgbe" {
+                return Err(ImageError::Unsupported("HDR formats other than RGBE"));
+            }
+        }
+    }
+    let resolution = std::str::from_utf8(next_line()?)
+        .map_err(|_| ImageError::Malformed("invalid HDR resolution"))?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        [_, _, _, _] => return Err(ImageError::Unsupported("HDR orientations other than -Y +X")),
+        _ => return Err(ImageError::Malformed("invalid HDR resolution")),
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        return Err(ImageError::Malformed("invalid HDR resolution"));
+    };
+
+    // every scanline must fit in what's left of the file before anything is
+    // allocated: flat ones take four bytes a pixel, and run-length encoded
+    // ones a marker and at least a run for every 127 pixels of each channel
+    let flat_bytes = width
+        .checked_mul(4)
+        .ok_or(ImageError::Malformed("HDR dimensions too large"))?;
+    let least_scanline_bytes = if (8..0x8000).contains(&width) {
+        flat_bytes.min(4 + 4 * 2 * width.div_ceil(127))
+    } else {
+        flat_bytes
+    };
+    image_size(least_scanline_bytes, height, 1, data.len() - position)?;
+
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    let mut scanline = vec![0; flat_bytes];
+    for _ in 0..height {
+        position = read_scanline(data, position, &mut scanline)?;
+        for pixel in scanline.chunks(4) {
+            rgb.extend(rgbe_to_float(pixel));
+        }
+    }
+
+    Ok(HdrImage { width, height, rgb })
+}
+
+// fills the scanline with RGBE quadruples, returning the position after it
+fn read_scanline(
+    data: &[u8],
+    mut position: usize,
+    scanline: &mut [u8],
+) -> Result<usize, ImageError> {
+    let width = scanline.len() / 4;
+    let marker = data
+        .get(position..position + 4)
+        .ok_or(ImageError::Truncated)?;
+    let is_run_length_encoded =
+        (8..0x8000).contains(&width) && marker[0] == 2 && marker[1] == 2 && marker[2] < 0x80;
+    if !is_run_length_encoded {
+        if marker[..3] == [1, 1, 1] {
+            return Err(ImageError::Unsupported("old-style HDR run lengths"));
+        }
+        let pixels = data
+            .get(position..position + width * 4)
+            .ok_or(ImageError::Truncated)?;
+        scanline.copy_from_slice(pixels);
+        return Ok(position + width * 4);
+    }
+    if ((marker[2] as usize) << 8 | marker[3] as usize) != width {
+        return Err(ImageError::Malformed("HDR scanline width mismatch"));
+    }
+    position += 4;
+
+    // each channel is encoded separately, as runs and literal spans
+    for channel in 0..4 {
+        let mut column = 0;
+        while column < width {
+            let count = *data.get(position).ok_or(ImageError::Truncated)? as usize;
+            position += 1;
+            let (length, is_run) = if count > 128 {
+                (count - 128, true)
+            } else {
+                (count, false)
+            };
+            if length == 0 || column + length > width {
+                return Err(ImageError::Malformed("bad HDR run length"));
+            }
+            for offset in 0..length {
+                let byte = if is_run {
+                    data.get(position)
+                } else {
+                    data.get(position + offset)
+                };
+                scanline[(column + offset) * 4 + channel] = *byte.ok_or(ImageError::Truncated)?;
+            }
+            position += if is_run { 1 } else { length };
+            column += length;
+        }
+    }
+    Ok(position)
+}
+
+fn rgbe_to_float(pixel: &[u8]) -> [f32; 3] {
+    if pixel[3] == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2_f32.powi(pixel[3] as i32 - (128 + 8));
+    [
+        pixel[0] as f32 * scale,
+        pixel[1] as f32 * scale,
+        pixel[2] as f32 * scale,
+    ]
+}
+
+// negative and NaN channels become 0; channels of 2^127 and more, past what
+// the exponent byte can hold, become the largest RGBE value
+fn float_to_rgbe(rgb: [f32; 3]) -> [u8; 4] {
+    let [red, green, blue] = rgb.map(|value| value.min(f32::MAX));
+    let largest = red.max(green).max(blue);
+    if largest < 1e-32 {
+        return [0; 4];
+    }
+    let exponent = (largest.log2().floor() as i32 + 1).min(127);
+    // in two steps, since 2^127 is as far as an f32 power of two goes
+    let scale = 256.0 / 2_f32.powi(exponent / 2) / 2_f32.powi(exponent - exponent / 2);
+    let mantissa = |value: f32| (value * scale).min(255.0) as u8;
+    [
+        mantissa(red),
+        mantissa(green),
+        mantissa(blue),
+        (exponent + 128) as u8,
+    ]
+}
+
+// run-length encoded scanlines wherever the width allows
+pub(crate) fn encode(width: usize, height: usize, rgb: &[f32]) -> Vec<u8> {
+    let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n".to_vec();
+    hdr.extend(format!("-Y {height} +X {width}\n").as_bytes());
+    for row in rgb.chunks(width.max(1) * 3).take(height) {
+        let pixels: Vec<[u8; 4]> = row
+            .chunks(3)
+            .map(|pixel| float_to_rgbe([pixel[0], pixel[1], pixel[2]]))
+            .collect();
+        if !(8..0x8000).contains(&width) {
+            hdr.extend(pixels.concat());
+            continue;
+        }
+        hdr.extend([2, 2, (width >> 8) as u8, width as u8]);
+        for channel in 0..4 {
+            let bytes: Vec<u8> = pixels.iter().map(|pixel| pixel[channel]).collect();
+            // runs of repeated bytes, and single literal bytes otherwise
+            for run in bytes.chunk_by(|a, b| a == b) {
+                for part in run.chunks(127) {
+                    if part.len() > 1 {
+                        hdr.extend([128 + part.len() as u8, part[0]]);
+                    } else {
+                        hdr.extend([1, part[0]]);
+                    }
+                }
+            }
+        }
+    }
+    hdr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_run_length_encoded() {
+        let mut rgb = vec![];
+        for column in 0..10 {
+            rgb.extend(if column < 6 {
+                [0.5, 2.0, 10.0]
+            } else {
+                [column as f32, 0.0, 0.25]
+            });
+        }
+        let image = decode(&encode(10, 1, &rgb)).unwrap();
+        assert_eq!((image.width, image.height), (10, 1));
+        assert_eq!(image.pixel(2, 0), [0.5, 2.0, 10.0]);
+        assert_eq!(image.pixel(7, 0), [7.0, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn decode_flat() {
+        let rgb = [1.0, 0.0, 0.0, 0.0, 0.5, 64.0, 3.0, 3.0, 3.0, 0.0, 0.0, 0.0];
+        let image = decode(&encode(2, 2, &rgb)).unwrap();
+        assert_eq!(image.pixel(1, 0), [0.0, 0.5, 64.0]);
+        assert_eq!(image.pixel(0, 1), [3.0, 3.0, 3.0]);
+        assert_eq!(image.pixel(1, 1), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn reject_unsupported_and_truncated() {
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n+Y 1 +X 1\n\x80\x80\x80\x81"),
+            Err(ImageError::Unsupported("HDR orientations other than -Y +X"))
+        );
+        assert_eq!(
+            decode(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"),
+            Err(ImageError::Unsupported("HDR formats other than RGBE"))
+        );
+        let mut data = encode(10, 2, &[1.0; 60]);
+        data.truncate(data.len() - 3);
+        assert_eq!(decode(&data), Err(ImageError::Truncated));
+    }
+
+    #[test]
+    fn clamp_huge_values_to_the_largest_rgbe() {
+        assert_eq!(
+            float_to_rgbe([f32::INFINITY, 1e39_f64 as f32, 2.0]),
+            [255, 255, 0, 255]
+        );
+        assert_eq!(float_to_rgbe([2_f32.powi(127), 0.0, 0.0]), [255, 0, 0, 255]);
+        let largest = rgbe_to_float(&[255, 255, 255, 255]);
+        assert_eq!(float_to_rgbe(largest), [255, 255, 255, 255]);
+        assert_eq!(float_to_rgbe([1.0, 0.5, 0.0]), [128, 64, 0, 129]);
+    }
+
+    #[test]
+    fn reject_impossible_dimensions() {
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n-Y 1 +X 18446744073709551615\n"),
+            Err(ImageError::Malformed("HDR dimensions too large"))
+        );
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n-Y 18446744073709551615 +X 1099511627776\n"),
+            Err(ImageError::Malformed("image dimensions too large"))
+        );
+        // far more scanlines than the file holds, refused before they are
+        // allocated
+        assert_eq!(
+            decode(b"#?RADIANCE\n\n-Y 1000000000 +X 1000\n\x02\x02\x03\xe8"),
+            Err(ImageError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+
+use crate::collections::Vector;
+use crate::utils::consts::PI;
+use crate::utils::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IesError {
+    Truncated,
+    Malformed(&'static str),
+    Unsupported(&'static str),
+}
+
+impl Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IesError::Truncated => write!(f, "IES data ends unexpectedly"),
+            IesError::Malformed(problem) => write!(f, "malformed IES data: {problem}"),
+            IesError::Unsupported(feature) => write!(f, "unsupported IES feature: {feature}"),
+        }
+    }
+}
+
+impl Error for IesError {}
+
+// The angular distribution of a real luminaire's light, from an IESNA LM-63
+// (.ies) photometric file. Only type C photometry, the usual one for
+// architectural fittings, is read: vertical angles run from the nadir
+// (straight down, 0°) to the zenith (180°), and horizontal angles around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IesProfile {
+    vertical_angles: Vec<Float>,
+    horizontal_angles: Vec<Float>,
+    // one row of vertical angles per horizontal angle
+    candela: Vec<Vec<Float>>,
+    peak: Float,
+}
+
+impl IesProfile {
+    pub fn load(path_string: &str) -> Result<IesProfile, Box<dyn Error>> {
+        Ok(IesProfile::parse(&fs::read_to_string(path_string)?)?)
+    }
+
+    pub fn parse(text: &str) -> Result<IesProfile, IesError> {
+        let mut lines = text.lines();
+        let tilt = loop {
+            let line = lines
+                .next()
+                .ok_or(IesError::Malformed("missing TILT line"))?;
+            if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+                break tilt.trim();
+            }
+        };
+
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<Float>()
+                    .map_err(|_| IesError::Malformed("invalid number"))
+            });
+        let mut next = || numbers.next().unwrap_or(Err(IesError::Truncated));
+        // whole numbers of things to read; the reads themselves stop at the
+        // end of the data, however large the count
+        let count = |value: Float| {
+            if value >= 0.0 && value.fract() == 0.0 && value <= u32::MAX as Float {
+                Ok(value as usize)
+            } else {
+                Err(IesError::Malformed("invalid count"))
+            }
+        };
+
+        match tilt {
+            "NONE" => {}
+            // lamp tilt factors, which only matter for lamps that are not
+            // mounted as they were measured
+            "INCLUDE" => {
+                next()?;
+                let pairs = count(next()?)?;
+                for _ in 0..2 * pairs {
+                    next()?;
+                }
+            }
+            _ => return Err(IesError::Unsupported("tilt data in a separate file")),
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = count(next()?)?;
+        let horizontal_count = count(next()?)?;
+        let photometric_type = next()?;
+        for _ in 0..4 {
+            // units and luminous opening dimensions
+            next()?;
+        }
+        let ballast_factor = next()?;
+        for _ in 0..2 {
+            // future use and input watts
+            next()?;
+        }
+        if photometric_type != 1.0 {
+            return Err(IesError::Unsupported("type A or B photometry"));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(IesError::Malformed("no angles"));
+        }
+
+        let mut read = |count: usize| (0..count).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical_angles = read(vertical_count)?;
+        let horizontal_angles = read(horizontal_count)?;
+        let candela = (0..horizontal_count)
+            .map(|_| {
+                read(vertical_count).map(|row| {
+                    row.into_iter()
+                        .map(|value| value * multiplier * ballast_factor)
+                        .collect()
+                })
+            })
+            .collect::<Result<Vec<Vec<Float>>, _>>()?;
+
+        let is_ascending = |angles: &[Float]| angles.windows(2).all(|pair| pair[0] < pair[1]);
+        if !is_ascending(&vertical_angles) || !is_ascending(&horizontal_angles) {
+            return Err(IesError::Malformed("angles out of order"));
+        }
+        let peak = candela
+            .iter()
+            .flatten()
+            .fold(0.0, |peak: Float, &value| peak.max(value));
+
+        Ok(IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            peak,
+        })
+    }
+
+    // the brightest intensity in any direction, in candela
+    pub fn peak(&self) -> Float {
+        self.peak
+    }
+
+    // in candela, at angles in degrees, interpolated between those measured
+    // and mirrored as the file's symmetry implies
+    pub fn candela(&self, vertical: Float, horizontal: Float) -> Float {
+        let horizontal = horizontal.rem_euclid(360.0);
+        let horizontal = match self.horizontal_angles.last() {
+            // the same all the way around
+            _ if self.horizontal_angles.len() == 1 => self.horizontal_angles[0],
+            // symmetric in each quadrant
+            Some(&90.0) => {
+                let horizontal = if horizontal > 180.0 {
+                    360.0 - horizontal
+                } else {
+                    horizontal
+                };
+                if horizontal > 90.0 {
+                    180.0 - horizontal
+                } else {
+                    horizontal
+                }
+            }
+            // symmetric about the 0-180° plane
+            Some(&last) if last == 180.0 && horizontal > 180.0 => 360.0 - horizontal,
+            _ => horizontal,
+        };
+
+        let Some((idx_vertical, vertical_fraction)) = bracket(&self.vertical_angles, vertical)
+        else {
+            return 0.0;
+        };
+        let Some((idx_horizontal, horizontal_fraction)) =
+            bracket(&self.horizontal_angles, horizontal)
+        else {
+            return 0.0;
+        };
+        let along_vertical = |row: &Vec<Float>| {
+            let next = (idx_vertical + 1).min(row.len() - 1);
+            row[idx_vertical] * (1.0 - vertical_fraction) + row[next] * vertical_fraction
+        };
+        let next = (idx_horizontal + 1).min(self.candela.len() - 1);
+        along_vertical(&self.candela[idx_horizontal]) * (1.0 - horizontal_fraction)
+            + along_vertical(&self.candela[next]) * horizontal_fraction
+    }
+
+    // the fraction of the peak intensity shining along direction from a
+    // luminaire aimed at nadir; horizontal angles are measured from whichever
+    // of +x and +z is further from nadir
+    pub fn relative_intensity(&self, direction: Vector, nadir: Vector) -> Float {
+        if self.peak <= 0.0 {
+            return 0.0;
+        }
+        let direction = direction.normalise();
+        let nadir = nadir.normalise();
+        let vertical = direction.dot(nadir).clamp(-1.0, 1.0).acos();
+
+        let reference = if nadir.x.abs() < 0.9 {
+            Vector::new(1.0, 0.0, 0.0)
+        } else {
+            Vector::new(0.0, 0.0, 1.0)
+        };
+        let zero = (reference - nadir * reference.dot(nadir)).normalise();
+        let ninety = zero.cross(nadir);
+        let horizontal = direction.dot(ninety).atan2(direction.dot(zero));
+
+        self.candela(vertical * 180.0 / PI, horizontal * 180.0 / PI) / self.peak
+    }
+}
+
+// the index of the measured angle at or before angle and how far angle is
+// towards the next, or None if it is outside the measured range (or NaN)
+fn bracket(angles: &[Float], angle: Float) -> Option<(usize, Float)> {
+    let first = *angles.first()?;
+    let last = *angles.last()?;
+    if angles.len() == 1 || angle == last {
+        return (angle == last).then_some((angles.len() - 1, 0.0));
+    }
+    if !(first..=last).contains(&angle) {
+        return None;
+    }
+    let idx = angles.partition_point(|&measured| measured <= angle) - 1;
+    let fraction = (angle - angles[idx]) / (angles[idx + 1] - angles[idx]);
+    Some((idx, fraction))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    // a downlight, brightest straight down and dark above the horizon,
+    // brighter towards 0° than 90° horizontally
+    pub(crate) const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] downlight
+[MANUFAC] none
+TILT=NONE
+1 1000 1 3 2 1 2 0 0 0
+1 1 10
+0 45 90
+0 90
+1000 500 0
+1000 250 0
+";
+
+    #[test]
+    fn parse_profile() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        approx_eq!(profile.peak(), 1000.0);
+        approx_eq!(profile.candela(0.0, 0.0), 1000.0);
+        approx_eq!(profile.candela(45.0, 0.0), 500.0);
+        approx_eq!(profile.candela(45.0, 90.0), 250.0);
+        approx_eq!(profile.candela(22.5, 0.0), 750.0);
+        // mirrored into the first quadrant
+        approx_eq!(profile.candela(45.0, 270.0), 250.0);
+        approx_eq!(profile.candela(45.0, 135.0), 375.0);
+        approx_eq!(profile.candela(120.0, 0.0), 0.0);
+        // undefined directions are dark
+        assert_eq!(profile.candela(Float::NAN, 0.0), 0.0);
+        assert_eq!(profile.candela(45.0, Float::NAN), 0.0);
+    }
+
+    #[test]
+    fn relative_intensity_from_direction() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        let down = Vector::new(0.0, -1.0, 0.0);
+        approx_eq!(profile.relative_intensity(down, down), 1.0);
+        approx_eq!(
+            profile.relative_intensity(Vector::new(1.0, -1.0, 0.0), down),
+            0.5
+        );
+        approx_eq!(
+            profile.relative_intensity(Vector::new(0.0, -1.0, 1.0), down),
+            0.25
+        );
+        approx_eq!(
+            profile.relative_intensity(Vector::new(0.0, 1.0, 0.0), down),
+            0.0
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_and_truncated() {
+        let type_b = DOWNLIGHT.replace("1 1000 1 3 2 1 2", "1 1000 1 3 2 2 2");
+        assert_eq!(
+            IesProfile::parse(&type_b),
+            Err(IesError::Unsupported("type A or B photometry"))
+        );
+        assert_eq!(
+            IesProfile::parse(&DOWNLIGHT.replace("TILT=NONE", "TILT=lamp.tlt")),
+            Err(IesError::Unsupported("tilt data in a separate file"))
+        );
+        assert_eq!(
+            IesProfile::parse(&DOWNLIGHT[..DOWNLIGHT.len() - 8]),
+            Err(IesError::Truncated)
+        );
+        assert_eq!(
+            IesProfile::parse("no tilt here"),
+            Err(IesError::Malformed("missing TILT line"))
+        );
+        let tilted =
+            |pairs: &str| DOWNLIGHT.replace("TILT=NONE", &format!("TILT=INCLUDE\n1 {pairs}"));
+        for pairs in ["1.8e19", "-1", "NaN", "2.5"] {
+            assert_eq!(
+                IesProfile::parse(&tilted(pairs)),
+                Err(IesError::Malformed("invalid count"))
+            );
+        }
+        // more pairs than there are numbers
+        assert_eq!(
+            IesProfile::parse(&tilted("4000000000")),
+            Err(IesError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+
+use crate::utils::{jpeg, png};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageError {
+    Truncated,
+    Malformed(&'static str),
+    Unsupported(&'static str),
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Truncated => write!(f, "image data ends unexpectedly"),
+            ImageError::Malformed(problem) => write!(f, "malformed image: {problem}"),
+            ImageError::Unsupported(feature) => write!(f, "unsupported image feature: {feature}"),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+// Decoded 8-bit RGBA pixels, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl RawImage {
+    pub fn load(path_string: &str) -> Result<RawImage, Box<dyn Error>> {
+        Ok(RawImage::decode(&fs::read(path_string)?)?)
+    }
+
+    // PNG, baseline JPEG, or PPM (P3 or P6), recognised by their contents
+    pub fn decode(data: &[u8]) -> Result<RawImage, ImageError> {
+        match data {
+            [0x89, b'P', b'N', b'G', ..] => png::decode(data),
+            [0xff, 0xd8, ..] => jpeg::decode(data),
+            [b'P', b'3' | b'6', ..] => RawImage::decode_ppm(data),
+            _ => Err(ImageError::Unsupported("unrecognised image format")),
+        }
+    }
+
+    pub fn pixel(&self, column: usize, row: usize) -> [u8; 4] {
+        let offset = (row * self.width + column) * 4;
+        [
+            self.rgba[offset],
+            self.rgba[offset + 1],
+            self.rgba[offset + 2],
+            self.rgba[offset + 3],
+        ]
+    }
+
+    // samples are scaled to 8 bits
+    pub(crate) fn decode_ppm(data: &[u8]) -> Result<RawImage, ImageError> {
+        let ppm = PpmImage::decode(data)?;
+        let scale = |sample: u16| (sample as usize * 255 / ppm.max_value) as u8;
+        let rgba = ppm
+            .samples
+            .chunks(3)
+            .flat_map(|rgb| [scale(rgb[0]), scale(rgb[1]), scale(rgb[2]), 255])
+            .collect();
+        Ok(RawImage {
+            width: ppm.width,
+            height: ppm.height,
+            rgba,
+        })
+    }
+}
+
+// The samples of a P3 or P6 PPM as stored, up to its maximum value (at most
+// 65535), three per pixel, rows top to bottom.
+pub(crate) struct PpmImage {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) max_value: usize,
+    pub(crate) samples: Vec<u16>,
+}
+
+impl PpmImage {
+    pub(crate) fn decode(data: &[u8]) -> Result<PpmImage, ImageError> {
+        let is_binary = match data {
+            [b'P', b'3', ..] => false,
+            [b'P', b'6', ..] => true,
+            _ => return Err(ImageError::Malformed("expected a P3 or P6 PPM header")),
+        };
+        let mut position = 2;
+        // skips whitespace and comments before each header field (and, for
+        // P3, each sample)
+        let next_number = |position: &mut usize| -> Result<usize, ImageError> {
+            loop {
+                match data.get(*position) {
+                    Some(b'#') => {
+                        while data.get(*position).is_some_and(|&byte| byte != b'\n') {
+                            *position += 1;
+                        }
+                    }
+                    Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+                    Some(_) => break,
+                    None => return Err(ImageError::Truncated),
+                }
+            }
+            let start = *position;
+            while data
+                .get(*position)
+                .is_some_and(|byte| byte.is_ascii_digit())
+            {
+                *position += 1;
+            }
+            std::str::from_utf8(&data[start..*position])
+                .ok()
+                .and_then(|digits| digits.parse().ok())
+                .ok_or(ImageError::Malformed("expected a number in PPM data"))
+        };
+
+        let width = next_number(&mut position)?;
+        let height = next_number(&mut position)?;
+        let max_value = next_number(&mut position)?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(ImageError::Malformed("invalid PPM maximum value"));
+        }
+        let clamp = |sample: usize| sample.min(max_value) as u16;
+        // binary samples take two bytes, big-endian, past 255
+        let sample_bytes = if max_value > 255 { 2 } else { 1 };
+
+        // exactly one whitespace byte separates a binary header from the
+        // samples; ASCII samples take at least a byte each
+        let start = position + 1;
+        let available = data.len().saturating_sub(start);
+        let pixel_bytes = if is_binary { 3 * sample_bytes } else { 3 };
+        let length = image_size(width, height, pixel_bytes, available)?;
+        let mut samples = Vec::with_capacity(length / pixel_bytes * 3);
+        if is_binary {
+            let sample = |bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .fold(0, |sample, &byte| sample << 8 | byte as usize)
+            };
+            let bytes = &data[start..start + length];
+            samples.extend(bytes.chunks(sample_bytes).map(|bytes| clamp(sample(bytes))));
+        } else {
+            for _ in 0..width * height * 3 {
+                samples.push(clamp(next_number(&mut position)?));
+            }
+        }
+
+        Ok(PpmImage {
+            width,
+            height,
+            max_value,
+            samples,
+        })
+    }
+}
+
+// the bytes taken by width by height pixels of the given size, checked
+// against the data available for them before anything is allocated, so that
+// a header can't ask for more memory than the file could fill
+pub(crate) fn image_size(
+    width: usize,
+    height: usize,
+    pixel_bytes: usize,
+    available: usize,
+) -> Result<usize, ImageError> {
+    let length = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(pixel_bytes))
+        .ok_or(ImageError::Malformed("image dimensions too large"))?;
+    if length > available {
+        return Err(ImageError::Truncated);
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_ppm_images() {
+        let ascii = b"P3\n# a comment\n2 1\n10\n10 0 0 0 5 10\n";
+        let image = RawImage::decode(ascii).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [0, 127, 255, 255]);
+
+        let binary = b"P6 1 2 255\n\x01\x02\x03\xfa\xfb\xfc";
+        let image = RawImage::decode(binary).unwrap();
+        assert_eq!(image.pixel(0, 1), [250, 251, 252, 255]);
+
+        let wide = b"P6 1 1 65535\n\xff\xff\x80\x00\x00\x01";
+        let image = RawImage::decode(wide).unwrap();
+        assert_eq!(image.pixel(0, 0), [255, 127, 0, 255]);
+    }
+
+    #[test]
+    fn decode_unknown_or_truncated_images() {
+        assert_eq!(
+            RawImage::decode(b"GIF89a"),
+            Err(ImageError::Unsupported("unrecognised image format"))
+        );
+        assert_eq!(
+            RawImage::decode(b"P6 2 2 255\n\x00"),
+            Err(ImageError::Truncated)
+        );
+        for huge in [
+            &b"P3 18446744073709551615 2 255\n0 0 0"[..],
+            b"P6 4294967296 4294967296 255\n",
+        ] {
+            assert_eq!(
+                RawImage::decode(huge),
+                Err(ImageError::Malformed("image dimensions too large"))
+            );
+        }
+        // refused before the pixels are allocated
+        assert_eq!(
+            RawImage::decode(b"P6 100000 100000 255\n\x00\x00\x00"),
+            Err(ImageError::Truncated)
+        );
+        assert_eq!(
+            RawImage::decode(b"P3 1000000 1000000 255\n0 0 0"),
+            Err(ImageError::Truncated)
+        );
+    }
+}
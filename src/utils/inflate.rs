@@ -0,0 +1,300 @@
+use crate::utils::ImageError;
+
+// A small, unoptimised DEFLATE decoder (RFC 1951), decoding Huffman codes a
+// bit at a time as in zlib's puff.
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// the order in which code length code lengths are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.position).ok_or(ImageError::Truncated)?;
+            self.position += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u64 << count) - 1) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+}
+
+// canonical Huffman code, as the number of codes of each length and the
+// symbols in code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, ImageError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(ImageError::Malformed("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; offsets[MAX_BITS + 1] as usize];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        counts[0] = 0;
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ImageError::Malformed("invalid Huffman code"))
+    }
+}
+
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let mut reader = BitReader {
+        data,
+        position: 0,
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+    let mut output = vec![];
+
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(&mut reader, &mut output)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                compressed_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                compressed_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(ImageError::Malformed("invalid deflate block type")),
+        }
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+// skips the two byte zlib header and ignores the trailing checksum
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let header = data.get(..2).ok_or(ImageError::Truncated)?;
+    if header[0] & 0x0f != 8 || !(header[0] as u16 * 256 + header[1] as u16).is_multiple_of(31) {
+        return Err(ImageError::Malformed("invalid zlib header"));
+    }
+    if header[1] & 0x20 != 0 {
+        return Err(ImageError::Unsupported("zlib preset dictionary"));
+    }
+    inflate(&data[2..])
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), ImageError> {
+    reader.align_to_byte();
+    let position = reader.position;
+    let header = reader
+        .data
+        .get(position..position + 4)
+        .ok_or(ImageError::Truncated)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if length != !complement {
+        return Err(ImageError::Malformed("stored block length mismatch"));
+    }
+    let start = position + 4;
+    let end = start + length as usize;
+    output.extend(reader.data.get(start..end).ok_or(ImageError::Truncated)?);
+    reader.position = end;
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), ImageError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(ImageError::Malformed("too many deflate codes"));
+    }
+
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths)?;
+
+    let mut lengths = vec![];
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or(ImageError::Malformed("repeat with no previous length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(ImageError::Malformed("too many code lengths"));
+    }
+    if lengths[256] == 0 {
+        return Err(ImageError::Malformed("no end of block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(ImageError::Malformed("invalid length code"));
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(ImageError::Malformed("invalid distance code"));
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(ImageError::Malformed("distance too far back"));
+                }
+
+                // copies may overlap the bytes they produce
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inflate_fixed_and_dynamic_blocks() {
+        let fixed = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01, 0x68, 0x03,
+            0x08, 0xb1,
+        ];
+        assert_eq!(
+            zlib_decompress(&fixed).unwrap(),
+            b"hello hello hello hello".to_vec()
+        );
+
+        // a mix of literals and matches, compressed with a dynamic code
+        let expected: Vec<u8> = (0..300usize)
+            .map(|i| b"abcdefgh"[(i * i * 7 + i * 3) / 5 % 8])
+            .collect();
+        let dynamic = [
+            0x78, 0xda, 0xed, 0xca, 0x41, 0x09, 0x00, 0x40, 0x10, 0xc3, 0x40, 0xad, 0xa1, 0x84,
+            0xfa, 0x77, 0x70, 0x47, 0x75, 0x2c, 0xe4, 0x35, 0x84, 0xb4, 0xac, 0x80, 0x91, 0xa2,
+            0x4d, 0x5c, 0x75, 0xf0, 0x99, 0xfb, 0x7c, 0xec, 0x30, 0x75, 0x31,
+        ];
+        assert_eq!(dynamic[2] & 0b110, 0b100);
+        assert_eq!(zlib_decompress(&dynamic).unwrap(), expected);
+    }
+
+    #[test]
+    fn inflate_rejects_bad_data() {
+        assert_eq!(zlib_decompress(&[0x78]), Err(ImageError::Truncated));
+        assert_eq!(
+            zlib_decompress(&[0x78, 0x00, 0x00]),
+            Err(ImageError::Malformed("invalid zlib header"))
+        );
+        assert_eq!(
+            inflate(&[0x07]),
+            Err(ImageError::Malformed("invalid deflate block type"))
+        );
+    }
+}
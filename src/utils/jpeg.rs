@@ -0,0 +1,866 @@
+use std::f64::consts::PI;
+
+use crate::utils::{ImageError, RawImage};
+
+// A baseline (sequential, Huffman coded, 8-bit) JPEG decoder and encoder.
+// Chroma is upsampled by repeating samples, and three component images are
+// taken to be YCbCr as in JFIF. Encoded images are JFIF with full resolution
+// chroma, using the example quantisation and Huffman tables of the standard
+// (Annex K), so need no optimisation pass.
+
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+#[derive(Clone, Default)]
+struct Huffman {
+    counts: [u16; 17],
+    symbols: Vec<u8>,
+}
+
+impl Huffman {
+    fn decode(&self, reader: &mut BitReader) -> Result<u8, ImageError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=16 {
+            code |= reader.bit()? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or(ImageError::Malformed("invalid Huffman code"));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ImageError::Malformed("invalid Huffman code"))
+    }
+}
+
+struct Component {
+    id: u8,
+    horizontal: usize,
+    vertical: usize,
+    quantisation: usize,
+    dc_table: usize,
+    ac_table: usize,
+    dc_prediction: i32,
+    // samples for whole MCUs, so possibly wider and taller than the image
+    stride: usize,
+    samples: Vec<u8>,
+}
+
+// reads entropy coded data, dropping stuffed zero bytes and stopping at the
+// next marker
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Result<u32, ImageError> {
+        if self.bit_count == 0 {
+            let byte = *self.data.get(self.position).ok_or(ImageError::Truncated)?;
+            if byte == 0xff {
+                match self.data.get(self.position + 1) {
+                    Some(0x00) => self.position += 2,
+                    // a marker, so the scan has run out of data
+                    Some(_) => return Err(ImageError::Malformed("JPEG scan ends early")),
+                    None => return Err(ImageError::Truncated),
+                }
+            } else {
+                self.position += 1;
+            }
+            self.bit_buffer = byte as u32;
+            self.bit_count = 8;
+        }
+        self.bit_count -= 1;
+        Ok((self.bit_buffer >> self.bit_count) & 1)
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.bit()?;
+        }
+        Ok(value)
+    }
+
+    // a value of the given bit length, where codes with a leading 0 are
+    // negative; no coefficient of 8-bit samples needs more than 16 bits
+    fn signed(&mut self, length: u32) -> Result<i32, ImageError> {
+        if length == 0 {
+            return Ok(0);
+        }
+        if length > 16 {
+            return Err(ImageError::Malformed("JPEG coefficient too long"));
+        }
+        let value = self.bits(length)? as i32;
+        if value < 1 << (length - 1) {
+            Ok(value - (1 << length) + 1)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn restart(&mut self) -> Result<(), ImageError> {
+        self.bit_count = 0;
+        match self.data.get(self.position..self.position + 2) {
+            Some([0xff, 0xd0..=0xd7]) => {
+                self.position += 2;
+                Ok(())
+            }
+            Some(_) => Err(ImageError::Malformed("missing JPEG restart marker")),
+            None => Err(ImageError::Truncated),
+        }
+    }
+}
+
+struct Decoder {
+    width: usize,
+    height: usize,
+    quantisation: [[u16; 64]; 4],
+    dc_tables: [Huffman; 4],
+    ac_tables: [Huffman; 4],
+    restart_interval: usize,
+    components: Vec<Component>,
+    max_horizontal: usize,
+    max_vertical: usize,
+    has_frame: bool,
+    idct: [[f64; 8]; 8],
+}
+
+// basis[x][u] = C(u) cos((2x + 1) u pi / 16) / 2, for the DCT both ways
+fn dct_basis() -> [[f64; 8]; 8] {
+    let mut basis = [[0.0; 8]; 8];
+    for (x, row) in basis.iter_mut().enumerate() {
+        for (u, entry) in row.iter_mut().enumerate() {
+            let scale = if u == 0 { 0.5_f64.sqrt() } else { 1.0 };
+            *entry = scale * ((2 * x + 1) as f64 * u as f64 * PI / 16.0).cos() / 2.0;
+        }
+    }
+    basis
+}
+
+pub fn decode(data: &[u8]) -> Result<RawImage, ImageError> {
+    if data.get(..2) != Some(&[0xff, 0xd8][..]) {
+        return Err(ImageError::Malformed("missing JPEG start of image"));
+    }
+
+    let idct = dct_basis();
+    let mut decoder = Decoder {
+        width: 0,
+        height: 0,
+        quantisation: [[1; 64]; 4],
+        dc_tables: Default::default(),
+        ac_tables: Default::default(),
+        restart_interval: 0,
+        components: vec![],
+        max_horizontal: 1,
+        max_vertical: 1,
+        has_frame: false,
+        idct,
+    };
+
+    let mut position = 2;
+    loop {
+        // markers may be padded with any number of fill bytes
+        while data.get(position) == Some(&0xff) && data.get(position + 1) == Some(&0xff) {
+            position += 1;
+        }
+        let marker = match data.get(position..position + 2) {
+            Some([0xff, marker]) => *marker,
+            Some(_) => return Err(ImageError::Malformed("expected a JPEG marker")),
+            None => return Err(ImageError::Truncated),
+        };
+        if marker == 0xd9 {
+            break;
+        }
+        let length_bytes = data
+            .get(position + 2..position + 4)
+            .ok_or(ImageError::Truncated)?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let segment = data
+            .get(position + 4..position + 2 + length)
+            .ok_or(ImageError::Truncated)?;
+        position += 2 + length;
+
+        match marker {
+            0xc0 | 0xc1 => decoder.read_frame(segment, data.len())?,
+            0xc2 | 0xc6 | 0xca | 0xce => return Err(ImageError::Unsupported("progressive JPEG")),
+            0xc3 | 0xc5 | 0xc7 | 0xc9 | 0xcb | 0xcd | 0xcf => {
+                return Err(ImageError::Unsupported("lossless or arithmetic coded JPEG"))
+            }
+            0xc4 => decoder.read_huffman_tables(segment)?,
+            0xdb => decoder.read_quantisation_tables(segment)?,
+            0xdd => {
+                let interval = segment.get(..2).ok_or(ImageError::Truncated)?;
+                decoder.restart_interval = u16::from_be_bytes([interval[0], interval[1]]) as usize;
+            }
+            0xda => position += decoder.read_scan(segment, &data[position..])?,
+            _ => (),
+        }
+    }
+
+    decoder.to_image()
+}
+
+impl Decoder {
+    // the whole file's length bounds the size of the image, since every
+    // block takes at least two bits of it
+    fn read_frame(&mut self, segment: &[u8], file_length: usize) -> Result<(), ImageError> {
+        if self.has_frame {
+            return Err(ImageError::Malformed("more than one JPEG frame header"));
+        }
+        let header = segment.get(..6).ok_or(ImageError::Truncated)?;
+        if header[0] != 8 {
+            return Err(ImageError::Unsupported("JPEG samples wider than 8 bits"));
+        }
+        self.height = u16::from_be_bytes([header[1], header[2]]) as usize;
+        self.width = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let component_count = header[5] as usize;
+        if self.width == 0 || self.height == 0 {
+            return Err(ImageError::Unsupported("JPEG with height defined later"));
+        }
+        if component_count != 1 && component_count != 3 {
+            return Err(ImageError::Unsupported("JPEG colour space"));
+        }
+
+        let specifications = segment
+            .get(6..6 + 3 * component_count)
+            .ok_or(ImageError::Truncated)?;
+        for specification in specifications.chunks(3) {
+            let horizontal = (specification[1] >> 4) as usize;
+            let vertical = (specification[1] & 0x0f) as usize;
+            if !(1..=4).contains(&horizontal) || !(1..=4).contains(&vertical) {
+                return Err(ImageError::Malformed("invalid JPEG sampling factor"));
+            }
+            self.components.push(Component {
+                id: specification[0],
+                horizontal,
+                vertical,
+                quantisation: (specification[2] & 3) as usize,
+                dc_table: 0,
+                ac_table: 0,
+                dc_prediction: 0,
+                stride: 0,
+                samples: vec![],
+            });
+        }
+
+        self.max_horizontal = self.components.iter().map(|c| c.horizontal).max().unwrap();
+        self.max_vertical = self.components.iter().map(|c| c.vertical).max().unwrap();
+        let mcu_columns = self.width.div_ceil(8 * self.max_horizontal);
+        let mcu_rows = self.height.div_ceil(8 * self.max_vertical);
+        let blocks: usize = self
+            .components
+            .iter()
+            .map(|c| mcu_columns * c.horizontal * mcu_rows * c.vertical)
+            .sum();
+        if blocks > file_length.saturating_mul(4) {
+            return Err(ImageError::Truncated);
+        }
+        for component in &mut self.components {
+            component.stride = mcu_columns * component.horizontal * 8;
+            component.samples = vec![0; component.stride * mcu_rows * component.vertical * 8];
+        }
+        self.has_frame = true;
+        Ok(())
+    }
+
+    fn read_huffman_tables(&mut self, mut segment: &[u8]) -> Result<(), ImageError> {
+        while !segment.is_empty() {
+            let header = segment.get(..17).ok_or(ImageError::Truncated)?;
+            let mut counts = [0u16; 17];
+            for (length, &count) in header[1..].iter().enumerate() {
+                counts[length + 1] = count as u16;
+            }
+            let total = counts.iter().sum::<u16>() as usize;
+            let symbols = segment
+                .get(17..17 + total)
+                .ok_or(ImageError::Truncated)?
+                .to_vec();
+
+            let table = Huffman { counts, symbols };
+            let index = (header[0] & 3) as usize;
+            match header[0] >> 4 {
+                0 => self.dc_tables[index] = table,
+                1 => self.ac_tables[index] = table,
+                _ => return Err(ImageError::Malformed("invalid JPEG Huffman table class")),
+            }
+            segment = &segment[17 + total..];
+        }
+        Ok(())
+    }
+
+    fn read_quantisation_tables(&mut self, mut segment: &[u8]) -> Result<(), ImageError> {
+        while let Some(&header) = segment.first() {
+            let is_wide = header >> 4 == 1;
+            let size = if is_wide { 128 } else { 64 };
+            let values = segment.get(1..1 + size).ok_or(ImageError::Truncated)?;
+            // kept in zigzag order, like the coefficients they scale
+            let table = &mut self.quantisation[(header & 3) as usize];
+            for (idx, entry) in table.iter_mut().enumerate() {
+                *entry = if is_wide {
+                    u16::from_be_bytes([values[2 * idx], values[2 * idx + 1]])
+                } else {
+                    values[idx] as u16
+                };
+            }
+            segment = &segment[1 + size..];
+        }
+        Ok(())
+    }
+
+    // decodes the entropy coded data following the scan header, returning
+    // its length
+    fn read_scan(&mut self, header: &[u8], data: &[u8]) -> Result<usize, ImageError> {
+        if !self.has_frame {
+            return Err(ImageError::Malformed("JPEG scan before frame header"));
+        }
+        let count = *header.first().ok_or(ImageError::Truncated)? as usize;
+        let selectors = header.get(1..1 + 2 * count).ok_or(ImageError::Truncated)?;
+        let mut scan_components = vec![];
+        for selector in selectors.chunks(2) {
+            let index = self
+                .components
+                .iter()
+                .position(|component| component.id == selector[0])
+                .ok_or(ImageError::Malformed("JPEG scan of unknown component"))?;
+            let component = &mut self.components[index];
+            component.dc_table = (selector[1] >> 4 & 3) as usize;
+            component.ac_table = (selector[1] & 3) as usize;
+            component.dc_prediction = 0;
+            scan_components.push(index);
+        }
+
+        // a single component scan covers just that component's blocks, with
+        // no padding out to whole MCUs
+        let (mcu_columns, mcu_rows) = if let [index] = scan_components[..] {
+            let component = &self.components[index];
+            (
+                (self.width * component.horizontal)
+                    .div_ceil(self.max_horizontal)
+                    .div_ceil(8),
+                (self.height * component.vertical)
+                    .div_ceil(self.max_vertical)
+                    .div_ceil(8),
+            )
+        } else {
+            (
+                self.width.div_ceil(8 * self.max_horizontal),
+                self.height.div_ceil(8 * self.max_vertical),
+            )
+        };
+
+        let mut reader = BitReader {
+            data,
+            position: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        };
+        for mcu in 0..mcu_columns * mcu_rows {
+            if self.restart_interval > 0 && mcu > 0 && mcu % self.restart_interval == 0 {
+                reader.restart()?;
+                for &index in &scan_components {
+                    self.components[index].dc_prediction = 0;
+                }
+            }
+            let (mcu_column, mcu_row) = (mcu % mcu_columns, mcu / mcu_columns);
+            for &index in &scan_components {
+                let (horizontal, vertical) = if scan_components.len() == 1 {
+                    (1, 1)
+                } else {
+                    let component = &self.components[index];
+                    (component.horizontal, component.vertical)
+                };
+                for block in 0..horizontal * vertical {
+                    let block_column = mcu_column * horizontal + block % horizontal;
+                    let block_row = mcu_row * vertical + block / horizontal;
+                    self.read_block(&mut reader, index, block_column, block_row)?;
+                }
+            }
+        }
+
+        // skips to the next marker other than a restart
+        let mut position = reader.position;
+        while let Some(window) = data.get(position..position + 2) {
+            match window {
+                [0xff, 0x00 | 0xd0..=0xd7] => position += 2,
+                [0xff, _] => break,
+                _ => position += 1,
+            }
+        }
+        Ok(position)
+    }
+
+    fn read_block(
+        &mut self,
+        reader: &mut BitReader,
+        index: usize,
+        block_column: usize,
+        block_row: usize,
+    ) -> Result<(), ImageError> {
+        let component = &self.components[index];
+        let quantisation = &self.quantisation[component.quantisation];
+        let dc_table = &self.dc_tables[component.dc_table];
+        let ac_table = &self.ac_tables[component.ac_table];
+
+        let mut coefficients = [0.0; 64];
+        let length = dc_table.decode(reader)? as u32;
+        let dc = component
+            .dc_prediction
+            .checked_add(reader.signed(length)?)
+            .ok_or(ImageError::Malformed("JPEG DC coefficient out of range"))?;
+        coefficients[0] = dc as f64 * quantisation[0] as f64;
+
+        let mut k = 1;
+        while k < 64 {
+            let symbol = ac_table.decode(reader)?;
+            let (run, length) = ((symbol >> 4) as usize, (symbol & 0x0f) as u32);
+            if length == 0 {
+                if run != 15 {
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run;
+            if k >= 64 {
+                return Err(ImageError::Malformed("JPEG coefficient out of range"));
+            }
+            let value = reader.signed(length)?;
+            coefficients[ZIGZAG[k]] = value as f64 * quantisation[k] as f64;
+            k += 1;
+        }
+
+        // separable inverse DCT, rows then columns
+        let idct = &self.idct;
+        let mut rows = [0.0; 64];
+        for v in 0..8 {
+            for x in 0..8 {
+                rows[v * 8 + x] = (0..8).map(|u| idct[x][u] * coefficients[v * 8 + u]).sum();
+            }
+        }
+
+        let component = &mut self.components[index];
+        component.dc_prediction = dc;
+        let stride = component.stride;
+        for (y, weights) in idct.iter().enumerate() {
+            let row = block_row * 8 + y;
+            for x in 0..8 {
+                let value: f64 = (0..8).map(|v| weights[v] * rows[v * 8 + x]).sum();
+                let offset = row * stride + block_column * 8 + x;
+                if let Some(sample) = component.samples.get_mut(offset) {
+                    *sample = (value + 128.0).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_image(&self) -> Result<RawImage, ImageError> {
+        if !self.has_frame {
+            return Err(ImageError::Malformed("missing JPEG frame header"));
+        }
+        let sample = |component: &Component, column: usize, row: usize| -> f64 {
+            let x = column * component.horizontal / self.max_horizontal;
+            let y = row * component.vertical / self.max_vertical;
+            component.samples[y * component.stride + x] as f64
+        };
+
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for row in 0..self.height {
+            for column in 0..self.width {
+                match &self.components[..] {
+                    [grey] => {
+                        let grey = sample(grey, column, row) as u8;
+                        rgba.extend([grey, grey, grey, 255]);
+                    }
+                    [luma, blue, red] => {
+                        let y = sample(luma, column, row);
+                        let cb = sample(blue, column, row) - 128.0;
+                        let cr = sample(red, column, row) - 128.0;
+                        let to_byte = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+                        rgba.extend([
+                            to_byte(y + 1.402 * cr),
+                            to_byte(y - 0.344136 * cb - 0.714136 * cr),
+                            to_byte(y + 1.772 * cb),
+                            255,
+                        ]);
+                    }
+                    _ => return Err(ImageError::Unsupported("JPEG colour space")),
+                }
+            }
+        }
+
+        Ok(RawImage {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
+
+// the example tables of Annex K, quantisation in natural order and Huffman
+// tables as counts of codes of each length from 1 to 16, then symbols
+const LUMA_QUANTISATION: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMA_QUANTISATION: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+const LUMA_DC_COUNTS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const CHROMA_DC_COUNTS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_SYMBOLS: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const LUMA_AC_COUNTS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const LUMA_AC_SYMBOLS: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5,
+    0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2,
+    0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+const CHROMA_AC_COUNTS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const CHROMA_AC_SYMBOLS: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0,
+    0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26,
+    0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+    0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+    0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda,
+    0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+
+// (code, length) for each symbol, assigned canonically
+fn huffman_codes(counts: &[u8; 16], symbols: &[u8]) -> [(u16, u8); 256] {
+    let mut codes = [(0, 0); 256];
+    let (mut code, mut index) = (0u16, 0);
+    for (length, &count) in (1..=16).zip(counts) {
+        for &symbol in &symbols[index..index + count as usize] {
+            codes[symbol as usize] = (code, length);
+            code += 1;
+        }
+        index += count as usize;
+        code <<= 1;
+    }
+    codes
+}
+
+// writes entropy coded data, stuffing a zero byte after each 0xff
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        for shift in (0..count).rev() {
+            self.bit_buffer = (self.bit_buffer << 1) | ((value >> shift) & 1);
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.data.push(self.bit_buffer as u8);
+                if self.bit_buffer as u8 == 0xff {
+                    self.data.push(0x00);
+                }
+                (self.bit_buffer, self.bit_count) = (0, 0);
+            }
+        }
+    }
+
+    fn code(&mut self, (code, length): (u16, u8)) {
+        self.bits(code as u32, length as u32);
+    }
+
+    // the bit length of a value, then its bits, with negative values written
+    // less one as signed reads them
+    fn signed(&mut self, value: i32, codes: &[(u16, u8); 256], run: u8) {
+        let length = 32 - value.unsigned_abs().leading_zeros();
+        self.code(codes[(run << 4 | length as u8) as usize]);
+        let bits = if value < 0 { value - 1 } else { value };
+        self.bits(bits as u32 & ((1 << length) - 1), length);
+    }
+
+    // padded with 1s
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bits(0x7f, 8 - self.bit_count);
+        }
+        self.data
+    }
+}
+
+fn write_segment(jpeg: &mut Vec<u8>, marker: u8, body: &[u8]) {
+    jpeg.extend([0xff, marker]);
+    jpeg.extend(((body.len() + 2) as u16).to_be_bytes());
+    jpeg.extend(body);
+}
+
+// the example tables scaled as by libjpeg, so that quality 50 gives them
+// unchanged and 100 gives all 1s
+fn scaled_quantisation(table: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    table.map(|value| ((value as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+// sizes are 16-bit
+pub fn fits(width: usize, height: usize) -> bool {
+    width <= u16::MAX as usize && height <= u16::MAX as usize
+}
+
+// rgb holds the rows top to bottom, three bytes per pixel; quality is from 1
+// (smallest) to 100 (best)
+pub fn encode_rgb(width: usize, height: usize, rgb: &[u8], quality: u8) -> Vec<u8> {
+    assert!(fits(width, height));
+    assert_eq!(rgb.len(), width * height * 3);
+    let quantisation = [
+        scaled_quantisation(&LUMA_QUANTISATION, quality),
+        scaled_quantisation(&CHROMA_QUANTISATION, quality),
+    ];
+
+    let mut jpeg = vec![0xff, 0xd8];
+    // JFIF 1.1, no density or thumbnail
+    write_segment(
+        &mut jpeg,
+        0xe0,
+        &[b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0],
+    );
+    let mut tables = vec![];
+    for (id, table) in quantisation.iter().enumerate() {
+        tables.push(id as u8);
+        tables.extend(ZIGZAG.map(|index| table[index] as u8));
+    }
+    write_segment(&mut jpeg, 0xdb, &tables);
+    let mut frame = vec![8];
+    frame.extend((height as u16).to_be_bytes());
+    frame.extend((width as u16).to_be_bytes());
+    frame.extend([3, 1, 0x11, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    write_segment(&mut jpeg, 0xc0, &frame);
+    let huffman_tables: [(u8, &[u8; 16], &[u8]); 4] = [
+        (0x00, &LUMA_DC_COUNTS, &DC_SYMBOLS),
+        (0x10, &LUMA_AC_COUNTS, &LUMA_AC_SYMBOLS),
+        (0x01, &CHROMA_DC_COUNTS, &DC_SYMBOLS),
+        (0x11, &CHROMA_AC_COUNTS, &CHROMA_AC_SYMBOLS),
+    ];
+    let mut tables = vec![];
+    for (class_and_id, counts, symbols) in huffman_tables {
+        tables.push(class_and_id);
+        tables.extend(counts);
+        tables.extend(symbols);
+    }
+    write_segment(&mut jpeg, 0xc4, &tables);
+    write_segment(&mut jpeg, 0xda, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let codes = [
+        [
+            huffman_codes(&LUMA_DC_COUNTS, &DC_SYMBOLS),
+            huffman_codes(&LUMA_AC_COUNTS, &LUMA_AC_SYMBOLS),
+        ],
+        [
+            huffman_codes(&CHROMA_DC_COUNTS, &DC_SYMBOLS),
+            huffman_codes(&CHROMA_AC_COUNTS, &CHROMA_AC_SYMBOLS),
+        ],
+    ];
+    let basis = dct_basis();
+    let mut writer = BitWriter::default();
+    let mut dc_predictions = [0; 3];
+    for block_row in 0..height.div_ceil(8) {
+        for block_column in 0..width.div_ceil(8) {
+            // YCbCr samples less 128, repeating the edge pixels past the
+            // image's right and bottom edges
+            let mut samples = [[0.0; 64]; 3];
+            for y in 0..8 {
+                for x in 0..8 {
+                    let row = (block_row * 8 + y).min(height - 1);
+                    let column = (block_column * 8 + x).min(width - 1);
+                    let offset = (row * width + column) * 3;
+                    let [red, green, blue] =
+                        [rgb[offset], rgb[offset + 1], rgb[offset + 2]].map(|value| value as f64);
+                    samples[0][y * 8 + x] = 0.299 * red + 0.587 * green + 0.114 * blue - 128.0;
+                    samples[1][y * 8 + x] = -0.168736 * red - 0.331264 * green + 0.5 * blue;
+                    samples[2][y * 8 + x] = 0.5 * red - 0.418688 * green - 0.081312 * blue;
+                }
+            }
+
+            for (component, samples) in samples.iter().enumerate() {
+                let table = component.min(1);
+                let [dc_codes, ac_codes] = &codes[table];
+                // separable forward DCT, rows then columns, then quantised
+                // in zigzag order
+                let mut rows = [0.0; 64];
+                for y in 0..8 {
+                    for u in 0..8 {
+                        rows[y * 8 + u] = (0..8).map(|x| basis[x][u] * samples[y * 8 + x]).sum();
+                    }
+                }
+                let levels: [i32; 64] = std::array::from_fn(|k| {
+                    let (v, u) = (ZIGZAG[k] / 8, ZIGZAG[k] % 8);
+                    let coefficient: f64 = (0..8).map(|y| basis[y][v] * rows[y * 8 + u]).sum();
+                    (coefficient / quantisation[table][ZIGZAG[k]] as f64).round() as i32
+                });
+
+                writer.signed(levels[0] - dc_predictions[component], dc_codes, 0);
+                dc_predictions[component] = levels[0];
+                let mut run = 0;
+                for &level in &levels[1..] {
+                    if level == 0 {
+                        run += 1;
+                        continue;
+                    }
+                    while run >= 16 {
+                        writer.code(ac_codes[0xf0]);
+                        run -= 16;
+                    }
+                    writer.signed(level, ac_codes, run);
+                    run = 0;
+                }
+                if run > 0 {
+                    writer.code(ac_codes[0x00]);
+                }
+            }
+        }
+    }
+    jpeg.extend(writer.finish());
+    jpeg.extend([0xff, 0xd9]);
+    jpeg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_baseline_jpeg_with_chroma_subsampling() {
+        let jpeg = std::fs::read("./resources/test_inputs/python.jpg").unwrap();
+        let image = decode(&jpeg).unwrap();
+        let reference = RawImage::load("./resources/test_inputs/python.ppm").unwrap();
+        assert_eq!((image.width, image.height), (16, 16));
+
+        // lossy, so close to the original on average rather than exact
+        let total_error: usize = image
+            .rgba
+            .iter()
+            .zip(&reference.rgba)
+            .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs() as usize)
+            .sum();
+        assert!(total_error / image.rgba.len() < 8);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        // a gradient with a hard edge, 13 by 10 so that blocks overhang
+        let (width, height) = (13, 10);
+        let mut rgb = vec![];
+        for row in 0..height {
+            for column in 0..width {
+                let edge = if column < 6 { 40 } else { 220 };
+                rgb.extend([edge, (row * 20) as u8, (column * 15) as u8]);
+            }
+        }
+        let error_at = |quality| {
+            let image = decode(&encode_rgb(width, height, &rgb, quality)).unwrap();
+            assert_eq!((image.width, image.height), (width, height));
+            let total_error: usize = image
+                .rgba
+                .chunks(4)
+                .zip(rgb.chunks(3))
+                .flat_map(|(decoded, original)| decoded[..3].iter().zip(original))
+                .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs() as usize)
+                .sum();
+            total_error as f64 / rgb.len() as f64
+        };
+        assert!(error_at(100) < 2.0);
+        assert!(error_at(90) < 4.0);
+        assert!(error_at(10) > error_at(90));
+
+        let small = encode_rgb(width, height, &rgb, 10);
+        assert!(small.len() < encode_rgb(width, height, &rgb, 100).len());
+    }
+
+    // 1x1 greyscale frame
+    const GREY_FRAME: [u8; 13] = [0xff, 0xc0, 0, 11, 8, 0, 1, 0, 1, 1, 1, 0x11, 0];
+
+    fn greyscale_jpeg(frames: &[[u8; 13]], dc_symbol: u8) -> Vec<u8> {
+        let mut jpeg = vec![0xff, 0xd8];
+        jpeg.extend(frames.concat());
+        // DC table with the one symbol given (4 for a four bit difference),
+        // AC table with the one symbol 0 (end of block), both coded by a
+        // single 0 bit
+        jpeg.extend([0xff, 0xc4, 0, 20, 0x00, 1, 0, 0, 0, 0, 0, 0, 0]);
+        jpeg.extend([0, 0, 0, 0, 0, 0, 0, 0, dc_symbol]);
+        jpeg.extend([0xff, 0xc4, 0, 20, 0x10, 1, 0, 0, 0, 0, 0, 0, 0]);
+        jpeg.extend([0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // quantisation of 8 for the DC coefficient, so a difference of 10
+        // gives a level of 10 above mid grey
+        let mut quantisation = vec![0xff, 0xdb, 0, 67, 0x00, 8];
+        quantisation.extend([1; 63]);
+        jpeg.extend(quantisation);
+        // DC code 0, value 1010, AC code 0, padded with 1s
+        jpeg.extend([0xff, 0xda, 0, 8, 1, 1, 0x00, 0, 63, 0, 0b0101_0011]);
+        jpeg.extend([0xff, 0xd9]);
+        jpeg
+    }
+
+    #[test]
+    fn decode_greyscale_jpeg() {
+        let jpeg = greyscale_jpeg(&[GREY_FRAME], 4);
+        let image = decode(&jpeg).unwrap();
+        assert_eq!(image.pixel(0, 0), [138, 138, 138, 255]);
+    }
+
+    #[test]
+    fn reject_progressive_jpeg() {
+        let jpeg = [0xff, 0xd8, 0xff, 0xc2, 0, 2, 0xff, 0xd9];
+        assert_eq!(
+            decode(&jpeg),
+            Err(ImageError::Unsupported("progressive JPEG"))
+        );
+    }
+
+    #[test]
+    fn reject_malformed_jpeg() {
+        assert_eq!(
+            decode(&greyscale_jpeg(&[GREY_FRAME, GREY_FRAME], 4)),
+            Err(ImageError::Malformed("more than one JPEG frame header"))
+        );
+        assert_eq!(
+            decode(&greyscale_jpeg(&[GREY_FRAME], 32)),
+            Err(ImageError::Malformed("JPEG coefficient too long"))
+        );
+        // far more blocks than the file could code, refused before the
+        // samples are allocated
+        let mut huge = GREY_FRAME;
+        huge[5..9].copy_from_slice(&[0xff; 4]);
+        assert_eq!(
+            decode(&greyscale_jpeg(&[huge], 4)),
+            Err(ImageError::Truncated)
+        );
+    }
+}
@@ -1,15 +1,41 @@
+pub(crate) mod bmp;
 pub mod builder;
+pub(crate) mod exr;
 pub(crate) mod filehandler;
 pub(crate) mod floats;
+pub mod font;
+pub(crate) mod gif;
+pub(crate) mod hdr;
+pub mod ies;
+pub mod image;
+pub(crate) mod inflate;
+pub(crate) mod jpeg;
+pub mod noise;
 pub mod objparser;
+pub(crate) mod png;
+pub mod random;
+pub(crate) mod tga;
 
 // crate-level re-exports
 pub(crate) use builder::*;
-pub(crate) use filehandler::*;
 pub(crate) use floats::*;
-pub(crate) use objparser::*;
+pub(crate) use font::*;
+pub(crate) use hdr::HdrImage;
+pub(crate) use ies::*;
+pub(crate) use image::*;
+pub(crate) use inflate::*;
+pub(crate) use noise::*;
+pub(crate) use random::*;
 
 // public re-exports (through crate::prelude)
 pub(super) mod prelude {
     pub use super::builder::{BuildInto, Buildable, ConsumingBuilder};
+    pub use super::floats::Float;
+    pub use super::font::{Font, FontError};
+    pub use super::hdr::HdrImage;
+    pub use super::ies::{IesError, IesProfile};
+    pub use super::image::{ImageError, RawImage};
+    pub use super::noise::Perlin;
+    pub use super::objparser::{parse_obj, ObjError, ObjModel};
+    pub use super::random::{Random, SampleKey};
 }
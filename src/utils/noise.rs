@@ -0,0 +1,161 @@
+use std::fmt::{self, Debug};
+
+use crate::collections::Point;
+use crate::utils::{Float, Random};
+
+// Ken Perlin's improved gradient noise, with the permutation table shuffled
+// from a seed. Noise is 0 at every integer lattice point and varies smoothly
+// in between, staying roughly within [-1, 1].
+#[derive(Clone, PartialEq)]
+pub struct Perlin {
+    seed: u64,
+    permutation: Vec<u8>,
+}
+
+impl Debug for Perlin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Perlin").field("seed", &self.seed).finish()
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Perlin {
+        Perlin::new(0)
+    }
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut permutation: Vec<u8> = (0..=255).collect();
+        let mut random = Random::new(seed);
+        // Fisher-Yates
+        for idx in (1..256).rev() {
+            let other = (random.next_u64() % (idx as u64 + 1)) as usize;
+            permutation.swap(idx, other);
+        }
+        permutation.extend_from_within(..);
+
+        Perlin { seed, permutation }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn noise(&self, point: Point) -> Float {
+        let Point { x, y, z } = point;
+        let cell = [x.floor(), y.floor(), z.floor()].map(|c| (c as i64).rem_euclid(256) as usize);
+        let [x, y, z] = [x - x.floor(), y - y.floor(), z - z.floor()];
+        let [u, v, w] = [fade(x), fade(y), fade(z)];
+
+        let p = &self.permutation;
+        let hash = |dx: usize, dy: usize, dz: usize| {
+            let a = p[cell[0] + dx] as usize + cell[1] + dy;
+            p[p[a] as usize + cell[2] + dz]
+        };
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            gradient(
+                hash(dx, dy, dz),
+                x - dx as Float,
+                y - dy as Float,
+                z - dz as Float,
+            )
+        };
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    // fractal sum of octaves, each at twice the frequency and half the
+    // amplitude of the last, normalised back to roughly [-1, 1]
+    pub fn fractal(&self, point: Point, octaves: usize) -> Float {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for _ in 0..octaves.max(1) {
+            let Point { x, y, z } = point;
+            sum += amplitude * self.noise(Point::new(x * frequency, y * frequency, z * frequency));
+            total_amplitude += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum / total_amplitude
+    }
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+// dot product with one of the 12 edge directions of a cube
+fn gradient(hash: u8, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_zero_on_lattice() {
+        let perlin = Perlin::new(7);
+        for point in [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(3.0, -2.0, 17.0),
+            Point::new(-300.0, 1.0, 255.0),
+        ] {
+            assert_eq!(perlin.noise(point), 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_is_seeded_bounded_and_smooth() {
+        let perlin = Perlin::new(7);
+        let mut values = vec![];
+        for step in 0..200 {
+            let point = Point::new(step as Float * 0.173, step as Float * 0.071, 0.5);
+            let value = perlin.noise(point);
+            assert!(value.abs() <= 1.1);
+            assert_eq!(value, Perlin::new(7).noise(point));
+
+            let nearby = perlin.noise(Point::new(point.x + 1e-4, point.y, point.z));
+            assert!((value - nearby).abs() < 1e-3);
+            values.push(value);
+        }
+        assert!(values.iter().any(|&value| value > 0.1));
+        assert!(values.iter().any(|&value| value < -0.1));
+
+        let point = Point::new(0.5, 0.5, 0.5);
+        assert_ne!(perlin.noise(point), Perlin::new(8).noise(point));
+    }
+
+    #[test]
+    fn fractal_with_one_octave_is_noise() {
+        let perlin = Perlin::new(3);
+        let point = Point::new(1.3, 2.7, -0.4);
+        assert_eq!(perlin.fractal(point, 1), perlin.noise(point));
+        assert!(perlin.fractal(point, 4).abs() <= 1.1);
+    }
+}
@@ -1,195 +1,234 @@
-// use std::cell::RefCell;
-// use std::fs::File;
-// use std::io::Read;
-
-// use crate::collections::{Point, Vector};
-// use crate::objects::{Group, Material, Transform, Triangle};
-
-// type ParsedObjects = (Vec<Point>, Vec<Vector>, Vec<Triangle>);
-
-// pub fn parse_obj(file_path: &str) -> Result<ParsedObjects, Box<dyn std::error::Error>> {
-//     let mut file_contents_as_string = String::new();
-//     File::open(file_path)?.read_to_string(&mut file_contents_as_string)?;
-//     let file_lines: Vec<&str> = file_contents_as_string.split("\n").collect();
-
-//     let mut parsed_vertices = vec![];
-//     let mut parsed_normals = vec![];
-//     let mut parsed_shapes: Vec<Triangle> = vec![];
-//     let mut parsed_groups = vec![];
-
-//     let default_group = Group::builder();
-//     let mut current_group: Option<Rc<RefCell<Group>>> = None;
-
-//     for line in file_lines {
-//         match line.split(" ").collect::<Vec<&str>>() {
-//             vertex if vertex[0] == "v" => {
-//                 if let [x_str, y_str, z_str] = vertex[1..4] {
-//                     let x = x_str.parse()?;
-//                     let y = y_str.parse()?;
-//                     let z = z_str.parse()?;
-
-//                     parsed_vertices.push(Point::new(x, y, z));
-//                 } else {
-//                     continue;
-//                 }
-//             }
-
-//             vertex_normal if vertex_normal[0] == "vn" => {
-//                 if let [x_str, y_str, z_str] = vertex_normal[1..4] {
-//                     let x = x_str.parse()?;
-//                     let y = y_str.parse()?;
-//                     let z = z_str.parse()?;
-
-//                     parsed_normals.push(Vector::new(x, y, z));
-//                 } else {
-//                     continue;
-//                 }
-//             }
-
-//             face if face[0] == "f" => {
-//                 if face.len() >= 4 {
-//                     let vertex_indices_as_str = face[1..].to_vec();
-
-//                     let mut vertices = vec![];
-//                     for vertex_idx_str in vertex_indices_as_str {
-//                         let vertex_idx: usize = vertex_idx_str.parse()?;
-
-//                         // 1-indexed to 0-indexed array indices
-//                         vertices.push(parsed_vertices[vertex_idx - 1]);
-//                     }
-
-//                     let triangles = face_triangulation(vertices);
-
-//                     for mut triangle in triangles {
-//                         if current_group.is_some() {
-//                             current_group
-//                                 .as_mut()
-//                                 .unwrap()
-//                                 .borrow_mut()
-//                                 .add_object(&mut triangle);
-//                         } else {
-//                             default_group.borrow_mut().add_object(&mut triangle);
-//                         }
-
-//                         parsed_shapes.push(triangle);
-//                     }
-//                 } else {
-//                     if let [idx1_str, idx2_str, idx3_str] = face[1..4] {
-//                         let idx1: usize = idx1_str.parse()?;
-//                         let idx2: usize = idx2_str.parse()?;
-//                         let idx3: usize = idx3_str.parse()?;
-
-//                         // 1-indexed to 0-indexed array indices
-//                         let vertex1 = parsed_vertices[idx1 - 1];
-//                         let vertex2 = parsed_vertices[idx2 - 1];
-//                         let vertex3 = parsed_vertices[idx3 - 1];
-
-//                         let mut triangle =
-//                             Triangle::new(Material::default(), [vertex1, vertex2, vertex3]);
-//                         if current_group.is_some() {
-//                             current_group
-//                                 .as_mut()
-//                                 .unwrap()
-//                                 .borrow_mut()
-//                                 .add_object(&mut triangle);
-//                         } else {
-//                             default_group.borrow_mut().add_object(&mut triangle);
-//                         }
-
-//                         parsed_shapes.push(triangle);
-//                     } else {
-//                         continue;
-//                     }
-//                 }
-//             }
-
-//             group if group[0] == "g" => {
-//                 if let Some(old_group) = current_group {
-//                     parsed_groups.push(old_group);
-//                 }
-
-//                 let new_group = Group::new::<Triangle>(Transform::default(), vec![]);
-//                 current_group = Some(new_group);
-//                 current_group
-//                     .as_mut()
-//                     .unwrap()
-//                     .borrow_mut()
-//                     .set_parent(Rc::clone(&default_group));
-//             }
-
-//             _ => continue,
-//         }
-//     }
-
-//     if let Some(old_group) = current_group {
-//         parsed_groups.push(old_group);
-//     }
-
-//     Ok((
-//         parsed_vertices,
-//         parsed_normals,
-//         parsed_shapes,
-//         parsed_groups,
-//     ))
-// }
-
-// fn face_triangulation(vertices: Vec<Point>) -> Vec<Triangle> {
-//     assert!(vertices.len() >= 3);
-
-//     let mut parsed_triangles = vec![];
-
-//     let vertex1 = vertices[0];
-//     for (&vertex2, &vertex3) in vertices[1..].iter().zip(vertices[2..].iter()) {
-//         parsed_triangles.push(Triangle::new(vertex1, vertex2, vertex3));
-//     }
-
-//     parsed_triangles
-// }
-
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     #[test]
-//     fn objparser_ignores_unrecognised_commands() {
-//         let parsed_objects = parse_obj("./resources/gibberish.obj").unwrap();
-//         let (parsed_vertices, parsed_normals, parsed_triangles, parsed_groups) = parsed_objects;
-//         assert_eq!(parsed_vertices.len(), 0);
-//         assert_eq!(parsed_normals.len(), 0);
-//         assert_eq!(parsed_triangles.len(), 0);
-//         assert_eq!(parsed_groups.len(), 1);
-//     }
-
-//     #[test]
-//     fn objparser_parses_vertex_data() {
-//         let parsed_objects = parse_obj("./resources/vertex.obj").unwrap();
-//         let parsed_vertices = parsed_objects.0;
-//         assert_eq!(parsed_vertices.len(), 4);
-//         assert_eq!(parsed_vertices[0], Point::new(-1.0, 1.0, 0.0));
-//         assert_eq!(parsed_vertices[1], Point::new(-1.0, 0.5, 0.0));
-//         assert_eq!(parsed_vertices[2], Point::new(1.0, 0.0, 0.0));
-//         assert_eq!(parsed_vertices[3], Point::new(1.0, 1.0, 0.0));
-//     }
-
-//     #[test]
-//     fn objparser_parses_triangle_data() {
-//         let parsed_objects = parse_obj("./resources/triangle.obj").unwrap();
-//         let parsed_shapes = parsed_objects.2;
-//         assert_eq!(parsed_shapes.len(), 2);
-//     }
-
-//     #[test]
-//     fn objparser_parses_polygon_data() {
-//         let parsed_objects = parse_obj("./resources/polygon.obj").unwrap();
-//         let parsed_shapes = parsed_objects.2;
-//         assert_eq!(parsed_shapes.len(), 3);
-//     }
-
-//     #[test]
-//     fn objparser_parses_groups() {
-//         let parsed_objects = parse_obj("./resources/group.obj").unwrap();
-//         let (_, _, _, parsed_groups) = parsed_objects;
-
-//         assert_eq!(parsed_groups.len(), 3);
-//     }
-// }
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+
+use crate::collections::Point;
+use crate::objects::{Mesh, MeshBuilder};
+use crate::utils::{Buildable, Float};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    // a line that could not be read, numbered from 1
+    Parse { line: usize, message: String },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "could not read OBJ file: {}", error),
+            ObjError::Parse { line, message } => write!(f, "OBJ line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> ObjError {
+        ObjError::Io(error)
+    }
+}
+
+// The vertices and faces of a Wavefront OBJ file. Only vertices (v), faces
+// (f) and groups (g) are read; normals, texture coordinates, materials and
+// anything else are ignored, as meshes work out their own normals. Faces are
+// kept as polygons, and turned into triangles when a mesh is built from them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjModel {
+    vertices: Vec<Point>,
+    // each group's name and faces, starting with the default group for faces
+    // before any g line
+    groups: Vec<(String, Vec<Vec<usize>>)>,
+}
+
+impl ObjModel {
+    const DEFAULT_GROUP: &'static str = "default";
+
+    pub fn parse(source: &str) -> Result<ObjModel, ObjError> {
+        let mut model = ObjModel {
+            vertices: vec![],
+            groups: vec![(ObjModel::DEFAULT_GROUP.to_string(), vec![])],
+        };
+
+        for (idx_line, line) in source.lines().enumerate() {
+            let error = |message: String| ObjError::Parse {
+                line: idx_line + 1,
+                message,
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => {
+                    let coordinates = words
+                        .take(3)
+                        .map(|word| {
+                            word.parse()
+                                .map_err(|_| error(format!("{:?} is not a number", word)))
+                        })
+                        .collect::<Result<Vec<Float>, ObjError>>()?;
+                    let &[x, y, z] = coordinates.as_slice() else {
+                        return Err(error("a vertex needs x, y and z".to_string()));
+                    };
+                    model.vertices.push(Point::new(x, y, z));
+                }
+                Some("f") => {
+                    let polygon = words
+                        .map(|word| model.vertex_index(word).map_err(error))
+                        .collect::<Result<Vec<usize>, ObjError>>()?;
+                    if polygon.len() < 3 {
+                        return Err(error("a face needs at least 3 vertices".to_string()));
+                    }
+                    let (_, polygons) = model.groups.last_mut().unwrap();
+                    polygons.push(polygon);
+                }
+                Some("g") => {
+                    let name = words.collect::<Vec<&str>>().join(" ");
+                    model.groups.push((name, vec![]));
+                }
+                _ => continue,
+            }
+        }
+
+        Ok(model)
+    }
+
+    // a face's reference to a vertex, e.g. 3, 3/1, 3//2 or 3/1/2, of which
+    // only the vertex is used; counted from 1, or back from the last vertex
+    // so far if negative
+    fn vertex_index(&self, word: &str) -> Result<usize, String> {
+        let vertex = word.split('/').next().unwrap_or(word);
+        let index: isize = vertex
+            .parse()
+            .map_err(|_| format!("{:?} is not a vertex index", word))?;
+        let count = self.vertices.len() as isize;
+        let resolved = if index < 0 { count + index } else { index - 1 };
+        if index == 0 || resolved < 0 || resolved >= count {
+            return Err(format!(
+                "vertex {} is not one of the {} so far",
+                index, count
+            ));
+        }
+        Ok(resolved as usize)
+    }
+
+    pub fn vertices(&self) -> &Vec<Point> {
+        &self.vertices
+    }
+
+    // the names of the groups, starting with the default group
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    // every face, in every group
+    pub fn polygons(&self) -> Vec<Vec<usize>> {
+        self.groups
+            .iter()
+            .flat_map(|(_, polygons)| polygons.iter().cloned())
+            .collect()
+    }
+
+    // A mesh of every face, to be finished with the builder, e.g. with
+    // set_subdivision_level so that a coarse cage is smoothed as it is loaded.
+    pub fn mesh(&self) -> MeshBuilder {
+        Mesh::builder()
+            .set_vertices(self.vertices.clone())
+            .set_polygons(self.polygons())
+    }
+
+    // as mesh, for the faces of the first group with the name
+    pub fn group_mesh(&self, name: &str) -> Option<MeshBuilder> {
+        let (_, polygons) = self.groups.iter().find(|(group, _)| group == name)?;
+        Some(
+            Mesh::builder()
+                .set_vertices(self.vertices.clone())
+                .set_polygons(polygons.clone()),
+        )
+    }
+}
+
+pub fn parse_obj(file_path: &str) -> Result<ObjModel, ObjError> {
+    ObjModel::parse(&fs::read_to_string(file_path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ConsumingBuilder;
+
+    #[test]
+    fn objparser_ignores_unrecognised_commands() {
+        let model = parse_obj("./resources/test_inputs/gibberish.obj").unwrap();
+        assert_eq!(model.vertices().len(), 0);
+        assert_eq!(model.polygons().len(), 0);
+        assert_eq!(model.group_names(), ["default"]);
+    }
+
+    #[test]
+    fn objparser_parses_vertex_data() {
+        let model = parse_obj("./resources/test_inputs/vertex.obj").unwrap();
+        assert_eq!(
+            model.vertices(),
+            &vec![
+                Point::new(-1.0, 1.0, 0.0),
+                Point::new(-1.0, 0.5, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn objparser_parses_triangle_data() {
+        let model = parse_obj("./resources/test_inputs/triangle.obj").unwrap();
+        assert_eq!(model.polygons(), [vec![0, 1, 2], vec![0, 2, 3]]);
+    }
+
+    #[test]
+    fn objparser_parses_polygon_data() {
+        let model = parse_obj("./resources/test_inputs/polygon.obj").unwrap();
+        assert_eq!(model.polygons(), [vec![0, 1, 2, 3, 4]]);
+        let mesh = model.mesh().build();
+        assert_eq!(mesh.faces(), &vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn objparser_parses_groups() {
+        let model = parse_obj("./resources/test_inputs/group.obj").unwrap();
+        assert_eq!(
+            model.group_names(),
+            ["default", "FirstGroup", "SecondGroup"]
+        );
+        let second = model.group_mesh("SecondGroup").unwrap().build();
+        assert_eq!(second.faces(), &vec![[0, 2, 3]]);
+        assert!(model.group_mesh("ThirdGroup").is_none());
+    }
+
+    #[test]
+    fn objparser_reads_face_vertex_forms() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1/1 2//1 -1/1/1\n";
+        let model = ObjModel::parse(source).unwrap();
+        assert_eq!(model.polygons(), [vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn objparser_reports_bad_lines() {
+        let error = ObjModel::parse("v 0 0 0\nv 1 0\n").unwrap_err();
+        assert!(matches!(error, ObjError::Parse { line: 2, .. }));
+        let error = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n").unwrap_err();
+        assert!(matches!(error, ObjError::Parse { line: 4, .. }));
+        assert!(matches!(parse_obj("./missing.obj"), Err(ObjError::Io(_))));
+    }
+
+    #[test]
+    fn objparser_meshes_subdivide_as_they_are_loaded() {
+        // a tetrahedron, which Loop subdivision rounds towards a sphere
+        let source =
+            "v 1 1 1\nv -1 -1 1\nv -1 1 -1\nv 1 -1 -1\nf 1 2 3\nf 1 4 2\nf 1 3 4\nf 2 4 3\n";
+        let model = ObjModel::parse(source).unwrap();
+        let mesh = model.mesh().set_subdivision_level(2).build();
+        assert_eq!(mesh.faces().len(), 4 * 16);
+        assert_eq!(mesh.vertices().len(), 34);
+    }
+}
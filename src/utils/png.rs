@@ -0,0 +1,494 @@
+use std::io::{self, Write};
+
+use crate::utils::{image_size, zlib_decompress, ImageError, RawImage};
+
+// A minimal PNG encoder for 8- and 16-bit RGBA images, and a decoder for
+// non-interlaced images of any colour type and bit depth. Encoded image data
+// is stored in uncompressed deflate blocks, so files are large but need no
+// compressor.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK: usize = 65535;
+
+// rgba holds the rows top to bottom, four bytes per pixel
+pub fn encode_rgba(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    encode(width, height, 8, rgba)
+}
+
+// as encode_rgba, but with two bytes per channel, big-endian
+pub fn encode_rgba16(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    encode(width, height, 16, rgba)
+}
+
+fn encode(width: usize, height: usize, bit_depth: u8, rgba: &[u8]) -> Vec<u8> {
+    let stride = width * 4 * bit_depth as usize / 8;
+    assert_eq!(rgba.len(), stride * height);
+
+    let write = || -> io::Result<Vec<u8>> {
+        let mut writer = PngWriter::new(Vec::new(), width, height, bit_depth)?;
+        for row in rgba.chunks(stride.max(1)).take(height) {
+            writer.write_row(row)?;
+        }
+        writer.finish()
+    };
+    write().expect("writing to a Vec can't fail")
+}
+
+// Encodes a PNG row by row as the rows become available, holding no more
+// than one deflate block (64K) of image data at a time. Each full block is
+// written out as its own IDAT chunk.
+pub(crate) struct PngWriter<W: Write> {
+    writer: W,
+    pending: Vec<u8>,
+    adler: Adler32,
+    started: bool,
+}
+
+impl<W: Write> PngWriter<W> {
+    // a bit depth of 8 or 16
+    pub(crate) fn new(
+        mut writer: W,
+        width: usize,
+        height: usize,
+        bit_depth: u8,
+    ) -> io::Result<PngWriter<W>> {
+        let mut header = vec![];
+        header.extend((width as u32).to_be_bytes());
+        header.extend((height as u32).to_be_bytes());
+        // colour type 6 (RGBA), default compression, filter and no
+        // interlacing
+        header.extend([bit_depth, 6, 0, 0, 0]);
+
+        let mut start = SIGNATURE.to_vec();
+        write_chunk(&mut start, b"IHDR", &header);
+        writer.write_all(&start)?;
+        Ok(PngWriter {
+            writer,
+            pending: Vec::with_capacity(MAX_STORED_BLOCK),
+            adler: Adler32::new(),
+            started: false,
+        })
+    }
+
+    // four channels per pixel, at the bit depth; the caller writes exactly
+    // the height's worth
+    pub(crate) fn write_row(&mut self, rgba: &[u8]) -> io::Result<()> {
+        // every scanline starts with its filter type, 0 (none)
+        let mut scanline = [0].iter().chain(rgba);
+        loop {
+            let space = MAX_STORED_BLOCK - self.pending.len();
+            let before = self.pending.len();
+            self.pending.extend(scanline.by_ref().take(space));
+            self.adler.update(&self.pending[before..]);
+            if self.pending.len() < MAX_STORED_BLOCK {
+                return Ok(());
+            }
+            self.write_block(false)?;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.write_block(true)?;
+        let mut end = vec![];
+        write_chunk(&mut end, b"IEND", &[]);
+        self.writer.write_all(&end)?;
+        Ok(self.writer)
+    }
+
+    // the pending data as a stored deflate block in an IDAT chunk, led by
+    // the zlib header if it's the first and followed by the checksum if it's
+    // the last
+    fn write_block(&mut self, is_final: bool) -> io::Result<()> {
+        let mut zlib = vec![];
+        if !self.started {
+            // deflate with a 32K window and no preset dictionary
+            zlib.extend([0x78, 0x01]);
+            self.started = true;
+        }
+        zlib.push(is_final as u8);
+        let length = self.pending.len() as u16;
+        zlib.extend(length.to_le_bytes());
+        zlib.extend((!length).to_le_bytes());
+        zlib.append(&mut self.pending);
+        if is_final {
+            zlib.extend(self.adler.value().to_be_bytes());
+        }
+        let mut chunk = vec![];
+        write_chunk(&mut chunk, b"IDAT", &zlib);
+        self.writer.write_all(&chunk)
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<RawImage, ImageError> {
+    if data.get(..8) != Some(&SIGNATURE[..]) {
+        return Err(ImageError::Malformed("missing PNG signature"));
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = vec![];
+    let mut position = 8;
+    loop {
+        let length_bytes = data
+            .get(position..position + 4)
+            .ok_or(ImageError::Truncated)?;
+        let length = u32::from_be_bytes(length_bytes.try_into().unwrap()) as usize;
+        let chunk_type = data
+            .get(position + 4..position + 8)
+            .ok_or(ImageError::Truncated)?;
+        let chunk = data
+            .get(position + 8..position + 8 + length)
+            .ok_or(ImageError::Truncated)?;
+        match chunk_type {
+            b"IHDR" => header = Some(chunk),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend(chunk),
+            b"IEND" => break,
+            _ => (),
+        }
+        // skips the CRC too
+        position += 12 + length;
+    }
+
+    let header = header
+        .filter(|header| header.len() == 13)
+        .ok_or(ImageError::Malformed("missing PNG header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    let (bit_depth, colour_type) = (header[8] as usize, header[9]);
+    if header[12] != 0 {
+        return Err(ImageError::Unsupported("interlaced PNG"));
+    }
+    let channels = match colour_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(ImageError::Malformed("invalid PNG colour type")),
+    };
+    if ![1, 2, 4, 8, 16].contains(&bit_depth) {
+        return Err(ImageError::Malformed("invalid PNG bit depth"));
+    }
+
+    if width == 0 || height == 0 {
+        return Err(ImageError::Malformed("invalid PNG dimensions"));
+    }
+
+    let scanlines = zlib_decompress(&compressed)?;
+    let stride = width
+        .checked_mul(channels * bit_depth)
+        .ok_or(ImageError::Malformed("image dimensions too large"))?
+        .div_ceil(8);
+    let filter_step = (channels * bit_depth).div_ceil(8);
+    let rows = unfilter(&scanlines, stride, height, filter_step)?;
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in rows.chunks(stride) {
+        for column in 0..width {
+            let sample = |channel: usize| -> u16 {
+                let index = column * channels + channel;
+                match bit_depth {
+                    16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+                    8 => row[index] as u16,
+                    _ => {
+                        let bit = index * bit_depth;
+                        let shift = 8 - bit_depth - bit % 8;
+                        (row[bit / 8] >> shift) as u16 & ((1 << bit_depth) - 1)
+                    }
+                }
+            };
+            // scales samples of any depth to 8 bits
+            let to_byte = |value: u16| -> u8 {
+                match bit_depth {
+                    16 => (value >> 8) as u8,
+                    _ => (value as usize * 255 / ((1 << bit_depth) - 1)) as u8,
+                }
+            };
+
+            match colour_type {
+                0 => {
+                    let grey = to_byte(sample(0));
+                    rgba.extend([grey, grey, grey, 255]);
+                }
+                2 => rgba.extend([
+                    to_byte(sample(0)),
+                    to_byte(sample(1)),
+                    to_byte(sample(2)),
+                    255,
+                ]),
+                3 => {
+                    let index = sample(0) as usize;
+                    let colour = palette
+                        .get(index * 3..index * 3 + 3)
+                        .ok_or(ImageError::Malformed("PNG palette index out of range"))?;
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    rgba.extend(colour);
+                    rgba.push(alpha);
+                }
+                4 => {
+                    let grey = to_byte(sample(0));
+                    rgba.extend([grey, grey, grey, to_byte(sample(1))]);
+                }
+                _ => rgba.extend((0..4).map(|channel| to_byte(sample(channel)))),
+            }
+        }
+    }
+
+    Ok(RawImage {
+        width,
+        height,
+        rgba,
+    })
+}
+
+// reverses the per-scanline filters, where filter_step is the number of bytes
+// per pixel (at least 1)
+fn unfilter(
+    scanlines: &[u8],
+    stride: usize,
+    height: usize,
+    filter_step: usize,
+) -> Result<Vec<u8>, ImageError> {
+    // every filtered row must be there before the rows are allocated
+    image_size(stride + 1, height, 1, scanlines.len())?;
+    let mut rows = vec![0u8; height * stride];
+    for row in 0..height {
+        let filter = scanlines[row * (stride + 1)];
+        let filtered = &scanlines[row * (stride + 1) + 1..(row + 1) * (stride + 1)];
+        let (previous_rows, current_rows) = rows.split_at_mut(row * stride);
+        let previous = previous_rows.get(previous_rows.len().saturating_sub(stride)..);
+        let previous = previous.filter(|_| row > 0);
+        let current = &mut current_rows[..stride];
+
+        for idx in 0..stride {
+            let left = if idx >= filter_step {
+                current[idx - filter_step]
+            } else {
+                0
+            };
+            let up = previous.map_or(0, |previous| previous[idx]);
+            let up_left = match previous {
+                Some(previous) if idx >= filter_step => previous[idx - filter_step],
+                _ => 0,
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(ImageError::Malformed("invalid PNG filter type")),
+            };
+            current[idx] = filtered[idx].wrapping_add(predictor);
+        }
+    }
+    Ok(rows)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(chunk_type);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// kept running, as the data is checked block by block
+struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.a = (self.a + byte as u32) % 65521;
+            self.b = (self.b + self.a) % 65521;
+        }
+    }
+
+    fn value(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        let mut adler = Adler32::new();
+        adler.update(b"Wiki");
+        adler.update(b"pedia");
+        assert_eq!(adler.value(), 0x11e6_0398);
+    }
+
+    #[test]
+    fn encode_single_pixel() {
+        let png = encode_rgba(1, 1, &[255, 0, 0, 128]);
+        assert_eq!(png[..8], SIGNATURE);
+        // IHDR: 13 bytes, 1x1, 8-bit RGBA
+        assert_eq!(png[8..16], [0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+        assert_eq!(png[16..29], [0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        // IDAT: zlib header, one final stored block of the filter byte and
+        // the pixel, then the Adler-32 checksum
+        let idat = &png[33..];
+        assert_eq!(idat[..8], [0, 0, 0, 16, b'I', b'D', b'A', b'T']);
+        assert_eq!(
+            idat[8..24],
+            [0x78, 0x01, 1, 5, 0, 0xfa, 0xff, 0, 255, 0, 0, 128, 0x04, 0x81, 0x01, 0x80]
+        );
+        assert_eq!(
+            png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn large_images_span_several_stored_blocks() {
+        let (width, height) = (200, 100);
+        let png = encode_rgba(width, height, &vec![7; width * height * 4]);
+        let scanline_bytes = height * (width * 4 + 1);
+        let blocks = scanline_bytes.div_ceil(MAX_STORED_BLOCK);
+        // one IDAT chunk per block
+        let mut idat_lengths = vec![];
+        let mut position = 8;
+        while position < png.len() {
+            let length = u32::from_be_bytes(png[position..position + 4].try_into().unwrap());
+            if &png[position + 4..position + 8] == b"IDAT" {
+                idat_lengths.push(length as usize);
+            }
+            position += 12 + length as usize;
+        }
+        assert_eq!(idat_lengths.len(), blocks);
+        assert_eq!(
+            idat_lengths.iter().sum::<usize>(),
+            2 + scanline_bytes + 5 * blocks + 4
+        );
+        let image = decode(&png).unwrap();
+        assert!(image.rgba.iter().all(|&byte| byte == 7));
+    }
+
+    #[test]
+    fn write_rows_as_they_come() {
+        let rgba: Vec<u8> = (0..40 * 30 * 4).map(|value| (value % 251) as u8).collect();
+        let mut writer = PngWriter::new(Vec::new(), 40, 30, 8).unwrap();
+        for row in rgba.chunks(40 * 4) {
+            writer.write_row(row).unwrap();
+        }
+        let png = writer.finish().unwrap();
+        assert_eq!(decode(&png).unwrap().rgba, rgba);
+    }
+
+    #[test]
+    fn decode_round_trips_encoded_images() {
+        let rgba: Vec<u8> = (0..3 * 2 * 4).map(|value| value * 10).collect();
+        let image = decode(&encode_rgba(3, 2, &rgba)).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.rgba, rgba);
+    }
+
+    #[test]
+    fn encode_sixteen_bits_per_channel() {
+        let rgba = [0x12, 0x34, 0, 0, 0xff, 0xff, 0x80, 0x00];
+        let png = encode_rgba16(1, 1, &rgba);
+        assert_eq!(png[24], 16);
+        let image = decode(&png).unwrap();
+        assert_eq!(image.rgba, [0x12, 0, 0xff, 0x80]);
+    }
+
+    #[test]
+    fn decode_filtered_palette_image() {
+        let png = std::fs::read("./resources/test_inputs/python.png").unwrap();
+        let image = decode(&png).unwrap();
+        let reference = RawImage::load("./resources/test_inputs/python.ppm").unwrap();
+        assert_eq!((image.width, image.height), (16, 16));
+        // the palette has transparent entries, which the PPM cannot record
+        let mut transparent = 0;
+        for (pixel, expected) in image.rgba.chunks(4).zip(reference.rgba.chunks(4)) {
+            if pixel[3] == 255 {
+                assert_eq!(pixel, expected);
+            } else {
+                transparent += 1;
+            }
+        }
+        assert!(transparent > 0);
+    }
+
+    #[test]
+    fn decode_rejects_interlaced_images() {
+        let mut png = encode_rgba(1, 1, &[0; 4]);
+        png[28] = 1;
+        assert_eq!(decode(&png), Err(ImageError::Unsupported("interlaced PNG")));
+    }
+
+    #[test]
+    fn decode_rejects_bad_dimensions() {
+        let mut png = encode_rgba(1, 1, &[0; 4]);
+        png[16..20].copy_from_slice(&[0; 4]);
+        assert_eq!(
+            decode(&png),
+            Err(ImageError::Malformed("invalid PNG dimensions"))
+        );
+        // rows the data doesn't have aren't allocated
+        png[16..20].copy_from_slice(&1u32.to_be_bytes());
+        png[20..24].copy_from_slice(&[0xff; 4]);
+        assert_eq!(decode(&png), Err(ImageError::Truncated));
+        png[16..20].copy_from_slice(&[0xff; 4]);
+        assert_eq!(
+            decode(&png),
+            Err(ImageError::Malformed("image dimensions too large"))
+        );
+    }
+
+    #[test]
+    fn unfilter_sub_up_average_and_paeth() {
+        // two rows of two 1-byte pixels
+        let scanlines = [1, 10, 5, 2, 1, 1, 3, 4, 4, 4, 2, 2];
+        let rows = unfilter(&scanlines[..6], 2, 2, 1).unwrap();
+        assert_eq!(rows, [10, 15, 11, 16]);
+        let rows = unfilter(&[[0, 10, 20], [3, 4, 4]].concat(), 2, 2, 1).unwrap();
+        assert_eq!(rows, [10, 20, 9, 18]);
+        let rows = unfilter(&[[0, 10, 20], [4, 2, 2]].concat(), 2, 2, 1).unwrap();
+        assert_eq!(rows, [10, 20, 12, 22]);
+    }
+}
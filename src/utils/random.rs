@@ -0,0 +1,128 @@
+use crate::utils::Float;
+
+// SplitMix64: small, fast and deterministic across platforms, which is all
+// that's needed for reproducible variation (not for statistics or security)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    // a generator that is stable for each (seed, key) pair, e.g. one per
+    // instance of an object
+    pub fn keyed(seed: u64, key: u64) -> Random {
+        let mut mixer = Random::new(seed ^ key.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        Random::new(mixer.next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> Float {
+        let bits = Float::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float
+    }
+
+    // uniform in [-1, 1)
+    pub fn next_signed(&mut self) -> Float {
+        2.0 * self.next_f64() - 1.0
+    }
+}
+
+// One sample of one pixel (by its row-major index) of one frame of a render.
+// Every random choice made for a sample is keyed by it, so that renders with
+// the same seed come out the same however their pixels are split between
+// threads, and successive frames of an animation do not repeat the same noise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SampleKey {
+    pub seed: u64,
+    pub frame: u64,
+    pub pixel: u64,
+    pub sample: u64,
+}
+
+// what a sample's random numbers are drawn for, so each use gets its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stream {
+    // where the sample goes within its pixel
+    Placement,
+    // when the shutter is open for it
+    Shutter,
+    // which way its paths bounce
+    Paths,
+}
+
+impl SampleKey {
+    pub fn new(seed: u64, frame: u64, pixel: u64, sample: u64) -> SampleKey {
+        SampleKey {
+            seed,
+            frame,
+            pixel,
+            sample,
+        }
+    }
+
+    pub(crate) fn random(&self, stream: Stream) -> Random {
+        let seed = [self.frame, self.pixel, self.sample, stream as u64]
+            .into_iter()
+            .fold(self.seed, |seed, key| Random::keyed(seed, key).next_u64());
+        Random::new(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut random_a = Random::new(42);
+        let mut random_b = Random::new(42);
+        for _ in 0..10 {
+            assert_eq!(random_a.next_u64(), random_b.next_u64());
+        }
+    }
+
+    #[test]
+    fn keyed_generators_differ_by_key() {
+        let first = Random::keyed(7, 0).next_f64();
+        assert_eq!(first, Random::keyed(7, 0).next_f64());
+        assert_ne!(first, Random::keyed(7, 1).next_f64());
+        assert_ne!(first, Random::keyed(8, 0).next_f64());
+    }
+
+    #[test]
+    fn sample_keys_differ_in_every_part() {
+        let key = SampleKey::new(1, 2, 3, 4);
+        let first = key.random(Stream::Paths).next_u64();
+        assert_eq!(first, key.random(Stream::Paths).next_u64());
+        assert_ne!(first, key.random(Stream::Shutter).next_u64());
+        for other in [
+            SampleKey { seed: 0, ..key },
+            SampleKey { frame: 0, ..key },
+            SampleKey { pixel: 0, ..key },
+            SampleKey { sample: 0, ..key },
+        ] {
+            assert_ne!(first, other.random(Stream::Paths).next_u64());
+        }
+    }
+
+    #[test]
+    fn floats_in_unit_interval() {
+        let mut random = Random::new(0);
+        for _ in 0..1000 {
+            let value = random.next_f64();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+}
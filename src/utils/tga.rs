@@ -0,0 +1,45 @@
+// A minimal encoder for uncompressed 32-bit Truevision TGA images, stored top
+// to bottom with 8 bits of alpha, and the TGA 2.0 footer.
+
+// sizes are 16-bit
+pub fn fits(width: usize, height: usize) -> bool {
+    width <= u16::MAX as usize && height <= u16::MAX as usize
+}
+
+// rgba holds the rows top to bottom, four bytes per pixel
+pub fn encode_rgba(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    assert!(fits(width, height));
+    assert_eq!(rgba.len(), width * height * 4);
+
+    // no image id or colour map, uncompressed true colour (type 2), at the
+    // origin
+    let mut tga = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    tga.extend((width as u16).to_le_bytes());
+    tga.extend((height as u16).to_le_bytes());
+    // 32 bits per pixel, with 8 alpha bits and the first row at the top
+    tga.extend([32, 0x28]);
+    for pixel in rgba.chunks(4) {
+        tga.extend([pixel[2], pixel[1], pixel[0], pixel[3]]);
+    }
+    // no extension or developer areas
+    tga.extend([0; 8]);
+    tga.extend(b"TRUEVISION-XFILE.\0");
+    tga
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_header_and_pixels() {
+        let tga = encode_rgba(2, 1, &[255, 128, 0, 255, 1, 2, 3, 4]);
+        assert_eq!(
+            &tga[..18],
+            &[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1, 0, 32, 0x28]
+        );
+        assert_eq!(&tga[18..26], &[0, 128, 255, 255, 3, 2, 1, 4]);
+        assert!(tga.ends_with(b"TRUEVISION-XFILE.\0"));
+        assert!(fits(65535, 65535) && !fits(65536, 1));
+    }
+}
@@ -53,6 +53,9 @@ fn basic_2d_projectile_motion_scene() {
     }
 
     canvas
-        .output_to_ppm("resources/test_outputs/test_output_projmotion.ppm")
+        .output_to_ppm(
+            "resources/test_outputs/test_output_projmotion.ppm",
+            PpmFormat::Ascii,
+        )
         .unwrap();
 }
@@ -6,12 +6,12 @@ fn raycast_sphere() {
     let sphere = Sphere::builder()
         .set_material(Material::preset())
         .build_into();
-    let light = Light::new(Point::new(10.0, 10.0, 10.0), Colour::new(1.0, 1.0, 1.0));
-    let world = World::new(vec![sphere], vec![light]);
+    let light = PointLight::new(Point::new(10.0, 10.0, 10.0), Colour::new(1.0, 1.0, 1.0));
+    let world = World::new(vec![sphere], vec![Box::new(light)]);
     let camera = Camera::new(Native::new(
         100,
         100,
-        Angle::from_radians(std::f64::consts::FRAC_PI_2),
+        Angle::from_degrees(90.0),
         Orientation::new(
             Point::new(10.0, 10.0, 0.0),
             Point::new(0.0, 0.0, 0.0),
@@ -21,7 +21,10 @@ fn raycast_sphere() {
     camera
         .render(&world)
         .unwrap()
-        .output_to_ppm("resources/test_outputs/test_raycast_sphere.ppm")
+        .output_to_ppm(
+            "resources/test_outputs/test_raycast_sphere.ppm",
+            PpmFormat::Ascii,
+        )
         .unwrap();
 }
 
@@ -39,8 +42,8 @@ fn raycast_scene_native_resolution() {
     let left_wall = Sphere::builder()
         .set_frame_transformation(Transform::from(vec![
             TransformKind::Scale(10.0, 0.01, 10.0),
-            TransformKind::Rotate(Axis::X, Angle::from_radians(std::f64::consts::FRAC_PI_2)),
-            TransformKind::Rotate(Axis::Y, Angle::from_radians(-std::f64::consts::FRAC_PI_4)),
+            TransformKind::Rotate(Axis::X, Angle::from_degrees(90.0)),
+            TransformKind::Rotate(Axis::Y, Angle::from_degrees(-45.0)),
             TransformKind::Translate(0.0, 0.0, 5.0),
         ]))
         .set_material(Material::preset())
@@ -48,8 +51,8 @@ fn raycast_scene_native_resolution() {
     let right_wall = Sphere::builder()
         .set_frame_transformation(Transform::from(vec![
             TransformKind::Scale(10.0, 0.01, 10.0),
-            TransformKind::Rotate(Axis::X, Angle::from_radians(std::f64::consts::FRAC_PI_2)),
-            TransformKind::Rotate(Axis::Y, Angle::from_radians(std::f64::consts::FRAC_PI_4)),
+            TransformKind::Rotate(Axis::X, Angle::from_degrees(90.0)),
+            TransformKind::Rotate(Axis::Y, Angle::from_degrees(45.0)),
             TransformKind::Translate(0.0, 0.0, 5.0),
         ]))
         .set_material(Material::preset())
@@ -87,7 +90,7 @@ fn raycast_scene_native_resolution() {
             ..Material::preset()
         })
         .build_into();
-    let light_source = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+    let light_source = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
     let world = World::new(
         vec![
             floor,
@@ -97,12 +100,12 @@ fn raycast_scene_native_resolution() {
             right_sphere,
             left_sphere,
         ],
-        vec![light_source],
+        vec![Box::new(light_source)],
     );
     let camera = Camera::new(Native::new(
         100,
         50,
-        Angle::from_radians(std::f64::consts::FRAC_PI_3),
+        Angle::from_degrees(60.0),
         Orientation::new(
             Point::new(0.0, 1.5, -5.0),
             Point::new(0.0, 1.0, 0.0),
@@ -111,7 +114,10 @@ fn raycast_scene_native_resolution() {
     ));
     let image = camera.render(&world).unwrap();
     image
-        .output_to_ppm("resources/test_outputs/test_output_raycast_scene_native.ppm")
+        .output_to_ppm(
+            "resources/test_outputs/test_output_raycast_scene_native.ppm",
+            PpmFormat::Ascii,
+        )
         .unwrap();
 }
 
@@ -129,8 +135,8 @@ fn raycast_scene_agss() {
     let left_wall = Sphere::builder()
         .set_frame_transformation(Transform::from(vec![
             TransformKind::Scale(10.0, 0.01, 10.0),
-            TransformKind::Rotate(Axis::X, Angle::from_radians(std::f64::consts::FRAC_PI_2)),
-            TransformKind::Rotate(Axis::Y, Angle::from_radians(-std::f64::consts::FRAC_PI_4)),
+            TransformKind::Rotate(Axis::X, Angle::from_degrees(90.0)),
+            TransformKind::Rotate(Axis::Y, Angle::from_degrees(-45.0)),
             TransformKind::Translate(0.0, 0.0, 5.0),
         ]))
         .set_material(Material::preset())
@@ -138,8 +144,8 @@ fn raycast_scene_agss() {
     let right_wall = Sphere::builder()
         .set_frame_transformation(Transform::from(vec![
             TransformKind::Scale(10.0, 0.01, 10.0),
-            TransformKind::Rotate(Axis::X, Angle::from_radians(std::f64::consts::FRAC_PI_2)),
-            TransformKind::Rotate(Axis::Y, Angle::from_radians(std::f64::consts::FRAC_PI_4)),
+            TransformKind::Rotate(Axis::X, Angle::from_degrees(90.0)),
+            TransformKind::Rotate(Axis::Y, Angle::from_degrees(45.0)),
             TransformKind::Translate(0.0, 0.0, 5.0),
         ]))
         .set_material(Material::preset())
@@ -177,7 +183,7 @@ fn raycast_scene_agss() {
             ..Material::preset()
         })
         .build_into();
-    let light_source = Light::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
+    let light_source = PointLight::new(Point::new(-10.0, 10.0, -10.0), Colour::new(1.0, 1.0, 1.0));
     let world = World::new(
         vec![
             floor,
@@ -187,12 +193,12 @@ fn raycast_scene_agss() {
             right_sphere,
             left_sphere,
         ],
-        vec![light_source],
+        vec![Box::new(light_source)],
     );
     let camera = Camera::new(Agss::new(
         100,
         50,
-        Angle::from_radians(std::f64::consts::FRAC_PI_3),
+        Angle::from_degrees(60.0),
         Orientation::new(
             Point::new(0.0, 1.5, -5.0),
             Point::new(0.0, 1.0, 0.0),
@@ -202,6 +208,9 @@ fn raycast_scene_agss() {
     ));
     let image = camera.render(&world).unwrap();
     image
-        .output_to_ppm("resources/test_outputs/test_output_raycast_scene_agss.ppm")
+        .output_to_ppm(
+            "resources/test_outputs/test_output_raycast_scene_agss.ppm",
+            PpmFormat::Ascii,
+        )
         .unwrap();
 }